@@ -0,0 +1,412 @@
+use crate::apps::rest::experiment::assignments;
+use crate::apps::routes::route_table;
+use crate::apps::{app_type_supported, build_app_router};
+use crate::builtins::builtin::memory::{delete_memory, get_memory_value, set_memory};
+use crate::builtins::builtin::pool_stats;
+use crate::core::{extract_data_sources, extract_steps, extract_schemas, get_app_type, AppState};
+use crate::rune_ast::RuneDocument;
+use crate::rune_parser::{load_rune_document_from_path, load_rune_document_from_str_with_base};
+use crate::util::{log, LogLevel};
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+/// Path prefix reserved for the admin API.
+pub const ADMIN_PREFIX: &str = "/__admin";
+
+/// Environment variable that enables the admin API when no `@Admin token` is set.
+pub const ADMIN_TOKEN_ENV: &str = "VECTRUNE_ADMIN_TOKEN";
+
+/// Largest request body the admin API reads, for uploaded documents and memory values.
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Files the running document was loaded from, re-read by `POST /__admin/reload`.
+static RELOAD_SOURCES: Lazy<std::sync::RwLock<Vec<PathBuf>>> =
+    Lazy::new(|| std::sync::RwLock::new(Vec::new()));
+
+/// Records the script paths the server was started with so the admin API can reload them.
+pub fn set_reload_sources(paths: Vec<PathBuf>) {
+    *RELOAD_SOURCES.write().unwrap() = paths;
+}
+
+/// A fully built application: the document it was built from and its router.
+#[derive(Clone)]
+struct ActiveApp {
+    doc: Arc<RuneDocument>,
+    router: Router,
+}
+
+/// The app serving traffic and the one it replaced, behind one lock so a switch and a
+/// rollback never wait on each other.
+struct Apps {
+    active: ActiveApp,
+    previous: Option<ActiveApp>,
+}
+
+/// Shared state behind the admin API. Traffic is always served by the active app;
+/// a successful upload moves the old app into `previous` so it can be restored.
+/// Without a token the admin routes are not mounted, but the document can still be
+/// switched through a [`DocumentSwitch`].
+pub struct AdminState {
+    token: Option<String>,
+    path: PathBuf,
+    apps: RwLock<Apps>,
+}
+
+/// Returns the admin bearer token from `@Admin token = ...`, falling back to
+/// `VECTRUNE_ADMIN_TOKEN`. The admin API is disabled when neither is set.
+pub fn admin_token(doc: &RuneDocument) -> Option<String> {
+    doc.get_section("Admin")
+        .and_then(|sec| sec.kv.get("token"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| std::env::var(ADMIN_TOKEN_ENV).ok())
+        .filter(|t| !t.is_empty())
+}
+
+/// Wraps the app router with the admin API when an admin token is configured.
+/// All non-admin requests are delegated to whichever router is currently active,
+/// so a document switch takes effect atomically for the next request.
+pub async fn build_admin_router(state: AppState) -> Router {
+    if admin_token(&state.doc).is_none() {
+        return build_app_router(state).await;
+    }
+    build_switchable_router(state).await.0
+}
+
+/// Replaces the document a running server answers with, as the admin API does.
+#[derive(Clone)]
+pub struct DocumentSwitch(Arc<AdminState>);
+
+impl DocumentSwitch {
+    /// Builds a router for `doc` and makes it the active app, returning its section
+    /// count. On failure the current document keeps serving.
+    pub async fn activate(&self, doc: RuneDocument) -> Result<usize, String> {
+        try_activate(&self.0, doc).await
+    }
+}
+
+/// Like [`build_admin_router`], but always delegates through a switchable app and
+/// returns the handle for switching it. The admin API is mounted when a token is set.
+pub async fn build_switchable_router(state: AppState) -> (Router, DocumentSwitch) {
+    let token = admin_token(&state.doc);
+    let doc = state.doc.clone();
+    let path = state.path.clone();
+    let router = build_app_router(state).await;
+    let admin = Arc::new(AdminState {
+        token,
+        path,
+        apps: RwLock::new(Apps { active: ActiveApp { doc, router }, previous: None }),
+    });
+    let switch = DocumentSwitch(admin.clone());
+
+    let delegate = {
+        let admin = admin.clone();
+        move |req: Request<Body>| {
+            let admin = admin.clone();
+            async move {
+                let router = admin.apps.read().await.active.router.clone();
+                router.oneshot(req).await.unwrap_or_else(|e| match e {})
+            }
+        }
+    };
+    if admin.token.is_none() {
+        return (Router::new().fallback(delegate), switch);
+    }
+
+    log(
+        LogLevel::Info,
+        &format!("Admin API enabled at {}", ADMIN_PREFIX),
+    );
+
+    let router = Router::new()
+        .route(
+            &format!("{}/document", ADMIN_PREFIX),
+            get({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { get_document(admin, req).await }
+                }
+            })
+            .post({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { switch_document(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/rollback", ADMIN_PREFIX),
+            post({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { rollback_document(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/reload", ADMIN_PREFIX),
+            post({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { reload_document(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/routes", ADMIN_PREFIX),
+            get({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { list_routes(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/experiments", ADMIN_PREFIX),
+            get({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { list_experiments(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/datasources", ADMIN_PREFIX),
+            get({
+                let admin = admin.clone();
+                move |req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { list_datasources(admin, req).await }
+                }
+            }),
+        )
+        .route(
+            &format!("{}/memory/{{key}}", ADMIN_PREFIX),
+            get({
+                let admin = admin.clone();
+                move |Path(key): Path<String>, req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { memory_get(admin, key, req).await }
+                }
+            })
+            .put({
+                let admin = admin.clone();
+                move |Path(key): Path<String>, req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { memory_set(admin, key, req).await }
+                }
+            })
+            .delete({
+                let admin = admin.clone();
+                move |Path(key): Path<String>, req: Request<Body>| {
+                    let admin = admin.clone();
+                    async move { memory_delete(admin, key, req).await }
+                }
+            }),
+        )
+        .fallback(delegate);
+    (router, switch)
+}
+
+fn authorized(admin: &AdminState, req: &Request<Body>) -> bool {
+    req.headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .zip(admin.token.as_deref())
+        .is_some_and(|(given, token)| bool::from(given.as_bytes().ct_eq(token.as_bytes())))
+}
+
+/// Reads a request body of at most `MAX_BODY_BYTES`.
+async fn read_body(req: Request<Body>) -> Result<axum::body::Bytes, Response> {
+    axum::body::to_bytes(req.into_body(), MAX_BODY_BYTES)
+        .await
+        .map_err(|e| admin_error(StatusCode::BAD_REQUEST, &e.to_string()))
+}
+
+fn admin_error(status: StatusCode, message: &str) -> Response {
+    (status, axum::Json(json!({ "error": message }))).into_response()
+}
+
+async fn get_document(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let doc = admin.apps.read().await.active.doc.clone();
+    (StatusCode::OK, doc.to_string()).into_response()
+}
+
+/// Validates the uploaded document, builds its router off the request path and
+/// only then swaps it in. Any failure leaves the current document serving.
+async fn switch_document(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let body = match read_body(req).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(response) => return response,
+    };
+
+    match load_rune_document_from_str_with_base(&body, &admin.path, "<admin upload>") {
+        Ok(doc) => activate(&admin, doc).await,
+        Err(e) => admin_error(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()),
+    }
+}
+
+/// Re-reads the documents the server was started from and switches to them.
+async fn reload_document(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let sources = RELOAD_SOURCES.read().unwrap().clone();
+    if sources.is_empty() {
+        return admin_error(StatusCode::CONFLICT, "No reloadable source files");
+    }
+    let mut doc = RuneDocument { sections: Vec::new() };
+    for source in &sources {
+        match load_rune_document_from_path(source) {
+            Ok(loaded) => doc.merge(loaded),
+            Err(e) => return admin_error(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()),
+        }
+    }
+    activate(&admin, doc).await
+}
+
+/// Builds a router for `doc` and makes it the active app. Any failure leaves
+/// the current document serving.
+async fn activate(admin: &AdminState, doc: RuneDocument) -> Response {
+    match try_activate(admin, doc).await {
+        Ok(sections) => (
+            StatusCode::OK,
+            axum::Json(json!({ "status": "switched", "sections": sections })),
+        )
+            .into_response(),
+        Err(e) => admin_error(StatusCode::UNPROCESSABLE_ENTITY, &e),
+    }
+}
+
+async fn try_activate(admin: &AdminState, doc: RuneDocument) -> Result<usize, String> {
+    let app_type = get_app_type(&doc).unwrap_or_else(|| "REST".to_string());
+    if !app_type_supported(&app_type) {
+        return Err(format!("Unsupported App type: {}", app_type));
+    }
+
+    let doc = Arc::new(doc);
+    let state = AppState {
+        doc: doc.clone(),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: admin.path.clone(),
+    };
+    // Build in a separate task so a panicking router build (e.g. conflicting
+    // routes) is reported as an error instead of taking the server down.
+    let router = match tokio::spawn(build_app_router(state)).await {
+        Ok(router) => router,
+        Err(_) => {
+            log(
+                LogLevel::Error,
+                "Admin document switch failed while building router; keeping current document",
+            );
+            return Err("Failed to build router for uploaded document".to_string());
+        }
+    };
+
+    let sections = doc.sections.len();
+    let mut apps = admin.apps.write().await;
+    let old = std::mem::replace(&mut apps.active, ActiveApp { doc, router });
+    apps.previous = Some(old);
+    drop(apps);
+
+    log(LogLevel::Info, "Admin document switch complete");
+    Ok(sections)
+}
+
+async fn rollback_document(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let mut apps = admin.apps.write().await;
+    let Some(prev) = apps.previous.take() else {
+        return admin_error(StatusCode::CONFLICT, "No previous document to roll back to");
+    };
+    apps.previous = Some(std::mem::replace(&mut apps.active, prev));
+
+    log(LogLevel::Info, "Admin rolled back to previous document");
+    (StatusCode::OK, axum::Json(json!({ "status": "rolled_back" }))).into_response()
+}
+
+async fn list_routes(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let doc = admin.apps.read().await.active.doc.clone();
+    (StatusCode::OK, axum::Json(route_table(&doc))).into_response()
+}
+
+async fn list_experiments(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    (StatusCode::OK, axum::Json(assignments())).into_response()
+}
+
+/// Pool size, idle connections and acquire wait times for each connected datasource.
+async fn list_datasources(admin: Arc<AdminState>, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    (StatusCode::OK, axum::Json(pool_stats::snapshot())).into_response()
+}
+
+async fn memory_get(admin: Arc<AdminState>, key: String, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    match get_memory_value(&key).await {
+        Some(value) => (StatusCode::OK, axum::Json(value)).into_response(),
+        None => admin_error(StatusCode::NOT_FOUND, &format!("No memory entry for {}", key)),
+    }
+}
+
+async fn memory_set(admin: Arc<AdminState>, key: String, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    let body = match read_body(req).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    // Non-JSON bodies are stored as plain strings.
+    let value = serde_json::from_slice(&body)
+        .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&body).to_string()));
+    set_memory(&key, value).await;
+    (StatusCode::OK, axum::Json(json!({ "status": "ok" }))).into_response()
+}
+
+async fn memory_delete(admin: Arc<AdminState>, key: String, req: Request<Body>) -> Response {
+    if !authorized(&admin, &req) {
+        return admin_error(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    delete_memory(&key).await;
+    (StatusCode::OK, axum::Json(json!({ "status": "ok" }))).into_response()
+}
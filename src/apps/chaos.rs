@@ -0,0 +1,153 @@
+//! Fault injection for testing clients against a misbehaving API. A spec such as
+//! `latency=200ms,errors=5%` delays every request and fails a share of them:
+//!
+//! - `latency=200ms` adds a fixed delay; `latency=100ms..500ms` a random one in that range
+//! - `errors=5%` (or `errors=0.05`) fails that share of requests
+//! - `status=503` is the status of injected failures (default `503`)
+//!
+//! `vectrune app.rune --chaos "<spec>"` applies the spec to every route and to datasource
+//! calls; a route's own `chaos = "<spec>"` replaces it for that route, and
+//! `chaos = off` exempts it.
+
+use crate::builtins::builtin::control::parse_duration;
+use crate::rune_ast::Section;
+use crate::util::{log, LogLevel};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// The `--chaos` spec the server was started with.
+static GLOBAL_CHAOS: Lazy<RwLock<Option<ChaosConfig>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    pub latency: Option<(Duration, Duration)>,
+    /// Share of requests to fail, between 0 and 1.
+    pub error_rate: f64,
+    pub status: StatusCode,
+}
+
+impl ChaosConfig {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut config = ChaosConfig {
+            latency: None,
+            error_rate: 0.0,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        };
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, found `{}`", part))?;
+            let value = value.trim();
+            match key.trim() {
+                "latency" => {
+                    let (min, max) = value.split_once("..").unwrap_or((value, value));
+                    let duration = |s: &str| parse_duration(s).ok_or_else(|| format!("invalid latency `{}`", s));
+                    let (min, max) = (duration(min)?, duration(max)?);
+                    if max < min {
+                        return Err(format!("latency range `{}` is reversed", value));
+                    }
+                    config.latency = Some((min, max));
+                }
+                "errors" => {
+                    let rate = match value.strip_suffix('%') {
+                        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+                        None => value.parse::<f64>(),
+                    }
+                    .map_err(|_| format!("invalid error rate `{}`", value))?;
+                    if !(0.0..=1.0).contains(&rate) {
+                        return Err(format!("error rate `{}` must be between 0% and 100%", value));
+                    }
+                    config.error_rate = rate;
+                }
+                "status" => {
+                    config.status = value
+                        .parse::<u16>()
+                        .ok()
+                        .and_then(|s| StatusCode::from_u16(s).ok())
+                        .ok_or_else(|| format!("invalid status `{}`", value))?;
+                }
+                other => return Err(format!("unknown chaos setting `{}`", other)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Waits out the injected latency and reports whether this call should fail.
+    pub async fn disturb(&self) -> bool {
+        if let Some((min, max)) = self.latency {
+            let delay = min + (max - min).mul_f64(random_unit());
+            tokio::time::sleep(delay).await;
+        }
+        self.error_rate > 0.0 && random_unit() < self.error_rate
+    }
+}
+
+/// A uniformly distributed number in `[0, 1)`.
+fn random_unit() -> f64 {
+    let bytes = uuid::Uuid::new_v4().into_bytes();
+    let n = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    (n >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Applies `spec` to every route and datasource call built from now on.
+pub fn set_global_chaos(spec: &str) -> Result<(), String> {
+    let config = ChaosConfig::parse(spec)?;
+    log(LogLevel::Warn, &format!("Chaos mode enabled: {}", spec));
+    *GLOBAL_CHAOS.write().unwrap() = Some(config);
+    Ok(())
+}
+
+pub fn global_chaos() -> Option<ChaosConfig> {
+    GLOBAL_CHAOS.read().unwrap().clone()
+}
+
+/// The chaos applied to a route: its own `chaos` key, or else the global spec.
+pub fn chaos_for_route(section: &Section) -> Option<ChaosConfig> {
+    match section.kv.get("chaos").and_then(|v| v.as_str()) {
+        Some("off") | Some("none") => None,
+        Some(spec) => match ChaosConfig::parse(spec) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                log(
+                    LogLevel::Warn,
+                    &format!("Ignoring chaos on @{}: {}", section.path.join("/"), e),
+                );
+                global_chaos()
+            }
+        },
+        None => global_chaos(),
+    }
+}
+
+/// Middleware delaying the request and answering with the configured failure status.
+pub async fn inject(req: Request<Body>, next: Next, config: ChaosConfig) -> Response {
+    if config.disturb().await {
+        return (config.status, "Injected failure (chaos mode)").into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chaos_specs() {
+        let config = ChaosConfig::parse("latency=100ms..300ms, errors=5%, status=500").unwrap();
+        assert_eq!(
+            config.latency,
+            Some((Duration::from_millis(100), Duration::from_millis(300)))
+        );
+        assert!((config.error_rate - 0.05).abs() < 1e-9);
+        assert_eq!(config.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(ChaosConfig::parse("errors=150%").is_err());
+        assert!(ChaosConfig::parse("jitter=1s").is_err());
+    }
+}
@@ -1,50 +1,88 @@
-use crate::core::{execute_steps, AppState};
-use crate::rune_ast::Value as RuneValue;
+use crate::builtins::Context;
+use crate::core::constants::{self, enum_members};
+use crate::core::relations;
+use crate::core::tokenizer::split_top_level;
+use crate::core::{errors, execute_steps, execute_steps_inner, AppState};
+use crate::rune_ast::{RuneDocument, Value as RuneValue};
 use crate::util::{log, LogLevel};
 use async_graphql::dynamic::{
-    Field, FieldFuture, FieldValue, InputValue, Object, Scalar, Schema, TypeRef,
+    Enum, Field, FieldFuture, FieldValue, InputObject, InputValue, Object, Scalar, Schema, SchemaBuilder,
+    TypeRef,
 };
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextResolve, ResolveInfo};
+use async_graphql::{ErrorExtensions, PathSegment, ServerResult};
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 use axum::{response::IntoResponse, routing::get, Router};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
 
 pub async fn build_graphql_router(state: AppState) -> Router {
     // Memory initialization moved to core::initialize_memory_from_doc
     crate::core::initialize_memory_from_doc(&state.doc, &state.path).await;
 
-    // 2. Build Schema
+    let schema = build_schema(&state);
+
+    let graphql_handler = move |req: GraphQLRequest| {
+        let schema = schema.clone();
+        async move { GraphQLResponse::from(schema.execute(req.into_inner()).await) }
+    };
+
+    Router::new().route("/graphql", get(graphql_playground).post(graphql_handler))
+}
+
+/// Builds the executable schema from the document's `@Schema`, `@GraphQL/Query`,
+/// `@GraphQL/Mutation`, `@GraphQL/Enum` and `@GraphQL/Scalar` sections, plus the `JSON`
+/// and `DateTime` scalars unless the document defines its own. REST routes with a
+/// `graphql` query run against it too.
+pub fn build_schema(state: &AppState) -> Schema {
     let mut query_object = Object::new("Query");
     let mut mutation_object = Object::new("Mutation");
 
     // Pre-calculate schemas for easy access
     let schemas = state.schemas.clone();
 
-    // Helper to map Rune Schema to GraphQL Type
-    fn map_type(rune_type: &str) -> TypeRef {
-        match rune_type {
-            "number" => TypeRef::named_nn(TypeRef::FLOAT),
-            "string" => TypeRef::named_nn(TypeRef::STRING),
-            "bool" => TypeRef::named_nn(TypeRef::BOOLEAN),
-            _ => TypeRef::named_nn(rune_type),
-        }
-    }
-
     let mutation_has_fields = state.doc.sections.iter().any(|s| {
         s.path.len() >= 2 && &s.path[0..2] == vec!["GraphQL", "Mutation"] && !s.series.is_empty()
     });
 
-    let mut schema_builder = if mutation_has_fields {
+    let schema_builder = if mutation_has_fields {
         Schema::build("Query", Some("Mutation"), None)
     } else {
         Schema::build("Query", None, None)
-    };
+    }
+    .extension(ErrorPaths);
+    let (mut schema_builder, scalars) = register_custom_types(schema_builder, &state.doc);
+    if !scalars.contains("JSON") {
+        schema_builder = schema_builder.register(Scalar::new("JSON"));
+    }
+    if !scalars.contains("DateTime") {
+        schema_builder = schema_builder.register(
+            Scalar::new("DateTime")
+                .description("A date and time, written in RFC 3339 (`2024-01-05T09:30:00Z`)")
+                .specified_by_url("https://datatracker.ietf.org/doc/html/rfc3339")
+                .validator(|value| {
+                    matches!(value, async_graphql::Value::String(s) if crate::builtins::builtin::date::parse_datetime(s).is_some())
+                }),
+        );
+    }
 
-    // Register all schemas as GraphQL Objects
+    // Register all schemas as GraphQL Objects, with `@GraphQL/Type/<Name>` resolvers
+    let mut resolvers = field_resolvers(&state.doc);
     for (name, section) in schemas.iter() {
         let mut obj = Object::new(name);
+        let mut type_resolvers = resolvers.remove(name).unwrap_or_default();
         for (field_name, field_type) in &section.kv {
-            let type_name = field_type.as_str().unwrap_or("string").to_string();
+            // Boolean entries are table options (`timestamps = true`), not fields
+            let Some(field_type) = field_type.as_str() else {
+                continue;
+            };
+            let type_name = relations::resolve_type(field_type, &schemas).to_string();
+            if let Some(pos) = type_resolvers.iter().position(|r| &r.name == field_name) {
+                let resolver = type_resolvers.remove(pos);
+                obj = obj.field(resolver_field(state, resolver, map_type(&type_name)));
+                continue;
+            }
             obj = obj.field(Field::new(field_name, map_type(&type_name), |ctx| {
                 FieldFuture::new(async move {
                     let parent = ctx.parent_value.as_value().unwrap();
@@ -60,8 +98,21 @@ pub async fn build_graphql_router(state: AppState) -> Router {
                 })
             }));
         }
+        for resolver in type_resolvers {
+            let return_type = infer_return_type(&resolver.name, resolver.return_type.as_ref());
+            obj = obj.field(resolver_field(state, resolver, return_type));
+        }
         schema_builder = schema_builder.register(obj);
     }
+    for input in input_objects(&schemas) {
+        schema_builder = schema_builder.register(input);
+    }
+    for type_name in resolvers.keys() {
+        log(
+            LogLevel::Warn,
+            &format!("@GraphQL/Type/{}: no @Schema/{} to attach field resolvers to", type_name, type_name),
+        );
+    }
 
     // Register Queries
     for query_section in state
@@ -71,76 +122,10 @@ pub async fn build_graphql_router(state: AppState) -> Router {
         .filter(|s| s.path.len() >= 2 && &s.path[0..2] == vec!["GraphQL", "Query"])
     {
         for (field_name, field_value) in &query_section.series {
-            let mut name = field_name.clone();
-            let mut arg_defs = Vec::new();
-            if let Some(pos) = field_name.find('(') {
-                name = field_name[..pos].trim().to_string();
-                let args_str = &field_name[pos + 1..field_name.len() - 1];
-                for arg_part in args_str.split(',') {
-                    let parts: Vec<&str> = arg_part.split(':').map(|s| s.trim()).collect();
-                    if parts.len() == 2 {
-                        arg_defs.push((parts[0].to_string(), parts[1].to_string()));
-                    }
-                }
-            }
-
-            // Infer return type
-            let return_type = if query_section.path.len() > 2 {
-                TypeRef::named_nn(query_section.path[2].clone())
-            } else if name.ends_with('s') {
-                // Plural: treat as list, auto uppercase first char
-                let singular = name.trim_end_matches('s');
-                let type_name = format!("{}{}", singular[..1].to_uppercase(), &singular[1..]);
-                TypeRef::named_nn_list_nn(&type_name)
-            } else {
-                let type_name = format!("{}{}", name[..1].to_uppercase(), &name[1..]);
-                TypeRef::named_nn(&type_name)
-            };
-
-            let steps = field_value.clone();
-            let state_clone = state.clone();
-            let arg_defs_clone = arg_defs.clone();
-
-            let mut field = Field::new(name, return_type, move |ctx| {
-                let steps = steps.clone();
-                let state_clone = state_clone.clone();
-                let arg_defs = arg_defs_clone.clone();
-                FieldFuture::new(async move {
-                    let mut path_params = HashMap::new();
-                    for (arg_name, _arg_type) in &arg_defs {
-                        if let Some(val) = ctx.args.get(arg_name) {
-                            let v = val.as_value();
-                            let s = match v {
-                                async_graphql::Value::String(s) => s.clone(),
-                                _ => v.to_string().trim_matches('"').to_string(),
-                            };
-                            log(
-                                LogLevel::Debug,
-                                &format!("GraphQL Arg (Query): {} = {}", arg_name, s),
-                            );
-                            path_params.insert(arg_name.clone(), s);
-                        }
-                    }
-
-                    log(
-                        LogLevel::Debug,
-                        &format!("Executing GraphQL Query steps: {:?}", steps),
-                    );
-                    let (_code, resp) =
-                        execute_steps(state_clone, steps, None, Some(path_params)).await;
-                    log(LogLevel::Debug, &format!("GraphQL Query Resp: {}", resp));
-                    let json_res: serde_json::Value =
-                        serde_json::from_str(&resp).unwrap_or(serde_json::Value::String(resp));
-                    let gql_val = async_graphql::Value::from_json(json_res)
-                        .unwrap_or(async_graphql::Value::Null);
-                    Ok(Some(FieldValue::from(gql_val)))
-                })
-            });
-
-            for (arg_name, arg_type) in &arg_defs {
-                field = field.argument(InputValue::new(arg_name, map_type(arg_type)));
-            }
+            let (name, arg_defs) = parse_field_signature(field_name);
+            let return_type = infer_return_type(&name, query_section.path.get(2));
 
+            let field = step_field(state, name, return_type, arg_defs, field_value.clone(), "Query");
             query_object = query_object.field(field);
         }
     }
@@ -153,18 +138,7 @@ pub async fn build_graphql_router(state: AppState) -> Router {
         .filter(|s| s.path.len() >= 2 && &s.path[0..2] == vec!["GraphQL", "Mutation"])
     {
         for (field_name, field_value) in &mutation_section.series {
-            let mut name = field_name.clone();
-            let mut arg_defs = Vec::new();
-            if let Some(pos) = field_name.find('(') {
-                name = field_name[..pos].trim().to_string();
-                let args_str = &field_name[pos + 1..field_name.len() - 1];
-                for arg_part in args_str.split(',') {
-                    let parts: Vec<&str> = arg_part.split(':').map(|s| s.trim()).collect();
-                    if parts.len() == 2 {
-                        arg_defs.push((parts[0].to_string(), parts[1].to_string()));
-                    }
-                }
-            }
+            let (name, arg_defs) = parse_field_signature(field_name);
 
             // Infer return type
             let return_type = if mutation_section.path.len() > 2 {
@@ -173,50 +147,7 @@ pub async fn build_graphql_router(state: AppState) -> Router {
                 TypeRef::named_nn("JSON")
             };
 
-            let steps = field_value.clone();
-            let state_clone = state.clone();
-            let arg_defs_clone = arg_defs.clone();
-
-            let mut field = Field::new(name, return_type, move |ctx| {
-                let steps = steps.clone();
-                let state_clone = state_clone.clone();
-                let arg_defs = arg_defs_clone.clone();
-                FieldFuture::new(async move {
-                    let mut path_params = HashMap::new();
-                    for (arg_name, _arg_type) in &arg_defs {
-                        if let Some(val) = ctx.args.get(arg_name) {
-                            let v = val.as_value();
-                            let s = match v {
-                                async_graphql::Value::String(s) => s.clone(),
-                                _ => v.to_string().trim_matches('"').to_string(),
-                            };
-                            log(
-                                LogLevel::Debug,
-                                &format!("GraphQL Arg (Mutation): {} = {}", arg_name, s),
-                            );
-                            path_params.insert(arg_name.clone(), s);
-                        }
-                    }
-
-                    log(
-                        LogLevel::Debug,
-                        &format!("Executing GraphQL Mutation steps: {:?}", steps),
-                    );
-                    let (_code, resp) =
-                        execute_steps(state_clone, steps, None, Some(path_params)).await;
-                    log(LogLevel::Debug, &format!("GraphQL Mutation Resp: {}", resp));
-                    let json_res: serde_json::Value =
-                        serde_json::from_str(&resp).unwrap_or(serde_json::Value::String(resp));
-                    let gql_val = async_graphql::Value::from_json(json_res)
-                        .unwrap_or(async_graphql::Value::Null);
-                    Ok(Some(FieldValue::from(gql_val)))
-                })
-            });
-
-            for (arg_name, arg_type) in &arg_defs {
-                field = field.argument(InputValue::new(arg_name, map_type(arg_type)));
-            }
-
+            let field = step_field(state, name, return_type, arg_defs, field_value.clone(), "Mutation");
             mutation_object = mutation_object.field(field);
         }
     }
@@ -242,8 +173,8 @@ pub async fn build_graphql_router(state: AppState) -> Router {
                 } else {
                     Vec::new()
                 };
-                let (_code, resp) = execute_steps(state, steps, None, None).await;
-                Ok(Some(FieldValue::value(resp)))
+                let response = execute_steps(state, &steps, None, None).await;
+                Ok(Some(FieldValue::value(response.body)))
             })
         })
         .argument(InputValue::new(
@@ -253,29 +184,465 @@ pub async fn build_graphql_router(state: AppState) -> Router {
     );
 
     // Build schema only once, after all objects are registered
-    let schema = if mutation_has_fields {
+    if mutation_has_fields {
         schema_builder
             .register(query_object)
             .register(mutation_object)
-            .register(Scalar::new("JSON"))
             .finish()
             .map_err(|e| e.to_string())
             .unwrap()
     } else {
         schema_builder
             .register(query_object)
-            .register(Scalar::new("JSON"))
             .finish()
             .map_err(|e| e.to_string())
             .unwrap()
+    }
+}
+
+/// The GraphQL name of a Rune type: `number` is `Float`, `string` is `String`, `bool` is
+/// `Boolean`, and `datetime`, `json` and `vector(...)` are the `DateTime` and `JSON`
+/// scalars; other names are used as they are.
+fn graphql_type_name(rune_type: &str) -> &str {
+    match rune_type {
+        "number" => TypeRef::FLOAT,
+        "string" => TypeRef::STRING,
+        "bool" => TypeRef::BOOLEAN,
+        "datetime" => "DateTime",
+        "json" => "JSON",
+        other if crate::core::schema_options::vector_dimensions(other).is_some() => "JSON",
+        other => other,
+    }
+}
+
+/// Maps a Rune schema type to a non-null GraphQL type.
+fn map_type(rune_type: &str) -> TypeRef {
+    TypeRef::named_nn(graphql_type_name(rune_type))
+}
+
+/// Maps an argument type to a GraphQL type. `[number]` is a list, a trailing `?` makes a
+/// type nullable and a trailing `!` (the default) keeps it non-null, so `[string?]?` is a
+/// nullable list of nullable strings.
+fn arg_type_ref(spec: &str) -> TypeRef {
+    let spec = spec.trim();
+    let (spec, nullable) = match spec.strip_suffix('?') {
+        Some(spec) => (spec.trim_end(), true),
+        None => (spec.strip_suffix('!').unwrap_or(spec).trim_end(), false),
     };
+    let inner = match spec.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(item) => TypeRef::List(Box::new(arg_type_ref(item))),
+        None => TypeRef::named(graphql_type_name(spec)),
+    };
+    if nullable {
+        inner
+    } else {
+        TypeRef::NonNull(Box::new(inner))
+    }
+}
 
-    let graphql_handler = move |req: GraphQLRequest| {
-        let schema = schema.clone();
-        async move { GraphQLResponse::from(schema.execute(req.into_inner()).await) }
+/// An argument of a field signature: `limit: number = 10` or `ids: [number]?`.
+#[derive(Debug, Clone, PartialEq)]
+struct ArgDef {
+    name: String,
+    /// The type as written, with any list brackets and nullability markers.
+    spec: String,
+    /// The default value as written, e.g. `10`, `"draft"` or `[1, 2]`.
+    default: Option<String>,
+}
+
+impl ArgDef {
+    /// The type name without list brackets or nullability markers.
+    fn base_type(&self) -> &str {
+        self.spec.trim_matches(|c: char| matches!(c, '[' | ']' | '?' | '!') || c.is_whitespace())
+    }
+
+    fn is_list(&self) -> bool {
+        self.spec.trim_start().starts_with('[')
+    }
+
+    /// The default as a GraphQL value: JSON literals as such, and other words as enum
+    /// values (or strings, for `string` arguments).
+    fn default_value(&self) -> Option<async_graphql::Value> {
+        let text = self.default.as_deref()?;
+        Some(match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(json) => async_graphql::Value::from_json(json).unwrap_or(async_graphql::Value::Null),
+            Err(_) if self.base_type() == "string" => async_graphql::Value::String(text.to_string()),
+            Err(_) => async_graphql::Value::Enum(async_graphql::Name::new(text)),
+        })
+    }
+
+    fn input_value(&self) -> InputValue {
+        let input = InputValue::new(&self.name, arg_type_ref(&self.spec));
+        match self.default_value() {
+            Some(value) => input.default_value(value),
+            None => input,
+        }
+    }
+}
+
+/// The argument's value, or its default when the query leaves it out.
+fn arg_value(ctx: &async_graphql::dynamic::ResolverContext<'_>, arg: &ArgDef) -> Option<async_graphql::Value> {
+    match ctx.args.get(&arg.name) {
+        Some(val) => Some(val.as_value().clone()),
+        None => arg.default_value(),
+    }
+}
+
+/// The name of the input object generated for a `@Schema`: `BookInput` for `Book`.
+fn input_name(schema: &str) -> String {
+    format!("{}Input", schema)
+}
+
+/// A `<Name>Input` object for every `@Schema`, so arguments such as `input: BookInput` take
+/// a whole record. Fields are optional, leaving required ones to `validate`; fields typed
+/// by another schema take its input object.
+fn input_objects(schemas: &HashMap<String, crate::rune_ast::Section>) -> Vec<InputObject> {
+    let mut inputs = Vec::new();
+    for (name, section) in schemas {
+        let mut fields: Vec<(&String, &str)> = section
+            .kv
+            .iter()
+            .filter_map(|(field, typ)| Some((field, relations::resolve_type(typ.as_str()?, schemas))))
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        fields.sort();
+        let mut input = InputObject::new(input_name(name));
+        for (field, typ) in fields {
+            let type_ref = match typ {
+                other if schemas.contains_key(other) => TypeRef::named(input_name(other)),
+                other => TypeRef::named(graphql_type_name(other)),
+            };
+            input = input.field(InputValue::new(field, type_ref));
+        }
+        inputs.push(input);
+    }
+    inputs
+}
+
+/// The field error for an error response from a field's steps. The message is the body's
+/// `message` (or `error`), or the body itself; `extensions` carry the `status`, a `code`
+/// such as `NOT_FOUND`, and an object body as `details`. The field's path is added by the
+/// executor, so clients can tell which field failed.
+fn response_error(status: u16, body: &str) -> async_graphql::Error {
+    let parsed: Option<serde_json::Value> = serde_json::from_str(body).ok();
+    let reason = axum::http::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Error");
+    let message = errors::response_message(status, body);
+    let code = reason.to_uppercase().replace([' ', '-'], "_");
+    async_graphql::Error::new(message).extend_with(|_, extensions| {
+        extensions.set("status", status);
+        extensions.set("code", code);
+        if let Some(details @ serde_json::Value::Object(_)) = parsed {
+            if let Ok(details) = async_graphql::Value::from_json(details) {
+                extensions.set("details", details);
+            }
+        }
+    })
+}
+
+/// Gives field errors the path of the field that failed, which the dynamic schema's
+/// executor leaves empty for errors returned by resolvers.
+struct ErrorPaths;
+
+impl ExtensionFactory for ErrorPaths {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ErrorPaths)
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for ErrorPaths {
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<async_graphql::Value>> {
+        let path = *info.path_node;
+        next.run(ctx, info).await.map_err(|mut err| {
+            if err.path.is_empty() {
+                err.path = path
+                    .to_string_vec()
+                    .into_iter()
+                    .map(|segment| match segment.parse() {
+                        Ok(index) => PathSegment::Index(index),
+                        Err(_) => PathSegment::Field(segment),
+                    })
+                    .collect();
+            }
+            err
+        })
+    }
+}
+
+/// A `@GraphQL/Query` or `@GraphQL/Mutation` field answered by steps. Scalar arguments are
+/// visible by name; the first input object argument (`input: BookInput`) is `body`. Error
+/// responses become field errors.
+fn step_field(
+    state: &AppState,
+    name: String,
+    return_type: TypeRef,
+    arg_defs: Vec<ArgDef>,
+    steps: Vec<RuneValue>,
+    kind: &'static str,
+) -> Field {
+    let inputs: HashSet<String> = state.schemas.keys().map(|s| input_name(s)).collect();
+    let state = state.clone();
+    let field_args = arg_defs.clone();
+    let mut field = Field::new(name, return_type, move |ctx| {
+        let steps = steps.clone();
+        let state = state.clone();
+        let arg_defs = arg_defs.clone();
+        let inputs = inputs.clone();
+        FieldFuture::new(async move {
+            let mut path_params = HashMap::new();
+            let mut body = None;
+            let mut prelude = Vec::new();
+            for arg in &arg_defs {
+                let Some(v) = arg_value(&ctx, arg) else {
+                    continue;
+                };
+                if inputs.contains(arg.base_type()) && !arg.is_list() {
+                    if body.is_none() {
+                        body = Some(v.into_json()?.to_string());
+                        prelude.push(RuneValue::String("parse-json".to_string()));
+                    }
+                    continue;
+                }
+                let s = match v {
+                    async_graphql::Value::String(s) => s,
+                    async_graphql::Value::Null => continue,
+                    // Lists and objects arrive as JSON text and are parsed before the steps run
+                    v @ (async_graphql::Value::List(_) | async_graphql::Value::Object(_)) => {
+                        prelude.push(RuneValue::String(format!("{0} = parse-json {0}", arg.name)));
+                        v.into_json()?.to_string()
+                    }
+                    v => v.to_string().trim_matches('"').to_string(),
+                };
+                log(LogLevel::Debug, &format!("GraphQL Arg ({}): {} = {}", kind, arg.name, s));
+                path_params.insert(arg.name.clone(), s);
+            }
+            let steps: Vec<RuneValue> = prelude.into_iter().chain(steps).collect();
+
+            log(LogLevel::Debug, &format!("Executing GraphQL {} steps: {:?}", kind, steps));
+            let response = execute_steps(state, &steps, body, Some(path_params)).await;
+            log(LogLevel::Debug, &format!("GraphQL {} Resp: {}", kind, response.body));
+            if response.status.as_u16() >= 400 {
+                return Err(response_error(response.status.as_u16(), &response.body));
+            }
+            let gql_val = async_graphql::Value::from_json(response.value()).unwrap_or(async_graphql::Value::Null);
+            Ok(Some(FieldValue::from(gql_val)))
+        })
+    });
+    for arg in &field_args {
+        field = field.argument(arg.input_value());
+    }
+    field
+}
+
+/// Splits `books(limit: number = 10, ids: [number]?)` into the field name and its arguments.
+fn parse_field_signature(signature: &str) -> (String, Vec<ArgDef>) {
+    let Some(pos) = signature.find('(') else {
+        return (signature.to_string(), Vec::new());
     };
+    let args_str = signature[pos + 1..].trim_end().strip_suffix(')').unwrap_or(&signature[pos + 1..]);
+    let arg_defs = split_top_level(args_str, ',')
+        .into_iter()
+        .filter_map(|arg_part| {
+            let (name, rest) = arg_part.split_once(':')?;
+            let (spec, default) = match rest.split_once('=') {
+                Some((spec, default)) => (spec, Some(default.trim().to_string())),
+                None => (rest, None),
+            };
+            let (name, spec) = (name.trim(), spec.trim());
+            (!name.is_empty() && !spec.is_empty()).then(|| ArgDef {
+                name: name.to_string(),
+                spec: spec.to_string(),
+                default,
+            })
+        })
+        .collect();
+    (signature[..pos].trim().to_string(), arg_defs)
+}
 
-    Router::new().route("/graphql", get(graphql_playground).post(graphql_handler))
+/// The return type named in the section path, or one inferred from the field name:
+/// `books` returns `[Book!]!` and `book` returns `Book!`.
+fn infer_return_type(name: &str, declared: Option<&String>) -> TypeRef {
+    if let Some(type_name) = declared {
+        TypeRef::named_nn(type_name.clone())
+    } else if name.ends_with('s') {
+        // Plural: treat as list, auto uppercase first char
+        let singular = name.trim_end_matches('s');
+        let type_name = format!("{}{}", singular[..1].to_uppercase(), &singular[1..]);
+        TypeRef::named_nn_list_nn(&type_name)
+    } else {
+        let type_name = format!("{}{}", name[..1].to_uppercase(), &name[1..]);
+        TypeRef::named_nn(&type_name)
+    }
+}
+
+/// A field of an object type resolved by steps, from a `@GraphQL/Type/<Name>` series.
+struct FieldResolver {
+    name: String,
+    args: Vec<ArgDef>,
+    steps: Vec<RuneValue>,
+    return_type: Option<String>,
+}
+
+/// The field resolvers of every `@GraphQL/Type/<Name>[/<ReturnType>]` section, by type name.
+fn field_resolvers(doc: &RuneDocument) -> HashMap<String, Vec<FieldResolver>> {
+    let mut resolvers: HashMap<String, Vec<FieldResolver>> = HashMap::new();
+    for section in doc
+        .sections
+        .iter()
+        .filter(|s| s.path.len() >= 3 && s.path[0] == "GraphQL" && s.path[1] == "Type")
+    {
+        for (signature, steps) in &section.series {
+            let (name, args) = parse_field_signature(signature);
+            resolvers.entry(section.path[2].clone()).or_default().push(FieldResolver {
+                name,
+                args,
+                steps: steps.clone(),
+                return_type: section.path.get(3).cloned(),
+            });
+        }
+    }
+    resolvers
+}
+
+/// A field whose value comes from running the resolver's steps, with the enclosing
+/// object as `parent` and each argument by name. Error responses become field errors.
+fn resolver_field(state: &AppState, resolver: FieldResolver, return_type: TypeRef) -> Field {
+    let state = state.clone();
+    let FieldResolver { name, args, steps, .. } = resolver;
+    let arg_defs = args.clone();
+    let mut field = Field::new(name, return_type, move |ctx| {
+        let state = state.clone();
+        let steps = steps.clone();
+        let arg_defs = arg_defs.clone();
+        FieldFuture::new(async move {
+            let mut step_ctx = Context::new();
+            constants::seed_context(&state.doc, &mut step_ctx);
+            let parent = ctx.parent_value.as_value().cloned().unwrap_or(async_graphql::Value::Null);
+            step_ctx.insert("parent".to_string(), parent.into_json()?);
+            for arg in &arg_defs {
+                if let Some(val) = arg_value(&ctx, arg) {
+                    step_ctx.insert(arg.name.clone(), val.into_json()?);
+                }
+            }
+            let resp = execute_steps_inner(state, &steps, &mut step_ctx).await;
+            crate::builtins::builtin::transaction::rollback_all(&mut step_ctx).await;
+            let json_res = match resp {
+                Some((code, body)) if code >= 400 => return Err(response_error(code, &body)),
+                Some((_, body)) => serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body)),
+                None => serde_json::Value::Null,
+            };
+            let gql_val = async_graphql::Value::from_json(json_res).unwrap_or(async_graphql::Value::Null);
+            Ok(Some(FieldValue::from(gql_val)))
+        })
+    });
+    for arg in &args {
+        field = field.argument(arg.input_value());
+    }
+    field
+}
+
+/// Whether `name` is a valid GraphQL name (`[_A-Za-z][_0-9A-Za-z]*`).
+fn is_graphql_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+fn member_names(section: &crate::rune_ast::Section) -> Vec<String> {
+    enum_members(section)
+        .iter()
+        .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+        .collect()
+}
+
+/// Registers the document's custom types so fields and arguments can name them:
+///
+/// - `@GraphQL/Enum/<Name>` with `values = (...)` becomes an enum; invalid member names are skipped
+/// - `@Enum/<Name>` becomes an enum too, or a scalar when a member is not a valid GraphQL name
+/// - `@GraphQL/Scalar/<Name>` becomes a scalar; `pattern` restricts its inputs to matching
+///   strings and `specified_by` sets its specification URL
+///
+/// Both take an optional `description`. Returns the builder and the scalar names.
+fn register_custom_types(mut builder: SchemaBuilder, doc: &RuneDocument) -> (SchemaBuilder, HashSet<String>) {
+    let description = |section: &crate::rune_ast::Section| {
+        section.kv.get("description").and_then(|v| v.as_str()).map(str::to_string)
+    };
+    let mut enums: BTreeMap<String, (Vec<String>, Option<String>)> = BTreeMap::new();
+    let mut scalars: BTreeMap<String, Scalar> = BTreeMap::new();
+
+    for section in doc.sections.iter().filter(|s| s.path.len() == 2 && s.path[0] == "Enum") {
+        let members = member_names(section);
+        if members.iter().all(|m| is_graphql_name(m)) {
+            enums.insert(section.path[1].clone(), (members, None));
+        } else {
+            scalars.insert(section.path[1].clone(), Scalar::new(&section.path[1]));
+        }
+    }
+    for section in doc.sections.iter().filter(|s| s.path.len() == 3 && s.path[0] == "GraphQL") {
+        let name = section.path[2].clone();
+        match section.path[1].as_str() {
+            "Enum" => {
+                let (valid, invalid): (Vec<String>, Vec<String>) =
+                    member_names(section).into_iter().partition(|m| is_graphql_name(m));
+                if !invalid.is_empty() {
+                    log(
+                        LogLevel::Warn,
+                        &format!("GraphQL enum {}: skipping invalid values {}", name, invalid.join(", ")),
+                    );
+                }
+                scalars.remove(&name);
+                enums.insert(name, (valid, description(section)));
+            }
+            "Scalar" => {
+                let mut scalar = Scalar::new(&name);
+                if let Some(text) = description(section) {
+                    scalar = scalar.description(text);
+                }
+                if let Some(url) = section.kv.get("specified_by").and_then(|v| v.as_str()) {
+                    scalar = scalar.specified_by_url(url);
+                }
+                if let Some(pattern) = section.kv.get("pattern").and_then(|v| v.as_str()) {
+                    match regex::Regex::new(pattern) {
+                        Ok(re) => {
+                            scalar = scalar.validator(move |value| {
+                                matches!(value, async_graphql::Value::String(s) if re.is_match(s))
+                            })
+                        }
+                        Err(e) => log(
+                            LogLevel::Warn,
+                            &format!("GraphQL scalar {}: ignoring invalid pattern: {}", name, e),
+                        ),
+                    }
+                }
+                enums.remove(&name);
+                scalars.insert(name, scalar);
+            }
+            _ => {}
+        }
+    }
+
+    for (name, (members, text)) in enums {
+        let mut graphql_enum = Enum::new(&name).items(members);
+        if let Some(text) = text {
+            graphql_enum = graphql_enum.description(text);
+        }
+        builder = builder.register(graphql_enum);
+    }
+    let names = scalars.keys().cloned().collect();
+    for (_, scalar) in scalars {
+        builder = builder.register(scalar);
+    }
+    (builder, names)
 }
 
 async fn graphql_playground() -> impl IntoResponse {
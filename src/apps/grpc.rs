@@ -0,0 +1,567 @@
+//! The gRPC app type, `type = Grpc`: a service generated from the document's `@Schema`
+//! and `@Rpc` sections.
+//!
+//! ```rune
+//! @App
+//! type = Grpc
+//! package = library
+//!
+//! @Schema/Book
+//! id = number
+//! title = string
+//!
+//! @Schema/BookId
+//! id = number
+//!
+//! @Rpc/Library/GetBook
+//! input = BookId
+//! output = Book
+//! run:
+//!     books = memory.get "books"
+//!     book = books.find it.id == body.id
+//!     respond 200 book
+//! ```
+//!
+//! Each `@Rpc/<Service>/<Method>` is a unary call of `<package>.<Service>` (package
+//! `vectrune` by default). Its steps get the request message as `body`, already parsed, and
+//! respond with the output message. A missing `input` or `output` is
+//! `google.protobuf.Empty`, and `output = [Book]` answers with a `<Method>Response` holding
+//! the list as `items`. Error responses become gRPC statuses: `404` is `NOT_FOUND`, `400`
+//! is `INVALID_ARGUMENT`, and so on.
+//!
+//! Schemas become messages with their fields numbered in name order: `number` is a
+//! `double`, `bool` a `bool`, `json` a `google.protobuf.Value`, a schema name that schema's
+//! message, and other types a `string`. Server reflection is enabled, so tools such as
+//! `grpcurl` can list and call the methods without a `.proto` file.
+
+use crate::core::{errors, execute_steps, relations, AppState};
+use crate::rune_ast::{RuneDocument, Section, Value};
+use crate::util::{log, LogLevel};
+use axum::Router;
+use prost_reflect::prost::Message;
+use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+use prost_reflect::prost_types::{
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet, MethodDescriptorProto,
+    ServiceDescriptorProto,
+};
+use prost_reflect::{DescriptorPool, DeserializeOptions, DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::{Code, Status};
+
+const DEFAULT_PACKAGE: &str = "vectrune";
+const FILE_NAME: &str = "vectrune.proto";
+const EMPTY: &str = "google.protobuf.Empty";
+const EMPTY_FILE: &str = "google/protobuf/empty.proto";
+const VALUE: &str = "google.protobuf.Value";
+const STRUCT_FILE: &str = "google/protobuf/struct.proto";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rpc {
+    pub service: String,
+    pub method: String,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub steps: Vec<Value>,
+}
+
+impl Rpc {
+    pub fn from_section(section: &Section) -> Result<Self, String> {
+        let (Some(service), Some(method)) = (section.path.get(1), section.path.get(2)) else {
+            return Err(format!("@{} must be named @Rpc/<Service>/<Method>", section.path.join("/")));
+        };
+        let steps = section
+            .series
+            .get("run")
+            .cloned()
+            .ok_or_else(|| format!("@Rpc/{}/{} requires run: steps", service, method))?;
+        let text = |key: &str| section.kv.get(key).and_then(Value::as_str).map(str::to_string);
+        Ok(Rpc {
+            service: service.clone(),
+            method: method.clone(),
+            input: text("input"),
+            output: text("output"),
+            steps,
+        })
+    }
+
+    /// The item type of a list output: `Book` for `output = [Book]`.
+    fn output_items(&self) -> Option<&str> {
+        self.output.as_deref()?.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+    }
+
+    fn response_name(&self) -> String {
+        format!("{}Response", self.method)
+    }
+}
+
+/// The document's `@Rpc` sections; invalid ones are skipped with a warning.
+pub fn rpcs(doc: &RuneDocument) -> Vec<Rpc> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Rpc"))
+        .filter_map(|section| match Rpc::from_section(section) {
+            Ok(rpc) => Some(rpc),
+            Err(e) => {
+                log(LogLevel::Warn, &e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The protobuf package of the services: the `@App` section's `package`, or `vectrune`.
+pub fn package(doc: &RuneDocument) -> String {
+    doc.get_section("App")
+        .and_then(|s| s.kv.get("package"))
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_PACKAGE)
+        .to_string()
+}
+
+/// A message field of the given Rune type.
+fn field(name: &str, number: i32, typ: &str, package: &str, schemas: &HashMap<String, Section>) -> FieldDescriptorProto {
+    let (r#type, type_name) = match relations::resolve_type(typ, schemas) {
+        "number" => (Type::Double, None),
+        "bool" => (Type::Bool, None),
+        "json" => (Type::Message, Some(format!(".{}", VALUE))),
+        other if schemas.contains_key(other) => (Type::Message, Some(format!(".{}.{}", package, other))),
+        _ => (Type::String, None),
+    };
+    FieldDescriptorProto {
+        name: Some(name.to_string()),
+        number: Some(number),
+        label: Some(Label::Optional as i32),
+        r#type: Some(r#type as i32),
+        type_name,
+        ..Default::default()
+    }
+}
+
+/// The `.proto` file describing the document's schemas and services.
+pub fn file_descriptor(doc: &RuneDocument, schemas: &HashMap<String, Section>) -> Result<FileDescriptorProto, String> {
+    let package = package(doc);
+    let rpcs = rpcs(doc);
+    let mut messages = Vec::new();
+    let mut uses_json = false;
+    let mut sorted: Vec<_> = schemas.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, schema) in sorted {
+        let mut fields: Vec<(&String, &str)> = schema
+            .kv
+            .iter()
+            .filter_map(|(field, typ)| Some((field, typ.as_str()?)))
+            .collect();
+        fields.sort();
+        let field = fields
+            .iter()
+            .enumerate()
+            .map(|(i, (field_name, typ))| {
+                uses_json |= relations::resolve_type(typ, schemas) == "json";
+                field(field_name, i as i32 + 1, typ, &package, schemas)
+            })
+            .collect();
+        messages.push(DescriptorProto {
+            name: Some(name.clone()),
+            field,
+            ..Default::default()
+        });
+    }
+
+    let mut uses_empty = false;
+    let mut message_type = |rpc: &Rpc, typ: Option<&str>| match typ {
+        None => {
+            uses_empty = true;
+            Ok(format!(".{}", EMPTY))
+        }
+        Some(name) if schemas.contains_key(name) => Ok(format!(".{}.{}", package, name)),
+        Some(name) => Err(format!("@Rpc/{}/{}: no @Schema/{}", rpc.service, rpc.method, name)),
+    };
+    let mut services: BTreeMap<&str, Vec<MethodDescriptorProto>> = BTreeMap::new();
+    for rpc in &rpcs {
+        let output_type = match rpc.output_items() {
+            Some(items) => {
+                let mut list = field("items", 1, items, &package, schemas);
+                list.label = Some(Label::Repeated as i32);
+                uses_json |= relations::resolve_type(items, schemas) == "json";
+                messages.push(DescriptorProto {
+                    name: Some(rpc.response_name()),
+                    field: vec![list],
+                    ..Default::default()
+                });
+                format!(".{}.{}", package, rpc.response_name())
+            }
+            None => message_type(rpc, rpc.output.as_deref())?,
+        };
+        let input_type = message_type(rpc, rpc.input.as_deref())?;
+        services.entry(&rpc.service).or_default().push(MethodDescriptorProto {
+            name: Some(rpc.method.clone()),
+            input_type: Some(input_type),
+            output_type: Some(output_type),
+            ..Default::default()
+        });
+    }
+
+    let mut dependency = Vec::new();
+    if uses_empty {
+        dependency.push(EMPTY_FILE.to_string());
+    }
+    if uses_json {
+        dependency.push(STRUCT_FILE.to_string());
+    }
+    Ok(FileDescriptorProto {
+        name: Some(FILE_NAME.to_string()),
+        package: Some(package.clone()),
+        dependency,
+        message_type: messages,
+        service: services
+            .into_iter()
+            .map(|(name, method)| ServiceDescriptorProto {
+                name: Some(name.to_string()),
+                method,
+                ..Default::default()
+            })
+            .collect(),
+        syntax: Some("proto3".to_string()),
+        ..Default::default()
+    })
+}
+
+/// The descriptors of the document's messages and services, with the well-known types
+/// they may use.
+pub fn descriptor_pool(doc: &RuneDocument, schemas: &HashMap<String, Section>) -> Result<DescriptorPool, String> {
+    let mut pool = DescriptorPool::global();
+    pool.add_file_descriptor_proto(file_descriptor(doc, schemas)?)
+        .map_err(|e| e.to_string())?;
+    Ok(pool)
+}
+
+/// `books` or `book-reviews` as `Books` or `BookReviews`.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Adds a `<Schema>Service` for each `@Route/CRUD` section: `List<Collection>`, `Get<Schema>`,
+/// `Create<Schema>`, `Update<Schema>` and `Delete<Schema>`, with a `<Schema>Id` request
+/// message and a `List<Collection>Response` holding the records as `items`.
+fn add_crud_services(file: &mut FileDescriptorProto, doc: &RuneDocument, schemas: &HashMap<String, Section>) {
+    let package = package(doc);
+    let qualified = |name: &str| format!(".{}.{}", package, name);
+    let mut uses_empty = false;
+    for route in doc.sections.iter().filter(|s| {
+        s.path.first().map(String::as_str) == Some("Route") && s.path.get(1).is_some_and(|m| m.eq_ignore_ascii_case("CRUD"))
+    }) {
+        let Some(schema) = route.kv.get("schema").and_then(Value::as_str).filter(|s| schemas.contains_key(*s)) else {
+            continue;
+        };
+        let collection = pascal_case(route.path[2..].iter().rev().find(|p| !p.is_empty()).map_or(schema, |p| p.as_str()));
+        let id_type = schemas[schema].kv.get("id").and_then(Value::as_str).unwrap_or("string");
+        let id_name = format!("{}Id", schema);
+        let list_name = format!("List{}Response", collection);
+        let mut items = field("items", 1, schema, &package, schemas);
+        items.label = Some(Label::Repeated as i32);
+        for (name, field) in [(&id_name, field("id", 1, id_type, &package, schemas)), (&list_name, items)] {
+            if !file.message_type.iter().any(|m| m.name.as_deref() == Some(name)) {
+                file.message_type.push(DescriptorProto {
+                    name: Some(name.clone()),
+                    field: vec![field],
+                    ..Default::default()
+                });
+            }
+        }
+        let empty = format!(".{}", EMPTY);
+        let method = |name: String, input: String, output: String| MethodDescriptorProto {
+            name: Some(name),
+            input_type: Some(input),
+            output_type: Some(output),
+            ..Default::default()
+        };
+        let methods = vec![
+            method(format!("List{}", collection), empty.clone(), qualified(&list_name)),
+            method(format!("Get{}", schema), qualified(&id_name), qualified(schema)),
+            method(format!("Create{}", schema), qualified(schema), qualified(schema)),
+            method(format!("Update{}", schema), qualified(schema), qualified(schema)),
+            method(format!("Delete{}", schema), qualified(&id_name), empty),
+        ];
+        uses_empty = true;
+        let service_name = format!("{}Service", schema);
+        match file.service.iter_mut().find(|s| s.name.as_deref() == Some(service_name.as_str())) {
+            Some(service) => service.method.extend(methods),
+            None => file.service.push(ServiceDescriptorProto {
+                name: Some(service_name),
+                method: methods,
+                ..Default::default()
+            }),
+        }
+    }
+    if uses_empty && !file.dependency.iter().any(|d| d == EMPTY_FILE) {
+        file.dependency.insert(0, EMPTY_FILE.to_string());
+    }
+}
+
+/// The `.proto` contract of the document: its schemas as messages, its `@Rpc` sections as
+/// services, and a service per `@Route/CRUD` section.
+pub fn proto_file(doc: &RuneDocument, schemas: &HashMap<String, Section>) -> Result<FileDescriptorProto, String> {
+    let mut file = file_descriptor(doc, schemas)?;
+    add_crud_services(&mut file, doc, schemas);
+    Ok(file)
+}
+
+/// `file` as `.proto` source.
+pub fn render_proto(file: &FileDescriptorProto) -> String {
+    let package = file.package();
+    let type_name = |name: &str| {
+        let name = name.trim_start_matches('.');
+        name.strip_prefix(package).and_then(|n| n.strip_prefix('.')).unwrap_or(name).to_string()
+    };
+    let mut out = format!("syntax = \"proto3\";\n\npackage {};\n", package);
+    if !file.dependency.is_empty() {
+        out.push('\n');
+        for dependency in &file.dependency {
+            out.push_str(&format!("import \"{}\";\n", dependency));
+        }
+    }
+    for message in &file.message_type {
+        out.push_str(&format!("\nmessage {} {{\n", message.name()));
+        for field in &message.field {
+            let typ = match field.r#type() {
+                Type::Message | Type::Enum => type_name(field.type_name()),
+                Type::Double => "double".to_string(),
+                Type::Bool => "bool".to_string(),
+                _ => "string".to_string(),
+            };
+            let repeated = if field.label() == Label::Repeated { "repeated " } else { "" };
+            out.push_str(&format!("  {}{} {} = {};\n", repeated, typ, field.name(), field.number()));
+        }
+        out.push_str("}\n");
+    }
+    for service in &file.service {
+        out.push_str(&format!("\nservice {} {{\n", service.name()));
+        for method in &service.method {
+            out.push_str(&format!(
+                "  rpc {}({}) returns ({});\n",
+                method.name(),
+                type_name(method.input_type()),
+                type_name(method.output_type())
+            ));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// A codec for messages known only at runtime: encodes any `DynamicMessage` and decodes
+/// messages of the given type.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec(pub MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicCodec;
+    type Decoder = DynamicCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.clone()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        self.clone()
+    }
+}
+
+impl Encoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst).map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+impl Decoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|e| Status::invalid_argument(e.to_string()))
+    }
+}
+
+/// The gRPC status code for an HTTP error status.
+fn status_code(status: u16) -> Code {
+    match status {
+        400 | 422 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        409 => Code::AlreadyExists,
+        412 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        501 => Code::Unimplemented,
+        503 => Code::Unavailable,
+        504 => Code::DeadlineExceeded,
+        400..=499 => Code::FailedPrecondition,
+        _ => Code::Internal,
+    }
+}
+
+/// Writes whole `double`s as integers, so `id = 1` reads as `1` rather than `1.0`.
+fn whole_numbers(value: JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < i64::MAX as f64 => JsonValue::from(f as i64),
+            _ => JsonValue::Number(n),
+        },
+        JsonValue::Array(items) => JsonValue::Array(items.into_iter().map(whole_numbers).collect()),
+        JsonValue::Object(obj) => JsonValue::Object(obj.into_iter().map(|(k, v)| (k, whole_numbers(v))).collect()),
+        other => other,
+    }
+}
+
+/// Runs the call's steps with the request as `body` and reads the response as `output`.
+async fn call(
+    state: AppState,
+    rpc: Rpc,
+    output: MessageDescriptor,
+    request: tonic::Request<DynamicMessage>,
+) -> Result<tonic::Response<DynamicMessage>, Status> {
+    let options = SerializeOptions::new().use_proto_field_name(true).skip_default_fields(false);
+    let body = request
+        .into_inner()
+        .serialize_with_options(serde_json::value::Serializer, &options)
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+    let steps: Vec<Value> = std::iter::once(Value::String("parse-json".to_string()))
+        .chain(rpc.steps.iter().cloned())
+        .collect();
+    let response = execute_steps(state, &steps, Some(whole_numbers(body).to_string()), None).await;
+    let code = response.status.as_u16();
+    log(LogLevel::Debug, &format!("gRPC {}/{} Resp: {}", rpc.service, rpc.method, response.body));
+    if code >= 400 {
+        return Err(Status::new(status_code(code), errors::response_message(code, &response.body)));
+    }
+    if output.full_name() == EMPTY {
+        return Ok(tonic::Response::new(DynamicMessage::new(output)));
+    }
+    let mut value = response.value();
+    if rpc.output_items().is_some() {
+        value = json!({ "items": value });
+    }
+    if value.is_null() {
+        return Ok(tonic::Response::new(DynamicMessage::new(output)));
+    }
+    let options = DeserializeOptions::new().deny_unknown_fields(false);
+    DynamicMessage::deserialize_with_options(output.clone(), value, &options)
+        .map(tonic::Response::new)
+        .map_err(|e| Status::internal(format!("response is not a {}: {}", output.full_name(), e)))
+}
+
+/// Serves each `@Rpc` at `/<package>.<Service>/<Method>`, with gRPC server reflection.
+pub async fn build_grpc_router(state: AppState) -> Router {
+    crate::core::initialize_memory_from_doc(&state.doc, &state.path).await;
+
+    let pool = match descriptor_pool(&state.doc, &state.schemas) {
+        Ok(pool) => pool,
+        Err(e) => {
+            log(LogLevel::Error, &format!("Cannot build the gRPC service: {}", e));
+            return Router::new().fallback(move || {
+                let status = Status::internal(format!("Cannot build the gRPC service: {}", e));
+                async move { status.into_http::<axum::body::Body>() }
+            });
+        }
+    };
+    let package = package(&state.doc);
+
+    let files = FileDescriptorSet {
+        file: pool.file_descriptor_protos().cloned().collect(),
+    };
+    let reflection = || tonic_reflection::server::Builder::configure().register_file_descriptor_set(files.clone());
+    let mut routes = tonic::service::Routes::default();
+    match (reflection().build_v1(), reflection().build_v1alpha()) {
+        (Ok(v1), Ok(v1alpha)) => routes = routes.add_service(v1).add_service(v1alpha),
+        (Err(e), _) | (_, Err(e)) => log(LogLevel::Warn, &format!("gRPC reflection is unavailable: {}", e)),
+    }
+    let mut router = routes.into_axum_router();
+
+    for rpc in rpcs(&state.doc) {
+        let full_name = format!("{}.{}", package, rpc.service);
+        let Some(method) = pool
+            .get_service_by_name(&full_name)
+            .and_then(|service| service.methods().find(|m| m.name() == rpc.method))
+        else {
+            continue;
+        };
+        let path = format!("/{}/{}", full_name, rpc.method);
+        let state = state.clone();
+        router = router.route_service(
+            &path,
+            tower::service_fn(move |req: axum::http::Request<axum::body::Body>| {
+                let state = state.clone();
+                let rpc = rpc.clone();
+                let method = method.clone();
+                async move {
+                    let mut grpc = tonic::server::Grpc::new(DynamicCodec(method.input()));
+                    let handler = tower::service_fn(move |request| {
+                        call(state.clone(), rpc.clone(), method.output(), request)
+                    });
+                    Ok::<_, Infallible>(grpc.unary(handler, req).await)
+                }
+            }),
+        );
+    }
+    router
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn describes_schemas_and_services() {
+        let doc = parse_rune(
+            "#!RUNE\n@App\ntype = Grpc\npackage = library\n\n@Schema/Book\ntitle = string\nid = number\nmeta = json\n\n@Rpc/Library/ListBooks\noutput = [Book]\nrun:\n    respond 200 \"[]\"\n\n@Rpc/Library/Ping\nrun:\n    respond 200 \"ok\"\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        let pool = descriptor_pool(&doc, &schemas).unwrap();
+        let book = pool.get_message_by_name("library.Book").unwrap();
+        assert_eq!(book.fields().map(|f| f.name().to_string()).collect::<Vec<_>>(), ["id", "meta", "title"]);
+        let service = pool.get_service_by_name("library.Library").unwrap();
+        let list = service.methods().find(|m| m.name() == "ListBooks").unwrap();
+        assert_eq!(list.output().full_name(), "library.ListBooksResponse");
+        let ping = service.methods().find(|m| m.name() == "Ping").unwrap();
+        assert_eq!(ping.input().full_name(), EMPTY);
+
+        let bad = parse_rune("#!RUNE\n@Rpc/Library/Get\ninput = Missing\nrun:\n    respond 200 \"x\"\n").unwrap();
+        assert!(file_descriptor(&bad, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn renders_crud_routes_as_services() {
+        let doc = parse_rune(
+            "#!RUNE\n@App\ntype = REST\npackage = shop\n\n@Schema/Product\nid = number\nname = string\n\n@Route/CRUD /products\nschema = Product\ndata_source = Db\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        let file = proto_file(&doc, &schemas).unwrap();
+        assert!(DescriptorPool::global().add_file_descriptor_proto(file.clone()).is_ok());
+        let proto = render_proto(&file);
+        assert!(proto.starts_with("syntax = \"proto3\";\n\npackage shop;\n\nimport \"google/protobuf/empty.proto\";\n"), "{}", proto);
+        assert!(proto.contains("message Product {\n  double id = 1;\n  string name = 2;\n}\n"), "{}", proto);
+        assert!(proto.contains("message ListProductsResponse {\n  repeated Product items = 1;\n}\n"), "{}", proto);
+        assert!(proto.contains("  rpc GetProduct(ProductId) returns (Product);\n"), "{}", proto);
+        assert!(proto.contains("  rpc DeleteProduct(ProductId) returns (google.protobuf.Empty);\n"), "{}", proto);
+    }
+}
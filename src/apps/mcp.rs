@@ -0,0 +1,351 @@
+//! The MCP app type, `type = MCP`: a Model Context Protocol server whose tools are the
+//! document's `@Tool` sections.
+//!
+//! ```rune
+//! @App
+//! type = MCP
+//! transport = stdio
+//!
+//! @Schema/BookQuery
+//! title = string
+//!
+//! @Tool/find_books
+//! description = "Find the books with a title"
+//! input = BookQuery
+//! run:
+//!     books = memory.get "books"
+//!     matching = books.filter it.title == body.title
+//!     respond 200 matching
+//! ```
+//!
+//! A tool's arguments are its steps' `body`, already parsed, described to clients by the
+//! `input` schema; its response is the tool's text result, and error responses are
+//! reported as failed calls (`isError`).
+//!
+//! With `transport = stdio` the server reads JSON-RPC messages from stdin, one per line,
+//! and writes its replies to stdout, sending logs to stderr. Otherwise (`transport = sse`,
+//! the default) it listens over HTTP: `GET /sse` opens an event stream whose first
+//! `endpoint` event names the URL to `POST` messages to, and replies arrive as `message`
+//! events. `POST /mcp` answers a message directly in the response too.
+
+use crate::apps::rest::swagger::schema::build_openapi_components;
+use crate::core::{errors, execute_steps, AppState};
+use crate::rune_ast::{Section, Value};
+use crate::util::{log, LogLevel};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{Stream, StreamExt};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+/// Protocol revisions the server speaks, oldest first.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", "2025-06-18"];
+
+const COMPONENT_REF: &str = "#/components/schemas/";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The open `GET /sse` streams, by session id.
+static SESSIONS: Lazy<Mutex<HashMap<String, mpsc::UnboundedSender<JsonValue>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tool {
+    pub name: String,
+    pub description: Option<String>,
+    pub input: Option<String>,
+    pub steps: Vec<Value>,
+}
+
+impl Tool {
+    pub fn from_section(section: &Section) -> Result<Self, String> {
+        let name = section.path.get(1).cloned().unwrap_or_default();
+        if name.is_empty() {
+            return Err("@Tool sections must be named @Tool/<name>".to_string());
+        }
+        let steps = section
+            .series
+            .get("run")
+            .cloned()
+            .ok_or_else(|| format!("@Tool/{} requires run: steps", name))?;
+        let text = |key: &str| section.kv.get(key).and_then(Value::as_str).map(str::to_string);
+        Ok(Tool {
+            description: text("description"),
+            input: text("input"),
+            steps,
+            name,
+        })
+    }
+}
+
+/// The document's `@Tool` sections; invalid ones, or ones naming an unknown `input`
+/// schema, are skipped with a warning.
+pub fn tools(state: &AppState) -> Vec<Tool> {
+    state
+        .doc
+        .sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Tool"))
+        .filter_map(|section| match Tool::from_section(section) {
+            Ok(tool) if tool.input.as_ref().is_some_and(|s| !state.schemas.contains_key(s)) => {
+                log(
+                    LogLevel::Warn,
+                    &format!("@Tool/{}: no @Schema/{}", tool.name, tool.input.unwrap_or_default()),
+                );
+                None
+            }
+            Ok(tool) => Some(tool),
+            Err(e) => {
+                log(LogLevel::Warn, &e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The JSON Schema of a tool's arguments: its `input` schema, with the schemas and enums
+/// it refers to under `$defs`, or an empty object.
+fn input_schema(state: &AppState, tool: &Tool) -> JsonValue {
+    let Some(name) = &tool.input else {
+        return json!({ "type": "object", "properties": {} });
+    };
+    let components = build_openapi_components(&state.doc);
+    let mut schema = components.get(name).cloned().unwrap_or_else(|| json!({ "type": "object" }));
+    if !schema.to_string().contains(COMPONENT_REF) {
+        return schema;
+    }
+    schema["$defs"] = JsonValue::Object(components);
+    let inlined = schema.to_string().replace(COMPONENT_REF, "#/$defs/");
+    serde_json::from_str(&inlined).unwrap_or(schema)
+}
+
+fn server_info(state: &AppState) -> JsonValue {
+    let name = state
+        .doc
+        .get_section("App")
+        .and_then(|s| s.kv.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or("vectrune");
+    let version = state
+        .doc
+        .get_section("App")
+        .and_then(|s| s.kv.get("version"))
+        .and_then(Value::as_str)
+        .unwrap_or(env!("CARGO_PKG_VERSION"));
+    json!({ "name": name, "version": version })
+}
+
+/// Runs a tool's steps with its arguments as `body`.
+async fn call_tool(state: &AppState, params: &JsonValue) -> Result<JsonValue, (i64, String)> {
+    let name = params["name"].as_str().ok_or((INVALID_PARAMS, "tools/call requires a name".to_string()))?;
+    let tool = tools(state)
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| (INVALID_PARAMS, format!("unknown tool: {}", name)))?;
+    let arguments = match &params["arguments"] {
+        JsonValue::Null => json!({}),
+        args => args.clone(),
+    };
+    let steps: Vec<Value> = std::iter::once(Value::String("parse-json".to_string()))
+        .chain(tool.steps)
+        .collect();
+    let response = execute_steps(state.clone(), &steps, Some(arguments.to_string()), None).await;
+    log(LogLevel::Debug, &format!("MCP tool {} Resp: {}", name, response.body));
+    let code = response.status.as_u16();
+    let failed = code >= 400;
+    let text = match response.value() {
+        _ if failed => errors::response_message(code, &response.body),
+        JsonValue::String(text) => text,
+        _ => response.body,
+    };
+    Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": failed }))
+}
+
+/// Answers one JSON-RPC message; notifications and responses get no reply.
+pub async fn handle_message(state: &AppState, message: JsonValue) -> Option<JsonValue> {
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(JsonValue::as_str) else {
+        if message.get("result").is_some() || message.get("error").is_some() {
+            return None;
+        }
+        return Some(error_reply(id.unwrap_or(JsonValue::Null), INVALID_REQUEST, "expected a JSON-RPC request"));
+    };
+    let Some(id) = id else {
+        log(LogLevel::Debug, &format!("MCP notification: {}", method));
+        return None;
+    };
+    let params = message.get("params").cloned().unwrap_or(JsonValue::Null);
+    let result = match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str().unwrap_or_default();
+            let version = PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| **v == requested)
+                .unwrap_or(&PROTOCOL_VERSIONS[PROTOCOL_VERSIONS.len() - 1]);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": server_info(state),
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => {
+            let tools: Vec<JsonValue> = tools(state)
+                .iter()
+                .map(|tool| {
+                    let mut entry = json!({ "name": tool.name, "inputSchema": input_schema(state, tool) });
+                    if let Some(description) = &tool.description {
+                        entry["description"] = json!(description);
+                    }
+                    entry
+                })
+                .collect();
+            Ok(json!({ "tools": tools }))
+        }
+        "tools/call" => call_tool(state, &params).await,
+        other => Err((METHOD_NOT_FOUND, format!("method not found: {}", other))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_reply(id, code, &message),
+    })
+}
+
+fn error_reply(id: JsonValue, code: i64, message: &str) -> JsonValue {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Answers a line of input: a message, or a batch of them.
+async fn handle_text(state: &AppState, text: &str) -> Option<JsonValue> {
+    match serde_json::from_str::<JsonValue>(text) {
+        Ok(JsonValue::Array(batch)) => {
+            let mut replies = Vec::new();
+            for message in batch {
+                replies.extend(handle_message(state, message).await);
+            }
+            (!replies.is_empty()).then_some(JsonValue::Array(replies))
+        }
+        Ok(message) => handle_message(state, message).await,
+        Err(e) => Some(error_reply(JsonValue::Null, PARSE_ERROR, &format!("parse error: {}", e))),
+    }
+}
+
+/// Serves MCP over stdin and stdout until stdin closes.
+pub async fn serve_stdio(state: AppState) -> std::io::Result<()> {
+    crate::core::initialize_memory_from_doc(&state.doc, &state.path).await;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(reply) = handle_text(&state, &line).await {
+            stdout.write_all(format!("{}\n", reply).as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the document asks for the stdio transport.
+pub fn uses_stdio(doc: &crate::rune_ast::RuneDocument) -> bool {
+    doc.get_section("App")
+        .and_then(|s| s.kv.get("transport"))
+        .and_then(Value::as_str)
+        .is_some_and(|t| t.eq_ignore_ascii_case("stdio"))
+}
+
+async fn open_stream() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let session = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+    SESSIONS.lock().unwrap().insert(session.clone(), tx);
+    log(LogLevel::Debug, &format!("MCP session {} opened", session));
+    let endpoint = Event::default().event("endpoint").data(format!("/messages?session_id={}", session));
+    let replies = futures::stream::unfold(rx, |mut rx| async move {
+        let reply = rx.recv().await?;
+        Some((Event::default().event("message").data(reply.to_string()), rx))
+    });
+    let events = futures::stream::once(async move { endpoint }).chain(replies).map(Ok);
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn post_message(
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+    body: String,
+) -> Response {
+    let sender = query
+        .get("session_id")
+        .and_then(|id| SESSIONS.lock().unwrap().get(id).cloned());
+    let Some(sender) = sender else {
+        return (StatusCode::NOT_FOUND, "unknown session").into_response();
+    };
+    if let Some(reply) = handle_text(&state, &body).await {
+        if sender.send(reply).is_err() {
+            if let Some(id) = query.get("session_id") {
+                SESSIONS.lock().unwrap().remove(id);
+            }
+            return (StatusCode::GONE, "session closed").into_response();
+        }
+    }
+    StatusCode::ACCEPTED.into_response()
+}
+
+async fn post_mcp(State(state): State<AppState>, body: String) -> Response {
+    match handle_text(&state, &body).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// Serves MCP over HTTP: `GET /sse` and `POST /messages`, plus `POST /mcp`.
+pub async fn build_mcp_router(state: AppState) -> Router {
+    crate::core::initialize_memory_from_doc(&state.doc, &state.path).await;
+    Router::new()
+        .route("/sse", get(open_stream))
+        .route("/messages", post(post_message))
+        .route("/mcp", post(post_mcp))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn answers_lifecycle_messages() {
+        let doc = parse_rune("#!RUNE\n@App\ntype = MCP\nname = books\n\n@Tool/broken\ndescription = \"no steps\"\n").unwrap();
+        let state = AppState {
+            doc: Arc::new(doc.clone()),
+            schemas: Arc::new(crate::core::extract_schemas(&doc)),
+            data_sources: Arc::new(crate::core::extract_data_sources(&doc)),
+            steps: Arc::new(crate::core::extract_steps(&doc)),
+            path: PathBuf::from("."),
+        };
+        let init = handle_message(&state, json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2024-11-05" } }))
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(init["result"]["serverInfo"]["name"], "books");
+        assert!(handle_message(&state, json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.is_none());
+        let list = handle_message(&state, json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" })).await.unwrap();
+        assert_eq!(list["result"]["tools"], json!([]));
+        let unknown = handle_message(&state, json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" })).await.unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -1,8 +1,16 @@
+pub mod admin;
+pub mod chaos;
 pub mod graphql;
+pub mod grpc;
+pub mod mcp;
 pub mod rest;
+pub mod routes;
 pub mod rune_web;
+pub mod trace;
 
 use self::graphql::build_graphql_router;
+use self::grpc::build_grpc_router;
+use self::mcp::build_mcp_router;
 use self::rest::build_rest_router;
 use crate::core::{get_app_type, AppState};
 use crate::rune_ast::RuneDocument;
@@ -16,8 +24,10 @@ use tower_http::services::ServeDir;
 pub async fn build_app_router(state: AppState) -> Router {
     let app_type = get_app_type(&state.doc).unwrap_or_else(|| "REST".to_string());
 
-    match app_type.to_uppercase().as_str() {
+    let router = match app_type.to_uppercase().as_str() {
         "GRAPHQL" => build_graphql_router(state).await,
+        "GRPC" => build_grpc_router(state).await,
+        "MCP" => build_mcp_router(state).await,
         "REST" => build_rest_router(state).await,
         "STATIC" => build_static_router(state).await,
         other => {
@@ -31,7 +41,7 @@ pub async fn build_app_router(state: AppState) -> Router {
                         (
                             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                             format!(
-                                "Unsupported App type: {}. Only REST, GRAPHQL, and STATIC are supported.",
+                                "Unsupported App type: {}. Only REST, GRAPHQL, GRPC, MCP, and STATIC are supported.",
                                 other
                             ),
                         )
@@ -39,7 +49,8 @@ pub async fn build_app_router(state: AppState) -> Router {
                 }),
             )
         }
-    }
+    };
+    router.layer(axum::middleware::from_fn(trace::propagate))
 }
 
 pub async fn build_static_router(state: AppState) -> Router {
@@ -60,18 +71,20 @@ pub async fn build_vectrune_router(
     doc: Arc<RuneDocument>,
     schemas: Arc<std::collections::HashMap<String, crate::rune_ast::Section>>,
     data_sources: Arc<std::collections::HashMap<String, crate::rune_ast::Section>>,
+    steps: Arc<crate::core::step::DocumentSteps>,
     path: PathBuf
 ) -> Router {
     let state = AppState {
         doc,
         schemas,
         data_sources,
+        steps,
         path,
     };
-    build_app_router(state).await
+    admin::build_admin_router(state).await
 }
 
 /// Returns true if the app type is supported for server launch
 pub fn app_type_supported(app_type: &str) -> bool {
-    matches!(app_type.to_uppercase().as_str(), "REST" | "GRAPHQL" | "STATIC")
+    matches!(app_type.to_uppercase().as_str(), "REST" | "GRAPHQL" | "GRPC" | "MCP" | "STATIC")
 }
@@ -0,0 +1,64 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+/// Strong ETag for a response body: the quoted hex SHA-256 digest.
+pub fn etag_for(body: &[u8]) -> String {
+    format!("\"{:x}\"", Sha256::digest(body))
+}
+
+/// True if an `If-None-Match` header value matches `etag` (`*` matches anything).
+fn if_none_match_matches(header_value: &str, etag: &str) -> bool {
+    header_value
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t == etag || t.strip_prefix("W/") == Some(etag))
+}
+
+/// Adds an ETag to successful GET responses, answers matching `If-None-Match`
+/// requests with `304 Not Modified`, and sets `Cache-Control` when `cache_ttl` is given.
+/// Responses to `private` routes, those behind `auth`, are kept out of shared caches.
+pub async fn conditional_get(req: Request<Body>, next: Next, cache_ttl: Option<u64>, private: bool) -> Response {
+    let if_none_match = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let etag = etag_for(&bytes);
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+    if let Some(ttl) = cache_ttl {
+        let scope = if private { "private" } else { "public" };
+        if let Ok(value) = HeaderValue::from_str(&format!("{}, max-age={}", scope, ttl)) {
+            parts.headers.insert(header::CACHE_CONTROL, value);
+        }
+    }
+
+    if if_none_match
+        .as_deref()
+        .map(|v| if_none_match_matches(v, &etag))
+        .unwrap_or(false)
+    {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.remove(header::CONTENT_TYPE);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
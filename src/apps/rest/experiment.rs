@@ -0,0 +1,238 @@
+//! `@Experiment/<name>` sections split a route's traffic between alternative step lists:
+//!
+//! ```rune
+//! @Experiment/pricing
+//! route = GET /price
+//! sticky = header x-session-id
+//! split {
+//!     control = 80
+//!     discount = 20
+//! }
+//! discount:
+//!     respond 200 "7.99"
+//! ```
+//!
+//! Weights are relative. A variant with its own series runs those steps; any other
+//! variant (here `control`) runs the route's `run:`. With `sticky = header <name>`,
+//! `cookie <name>` or `claim <name>` the same caller always lands in the same variant;
+//! otherwise each request is assigned at random. The chosen variant is returned in an
+//! `X-Experiment` header and counted for `GET /__admin/experiments`.
+
+use crate::rune_ast::{RuneDocument, Section, Value};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Requests served per experiment and variant since the process started.
+static ASSIGNMENTS: Lazy<Mutex<BTreeMap<String, BTreeMap<String, u64>>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// Where the sticky assignment key is read from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sticky {
+    Header(String),
+    Cookie(String),
+    Claim(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub weight: u64,
+    /// The variant's own steps; `None` runs the route's `run:`.
+    pub steps: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub name: String,
+    pub method: String,
+    /// Route path in axum form, e.g. `/users/{id}`.
+    pub path: String,
+    pub sticky: Option<Sticky>,
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    pub fn from_section(section: &Section) -> Option<Self> {
+        let name = section.path.get(1).filter(|n| !n.is_empty())?.clone();
+        let route = section.kv.get("route").and_then(|v| v.as_str())?;
+        let (method, path) = route.trim().split_once(char::is_whitespace)?;
+        let path = path.trim().trim_start_matches('/');
+        let sticky = section
+            .kv
+            .get("sticky")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.trim().split_once(char::is_whitespace))
+            .and_then(|(source, key)| {
+                let key = key.trim().to_string();
+                match source {
+                    "header" => Some(Sticky::Header(key.to_ascii_lowercase())),
+                    "cookie" => Some(Sticky::Cookie(key)),
+                    "claim" => Some(Sticky::Claim(key)),
+                    _ => None,
+                }
+            });
+        let mut variants: Vec<Variant> = match section.kv.get("split") {
+            Some(Value::Map(split)) => split
+                .iter()
+                .filter_map(|(variant, weight)| {
+                    // Values inside `{ ... }` blocks arrive as text
+                    let weight = match weight {
+                        Value::String(s) => s.trim().parse().ok(),
+                        other => other.as_u64(),
+                    }
+                    .filter(|w| *w > 0)?;
+                    Some(Variant {
+                        name: variant.clone(),
+                        weight,
+                        steps: section.series.get(variant).cloned(),
+                    })
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        variants.sort_by(|a, b| a.name.cmp(&b.name));
+        if variants.iter().map(|v| v.weight).sum::<u64>() == 0 {
+            return None;
+        }
+        Some(Experiment {
+            name,
+            method: method.to_uppercase(),
+            path: format!("/{}", path),
+            sticky,
+            variants,
+        })
+    }
+
+    /// Picks the variant for a request. Sticky keys hash to a fixed bucket; requests
+    /// without one get a random bucket.
+    pub fn choose(&self, headers: &HashMap<String, String>) -> &Variant {
+        let key = self.sticky.as_ref().and_then(|sticky| sticky_key(sticky, headers));
+        let seed = match key {
+            Some(key) => Sha256::digest(format!("{}:{}", self.name, key).as_bytes()).to_vec(),
+            None => uuid::Uuid::new_v4().as_bytes().to_vec(),
+        };
+        let total: u64 = self.variants.iter().map(|v| v.weight).sum();
+        let mut bucket = u64::from_be_bytes(seed[..8].try_into().unwrap()) % total;
+        for variant in &self.variants {
+            if bucket < variant.weight {
+                return variant;
+            }
+            bucket -= variant.weight;
+        }
+        &self.variants[self.variants.len() - 1]
+    }
+}
+
+fn sticky_key(sticky: &Sticky, headers: &HashMap<String, String>) -> Option<String> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+    match sticky {
+        Sticky::Header(name) => header(name).map(str::to_string),
+        Sticky::Cookie(name) => header("cookie")?.split(';').find_map(|pair| {
+            let (k, v) = pair.trim().split_once('=')?;
+            (k == name).then(|| v.to_string())
+        }),
+        // The route's auth layer has already verified the token; only the payload is read.
+        Sticky::Claim(name) => {
+            let token = header("authorization")?.strip_prefix("Bearer ")?;
+            let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+            let claims: JsonValue = serde_json::from_slice(&payload).ok()?;
+            match claims.get(name)? {
+                JsonValue::String(s) => Some(s.clone()),
+                other => Some(other.to_string()),
+            }
+        }
+    }
+}
+
+/// All `@Experiment` sections in the document.
+pub fn extract_experiments(doc: &RuneDocument) -> Vec<Experiment> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Experiment"))
+        .filter_map(|section| {
+            let experiment = Experiment::from_section(section);
+            if experiment.is_none() {
+                crate::util::log(
+                    crate::util::LogLevel::Warn,
+                    &format!(
+                        "@{} needs a name, `route = METHOD /path` and a `split` with positive weights; skipping",
+                        section.path.join("/")
+                    ),
+                );
+            }
+            experiment
+        })
+        .collect()
+}
+
+/// Counts a request served by `variant`.
+pub fn record(experiment: &str, variant: &str) {
+    crate::util::log(
+        crate::util::LogLevel::Debug,
+        &format!("Experiment {}: served variant {}", experiment, variant),
+    );
+    *ASSIGNMENTS
+        .lock()
+        .unwrap()
+        .entry(experiment.to_string())
+        .or_default()
+        .entry(variant.to_string())
+        .or_default() += 1;
+}
+
+/// Per-experiment variant counts, e.g. `{"pricing": {"control": 41, "discount": 9}}`.
+pub fn assignments() -> JsonValue {
+    json!(*ASSIGNMENTS.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment(sticky: Option<Sticky>) -> Experiment {
+        Experiment {
+            name: "pricing".to_string(),
+            method: "GET".to_string(),
+            path: "/price".to_string(),
+            sticky,
+            variants: ["a", "b"]
+                .iter()
+                .map(|name| Variant { name: name.to_string(), weight: 50, steps: None })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sticky_callers_keep_their_variant() {
+        let exp = experiment(Some(Sticky::Cookie("session".to_string())));
+        let headers = HashMap::from([("Cookie".to_string(), "theme=dark; session=abc".to_string())]);
+        let first = exp.choose(&headers).name.clone();
+        for _ in 0..20 {
+            assert_eq!(exp.choose(&headers).name, first);
+        }
+    }
+
+    #[test]
+    fn reads_claims_from_the_bearer_token() {
+        let payload = URL_SAFE_NO_PAD.encode(br#"{"sub":"user-7"}"#);
+        let headers = HashMap::from([(
+            "authorization".to_string(),
+            format!("Bearer x.{}.y", payload),
+        )]);
+        assert_eq!(
+            sticky_key(&Sticky::Claim("sub".to_string()), &headers),
+            Some("user-7".to_string())
+        );
+    }
+}
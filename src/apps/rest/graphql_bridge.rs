@@ -0,0 +1,192 @@
+//! REST routes answered by a GraphQL query:
+//!
+//! ```rune
+//! @Route/GET /books/{id}
+//! graphql = "query($id: Float!) { book(id: $id) { id title } }"
+//! ```
+//!
+//! The query runs against the document's own `@GraphQL` schema, or against
+//! `graphql_endpoint` when the route sets one. Path params, query string parameters and
+//! (for POST/PUT) a JSON object body become the query's variables, converted to the types
+//! the query declares for them. A successful response's `data` is returned as JSON.
+
+use crate::rune_ast::Section;
+use crate::util::{log, LogLevel};
+use async_graphql::dynamic::Schema;
+use axum::{
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put, MethodRouter},
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+static VARIABLE_DECL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\$(\w+)\s*:\s*\[?\s*(\w+)").expect("valid variable regex"));
+
+/// Where a bridge route sends its query.
+#[derive(Clone)]
+pub enum GraphqlTarget {
+    /// The document's own schema.
+    Local(Schema),
+    /// A remote GraphQL endpoint URL.
+    Remote(String),
+}
+
+/// A route's `graphql` query and where it runs.
+#[derive(Clone)]
+pub struct GraphqlBridge {
+    pub query: String,
+    pub target: GraphqlTarget,
+}
+
+impl GraphqlBridge {
+    /// The section's `graphql` query with its target, if the section declares one.
+    /// `local` builds the document's schema and is only called for local targets.
+    pub fn from_section(section: &Section, local: impl FnOnce() -> Schema) -> Option<Self> {
+        let query = section.kv.get("graphql").and_then(|v| v.as_str())?.to_string();
+        let target = match section.kv.get("graphql_endpoint").and_then(|v| v.as_str()) {
+            Some(url) => GraphqlTarget::Remote(url.to_string()),
+            None => GraphqlTarget::Local(local()),
+        };
+        Some(Self { query, target })
+    }
+}
+
+/// Converts a request value to the type the query declares for its variable:
+/// `Int`/`Float` to numbers and `Boolean` to a bool; anything else stays as given.
+fn coerce(value: JsonValue, declared: Option<&str>) -> JsonValue {
+    let JsonValue::String(s) = &value else {
+        return value;
+    };
+    let converted = match declared {
+        Some("Int") => s.parse::<i64>().ok().map(JsonValue::from),
+        Some("Float") => s.parse::<f64>().ok().map(JsonValue::from),
+        Some("Boolean") => s.parse::<bool>().ok().map(JsonValue::Bool),
+        _ => None,
+    };
+    converted.unwrap_or(value)
+}
+
+/// Builds the query's variables from the request: path params win over query string
+/// parameters, which win over body fields.
+pub fn variables(
+    query: &str,
+    path_params: &HashMap<String, String>,
+    query_params: &HashMap<String, String>,
+    body: Option<&str>,
+) -> Map<String, JsonValue> {
+    let declared: HashMap<&str, &str> = VARIABLE_DECL
+        .captures_iter(query)
+        .filter_map(|c| Some((c.get(1)?.as_str(), c.get(2)?.as_str())))
+        .collect();
+    let mut vars = match body.and_then(|b| serde_json::from_str::<JsonValue>(b).ok()) {
+        Some(JsonValue::Object(map)) => map,
+        _ => Map::new(),
+    };
+    for (name, value) in query_params.iter().chain(path_params) {
+        vars.insert(name.clone(), JsonValue::String(value.clone()));
+    }
+    vars.into_iter()
+        .map(|(name, value)| {
+            let value = coerce(value, declared.get(name.as_str()).copied());
+            (name, value)
+        })
+        .collect()
+}
+
+fn json_response(status: StatusCode, body: &JsonValue) -> Response {
+    (status, [(header::CONTENT_TYPE, "application/json")], body.to_string()).into_response()
+}
+
+/// Runs the bridge query. Query errors respond 400 locally and 502 from a remote endpoint,
+/// with the GraphQL `errors` list as the body.
+pub async fn bridge_handler(bridge: &GraphqlBridge, variables: Map<String, JsonValue>) -> Response {
+    let (result, error_status) = match &bridge.target {
+        GraphqlTarget::Local(schema) => {
+            let request = async_graphql::Request::new(bridge.query.clone())
+                .variables(async_graphql::Variables::from_json(JsonValue::Object(variables)));
+            let response = schema.execute(request).await;
+            (serde_json::to_value(&response).unwrap_or(JsonValue::Null), StatusCode::BAD_REQUEST)
+        }
+        GraphqlTarget::Remote(url) => {
+            let mut request = CLIENT.post(url);
+            if let Some(traceparent) = crate::apps::trace::outgoing_traceparent() {
+                request = request.header(crate::apps::trace::TRACEPARENT, traceparent);
+            }
+            let sent = request
+                .json(&json!({ "query": bridge.query, "variables": variables }))
+                .send()
+                .await;
+            let received = match sent {
+                Ok(resp) => resp.json::<JsonValue>().await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match received {
+                Ok(value) => (value, StatusCode::BAD_GATEWAY),
+                Err(e) => {
+                    log(LogLevel::Error, &format!("GraphQL bridge: {} failed: {}", url, e));
+                    let body = json!({ "errors": [{ "message": format!("upstream GraphQL request failed: {}", e) }] });
+                    return json_response(StatusCode::BAD_GATEWAY, &body);
+                }
+            }
+        }
+    };
+    match result.get("errors").and_then(JsonValue::as_array) {
+        Some(errors) if !errors.is_empty() => json_response(error_status, &json!({ "errors": errors })),
+        _ => json_response(StatusCode::OK, result.get("data").unwrap_or(&JsonValue::Null)),
+    }
+}
+
+async fn run(
+    bridge: Arc<GraphqlBridge>,
+    params: HashMap<String, String>,
+    query: HashMap<String, String>,
+    body: Option<String>,
+) -> Response {
+    let vars = variables(&bridge.query, &params, &query, body.as_deref());
+    bridge_handler(&bridge, vars).await
+}
+
+/// The route for a bridge on `method`; `None` for methods routes don't support.
+pub fn method_router(bridge: GraphqlBridge, method: &str) -> Option<MethodRouter> {
+    let bridge = Arc::new(bridge);
+    let route = match method {
+        "GET" => get(move |Path(params), Query(query)| run(bridge.clone(), params, query, None)),
+        "DELETE" => delete(move |Path(params), Query(query)| run(bridge.clone(), params, query, None)),
+        "POST" => post(move |Path(params), Query(query), body: String| {
+            run(bridge.clone(), params, query, Some(body))
+        }),
+        "PUT" => put(move |Path(params), Query(query), body: String| {
+            run(bridge.clone(), params, query, Some(body))
+        }),
+        _ => return None,
+    };
+    Some(route)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_follow_declared_types() {
+        let query = "query($id: Int!, $price: Float, $tags: [String], $live: Boolean) { x }";
+        let path = HashMap::from([("id".to_string(), "7".to_string())]);
+        let params = HashMap::from([
+            ("live".to_string(), "true".to_string()),
+            ("tags".to_string(), "a".to_string()),
+        ]);
+        let vars = variables(query, &path, &params, Some(r#"{"price":"2.5","id":1}"#));
+        assert_eq!(vars["id"], json!(7));
+        assert_eq!(vars["price"], json!(2.5));
+        assert_eq!(vars["live"], json!(true));
+        assert_eq!(vars["tags"], json!("a"));
+    }
+}
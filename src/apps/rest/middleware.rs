@@ -0,0 +1,77 @@
+//! `@Middleware/<name>` sections hold steps that run before the steps of every route
+//! listing them:
+//!
+//! ```rune
+//! @Middleware/api_key
+//! run:
+//!     if request.headers.authorization != "Key secret":
+//!         respond 401 "missing API key"
+//!     caller = "partner"
+//!
+//! @Route/GET /reports
+//! middleware = (request_log api_key)
+//! run:
+//!     respond 200 caller
+//! ```
+//!
+//! Middleware run in the order listed and share the route's context, so values they set
+//! are visible to the later steps, and a response ends the request before the route's own
+//! steps run. They apply to `@Route/CRUD` operations too.
+
+use crate::rune_ast::{RuneDocument, Section, Value};
+
+/// The names in a route's `middleware` attribute, a list or a comma or space separated string.
+pub fn names(route: &Section) -> Vec<String> {
+    match route.kv.get("middleware") {
+        Some(Value::List(items)) => items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+        Some(Value::String(s)) => s.split([',', ' ']).filter(|n| !n.is_empty()).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The steps of the route's middleware, in order. Naming a middleware that has no
+/// `@Middleware` section with `run:` steps is an error.
+pub fn steps_for(doc: &RuneDocument, route: &Section) -> Result<Vec<Value>, String> {
+    let mut steps = Vec::new();
+    for name in names(route) {
+        let section = doc.sections.iter().find(|s| {
+            s.path.first().map(String::as_str) == Some("Middleware") && s.path.get(1) == Some(&name)
+        });
+        match section.and_then(|s| s.series.get("run")) {
+            Some(run) => steps.extend(run.iter().cloned()),
+            None => return Err(format!("middleware '{}' is not declared with @Middleware/{} run:", name, name)),
+        }
+    }
+    Ok(steps)
+}
+
+/// The steps to run before the route's own. When a middleware is missing this is a
+/// `respond 500`, so the route is never served without the checks it asked for.
+pub fn resolve(doc: &RuneDocument, route: &Section) -> Vec<Value> {
+    steps_for(doc, route).unwrap_or_else(|e| {
+        crate::util::log(crate::util::LogLevel::Error, &format!("@{}: {}", route.path.join("/"), e));
+        vec![Value::String(format!("respond 500 \"{}\"", e.replace('"', "'")))]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn middleware_steps_come_first_in_the_listed_order() {
+        let doc = parse_rune(
+            "#!RUNE\n@Middleware/a\nrun:\n    log \"a\"\n\n@Middleware/b\nrun:\n    log \"b\"\n\n@Route/GET /x\nmiddleware = (b a)\nrun:\n    respond 200 \"x\"\n\n@Route/GET /y\nmiddleware = b, missing\n",
+        )
+        .unwrap();
+        let route = |n: usize| doc.sections.iter().filter(|s| s.path[0] == "Route").nth(n).unwrap();
+        let steps = resolve(&doc, route(0));
+        let steps: Vec<&str> = steps.iter().filter_map(Value::as_str).collect();
+        assert_eq!(steps, ["log \"b\"", "log \"a\""]);
+
+        assert_eq!(names(route(1)), ["b", "missing"]);
+        assert!(steps_for(&doc, route(1)).unwrap_err().contains("'missing'"));
+        assert!(resolve(&doc, route(1))[0].as_str().unwrap().starts_with("respond 500"));
+    }
+}
@@ -1,15 +1,25 @@
+pub mod cache;
+pub mod experiment;
+pub mod graphql_bridge;
+pub mod middleware;
+pub mod proxy;
 pub mod ws;
 pub mod swagger;
+pub mod ts_client;
 
 use crate::apps::rune_web::build_rune_web_router;
-use crate::core::{execute_steps, extract_auth_configs, jwt_auth, AppState};
+use crate::core::{
+    execute_request_steps, extract_auth_configs, jwt_auth, request_body, step, AppState, StepResponse,
+};
+use crate::core::step::Step;
+use crate::builtins::builtin::respond::content_type_for;
 use crate::crud_web_fe::create_web_fe_handler;
 use crate::rune_ast::Value;
 use axum::{
     http::Request,
     http::StatusCode,
     response::IntoResponse,
-    routing::{delete, get, post, put},
+    routing::{any, delete, get, post, put},
     Router,
 };
 use base64::engine::general_purpose::STANDARD as BASE64;
@@ -28,7 +38,10 @@ pub async fn build_rest_router(state: AppState) -> Router {
 
     let doc = state.doc.clone();
     let auth_configs = Arc::new(extract_auth_configs(&doc));
+    let experiments = experiment::extract_experiments(&doc);
     let mut router = Router::with_state(Router::new(), state.clone());
+    // Built on first use by a `graphql` route and shared by the rest
+    let mut local_schema: Option<async_graphql::dynamic::Schema> = None;
 
     // If @App section has a "run" kv, execute its steps once
     let mut swagger_enabled = false;
@@ -38,8 +51,8 @@ pub async fn build_rest_router(state: AppState) -> Router {
         .iter()
         .find(|s| s.path.first().map(|p| p.as_str()) == Some("App"))
     {
-        if let Some(run_steps) = app_section.series.get("run") {
-            let _ = execute_steps(state.clone(), run_steps.clone(), None, None).await;
+        if let Some(run_steps) = state.steps.get(app_section, "run") {
+            let _ = execute_request_steps(state.clone(), &run_steps, None, None, None, None, None).await;
         }
         if let Some(Value::Bool(true)) = app_section.kv.get("swagger") {
             swagger_enabled = true;
@@ -178,78 +191,202 @@ pub async fn build_rest_router(state: AppState) -> Router {
                 .collect::<Vec<_>>()
                 .join("/");
             let axum_path = format!("/{}", path_template);
-            let default_step = vec![Value::String("respond 200 OK".to_string())];
+            let chaos = crate::apps::chaos::chaos_for_route(section);
 
             let state_clone = state.clone();
-            let run_steps = section.series.get("run").cloned().unwrap_or(default_step);
+            let middleware = step::compile(&middleware::resolve(&state.doc, section));
+            let on_error = state.steps.get(section, "on_error");
 
-            if method == "CRUD" {
-                for m in &["GET", "POST", "PUT", "DELETE"] {
-                    for &with_id in &[false, true] {
-                        let path = if with_id {
-                            format!("{}/{}", axum_path, "{id}")
-                        } else {
-                            axum_path.clone()
-                        };
-                        let run_steps =
-                            crate::builtins::builtin::data_source::get_data_source_commands(
-                                m,
-                                section.clone(),
-                                &state.schemas,
-                                &state.data_sources,
-                                with_id,
-                            );
-                        let handler =
-                            create_handler(state_clone.clone(), run_steps.clone());
-                        let route_fn = match *m {
-                            "GET" => get(move |params| handler(params, None)),
-                            "POST" => post(move |params, body| handler(params, Some(body))),
-                            "PUT" => put(move |params, body| handler(params, Some(body))),
-                            "DELETE" => delete(move |params| handler(params, None)),
-                            _ => unreachable!(),
-                        };
-                        let new_router = Router::new();
-                        let mut route = new_router.route(&path, route_fn);
-                        if let Some(auth_name) = section.kv.get("auth").and_then(|v| v.as_str()) {
-                            if let Some(auth_section) = auth_configs.get(auth_name) {
-                                if let Some(Value::String(secret)) = auth_section.kv.get("secret") {
-                                    let secret = secret.clone();
-                                    route =
-                                        route.layer(axum::middleware::from_fn(move |req, next| {
-                                            jwt_auth(req, next, secret.clone())
-                                        }));
-                                }
-                            }
+            if method == "PROXY" {
+                // `@Route/PROXY /api/*` forwards everything under /api to the upstream
+                let (prefix, wildcard) = match path_template.strip_suffix('*') {
+                    Some(p) => (format!("/{}", p.trim_end_matches('/')), true),
+                    None => (axum_path.clone(), false),
+                };
+                let Some(config) = proxy::ProxyConfig::from_section(section, &prefix) else {
+                    crate::util::log(
+                        crate::util::LogLevel::Warn,
+                        &format!("Proxy route {} has no upstream; skipping", axum_path),
+                    );
+                    continue;
+                };
+                let config = Arc::new(config);
+                let route_fn = any(move |req: Request<axum::body::Body>| {
+                    proxy::proxy_handler(config.clone(), req)
+                });
+                let mut route = Router::new().route(&prefix, route_fn.clone());
+                if wildcard {
+                    let catch_all = if prefix == "/" {
+                        "/{*rest}".to_string()
+                    } else {
+                        format!("{}/{{*rest}}", prefix)
+                    };
+                    route = route.route(&catch_all, route_fn);
+                }
+                if let Some(chaos) = chaos.clone() {
+                    route = route.layer(axum::middleware::from_fn(move |req, next| {
+                        crate::apps::chaos::inject(req, next, chaos.clone())
+                    }));
+                }
+                if let Some(auth_name) = section.kv.get("auth").and_then(|v| v.as_str()) {
+                    if let Some(auth_section) = auth_configs.get(auth_name) {
+                        if let Some(Value::String(secret)) = auth_section.kv.get("secret") {
+                            let secret = secret.clone();
+                            route = route.layer(axum::middleware::from_fn(move |req, next| {
+                                jwt_auth(req, next, secret.clone())
+                            }));
                         }
-                        router = router.merge(route);
                     }
                 }
+                router = router.merge(route);
                 continue;
             }
 
-            let handler = create_handler(state_clone.clone(), run_steps.clone());
-            let route_fn = match method.as_str() {
-                "GET" | "DELETE" => {
-                    let handler = handler.clone();
-                    match method.as_str() {
-                        "GET" => get(move |params| handler(params, None)),
-                        "DELETE" => delete(move |params| handler(params, None)),
+            let cache_ttl = section.kv.get("cache_ttl").and_then(|v| v.as_u64());
+            let private = section.kv.contains_key("auth");
+
+            if method == "CRUD" {
+                let mut operations = Vec::new();
+                for m in ["GET", "POST", "PUT", "DELETE"] {
+                    operations.push((m, false, axum_path.clone()));
+                    operations.push((m, true, format!("{}/{}", axum_path, "{id}")));
+                }
+                operations.push(("SEARCH", false, format!("{}/search", axum_path)));
+                operations.push(("BULK_POST", false, format!("{}/bulk", axum_path)));
+                operations.push(("BULK_DELETE", false, format!("{}/bulk", axum_path)));
+                for (m, with_id, path) in operations {
+                    let run_steps =
+                        crate::builtins::builtin::data_source::get_data_source_commands(
+                            m,
+                            section.clone(),
+                            &state.schemas,
+                            &state.data_sources,
+                            with_id,
+                        );
+                    let handler = create_handler(
+                        state_clone.clone(),
+                        &step::compile(&run_steps),
+                        middleware.clone(),
+                        None,
+                        on_error.clone(),
+                        None,
+                        None,
+                    );
+                    let route_fn = match m {
+                        "GET" | "SEARCH" => {
+                            get(move |params, query, headers| handler(params, query, headers, None))
+                        }
+                        "POST" | "BULK_POST" => post(move |params, query, headers, body| {
+                            handler(params, query, headers, Some(body))
+                        }),
+                        "PUT" => put(move |params, query, headers, body| {
+                            handler(params, query, headers, Some(body))
+                        }),
+                        "DELETE" => {
+                            delete(move |params, query, headers| handler(params, query, headers, None))
+                        }
+                        "BULK_DELETE" => delete(move |params, query, headers, body| {
+                            handler(params, query, headers, Some(body))
+                        }),
                         _ => unreachable!(),
+                    };
+                    let new_router = Router::new();
+                    let mut route = new_router.route(&path, route_fn);
+                    if matches!(m, "GET" | "SEARCH") {
+                        route = route.layer(axum::middleware::from_fn(move |req, next| {
+                            cache::conditional_get(req, next, cache_ttl, private)
+                        }));
+                    }
+                    if let Some(chaos) = chaos.clone() {
+                        route = route.layer(axum::middleware::from_fn(move |req, next| {
+                            crate::apps::chaos::inject(req, next, chaos.clone())
+                        }));
                     }
+                    if let Some(auth_name) = section.kv.get("auth").and_then(|v| v.as_str()) {
+                        if let Some(auth_section) = auth_configs.get(auth_name) {
+                            if let Some(Value::String(secret)) = auth_section.kv.get("secret") {
+                                let secret = secret.clone();
+                                route =
+                                    route.layer(axum::middleware::from_fn(move |req, next| {
+                                        jwt_auth(req, next, secret.clone())
+                                    }));
+                            }
+                        }
+                    }
+                    router = router.merge(route);
                 }
-                "POST" | "PUT" => {
-                    let handler = handler.clone();
-                    match method.as_str() {
-                        "POST" => post(move |params, body| handler(params, Some(body))),
-                        "PUT" => put(move |params, body| handler(params, Some(body))),
-                        _ => unreachable!(),
+                continue;
+            }
+
+            let bridge = graphql_bridge::GraphqlBridge::from_section(section, || {
+                local_schema
+                    .get_or_insert_with(|| crate::apps::graphql::build_schema(&state))
+                    .clone()
+            });
+            let route_fn = if let Some(bridge) = bridge {
+                let Some(route_fn) = graphql_bridge::method_router(bridge, &method) else {
+                    continue;
+                };
+                route_fn
+            } else {
+                let content_type = section
+                    .kv
+                    .get("content_type")
+                    .and_then(|v| v.as_str())
+                    .map(content_type_for);
+                let experiment = experiments
+                    .iter()
+                    .find(|e| e.method == method && e.path == axum_path)
+                    .cloned()
+                    .map(Arc::new);
+                let schema = section.kv.get("schema").and_then(|v| v.as_str()).map(str::to_string);
+                let run_steps = state.steps.get(section, "run").unwrap_or_else(|| {
+                    step::compile(&[Value::String("respond 200 OK".to_string())]).into()
+                });
+                let handler = create_handler(
+                    state_clone.clone(),
+                    &run_steps,
+                    middleware.clone(),
+                    schema,
+                    on_error.clone(),
+                    content_type,
+                    experiment,
+                );
+                match method.as_str() {
+                    "GET" | "DELETE" => {
+                        let handler = handler.clone();
+                        match method.as_str() {
+                            "GET" => get(move |params, query, headers| handler(params, query, headers, None)),
+                            "DELETE" => delete(move |params, query, headers| handler(params, query, headers, None)),
+                            _ => unreachable!(),
+                        }
                     }
+                    "POST" | "PUT" => {
+                        let handler = handler.clone();
+                        match method.as_str() {
+                            "POST" => {
+                                post(move |params, query, headers, body| handler(params, query, headers, Some(body)))
+                            }
+                            "PUT" => put(move |params, query, headers, body| handler(params, query, headers, Some(body))),
+                            _ => unreachable!(),
+                        }
+                    }
+                    _ => continue,
                 }
-                _ => continue,
             };
 
             let new_router = Router::new();
             let mut route = new_router.route(&axum_path, route_fn);
+            if method == "GET" && cache_ttl.is_some() {
+                route = route.layer(axum::middleware::from_fn(move |req, next| {
+                    cache::conditional_get(req, next, cache_ttl, private)
+                }));
+            }
+            if let Some(chaos) = chaos {
+                route = route.layer(axum::middleware::from_fn(move |req, next| {
+                    crate::apps::chaos::inject(req, next, chaos.clone())
+                }));
+            }
             if let Some(auth_name) = section.kv.get("auth").and_then(|v| v.as_str()) {
                 if let Some(auth_section) = auth_configs.get(auth_name) {
                     if let Some(Value::String(secret)) = auth_section.kv.get("secret") {
@@ -267,19 +404,97 @@ pub async fn build_rest_router(state: AppState) -> Router {
     add_token_endpoints(router, &auth_configs)
 }
 
+/// A route's steps with its middleware in front, compiled once: `validated` also checks
+/// the body against the route's schema first.
+struct RouteSteps {
+    plain: Arc<[Step]>,
+    validated: Arc<[Step]>,
+}
+
+impl RouteSteps {
+    fn new(middleware: &[Step], validation: &[Step], run: &[Step]) -> Self {
+        RouteSteps {
+            plain: middleware.iter().chain(run).cloned().collect(),
+            validated: middleware.iter().chain(validation).chain(run).cloned().collect(),
+        }
+    }
+
+    fn pick(&self, validate: bool) -> Arc<[Step]> {
+        if validate { self.validated.clone() } else { self.plain.clone() }
+    }
+}
+
+/// `middleware` steps run first. With a `schema`, a body parsed from its Content-Type is
+/// validated against it before the route's steps run.
+/// `content_type` is the route's default Content-Type, used when the steps don't set one.
+/// With an `experiment`, each request runs the steps of the variant it is assigned to.
 fn create_handler(
     state: AppState,
-    steps: Vec<Value>,
+    steps: &[Step],
+    middleware: Vec<Step>,
+    schema: Option<String>,
+    on_error: Option<Arc<[Step]>>,
+    content_type: Option<String>,
+    experiment: Option<Arc<experiment::Experiment>>,
 ) -> impl Fn(
     axum::extract::Path<HashMap<String, String>>,
+    axum::extract::Query<HashMap<String, String>>,
+    axum::http::HeaderMap,
     Option<String>,
-) -> std::pin::Pin<Box<dyn std::future::Future<Output = (StatusCode, String)> + Send>>
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = StepResponse> + Send>>
        + Clone {
+    let validation = match &schema {
+        Some(schema) => step::compile(&[Value::String(format!("validate body #{}", schema))]),
+        None => Vec::new(),
+    };
+    let route_steps = Arc::new(RouteSteps::new(&middleware, &validation, steps));
+    let variant_steps: Arc<HashMap<String, RouteSteps>> = Arc::new(
+        experiment
+            .iter()
+            .flat_map(|e| e.variants.iter().map(move |v| (e, v)))
+            .filter_map(|(e, v)| {
+                let run = step::compile(v.steps.as_ref()?);
+                step::report(&format!("experiment {} variant {}", e.name, v.name), &run);
+                Some((v.name.clone(), RouteSteps::new(&middleware, &validation, &run)))
+            })
+            .collect(),
+    );
     move |axum::extract::Path(params): axum::extract::Path<HashMap<String, String>>,
+          axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>,
+          headers: axum::http::HeaderMap,
           body: Option<String>| {
         let state = state.clone();
-        let steps = steps.clone();
-        Box::pin(async move { execute_steps(state, steps, body, Some(params)).await })
+        let on_error = on_error.clone();
+        let content_type = content_type.clone();
+        let headers: HashMap<String, String> = headers
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.as_str().to_string(), v.to_string())))
+            .collect();
+        let variant = experiment.as_ref().map(|e| (e.name.clone(), e.choose(&headers).clone()));
+        let validate = schema.is_some()
+            && body.as_deref().is_some_and(|b| !b.trim().is_empty())
+            && request_body::is_parsed(headers.get("content-type").map(String::as_str));
+        let steps = variant
+            .as_ref()
+            .and_then(|(_, v)| variant_steps.get(&v.name))
+            .unwrap_or(&route_steps)
+            .pick(validate);
+        Box::pin(async move {
+            let mut response =
+                execute_request_steps(state, &steps, body, Some(params), Some(query), Some(headers), on_error).await;
+            if let Some((name, variant)) = variant {
+                experiment::record(&name, &variant.name);
+                response
+                    .headers
+                    .push(("x-experiment".to_string(), format!("{}={}", name, variant.name)));
+            }
+            if let Some(content_type) = content_type {
+                if !response.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("content-type")) {
+                    response.headers.push(("content-type".to_string(), content_type));
+                }
+            }
+            response
+        })
     }
 }
 
@@ -0,0 +1,144 @@
+use crate::rune_ast::{Section, Value};
+use crate::util::{log, LogLevel};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::TryStreamExt;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+static PROXY_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("failed to build proxy HTTP client")
+});
+
+/// Headers that only describe a single hop and must not be forwarded.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// Configuration of a single `@Route/PROXY /prefix/*` section.
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Upstream base URL, e.g. `http://backend:8080`.
+    pub upstream: String,
+    /// Static route prefix the section matched on, e.g. `/api`.
+    pub prefix: String,
+    /// Replacement for `prefix` in the forwarded path (`rewrite = /v2`, or `/` to strip it).
+    pub rewrite: Option<String>,
+    /// Extra headers added to every forwarded request.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ProxyConfig {
+    pub fn from_section(section: &Section, prefix: &str) -> Option<Self> {
+        let upstream = section.kv.get("upstream").and_then(|v| v.as_str())?;
+        let rewrite = section
+            .kv
+            .get("rewrite")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let headers = match section.kv.get("headers") {
+            Some(Value::Map(map)) => {
+                let mut headers: Vec<(String, String)> = map
+                    .iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect();
+                headers.sort();
+                headers
+            }
+            _ => Vec::new(),
+        };
+        Some(ProxyConfig {
+            upstream: upstream.trim_end_matches('/').to_string(),
+            prefix: prefix.trim_end_matches('/').to_string(),
+            rewrite,
+            headers,
+        })
+    }
+
+    /// Builds the upstream URL for an incoming path and query string.
+    pub fn upstream_url(&self, path: &str, query: Option<&str>) -> String {
+        let forwarded = match &self.rewrite {
+            Some(rewrite) => {
+                let rest = path.strip_prefix(&self.prefix).unwrap_or(path);
+                let base = rewrite.trim_end_matches('/');
+                if rest.is_empty() && base.is_empty() {
+                    "/".to_string()
+                } else {
+                    format!("{}{}", base, rest)
+                }
+            }
+            None => path.to_string(),
+        };
+        match query {
+            Some(q) if !q.is_empty() => format!("{}{}?{}", self.upstream, forwarded, q),
+            _ => format!("{}{}", self.upstream, forwarded),
+        }
+    }
+}
+
+/// Forwards the request to the configured upstream and streams the response back.
+pub async fn proxy_handler(config: Arc<ProxyConfig>, req: Request<Body>) -> Response {
+    let (parts, body) = req.into_parts();
+    let url = config.upstream_url(parts.uri.path(), parts.uri.query());
+    log(
+        LogLevel::Debug,
+        &format!("Proxy {} {} -> {}", parts.method, parts.uri, url),
+    );
+
+    let traceparent = crate::apps::trace::outgoing_traceparent();
+    let mut upstream_req = PROXY_CLIENT.request(parts.method.clone(), &url);
+    for (name, value) in parts.headers.iter() {
+        let replaced = traceparent.is_some() && name.as_str() == crate::apps::trace::TRACEPARENT;
+        if !HOP_BY_HOP.contains(&name.as_str()) && !replaced {
+            upstream_req = upstream_req.header(name, value);
+        }
+    }
+    if let Some(host) = parts.headers.get("host") {
+        upstream_req = upstream_req.header("x-forwarded-host", host);
+    }
+    for (name, value) in &config.headers {
+        upstream_req = upstream_req.header(name.as_str(), value.as_str());
+    }
+    if let Some(traceparent) = traceparent {
+        upstream_req = upstream_req.header(crate::apps::trace::TRACEPARENT, traceparent);
+    }
+    let body_stream = body.into_data_stream();
+    upstream_req = upstream_req.body(reqwest::Body::wrap_stream(body_stream));
+
+    let upstream_resp = match upstream_req.send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            log(LogLevel::Error, &format!("Proxy to {} failed: {}", url, e));
+            return (StatusCode::BAD_GATEWAY, format!("Upstream error: {}", e)).into_response();
+        }
+    };
+
+    let status = upstream_resp.status();
+    let mut response = Response::builder().status(status.as_u16());
+    for (name, value) in upstream_resp.headers().iter() {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            response = response.header(name, value);
+        }
+    }
+    let stream = upstream_resp
+        .bytes_stream()
+        .map_err(std::io::Error::other);
+    response
+        .body(Body::from_stream(stream))
+        .unwrap_or_else(|e| (StatusCode::BAD_GATEWAY, e.to_string()).into_response())
+}
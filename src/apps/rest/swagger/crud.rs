@@ -49,6 +49,16 @@ pub fn add_crud_routes(
                 );
             }
 
+            if *m == "get" && !with_id && section.kv.contains_key("page_size") {
+                operation.insert(
+                    "parameters".to_string(),
+                    json!([
+                        { "name": "page", "in": "query", "schema": { "type": "integer", "minimum": 1 } },
+                        { "name": "size", "in": "query", "schema": { "type": "integer", "minimum": 1 } }
+                    ]),
+                );
+            }
+
             // Add request body for POST and PUT
             if *m == "post" || *m == "put" {
                 add_expect_request_body(&mut operation, section, components_schemas);
@@ -57,5 +67,55 @@ pub fn add_crud_routes(
             path_item.insert(m.to_string(), serde_json::Value::Object(operation));
         }
     }
+
+    let schema_ref = section
+        .kv
+        .get("schema")
+        .and_then(|v| v.as_str())
+        .map(|name| json!({ "$ref": format!("#/components/schemas/{}", name) }))
+        .unwrap_or_else(|| json!({ "type": "object" }));
+    let search_path = format!("{}/search", axum_path);
+    paths.insert(
+        search_path.clone(),
+        json!({
+            "get": {
+                "summary": format!("GET {}", search_path),
+                "description": description,
+                "parameters": [
+                    { "name": "q", "in": "query", "required": true, "schema": { "type": "string" },
+                      "description": "Text to find, ignoring case, in the schema's string fields" },
+                    { "name": "fields", "in": "query", "schema": { "type": "string" },
+                      "description": "Comma separated string fields to search instead of all of them" }
+                ],
+                "responses": {
+                    "200": { "description": "OK" },
+                    "400": { "description": "Missing q, or a field that cannot be searched" }
+                }
+            }
+        }),
+    );
+
+    let bulk_path = format!("{}/bulk", axum_path);
+    let bulk = paths
+        .entry(bulk_path.clone())
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+        .as_object_mut()
+        .unwrap();
+    for (m, items) in [("post", schema_ref), ("delete", json!({ "type": "string" }))] {
+        bulk.insert(
+            m.to_string(),
+            json!({
+                "summary": format!("{} {}", m.to_uppercase(), bulk_path),
+                "description": description,
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": { "type": "array", "items": items } } }
+                },
+                "responses": {
+                    "200": { "description": "OK, with `{succeeded, failed, results}` and a status per item" }
+                }
+            }),
+        );
+    }
 }
 
@@ -1,9 +1,15 @@
+use crate::builtins::builtin::validate::conditional_rules;
 use serde_json::json;
 
 pub fn build_openapi_components(
     doc: &crate::rune_ast::RuneDocument,
 ) -> serde_json::Map<String, serde_json::Value> {
     let mut schemas = serde_json::Map::new();
+    let rune_schemas = crate::core::extract_schemas(doc);
+
+    for (name, values) in crate::core::constants::enums(doc) {
+        schemas.insert(name, json!({ "type": "string", "enum": values }));
+    }
 
     for section in &doc.sections {
         if section.path.first().map(|s| s.as_str()) != Some("Schema") {
@@ -16,13 +22,27 @@ pub fn build_openapi_components(
 
         let mut properties = serde_json::Map::new();
         let mut required = Vec::new();
+        // Fields named by `validate_if` rules are only conditionally required
+        let conditional: Vec<String> = conditional_rules(section)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|rule| rule.requires)
+            .collect();
 
         for (field_name, field_type) in &section.kv {
             if let Some(field_type) = field_type.as_str() {
+                let field_type = crate::core::relations::resolve_type(field_type, &rune_schemas);
                 properties.insert(field_name.clone(), rune_schema_field_type(field_type));
-                required.push(field_name.clone());
+                if !conditional.contains(field_name) {
+                    required.push(field_name.clone());
+                }
             }
         }
+        for column in crate::core::schema_options::SchemaOptions::of(schema_name, doc).columns() {
+            properties
+                .entry(column)
+                .or_insert(json!({ "type": "string", "format": "date-time", "readOnly": true }));
+        }
 
         schemas.insert(
             schema_name.clone(),
@@ -42,6 +62,11 @@ fn rune_schema_field_type(field_type: &str) -> serde_json::Value {
         "number" => json!({ "type": "number" }),
         "bool" => json!({ "type": "boolean" }),
         "string" => json!({ "type": "string" }),
+        "datetime" => json!({ "type": "string", "format": "date-time" }),
+        "json" => json!({}),
+        other if crate::core::schema_options::vector_dimensions(other).is_some() => {
+            json!({ "type": "array", "items": { "type": "number" } })
+        }
         other => json!({ "$ref": format!("#/components/schemas/{}", other) }),
     }
 }
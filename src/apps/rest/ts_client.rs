@@ -0,0 +1,357 @@
+//! `vectrune app.rune -o ts-client`: a TypeScript module for calling the document's REST
+//! routes from a frontend.
+//!
+//! Each `@Enum` becomes a union type and each `@Schema` an interface. `createClient()`
+//! returns a function per `@Route` (`getBooksById(id)`, `postOrders(body)`) and an object per
+//! `@Route/CRUD` collection (`books.list()`, `books.get(id)`, ...). Routes with `auth` send
+//! the client's token as a bearer `Authorization` header, and each `@Authentication` section
+//! with a `token_endpoint` gets a `login<Name>(username, password)` that fetches and keeps one.
+
+use std::collections::HashMap;
+
+use crate::core::relations::resolve_type;
+use crate::core::schema_options::SchemaOptions;
+use crate::core::{constants, extract_schemas};
+use crate::rune_ast::{RuneDocument, Section, Value};
+
+const RUNTIME: &str = r#"export type Query = Record<string, string | number | boolean | undefined>;
+
+export interface ClientOptions {
+  /** Base URL of the API. */
+  baseUrl?: string;
+  /** Bearer token, or a function returning one, sent to routes that require auth. */
+  token?: string | (() => string | undefined | Promise<string | undefined>);
+  /** Headers sent with every request. */
+  headers?: Record<string, string>;
+  /** fetch implementation; defaults to the global fetch. */
+  fetch?: typeof fetch;
+}
+
+export class ApiError extends Error {
+  constructor(public readonly status: number, public readonly body: unknown) {
+    super(`Request failed with status ${status}`);
+  }
+}
+
+interface RequestOptions {
+  query?: Query;
+  body?: unknown;
+  auth?: boolean;
+  headers?: Record<string, string>;
+}
+
+function parseBody(text: string): unknown {
+  if (text === "") return undefined;
+  try {
+    return JSON.parse(text);
+  } catch {
+    return text;
+  }
+}
+
+function segment(value: string | number): string {
+  return encodeURIComponent(String(value));
+}
+"#;
+
+const REQUEST: &str = r#"  const doFetch = options.fetch ?? fetch;
+  let token = options.token;
+
+  async function request<T>(method: string, path: string, init: RequestOptions = {}): Promise<T> {
+    const url = new URL(baseUrl + path);
+    for (const [key, value] of Object.entries(init.query ?? {})) {
+      if (value !== undefined) url.searchParams.set(key, String(value));
+    }
+    const headers: Record<string, string> = { ...options.headers, ...init.headers };
+    if (init.body !== undefined) headers["Content-Type"] = "application/json";
+    if (init.auth) {
+      const value = typeof token === "function" ? await token() : token;
+      if (value) headers["Authorization"] = `Bearer ${value}`;
+    }
+    const response = await doFetch(url, {
+      method,
+      headers,
+      body: init.body === undefined ? undefined : JSON.stringify(init.body),
+    });
+    const body = parseBody(await response.text());
+    if (!response.ok) throw new ApiError(response.status, body);
+    return body as T;
+  }
+"#;
+
+/// `book-reviews` or `book_reviews` as `BookReviews`.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+    chars.next().map(|c| c.to_lowercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// A property name, quoted when it is not a plain identifier.
+fn property(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if plain {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap_or_default()
+    }
+}
+
+fn ts_type(typ: &str, schemas: &HashMap<String, Section>, enums: &HashMap<String, Vec<serde_json::Value>>) -> String {
+    match resolve_type(typ, schemas) {
+        "number" => "number".to_string(),
+        "bool" => "boolean".to_string(),
+        "json" => "unknown".to_string(),
+        other if schemas.contains_key(other) || enums.contains_key(other) => other.to_string(),
+        _ => "string".to_string(),
+    }
+}
+
+fn interfaces(doc: &RuneDocument, out: &mut String) {
+    let schemas = extract_schemas(doc);
+    let enums = constants::enums(doc);
+    let mut enum_names: Vec<&String> = enums.keys().collect();
+    enum_names.sort();
+    for name in enum_names {
+        let members: Vec<String> = enums[name].iter().map(|m| m.to_string()).collect();
+        let members = if members.is_empty() { "never".to_string() } else { members.join(" | ") };
+        out.push_str(&format!("export type {} = {};\n\n", name, members));
+    }
+    let mut names: Vec<&String> = schemas.keys().collect();
+    names.sort();
+    for name in names {
+        let mut fields: Vec<(&String, &str)> = schemas[name]
+            .kv
+            .iter()
+            .filter_map(|(field, typ)| Some((field, typ.as_str()?)))
+            .collect();
+        fields.sort();
+        out.push_str(&format!("export interface {} {{\n", name));
+        for (field, typ) in fields {
+            out.push_str(&format!("  {}: {};\n", property(field), ts_type(typ, &schemas, &enums)));
+        }
+        for column in SchemaOptions::of(name, doc).columns() {
+            out.push_str(&format!("  readonly {}?: string;\n", column));
+        }
+        out.push_str("}\n\n");
+    }
+}
+
+/// A path template such as `/books/{id}` as a template literal over its parameters.
+fn path_literal(path: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+    let mut literal = String::new();
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        literal.push('/');
+        match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(param) => {
+                let name = camel_case(param);
+                literal.push_str(&format!("${{segment({})}}", name));
+                params.push(name);
+            }
+            None => literal.push_str(part),
+        }
+    }
+    if literal.is_empty() {
+        literal.push('/');
+    }
+    (format!("`{}`", literal), params)
+}
+
+/// `GET /books/{id}` as `getBooksById`.
+fn function_name(method: &str, path: &str) -> String {
+    let mut name = method.to_lowercase();
+    let mut any = false;
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        any = true;
+        match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(param) => name.push_str(&format!("By{}", pascal_case(param))),
+            None => name.push_str(&pascal_case(part)),
+        }
+    }
+    if !any {
+        name.push_str("Root");
+    }
+    name
+}
+
+fn auth_flag(section: &Section) -> &'static str {
+    if section.kv.contains_key("auth") {
+        ", auth: true"
+    } else {
+        ""
+    }
+}
+
+fn single_route(method: &str, path: &str, section: &Section, schemas: &HashMap<String, Section>) -> String {
+    let (literal, params) = path_literal(path);
+    let mut args: Vec<String> = params.iter().map(|p| format!("{}: string | number", p)).collect();
+    let has_body = matches!(method, "POST" | "PUT" | "PATCH");
+    if has_body {
+        let body_type = section
+            .kv
+            .get("expect")
+            .and_then(Value::as_str)
+            .filter(|s| schemas.contains_key(*s))
+            .unwrap_or("unknown");
+        args.push(format!("body: {}", body_type));
+    }
+    args.push("query?: Query".to_string());
+    format!(
+        "    {}: <T = unknown>({}) =>\n      request<T>(\"{}\", {}, {{ query{}{} }}),\n",
+        function_name(method, path),
+        args.join(", "),
+        method,
+        literal,
+        if has_body { ", body" } else { "" },
+        auth_flag(section),
+    )
+}
+
+fn crud_route(path: &str, section: &Section, schemas: &HashMap<String, Section>) -> String {
+    let (base, params) = path_literal(path);
+    let base_inner = base.trim_matches('`');
+    let args: Vec<String> = params.iter().map(|p| format!("{}: string | number", p)).collect();
+    let item = section.kv.get("schema").and_then(Value::as_str).filter(|s| schemas.contains_key(*s));
+    let record = item.unwrap_or("Record<string, unknown>").to_string();
+    let new_record = match item {
+        Some(schema) if schemas[schema].kv.contains_key("id") => format!("Omit<{0}, \"id\"> & {{ id?: {0}[\"id\"] }}", schema),
+        _ => record.clone(),
+    };
+    let list = if section.kv.contains_key("page_size") {
+        format!("{{ data: {}[]; page: number; size: number; total: number; next: number | null }}", record)
+    } else {
+        format!("{}[]", record)
+    };
+    let auth = auth_flag(section);
+    let name = camel_case(&path.split('/').filter(|p| !p.is_empty() && !p.starts_with('{')).collect::<Vec<_>>().join("-"));
+    let with = |extra: &[&str]| {
+        let mut all = args.clone();
+        all.extend(extra.iter().map(|s| s.to_string()));
+        all.join(", ")
+    };
+    let item_path = format!("`{}/${{segment(id)}}`", base_inner);
+    let mut out = format!("    {}: {{\n", if name.is_empty() { "root".to_string() } else { property(&name) });
+    out.push_str(&format!(
+        "      list: ({}) => request<{}>(\"GET\", {}, {{ query{} }}),\n",
+        with(&["query?: Query"]),
+        list,
+        base,
+        auth
+    ));
+    out.push_str(&format!(
+        "      get: ({}) => request<{}>(\"GET\", {}{}),\n",
+        with(&["id: string | number"]),
+        record,
+        item_path,
+        if auth.is_empty() { String::new() } else { ", { auth: true }".to_string() }
+    ));
+    out.push_str(&format!(
+        "      create: ({}) => request<{}>(\"POST\", {}, {{ body{} }}),\n",
+        with(&[&format!("body: {}", new_record)]),
+        record,
+        base,
+        auth
+    ));
+    out.push_str(&format!(
+        "      update: ({}) => request<{}>(\"PUT\", {}, {{ body{} }}),\n",
+        with(&["id: string | number", &format!("body: Partial<{}>", record)]),
+        record,
+        item_path,
+        auth
+    ));
+    out.push_str(&format!(
+        "      remove: ({}) => request<unknown>(\"DELETE\", {}{}),\n",
+        with(&["id: string | number"]),
+        item_path,
+        if auth.is_empty() { String::new() } else { ", { auth: true }".to_string() }
+    ));
+    out.push_str(&format!(
+        "      search: ({}) =>\n        request<{}[]>(\"GET\", `{}/search`, {{ query: {{ q, fields: fields?.join(\",\") }}{} }}),\n",
+        with(&["q: string", "fields?: string[]"]),
+        record,
+        base_inner,
+        auth
+    ));
+    out.push_str(&format!(
+        "      bulkCreate: ({}) => request<unknown>(\"POST\", `{}/bulk`, {{ body: items{} }}),\n",
+        with(&[&format!("items: Array<{}>", new_record)]),
+        base_inner,
+        auth
+    ));
+    out.push_str(&format!(
+        "      bulkDelete: ({}) => request<unknown>(\"DELETE\", `{}/bulk`, {{ body: ids{} }}),\n",
+        with(&["ids: Array<string | number>"]),
+        base_inner,
+        auth
+    ));
+    out.push_str("    },\n");
+    out
+}
+
+/// The URL the app listens on, from `@App host` and `port`.
+fn default_base_url(doc: &RuneDocument) -> String {
+    let app = doc.get_section("App");
+    let host = app.and_then(|s| s.kv.get("host")).and_then(Value::as_str).unwrap_or("127.0.0.1");
+    let port = app.and_then(|s| s.kv.get("port")).and_then(Value::as_u64).unwrap_or(3000);
+    format!("http://{}:{}", host, port)
+}
+
+pub fn generate_ts_client(doc: &RuneDocument) -> String {
+    let schemas = extract_schemas(doc);
+    let title = doc
+        .get_section("App")
+        .and_then(|s| s.kv.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or("Vectrune API");
+    let mut out = format!("// TypeScript client for {}, generated by `vectrune -o ts-client`.\n\n", title);
+    interfaces(doc, &mut out);
+    out.push_str(RUNTIME);
+    out.push_str(&format!(
+        "\nexport function createClient(options: ClientOptions = {{}}) {{\n  const baseUrl = (options.baseUrl ?? \"{}\").replace(/\\/+$/, \"\");\n",
+        default_base_url(doc)
+    ));
+    out.push_str(REQUEST);
+    out.push_str("\n  return {\n");
+    out.push_str("    /** Replaces the bearer token sent to routes that require auth. */\n");
+    out.push_str("    setToken(value: ClientOptions[\"token\"]) {\n      token = value;\n    },\n");
+
+    let mut auth_sections: Vec<&Section> = doc
+        .sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Authentication"))
+        .collect();
+    auth_sections.sort_by(|a, b| a.path.cmp(&b.path));
+    for section in auth_sections {
+        let (Some(name), Some(endpoint)) = (section.path.get(1), section.kv.get("token_endpoint").and_then(Value::as_str)) else {
+            continue;
+        };
+        out.push_str(&format!(
+            "    /** Fetches a token from {0} and sends it to routes that require auth. */\n    async login{1}(username: string, password: string): Promise<string> {{\n      const value = await request<string>(\"POST\", \"{0}\", {{\n        headers: {{ Authorization: `Basic ${{btoa(`${{username}}:${{password}}`)}}` }},\n      }});\n      token = String(value);\n      return token;\n    }},\n",
+            endpoint,
+            pascal_case(name)
+        ));
+    }
+
+    for section in doc.sections.iter().filter(|s| s.path.first().map(String::as_str) == Some("Route") && s.path.len() >= 3) {
+        let method = section.path[1].to_uppercase();
+        let path = format!("/{}", section.path[2..].join("/"));
+        match method.as_str() {
+            "CRUD" => out.push_str(&crud_route(&path, section, &schemas)),
+            "GET" | "POST" | "PUT" | "PATCH" | "DELETE" => out.push_str(&single_route(&method, &path, section, &schemas)),
+            _ => {}
+        }
+    }
+    out.push_str("  };\n}\n\nexport type Client = ReturnType<typeof createClient>;\n");
+    out
+}
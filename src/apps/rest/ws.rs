@@ -2,7 +2,7 @@ use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
     response::IntoResponse,
 };
-use crate::core::{execute_steps, AppState};
+use crate::core::{execute_request_steps, AppState};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -52,10 +52,10 @@ async fn handle_socket(socket: WebSocket, state: AppState, path: String) {
     if let Some(ws_section) = state.doc.sections.iter().find(|s| {
         s.path.len() >= 2 && s.path[0] == "Websocket" && s.path[1] == normalized_path
     }) {
-        if let Some(steps) = ws_section.series.get("on_connect") {
+        if let Some(steps) = state.steps.get(ws_section, "on_connect") {
             let mut params = HashMap::new();
             params.insert("ws_id".to_string(), conn_id.to_string());
-            execute_steps(state.clone(), steps.clone(), None, Some(params)).await;
+            execute_request_steps(state.clone(), &steps, None, Some(params), None, None, None).await;
         }
     }
 
@@ -70,10 +70,10 @@ async fn handle_socket(socket: WebSocket, state: AppState, path: String) {
     if let Some(ws_section) = state.doc.sections.iter().find(|s| {
         s.path.len() >= 2 && s.path[0] == "Websocket" && s.path[1] == normalized_path
     }) {
-        if let Some(steps) = ws_section.series.get("on_disconnect") {
+        if let Some(steps) = state.steps.get(ws_section, "on_disconnect") {
             let mut params = HashMap::new();
             params.insert("ws_id".to_string(), conn_id.to_string());
-            execute_steps(state.clone(), steps.clone(), None, Some(params)).await;
+            execute_request_steps(state.clone(), &steps, None, Some(params), None, None, None).await;
         }
     }
 
@@ -105,7 +105,7 @@ async fn handle_message(text: String, conn_id: Uuid, state: &AppState, path: &st
         let normalized_path = path.trim_start_matches('/');
         let event_path = vec!["Event".to_string(), normalized_path.to_string(), normalized_event.to_string()];
         if let Some(event_section) = state.doc.sections.iter().find(|s| s.path == event_path) {
-            if let Some(steps) = event_section.series.get("run") {
+            if let Some(steps) = state.steps.get(event_section, "run") {
                 let mut params = HashMap::new();
                 params.insert("ws_id".to_string(), conn_id.to_string());
                 // Flatten the JSON body into context for easier access
@@ -114,7 +114,7 @@ async fn handle_message(text: String, conn_id: Uuid, state: &AppState, path: &st
                         params.insert(k.clone(), v.to_string());
                     }
                 }
-                execute_steps(state.clone(), steps.clone(), Some(text), Some(params)).await;
+                execute_request_steps(state.clone(), &steps, Some(text), Some(params), None, None, None).await;
                 return;
             }
         }
@@ -126,10 +126,10 @@ async fn handle_message(text: String, conn_id: Uuid, state: &AppState, path: &st
 async fn run_generic_on_message(text: String, conn_id: Uuid, state: &AppState, path: &str) {
      // path is already normalized (no leading /), so we can compare directly
      if let Some(ws_section) = state.doc.sections.iter().find(|s| s.path.len() >= 2 && s.path[0] == "Websocket" && s.path[1] == path) {
-        if let Some(steps) = ws_section.series.get("on_message") {
+        if let Some(steps) = state.steps.get(ws_section, "on_message") {
             let mut params = HashMap::new();
             params.insert("ws_id".to_string(), conn_id.to_string());
-            execute_steps(state.clone(), steps.clone(), Some(text), Some(params)).await;
+            execute_request_steps(state.clone(), &steps, Some(text), Some(params), None, None, None).await;
         }
     }
 }
@@ -0,0 +1,222 @@
+use crate::apps::admin::{admin_token, ADMIN_PREFIX};
+use crate::core::get_app_type;
+use crate::rune_ast::{RuneDocument, Value};
+use serde::Serialize;
+
+/// A single HTTP route mounted from a `@Route/<METHOD> <path>` section.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RouteInfo {
+    pub method: String,
+    pub path: String,
+    pub auth: Option<String>,
+    pub schema: Option<String>,
+    pub datasource: Option<String>,
+}
+
+/// A non-route mount: frontends, websocket listeners, proxies and built-in endpoints.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MountInfo {
+    pub kind: String,
+    pub path: String,
+    pub detail: String,
+}
+
+/// Lists the routes a REST document mounts, expanding CRUD sections into
+/// their GET/POST/PUT/DELETE collection and item routes, search and bulk routes.
+pub fn route_table(doc: &RuneDocument) -> Vec<RouteInfo> {
+    let mut routes = Vec::new();
+    for section in &doc.sections {
+        if section.path.first().map(|s| s.as_str()) != Some("Route") || section.path.len() < 3 {
+            continue;
+        }
+        let method = section.path[1].to_uppercase();
+        let path = format!("/{}", section.path[2..].join("/"));
+        let kv_string = |key: &str| {
+            section
+                .kv
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+        let auth = kv_string("auth");
+
+        match method.as_str() {
+            "CRUD" => {
+                let mut operations = Vec::new();
+                for m in ["GET", "POST", "PUT", "DELETE"] {
+                    operations.push((m, path.clone()));
+                    operations.push((m, format!("{}/{{id}}", path)));
+                }
+                operations.push(("GET", format!("{}/search", path)));
+                operations.push(("POST", format!("{}/bulk", path)));
+                operations.push(("DELETE", format!("{}/bulk", path)));
+                for (m, path) in operations {
+                    routes.push(RouteInfo {
+                        method: m.to_string(),
+                        path,
+                        auth: auth.clone(),
+                        schema: kv_string("schema"),
+                        datasource: kv_string("data_source"),
+                    });
+                }
+            }
+            "GET" | "POST" | "PUT" | "DELETE" | "PROXY" => routes.push(RouteInfo {
+                method,
+                path,
+                auth,
+                schema: kv_string("schema"),
+                datasource: kv_string("data_source"),
+            }),
+            _ => {}
+        }
+    }
+    routes
+}
+
+/// Lists frontend mounts, websocket listeners and built-in endpoints for a document.
+pub fn mount_table(doc: &RuneDocument) -> Vec<MountInfo> {
+    let mut mounts = Vec::new();
+    for section in &doc.sections {
+        match section.path.first().map(|s| s.as_str()) {
+            Some("Websocket") => {
+                let path = section.path.get(1).map(|s| s.as_str()).unwrap_or("ws");
+                mounts.push(MountInfo {
+                    kind: "websocket".to_string(),
+                    path: format!("/{}", path.trim_start_matches('/')),
+                    detail: String::new(),
+                });
+            }
+            Some("Frontend") => {
+                let Some(Value::String(fe_type)) = section.kv.get("type") else {
+                    continue;
+                };
+                let path = section
+                    .kv
+                    .get("path")
+                    .and_then(|v| v.as_str())
+                    .map(|p| if p == "%ROOT%" { "/" } else { p })
+                    .unwrap_or("/");
+                let detail = match section.kv.get("src").and_then(|v| v.as_str()) {
+                    Some(src) => format!("{} {}", fe_type, src),
+                    None => fe_type.clone(),
+                };
+                mounts.push(MountInfo {
+                    kind: "frontend".to_string(),
+                    path: path.to_string(),
+                    detail,
+                });
+            }
+            Some("Route")
+                if section.path.get(1).map(|m| m.eq_ignore_ascii_case("PROXY")) == Some(true) =>
+            {
+                let upstream = section
+                    .kv
+                    .get("upstream")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("-");
+                mounts.push(MountInfo {
+                    kind: "proxy".to_string(),
+                    path: format!("/{}", section.path[2..].join("/")),
+                    detail: format!("-> {}", upstream),
+                });
+            }
+            Some("App") => {
+                if let Some(Value::Bool(true)) = section.kv.get("swagger") {
+                    mounts.push(MountInfo {
+                        kind: "swagger".to_string(),
+                        path: "/swagger-ui".to_string(),
+                        detail: "/openapi.json".to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if admin_token(doc).is_some() {
+        mounts.push(MountInfo {
+            kind: "admin".to_string(),
+            path: ADMIN_PREFIX.to_string(),
+            detail: String::new(),
+        });
+    }
+    mounts
+}
+
+fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{:width$}", c, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut out = vec![line(headers.to_vec())];
+    for row in rows {
+        out.push(line(row.iter().map(|s| s.as_str()).collect()));
+    }
+    out.join("\n")
+}
+
+/// Renders the startup banner: app summary, listener, route table and mounts.
+pub fn render_route_summary(doc: &RuneDocument, listening_on: Option<&str>) -> String {
+    let app = doc.get_section("App");
+    let app_kv = |key: &str| {
+        app.and_then(|sec| sec.kv.get(key))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    let app_type = get_app_type(doc)
+        .unwrap_or_else(|| "REST".to_string())
+        .to_uppercase();
+    let mut out = match (app_kv("name"), app_kv("version")) {
+        (Some(name), Some(version)) => format!("Vectrune {} app: {} v{}", app_type, name, version),
+        (Some(name), None) => format!("Vectrune {} app: {}", app_type, name),
+        _ => format!("Vectrune {} app", app_type),
+    };
+    if let Some(addr) = listening_on {
+        out.push_str(&format!("\nListening on http://{}", addr));
+    }
+
+    let dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    let routes: Vec<Vec<String>> = route_table(doc)
+        .iter()
+        .filter(|r| r.method != "PROXY")
+        .map(|r| {
+            vec![
+                r.method.clone(),
+                r.path.clone(),
+                dash(&r.auth),
+                dash(&r.schema),
+                dash(&r.datasource),
+            ]
+        })
+        .collect();
+    out.push_str("\n\nRoutes:\n");
+    if routes.is_empty() {
+        out.push_str("  (none)");
+    } else {
+        out.push_str(&render_table(
+            &["METHOD", "PATH", "AUTH", "SCHEMA", "DATASOURCE"],
+            &routes,
+        ));
+    }
+
+    let mounts: Vec<Vec<String>> = mount_table(doc)
+        .into_iter()
+        .map(|m| vec![m.kind, m.path, m.detail])
+        .collect();
+    if !mounts.is_empty() {
+        out.push_str("\n\nMounts:\n");
+        out.push_str(&render_table(&["KIND", "PATH", "DETAIL"], &mounts));
+    }
+    out
+}
@@ -0,0 +1,152 @@
+//! W3C Trace Context propagation. An incoming `traceparent` header is continued (a new
+//! trace is started when it is missing or malformed), each request gets its own span id,
+//! and outgoing proxy and GraphQL calls carry a `traceparent` naming that span as their
+//! parent, so traces through several services stay connected.
+//!
+//! Steps see the ids as `trace.trace_id`, `trace.span_id` and `trace.parent_id`.
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Value as JsonValue};
+
+pub const TRACEPARENT: &str = "traceparent";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    /// The caller's span, if the request arrived with a valid `traceparent`.
+    pub parent_id: Option<String>,
+    pub flags: u8,
+}
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn random_hex(len: usize) -> String {
+    let mut out = String::new();
+    while out.len() < len {
+        out.push_str(&uuid::Uuid::new_v4().simple().to_string());
+    }
+    out.truncate(len);
+    out
+}
+
+/// Parses a `traceparent` value into `(trace_id, parent_id, flags)`.
+pub fn parse_traceparent(value: &str) -> Option<(String, String, u8)> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let [version, trace_id, parent_id, flags, ..] = parts.as_slice() else {
+        return None;
+    };
+    if !is_hex(version, 2) || *version == "ff" || (*version == "00" && parts.len() != 4) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || trace_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    if !is_hex(parent_id, 16) || parent_id.bytes().all(|b| b == b'0') || !is_hex(flags, 2) {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some((trace_id.to_string(), parent_id.to_string(), flags))
+}
+
+impl TraceContext {
+    /// Continues the trace in `headers`, or starts a new sampled one.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let incoming = headers
+            .get(TRACEPARENT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_traceparent);
+        match incoming {
+            Some((trace_id, parent_id, flags)) => TraceContext {
+                trace_id,
+                span_id: random_hex(16),
+                parent_id: Some(parent_id),
+                flags,
+            },
+            None => TraceContext {
+                trace_id: random_hex(32),
+                span_id: random_hex(16),
+                parent_id: None,
+                flags: 1,
+            },
+        }
+    }
+
+    /// The `traceparent` to send on outgoing calls made while handling this span.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "trace_id": self.trace_id,
+            "span_id": self.span_id,
+            "parent_id": self.parent_id,
+            "traceparent": self.traceparent(),
+        })
+    }
+}
+
+/// The trace of the request being handled on this task, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|ctx| ctx.clone()).ok()
+}
+
+/// The `traceparent` header value for an outgoing call, if a request is being traced.
+pub fn outgoing_traceparent() -> Option<String> {
+    current().map(|ctx| ctx.traceparent())
+}
+
+/// Middleware that opens a span for every request and echoes its `traceparent` on the response.
+pub async fn propagate(req: Request<Body>, next: Next) -> Response {
+    let ctx = TraceContext::from_headers(req.headers());
+    let header = ctx.traceparent();
+    let mut response = CURRENT.scope(ctx, next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&header) {
+        response.headers_mut().insert(TRACEPARENT, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_rejects_traceparents() {
+        let parsed = parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01");
+        assert_eq!(
+            parsed,
+            Some(("4bf92f3577b34da6a3ce929d0e0e4736".to_string(), "00f067aa0ba902b7".to_string(), 1))
+        );
+        assert!(parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("garbage").is_none());
+    }
+
+    #[test]
+    fn continues_an_incoming_trace_with_a_new_span() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACEPARENT,
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        let ctx = TraceContext::from_headers(&headers);
+        assert_eq!(ctx.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_ne!(ctx.span_id, "00f067aa0ba902b7");
+        assert!(parse_traceparent(&ctx.traceparent()).is_some());
+    }
+}
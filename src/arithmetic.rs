@@ -1,5 +1,6 @@
 // Arithmetic expression parser and evaluator for Rune
-// Supports +, -, *, /, parentheses, and numbers
+// Supports +, -, *, /, %, parentheses, numbers (including negative and float
+// literals) and identifiers resolved by the caller, e.g. `scores[i + 1] * 2`
 
 #[derive(Debug, PartialEq)]
 pub enum Token {
@@ -8,6 +9,8 @@ pub enum Token {
     Minus,
     Mul,
     Div,
+    Mod,
+    Ident(String),
     LParen,
     RParen,
 }
@@ -22,6 +25,7 @@ pub fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
             '-' => { tokens.push(Token::Minus); chars.next(); },
             '*' => { tokens.push(Token::Mul); chars.next(); },
             '/' => { tokens.push(Token::Div); chars.next(); },
+            '%' => { tokens.push(Token::Mod); chars.next(); },
             '(' => { tokens.push(Token::LParen); chars.next(); },
             ')' => { tokens.push(Token::RParen); chars.next(); },
             '0'..='9' | '.' => {
@@ -37,14 +41,69 @@ pub fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
                 let val = num.parse::<f64>().map_err(|_| format!("Invalid number: {}", num))?;
                 tokens.push(Token::Number(val));
             },
+            c if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+                // Identifier path: `a.b`, `it.id`, `users[index + 1].name`
+                let mut ident = String::new();
+                let mut depth = 0usize;
+                while let Some(&d) = chars.peek() {
+                    // `parse-json` style names: a hyphen between letters is part of the name
+                    let hyphenated = d == '-'
+                        && depth == 0
+                        && ident.ends_with(|p: char| p.is_ascii_alphabetic() || p == '_')
+                        && chars
+                            .clone()
+                            .nth(1)
+                            .map(|n| n.is_ascii_alphabetic() || n == '_')
+                            .unwrap_or(false);
+                    match d {
+                        _ if hyphenated => {}
+                        '[' => depth += 1,
+                        ']' if depth > 0 => depth -= 1,
+                        ']' => break,
+                        _ if depth == 0
+                            && !(d.is_ascii_alphanumeric() || d == '_' || d == '.' || d == '$') =>
+                        {
+                            break
+                        }
+                        _ => {}
+                    }
+                    ident.push(d);
+                    chars.next();
+                }
+                if depth > 0 {
+                    return Err(format!("Unclosed bracket in: {}", ident));
+                }
+                tokens.push(Token::Ident(ident));
+            },
             _ => return Err(format!("Invalid character: {}", c)),
         }
     }
     Ok(tokens)
 }
 
+/// True if `expr` tokenizes as arithmetic and contains at least one operator, so
+/// a bare identifier or number is not treated as a calculation.
+pub fn has_operator(expr: &str) -> bool {
+    tokenize(expr)
+        .map(|tokens| {
+            tokens.iter().any(|t| {
+                matches!(t, Token::Plus | Token::Minus | Token::Mul | Token::Div | Token::Mod)
+            })
+        })
+        .unwrap_or(false)
+}
+
 // Recursive descent parser
 pub fn eval_arithmetic(expr: &str) -> Result<f64, String> {
+    eval_arithmetic_with(expr, |_| None)
+}
+
+/// Evaluates `expr`, looking up identifiers through `resolve`. Unknown identifiers
+/// are an error.
+pub fn eval_arithmetic_with<F>(expr: &str, resolve: F) -> Result<f64, String>
+where
+    F: Fn(&str) -> Option<f64>,
+{
     // If expr is just a number, return it directly
     let trimmed = expr.trim();
     if let Ok(val) = trimmed.parse::<f64>() {
@@ -52,49 +111,58 @@ pub fn eval_arithmetic(expr: &str) -> Result<f64, String> {
     }
     let tokens = tokenize(expr)?;
     let mut pos = 0;
-    fn parse_expr(tokens: &[Token], pos: &mut usize) -> Result<f64, String> {
-        let mut val = parse_term(tokens, pos)?;
+    type Resolve<'a> = &'a dyn Fn(&str) -> Option<f64>;
+    fn parse_expr(tokens: &[Token], pos: &mut usize, resolve: Resolve) -> Result<f64, String> {
+        let mut val = parse_term(tokens, pos, resolve)?;
         while *pos < tokens.len() {
             match tokens[*pos] {
                 Token::Plus => {
                     *pos += 1;
-                    val += parse_term(tokens, pos)?;
+                    val += parse_term(tokens, pos, resolve)?;
                 },
                 Token::Minus => {
                     *pos += 1;
-                    val -= parse_term(tokens, pos)?;
+                    val -= parse_term(tokens, pos, resolve)?;
                 },
                 _ => break,
             }
         }
         Ok(val)
     }
-    fn parse_term(tokens: &[Token], pos: &mut usize) -> Result<f64, String> {
-        let mut val = parse_factor(tokens, pos)?;
+    fn parse_term(tokens: &[Token], pos: &mut usize, resolve: Resolve) -> Result<f64, String> {
+        let mut val = parse_factor(tokens, pos, resolve)?;
         while *pos < tokens.len() {
             match tokens[*pos] {
                 Token::Mul => {
                     *pos += 1;
-                    val *= parse_factor(tokens, pos)?;
+                    val *= parse_factor(tokens, pos, resolve)?;
                 },
                 Token::Div => {
                     *pos += 1;
-                    val /= parse_factor(tokens, pos)?;
+                    val /= parse_factor(tokens, pos, resolve)?;
+                },
+                Token::Mod => {
+                    *pos += 1;
+                    val %= parse_factor(tokens, pos, resolve)?;
                 },
                 _ => break,
             }
         }
         Ok(val)
     }
-    fn parse_factor(tokens: &[Token], pos: &mut usize) -> Result<f64, String> {
+    fn parse_factor(tokens: &[Token], pos: &mut usize, resolve: Resolve) -> Result<f64, String> {
         match tokens.get(*pos) {
             Some(Token::Number(n)) => {
                 *pos += 1;
                 Ok(*n)
             },
+            Some(Token::Ident(name)) => {
+                *pos += 1;
+                resolve(name).ok_or_else(|| format!("Unknown identifier: {}", name))
+            },
             Some(Token::LParen) => {
                 *pos += 1;
-                let val = parse_expr(tokens, pos)?;
+                let val = parse_expr(tokens, pos, resolve)?;
                 if let Some(Token::RParen) = tokens.get(*pos) {
                     *pos += 1;
                     Ok(val)
@@ -104,12 +172,20 @@ pub fn eval_arithmetic(expr: &str) -> Result<f64, String> {
             },
             Some(Token::Minus) => {
                 *pos += 1;
-                Ok(-parse_factor(tokens, pos)?)
+                Ok(-parse_factor(tokens, pos, resolve)?)
+            },
+            Some(Token::Plus) => {
+                *pos += 1;
+                parse_factor(tokens, pos, resolve)
             },
             _ => Err("Unexpected token".to_string()),
         }
     }
-    parse_expr(&tokens, &mut pos)
+    let val = parse_expr(&tokens, &mut pos, &resolve)?;
+    if pos < tokens.len() {
+        return Err(format!("Unexpected token: {:?}", tokens[pos]));
+    }
+    Ok(val)
 }
 
 
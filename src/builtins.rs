@@ -6,45 +6,103 @@ use std::collections::HashMap;
 use std::string::ToString;
 
 pub mod builtin {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod ai;
+    pub mod collection;
     pub mod commands;
     pub mod context_ops;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod control;
+    pub mod crypto;
     pub mod csv;
+    pub mod date;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod email;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod exec;
+    pub mod file;
     #[cfg(not(target_arch = "wasm32"))]
     pub mod data_source;
     pub mod json;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod queue;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod record_store;
     pub mod logger;
     pub mod memory;
     #[cfg(not(target_arch = "wasm32"))]
     pub mod mysql;
+    pub mod object;
     pub mod parse_json;
     #[cfg(not(target_arch = "wasm32"))]
+    pub mod pool_stats;
+    #[cfg(not(target_arch = "wasm32"))]
     pub mod postgres;
+    pub mod regex;
+    pub mod render;
     pub mod respond;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod transaction;
     pub mod validate;
+    pub mod vector;
     pub mod function;
     #[cfg(not(target_arch = "wasm32"))]
+    pub mod webhook;
+    #[cfg(not(target_arch = "wasm32"))]
     pub mod ws;
 }
 pub mod path_utils;
 
 use crate::builtins::builtin::commands::builtin_append;
 use crate::builtins::builtin::memory::{builtin_clear_memory, builtin_del_memory, builtin_get_memory, builtin_set_memory};
-use crate::core::AppState;
+use crate::core::tokenizer::unquote;
+use crate::core::{step, AppState};
 use crate::util::{json_to_xml, log, LogLevel};
+use builtin::collection::{builtin_collection_method, builtin_paginate};
 use builtin::csv::{builtin_csv_append, builtin_csv_read, builtin_csv_write};
+use builtin::crypto::{
+    builtin_base64_decode, builtin_base64_encode, builtin_hmac, builtin_hmac_verify, builtin_sha256,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::crypto::{builtin_jwt_sign, builtin_jwt_verify};
+use builtin::date::{builtin_date_add, builtin_date_format, builtin_date_parse, builtin_now};
 #[cfg(not(target_arch = "wasm32"))]
 use builtin::data_source::builtin_data_source;
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::email::builtin_email_send;
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::ai::{builtin_ai, builtin_ai_embed};
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::exec::builtin_exec;
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::queue::builtin_enqueue;
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::webhook::builtin_emit_event;
+#[cfg(not(target_arch = "wasm32"))]
+use builtin::control;
+use builtin::file::{builtin_file_append, builtin_file_list, builtin_file_read, builtin_file_write};
 use builtin::json::builtin_json_read;
+use builtin::object::{builtin_obj_keys, builtin_obj_merge, builtin_obj_omit, builtin_obj_pick, builtin_obj_set};
+use builtin::regex::{builtin_regex_capture, builtin_regex_match, builtin_regex_replace};
+use builtin::render::builtin_render;
 use builtin::logger::builtin_log;
 use builtin::parse_json::builtin_parse_json;
-use builtin::respond::builtin_respond;
+use builtin::respond::{
+    builtin_respond, lookup_value, negotiate_format, serialize_as, set_content_type, split_format_suffix,
+};
 use builtin::validate::builtin_validate;
-use crate::builtins::builtin::function::{builtin_func, invoke_func};
+use builtin::vector::{builtin_vector_delete, builtin_vector_search, builtin_vector_upsert};
+use crate::builtins::builtin::function::{builtin_call, builtin_func, function_return, invoke_func};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::builtins::builtin::ws::{builtin_ws_broadcast, builtin_ws_id, builtin_ws_send};
 use crate::builtins::builtin::context_ops::{builtin_delete, builtin_is_set};
 
 pub const LAST_EXEC_RESULT: &str = "___last_exec_result___";
+/// Headers a step sequence wants set on its HTTP response (e.g. `content-type` from `respond ... as xml`).
+pub const RESPONSE_HEADERS: &str = "___response_headers___";
+/// The Content-Type implied by the value a step sequence responded with; a route's
+/// `content_type` and headers set by the steps take precedence over it.
+pub const RESPONSE_CONTENT_TYPE: &str = "___response_content_type___";
 
 pub type Context = HashMap<String, JsonValue>;
 
@@ -60,15 +118,78 @@ pub fn is_builtin(name: &str) -> bool {
     #[cfg(target_arch = "wasm32")]
     let db_builtins: [&str; 0] = [];
 
+    #[cfg(not(target_arch = "wasm32"))]
+    let control_builtins = ["sleep", "retry", "timeout", "enqueue"];
+    #[cfg(target_arch = "wasm32")]
+    let control_builtins: [&str; 0] = [];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let jwt_builtins = ["jwt.sign", "jwt.verify"];
+    #[cfg(target_arch = "wasm32")]
+    let jwt_builtins: [&str; 0] = [];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let email_builtins = ["email.send"];
+    #[cfg(target_arch = "wasm32")]
+    let email_builtins: [&str; 0] = [];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let webhook_builtins = ["emit-event"];
+    #[cfg(target_arch = "wasm32")]
+    let webhook_builtins: [&str; 0] = [];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let process_builtins = ["exec"];
+    #[cfg(target_arch = "wasm32")]
+    let process_builtins: [&str; 0] = [];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let ai_builtins = ["ai", "ai.embed"];
+    #[cfg(target_arch = "wasm32")]
+    let ai_builtins: [&str; 0] = [];
+
     let core_builtins = [
-        "func", "log", "respond", "parse-json", "validate", "csv.read", "csv.write",
+        "func", "call", "log", "respond", "parse-json", "validate", "csv.read", "csv.write",
         "csv.append", "json.read", "load-rune", "set-memory",
         "memory.set", "get-memory", "memory.get", "clear-memory", "memory.clear",
         "del-memory", "memory.del", "append", "memory.append", "delete", "is-set",
-        "return", "#"
+        "return", "now", "date.format", "date.parse", "date.add", "crypto.sha256", "crypto.hmac",
+        "crypto.hmac_verify", "base64.encode", "base64.decode", "regex.match", "regex.capture",
+        "regex.replace", "paginate", "obj.merge", "obj.pick", "obj.omit", "obj.keys", "obj.set", "render", "file.read", "file.write", "file.append", "file.list",
+        "vector.upsert", "vector.search", "vector.delete", "#"
     ];
 
-    core_builtins.contains(&name) || ws_builtins.contains(&name) || db_builtins.contains(&name)
+    core_builtins.contains(&name)
+        || ws_builtins.contains(&name)
+        || db_builtins.contains(&name)
+        || control_builtins.contains(&name)
+        || jwt_builtins.contains(&name)
+        || email_builtins.contains(&name)
+        || webhook_builtins.contains(&name)
+        || process_builtins.contains(&name)
+        || ai_builtins.contains(&name)
+}
+
+/// The text of a builtin argument: a context value (objects and lists as compact JSON) or
+/// the argument itself. Numeric literals keep their original spelling.
+pub fn arg_text(ctx: &Context, arg: &str) -> String {
+    if arg.parse::<f64>().is_ok() {
+        return arg.to_string();
+    }
+    match crate::core::resolve_path(ctx, arg, None) {
+        Some(JsonValue::String(s)) => s,
+        Some(JsonValue::Null) | None => arg.to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Stores a builtin's value in its assignment target (if any) and as the last result.
+pub fn store_result(ctx: &mut Context, assign_to: Option<&str>, value: JsonValue) -> BuiltinResult {
+    if let Some(var) = assign_to {
+        ctx.insert(var.to_string(), value.clone());
+    }
+    ctx.insert(LAST_EXEC_RESULT.to_string(), value);
+    BuiltinResult::Ok
 }
 
 #[derive(Debug)]
@@ -78,6 +199,18 @@ pub enum BuiltinResult {
     Error(String),
 }
 
+impl BuiltinResult {
+    /// Responds with a context value: strings are sent as text, anything else as JSON.
+    pub fn respond_value(ctx: &mut Context, status: u16, value: &JsonValue) -> Self {
+        let (body, content_type) = match value {
+            JsonValue::String(s) => (s.clone(), "text/plain; charset=utf-8"),
+            other => (other.to_string(), "application/json"),
+        };
+        ctx.insert(RESPONSE_CONTENT_TYPE.to_string(), content_type.into());
+        BuiltinResult::Respond(status, body)
+    }
+}
+
 // --- Builtin function declarations ---
 pub fn builtin_load_rune(
     args: &[String],
@@ -117,6 +250,44 @@ pub fn builtin_load_rune(
     }
 }
 
+/// Replaces argument groups such as `(a + b)` with their values, returning the new
+/// arguments and the temporaries holding list and object results. Groups that read `it`
+/// are left for builtins that evaluate them per item, and groups that are not compound
+/// expressions stay as written.
+async fn eval_paren_args(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+) -> Option<Result<(Vec<String>, Vec<String>), String>> {
+    let expr_of = |arg: &String| {
+        Some(arg)
+            .filter(|a| a.starts_with('(') && a.ends_with(')'))
+            .and_then(|a| crate::core::expr::parse(a).ok())
+            .filter(|e| e.is_compound() && !e.mentions_it())
+    };
+    if !args.iter().any(|arg| expr_of(arg).is_some()) {
+        return None;
+    }
+    let mut temps = Vec::new();
+    let mut evaluated = Vec::with_capacity(args.len());
+    for arg in args {
+        let token = match expr_of(arg) {
+            Some(e) => match e.eval(app_state, ctx).await {
+                Ok(value) => crate::core::expr::value_token(ctx, value, &mut temps),
+                Err(err) => {
+                    for temp in temps {
+                        ctx.remove(&temp);
+                    }
+                    return Some(Err(err));
+                }
+            },
+            None => arg.clone(),
+        };
+        evaluated.push(token);
+    }
+    Some(Ok((evaluated, temps)))
+}
+
 // --- Main dispatcher ---
 pub async fn call_builtin(
     name: &str,
@@ -125,46 +296,31 @@ pub async fn call_builtin(
     app_state: &AppState,
     assign_to: Option<&str>,
 ) -> BuiltinResult {
-    // Preprocess args: combine quoted strings and remove surrounding quotes
-    let mut processed_args = Vec::new();
-    let mut in_quotes = false;
-    let mut current = String::new();
-    let mut in_inline_object = false;
-
-    for arg in args {
-        if arg == "{" || arg == "[" {
-            in_inline_object = true;
-        } else if arg == "}" || arg == "]" {
-            in_inline_object = false;
+    let name = match crate::core::aliases::builtin_alias(name) {
+        Some(alias) => {
+            crate::core::aliases::warn_deprecated("builtin", alias.old, alias.current);
+            alias.current
         }
-        if in_quotes && !in_inline_object {
-            current.push(' ');
-            current.push_str(arg);
-            if arg.ends_with('"') && !arg.ends_with("\\\"") {
-                in_quotes = false;
-                // Remove surrounding quotes
-                let trimmed = current.trim();
-                if trimmed.starts_with('"') && trimmed.ends_with('"') && trimmed.len() >= 2 {
-                    processed_args.push(trimmed[1..trimmed.len() - 1].to_string());
-                } else {
-                    processed_args.push(current.clone());
-                }
-                current.clear();
-            }
-        } else if arg.starts_with('"') && !arg.ends_with('"') && !in_inline_object {
-            in_quotes = true;
-            current = arg.clone();
-        } else if arg.starts_with('"') && arg.ends_with('"') && arg.len() >= 2 && !in_inline_object {
-            // Remove surrounding quotes
-            processed_args.push(arg[1..arg.len() - 1].to_string());
-        } else {
-            processed_args.push(arg.clone());
+        None => name,
+    };
+    // `( ... )` groups are expressions: `respond 200 (price * qty)`
+    if let Some(evaluated) = eval_paren_args(args, ctx, app_state).await {
+        let (evaluated, temps) = match evaluated {
+            Ok(evaluated) => evaluated,
+            Err(e) => return BuiltinResult::Error(e),
+        };
+        let res = Box::pin(call_builtin(name, &evaluated, ctx, app_state, assign_to)).await;
+        for temp in temps {
+            ctx.remove(&temp);
         }
+        return res;
     }
-    if in_quotes && !current.is_empty() {
-        // Unclosed quote, push as is
-        processed_args.push(current);
-    }
+    // Args arrive tokenized by `core::tokenizer`; quoted strings become their contents
+    let raw_args = args;
+    let processed_args: Vec<String> = args
+        .iter()
+        .map(|arg| unquote(arg).unwrap_or_else(|| arg.clone()))
+        .collect();
     let args = &processed_args;
 
     // Handle method-style calls like "users.find", "users.find-index", "users.remove"
@@ -430,7 +586,13 @@ pub async fn call_builtin(
                 }
                 return BuiltinResult::Ok;
             }
-            _ => {}
+            _ => {
+                // List methods also work on nested lists: `body.items.filter it.qty > 0`
+                let (list_path, list_method) = name.rsplit_once('.').unwrap_or((target, method));
+                if let Some(res) = builtin_collection_method(list_path, list_method, raw_args, ctx, assign_to) {
+                    return res;
+                }
+            }
         }
     }
 
@@ -442,10 +604,11 @@ pub async fn call_builtin(
 
     match name {
         "func" => builtin_func(args, ctx).await,
+        "call" => builtin_call(raw_args, ctx, app_state, assign_to).await,
         "log" => builtin_log(args, ctx),
         "respond" => builtin_respond(args, ctx),
         "parse-json" => builtin_parse_json(args, ctx, assign_to),
-        "validate" => builtin_validate(args, ctx, &app_state.schemas),
+        "validate" => builtin_validate(args, ctx, &app_state.schemas, &app_state.doc),
         "csv.read" => builtin_csv_read(args, ctx, assign_to, app_state),
         "csv.write" => builtin_csv_write(args, ctx, app_state),
         "csv.append" => builtin_csv_append(args, ctx, app_state),
@@ -460,6 +623,58 @@ pub async fn call_builtin(
         "append" | "memory.append" => builtin_append(args, assign_to, ctx).await,
         "delete" => builtin_delete(args, ctx),
         "is-set" => builtin_is_set(args, ctx, assign_to),
+        "now" => builtin_now(args, ctx, assign_to),
+        "date.format" => builtin_date_format(args, ctx, assign_to),
+        "date.parse" => builtin_date_parse(args, ctx, assign_to),
+        "date.add" => builtin_date_add(args, ctx, assign_to),
+        "crypto.sha256" => builtin_sha256(args, ctx, assign_to),
+        "crypto.hmac" => builtin_hmac(args, ctx, assign_to),
+        "crypto.hmac_verify" => builtin_hmac_verify(args, ctx, assign_to),
+        "base64.encode" => builtin_base64_encode(args, ctx, assign_to),
+        "base64.decode" => builtin_base64_decode(args, ctx, assign_to),
+        "regex.match" => builtin_regex_match(args, ctx, assign_to),
+        "regex.capture" => builtin_regex_capture(args, ctx, assign_to),
+        "regex.replace" => builtin_regex_replace(args, ctx, assign_to),
+        "paginate" => builtin_paginate(args, ctx, assign_to),
+        "obj.merge" => builtin_obj_merge(raw_args, ctx, assign_to),
+        "obj.pick" => builtin_obj_pick(raw_args, ctx, assign_to),
+        "obj.omit" => builtin_obj_omit(raw_args, ctx, assign_to),
+        "obj.keys" => builtin_obj_keys(raw_args, ctx, assign_to),
+        "obj.set" => builtin_obj_set(raw_args, ctx, assign_to),
+        "render" => builtin_render(raw_args, ctx, app_state, assign_to),
+        "file.read" => builtin_file_read(raw_args, ctx, app_state, assign_to),
+        "file.write" => builtin_file_write(raw_args, ctx, app_state, assign_to),
+        "file.append" => builtin_file_append(raw_args, ctx, app_state, assign_to),
+        "file.list" => builtin_file_list(raw_args, ctx, app_state, assign_to),
+        "vector.upsert" => builtin_vector_upsert(raw_args, ctx, assign_to),
+        "vector.search" => builtin_vector_search(raw_args, ctx, assign_to),
+        "vector.delete" => builtin_vector_delete(raw_args, ctx, assign_to),
+        #[cfg(not(target_arch = "wasm32"))]
+        "jwt.sign" => builtin_jwt_sign(args, ctx, assign_to),
+        #[cfg(not(target_arch = "wasm32"))]
+        "jwt.verify" => builtin_jwt_verify(args, ctx, assign_to),
+        #[cfg(not(target_arch = "wasm32"))]
+        "email.send" => builtin_email_send(args, ctx, app_state, assign_to).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        "emit-event" => builtin_emit_event(args, ctx, app_state, assign_to),
+        #[cfg(not(target_arch = "wasm32"))]
+        "enqueue" => builtin_enqueue(args, ctx, app_state, assign_to),
+        #[cfg(not(target_arch = "wasm32"))]
+        "exec" => builtin_exec(raw_args, ctx, app_state, assign_to).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        "ai" => builtin_ai(raw_args, ctx, app_state, assign_to).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        "ai.embed" => builtin_ai_embed(raw_args, ctx, app_state, assign_to).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        "sleep" => control::builtin_sleep(args).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        "retry" | "timeout" => match step::split_inline_block(args) {
+            (opts, Some(steps)) if name == "retry" => {
+                control::builtin_retry(opts, &step::compile(&steps), ctx, app_state).await
+            }
+            (opts, Some(steps)) => control::builtin_timeout(opts, &step::compile(&steps), ctx, app_state).await,
+            (_, None) => BuiltinResult::Error(format!("{} requires a {{ ... }} block", name)),
+        },
         #[cfg(not(target_arch = "wasm32"))]
         "ws.id" => builtin_ws_id(ctx, assign_to).await,
         #[cfg(not(target_arch = "wasm32"))]
@@ -467,38 +682,25 @@ pub async fn call_builtin(
         #[cfg(not(target_arch = "wasm32"))]
         "ws.broadcast" | "broadcast-websocket" => builtin_ws_broadcast(args, ctx).await,
         "return" => {
-            if args.is_empty() {
+            if let Some(result) = function_return(raw_args, ctx) {
+                result
+            } else if args.is_empty() {
                 log(LogLevel::Error, "return: missing return value");
                 BuiltinResult::Error("missing value".to_string())
             } else {
-                if args.len() >= 3 && &args[1] == "as" {
-                    let output_type = &args[2];
-                    if output_type == "xml" {
-                        let val = ctx
-                            .get(args[0].as_str())
-                            .map_or("".to_string(), |v| json_to_xml(v, "root"));
-                        return BuiltinResult::Respond(200, val);
-                    } else {
-                        return BuiltinResult::Respond(
-                            400,
-                            format!("return: unsupported output type {}", output_type),
-                        );
-                    }
-                }
-
-                let val = ctx.get(args[0].as_str());
-                match val {
-                    Some(v) => {
-                        if v.is_object() || v.is_array() {
-                            BuiltinResult::Respond(
-                                200,
-                                serde_json::to_string(v).unwrap_or_default(),
-                            )
-                        } else {
-                            BuiltinResult::Respond(200, v.to_string())
+                let (args, format) = split_format_suffix(args);
+                let format = format.or_else(|| negotiate_format(ctx));
+                let val = lookup_value(ctx, &args[0]);
+                match (val, format) {
+                    (Some(v), Some(format)) => match serialize_as(&v, &format) {
+                        Ok((body, content_type)) => {
+                            set_content_type(ctx, &content_type);
+                            BuiltinResult::Respond(200, body)
                         }
-                    }
-                    None => BuiltinResult::Respond(200, "".to_string()),
+                        Err(e) => BuiltinResult::Respond(400, format!("return: {}", e)),
+                    },
+                    (Some(v), None) => BuiltinResult::respond_value(ctx, 200, &v),
+                    (None, _) => BuiltinResult::Respond(200, "".to_string()),
                 }
             }
         }
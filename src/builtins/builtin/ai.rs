@@ -0,0 +1,118 @@
+//! The `ai` builtin, for enriching requests with a model's reply:
+//!
+//! ```rune
+//! @Ai
+//! provider = ollama
+//! timeout = 20s
+//!
+//! @Route/POST /notes/summary
+//! run:
+//!     parse-json
+//!     summary = ai "Summarize in one sentence: ${body.text}" model=phi4
+//!     respond 200 summary
+//! ```
+//!
+//! The provider, model, URL and key come from the `@Ai` section or the environment, as
+//! for `vectrune ai` (see `core::ai`). `timeout=` caps the whole call, retries included;
+//! it defaults to the `@Ai` timeout.
+//!
+//! `ai.embed` turns text into an embedding with the `@Ai` `embedding_model`, for the
+//! `vector.*` builtins.
+
+use crate::builtins::builtin::control::parse_duration;
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+use crate::core::ai::AiConfig;
+use crate::core::tokenizer::unquote;
+use crate::core::AppState;
+use serde_json::Value as JsonValue;
+
+/// `ai <prompt> [model=<name>] [timeout=<duration>] [json=true]` — sends the prompt and
+/// stores the reply. A quoted prompt has its `{path}` and `${path}` placeholders filled
+/// from the context; a bare one names a context value. With `json=true` the reply is
+/// parsed as JSON.
+pub async fn builtin_ai(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some((prompt, options)) = args.split_first() else {
+        return BuiltinResult::Error("ai requires a prompt".to_string());
+    };
+    let prompt = match unquote(prompt) {
+        Some(template) => expand_log_message(&template.replace("${", "{"), ctx),
+        None => arg_text(ctx, prompt),
+    };
+    let (mut model, mut timeout, mut json) = (None, None, false);
+    for option in options {
+        let (key, value) = option.split_once('=').unwrap_or((option.as_str(), ""));
+        let value = unquote(value).unwrap_or_else(|| value.to_string());
+        match key {
+            "model" => model = Some(arg_text(ctx, &value)),
+            "timeout" => match parse_duration(&value) {
+                Some(limit) => timeout = Some(limit),
+                None => return BuiltinResult::Error(format!("ai: invalid timeout '{}'", value)),
+            },
+            "json" => json = value != "false",
+            _ => return BuiltinResult::Error(format!("ai: unknown option '{}'", option)),
+        }
+    }
+
+    let mut config = match AiConfig::from_doc(&app_state.doc, model.as_deref()) {
+        Ok(config) => config,
+        Err(e) => return BuiltinResult::Error(format!("ai: {}", e)),
+    };
+    let limit = timeout.unwrap_or(config.timeout);
+    config.timeout = limit;
+    let reply = match tokio::time::timeout(limit, config.complete(&prompt, json, |_| {})).await {
+        Ok(Ok(reply)) => reply.trim().to_string(),
+        Ok(Err(e)) => return BuiltinResult::Error(format!("ai: {}", e)),
+        Err(_) => return BuiltinResult::Error(format!("ai: timed out after {:?}", limit)),
+    };
+    let value = if json {
+        match serde_json::from_str(&reply) {
+            Ok(value) => value,
+            Err(e) => return BuiltinResult::Error(format!("ai: the reply is not JSON: {}", e)),
+        }
+    } else {
+        JsonValue::String(reply)
+    };
+    store_result(ctx, assign_to, value)
+}
+
+/// `ai.embed <text> [model=<name>] [timeout=<duration>]` — stores the embedding of the
+/// text (a context value or quoted text) as a list of numbers.
+pub async fn builtin_ai_embed(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some((text, options)) = args.split_first() else {
+        return BuiltinResult::Error("ai.embed requires text".to_string());
+    };
+    let text = match unquote(text) {
+        Some(template) => expand_log_message(&template.replace("${", "{"), ctx),
+        None => arg_text(ctx, text),
+    };
+    let mut config = match AiConfig::from_doc(&app_state.doc, None) {
+        Ok(config) => config,
+        Err(e) => return BuiltinResult::Error(format!("ai.embed: {}", e)),
+    };
+    for option in options {
+        match option.split_once('=') {
+            Some(("model", model)) => config.embedding_model = arg_text(ctx, model),
+            Some(("timeout", spec)) => match parse_duration(spec) {
+                Some(limit) => config.timeout = limit,
+                None => return BuiltinResult::Error(format!("ai.embed: invalid timeout '{}'", spec)),
+            },
+            _ => return BuiltinResult::Error(format!("ai.embed: unknown option '{}'", option)),
+        }
+    }
+    match tokio::time::timeout(config.timeout, config.embed(&text)).await {
+        Ok(Ok(embedding)) => store_result(ctx, assign_to, JsonValue::from(embedding)),
+        Ok(Err(e)) => BuiltinResult::Error(format!("ai.embed: {}", e)),
+        Err(_) => BuiltinResult::Error(format!("ai.embed: timed out after {:?}", config.timeout)),
+    }
+}
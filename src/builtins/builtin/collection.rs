@@ -0,0 +1,332 @@
+//! List methods called on a context array, e.g. `adults = users.filter it.age > 18`.
+//!
+//! Expressions see each item as `it`. `push` and `insert` change the list in place; the
+//! other methods return a new value. The same helpers back the `|>` pipe stages.
+
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::tokenizer::unquote;
+use crate::core::{
+    eval_condition, json_value_as_f64, mutate_path, number_to_json, parse_object_literal, resolve_index_expr,
+    resolve_path,
+};
+use serde_json::{Map, Value as JsonValue};
+use std::cmp::Ordering;
+
+/// Methods handled here, in addition to `find`, `find-index`, `max` and `remove`.
+pub const METHODS: &[&str] = &[
+    "filter", "map", "sort-by", "group-by", "sum", "count", "push", "insert", "slice", "unique", "reverse",
+    "contains",
+];
+
+const COMPARISONS: &[&str] = &["==", "!=", ">", "<", " contains "];
+
+pub fn is_truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(b) => *b,
+        JsonValue::Number(n) => n.as_f64().map(|f| f != 0.0).unwrap_or(false),
+        JsonValue::String(s) => !s.is_empty(),
+        JsonValue::Array(a) => !a.is_empty(),
+        JsonValue::Object(_) => true,
+    }
+}
+
+/// Orders numbers numerically and everything else by its string form; nulls sort first.
+pub fn compare_values(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Null, _) => Ordering::Less,
+        (_, JsonValue::Null) => Ordering::Greater,
+        (JsonValue::Number(x), JsonValue::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (JsonValue::String(x), JsonValue::String(y)) => x.cmp(y),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+/// Whether `item` passes `cond`: a comparison such as `it.age > 18`, or a path whose
+/// value is truthy (`it.active`).
+pub fn item_matches(ctx: &Context, cond: &str, item: &JsonValue) -> bool {
+    if COMPARISONS.iter().any(|op| cond.contains(op)) {
+        eval_condition(ctx, cond, Some(item))
+    } else {
+        resolve_path(ctx, cond, Some(item)).map(|v| is_truthy(&v)).unwrap_or(false)
+    }
+}
+
+pub fn filter_items(ctx: &Context, cond: &str, items: Vec<JsonValue>) -> Vec<JsonValue> {
+    items.into_iter().filter(|item| item_matches(ctx, cond, item)).collect()
+}
+
+pub fn map_items(ctx: &Context, expr: &str, items: &[JsonValue]) -> Vec<JsonValue> {
+    items
+        .iter()
+        .map(|item| resolve_index_expr(ctx, expr, Some(item)).unwrap_or(JsonValue::Null))
+        .collect()
+}
+
+/// Sorts by `[expr] [asc|desc]`; without an expression the items themselves are compared.
+pub fn sort_items(ctx: &Context, spec: &str, mut items: Vec<JsonValue>) -> Vec<JsonValue> {
+    let (key, desc) = match spec.strip_suffix("desc") {
+        Some(key) if key.is_empty() || key.ends_with(char::is_whitespace) => (key.trim(), true),
+        _ => (spec.strip_suffix("asc").map(str::trim).unwrap_or(spec), false),
+    };
+    let sort_key = |item: &JsonValue| {
+        if key.is_empty() {
+            item.clone()
+        } else {
+            resolve_path(ctx, key, Some(item)).unwrap_or(JsonValue::Null)
+        }
+    };
+    items.sort_by(|a, b| compare_values(&sort_key(a), &sort_key(b)));
+    if desc {
+        items.reverse();
+    }
+    items
+}
+
+/// Groups items into an object keyed by the text of `expr`; missing keys group under `null`.
+pub fn group_items(ctx: &Context, expr: &str, items: Vec<JsonValue>) -> Map<String, JsonValue> {
+    let mut groups = Map::new();
+    for item in items {
+        let key = match resolve_index_expr(ctx, expr, Some(&item)) {
+            Some(JsonValue::String(s)) => s,
+            Some(other) => other.to_string(),
+            None => "null".to_string(),
+        };
+        if let JsonValue::Array(group) = groups.entry(key).or_insert_with(|| JsonValue::Array(Vec::new())) {
+            group.push(item);
+        }
+    }
+    groups
+}
+
+/// Sums `expr` (or the items themselves) over the list. Numeric strings, as read from
+/// CSV, count as numbers and booleans as 1 or 0; anything else is skipped.
+pub fn sum_items(ctx: &Context, expr: &str, items: &[JsonValue]) -> JsonValue {
+    let total: f64 = items
+        .iter()
+        .filter_map(|item| {
+            let value = if expr.is_empty() {
+                Some(item.clone())
+            } else {
+                resolve_index_expr(ctx, expr, Some(item))
+            };
+            value.as_ref().and_then(json_value_as_f64)
+        })
+        .sum();
+    number_to_json(total)
+}
+
+/// A value argument: a quoted string, an inline object (JSON or `{ key: value }`), a context
+/// path or literal, or inline JSON; anything else is taken as a bare string.
+pub(crate) fn value_arg(ctx: &mut Context, token: &str) -> JsonValue {
+    if let Some(s) = unquote(token) {
+        return JsonValue::String(s);
+    }
+    if token.starts_with('{') {
+        return serde_json::from_str(token).unwrap_or_else(|_| JsonValue::Object(parse_object_literal(ctx, token)));
+    }
+    resolve_path(ctx, token, None)
+        .or_else(|| serde_json::from_str(token).ok())
+        .unwrap_or_else(|| JsonValue::String(token.to_string()))
+}
+
+/// Equality with numbers and numeric strings (as read from CSV) treated alike.
+fn loosely_equal(a: &JsonValue, b: &JsonValue) -> bool {
+    a == b
+        || match (a, b) {
+            (JsonValue::Number(_), JsonValue::String(_)) | (JsonValue::String(_), JsonValue::Number(_)) => {
+                matches!((json_value_as_f64(a), json_value_as_f64(b)), (Some(x), Some(y)) if x == y)
+            }
+            _ => false,
+        }
+}
+
+/// Drops repeated items (or items with a repeated `expr` key), keeping the first.
+pub fn unique_items(ctx: &Context, expr: &str, items: Vec<JsonValue>) -> Vec<JsonValue> {
+    let mut seen: Vec<JsonValue> = Vec::new();
+    items
+        .into_iter()
+        .filter(|item| {
+            let key = if expr.is_empty() {
+                item.clone()
+            } else {
+                resolve_index_expr(ctx, expr, Some(item)).unwrap_or(JsonValue::Null)
+            };
+            let fresh = !seen.contains(&key);
+            if fresh {
+                seen.push(key);
+            }
+            fresh
+        })
+        .collect()
+}
+
+/// `push` and `insert` change the list in place and also return it.
+fn mutate_list(
+    ctx: &mut Context,
+    target: &str,
+    method: &str,
+    args: &[String],
+    mut items: Vec<JsonValue>,
+) -> Result<JsonValue, String> {
+    match method {
+        "push" => {
+            if args.is_empty() {
+                return Err(format!("{}.push requires a value", target));
+            }
+            for token in args {
+                let value = value_arg(ctx, token);
+                items.push(value);
+            }
+        }
+        _ => {
+            if args.len() < 2 {
+                return Err(format!("{}.insert requires an index and a value", target));
+            }
+            let index = resolve_index_expr(ctx, &args[0], None)
+                .as_ref()
+                .and_then(json_value_as_f64)
+                .filter(|i| i.fract() == 0.0 && *i >= 0.0 && *i as usize <= items.len())
+                .ok_or_else(|| format!("{}.insert: index '{}' is out of range", target, args[0]))?;
+            let value = value_arg(ctx, &args[1]);
+            items.insert(index as usize, value);
+        }
+    }
+    let list = JsonValue::Array(items);
+    mutate_path(ctx, target, list.clone())?;
+    Ok(list)
+}
+
+/// `<list>.<method> [expr]` for the methods in [`METHODS`], where `<list>` is a context
+/// path such as `users` or `body.items`. `args` are the step's raw
+/// tokens, so quoted literals in conditions (`it.city == "Paris"`) stay literals.
+/// Returns `None` for other methods.
+pub fn builtin_collection_method(
+    target: &str,
+    method: &str,
+    args: &[String],
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> Option<BuiltinResult> {
+    if !METHODS.contains(&method) {
+        return None;
+    }
+    let Some(JsonValue::Array(items)) = resolve_path(ctx, target, None) else {
+        return Some(BuiltinResult::Error(format!("{}.{}: `{}` is not a list", target, method, target)));
+    };
+    if matches!(method, "push" | "insert") {
+        return Some(match mutate_list(ctx, target, method, args, items) {
+            Ok(list) => store_result(ctx, assign_to, list),
+            Err(e) => BuiltinResult::Error(e),
+        });
+    }
+    let expr = args.join(" ");
+    let expr = expr.trim();
+    if expr.is_empty() && matches!(method, "filter" | "map" | "group-by" | "contains") {
+        return Some(BuiltinResult::Error(format!("{}.{} requires an expression", target, method)));
+    }
+    let value = match method {
+        "filter" => JsonValue::Array(filter_items(ctx, expr, items)),
+        "map" => JsonValue::Array(map_items(ctx, expr, &items)),
+        "sort-by" => JsonValue::Array(sort_items(ctx, expr, items)),
+        "group-by" => JsonValue::Object(group_items(ctx, expr, items)),
+        "sum" => sum_items(ctx, expr, &items),
+        "slice" => {
+            let bounds = args.iter().map(String::as_str).collect::<Vec<_>>();
+            let (start, end) = (bounds.first().copied().unwrap_or(""), bounds.get(1).copied().unwrap_or(""));
+            match resolve_path(ctx, &format!("{}[{}:{}]", target, start, end), None) {
+                Some(slice) => slice,
+                None => {
+                    return Some(BuiltinResult::Error(format!("{}.slice: invalid bounds '{}'", target, expr)));
+                }
+            }
+        }
+        "unique" => JsonValue::Array(unique_items(ctx, expr, items)),
+        "reverse" => JsonValue::Array(items.into_iter().rev().collect()),
+        "contains" => {
+            let needle = value_arg(ctx, expr);
+            JsonValue::Bool(items.iter().any(|item| loosely_equal(item, &needle)))
+        }
+        _ if expr.is_empty() => JsonValue::from(items.len()),
+        _ => JsonValue::from(items.iter().filter(|item| item_matches(ctx, expr, item)).count()),
+    };
+    Some(store_result(ctx, assign_to, value))
+}
+
+/// Page size used by `paginate` when none is given.
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+
+/// A positive whole number from a page or size argument; `Ok(None)` when it is missing or null.
+fn page_number(ctx: &Context, arg: Option<&String>, what: &str) -> Result<Option<u64>, String> {
+    let Some(arg) = arg else {
+        return Ok(None);
+    };
+    match resolve_path(ctx, arg, None) {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(value) => json_value_as_f64(&value)
+            .filter(|n| n.fract() == 0.0 && *n >= 1.0)
+            .map(|n| Some(n as u64))
+            .ok_or_else(|| format!("paginate: {} must be a positive whole number", what)),
+    }
+}
+
+/// `paginate <items> [page] [size] [default_size]` — one page of a list as
+/// `{data, page, size, total, next}`, where `next` is the following page number or null.
+/// Pages start at 1. A missing or null page is 1 and a missing or null size is
+/// `default_size` (20 unless given); an invalid page or size responds 400.
+pub fn builtin_paginate(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(source) = args.first() else {
+        return BuiltinResult::Error("paginate requires a list".to_string());
+    };
+    let Some(JsonValue::Array(items)) = resolve_path(ctx, source, None) else {
+        return BuiltinResult::Error(format!("paginate: `{}` is not a list", source));
+    };
+    let page_and_size = page_number(ctx, args.get(1), "page").and_then(|page| {
+        let default_size = page_number(ctx, args.get(3), "default size")?.unwrap_or(DEFAULT_PAGE_SIZE);
+        Ok((page.unwrap_or(1), page_number(ctx, args.get(2), "size")?.unwrap_or(default_size)))
+    });
+    let (page, size) = match page_and_size {
+        Ok(found) => found,
+        Err(e) => return BuiltinResult::Respond(400, e),
+    };
+    let total = items.len() as u64;
+    let start = (page - 1).saturating_mul(size).min(total) as usize;
+    let data: Vec<JsonValue> = items.into_iter().skip(start).take(size as usize).collect();
+    let next = if page.saturating_mul(size) < total {
+        JsonValue::from(page + 1)
+    } else {
+        JsonValue::Null
+    };
+    let envelope = serde_json::json!({
+        "data": data,
+        "page": page,
+        "size": size,
+        "total": total,
+        "next": next,
+    });
+    store_result(ctx, assign_to, envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn groups_and_sums_csv_style_values() {
+        let ctx = Context::new();
+        let rows = vec![
+            json!({"city": "Oslo", "price": "2.5"}),
+            json!({"city": "Rome", "price": 4}),
+            json!({"city": "Oslo", "price": null}),
+        ];
+        let groups = group_items(&ctx, "it.city", rows.clone());
+        assert_eq!(groups["Oslo"].as_array().map(Vec::len), Some(2));
+        assert_eq!(sum_items(&ctx, "it.price", &rows), json!(6.5));
+        assert_eq!(sum_items(&ctx, "", &[json!(1), json!(2)]), json!(3));
+    }
+}
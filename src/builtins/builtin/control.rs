@@ -0,0 +1,135 @@
+use crate::builtins::{BuiltinResult, Context};
+use crate::core::errors::STEP_ERROR;
+use crate::core::step::Step;
+use crate::core::{run_compiled_steps, AppState};
+use crate::util::{log, LogLevel};
+use serde_json::Value as JsonValue;
+use std::time::Duration;
+
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Parses a duration like `500ms`, `2s`, `1.5s`, `1m` or `1h`. A bare number is milliseconds.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (num, scale) = if let Some(n) = s.strip_suffix("ms") {
+        (n, 0.001)
+    } else if let Some(n) = s.strip_suffix('s') {
+        (n, 1.0)
+    } else if let Some(n) = s.strip_suffix('m') {
+        (n, 60.0)
+    } else if let Some(n) = s.strip_suffix('h') {
+        (n, 3600.0)
+    } else {
+        (s, 0.001)
+    };
+    let value: f64 = num.trim().parse().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(value * scale))
+}
+
+/// Runs nested steps; a builtin error inside them is returned as `Error` again.
+pub(crate) async fn run_block(state: &AppState, steps: &[Step], ctx: &mut Context) -> BuiltinResult {
+    let response = run_compiled_steps(state, steps, ctx).await;
+    if let Some(JsonValue::String(err)) = ctx.remove(STEP_ERROR) {
+        return BuiltinResult::Error(err);
+    }
+    match response {
+        Some((code, body)) => BuiltinResult::Respond(code, body),
+        None => BuiltinResult::Ok,
+    }
+}
+
+fn is_failure(result: &BuiltinResult) -> bool {
+    match result {
+        BuiltinResult::Error(_) => true,
+        BuiltinResult::Respond(code, _) => *code >= 500,
+        BuiltinResult::Ok => false,
+    }
+}
+
+pub async fn builtin_sleep(args: &[String]) -> BuiltinResult {
+    let Some(duration) = args.first().and_then(|a| parse_duration(a)) else {
+        return BuiltinResult::Error("sleep requires a duration like 500ms or 2s".to_string());
+    };
+    tokio::time::sleep(duration).await;
+    BuiltinResult::Ok
+}
+
+/// `retry <attempts> [backoff=fixed|exp] [delay=<duration>]` — reruns the block while
+/// it fails (a builtin error or a 5xx response), waiting between attempts.
+pub async fn builtin_retry(
+    args: &[String],
+    steps: &[Step],
+    ctx: &mut Context,
+    state: &AppState,
+) -> BuiltinResult {
+    let attempts = match args.first().map(|a| a.parse::<u32>()) {
+        Some(Ok(n)) if n > 0 => n,
+        _ => return BuiltinResult::Error("retry requires a positive attempt count".to_string()),
+    };
+    let mut exponential = false;
+    let mut delay = DEFAULT_RETRY_DELAY;
+    for opt in &args[1..] {
+        match opt.split_once('=') {
+            Some(("backoff", "exp")) => exponential = true,
+            Some(("backoff", "fixed")) => exponential = false,
+            Some(("delay", d)) => match parse_duration(d) {
+                Some(d) => delay = d,
+                None => return BuiltinResult::Error(format!("retry: invalid delay '{}'", d)),
+            },
+            _ => return BuiltinResult::Error(format!("retry: unknown option '{}'", opt)),
+        }
+    }
+
+    let mut attempt = 1;
+    loop {
+        let result = run_block(state, steps, ctx).await;
+        if !is_failure(&result) || attempt >= attempts {
+            return result;
+        }
+        let wait = if exponential {
+            delay.saturating_mul(2u32.saturating_pow(attempt - 1))
+        } else {
+            delay
+        };
+        log(
+            LogLevel::Warn,
+            &format!("retry: attempt {} of {} failed, retrying in {:?}", attempt, attempts, wait),
+        );
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+/// `timeout <duration>` — runs the block, responding 504 if it does not finish in time.
+pub async fn builtin_timeout(
+    args: &[String],
+    steps: &[Step],
+    ctx: &mut Context,
+    state: &AppState,
+) -> BuiltinResult {
+    let Some(duration) = args.first().and_then(|a| parse_duration(a)) else {
+        return BuiltinResult::Error("timeout requires a duration like 500ms or 2s".to_string());
+    };
+    match tokio::time::timeout(duration, run_block(state, steps, ctx)).await {
+        Ok(result) => result,
+        Err(_) => BuiltinResult::Respond(504, format!("timed out after {}", args[0])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("250"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("soon"), None);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn hmac_for(secret: &str, value: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(value.as_bytes());
+    mac
+}
+
+/// Hex HMAC-SHA256 of `value`.
+pub(crate) fn hmac_hex(secret: &str, value: &str) -> String {
+    hex(&hmac_for(secret, value).finalize().into_bytes())
+}
+
+/// `crypto.sha256 <value>` — hex digest.
+pub fn builtin_sha256(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(arg) = args.first() else {
+        return BuiltinResult::Error("crypto.sha256 requires a value".to_string());
+    };
+    let digest = Sha256::digest(arg_text(ctx, arg).as_bytes());
+    store_result(ctx, assign_to, JsonValue::String(hex(&digest)))
+}
+
+/// `crypto.hmac <secret> <value>` — hex HMAC-SHA256.
+pub fn builtin_hmac(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 2 {
+        return BuiltinResult::Error("crypto.hmac requires a secret and a value".to_string());
+    }
+    let mac = hmac_hex(&arg_text(ctx, &args[0]), &arg_text(ctx, &args[1]));
+    store_result(ctx, assign_to, JsonValue::String(mac))
+}
+
+/// `crypto.hmac_verify <secret> <value> <signature>` — constant-time check of a hex
+/// HMAC-SHA256 signature, with or without a `sha256=` prefix (as webhook senders use).
+pub fn builtin_hmac_verify(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 3 {
+        return BuiltinResult::Error("crypto.hmac_verify requires a secret, a value and a signature".to_string());
+    }
+    let signature = arg_text(ctx, &args[2]);
+    let signature = signature.strip_prefix("sha256=").unwrap_or(&signature).to_ascii_lowercase();
+    let valid = from_hex(&signature)
+        .map(|sig| {
+            hmac_for(&arg_text(ctx, &args[0]), &arg_text(ctx, &args[1]))
+                .verify_slice(&sig)
+                .is_ok()
+        })
+        .unwrap_or(false);
+    store_result(ctx, assign_to, JsonValue::Bool(valid))
+}
+
+/// `base64.encode <value>`
+pub fn builtin_base64_encode(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(arg) = args.first() else {
+        return BuiltinResult::Error("base64.encode requires a value".to_string());
+    };
+    let encoded = BASE64.encode(arg_text(ctx, arg));
+    store_result(ctx, assign_to, JsonValue::String(encoded))
+}
+
+/// `base64.decode <value>` — responds 400 when the input is not base64-encoded UTF-8.
+pub fn builtin_base64_decode(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(arg) = args.first() else {
+        return BuiltinResult::Error("base64.decode requires a value".to_string());
+    };
+    match BASE64
+        .decode(arg_text(ctx, arg).trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+    {
+        Some(decoded) => store_result(ctx, assign_to, JsonValue::String(decoded)),
+        None => BuiltinResult::Respond(400, "base64.decode: invalid base64 input".to_string()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use jwt::{builtin_jwt_sign, builtin_jwt_verify};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod jwt {
+    use crate::builtins::builtin::control::parse_duration;
+    use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+    use crate::core::resolve_path;
+    use chrono::Utc;
+    use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+    use serde_json::Value as JsonValue;
+
+    /// `jwt.sign <claims> <secret> [expires_in]` — HS256 token. `claims` is a context
+    /// object or inline JSON; `iat` is added, and `exp` when `expires_in` (e.g. `1h`) is given.
+    pub fn builtin_jwt_sign(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+        if args.len() < 2 {
+            return BuiltinResult::Error("jwt.sign requires claims and a secret".to_string());
+        }
+        let claims = resolve_path(ctx, &args[0], None)
+            .or_else(|| serde_json::from_str(&args[0]).ok());
+        let Some(JsonValue::Object(mut claims)) = claims else {
+            return BuiltinResult::Error(format!("jwt.sign: claims '{}' must be an object", args[0]));
+        };
+        let now = Utc::now().timestamp();
+        claims.entry("iat").or_insert_with(|| JsonValue::from(now));
+        if let Some(expires_in) = args.get(2) {
+            let Some(duration) = parse_duration(expires_in) else {
+                return BuiltinResult::Error(format!("jwt.sign: invalid expiry '{}'", expires_in));
+            };
+            claims.insert("exp".to_string(), JsonValue::from(now + duration.as_secs() as i64));
+        }
+        let secret = arg_text(ctx, &args[1]);
+        match encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())) {
+            Ok(token) => store_result(ctx, assign_to, JsonValue::String(token)),
+            Err(e) => BuiltinResult::Error(format!("jwt.sign: {}", e)),
+        }
+    }
+
+    /// `jwt.verify <token> <secret>` — the token's claims, or a 401 response when the
+    /// signature or expiry is invalid. A `Bearer ` prefix is ignored.
+    pub fn builtin_jwt_verify(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+        if args.len() < 2 {
+            return BuiltinResult::Error("jwt.verify requires a token and a secret".to_string());
+        }
+        let token = arg_text(ctx, &args[0]);
+        let token = token.strip_prefix("Bearer ").unwrap_or(&token).trim();
+        let secret = arg_text(ctx, &args[1]);
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        match decode::<JsonValue>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation) {
+            Ok(data) => store_result(ctx, assign_to, data.claims),
+            Err(e) => BuiltinResult::Respond(401, format!("invalid token: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_matches_known_vector() {
+        // RFC 4231 test case 2
+        let mac = hmac_for("Jefe", "what do ya want for nothing?");
+        assert_eq!(
+            hex(&mac.finalize().into_bytes()),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(from_hex("0aFF"), Some(vec![0x0a, 0xff]));
+        assert_eq!(from_hex("abc"), None);
+    }
+}
@@ -1,21 +1,51 @@
 use crate::builtins::builtin::mysql::{
-    builtin_mysql_query, create_or_reuse_mysql_pool, create_table_mysql,
+    builtin_mysql_query, create_or_reuse_mysql_pool, create_table_mysql, table_columns_mysql,
 };
 use crate::builtins::builtin::postgres::{
     builtin_postgres_query, create_or_reuse_postgres_pool, create_table_columns_string,
-    create_table_postgres,
+    create_table_postgres, table_columns_postgres,
+};
+use crate::builtins::builtin::control::parse_duration;
+use crate::builtins::builtin::pool_stats;
+use crate::builtins::builtin::record_store::{self, FileStore, RecordStore, StoreFormat};
+use crate::builtins::builtin::transaction::{self, OpenTransaction};
+use crate::builtins::builtin::validate::builtin_validate;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::constants::enums;
+use crate::core::relations::{self, Reference};
+use crate::core::schema_options::{
+    is_unique, unique_fields, vector_dimensions, SchemaOptions, CREATED_AT, DELETED_AT, UPDATED_AT,
 };
-use crate::builtins::{BuiltinResult, Context};
 use crate::core::AppState;
-use crate::rune_ast::{Section, Value};
+use crate::rune_ast::{RuneDocument, Section, Value};
 use sqlx::types::JsonValue;
 use sqlx::{MySql, Pool, Postgres};
-use std::collections::HashMap;
-use std::sync::Arc;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use crate::util::log;
 use crate::util::LogLevel;
 // --- Shared Helpers ---
 
+/// Set by `--mock-datasources`: every postgres and mysql datasource runs as a mock.
+static MOCK_DATASOURCES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_mock_datasources(enabled: bool) {
+    MOCK_DATASOURCES.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether a postgres or mysql datasource is emulated in memory instead of connecting,
+/// because of `--mock-datasources` or its own `mode = mock`. A mock keeps each table as
+/// a list under the memory key named after its schema, so `@Memory/<Schema>` and
+/// `@Seed/<Schema>` sections fill it.
+pub fn is_mocked(section: &Section) -> bool {
+    let is_sql = matches!(section.kv.get("type").and_then(|v| v.as_str()), Some("postgres" | "mysql"));
+    is_sql
+        && (MOCK_DATASOURCES.load(Ordering::Relaxed)
+            || section.kv.get("mode").and_then(|v| v.as_str()) == Some("mock"))
+}
+
 async fn get_pool_details(
     datasource_name: &str,
     state: &AppState,
@@ -23,6 +53,9 @@ async fn get_pool_details(
     let datasource_section = state.data_sources.get(datasource_name).ok_or_else(|| {
         BuiltinResult::Error(format!("Data source '{}' not found", datasource_name))
     })?;
+    if is_mocked(datasource_section) {
+        return Ok((datasource_name.to_string(), "mock".to_string()));
+    }
 
     let conn_str = datasource_section
         .kv
@@ -62,9 +95,10 @@ async fn get_postgres_pool(
             datasource_name
         )));
     }
-    create_or_reuse_postgres_pool(&conn_str)
-        .await
-        .map_err(|e| BuiltinResult::Error(format!("failed to connect to postgres: {}", e)))
+    create_or_reuse_postgres_pool(&conn_str).await.map_err(|e| {
+        pool_stats::record_failure(datasource_name, "postgres");
+        BuiltinResult::Error(format!("failed to connect to postgres: {}", e))
+    })
 }
 
 async fn get_mysql_pool(
@@ -78,19 +112,137 @@ async fn get_mysql_pool(
             datasource_name
         )));
     }
-    create_or_reuse_mysql_pool(&conn_str)
-        .await
-        .map_err(|e| BuiltinResult::Error(format!("failed to connect to mysql: {}", e)))
+    create_or_reuse_mysql_pool(&conn_str).await.map_err(|e| {
+        pool_stats::record_failure(datasource_name, "mysql");
+        BuiltinResult::Error(format!("failed to connect to mysql: {}", e))
+    })
 }
 
-fn get_id_from_ctx(ctx: &Context) -> String {
-    ctx.get("path.params")
-        .and_then(|v| v.as_object()?.get("id")?.as_str().map(|s| s.to_string()))
-        .or_else(|| {
-            ctx.get("body")
-                .and_then(|v| v.as_object()?.get("id")?.as_str().map(|s| s.to_string()))
-        })
-        .unwrap_or_default()
+fn get_id_from_ctx(ctx: &Context) -> Option<JsonValue> {
+    let id_of = |key: &str| {
+        ctx.get(key)
+            .and_then(|v| v.as_object()?.get("id").cloned())
+            .filter(|v| v.is_string() || v.is_number())
+    };
+    id_of("path.params").or_else(|| id_of("body"))
+}
+
+/// The declared type of a schema's `id` field. Tables are created with an
+/// auto-incrementing integer key, so ids are numeric unless declared a string
+/// (`id = string`, `id: String` under `fields:`, or a `ref(...)` to a string id).
+fn schema_id_type(schemas: &HashMap<String, Section>, name: &str) -> &'static str {
+    let declared = schemas.get(name).and_then(|s| relations::declared_type(s, "id"));
+    match declared.map(|typ| relations::resolve_type(typ, schemas)) {
+        Some(typ) if typ.eq_ignore_ascii_case("string") => "string",
+        _ => "number",
+    }
+}
+
+/// Converts a raw id into the JSON type the schema expects.
+pub fn coerce_id(raw: &JsonValue, id_type: &str) -> Result<JsonValue, String> {
+    match (id_type, raw) {
+        ("string", JsonValue::String(_)) => Ok(raw.clone()),
+        ("string", JsonValue::Number(n)) => Ok(JsonValue::String(n.to_string())),
+        (_, JsonValue::Number(n)) if n.is_i64() || n.is_u64() => Ok(raw.clone()),
+        (_, JsonValue::String(s)) => s
+            .trim()
+            .parse::<i64>()
+            .map(JsonValue::from)
+            .map_err(|_| format!("invalid id '{}': expected an integer", s)),
+        _ => Err(format!("invalid id {}: expected an integer", raw)),
+    }
+}
+
+/// Rewrites the `{id}` path param in place as the schema's id type, so later
+/// queries and comparisons see a typed value. Invalid ids respond with 400.
+fn coerce_path_id(name: &str, state: &AppState, ctx: &mut Context) -> BuiltinResult {
+    let id_type = schema_id_type(&state.schemas, name);
+    let Some(raw) = ctx
+        .get("path.params")
+        .and_then(|v| v.as_object()?.get("id").cloned())
+    else {
+        return BuiltinResult::Ok;
+    };
+    let id = match coerce_id(&raw, id_type) {
+        Ok(id) => id,
+        Err(e) => return BuiltinResult::Respond(400, e),
+    };
+    if let Some(JsonValue::Object(params)) = ctx.get_mut("path.params") {
+        params.insert("id".to_string(), id.clone());
+    }
+    ctx.insert("id".to_string(), id);
+    BuiltinResult::Ok
+}
+
+/// The current id, coerced to the schema's id type.
+fn id_from_ctx(name: &str, state: &AppState, ctx: &Context) -> Result<JsonValue, BuiltinResult> {
+    let raw = get_id_from_ctx(ctx).ok_or_else(|| BuiltinResult::Error("missing id".into()))?;
+    coerce_id(&raw, schema_id_type(&state.schemas, name)).map_err(|e| BuiltinResult::Respond(400, e))
+}
+
+/// The SQL literal for the current id, coerced to the schema's id type.
+fn id_sql_from_ctx(name: &str, state: &AppState, ctx: &Context) -> Result<String, BuiltinResult> {
+    id_from_ctx(name, state, ctx).map(|id| format_sql_value(&id))
+}
+
+/// Whether a datasource keeps its records in a file or in memory rather than a database.
+fn is_record_store(conn_type: &str) -> bool {
+    matches!(conn_type, "jsonfile" | "csv" | "memory" | "mock")
+}
+
+/// The records of a `jsonfile`, `csv` or `memory` datasource holding schema `name`.
+async fn open_record_store(name: &str, datasource_name: &str, state: &AppState) -> Result<RecordStore, BuiltinResult> {
+    let (conn_str, conn_type) = get_pool_details(datasource_name, state).await?;
+    match conn_type.as_str() {
+        "memory" => return Ok(RecordStore::Memory(conn_str)),
+        "mock" => return Ok(RecordStore::Memory(name.to_string())),
+        _ => {}
+    }
+    let format = if conn_type == "csv" {
+        let schema = state.schemas.get(name);
+        let mut fields: Vec<(String, String)> = schema
+            .map(|s| {
+                s.kv.iter()
+                    .filter(|(field, _)| field.as_str() != "id")
+                    .filter_map(|(field, typ)| {
+                        typ.as_str().map(|t| (field.clone(), relations::resolve_type(t, &state.schemas).to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        for column in SchemaOptions::of(name, &state.doc).columns() {
+            if !fields.iter().any(|(field, _)| field == column) {
+                fields.push((column.to_string(), "datetime".to_string()));
+            }
+        }
+        fields.sort();
+        fields.insert(0, ("id".to_string(), schema_id_type(&state.schemas, name).to_string()));
+        StoreFormat::Csv(fields)
+    } else {
+        StoreFormat::Json
+    };
+    Ok(RecordStore::File(FileStore::new(&conn_str, &state.path, format)))
+}
+
+/// Query string parameters that name a schema field, as `fetch_all` filters.
+fn query_filters(name: &str, state: &AppState, ctx: &Context) -> Vec<(String, String)> {
+    let schema = state.schemas.get(name);
+    match ctx.get("request.query") {
+        Some(JsonValue::Object(query)) => query
+            .iter()
+            .filter(|(k, _)| k.as_str() == "id" || schema.map(|s| s.kv.contains_key(*k)).unwrap_or(false))
+            .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn body_object(ctx: &Context) -> Result<serde_json::Map<String, JsonValue>, BuiltinResult> {
+    match ctx.get("body") {
+        Some(JsonValue::Object(o)) => Ok(o.clone()),
+        Some(_) => Err(BuiltinResult::Error("body is not an object".into())),
+        None => Err(BuiltinResult::Error("body missing".into())),
+    }
 }
 
 fn format_sql_value(v: &serde_json::Value) -> String {
@@ -98,10 +250,66 @@ fn format_sql_value(v: &serde_json::Value) -> String {
         serde_json::Value::String(s) => format!("'{}'", s.replace("'", "''")),
         serde_json::Value::Number(n) => n.to_string(),
         serde_json::Value::Bool(b) => b.to_string(),
+        // An embedding, in the `'[0.1,0.2]'` text form pgvector reads
+        serde_json::Value::Array(items) if items.iter().all(JsonValue::is_number) => format!("'{}'", v),
         _ => "NULL".to_string(),
     }
 }
 
+/// The current time as a value for `datetime` columns: RFC 3339 in record stores, and a
+/// literal both postgres and mysql accept in SQL.
+fn now_for(conn_type: &str) -> JsonValue {
+    let now = chrono::Utc::now();
+    JsonValue::String(if is_record_store(conn_type) {
+        crate::builtins::builtin::date::to_iso(&now)
+    } else {
+        now.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+    })
+}
+
+/// `record` without the columns `options` maintain, stamped with `updated_at` (and
+/// `created_at` when `inserting`) if the schema keeps timestamps.
+fn stamp(
+    mut record: serde_json::Map<String, JsonValue>,
+    options: SchemaOptions,
+    conn_type: &str,
+    inserting: bool,
+) -> serde_json::Map<String, JsonValue> {
+    for column in options.columns() {
+        record.remove(column);
+    }
+    if options.timestamps {
+        let now = now_for(conn_type);
+        if inserting {
+            record.insert(CREATED_AT.to_string(), now.clone());
+        }
+        record.insert(UPDATED_AT.to_string(), now);
+    }
+    record
+}
+
+fn stamped_body(
+    ctx: &Context,
+    options: SchemaOptions,
+    conn_type: &str,
+    inserting: bool,
+) -> Result<serde_json::Map<String, JsonValue>, BuiltinResult> {
+    body_object(ctx).map(|body| stamp(body, options, conn_type, inserting))
+}
+
+fn is_deleted(record: &JsonValue) -> bool {
+    record.get(DELETED_AT).is_some_and(|v| !v.is_null())
+}
+
+/// `WHERE` condition text skipping soft-deleted rows, or nothing.
+fn live_rows(options: SchemaOptions) -> &'static str {
+    if options.soft_delete {
+        " AND deleted_at IS NULL"
+    } else {
+        ""
+    }
+}
+
 async fn execute_query(
     conn_type: &str,
     datasource_name: &str,
@@ -110,25 +318,59 @@ async fn execute_query(
     query: String,
     assign_to: Option<&str>,
 ) -> BuiltinResult {
+    execute_statement(conn_type, datasource_name, state, ctx, &[query], assign_to).await
+}
+
+/// Runs `args` (the SQL followed by the names of context values to bind) inside the
+/// datasource's open transaction, or on a pooled connection.
+async fn execute_statement(
+    conn_type: &str,
+    datasource_name: &str,
+    state: &AppState,
+    ctx: &mut Context,
+    args: &[String],
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    if let Some(tx) = transaction::current(ctx, datasource_name) {
+        let mut tx = tx.lock().await;
+        return match &mut *tx {
+            OpenTransaction::Postgres(tx) => builtin_postgres_query(args, ctx, &mut **tx, assign_to).await,
+            OpenTransaction::MySql(tx) => builtin_mysql_query(args, ctx, &mut **tx, assign_to).await,
+            OpenTransaction::Mock => mock_query_error(datasource_name),
+        };
+    }
     match conn_type {
         "mysql" => {
             let pool = match get_mysql_pool(datasource_name, state).await {
                 Ok(p) => p,
                 Err(e) => return e,
             };
-            builtin_mysql_query(&[query], ctx, &pool, assign_to).await
+            let mut conn = match pool_stats::acquire(datasource_name, &pool).await {
+                Ok(conn) => conn,
+                Err(e) => return BuiltinResult::Error(format!("mysql: {}", e)),
+            };
+            builtin_mysql_query(args, ctx, &mut *conn, assign_to).await
         }
         "postgres" => {
             let pool = match get_postgres_pool(datasource_name, state).await {
                 Ok(p) => p,
                 Err(e) => return e,
             };
-            builtin_postgres_query(&[query], ctx, &pool, assign_to).await
+            let mut conn = match pool_stats::acquire(datasource_name, &pool).await {
+                Ok(conn) => conn,
+                Err(e) => return BuiltinResult::Error(format!("postgres: {}", e)),
+            };
+            builtin_postgres_query(args, ctx, &mut *conn, assign_to).await
         }
+        "mock" => mock_query_error(datasource_name),
         _ => BuiltinResult::Error(format!("unsupported connection type '{}'", conn_type)),
     }
 }
 
+fn mock_query_error(datasource_name: &str) -> BuiltinResult {
+    BuiltinResult::Error(format!("datasource {} is mocked; SQL queries are not emulated", datasource_name))
+}
+
 // --- RESTful Command Generation ---
 
 fn get_section_by_string_key(
@@ -201,12 +443,36 @@ pub fn get_data_source_commands(
         )
     };
 
-    match method {
-        "GET" => vec![
-            Value::String(create_table_command),
-            Value::String(fetch_command),
-            Value::String("respond 200 data".to_string()),
-        ],
+    // `?include=author` nests the records that `ref(...)` fields point at
+    let include_command = schema
+        .as_ref()
+        .filter(|s| !relations::references(s).is_empty())
+        .map(|_| Value::String(format!("datasource include {} from {} into data", schema_name, data_source_name)));
+
+    // `page_size = 20` on a CRUD route pages the collection with `?page=` and `?size=`
+    let page_size = section
+        .kv
+        .get("page_size")
+        .and_then(|v| v.as_u64())
+        .filter(|size| *size > 0 && !single);
+
+    let mut commands = match method {
+        "GET" => match page_size {
+            Some(size) => vec![
+                Value::String(create_table_command),
+                Value::String(fetch_command),
+                Value::String(format!(
+                    "data_page = paginate data request.query.page request.query.size {}",
+                    size
+                )),
+                Value::String("respond 200 data_page".to_string()),
+            ],
+            None => vec![
+                Value::String(create_table_command),
+                Value::String(fetch_command),
+                Value::String("respond 200 data".to_string()),
+            ],
+        },
         "POST" => vec![
             Value::String("parse-json".to_string()),
             Value::String("validate body #".to_string() + &schema_name),
@@ -233,7 +499,100 @@ pub fn get_data_source_commands(
             )),
             Value::String("respond 204".to_string()),
         ],
+        // `<path>/bulk` takes an array of records to insert, or of ids to delete
+        "BULK_POST" => vec![
+            Value::String("parse-json".to_string()),
+            Value::String(create_table_command),
+            Value::String(format!(
+                "bulk = datasource bulk_insert {} into {}",
+                schema_name, data_source_name
+            )),
+            Value::String("respond 200 bulk".to_string()),
+        ],
+        // `<path>/search?q=` matches the schema's string fields
+        "SEARCH" => vec![
+            Value::String(create_table_command),
+            Value::String(format!(
+                "datasource search {} from {} into data",
+                schema_name, data_source_name
+            )),
+            Value::String("respond 200 data".to_string()),
+        ],
+        "BULK_DELETE" => vec![
+            Value::String("parse-json".to_string()),
+            Value::String(format!(
+                "bulk = datasource bulk_delete {} from {}",
+                schema_name, data_source_name
+            )),
+            Value::String("respond 200 bulk".to_string()),
+        ],
         _ => vec![],
+    };
+    if let (Some(include), "GET" | "SEARCH") = (include_command, method) {
+        commands.insert(2, include);
+    }
+    if single && !commands.is_empty() {
+        commands.insert(
+            0,
+            Value::String(format!("datasource coerce_id {}", schema_name)),
+        );
+    }
+    if let Some((event, payload)) = crud_event(method, single) {
+        let respond = commands.len() - 1;
+        commands.insert(
+            respond,
+            Value::String(format!("emit-event {}.{} {}", schema_name.to_lowercase(), event, payload)),
+        );
+    }
+    splice_hooks(&mut commands, method, &section);
+    commands
+}
+
+/// The event a CRUD operation emits to `@Webhook`s, and the context value sent with it.
+fn crud_event(method: &str, single: bool) -> Option<(&'static str, &'static str)> {
+    match (method, single) {
+        ("POST", false) => Some(("created", "created")),
+        ("POST", true) | ("PUT", _) => Some(("updated", "data_object")),
+        ("DELETE", true) => Some(("deleted", "path.params")),
+        _ => None,
+    }
+}
+
+/// The hook series a CRUD operation runs: `before_<event>:` and `after_<event>:`.
+fn hook_event(method: &str) -> Option<&'static str> {
+    match method {
+        "GET" | "SEARCH" => Some("read"),
+        "POST" => Some("create"),
+        "PUT" => Some("update"),
+        "DELETE" => Some("delete"),
+        _ => None,
+    }
+}
+
+/// Runs the route's `before_<event>:` steps once the body is parsed, ahead of the
+/// generated validation and datasource steps, and its `after_<event>:` steps just before
+/// the response, where the record read or written is in `data`, `created` or
+/// `data_object`. Either may `respond` to stop the request.
+fn splice_hooks(commands: &mut Vec<Value>, method: &str, section: &Section) {
+    let Some(event) = hook_event(method) else {
+        return;
+    };
+    if commands.is_empty() {
+        return;
+    }
+    if let Some(after) = section.series.get(&format!("after_{}", event)) {
+        let respond = commands.len() - 1;
+        commands.splice(respond..respond, after.iter().cloned());
+    }
+    if let Some(before) = section.series.get(&format!("before_{}", event)) {
+        let start = commands
+            .iter()
+            .position(|step| {
+                step.as_str()
+                    .is_none_or(|step| step != "parse-json" && !step.starts_with("datasource coerce_id"))
+            })
+            .unwrap_or(commands.len());
+        commands.splice(start..start, before.iter().cloned());
     }
 }
 
@@ -250,175 +609,930 @@ pub async fn builtin_data_source(
     }
     let action = &args[0];
     let name = if args.len() > 1 { &args[1] } else { "" };
+    if action != "coerce_id" {
+        if let Some(chaos) = crate::apps::chaos::global_chaos() {
+            if chaos.disturb().await {
+                return BuiltinResult::Error(format!("chaos: injected failure in datasource {}", name));
+            }
+        }
+    }
     let action_args = if args.len() > 2 { &args[2..] } else { &[] };
 
     match action.as_str() {
+        "coerce_id" => coerce_path_id(name, state, ctx),
+        "ping" => ping_datasource(name, action_args, state, ctx, assign_to).await,
+        "begin" => begin_transaction(name, state, ctx).await,
+        "commit" => transaction::commit(ctx, name).await,
+        "rollback" => transaction::rollback(ctx, name).await,
+        "query" => query_datasource(name, action_args, state, ctx, assign_to).await,
         "create_table" => create_table(name, action_args, state).await,
         "fetch_all" => fetch_all_from_datasource(name, action_args, state, ctx, assign_to).await,
         "fetch" => fetch_from_datasource(name, action_args, state, ctx, assign_to).await,
+        "include" => include_relations(name, action_args, state, ctx).await,
+        "search" => search_datasource(name, action_args, state, ctx, assign_to).await,
+        "vector_search" => vector_search_datasource(name, action_args, state, ctx, assign_to).await,
         "insert" => upsert_into_datasource(name, action_args, state, ctx).await,
         "update" => upsert_into_datasource(name, action_args, state, ctx).await,
         "delete" => delete_from_datasource(name, action_args, state, ctx, assign_to).await,
+        "bulk_insert" => bulk_insert(name, action_args, state, ctx, assign_to).await,
+        "bulk_delete" => bulk_delete(name, action_args, state, ctx, assign_to).await,
         _ => BuiltinResult::Error(format!("unknown action: {}", action)),
     }
 }
 
-pub async fn create_table(name: &str, args: &[String], state: &AppState) -> BuiltinResult {
-    let schema_section = state.schemas.get(name).unwrap_or_else(|| {
-        log(LogLevel::Error, &format!("datasource.create_table: schema '{}' not found", name));
-        panic!("schema not found");
-    });
-    let datasource_name = if args.len() > 1 && args[0] == "in" {
-        &args[1]
-    } else {
-        return BuiltinResult::Error("missing 'in'".into());
-    };
-    let (_, conn_type) = match get_pool_details(datasource_name, state).await {
+/// `datasource begin <name>`: opens a transaction that the datasource's queries run in
+/// until `datasource commit` or `datasource rollback`.
+pub(crate) async fn begin_transaction(ds_name: &str, state: &AppState, ctx: &mut Context) -> BuiltinResult {
+    if transaction::is_open(ctx, ds_name) {
+        return BuiltinResult::Error(format!("a transaction is already open on {}", ds_name));
+    }
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
         Ok(v) => v,
         Err(e) => return e,
     };
-
-    let mut columns: Vec<(String, String)> = Vec::new();
-    for (field, typ_value) in &schema_section.kv {
-        if let Value::String(typ) = typ_value {
-            let sql_type = match typ.as_str() {
-                "string" => "TEXT",
-                "number" => "FLOAT",
-                "bool" => "BOOLEAN",
-                _ => return BuiltinResult::Error(format!("unsupported type '{}'", typ)),
+    let tx = match conn_type.as_str() {
+        "postgres" => {
+            let pool = match get_postgres_pool(ds_name, state).await {
+                Ok(p) => p,
+                Err(e) => return e,
             };
-            columns.push((field.clone(), sql_type.to_string()));
+            pool_stats::begin(ds_name, &pool).await.map(OpenTransaction::Postgres)
         }
-    }
-
-    if conn_type == "mysql" {
-        columns.insert(
-            0,
-            (
-                "id".to_string(),
-                "INT AUTO_INCREMENT PRIMARY KEY".to_string(),
-            ),
-        );
-        let pool = match get_mysql_pool(datasource_name, state).await {
-            Ok(p) => p,
-            Err(e) => return e,
-        };
-        create_table_mysql(name, &[create_table_columns_string(&columns)], &pool).await
-    } else {
-        columns.insert(0, ("id".to_string(), "SERIAL PRIMARY KEY".to_string()));
-        let pool = match get_postgres_pool(datasource_name, state).await {
-            Ok(p) => p,
-            Err(e) => return e,
-        };
-        create_table_postgres(name, &[create_table_columns_string(&columns)], &pool).await
+        "mysql" => {
+            let pool = match get_mysql_pool(ds_name, state).await {
+                Ok(p) => p,
+                Err(e) => return e,
+            };
+            pool_stats::begin(ds_name, &pool).await.map(OpenTransaction::MySql)
+        }
+        "mock" => Ok(OpenTransaction::Mock),
+        other => {
+            return BuiltinResult::Error(format!(
+                "datasource {} ({}) does not support transactions",
+                ds_name, other
+            ))
+        }
+    };
+    match tx {
+        Ok(tx) => {
+            transaction::register(ctx, ds_name, tx);
+            BuiltinResult::Ok
+        }
+        Err(e) => BuiltinResult::Error(format!("{}: {}", conn_type, e)),
     }
 }
 
-pub async fn fetch_all_from_datasource(
-    name: &str,
+/// `datasource query <name> "<sql>" [with a b] [into var]`: runs SQL on a postgres or
+/// mysql datasource, binding the named context values to `$1`, `$2`... (`?` on mysql).
+/// Rows are stored in the `into` or assigned variable.
+pub(crate) async fn query_datasource(
+    ds_name: &str,
     args: &[String],
     state: &AppState,
     ctx: &mut Context,
     assign_to: Option<&str>,
 ) -> BuiltinResult {
-    let ds_name = if args.len() > 1 && args[0] == "from" {
-        &args[1]
-    } else {
-        ""
+    let Some(sql) = args.first() else {
+        return BuiltinResult::Error("datasource query: missing SQL".to_string());
     };
+    let mut statement = vec![sql.clone()];
+    let mut target = assign_to;
+    let mut rest = args[1..].iter();
+    while let Some(word) = rest.next() {
+        match word.as_str() {
+            "into" => target = rest.next().map(String::as_str),
+            "with" => {}
+            param => statement.push(param.to_string()),
+        }
+    }
     let (_, conn_type) = match get_pool_details(ds_name, state).await {
         Ok(v) => v,
         Err(e) => return e,
     };
-    let target = if args.len() > 3 && args[2] == "into" {
-        Some(args[3].as_str())
-    } else {
-        assign_to
-    };
+    execute_statement(&conn_type, ds_name, state, ctx, &statement, target).await
+}
 
-    execute_query(
-        &conn_type,
-        ds_name,
-        state,
-        ctx,
-        format!("SELECT * FROM {}", name),
-        target,
-    )
-    .await
+/// How long `datasource ping` waits before reporting a datasource as unavailable.
+const DEFAULT_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Connects to a SQL datasource and runs `SELECT 1`, or reads a file or memory store.
+async fn check_datasource(ds_name: &str, conn_type: &str, state: &AppState) -> Result<(), String> {
+    match conn_type {
+        "postgres" => {
+            let pool = get_postgres_pool(ds_name, state).await.map_err(|_| "failed to connect to postgres".to_string())?;
+            let mut conn = pool_stats::acquire(ds_name, &pool).await.map_err(|e| e.to_string())?;
+            sqlx::query("SELECT 1").execute(&mut *conn).await.map(|_| ()).map_err(|e| e.to_string())
+        }
+        "mysql" => {
+            let pool = get_mysql_pool(ds_name, state).await.map_err(|_| "failed to connect to mysql".to_string())?;
+            let mut conn = pool_stats::acquire(ds_name, &pool).await.map_err(|e| e.to_string())?;
+            sqlx::query("SELECT 1").execute(&mut *conn).await.map(|_| ()).map_err(|e| e.to_string())
+        }
+        other if is_record_store(other) => {
+            let store = open_record_store("", ds_name, state).await.map_err(|_| "invalid datasource".to_string())?;
+            record_store::fetch_all(store, Vec::new()).await.map(|_| ()).map_err(|e| match e {
+                BuiltinResult::Error(msg) | BuiltinResult::Respond(_, msg) => msg,
+                _ => "unreadable".to_string(),
+            })
+        }
+        other => Err(format!("unsupported connection type '{}'", other)),
+    }
 }
 
-pub async fn fetch_from_datasource(
-    name: &str,
+/// `datasource ping <name> [within <duration>]`: checks that a datasource is reachable
+/// (default timeout 5s). Assigned, the result is `{ok, latency_ms}` plus `error` on
+/// failure; unassigned, a failure responds 503.
+async fn ping_datasource(
+    ds_name: &str,
     args: &[String],
     state: &AppState,
     ctx: &mut Context,
     assign_to: Option<&str>,
 ) -> BuiltinResult {
-    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let limit = match args {
+        [within, duration, ..] if within == "within" => match parse_duration(duration) {
+            Some(limit) => limit,
+            None => return BuiltinResult::Error(format!("invalid ping timeout '{}'", duration)),
+        },
+        _ => DEFAULT_PING_TIMEOUT,
+    };
     let (_, conn_type) = match get_pool_details(ds_name, state).await {
         Ok(v) => v,
         Err(e) => return e,
     };
-    let target = if args.len() > 3 && args[2] == "into" {
-        Some(args[3].as_str())
-    } else {
-        assign_to
+    let started = std::time::Instant::now();
+    let outcome = match tokio::time::timeout(limit, check_datasource(ds_name, &conn_type, state)).await {
+        Ok(outcome) => outcome,
+        Err(_) => {
+            match conn_type.as_str() {
+                "postgres" => pool_stats::record_failure(ds_name, "postgres"),
+                "mysql" => pool_stats::record_failure(ds_name, "mysql"),
+                _ => {}
+            }
+            Err(format!("no response within {:?}", limit))
+        }
     };
-    let id = get_id_from_ctx(ctx);
-
-    if id.is_empty() {
-        return BuiltinResult::Error("missing id".into());
+    let latency_ms = (started.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0;
+    match (outcome, assign_to) {
+        (Ok(()), target) => store_result(ctx, target, serde_json::json!({ "ok": true, "latency_ms": latency_ms })),
+        (Err(e), Some(target)) => store_result(
+            ctx,
+            Some(target),
+            serde_json::json!({ "ok": false, "latency_ms": latency_ms, "error": e }),
+        ),
+        (Err(e), None) => BuiltinResult::Respond(503, format!("datasource {} unavailable: {}", ds_name, e)),
     }
-    match execute_query(
-        &conn_type,
-        ds_name,
-        state,
-        ctx,
-        format!("SELECT * FROM {} WHERE id = {} LIMIT 1", name, id),
-        target,
-    )
-    .await
-    {
-        BuiltinResult::Ok => {
-            // After fetching, move the first result into target variable
-            if let Some(var_name) = target {
-                if let Some(JsonValue::Array(arr)) = ctx.get(var_name) {
-                    if let Some(first) = arr.get(0) {
-                        ctx.insert(var_name.into(), first.clone());
-                    } else {
-                        return BuiltinResult::Respond(404, "no record found".into());
+}
+
+/// The SQL columns for a schema's fields, without the `id` key column.
+pub(crate) fn schema_columns(schema: &Section, doc: &RuneDocument) -> Result<Vec<(String, String)>, String> {
+    let enums = enums(doc);
+    let schemas = crate::core::extract_schemas(doc);
+    let mut columns: Vec<(String, String)> = Vec::new();
+    for (field, typ_value) in &schema.kv {
+        if let Value::String(typ) = typ_value {
+            let sql_type = match relations::resolve_type(typ, &schemas) {
+                // Keys referencing a generated id match its integer column
+                "number" if relations::parse_ref(typ).is_some_and(|(_, column)| column == "id") => "INTEGER",
+                enum_name if enums.contains_key(enum_name) => "TEXT",
+                "string" => "TEXT",
+                "number" => "FLOAT",
+                "bool" => "BOOLEAN",
+                "datetime" => "TIMESTAMP",
+                vector => match vector_dimensions(vector) {
+                    Some(dimensions) => {
+                        columns.push((field.clone(), format!("VECTOR({})", dimensions)));
+                        continue;
                     }
-                }
-            }
-            BuiltinResult::Ok
+                    None => return Err(format!("unsupported type '{}'", typ)),
+                },
+            };
+            let sql_type = if is_unique(typ) { format!("{} UNIQUE", sql_type) } else { sql_type.to_string() };
+            columns.push((field.clone(), sql_type));
+        }
+    }
+    let name = schema.path.get(1).map(String::as_str).unwrap_or_default();
+    for column in SchemaOptions::of(name, doc).columns() {
+        if !columns.iter().any(|(field, _)| field == column) {
+            columns.push((column.to_string(), "TIMESTAMP".to_string()));
         }
-        other => other,
     }
+    Ok(columns)
 }
 
-pub async fn delete_from_datasource(
-    name: &str,
-    args: &[String],
-    state: &AppState,
-    ctx: &mut Context,
-    assign_to: Option<&str>,
-) -> BuiltinResult {
-    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+/// MySQL can't index `TEXT` without a key length, so unique text columns become `VARCHAR(255)`.
+pub(crate) fn mysql_columns(columns: Vec<(String, String)>) -> Vec<(String, String)> {
+    columns
+        .into_iter()
+        .map(|(field, sql_type)| match sql_type.as_str() {
+            "TEXT UNIQUE" => (field, "VARCHAR(255) UNIQUE".to_string()),
+            _ => (field, sql_type),
+        })
+        .collect()
+}
+
+/// `FOREIGN KEY` constraints for a schema's `ref(...)` fields.
+pub(crate) fn foreign_keys(schema: &Section) -> Vec<String> {
+    relations::references(schema)
+        .iter()
+        .map(|r| format!("FOREIGN KEY ({}) REFERENCES {}({})", r.field, r.schema, r.column))
+        .collect()
+}
+
+/// The column list of a `CREATE TABLE`, followed by the schema's foreign keys.
+fn table_definition(columns: &[(String, String)], schema: &Section) -> String {
+    std::iter::once(create_table_columns_string(columns))
+        .chain(foreign_keys(schema))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The schemas `name` references, directly or not, in the order their tables must be
+/// created for the foreign keys to resolve. `name` itself and cycles are left out.
+fn referenced_tables(name: &str, schemas: &HashMap<String, Section>) -> Vec<String> {
+    fn visit(name: &str, schemas: &HashMap<String, Section>, order: &mut Vec<String>, seen: &mut HashSet<String>) {
+        if !seen.insert(name.to_string()) {
+            return;
+        }
+        for reference in schemas.get(name).map(relations::references).unwrap_or_default() {
+            visit(&reference.schema, schemas, order, seen);
+        }
+        order.push(name.to_string());
+    }
+    let mut order = Vec::new();
+    visit(name, schemas, &mut order, &mut HashSet::new());
+    order.pop();
+    order
+}
+
+pub async fn create_table(name: &str, args: &[String], state: &AppState) -> BuiltinResult {
+    for parent in referenced_tables(name, &state.schemas) {
+        if state.schemas.contains_key(&parent) {
+            let created = create_one_table(&parent, args, state).await;
+            if !matches!(created, BuiltinResult::Ok) {
+                return created;
+            }
+        }
+    }
+    create_one_table(name, args, state).await
+}
+
+async fn create_one_table(name: &str, args: &[String], state: &AppState) -> BuiltinResult {
+    let schema_section = state.schemas.get(name).unwrap_or_else(|| {
+        log(LogLevel::Error, &format!("datasource.create_table: schema '{}' not found", name));
+        panic!("schema not found");
+    });
+    let datasource_name = if args.len() > 1 && args[0] == "in" {
+        &args[1]
+    } else {
+        return BuiltinResult::Error("missing 'in'".into());
+    };
+    let (_, conn_type) = match get_pool_details(datasource_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if is_record_store(&conn_type) {
+        return match open_record_store(name, datasource_name, state).await {
+            Ok(store) => record_store::ensure(store).await.err().unwrap_or(BuiltinResult::Ok),
+            Err(e) => e,
+        };
+    }
+
+    let mut columns = match schema_columns(schema_section, &state.doc) {
+        Ok(columns) => columns,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+
+    let evolve_key = format!("{}.{}", datasource_name, name);
+    let evolved = EVOLVED_TABLES.lock().unwrap().contains(&evolve_key);
+    let auto_migrate = matches!(
+        state.data_sources.get(datasource_name).and_then(|ds| ds.kv.get("auto_migrate")),
+        Some(Value::Bool(true))
+    );
+
+    if conn_type == "mysql" {
+        if let Some((field, _)) = columns.iter().find(|(_, sql_type)| is_vector_column(sql_type)) {
+            return BuiltinResult::Error(format!(
+                "mysql.create_table error: {}.{} is a vector field, which needs a postgres datasource",
+                name, field
+            ));
+        }
+        columns = mysql_columns(columns);
+        let schema_fields = columns.clone();
+        columns.insert(
+            0,
+            (
+                "id".to_string(),
+                "INT AUTO_INCREMENT PRIMARY KEY".to_string(),
+            ),
+        );
+        let pool = match get_mysql_pool(datasource_name, state).await {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+        let created = create_table_mysql(name, &[table_definition(&columns, schema_section)], &pool).await;
+        if !matches!(created, BuiltinResult::Ok) || evolved {
+            return created;
+        }
+        let existing = match table_columns_mysql(name, &pool).await {
+            Ok(existing) => existing,
+            Err(e) => return BuiltinResult::Error(format!("mysql.create_table error: {}", e)),
+        };
+        for statement in plan_evolution(name, datasource_name, &schema_fields, &existing, auto_migrate) {
+            if let Err(e) = sqlx::query(&statement).execute(&pool).await {
+                return BuiltinResult::Error(format!("mysql.create_table error: {}: {}", statement, e));
+            }
+            log(LogLevel::Info, &format!("datasource {}: {}", datasource_name, statement));
+        }
+    } else {
+        let schema_fields = columns.clone();
+        columns.insert(0, ("id".to_string(), "SERIAL PRIMARY KEY".to_string()));
+        let pool = match get_postgres_pool(datasource_name, state).await {
+            Ok(p) => p,
+            Err(e) => return e,
+        };
+        if columns.iter().any(|(_, sql_type)| is_vector_column(sql_type)) {
+            if let Err(e) = sqlx::query("CREATE EXTENSION IF NOT EXISTS vector").execute(&pool).await {
+                return BuiltinResult::Error(format!("postgres.create_table error: pgvector: {}", e));
+            }
+        }
+        let created = create_table_postgres(name, &[table_definition(&columns, schema_section)], &pool).await;
+        if !matches!(created, BuiltinResult::Ok) || evolved {
+            return created;
+        }
+        let existing = match table_columns_postgres(name, &pool).await {
+            Ok(existing) => existing,
+            Err(e) => return BuiltinResult::Error(format!("postgres.create_table error: {}", e)),
+        };
+        for statement in plan_evolution(name, datasource_name, &schema_fields, &existing, auto_migrate) {
+            if let Err(e) = sqlx::query(&statement).execute(&pool).await {
+                return BuiltinResult::Error(format!("postgres.create_table error: {}: {}", statement, e));
+            }
+            log(LogLevel::Info, &format!("datasource {}: {}", datasource_name, statement));
+        }
+    }
+    EVOLVED_TABLES.lock().unwrap().insert(evolve_key);
+    BuiltinResult::Ok
+}
+
+fn is_vector_column(sql_type: &str) -> bool {
+    sql_type.starts_with("VECTOR(")
+}
+
+/// Tables already compared with their `@Schema` in this process, as `datasource.table`.
+static EVOLVED_TABLES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Whether an existing column's `data_type` (as reported by `information_schema`) can
+/// hold values of the SQL type `schema_columns` gives the field.
+fn column_type_matches(sql_type: &str, data_type: &str) -> bool {
+    let data_type = data_type.to_lowercase();
+    match sql_type.split_whitespace().next().unwrap_or_default() {
+        "TEXT" => matches!(
+            data_type.as_str(),
+            "text" | "character varying" | "varchar" | "character" | "char" | "tinytext" | "mediumtext" | "longtext"
+        ),
+        "FLOAT" => matches!(
+            data_type.as_str(),
+            "double precision" | "real" | "float" | "double" | "numeric" | "decimal" | "integer" | "int"
+                | "bigint" | "smallint"
+        ),
+        "INTEGER" => matches!(data_type.as_str(), "integer" | "int" | "bigint" | "smallint"),
+        "BOOLEAN" => matches!(data_type.as_str(), "boolean" | "tinyint" | "bit"),
+        "TIMESTAMP" => data_type.starts_with("timestamp") || data_type == "datetime",
+        vector if is_vector_column(vector) => data_type == "user-defined",
+        _ => true,
+    }
+}
+
+/// Compares a table's existing columns with its schema fields. Returns the
+/// `ALTER TABLE ... ADD COLUMN` statements for missing fields when `auto_migrate` is on;
+/// otherwise missing fields are only logged. Type mismatches are always logged, never altered.
+pub(crate) fn plan_evolution(
+    table: &str,
+    datasource: &str,
+    fields: &[(String, String)],
+    existing: &[(String, String)],
+    auto_migrate: bool,
+) -> Vec<String> {
+    if existing.is_empty() {
+        return Vec::new();
+    }
+    let existing: HashMap<String, &str> = existing
+        .iter()
+        .map(|(column, data_type)| (column.to_lowercase(), data_type.as_str()))
+        .collect();
+    let mut statements = Vec::new();
+    for (field, sql_type) in fields {
+        match existing.get(&field.to_lowercase()) {
+            Some(data_type) if !column_type_matches(sql_type, data_type) => log(
+                LogLevel::Warn,
+                &format!(
+                    "datasource {}: column {}.{} is {} but the schema expects {}",
+                    datasource, table, field, data_type, sql_type
+                ),
+            ),
+            Some(_) => {}
+            None if auto_migrate => {
+                statements.push(format!("ALTER TABLE {} ADD COLUMN {} {}", table, field, sql_type))
+            }
+            None => log(
+                LogLevel::Warn,
+                &format!(
+                    "datasource {}: table {} has no column for schema field '{}' (set auto_migrate = true to add it)",
+                    datasource, table, field
+                ),
+            ),
+        }
+    }
+    statements
+}
+
+pub async fn fetch_all_from_datasource(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = if args.len() > 1 && args[0] == "from" {
+        &args[1]
+    } else {
+        ""
+    };
     let (_, conn_type) = match get_pool_details(ds_name, state).await {
         Ok(v) => v,
         Err(e) => return e,
     };
-    let id = get_id_from_ctx(ctx);
+    let target = if args.len() > 3 && args[2] == "into" {
+        Some(args[3].as_str())
+    } else {
+        assign_to
+    };
+    if is_record_store(&conn_type) {
+        let store = match open_record_store(name, ds_name, state).await {
+            Ok(store) => store,
+            Err(e) => return e,
+        };
+        return match record_store::fetch_all(store, query_filters(name, state, ctx)).await {
+            Ok(JsonValue::Array(records)) => {
+                store_result(ctx, target, records.into_iter().filter(|r| !is_deleted(r)).collect())
+            }
+            Ok(records) => store_result(ctx, target, records),
+            Err(e) => e,
+        };
+    }
+    let query = if SchemaOptions::of(name, &state.doc).soft_delete {
+        format!("SELECT * FROM {} WHERE deleted_at IS NULL", name)
+    } else {
+        format!("SELECT * FROM {}", name)
+    };
+
+    execute_query(&conn_type, ds_name, state, ctx, query, target).await
+}
 
-    execute_query(
+pub async fn fetch_from_datasource(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let target = if args.len() > 3 && args[2] == "into" {
+        Some(args[3].as_str())
+    } else {
+        assign_to
+    };
+    if is_record_store(&conn_type) {
+        let fetched = match (open_record_store(name, ds_name, state).await, id_from_ctx(name, state, ctx)) {
+            (Ok(store), Ok(id)) => record_store::fetch(store, id).await,
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        return match fetched {
+            Ok(record) if is_deleted(&record) => BuiltinResult::Respond(404, "no record found".into()),
+            Ok(record) => store_result(ctx, target, record),
+            Err(e) => e,
+        };
+    }
+    let id = match id_sql_from_ctx(name, state, ctx) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    match execute_query(
         &conn_type,
         ds_name,
         state,
         ctx,
-        format!("DELETE FROM {} WHERE id = {}", name, id),
-        assign_to,
+        format!(
+            "SELECT * FROM {} WHERE id = {}{} LIMIT 1",
+            name,
+            id,
+            live_rows(SchemaOptions::of(name, &state.doc))
+        ),
+        target,
     )
     .await
+    {
+        BuiltinResult::Ok => {
+            // After fetching, move the first result into target variable
+            if let Some(var_name) = target {
+                if let Some(JsonValue::Array(arr)) = ctx.get(var_name) {
+                    if let Some(first) = arr.get(0) {
+                        ctx.insert(var_name.into(), first.clone());
+                    } else {
+                        return BuiltinResult::Respond(404, "no record found".into());
+                    }
+                }
+            }
+            BuiltinResult::Ok
+        }
+        other => other,
+    }
+}
+
+/// Compares keys across backends, where ids may come back as numbers or strings.
+fn join_key(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Number(n) => n.as_f64().map(|f| f.to_string()).unwrap_or_else(|| n.to_string()),
+        other => other.to_string(),
+    }
+}
+
+/// The records of `reference.schema` whose key is one of `keys`, read from the datasource
+/// its routes use, or else from `ds_name`.
+async fn fetch_referenced(
+    reference: &Reference,
+    ds_name: &str,
+    keys: &[JsonValue],
+    state: &AppState,
+    ctx: &mut Context,
+) -> Result<Vec<JsonValue>, BuiltinResult> {
+    let ds_name = relations::routed_data_source(&state.doc, &reference.schema).unwrap_or(ds_name);
+    let (_, conn_type) = get_pool_details(ds_name, state).await?;
+    let options = SchemaOptions::of(&reference.schema, &state.doc);
+    let records = if is_record_store(&conn_type) {
+        let store = open_record_store(&reference.schema, ds_name, state).await?;
+        record_store::fetch_all(store, Vec::new()).await?
+    } else {
+        const INCLUDED: &str = "___included___";
+        let keys: Vec<String> = keys.iter().map(format_sql_value).collect();
+        let query = format!(
+            "SELECT * FROM {} WHERE {} IN ({}){}",
+            reference.schema,
+            reference.column,
+            keys.join(", "),
+            live_rows(options)
+        );
+        match execute_query(&conn_type, ds_name, state, ctx, query, Some(INCLUDED)).await {
+            BuiltinResult::Ok => ctx.remove(INCLUDED).unwrap_or(JsonValue::Null),
+            other => return Err(other),
+        }
+    };
+    Ok(match records {
+        JsonValue::Array(records) => records.into_iter().filter(|r| !is_deleted(r)).collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// `datasource include <Schema> from <ds> into data`: for each relation named in
+/// `?include=` (comma separated), sets `data`'s records' relation field to the record
+/// their `ref(...)` key points at, or null when there is none.
+pub async fn include_relations(name: &str, args: &[String], state: &AppState, ctx: &mut Context) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let target = if args.len() > 3 && args[2] == "into" { args[3].as_str() } else { "data" };
+    let requested: Vec<String> = ctx
+        .get("request.query")
+        .and_then(|q| q.get("include"))
+        .and_then(JsonValue::as_str)
+        .map(|list| list.split(',').map(str::trim).filter(|r| !r.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    if requested.is_empty() {
+        return BuiltinResult::Ok;
+    }
+    let references = state.schemas.get(name).map(relations::references).unwrap_or_default();
+    for relation in requested {
+        let Some(reference) = references.iter().find(|r| r.name == relation) else {
+            let known: Vec<&str> = references.iter().map(|r| r.name.as_str()).collect();
+            return BuiltinResult::Respond(
+                400,
+                format!("unknown relation '{}' on {}; expected one of: {}", relation, name, known.join(", ")),
+            );
+        };
+        let records: Vec<&JsonValue> = match ctx.get(target) {
+            Some(JsonValue::Array(records)) => records.iter().collect(),
+            Some(record @ JsonValue::Object(_)) => vec![record],
+            _ => continue,
+        };
+        let mut keys: Vec<JsonValue> = Vec::new();
+        let mut seen = HashSet::new();
+        for key in records.iter().filter_map(|r| r.get(&reference.field)).filter(|k| !k.is_null()) {
+            if seen.insert(join_key(key)) {
+                keys.push(key.clone());
+            }
+        }
+        let parents: HashMap<String, JsonValue> = if keys.is_empty() {
+            HashMap::new()
+        } else {
+            match fetch_referenced(reference, ds_name, &keys, state, ctx).await {
+                Ok(parents) => parents
+                    .into_iter()
+                    .filter_map(|p| Some((join_key(p.get(&reference.column)?), p)))
+                    .filter(|(key, _)| seen.contains(key))
+                    .collect(),
+                Err(e) => return e,
+            }
+        };
+        let nest = |record: &mut JsonValue| {
+            let parent = record
+                .get(&reference.field)
+                .and_then(|key| parents.get(&join_key(key)))
+                .cloned()
+                .unwrap_or(JsonValue::Null);
+            if let JsonValue::Object(fields) = record {
+                fields.insert(reference.name.clone(), parent);
+            }
+        };
+        match ctx.get_mut(target) {
+            Some(JsonValue::Array(records)) => records.iter_mut().for_each(nest),
+            Some(record) => nest(record),
+            None => {}
+        }
+    }
+    BuiltinResult::Ok
+}
+
+/// The string fields `?q=` is matched against: those named in `?fields=` (comma
+/// separated), or all of the schema's string fields.
+fn search_fields(name: &str, state: &AppState, ctx: &Context) -> Result<Vec<String>, BuiltinResult> {
+    let mut searchable: Vec<String> = state
+        .schemas
+        .get(name)
+        .map(|schema| {
+            schema
+                .kv
+                .iter()
+                .filter(|(_, typ)| {
+                    typ.as_str().map(|t| relations::resolve_type(t, &state.schemas)) == Some("string")
+                })
+                .map(|(field, _)| field.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    searchable.sort();
+    let requested = ctx.get("request.query").and_then(|q| q.get("fields")).and_then(JsonValue::as_str);
+    let Some(requested) = requested else {
+        return Ok(searchable);
+    };
+    let mut fields = Vec::new();
+    for field in requested.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !searchable.iter().any(|s| s == field) {
+            return Err(BuiltinResult::Respond(
+                400,
+                format!(
+                    "cannot search '{}' on {}; expected one of: {}",
+                    field,
+                    name,
+                    searchable.join(", ")
+                ),
+            ));
+        }
+        fields.push(field.to_string());
+    }
+    Ok(fields)
+}
+
+/// `datasource search <Schema> from <ds> into data`: the records where any searched field
+/// contains `?q=`, ignoring case. `?fields=name,email` limits the fields searched.
+pub async fn search_datasource(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let target = if args.len() > 3 && args[2] == "into" {
+        Some(args[3].as_str())
+    } else {
+        assign_to
+    };
+    let term = ctx
+        .get("request.query")
+        .and_then(|q| q.get("q"))
+        .and_then(JsonValue::as_str)
+        .map(str::trim)
+        .unwrap_or_default()
+        .to_string();
+    if term.is_empty() {
+        return BuiltinResult::Respond(400, "missing search term: expected ?q=".into());
+    }
+    let fields = match search_fields(name, state, ctx) {
+        Ok(fields) if fields.is_empty() => return store_result(ctx, target, JsonValue::Array(Vec::new())),
+        Ok(fields) => fields,
+        Err(e) => return e,
+    };
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if is_record_store(&conn_type) {
+        let store = match open_record_store(name, ds_name, state).await {
+            Ok(store) => store,
+            Err(e) => return e,
+        };
+        let term = term.to_lowercase();
+        let contains = |record: &JsonValue| {
+            fields.iter().any(|field| {
+                record
+                    .get(field)
+                    .and_then(JsonValue::as_str)
+                    .is_some_and(|value| value.to_lowercase().contains(&term))
+            })
+        };
+        return match record_store::fetch_all(store, Vec::new()).await {
+            Ok(JsonValue::Array(records)) => store_result(
+                ctx,
+                target,
+                records.into_iter().filter(|r| !is_deleted(r) && contains(r)).collect(),
+            ),
+            Ok(records) => store_result(ctx, target, records),
+            Err(e) => e,
+        };
+    }
+
+    // The pattern is bound rather than inlined, with `!` escaping LIKE's wildcards
+    const PATTERN: &str = "___search_pattern___";
+    let escaped = term.replace('!', "!!").replace('%', "!%").replace('_', "!_");
+    ctx.insert(PATTERN.to_string(), JsonValue::String(format!("%{}%", escaped)));
+    let mut statement = vec![String::new()];
+    let conditions: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if conn_type == "postgres" {
+                format!("{} ILIKE $1 ESCAPE '!'", field)
+            } else {
+                statement.push(PATTERN.to_string());
+                format!("LOWER({}) LIKE LOWER(?) ESCAPE '!'", field)
+            }
+        })
+        .collect();
+    if conn_type == "postgres" {
+        statement.push(PATTERN.to_string());
+    }
+    statement[0] = format!(
+        "SELECT * FROM {} WHERE ({}){}",
+        name,
+        conditions.join(" OR "),
+        live_rows(SchemaOptions::of(name, &state.doc))
+    );
+    let result = execute_statement(&conn_type, ds_name, state, ctx, &statement, target).await;
+    ctx.remove(PATTERN);
+    result
+}
+
+/// `datasource vector_search <Schema> <embedding> from <ds> [top_k=5] [into results]`: the
+/// `top_k` records whose `vector(...)` field is nearest the embedding by cosine distance,
+/// best first, each with its similarity as `score`. Needs postgres with pgvector.
+pub async fn vector_search_datasource(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some((embedding, rest)) = args.split_first() else {
+        return BuiltinResult::Error("vector_search requires an embedding".into());
+    };
+    let (mut ds_name, mut target, mut top_k) = ("", assign_to, 5);
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "from" => ds_name = rest.next().map(String::as_str).unwrap_or_default(),
+            "into" => target = rest.next().map(String::as_str),
+            option => match option.strip_prefix("top_k=").map(str::parse) {
+                Some(Ok(n)) if n > 0 => top_k = n,
+                _ => return BuiltinResult::Error(format!("vector_search: invalid option '{}'", option)),
+            },
+        }
+    }
+    let Some(schema) = state.schemas.get(name) else {
+        return BuiltinResult::Error(format!("schema '{}' not found", name));
+    };
+    let columns = match schema_columns(schema, &state.doc) {
+        Ok(columns) => columns,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    let Some((field, dimensions)) = columns
+        .iter()
+        .find_map(|(field, _)| Some((field, vector_dimensions(schema.kv.get(field)?.as_str()?)?)))
+    else {
+        return BuiltinResult::Error(format!("vector_search: {} has no vector field", name));
+    };
+    let numbers: Option<Vec<String>> = match crate::builtins::builtin::collection::value_arg(ctx, embedding) {
+        JsonValue::Array(items) => items.iter().map(|n| n.as_f64().map(|n| n.to_string())).collect(),
+        _ => None,
+    };
+    let numbers = match numbers {
+        Some(numbers) if numbers.len() == dimensions => numbers,
+        Some(numbers) => {
+            return BuiltinResult::Error(format!(
+                "vector_search: the embedding has {} dimensions but {}.{} holds {}",
+                numbers.len(),
+                name,
+                field,
+                dimensions
+            ))
+        }
+        None => return BuiltinResult::Error(format!("vector_search: '{}' is not a list of numbers", embedding)),
+    };
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    if conn_type != "postgres" {
+        return BuiltinResult::Error(format!("vector_search needs a postgres datasource, not {}", conn_type));
+    }
+
+    const EMBEDDING: &str = "___vector_search_embedding___";
+    ctx.insert(EMBEDDING.to_string(), JsonValue::String(format!("[{}]", numbers.join(","))));
+    let returned: Vec<&str> = columns
+        .iter()
+        .filter(|(_, sql_type)| !is_vector_column(sql_type))
+        .map(|(column, _)| column.as_str())
+        .collect();
+    let query = vector_search_sql(name, field, &returned, top_k, SchemaOptions::of(name, &state.doc));
+    let result = execute_statement(&conn_type, ds_name, state, ctx, &[query, EMBEDDING.to_string()], target).await;
+    ctx.remove(EMBEDDING);
+    result
+}
+
+/// The pgvector query behind `vector_search`, with the embedding bound to `$1`. `<=>` is
+/// the cosine distance, so the similarity is one minus it.
+fn vector_search_sql(table: &str, field: &str, columns: &[&str], top_k: usize, options: SchemaOptions) -> String {
+    let mut selected = vec!["id"];
+    selected.extend(columns);
+    format!(
+        "SELECT {}, 1 - ({field} <=> $1::vector) AS score FROM {} WHERE {field} IS NOT NULL{} \
+         ORDER BY {field} <=> $1::vector LIMIT {}",
+        selected.join(", "),
+        table,
+        live_rows(options),
+        top_k,
+    )
+}
+
+pub async fn delete_from_datasource(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let options = SchemaOptions::of(name, &state.doc);
+    if is_record_store(&conn_type) {
+        let deleted = match (open_record_store(name, ds_name, state).await, id_from_ctx(name, state, ctx)) {
+            (Ok(store), Ok(id)) if options.soft_delete => soft_delete_record(store, id, &conn_type).await,
+            (Ok(store), Ok(id)) => record_store::delete(store, id).await,
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        return deleted.err().unwrap_or(BuiltinResult::Ok);
+    }
+    let id = match id_sql_from_ctx(name, state, ctx) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let query = if options.soft_delete {
+        format!(
+            "UPDATE {} SET deleted_at = {} WHERE id = {} AND deleted_at IS NULL",
+            name,
+            format_sql_value(&now_for(&conn_type)),
+            id
+        )
+    } else {
+        format!("DELETE FROM {} WHERE id = {}", name, id)
+    };
+
+    execute_query(&conn_type, ds_name, state, ctx, query, assign_to).await
+}
+
+/// Marks a stored record deleted; one already deleted is not found.
+async fn soft_delete_record(store: RecordStore, id: JsonValue, conn_type: &str) -> Result<(), BuiltinResult> {
+    if is_deleted(&record_store::fetch(store.clone(), id.clone()).await?) {
+        return Err(BuiltinResult::Respond(404, "no record found".into()));
+    }
+    let mut fields = serde_json::Map::new();
+    fields.insert(DELETED_AT.to_string(), now_for(conn_type));
+    record_store::update(store, id, fields, &[]).await.map(|_| ())
 }
 
 pub async fn upsert_into_datasource(
@@ -427,8 +1541,12 @@ pub async fn upsert_into_datasource(
     state: &AppState,
     ctx: &mut Context,
 ) -> BuiltinResult {
-    let id = get_id_from_ctx(ctx);
-    if !id.is_empty() && id != "0" {
+    let has_id = match get_id_from_ctx(ctx) {
+        Some(JsonValue::String(s)) => !s.is_empty() && s != "0",
+        Some(JsonValue::Number(n)) => n.as_f64() != Some(0.0),
+        _ => false,
+    };
+    if has_id {
         return update_datasource(name, args, state, ctx).await;
     }
     insert_into_datasource(name, args, state, ctx).await
@@ -445,12 +1563,23 @@ pub async fn insert_into_datasource(
         Ok(v) => v,
         Err(e) => return e,
     };
-    let obj = match ctx.get("body") {
-        Some(v) => match v.as_object() {
-            Some(o) => o,
-            None => return BuiltinResult::Error("body is not an object".into()),
-        },
-        None => return BuiltinResult::Error("body missing".into()),
+    let options = SchemaOptions::of(name, &state.doc);
+    if is_record_store(&conn_type) {
+        let inserted = match (open_record_store(name, ds_name, state).await, stamped_body(ctx, options, &conn_type, true)) {
+            (Ok(store), Ok(body)) => {
+                let unique = state.schemas.get(name).map(unique_fields).unwrap_or_default();
+                record_store::insert(store, body, schema_id_type(&state.schemas, name), &unique).await
+            }
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        return match inserted {
+            Ok(record) => store_result(ctx, Some("created"), record),
+            Err(e) => e,
+        };
+    }
+    let obj = match stamped_body(ctx, options, &conn_type, true) {
+        Ok(obj) => obj,
+        Err(e) => return e,
     };
     let fields: Vec<String> = obj.keys().cloned().collect();
     let values: Vec<String> = obj.values().map(format_sql_value).collect();
@@ -479,29 +1608,363 @@ pub async fn update_datasource(
         Ok(v) => v,
         Err(e) => return e,
     };
-    let id = get_id_from_ctx(ctx);
-    let obj = match ctx.get("body") {
-        Some(v) => match v.as_object() {
-            Some(o) => o,
-            None => return BuiltinResult::Error("body is not an object".into()),
-        },
-        None => return BuiltinResult::Error("body missing".into()),
+    let options = SchemaOptions::of(name, &state.doc);
+    let is_field = |field: &str| matches!(schema_section.kv.get(field), Some(Value::String(_)));
+    if is_record_store(&conn_type) {
+        let updated = match (
+            open_record_store(name, ds_name, state).await,
+            id_from_ctx(name, state, ctx),
+            stamped_body(ctx, options, &conn_type, false),
+        ) {
+            (Ok(store), Ok(id), Ok(body)) => {
+                let fields = body.into_iter().filter(|(k, _)| is_field(k) || k == UPDATED_AT).collect();
+                match record_store::fetch(store.clone(), id.clone()).await {
+                    Ok(record) if is_deleted(&record) => Err(BuiltinResult::Respond(404, "no record found".into())),
+                    Ok(_) => record_store::update(store, id, fields, &unique_fields(schema_section)).await,
+                    Err(e) => Err(e),
+                }
+            }
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Err(e),
+        };
+        return match updated {
+            Ok(record) => store_result(ctx, Some("data_object"), record),
+            Err(e) => e,
+        };
+    }
+    let id = match id_sql_from_ctx(name, state, ctx) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let obj = match stamped_body(ctx, options, &conn_type, false) {
+        Ok(obj) => obj,
+        Err(e) => return e,
     };
 
-    let assignments: Vec<String> = schema_section
-        .kv
-        .keys()
-        .filter_map(|f| {
-            obj.get(f)
-                .map(|v| format!("{} = {}", f, format_sql_value(v)))
-        })
+    let assignments: Vec<String> = obj
+        .iter()
+        .filter(|(f, _)| is_field(f) || f.as_str() == UPDATED_AT)
+        .map(|(f, v)| format!("{} = {}", f, format_sql_value(v)))
         .collect();
     let query = format!(
-        "UPDATE {} SET {} WHERE id = {}",
+        "UPDATE {} SET {} WHERE id = {}{}",
         name,
         assignments.join(", "),
-        id
+        id,
+        live_rows(options)
     );
 
     execute_query(&conn_type, ds_name, state, ctx, query, None).await
 }
+
+// --- Bulk Operations ---
+
+fn item_result(index: usize, status: u16, key: &str, value: JsonValue) -> JsonValue {
+    serde_json::json!({ "index": index, "status": status, key: value })
+}
+
+/// A failed item, keeping structured error bodies (such as `409` conflicts) as JSON.
+fn item_error(index: usize, status: u16, message: String) -> JsonValue {
+    let error = serde_json::from_str::<JsonValue>(&message)
+        .ok()
+        .filter(JsonValue::is_object)
+        .unwrap_or(JsonValue::String(message));
+    item_result(index, status, "error", error)
+}
+
+/// `{succeeded, failed, results}`, where `results` has an entry per item in order.
+fn bulk_summary(results: Vec<JsonValue>) -> JsonValue {
+    let failed = results.iter().filter(|r| r["status"].as_u64().unwrap_or(500) >= 400).count();
+    serde_json::json!({ "succeeded": results.len() - failed, "failed": failed, "results": results })
+}
+
+fn body_array(ctx: &Context, what: &str) -> Result<Vec<JsonValue>, BuiltinResult> {
+    match ctx.get("body") {
+        Some(JsonValue::Array(items)) => Ok(items.clone()),
+        _ => Err(BuiltinResult::Respond(400, format!("expected a JSON array of {}", what))),
+    }
+}
+
+/// Checks one bulk item against the schema as `validate body #<Schema>` would.
+fn validate_item(
+    name: &str,
+    item: JsonValue,
+    state: &AppState,
+    ctx: &mut Context,
+) -> Result<serde_json::Map<String, JsonValue>, (u16, String)> {
+    const ITEM: &str = "___bulk_item___";
+    let JsonValue::Object(record) = item else {
+        return Err((400, "item is not an object".to_string()));
+    };
+    ctx.insert(ITEM.to_string(), JsonValue::Object(record.clone()));
+    let validated = builtin_validate(&[ITEM.to_string(), format!("#{}", name)], ctx, &state.schemas, &state.doc);
+    ctx.remove(ITEM);
+    match validated {
+        BuiltinResult::Ok => Ok(record),
+        BuiltinResult::Respond(status, message) => Err((status, message)),
+        BuiltinResult::Error(message) => Err((500, message)),
+    }
+}
+
+/// `datasource bulk_insert <Schema> into <ds>`: inserts every object of the body array
+/// that validates against the schema, the SQL ones in a single multi-row `INSERT`.
+/// Stores `{succeeded, failed, results}` with `{index, status, record | error}` per item.
+pub async fn bulk_insert(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let items = match body_array(ctx, "records") {
+        Ok(items) => items,
+        Err(e) => return e,
+    };
+    let options = SchemaOptions::of(name, &state.doc);
+    let mut results = vec![JsonValue::Null; items.len()];
+    let mut valid = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match validate_item(name, item, state, ctx) {
+            Ok(record) => valid.push((index, stamp(record, options, &conn_type, true))),
+            Err((status, message)) => results[index] = item_error(index, status, message),
+        }
+    }
+
+    if is_record_store(&conn_type) {
+        let store = match open_record_store(name, ds_name, state).await {
+            Ok(store) => store,
+            Err(e) => return e,
+        };
+        let unique = state.schemas.get(name).map(unique_fields).unwrap_or_default();
+        let id_type = schema_id_type(&state.schemas, name);
+        for (index, record) in valid {
+            results[index] = match record_store::insert(store.clone(), record, id_type, &unique).await {
+                Ok(record) => item_result(index, 201, "record", record),
+                Err(BuiltinResult::Respond(status, message)) => item_error(index, status, message),
+                Err(e) => return e,
+            };
+        }
+    } else if !valid.is_empty() {
+        const INSERTED: &str = "___bulk_inserted___";
+        let mut columns: Vec<&String> = valid.iter().flat_map(|(_, record)| record.keys()).collect();
+        columns.sort();
+        columns.dedup();
+        let rows: Vec<String> = valid
+            .iter()
+            .map(|(_, record)| {
+                let values: Vec<String> = columns
+                    .iter()
+                    .map(|c| record.get(*c).map(format_sql_value).unwrap_or_else(|| "DEFAULT".to_string()))
+                    .collect();
+                format!("({})", values.join(", "))
+            })
+            .collect();
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES {}{}",
+            name,
+            columns.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", "),
+            rows.join(", "),
+            // MySQL has no RETURNING, so its results carry the records as sent
+            if conn_type == "postgres" { " RETURNING *" } else { "" }
+        );
+        let inserted = match execute_query(&conn_type, ds_name, state, ctx, query, Some(INSERTED)).await {
+            BuiltinResult::Ok => match ctx.remove(INSERTED) {
+                Some(JsonValue::Array(rows)) => Some(rows),
+                _ => Some(Vec::new()),
+            },
+            // One row the database rejects fails the whole statement
+            BuiltinResult::Respond(status, message) => {
+                for (index, _) in &valid {
+                    results[*index] = item_error(*index, status, message.clone());
+                }
+                None
+            }
+            e => return e,
+        };
+        if let Some(rows) = inserted {
+            for (position, (index, record)) in valid.into_iter().enumerate() {
+                let record = rows.get(position).cloned().unwrap_or(JsonValue::Object(record));
+                results[index] = item_result(index, 201, "record", record);
+            }
+        }
+    }
+    store_result(ctx, assign_to, bulk_summary(results))
+}
+
+/// `datasource bulk_delete <Schema> from <ds>`: deletes (or soft deletes) the records
+/// whose ids the body array lists. Stores `{succeeded, failed, results}` with
+/// `{index, status, id | error}` per id; unknown ids are `404`s.
+pub async fn bulk_delete(
+    name: &str,
+    args: &[String],
+    state: &AppState,
+    ctx: &mut Context,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let ds_name = args.get(1).map(|s| s.as_str()).unwrap_or("");
+    let (_, conn_type) = match get_pool_details(ds_name, state).await {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let items = match body_array(ctx, "ids") {
+        Ok(items) => items,
+        Err(e) => return e,
+    };
+    let options = SchemaOptions::of(name, &state.doc);
+    let id_type = schema_id_type(&state.schemas, name);
+    let mut results = vec![JsonValue::Null; items.len()];
+    let mut ids = Vec::new();
+    for (index, raw) in items.iter().enumerate() {
+        match coerce_id(raw, id_type) {
+            Ok(id) => ids.push((index, id)),
+            Err(e) => results[index] = item_error(index, 400, e),
+        }
+    }
+
+    if is_record_store(&conn_type) {
+        let store = match open_record_store(name, ds_name, state).await {
+            Ok(store) => store,
+            Err(e) => return e,
+        };
+        for (index, id) in ids {
+            let deleted = if options.soft_delete {
+                soft_delete_record(store.clone(), id.clone(), &conn_type).await
+            } else {
+                record_store::delete(store.clone(), id.clone()).await
+            };
+            results[index] = match deleted {
+                Ok(()) => item_result(index, 204, "id", id),
+                Err(BuiltinResult::Respond(status, message)) => item_error(index, status, message),
+                Err(e) => return e,
+            };
+        }
+    } else if !ids.is_empty() {
+        const EXISTING: &str = "___bulk_existing___";
+        let list: Vec<String> = ids.iter().map(|(_, id)| format_sql_value(id)).collect();
+        let select = format!("SELECT id FROM {} WHERE id IN ({}){}", name, list.join(", "), live_rows(options));
+        let existing: HashSet<String> = match execute_query(&conn_type, ds_name, state, ctx, select, Some(EXISTING)).await {
+            BuiltinResult::Ok => match ctx.remove(EXISTING) {
+                Some(JsonValue::Array(rows)) => rows.iter().filter_map(|r| r.get("id")).map(join_key).collect(),
+                _ => HashSet::new(),
+            },
+            other => return other,
+        };
+        let found: Vec<String> = ids
+            .iter()
+            .filter(|(_, id)| existing.contains(&join_key(id)))
+            .map(|(_, id)| format_sql_value(id))
+            .collect();
+        if !found.is_empty() {
+            let statement = if options.soft_delete {
+                format!(
+                    "UPDATE {} SET deleted_at = {} WHERE id IN ({}) AND deleted_at IS NULL",
+                    name,
+                    format_sql_value(&now_for(&conn_type)),
+                    found.join(", ")
+                )
+            } else {
+                format!("DELETE FROM {} WHERE id IN ({})", name, found.join(", "))
+            };
+            match execute_query(&conn_type, ds_name, state, ctx, statement, None).await {
+                BuiltinResult::Ok => {}
+                other => return other,
+            }
+        }
+        for (index, id) in ids {
+            results[index] = if existing.contains(&join_key(&id)) {
+                item_result(index, 204, "id", id)
+            } else {
+                item_error(index, 404, "no record found".to_string())
+            };
+        }
+    }
+    store_result(ctx, assign_to, bulk_summary(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn plan_evolution_adds_missing_columns_only_when_enabled() {
+        let fields = pairs(&[("name", "TEXT"), ("price", "FLOAT"), ("inStock", "BOOLEAN")]);
+        let existing = pairs(&[("id", "integer"), ("name", "text"), ("price", "text")]);
+
+        assert_eq!(
+            plan_evolution("Product", "db", &fields, &existing, true),
+            vec!["ALTER TABLE Product ADD COLUMN inStock BOOLEAN".to_string()]
+        );
+        assert!(plan_evolution("Product", "db", &fields, &existing, false).is_empty());
+        assert!(plan_evolution("Product", "db", &fields, &[], true).is_empty());
+    }
+
+    #[test]
+    fn column_types_match_across_backends() {
+        assert!(column_type_matches("FLOAT", "double precision"));
+        assert!(column_type_matches("BOOLEAN", "tinyint"));
+        assert!(column_type_matches("TIMESTAMP", "timestamp without time zone"));
+        assert!(!column_type_matches("FLOAT", "text"));
+        assert!(column_type_matches("VECTOR(3)", "USER-DEFINED"));
+        assert!(!column_type_matches("VECTOR(3)", "text"));
+    }
+
+    #[test]
+    fn vector_fields_become_pgvector_columns_ranked_by_cosine_distance() {
+        let doc = crate::rune_parser::parse_rune(
+            "#!RUNE\n@Schema/Note\ntext = string\nembedding = vector(3)\nsoft_delete = true\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        let mut columns = schema_columns(&schemas["Note"], &doc).unwrap();
+        columns.sort();
+        assert_eq!(
+            columns,
+            pairs(&[("deleted_at", "TIMESTAMP"), ("embedding", "VECTOR(3)"), ("text", "TEXT")])
+        );
+        assert_eq!(format_sql_value(&serde_json::json!([0.5, 1, -2])), "'[0.5,1,-2]'");
+        assert_eq!(
+            vector_search_sql("Note", "embedding", &["text"], 3, SchemaOptions::of("Note", &doc)),
+            "SELECT id, text, 1 - (embedding <=> $1::vector) AS score FROM Note WHERE embedding IS NOT NULL \
+             AND deleted_at IS NULL ORDER BY embedding <=> $1::vector LIMIT 3"
+        );
+    }
+
+    #[test]
+    fn referenced_tables_are_created_first_with_foreign_keys() {
+        let doc = crate::rune_parser::parse_rune(
+            "#!RUNE\n@Schema/Author\nname = string\n\n@Schema/Book\ntitle = string\nauthor_id = ref(Author.id)\n\n@Schema/Review\nbook_id = ref(Book)\nreply_to = ref(Review.id)\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        assert_eq!(referenced_tables("Review", &schemas), vec!["Author".to_string(), "Book".to_string()]);
+
+        let mut columns = schema_columns(&schemas["Book"], &doc).unwrap();
+        columns.sort();
+        assert_eq!(
+            table_definition(&columns, &schemas["Book"]),
+            "author_id INTEGER, title TEXT, FOREIGN KEY (author_id) REFERENCES Author(id)"
+        );
+    }
+
+    #[test]
+    fn schema_id_type_reads_every_declaration_form() {
+        let doc = crate::rune_parser::parse_rune(
+            "#!RUNE\n@Schema/Lower\nid = string\n\n@Schema/Upper\nid = String\n\n@Schema/Fields\nfields:\n    id: String\n    name: String\n\n@Schema/Ref\nid = ref(Fields)\n\n@Schema/Plain\nname = string\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        for name in ["Lower", "Upper", "Fields", "Ref"] {
+            assert_eq!(schema_id_type(&schemas, name), "string", "{}", name);
+        }
+        assert_eq!(schema_id_type(&schemas, "Plain"), "number");
+        assert_eq!(schema_id_type(&schemas, "Missing"), "number");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::resolve_path;
+use chrono::format::StrftimeItems;
+use chrono::{DateTime, Duration, Months, NaiveDate, NaiveDateTime, SecondsFormat, SubsecRound, TimeZone, Utc};
+use serde_json::Value as JsonValue;
+
+/// Parses an ISO-8601 timestamp: RFC 3339 (`2024-05-01T12:00:00Z`), a timestamp without
+/// an offset (`2024-05-01T12:00:00`, `2024-05-01 12:00:00`, read as UTC) or a bare date.
+pub fn parse_datetime(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(naive.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|naive| naive.and_utc())
+}
+
+/// Formats a timestamp the way every date builtin returns it: RFC 3339 in UTC.
+pub fn to_iso(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(3)
+}
+
+/// A date argument: `now`, a context path, an ISO-8601 string or unix seconds.
+fn resolve_datetime(ctx: &Context, arg: &str) -> Option<DateTime<Utc>> {
+    let value = resolve_path(ctx, arg, None).unwrap_or_else(|| JsonValue::String(arg.to_string()));
+    match value {
+        JsonValue::String(s) if s == "now" => Some(now()),
+        JsonValue::String(s) => parse_datetime(&s),
+        JsonValue::Number(n) => {
+            let secs = n.as_f64()?;
+            Utc.timestamp_millis_opt((secs * 1000.0).round() as i64).single()
+        }
+        _ => None,
+    }
+}
+
+/// Renders `dt` as `iso`, `unix`, `unix_ms` or a strftime pattern such as `%Y-%m-%d`.
+fn format_datetime(dt: &DateTime<Utc>, fmt: &str) -> Result<JsonValue, String> {
+    match fmt {
+        "iso" | "rfc3339" => Ok(JsonValue::String(to_iso(dt))),
+        "unix" => Ok(JsonValue::from(dt.timestamp())),
+        "unix_ms" => Ok(JsonValue::from(dt.timestamp_millis())),
+        _ => {
+            let items = StrftimeItems::new(fmt)
+                .parse()
+                .map_err(|_| format!("invalid date format '{}'", fmt))?;
+            Ok(JsonValue::String(dt.format_with_items(items.iter()).to_string()))
+        }
+    }
+}
+
+/// `now [format]` — the current UTC time, as ISO-8601 unless a format is given.
+pub fn builtin_now(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let fmt = args.first().map(String::as_str).unwrap_or("iso");
+    match format_datetime(&now(), fmt) {
+        Ok(value) => store_result(ctx, assign_to, value),
+        Err(e) => BuiltinResult::Error(format!("now: {}", e)),
+    }
+}
+
+/// `date.format <date> <format>`
+pub fn builtin_date_format(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 2 {
+        return BuiltinResult::Error("date.format requires a date and a format".to_string());
+    }
+    let Some(dt) = resolve_datetime(ctx, &args[0]) else {
+        return BuiltinResult::Respond(400, format!("date.format: invalid date '{}'", args[0]));
+    };
+    match format_datetime(&dt, &args[1..].join(" ")) {
+        Ok(value) => store_result(ctx, assign_to, value),
+        Err(e) => BuiltinResult::Error(format!("date.format: {}", e)),
+    }
+}
+
+/// `date.parse <value> [format]` — normalizes a date to ISO-8601. Without a format the
+/// value must already be ISO-8601 or unix seconds; with one it is read as strftime.
+pub fn builtin_date_parse(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(arg) = args.first() else {
+        return BuiltinResult::Error("date.parse requires a value".to_string());
+    };
+    let parsed = if args.len() > 1 {
+        let fmt = args[1..].join(" ");
+        let raw = match resolve_path(ctx, arg, None) {
+            Some(JsonValue::String(s)) => s,
+            Some(other) => other.to_string(),
+            None => arg.clone(),
+        };
+        DateTime::parse_from_str(&raw, &fmt)
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok()
+            .or_else(|| NaiveDateTime::parse_from_str(&raw, &fmt).ok().map(|n| n.and_utc()))
+            .or_else(|| {
+                NaiveDate::parse_from_str(&raw, &fmt)
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|n| n.and_utc())
+            })
+    } else {
+        resolve_datetime(ctx, arg)
+    };
+    match parsed {
+        Some(dt) => store_result(ctx, assign_to, JsonValue::String(to_iso(&dt))),
+        None => BuiltinResult::Respond(400, format!("date.parse: invalid date '{}'", arg)),
+    }
+}
+
+/// Applies one signed amount like `7d`, `-2h`, `30m`, `1mo` or `1y` to `dt`.
+fn add_amount(dt: DateTime<Utc>, amount: &str) -> Option<DateTime<Utc>> {
+    let split = amount
+        .char_indices()
+        .find(|(i, c)| c.is_ascii_alphabetic() && *i > 0)
+        .map(|(i, _)| i)?;
+    let (num, unit) = amount.split_at(split);
+    let n: i64 = num.parse().ok()?;
+    let months = |m: i64| -> Option<DateTime<Utc>> {
+        let delta = Months::new(u32::try_from(m.unsigned_abs()).ok()?);
+        if m >= 0 {
+            dt.checked_add_months(delta)
+        } else {
+            dt.checked_sub_months(delta)
+        }
+    };
+    let duration = match unit {
+        "ms" => Duration::try_milliseconds(n)?,
+        "s" => Duration::try_seconds(n)?,
+        "m" => Duration::try_minutes(n)?,
+        "h" => Duration::try_hours(n)?,
+        "d" => Duration::try_days(n)?,
+        "w" => Duration::try_weeks(n)?,
+        "mo" => return months(n),
+        "y" => return months(n.checked_mul(12)?),
+        _ => return None,
+    };
+    dt.checked_add_signed(duration)
+}
+
+/// `date.add <date> <amount>...` — amounts are `ms`, `s`, `m`, `h`, `d`, `w`, `mo` or `y`
+/// with an optional sign, e.g. `date.add now 7d` or `date.add created_at -1h 30m`.
+pub fn builtin_date_add(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 2 {
+        return BuiltinResult::Error("date.add requires a date and an amount like 7d".to_string());
+    }
+    let Some(mut dt) = resolve_datetime(ctx, &args[0]) else {
+        return BuiltinResult::Respond(400, format!("date.add: invalid date '{}'", args[0]));
+    };
+    for amount in &args[1..] {
+        match add_amount(dt, amount) {
+            Some(next) => dt = next,
+            None => return BuiltinResult::Error(format!("date.add: invalid amount '{}'", amount)),
+        }
+    }
+    store_result(ctx, assign_to, JsonValue::String(to_iso(&dt)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iso_8601_variants() {
+        let expected = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(parse_datetime("2024-05-01T12:00:00Z"), Some(expected));
+        assert_eq!(parse_datetime("2024-05-01T14:00:00+02:00"), Some(expected));
+        assert_eq!(parse_datetime("2024-05-01 12:00:00"), Some(expected));
+        assert_eq!(
+            parse_datetime("2024-05-01"),
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).single()
+        );
+        assert_eq!(parse_datetime("May 1st"), None);
+    }
+
+    #[test]
+    fn adds_signed_amounts() {
+        let dt = Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap();
+        assert_eq!(to_iso(&add_amount(dt, "1d").unwrap()), "2024-02-01T12:00:00Z");
+        assert_eq!(to_iso(&add_amount(dt, "-90m").unwrap()), "2024-01-31T10:30:00Z");
+        assert_eq!(to_iso(&add_amount(dt, "1mo").unwrap()), "2024-02-29T12:00:00Z");
+        assert_eq!(to_iso(&add_amount(dt, "-1y").unwrap()), "2023-01-31T12:00:00Z");
+        assert!(add_amount(dt, "7 days").is_none());
+        assert!(add_amount(dt, "d").is_none());
+    }
+}
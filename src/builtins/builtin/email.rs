@@ -0,0 +1,129 @@
+//! `@Smtp` configuration and the `email.send` builtin:
+//!
+//! ```rune
+//! @Smtp
+//! host = smtp.example.com
+//! port = 587
+//! username = $SMTP_USER$
+//! password = $SMTP_PASSWORD$
+//! from = "Bookshop <noreply@example.com>"
+//!
+//! @Route/POST /orders
+//! run:
+//!     parse-json
+//!     email.send body.email "Order received" "Thanks {body.name}, we got your order."
+//!     respond 201 body
+//! ```
+//!
+//! `tls` is `starttls` (the default), `tls` for implicit TLS (port 465), or `none` for
+//! plain local relays.
+
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+use crate::core::AppState;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::Value as JsonValue;
+
+fn smtp_section(doc: &RuneDocument) -> Option<&Section> {
+    doc.sections.iter().find(|s| s.path.first().map(String::as_str) == Some("Smtp"))
+}
+
+fn kv_text(section: &Section, key: &str) -> Option<String> {
+    match section.kv.get(key)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The SMTP transport described by an `@Smtp` section.
+fn transport(section: &Section) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+    let host = kv_text(section, "host").ok_or("@Smtp requires a host")?;
+    let tls = kv_text(section, "tls").unwrap_or_else(|| "starttls".to_string());
+    let mut builder = match tls.as_str() {
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).map_err(|e| e.to_string())?,
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).map_err(|e| e.to_string())?,
+        "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+        other => return Err(format!("@Smtp tls must be starttls, tls or none, not `{}`", other)),
+    };
+    if let Some(port) = section.kv.get("port").and_then(|v| v.as_u64()) {
+        let port = u16::try_from(port).map_err(|_| format!("@Smtp port {} is out of range", port))?;
+        builder = builder.port(port);
+    }
+    if let Some(username) = kv_text(section, "username") {
+        let password = kv_text(section, "password").unwrap_or_default();
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+    Ok(builder.build())
+}
+
+/// Recipients from a list, or a comma-separated string.
+fn recipients(value: JsonValue) -> Vec<String> {
+    match value {
+        JsonValue::Array(items) => items
+            .into_iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        JsonValue::String(s) => s.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `email.send <to> <subject> <body> [as html]` — sends a message through `@Smtp`.
+/// `to` is an address, a comma-separated string or a list; `{expr}` placeholders in the
+/// subject and body are filled from the context. Invalid recipients respond 400;
+/// configuration and delivery failures are step errors.
+pub async fn builtin_email_send(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    if args.len() < 3 {
+        return BuiltinResult::Error("email.send requires a recipient, a subject and a body".to_string());
+    }
+    let html = matches!(args.get(3..5), Some([as_kw, format]) if as_kw == "as" && format == "html");
+    let Some(section) = smtp_section(&app_state.doc) else {
+        return BuiltinResult::Error("email.send requires an @Smtp section".to_string());
+    };
+    let Some(from) = kv_text(section, "from").or_else(|| kv_text(section, "username")) else {
+        return BuiltinResult::Error("@Smtp requires a from address".to_string());
+    };
+    let from: Mailbox = match from.parse() {
+        Ok(mailbox) => mailbox,
+        Err(e) => return BuiltinResult::Error(format!("@Smtp from `{}`: {}", from, e)),
+    };
+
+    let to = crate::core::resolve_path(ctx, &args[0], None).unwrap_or_else(|| JsonValue::String(args[0].clone()));
+    let to = recipients(to);
+    if to.is_empty() {
+        return BuiltinResult::Respond(400, "email.send: no recipient".to_string());
+    }
+    let subject = expand_log_message(&arg_text(ctx, &args[1]), ctx);
+    let body = expand_log_message(&arg_text(ctx, &args[2]), ctx);
+
+    let mut builder = Message::builder().from(from).subject(subject);
+    for address in &to {
+        match address.parse::<Mailbox>() {
+            Ok(mailbox) => builder = builder.to(mailbox),
+            Err(e) => return BuiltinResult::Respond(400, format!("email.send: invalid recipient `{}`: {}", address, e)),
+        }
+    }
+    let content_type = if html { ContentType::TEXT_HTML } else { ContentType::TEXT_PLAIN };
+    let message = match builder.header(content_type).body(body) {
+        Ok(message) => message,
+        Err(e) => return BuiltinResult::Error(format!("email.send: {}", e)),
+    };
+    let mailer = match transport(section) {
+        Ok(mailer) => mailer,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    match mailer.send(message).await {
+        Ok(_) => store_result(ctx, assign_to, JsonValue::Bool(true)),
+        Err(e) => BuiltinResult::Error(format!("email.send: {}", e)),
+    }
+}
@@ -0,0 +1,102 @@
+//! The `exec` builtin, disabled unless the document allowlists the commands it may run:
+//!
+//! ```rune
+//! @App
+//! type = REST
+//! allow_exec = (scripts/thumbnail.sh git)
+//!
+//! @Route/POST /thumbnails
+//! run:
+//!     parse-json
+//!     result = exec scripts/thumbnail.sh body.path 200
+//!     respond 200 result.stdout
+//! ```
+//!
+//! Commands containing a `/` are resolved against the rune directory; bare names are
+//! looked up on `PATH`. Commands are run directly, never through a shell.
+
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::tokenizer::unquote;
+use crate::core::AppState;
+use crate::rune_ast::Value;
+use serde_json::{json, Value as JsonValue};
+use std::process::Stdio;
+use std::time::Duration;
+
+const EXEC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Commands listed in `@App allow_exec`.
+fn allowlist(app_state: &AppState) -> Vec<String> {
+    match app_state.doc.get_section("App").and_then(|s| s.kv.get("allow_exec")) {
+        Some(Value::List(items)) => items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+        Some(Value::String(s)) => s.split([',', ' ']).filter(|c| !c.is_empty()).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A command-line argument from a step token; lists expand to one argument per item.
+fn push_args(ctx: &mut Context, token: &str, out: &mut Vec<String>) {
+    let text = |value: JsonValue| match value {
+        JsonValue::String(s) => s,
+        other => other.to_string(),
+    };
+    match value_arg(ctx, token) {
+        JsonValue::Array(items) => out.extend(items.into_iter().map(text)),
+        value => out.push(text(value)),
+    }
+}
+
+/// `exec <command> [args...]` — runs an allowlisted command and stores
+/// `{stdout, stderr, code}`. Arguments are resolved from the context; a non-zero exit
+/// code is reported in `code` rather than failing the step.
+pub async fn builtin_exec(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some((command, rest)) = args.split_first() else {
+        return BuiltinResult::Error("exec requires a command".to_string());
+    };
+    let command = unquote(command).unwrap_or_else(|| command.clone());
+    if !allowlist(app_state).contains(&command) {
+        return BuiltinResult::Error(format!(
+            "exec: `{}` is not listed in @App allow_exec",
+            command
+        ));
+    }
+    let program = if command.contains('/') {
+        app_state.path.join(&command)
+    } else {
+        command.clone().into()
+    };
+    let mut argv = Vec::new();
+    for token in rest {
+        push_args(ctx, token, &mut argv);
+    }
+
+    let child = tokio::process::Command::new(&program)
+        .args(&argv)
+        .current_dir(&app_state.path)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(EXEC_TIMEOUT, child).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return BuiltinResult::Error(format!("exec {}: {}", command, e)),
+        Err(_) => {
+            return BuiltinResult::Error(format!(
+                "exec {}: timed out after {}s",
+                command,
+                EXEC_TIMEOUT.as_secs()
+            ))
+        }
+    };
+    let result = json!({
+        "stdout": String::from_utf8_lossy(&output.stdout).trim_end(),
+        "stderr": String::from_utf8_lossy(&output.stderr).trim_end(),
+        "code": output.status.code(),
+    });
+    store_result(ctx, assign_to, result)
+}
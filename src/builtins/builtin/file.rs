@@ -0,0 +1,142 @@
+//! File builtins scoped to the rune directory, for small JSON blobs and config files:
+//!
+//! ```rune
+//! settings = file.read config/settings.json
+//! file.write "data/{id}.json" body
+//! file.append logs/orders.jsonl body
+//! reports = file.list reports
+//! ```
+//!
+//! Paths may be quoted, come from the context, and contain `{expr}` placeholders. Absolute
+//! paths and paths leaving the rune directory are rejected with `400`.
+
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::path_utils::scoped_path;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::AppState;
+use serde_json::Value as JsonValue;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The in-directory path named by `token`, or the response rejecting it.
+fn path_arg(name: &str, ctx: &mut Context, token: &str, app_state: &AppState) -> Result<PathBuf, BuiltinResult> {
+    let filename = match value_arg(ctx, token) {
+        JsonValue::String(s) => s,
+        other => other.to_string(),
+    };
+    let filename = expand_log_message(&filename, ctx);
+    scoped_path(&filename, &app_state.path).map_err(|e| BuiltinResult::Respond(400, format!("{}: {}", name, e)))
+}
+
+/// File contents for a value: strings as-is, anything else as JSON.
+fn contents(value: &JsonValue, pretty: bool) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other if pretty => serde_json::to_string_pretty(other).unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) => std::fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+/// `file.read <path> [as json|text]` — the file's text, parsed when it is a `.json` file
+/// (or `as json` is given).
+pub fn builtin_file_read(args: &[String], ctx: &mut Context, app_state: &AppState, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(token) = args.first() else {
+        return BuiltinResult::Error("file.read requires a path".to_string());
+    };
+    let path = match path_arg("file.read", ctx, token, app_state) {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    let as_json = match args.get(1..3) {
+        Some([as_kw, format]) if as_kw == "as" => format == "json",
+        _ => path.extension().and_then(|e| e.to_str()) == Some("json"),
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => return BuiltinResult::Error(format!("file.read {}: {}", path.display(), e)),
+    };
+    let value = if as_json {
+        match serde_json::from_str(&text) {
+            Ok(value) => value,
+            Err(e) => return BuiltinResult::Error(format!("file.read {}: {}", path.display(), e)),
+        }
+    } else {
+        JsonValue::String(text)
+    };
+    store_result(ctx, assign_to, value)
+}
+
+/// `file.write <path> <value>` — replaces the file, creating missing directories. Strings
+/// are written as-is and other values as pretty-printed JSON.
+pub fn builtin_file_write(args: &[String], ctx: &mut Context, app_state: &AppState, assign_to: Option<&str>) -> BuiltinResult {
+    let [token, value, ..] = args else {
+        return BuiltinResult::Error("file.write requires a path and a value".to_string());
+    };
+    let path = match path_arg("file.write", ctx, token, app_state) {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    let value = value_arg(ctx, value);
+    match create_parent(&path).and_then(|_| std::fs::write(&path, contents(&value, true))) {
+        Ok(()) => store_result(ctx, assign_to, value),
+        Err(e) => BuiltinResult::Error(format!("file.write {}: {}", path.display(), e)),
+    }
+}
+
+/// `file.append <path> <value>` — adds the value as a line: strings as-is, anything else
+/// as compact JSON, so appending objects builds a JSON Lines file.
+pub fn builtin_file_append(args: &[String], ctx: &mut Context, app_state: &AppState, assign_to: Option<&str>) -> BuiltinResult {
+    let [token, value, ..] = args else {
+        return BuiltinResult::Error("file.append requires a path and a value".to_string());
+    };
+    let path = match path_arg("file.append", ctx, token, app_state) {
+        Ok(path) => path,
+        Err(response) => return response,
+    };
+    let value = value_arg(ctx, value);
+    let appended = create_parent(&path).and_then(|_| {
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", contents(&value, false))
+    });
+    match appended {
+        Ok(()) => store_result(ctx, assign_to, value),
+        Err(e) => BuiltinResult::Error(format!("file.append {}: {}", path.display(), e)),
+    }
+}
+
+/// `file.list [dir]` — sorted entry names in a directory (default: the rune directory);
+/// subdirectories end with `/`.
+pub fn builtin_file_list(args: &[String], ctx: &mut Context, app_state: &AppState, assign_to: Option<&str>) -> BuiltinResult {
+    let path = match args.first() {
+        Some(token) => match path_arg("file.list", ctx, token, app_state) {
+            Ok(path) => path,
+            Err(response) => return response,
+        },
+        None => app_state.path.clone(),
+    };
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(e) => return BuiltinResult::Error(format!("file.list {}: {}", path.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                format!("{}/", name)
+            } else {
+                name
+            }
+        })
+        .collect();
+    names.sort();
+    store_result(ctx, assign_to, JsonValue::Array(names.into_iter().map(JsonValue::String).collect()))
+}
@@ -1,10 +1,42 @@
+//! Step functions: `func` defines one inline, and `@Function` sections declare reusable
+//! blocks with named parameters:
+//!
+//! ```rune
+//! @Function/slugify(text)
+//! run:
+//!     lower = text.lower
+//!     return lower
+//!
+//! @Route/POST /posts
+//! run:
+//!     parse-json
+//!     slug = call slugify body.title
+//!     respond 201 slug
+//! ```
+//!
+//! `call` runs the function's `run:` steps in a context of their own, holding the
+//! constants, enums and the arguments bound to the parameters (missing ones are null), so
+//! nothing leaks between the function and its caller. `return <value>` ends the function
+//! and gives the value to the call; any other response, such as `respond 404`, ends the
+//! calling route too.
+
 use serde_json::Value;
-use crate::core::AppState;
-use crate::builtins::{Context, LAST_EXEC_RESULT};
+use crate::core::{constants, AppState};
+use crate::builtins::{store_result, Context, LAST_EXEC_RESULT, RESPONSE_CONTENT_TYPE};
 use crate::builtins::BuiltinResult;
-use crate::core::execute_steps_inner;
+use crate::builtins::builtin::collection::value_arg;
+use crate::core::errors::STEP_ERROR;
+use crate::core::{execute_steps_inner, run_compiled_steps};
+use crate::rune_ast::RuneDocument;
 use crate::util::{log, LogLevel};
 
+/// Set in the context of a `call`, to the number of calls it is nested in.
+const CALL_DEPTH: &str = "___call_depth___";
+/// Where `return` leaves its value in a `call`'s context.
+const RETURN_VALUE: &str = "___return_value___";
+/// Calls nested deeper than this fail, so runaway recursion can't exhaust the stack.
+const MAX_CALL_DEPTH: u64 = 16;
+
 pub async fn builtin_func(args: &[String], ctx: &mut Context) -> BuiltinResult {
     // First arg is function name, rest are steps
     if args.is_empty() {
@@ -64,4 +96,113 @@ pub async fn invoke_func(appstate: &AppState, name: &str, ctx: &mut Context, arg
     } else {
         BuiltinResult::Error(format!("Function '{}' not found or invalid", name))
     }
-}
\ No newline at end of file
+}
+/// Splits a `@Function` header such as `slugify(text, sep)` into its name and parameters.
+pub fn parse_signature(header: &str) -> Result<(&str, Vec<&str>), String> {
+    let Some((name, params)) = header.split_once('(') else {
+        return Ok((header.trim(), Vec::new()));
+    };
+    let params = params
+        .strip_suffix(')')
+        .ok_or_else(|| format!("@Function/{}: the parameter list is not closed", header))?;
+    let params: Vec<&str> = params.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+    let is_name = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !is_name(name.trim()) {
+        return Err(format!("@Function/{}: invalid function name", header));
+    }
+    if let Some(param) = params.iter().find(|p| !is_name(p)) {
+        return Err(format!("@Function/{}: invalid parameter '{}'", header, param));
+    }
+    Ok((name.trim(), params))
+}
+
+/// The `@Function` section declaring `name`.
+pub(crate) fn function_section<'a>(doc: &'a RuneDocument, name: &str) -> Option<&'a crate::rune_ast::Section> {
+    doc.sections.iter().find(|s| {
+        s.path.first().map(String::as_str) == Some("Function")
+            && s.path.get(1).and_then(|header| parse_signature(header).ok()).is_some_and(|(n, _)| n == name)
+    })
+}
+
+/// `call <function> [args...]` — runs a `@Function` with the arguments (context values or
+/// literals) bound to its parameters, and stores what it returns.
+pub async fn builtin_call(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some((name, call_args)) = args.split_first() else {
+        return BuiltinResult::Error("call requires a function name".to_string());
+    };
+    let Some(section) = function_section(&app_state.doc, name) else {
+        return BuiltinResult::Error(format!("call: no @Function/{} is declared", name));
+    };
+    let header = section.path.get(1).map(String::as_str).unwrap_or_default();
+    let params = match parse_signature(header) {
+        Ok((_, params)) => params,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    if call_args.len() > params.len() {
+        return BuiltinResult::Error(format!(
+            "call: {} takes {} argument(s) but was given {}",
+            name,
+            params.len(),
+            call_args.len()
+        ));
+    }
+    let depth = ctx.get(CALL_DEPTH).and_then(Value::as_u64).unwrap_or(0) + 1;
+    if depth > MAX_CALL_DEPTH {
+        return BuiltinResult::Error(format!("call: {} nested more than {} calls deep", name, MAX_CALL_DEPTH));
+    }
+    let Some(steps) = app_state.steps.get(section, "run") else {
+        return BuiltinResult::Error(format!("@Function/{} requires run: steps", header));
+    };
+
+    let mut frame = Context::new();
+    constants::seed_context(&app_state.doc, &mut frame);
+    for (i, param) in params.iter().enumerate() {
+        let value = call_args.get(i).map(|arg| value_arg(ctx, arg)).unwrap_or(Value::Null);
+        frame.insert(param.to_string(), value);
+    }
+    frame.insert(CALL_DEPTH.to_string(), Value::from(depth));
+    let response = Box::pin(run_compiled_steps(app_state, &steps, &mut frame)).await;
+    if let Some(Value::String(e)) = frame.remove(STEP_ERROR) {
+        return BuiltinResult::Error(format!("{}: {}", name, e));
+    }
+    match (frame.remove(RETURN_VALUE), response) {
+        (Some(value), _) => store_result(ctx, assign_to, value),
+        (None, None) => store_result(ctx, assign_to, Value::Null),
+        (None, Some((code, body))) => {
+            if let Some(content_type) = frame.remove(RESPONSE_CONTENT_TYPE) {
+                ctx.insert(RESPONSE_CONTENT_TYPE.to_string(), content_type);
+            }
+            BuiltinResult::Respond(code, body)
+        }
+    }
+}
+
+/// `return <value>` inside a `call`: ends the function with the value. `None` outside a
+/// call, where `return` responds instead.
+pub fn function_return(args: &[String], ctx: &mut Context) -> Option<BuiltinResult> {
+    if !ctx.contains_key(CALL_DEPTH) {
+        return None;
+    }
+    let value = args.first().map(|arg| value_arg(ctx, arg)).unwrap_or(Value::Null);
+    ctx.insert(RETURN_VALUE.to_string(), value);
+    Some(BuiltinResult::Respond(200, String::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_name_their_parameters() {
+        assert_eq!(parse_signature("slugify(text, sep)"), Ok(("slugify", vec!["text", "sep"])));
+        assert_eq!(parse_signature("now_utc()"), Ok(("now_utc", vec![])));
+        assert_eq!(parse_signature("now_utc"), Ok(("now_utc", vec![])));
+        assert!(parse_signature("slugify(text").is_err());
+        assert!(parse_signature("slugify(a.b)").is_err());
+    }
+}
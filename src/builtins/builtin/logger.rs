@@ -10,7 +10,7 @@ fn json_value_to_log_string(value: &serde_json::Value) -> String {
     }
 }
 
-fn expand_log_message(message: &str, ctx: &Context) -> String {
+pub(crate) fn expand_log_message(message: &str, ctx: &Context) -> String {
     let placeholder_regex = Regex::new(r"\{([^{}]+)\}").unwrap();
 
     placeholder_regex
@@ -100,3 +100,8 @@ pub async fn get_memory_value(key: &str) -> Option<Value> {
     let backend = get_backend().await;
     backend.get(key).await
 }
+
+pub async fn delete_memory(key: &str) {
+    let backend = get_backend().await;
+    backend.delete(key).await;
+}
@@ -1,5 +1,8 @@
 // src/builtins/datasource_mysql.rs
+use crate::builtins::builtin::date::to_iso;
 use crate::builtins::{BuiltinResult, Context};
+use crate::core::errors::conflict_body;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde_json::{Map, Value as JsonValue};
 use sqlx::{mysql::MySqlRow, Column, MySql, Pool, Row};
 
@@ -38,14 +41,28 @@ pub async fn create_table_mysql(name: &str, args: &[String], pool: &Pool<MySql>)
     }
 }
 
+/// The `(column, data_type)` pairs of an existing table, empty if it does not exist.
+pub async fn table_columns_mysql(name: &str, pool: &Pool<MySql>) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT CAST(column_name AS CHAR), CAST(data_type AS CHAR) FROM information_schema.columns \
+         WHERE table_schema = DATABASE() AND table_name = ?",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+}
+
 /// Built-in to execute a MySQL query and store results in context.
 /// Expected args: ["query_string", "param1", "param2", ...]
-pub async fn builtin_mysql_query(
+pub async fn builtin_mysql_query<'c, E>(
     args: &[String],
     ctx: &mut Context,
-    pool: &Pool<MySql>,
+    executor: E,
     assign_to: Option<&str>,
-) -> BuiltinResult {
+) -> BuiltinResult
+where
+    E: sqlx::Executor<'c, Database = MySql>,
+{
     if args.is_empty() {
         return BuiltinResult::Error("mysql: missing query argument".to_string());
     }
@@ -71,7 +88,7 @@ pub async fn builtin_mysql_query(
         }
     }
 
-    match query.fetch_all(pool).await {
+    match query.fetch_all(executor).await {
         Ok(rows) => {
             let json_rows: Vec<JsonValue> = rows.iter().map(row_to_json).collect();
             if let Some(target) = assign_to {
@@ -79,10 +96,26 @@ pub async fn builtin_mysql_query(
             }
             BuiltinResult::Ok
         }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            let duplicate = duplicate_entry(e.message());
+            BuiltinResult::Respond(
+                409,
+                conflict_body(duplicate.map(|(field, _)| field), duplicate.map(|(_, value)| value.into())),
+            )
+        }
         Err(e) => BuiltinResult::Error(format!("MySQL error: {}", e)),
     }
 }
 
+/// The column and value of a duplicate key error, from its message:
+/// `Duplicate entry 'a@example.com' for key 'users.email'` (MySQL 8 prefixes the table).
+fn duplicate_entry(message: &str) -> Option<(&str, &str)> {
+    let rest = message.strip_prefix("Duplicate entry '")?;
+    let (value, key) = rest.rsplit_once("' for key '")?;
+    let key = key.strip_suffix('\'')?;
+    Some((key.rsplit('.').next().unwrap_or(key), value))
+}
+
 /// Helper to convert a MySqlRow into a JSON Object
 fn row_to_json(row: &MySqlRow) -> JsonValue {
     let mut map = Map::new();
@@ -103,6 +136,9 @@ fn row_to_json(row: &MySqlRow) -> JsonValue {
             }))
             // Try to extract as bool
             .or_else(|_| row.try_get::<bool, &str>(name).map(JsonValue::Bool))
+            // Timestamps are returned as ISO-8601 strings
+            .or_else(|_| row.try_get::<DateTime<Utc>, &str>(name).map(|t| JsonValue::String(to_iso(&t))))
+            .or_else(|_| row.try_get::<NaiveDateTime, &str>(name).map(|t| JsonValue::String(to_iso(&t.and_utc()))))
             // Fallback to Null
             .unwrap_or(JsonValue::Null);
 
@@ -110,3 +146,17 @@ fn row_to_json(row: &MySqlRow) -> JsonValue {
     }
     JsonValue::Object(map)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_duplicate_entry_from_the_message() {
+        assert_eq!(
+            duplicate_entry("Duplicate entry 'ada@example.com' for key 'users.email'"),
+            Some(("email", "ada@example.com"))
+        );
+        assert_eq!(duplicate_entry("Duplicate entry 'it's' for key 'email'"), Some(("email", "it's")));
+    }
+}
@@ -0,0 +1,134 @@
+//! Object builtins for shaping response bodies without listing every key:
+//!
+//! ```rune
+//! profile = obj.pick user (name email)
+//! public = obj.omit user (password token)
+//! dto = obj.merge defaults body { source: "api" }
+//! obj.set user.profile.name body.name
+//! ```
+//!
+//! Arguments are the step's raw tokens, so quoted strings stay literals.
+
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::mutate_path;
+use crate::core::tokenizer::unquote;
+use serde_json::{Map, Value as JsonValue};
+
+/// The object named by `token` (a path, inline object or JSON).
+fn object_arg(ctx: &mut Context, name: &str, token: &str) -> Result<Map<String, JsonValue>, String> {
+    match value_arg(ctx, token) {
+        JsonValue::Object(map) => Ok(map),
+        _ => Err(format!("{}: `{}` is not an object", name, token)),
+    }
+}
+
+/// Key names from `(a b)`, `a, b`, a `[...]` JSON list or a context list.
+fn key_list(ctx: &mut Context, tokens: &[String]) -> Vec<String> {
+    if let [token] = tokens {
+        if !token.starts_with('(') {
+            if let JsonValue::Array(items) = value_arg(ctx, token) {
+                return items
+                    .into_iter()
+                    .map(|item| match item {
+                        JsonValue::String(s) => s,
+                        other => other.to_string(),
+                    })
+                    .collect();
+            }
+        }
+    }
+    tokens
+        .join(" ")
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|key| !key.is_empty())
+        .map(|key| unquote(key).unwrap_or_else(|| key.to_string()))
+        .collect()
+}
+
+/// `obj.merge <a> <b> [...]` — a shallow merge; later objects win on shared keys.
+pub fn builtin_obj_merge(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 2 {
+        return BuiltinResult::Error("obj.merge requires at least two objects".to_string());
+    }
+    let mut merged = Map::new();
+    for token in args {
+        match object_arg(ctx, "obj.merge", token) {
+            Ok(map) => merged.extend(map),
+            Err(e) => return BuiltinResult::Error(e),
+        }
+    }
+    store_result(ctx, assign_to, JsonValue::Object(merged))
+}
+
+/// `obj.pick <object> (key ...)` and `obj.omit <object> (key ...)` — a copy with only, or
+/// without, the listed keys.
+fn select_keys(name: &str, keep: bool, args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some((source, keys)) = args.split_first().filter(|(_, keys)| !keys.is_empty()) else {
+        return BuiltinResult::Error(format!("{} requires an object and a list of keys", name));
+    };
+    let map = match object_arg(ctx, name, source) {
+        Ok(map) => map,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    let keys = key_list(ctx, keys);
+    let selected: Map<String, JsonValue> = if keep {
+        keys.iter()
+            .filter_map(|key| map.get(key).map(|v| (key.clone(), v.clone())))
+            .collect()
+    } else {
+        map.into_iter().filter(|(key, _)| !keys.contains(key)).collect()
+    };
+    store_result(ctx, assign_to, JsonValue::Object(selected))
+}
+
+pub fn builtin_obj_pick(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    select_keys("obj.pick", true, args, ctx, assign_to)
+}
+
+pub fn builtin_obj_omit(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    select_keys("obj.omit", false, args, ctx, assign_to)
+}
+
+/// `obj.keys <object>` — the object's keys as a list.
+pub fn builtin_obj_keys(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(source) = args.first() else {
+        return BuiltinResult::Error("obj.keys requires an object".to_string());
+    };
+    match object_arg(ctx, "obj.keys", source) {
+        Ok(map) => store_result(ctx, assign_to, JsonValue::Array(map.into_iter().map(|(k, _)| JsonValue::String(k)).collect())),
+        Err(e) => BuiltinResult::Error(e),
+    }
+}
+
+/// `obj.set <path> <value>` — sets a nested path such as `user.profile.name`, creating
+/// missing objects on the way, and returns the value.
+pub fn builtin_obj_set(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let [path, value, ..] = args else {
+        return BuiltinResult::Error("obj.set requires a path and a value".to_string());
+    };
+    let value = value_arg(ctx, value);
+    match mutate_path(ctx, path, value.clone()) {
+        Ok(()) => store_result(ctx, assign_to, value),
+        Err(e) => BuiltinResult::Error(format!("obj.set: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn key_lists_accept_parens_commas_and_lists() {
+        let mut ctx = Context::new();
+        ctx.insert("fields".to_string(), json!(["a", "b"]));
+        let tokens = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(key_list(&mut ctx, &tokens("(name email)")), vec!["name", "email"]);
+        assert_eq!(key_list(&mut ctx, &tokens("name, \"email\"")), vec!["name", "email"]);
+        assert_eq!(key_list(&mut ctx, &tokens("(id)")), vec!["id"]);
+        assert_eq!(key_list(&mut ctx, &tokens("fields")), vec!["a", "b"]);
+    }
+}
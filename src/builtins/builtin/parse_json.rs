@@ -16,7 +16,13 @@ pub fn builtin_parse_json(
         "body"
     };
 
-    if let Some(JsonValue::String(s)) = ctx.get(value) {
+    // Bodies sent as JSON arrive parsed; the value is then only copied to the target
+    if let Some(parsed) = ctx.get(value).filter(|v| !v.is_string()).cloned() {
+        if target != value {
+            ctx.insert(target.into(), parsed.clone());
+        }
+        ctx.insert(LAST_EXEC_RESULT.to_string(), parsed);
+    } else if let Some(JsonValue::String(s)) = ctx.get(value) {
         match serde_json::from_str::<JsonValue>(s) {
             Ok(v) => {
                 ctx.insert(target.into(), v.clone());
@@ -0,0 +1,157 @@
+//! Connection pool statistics per datasource, reported by `GET /__admin/datasources`.
+//!
+//! Queries issued through a datasource acquire their connection via [`acquire`], which
+//! records how long the pool made them wait; failed connects and acquires are counted
+//! as failures.
+
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value as JsonValue};
+use sqlx::pool::PoolConnection;
+use sqlx::{Database, MySql, Pool, Postgres, Transaction};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A pool whose size can be reported.
+#[derive(Clone)]
+pub(crate) enum PoolRef {
+    Postgres(Pool<Postgres>),
+    MySql(Pool<MySql>),
+}
+
+impl PoolRef {
+    fn size(&self) -> u32 {
+        match self {
+            PoolRef::Postgres(p) => p.size(),
+            PoolRef::MySql(p) => p.size(),
+        }
+    }
+
+    fn idle(&self) -> usize {
+        match self {
+            PoolRef::Postgres(p) => p.num_idle(),
+            PoolRef::MySql(p) => p.num_idle(),
+        }
+    }
+
+    fn max_connections(&self) -> u32 {
+        match self {
+            PoolRef::Postgres(p) => p.options().get_max_connections(),
+            PoolRef::MySql(p) => p.options().get_max_connections(),
+        }
+    }
+}
+
+pub(crate) trait TrackedPool {
+    const KIND: &'static str;
+    fn pool_ref(&self) -> PoolRef;
+}
+
+impl TrackedPool for Pool<Postgres> {
+    const KIND: &'static str = "postgres";
+    fn pool_ref(&self) -> PoolRef {
+        PoolRef::Postgres(self.clone())
+    }
+}
+
+impl TrackedPool for Pool<MySql> {
+    const KIND: &'static str = "mysql";
+    fn pool_ref(&self) -> PoolRef {
+        PoolRef::MySql(self.clone())
+    }
+}
+
+#[derive(Default)]
+struct PoolStats {
+    kind: &'static str,
+    pool: Option<PoolRef>,
+    acquires: u64,
+    failures: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+    last_wait: Duration,
+}
+
+static POOL_STATS: Lazy<Mutex<BTreeMap<String, PoolStats>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+fn with_stats(datasource: &str, kind: &'static str, update: impl FnOnce(&mut PoolStats)) {
+    let mut stats = POOL_STATS.lock().unwrap();
+    let entry = stats.entry(datasource.to_string()).or_default();
+    entry.kind = kind;
+    update(entry);
+}
+
+/// Counts a failed connect for `datasource`.
+pub(crate) fn record_failure(datasource: &str, kind: &'static str) {
+    with_stats(datasource, kind, |stats| stats.failures += 1);
+}
+
+fn record_acquire<DB: Database>(datasource: &str, pool: &Pool<DB>, waited: Duration, ok: bool)
+where
+    Pool<DB>: TrackedPool,
+{
+    with_stats(datasource, <Pool<DB> as TrackedPool>::KIND, |stats| {
+        stats.pool = Some(pool.pool_ref());
+        if !ok {
+            stats.failures += 1;
+            return;
+        }
+        stats.acquires += 1;
+        stats.total_wait += waited;
+        stats.max_wait = stats.max_wait.max(waited);
+        stats.last_wait = waited;
+    });
+}
+
+/// Acquires a connection from `pool`, recording the wait against `datasource`.
+pub(crate) async fn acquire<DB: Database>(datasource: &str, pool: &Pool<DB>) -> Result<PoolConnection<DB>, sqlx::Error>
+where
+    Pool<DB>: TrackedPool,
+{
+    let started = Instant::now();
+    let conn = pool.acquire().await;
+    record_acquire(datasource, pool, started.elapsed(), conn.is_ok());
+    conn
+}
+
+/// Starts a transaction on a connection from `pool`, recorded like [`acquire`].
+pub(crate) async fn begin<DB: Database>(datasource: &str, pool: &Pool<DB>) -> Result<Transaction<'static, DB>, sqlx::Error>
+where
+    Pool<DB>: TrackedPool,
+{
+    let started = Instant::now();
+    let tx = pool.begin().await;
+    record_acquire(datasource, pool, started.elapsed(), tx.is_ok());
+    tx
+}
+
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+}
+
+/// Stats for every datasource that has connected or tried to, keyed by datasource name.
+pub fn snapshot() -> JsonValue {
+    let stats = POOL_STATS.lock().unwrap();
+    let mut out = Map::new();
+    for (name, s) in stats.iter() {
+        let (size, idle, max) = match &s.pool {
+            Some(pool) => (pool.size(), pool.idle(), pool.max_connections()),
+            None => (0, 0, 0),
+        };
+        let avg = if s.acquires > 0 { s.total_wait / s.acquires as u32 } else { Duration::ZERO };
+        out.insert(
+            name.clone(),
+            json!({
+                "type": s.kind,
+                "size": size,
+                "idle": idle,
+                "in_use": (size as usize).saturating_sub(idle),
+                "max_connections": max,
+                "acquires": s.acquires,
+                "failures": s.failures,
+                "wait_ms": { "last": millis(s.last_wait), "avg": millis(avg), "max": millis(s.max_wait) },
+            }),
+        );
+    }
+    JsonValue::Object(out)
+}
@@ -1,5 +1,8 @@
 // src/builtins/datasource_postgres.rs
+use crate::builtins::builtin::date::to_iso;
 use crate::builtins::{BuiltinResult, Context};
+use crate::core::errors::conflict_body;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde_json::{Map, Value as JsonValue};
 use sqlx::{postgres::PgRow, Column, Pool, Postgres, Row};
 
@@ -52,14 +55,31 @@ pub async fn create_table_postgres(
     }
 }
 
+/// The `(column, data_type)` pairs of an existing table, empty if it does not exist.
+pub async fn table_columns_postgres(
+    name: &str,
+    pool: &Pool<Postgres>,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT column_name::text, data_type::text FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1",
+    )
+    .bind(name.to_lowercase())
+    .fetch_all(pool)
+    .await
+}
+
 /// Built-in to execute a PostgreSQL query and store results in context.
 /// Expected args: ["query_string", "param1", "param2", ...]
-pub async fn builtin_postgres_query(
+pub async fn builtin_postgres_query<'c, E>(
     args: &[String],
     ctx: &mut Context,
-    pool: &Pool<Postgres>,
+    executor: E,
     assign_to: Option<&str>,
-) -> BuiltinResult {
+) -> BuiltinResult
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
     if args.is_empty() {
         return BuiltinResult::Error("postgres: missing query argument".to_string());
     }
@@ -85,7 +105,7 @@ pub async fn builtin_postgres_query(
         }
     }
 
-    match query.fetch_all(pool).await {
+    match query.fetch_all(executor).await {
         Ok(rows) => {
             let json_rows: Vec<JsonValue> = rows.iter().map(row_to_json).collect();
             if let Some(target) = assign_to {
@@ -93,10 +113,29 @@ pub async fn builtin_postgres_query(
             }
             BuiltinResult::Ok
         }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            let duplicate = e
+                .try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+                .and_then(|e| e.detail())
+                .and_then(duplicate_key);
+            BuiltinResult::Respond(
+                409,
+                conflict_body(duplicate.map(|(field, _)| field), duplicate.map(|(_, value)| value.into())),
+            )
+        }
         Err(e) => BuiltinResult::Error(format!("Postgres error: {}", e)),
     }
 }
 
+/// The column and value of a unique violation, from its detail:
+/// `Key (email)=(a@example.com) already exists.`
+fn duplicate_key(detail: &str) -> Option<(&str, &str)> {
+    let rest = detail.strip_prefix("Key (")?;
+    let (column, rest) = rest.split_once(")=(")?;
+    let value = rest.strip_suffix(") already exists.")?;
+    (!column.contains(',')).then_some((column, value))
+}
+
 /// Helper to convert a PgRow into a JSON Object
 fn row_to_json(row: &PgRow) -> JsonValue {
     let mut map = Map::new();
@@ -117,6 +156,9 @@ fn row_to_json(row: &PgRow) -> JsonValue {
             }))
             // Try to extract as bool
             .or_else(|_| row.try_get::<bool, &str>(name).map(JsonValue::Bool))
+            // Timestamps are returned as ISO-8601 strings
+            .or_else(|_| row.try_get::<DateTime<Utc>, &str>(name).map(|t| JsonValue::String(to_iso(&t))))
+            .or_else(|_| row.try_get::<NaiveDateTime, &str>(name).map(|t| JsonValue::String(to_iso(&t.and_utc()))))
             // Fallback to Null
             .unwrap_or(JsonValue::Null);
 
@@ -124,3 +166,17 @@ fn row_to_json(row: &PgRow) -> JsonValue {
     }
     JsonValue::Object(map)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_duplicate_key_from_the_detail() {
+        assert_eq!(
+            duplicate_key("Key (email)=(ada@example.com) already exists."),
+            Some(("email", "ada@example.com"))
+        );
+        assert_eq!(duplicate_key("Key (team_id, name)=(1, x) already exists."), None);
+    }
+}
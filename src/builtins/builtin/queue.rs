@@ -0,0 +1,209 @@
+//! `@Worker` sections and the `enqueue` builtin, an in-process job queue:
+//!
+//! ```rune
+//! @Worker/send_welcome_email
+//! concurrency = 4
+//! retries = 3
+//! run:
+//!     email.send job.email "Welcome" "Hi {job.name}!"
+//!
+//! @Route/POST /users
+//! run:
+//!     parse-json
+//!     enqueue send_welcome_email body
+//!     respond 201 body
+//! ```
+//!
+//! `enqueue` returns at once with the job's id; the worker's `run:` steps process the job
+//! in the background with the payload as `job`, its id as `job_id` and the attempt number
+//! (from 1) as `attempt`. At most `concurrency` jobs of a worker run at a time (default 1);
+//! the rest wait their turn in order.
+//!
+//! A job fails on a step error or a `5xx` response and is retried `retries` times
+//! (default 0), waiting `retry_delay` (default `1s`) between attempts, doubled each time
+//! unless `backoff = fixed`. When it is out of attempts, the worker's `on_error:` steps
+//! run with the failure as `error`. Jobs live in memory and are lost if the process exits.
+
+use crate::builtins::builtin::control::{parse_duration, run_block};
+use crate::builtins::builtin::respond::lookup_value;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::step::{DocumentSteps, Step};
+use crate::core::{constants, errors, AppState};
+use crate::rune_ast::{Section, Value};
+use crate::util::{log, LogLevel};
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The pool of each worker, by name, limiting how many of its jobs run at once.
+static POOLS: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct Worker {
+    pub name: String,
+    pub steps: Arc<[Step]>,
+    pub on_error: Option<Arc<[Step]>>,
+    pub concurrency: usize,
+    pub retries: u32,
+    pub retry_delay: Duration,
+    pub exponential: bool,
+}
+
+impl Worker {
+    /// Reads the worker from its section, with the steps compiled when its document was loaded.
+    pub fn from_section(compiled: &DocumentSteps, section: &Section) -> Result<Self, String> {
+        let name = section.path.get(1).cloned().unwrap_or_default();
+        let steps = compiled
+            .get(section, "run")
+            .ok_or_else(|| format!("@Worker/{} requires run: steps", name))?;
+        let count = |key: &str| section.kv.get(key).and_then(|v| v.as_u64());
+        let retry_delay = match section.kv.get("retry_delay").and_then(Value::as_str) {
+            Some(spec) => parse_duration(spec)
+                .ok_or_else(|| format!("@Worker/{}: invalid retry_delay `{}`", name, spec))?,
+            None => DEFAULT_RETRY_DELAY,
+        };
+        let exponential = match section.kv.get("backoff").and_then(Value::as_str) {
+            None | Some("exp") => true,
+            Some("fixed") => false,
+            Some(other) => return Err(format!("@Worker/{}: backoff must be exp or fixed, not `{}`", name, other)),
+        };
+        Ok(Worker {
+            on_error: compiled.get(section, "on_error"),
+            concurrency: count("concurrency").filter(|n| *n > 0).unwrap_or(1) as usize,
+            retries: count("retries").unwrap_or(0) as u32,
+            retry_delay,
+            exponential,
+            steps,
+            name,
+        })
+    }
+
+    /// How long to wait after failed attempt number `attempt`.
+    fn delay_after(&self, attempt: u32) -> Duration {
+        if self.exponential {
+            self.retry_delay.saturating_mul(2u32.saturating_pow(attempt - 1))
+        } else {
+            self.retry_delay
+        }
+    }
+}
+
+fn worker_section<'a>(state: &'a AppState, name: &str) -> Option<&'a Section> {
+    state.doc.sections.iter().find(|s| {
+        s.path.first().map(String::as_str) == Some("Worker") && s.path.get(1).map(String::as_str) == Some(name)
+    })
+}
+
+fn pool(worker: &Worker) -> Arc<Semaphore> {
+    POOLS
+        .lock()
+        .unwrap()
+        .entry(worker.name.clone())
+        .or_insert_with(|| Arc::new(Semaphore::new(worker.concurrency)))
+        .clone()
+}
+
+/// Queues a job for the worker `name` and returns its id.
+pub fn enqueue(state: &AppState, name: &str, payload: JsonValue) -> Result<String, String> {
+    let section = worker_section(state, name).ok_or_else(|| format!("no @Worker/{} to enqueue to", name))?;
+    let worker = Worker::from_section(&state.steps, section)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let pool = pool(&worker);
+    let state = state.clone();
+    let job_id = id.clone();
+    tokio::spawn(async move {
+        let Ok(_permit) = pool.acquire_owned().await else {
+            return;
+        };
+        run_job(state, worker, job_id, payload).await;
+    });
+    Ok(id)
+}
+
+async fn run_job(state: AppState, worker: Worker, id: String, payload: JsonValue) {
+    let mut attempt = 1;
+    let (failure, mut ctx) = loop {
+        let mut ctx = Context::new();
+        constants::seed_context(&state.doc, &mut ctx);
+        ctx.insert("job".to_string(), payload.clone());
+        ctx.insert("job_id".to_string(), JsonValue::String(id.clone()));
+        ctx.insert("attempt".to_string(), JsonValue::from(attempt));
+        let result = run_block(&state, &worker.steps, &mut ctx).await;
+        for datasource in crate::builtins::builtin::transaction::rollback_all(&mut ctx).await {
+            log(LogLevel::Warn, &format!("Rolled back uncommitted transaction on {}", datasource));
+        }
+        let failure = match result {
+            BuiltinResult::Error(message) => message,
+            BuiltinResult::Respond(code, body) if code >= 500 => format!("responded {}: {}", code, body),
+            _ => return,
+        };
+        if attempt > worker.retries {
+            break (failure, ctx);
+        }
+        let wait = worker.delay_after(attempt);
+        log(
+            LogLevel::Warn,
+            &format!("Worker {}: job {} attempt {} failed ({}), retrying in {:?}", worker.name, id, attempt, failure, wait),
+        );
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    };
+    log(
+        LogLevel::Warn,
+        &format!("Worker {}: job {} failed after {} attempt(s): {}", worker.name, id, attempt, failure),
+    );
+    if let Some(handler) = &worker.on_error {
+        ctx.insert("error".to_string(), errors::error_object(500, &failure));
+        run_block(&state, handler, &mut ctx).await;
+        crate::builtins::builtin::transaction::rollback_all(&mut ctx).await;
+    }
+}
+
+/// `enqueue <worker> [payload]` — queues a job for `@Worker/<worker>` with the payload's
+/// context value (or the text itself) and stores the job id. Naming a worker that does
+/// not exist is a step error.
+pub fn builtin_enqueue(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some(name) = args.first() else {
+        return BuiltinResult::Error("enqueue requires a worker name".to_string());
+    };
+    let payload = match args.get(1) {
+        Some(arg) => lookup_value(ctx, arg).unwrap_or_else(|| JsonValue::String(arg.clone())),
+        None => JsonValue::Null,
+    };
+    match enqueue(app_state, name, payload) {
+        Ok(id) => store_result(ctx, assign_to, JsonValue::String(id)),
+        Err(e) => BuiltinResult::Error(format!("enqueue: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn reads_worker_settings() {
+        let doc = parse_rune(
+            "#!RUNE\n@Worker/mail\nconcurrency = 4\nretries = 2\nretry_delay = 50ms\nrun:\n    log \"sending\"\n\n@Worker/fixed\nbackoff = fixed\nrun:\n    log \"x\"\n\n@Worker/empty\nconcurrency = 2\n",
+        )
+        .unwrap();
+        let steps = DocumentSteps::compile(&doc);
+        let mail = Worker::from_section(&steps, &doc.sections[0]).unwrap();
+        assert_eq!((mail.concurrency, mail.retries), (4, 2));
+        assert_eq!(mail.delay_after(3), Duration::from_millis(200));
+        let fixed = Worker::from_section(&steps, &doc.sections[1]).unwrap();
+        assert_eq!((fixed.concurrency, fixed.retries), (1, 0));
+        assert_eq!(fixed.delay_after(3), DEFAULT_RETRY_DELAY);
+        assert!(Worker::from_section(&steps, &doc.sections[2]).is_err());
+    }
+}
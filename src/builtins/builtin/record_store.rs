@@ -0,0 +1,475 @@
+//! Datasources kept outside a database: `type = jsonfile` keeps a JSON array of records in
+//! a single file, `type = csv` keeps one row per record under a header line, and
+//! `type = memory` keeps the records as a list under a key of the shared memory store.
+//!
+//! ```rune
+//! @DataSource/BooksFile
+//! type = jsonfile
+//! connection = data/books.json
+//!
+//! @DataSource/UsersCsv
+//! type = csv
+//! connection = data/users.csv
+//!
+//! @DataSource/Demo
+//! type = memory
+//! connection = books
+//! ```
+//!
+//! Every file operation holds an exclusive lock on `<file>.lock`, and writes go to a temporary
+//! file that is renamed over the original, so concurrent requests (and processes) never
+//! see a partial file. Numeric ids are assigned as one more than the largest id; string
+//! ids get a UUID.
+//!
+//! CSV columns are `id` followed by the schema's fields; a file that already has a header
+//! keeps its column order, with missing schema fields appended. Cells are read back as
+//! the schema's types, so `number` and `bool` fields round-trip as JSON numbers and bools.
+//!
+//! Memory records can be seeded with an `@Memory/<key>` section; operations on them are
+//! serialized within the process.
+
+use crate::builtins::builtin::memory::{get_memory_value, set_memory};
+use crate::builtins::path_utils::resolve_write_path;
+use crate::builtins::BuiltinResult;
+use crate::core::errors::conflict_body;
+use serde_json::{Map, Value as JsonValue};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// How records are laid out on disk.
+#[derive(Debug, Clone)]
+pub enum StoreFormat {
+    Json,
+    /// The schema's `(field, type)` pairs, `id` included.
+    Csv(Vec<(String, String)>),
+}
+
+/// A datasource's records file and its format.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    pub path: PathBuf,
+    pub format: StoreFormat,
+}
+
+impl FileStore {
+    /// The records file named by a datasource's `connection`, relative to the rune directory.
+    pub fn new(connection: &str, rune_dir: &Path, format: StoreFormat) -> Self {
+        FileStore {
+            path: resolve_write_path(connection, rune_dir),
+            format,
+        }
+    }
+}
+
+/// Where a datasource's records live.
+#[derive(Debug, Clone)]
+pub enum RecordStore {
+    File(FileStore),
+    /// A key of the shared memory store.
+    Memory(String),
+}
+
+/// Serializes read-modify-write cycles on memory records.
+static MEMORY_RECORDS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+fn read_json(path: &Path) -> Result<Vec<JsonValue>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_str(&text) {
+        Ok(JsonValue::Array(records)) => Ok(records),
+        Ok(_) => Err(format!("{} does not hold a JSON array", path.display())),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn write_json(path: &Path, records: &[JsonValue]) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(records).map_err(|e| e.to_string())? + "\n";
+    replace_file(path, text.as_bytes())
+}
+
+/// Converts a CSV cell to the JSON type of its schema field; unknown columns stay strings.
+fn csv_cell(cell: &str, typ: Option<&str>) -> JsonValue {
+    match typ {
+        Some("number") if cell.is_empty() => JsonValue::Null,
+        Some("number") => cell
+            .parse::<i64>()
+            .map(JsonValue::from)
+            .or_else(|_| cell.parse::<f64>().map(JsonValue::from))
+            .unwrap_or_else(|_| JsonValue::String(cell.to_string())),
+        Some("bool") if cell.is_empty() => JsonValue::Null,
+        Some("bool") => cell
+            .parse::<bool>()
+            .map(JsonValue::Bool)
+            .unwrap_or_else(|_| JsonValue::String(cell.to_string())),
+        _ => JsonValue::String(cell.to_string()),
+    }
+}
+
+fn csv_text(value: Option<&JsonValue>) -> String {
+    match value {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Reads the rows of a CSV file along with its header.
+fn read_csv(path: &Path, fields: &[(String, String)]) -> Result<(Vec<JsonValue>, Vec<String>), String> {
+    let mut reader = match csv::ReaderBuilder::new().from_path(path) {
+        Ok(reader) => reader,
+        Err(e) => match e.kind() {
+            csv::ErrorKind::Io(io) if io.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), Vec::new())),
+            _ => return Err(format!("{}: {}", path.display(), e)),
+        },
+    };
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    let types: Vec<Option<&str>> = header
+        .iter()
+        .map(|h| fields.iter().find(|(f, _)| f == h).map(|(_, t)| t.as_str()))
+        .collect();
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|e| format!("{}: {}", path.display(), e))?;
+        let record: Map<String, JsonValue> = header
+            .iter()
+            .zip(types.iter())
+            .zip(row.iter())
+            .map(|((name, typ), cell)| (name.clone(), csv_cell(cell, *typ)))
+            .collect();
+        records.push(JsonValue::Object(record));
+    }
+    Ok((records, header))
+}
+
+/// Writes records under `header`, extended with any schema field or record key it lacks.
+fn write_csv(path: &Path, records: &[JsonValue], mut header: Vec<String>, fields: &[(String, String)]) -> Result<(), String> {
+    let mut extra: Vec<&String> = records
+        .iter()
+        .filter_map(|r| r.as_object())
+        .flat_map(|r| r.keys())
+        .filter(|k| !fields.iter().any(|(f, _)| f == *k))
+        .collect();
+    extra.sort();
+    for column in fields.iter().map(|(f, _)| f).chain(extra) {
+        if !header.contains(column) {
+            header.push(column.clone());
+        }
+    }
+    let mut writer = csv::WriterBuilder::new().from_writer(Vec::new());
+    let rows = std::iter::once(header.clone()).chain(
+        records
+            .iter()
+            .map(|record| header.iter().map(|column| csv_text(record.get(column))).collect()),
+    );
+    for row in rows {
+        writer.write_record(&row).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    replace_file(path, &bytes)
+}
+
+fn replace_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    crate::util::replace_file(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Runs `op` on the store's records under an exclusive lock, writing them back when it
+/// returns `true` alongside its result.
+async fn with_records<T, F>(store: RecordStore, op: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Vec<JsonValue>) -> (T, bool) + Send + 'static,
+{
+    match store {
+        RecordStore::File(store) => with_file_records(store, op).await,
+        RecordStore::Memory(key) => {
+            let _guard = MEMORY_RECORDS.lock().await;
+            let mut records = match get_memory_value(&key).await {
+                Some(JsonValue::Array(records)) => records.into_iter().map(whole_numbers).collect(),
+                None | Some(JsonValue::Null) => Vec::new(),
+                Some(_) => return Err(format!("memory key '{}' does not hold a list", key)),
+            };
+            let (result, dirty) = op(&mut records);
+            if dirty {
+                set_memory(&key, JsonValue::Array(records)).await;
+            }
+            Ok(result)
+        }
+    }
+}
+
+/// Turns `1.0` into `1` in a record's fields; `@Memory` sections store every number as a float.
+fn whole_numbers(record: JsonValue) -> JsonValue {
+    match record {
+        JsonValue::Object(fields) => JsonValue::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| match v.as_f64() {
+                    Some(n) if !v.is_i64() && !v.is_u64() && n.fract() == 0.0 && n.abs() < 9e15 => {
+                        (k, JsonValue::from(n as i64))
+                    }
+                    _ => (k, v),
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+async fn with_file_records<T, F>(store: FileStore, op: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Vec<JsonValue>) -> (T, bool) + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let path = store.path;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+        }
+        let lock: File = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(&path))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        lock.lock().map_err(|e| format!("{}: {}", path.display(), e))?;
+        match store.format {
+            StoreFormat::Json => {
+                let mut records = read_json(&path)?;
+                let (result, dirty) = op(&mut records);
+                if dirty {
+                    write_json(&path, &records)?;
+                }
+                Ok(result)
+            }
+            StoreFormat::Csv(fields) => {
+                let (mut records, header) = read_csv(&path, &fields)?;
+                let (result, dirty) = op(&mut records);
+                if dirty {
+                    write_csv(&path, &records, header, &fields)?;
+                }
+                Ok(result)
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn same_id(record: &JsonValue, id: &JsonValue) -> bool {
+    match (record.get("id"), id) {
+        (Some(a), b) if a == b => true,
+        (Some(JsonValue::Number(a)), JsonValue::String(b)) | (Some(JsonValue::String(b)), JsonValue::Number(a)) => {
+            a.to_string() == *b
+        }
+        _ => false,
+    }
+}
+
+fn next_id(records: &[JsonValue], id_type: &str) -> JsonValue {
+    if id_type == "string" {
+        return JsonValue::String(uuid::Uuid::new_v4().to_string());
+    }
+    let max = records
+        .iter()
+        .filter_map(|r| r.get("id").and_then(|id| id.as_i64()))
+        .max()
+        .unwrap_or(0);
+    JsonValue::from(max + 1)
+}
+
+fn storage_error(e: String) -> BuiltinResult {
+    BuiltinResult::Error(format!("record store: {}", e))
+}
+
+/// Whether `record[field]` reads as `expected`, as in a query string.
+fn field_matches(record: &JsonValue, field: &str, expected: &str) -> bool {
+    match record.get(field) {
+        Some(JsonValue::String(s)) => s == expected,
+        Some(JsonValue::Number(n)) => expected.parse::<f64>().ok() == n.as_f64(),
+        Some(JsonValue::Bool(b)) => expected.parse::<bool>().ok() == Some(*b),
+        _ => false,
+    }
+}
+
+/// Creates the file (an empty array, or a CSV header) or memory list if it does not exist yet.
+pub async fn ensure(store: RecordStore) -> Result<(), BuiltinResult> {
+    with_records(store, |records| ((), records.is_empty())).await.map_err(storage_error)
+}
+
+/// All records whose fields equal every `(field, value)` filter.
+pub async fn fetch_all(store: RecordStore, filters: Vec<(String, String)>) -> Result<JsonValue, BuiltinResult> {
+    with_records(store, move |records| {
+        let matching = records
+            .iter()
+            .filter(|r| filters.iter().all(|(field, value)| field_matches(r, field, value)))
+            .cloned()
+            .collect();
+        (JsonValue::Array(matching), false)
+    })
+    .await
+    .map_err(storage_error)
+}
+
+pub async fn fetch(store: RecordStore, id: JsonValue) -> Result<JsonValue, BuiltinResult> {
+    let found = with_records(store, move |records| (records.iter().find(|r| same_id(r, &id)).cloned(), false))
+        .await
+        .map_err(storage_error)?;
+    found.ok_or_else(|| BuiltinResult::Respond(404, "no record found".into()))
+}
+
+/// The first of the `unique` fields whose value in `fields` a record other than `id`
+/// already holds, with that value.
+fn duplicate(
+    records: &[JsonValue],
+    fields: &Map<String, JsonValue>,
+    unique: &[String],
+    id: Option<&JsonValue>,
+) -> Option<(String, JsonValue)> {
+    unique.iter().find_map(|field| {
+        let value = fields.get(field).filter(|v| !v.is_null())?;
+        records
+            .iter()
+            .filter(|r| id.is_none_or(|id| !same_id(r, id)))
+            .any(|r| r.get(field) == Some(value))
+            .then(|| (field.clone(), value.clone()))
+    })
+}
+
+fn conflict((field, value): (String, JsonValue)) -> BuiltinResult {
+    BuiltinResult::Respond(409, conflict_body(Some(&field), Some(value)))
+}
+
+/// Appends `body`, assigning an id unless it carries one. Returns the stored record, or
+/// `409` when its id or a value of a `unique` field is taken.
+pub async fn insert(
+    store: RecordStore,
+    body: Map<String, JsonValue>,
+    id_type: &'static str,
+    unique: &[String],
+) -> Result<JsonValue, BuiltinResult> {
+    let unique = unique.to_vec();
+    let inserted = with_records(store, move |records| {
+        let mut record = body;
+        let id = match record.get("id") {
+            Some(id) if !id.is_null() => id.clone(),
+            _ => next_id(records, id_type),
+        };
+        if records.iter().any(|r| same_id(r, &id)) {
+            return (Err(("id".to_string(), id)), false);
+        }
+        if let Some(taken) = duplicate(records, &record, &unique, None) {
+            return (Err(taken), false);
+        }
+        record.insert("id".to_string(), id);
+        let record = JsonValue::Object(record);
+        records.push(record.clone());
+        (Ok(record), true)
+    })
+    .await
+    .map_err(storage_error)?;
+    inserted.map_err(conflict)
+}
+
+/// Overwrites `fields` of the record with `id`. Returns the updated record, or `409` when
+/// a value of a `unique` field belongs to another record.
+pub async fn update(
+    store: RecordStore,
+    id: JsonValue,
+    fields: Map<String, JsonValue>,
+    unique: &[String],
+) -> Result<JsonValue, BuiltinResult> {
+    let unique = unique.to_vec();
+    let updated = with_records(store, move |records| {
+        if let Some(taken) = duplicate(records, &fields, &unique, Some(&id)) {
+            return (Err(conflict(taken)), false);
+        }
+        match records.iter_mut().find(|r| same_id(r, &id)) {
+            Some(JsonValue::Object(record)) => {
+                record.extend(fields.into_iter().filter(|(k, _)| k != "id"));
+                (Ok(JsonValue::Object(record.clone())), true)
+            }
+            _ => (Err(BuiltinResult::Respond(404, "no record found".into())), false),
+        }
+    })
+    .await
+    .map_err(storage_error)?;
+    updated
+}
+
+pub async fn delete(store: RecordStore, id: JsonValue) -> Result<(), BuiltinResult> {
+    let removed = with_records(store, move |records| {
+        let before = records.len();
+        records.retain(|r| !same_id(r, &id));
+        let removed = records.len() != before;
+        (removed, removed)
+    })
+    .await
+    .map_err(storage_error)?;
+    if removed {
+        Ok(())
+    } else {
+        Err(BuiltinResult::Respond(404, "no record found".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn body(value: JsonValue) -> Map<String, JsonValue> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn assigns_ids_and_rewrites_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new("books.json", dir.path(), StoreFormat::Json);
+        let path = store.path.clone();
+        let store = RecordStore::File(store);
+        insert(store.clone(), body(json!({ "title": "a" })), "number", &[]).await.ok().unwrap();
+        let second = insert(store.clone(), body(json!({ "title": "b" })), "number", &[]).await.ok().unwrap();
+        assert_eq!(second["id"], 2);
+        delete(store.clone(), json!("1")).await.ok().unwrap();
+        let on_disk: JsonValue = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk, json!([{ "id": 2, "title": "b" }]));
+    }
+
+    #[tokio::test]
+    async fn csv_rows_keep_their_header_and_schema_types() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("users.csv"), "name,id\nada,1\n").unwrap();
+        let fields = vec![
+            ("id".to_string(), "number".to_string()),
+            ("active".to_string(), "bool".to_string()),
+            ("name".to_string(), "string".to_string()),
+        ];
+        let store = RecordStore::File(FileStore::new("users.csv", dir.path(), StoreFormat::Csv(fields)));
+        let created = insert(store.clone(), body(json!({ "name": "bob", "active": true })), "number", &[])
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(created["id"], 2);
+        let on_disk = std::fs::read_to_string(dir.path().join("users.csv")).unwrap();
+        assert_eq!(on_disk, "name,id,active\nada,1,\nbob,2,true\n");
+        let active = fetch_all(store, vec![("active".into(), "true".into())]).await.ok().unwrap();
+        assert_eq!(active, json!([{ "id": 2, "name": "bob", "active": true }]));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+use ::regex::Regex;
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+static PATTERNS: Lazy<Mutex<HashMap<String, Regex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Compiles `pattern`, reusing earlier compilations so per-request steps stay cheap.
+pub fn compile(pattern: &str) -> Result<Regex, String> {
+    let mut cache = PATTERNS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(re) = cache.get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = Regex::new(pattern).map_err(|e| format!("invalid regex '{}': {}", pattern, e))?;
+    cache.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+/// A pattern argument. Only plain identifiers and dotted paths (`const.slug_pattern`) are
+/// looked up in the context, so regex syntax is never mistaken for a path expression.
+pub fn pattern_text(ctx: &Context, arg: &str) -> String {
+    let is_path = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && !arg.starts_with('.')
+        && !arg.ends_with('.');
+    if is_path {
+        if let Some(JsonValue::String(s)) = crate::core::resolve_path(ctx, arg, None) {
+            return s;
+        }
+    }
+    arg.to_string()
+}
+
+/// The value and compiled pattern shared by every regex builtin.
+fn value_and_pattern(name: &str, args: &[String], ctx: &Context) -> Result<(String, Regex), String> {
+    if args.len() < 2 {
+        return Err(format!("{} requires a value and a pattern", name));
+    }
+    let re = compile(&pattern_text(ctx, &args[1])).map_err(|e| format!("{}: {}", name, e))?;
+    Ok((arg_text(ctx, &args[0]), re))
+}
+
+/// `regex.match <value> <pattern>` — whether the pattern matches anywhere in the value.
+pub fn builtin_regex_match(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    match value_and_pattern("regex.match", args, ctx) {
+        Ok((value, re)) => store_result(ctx, assign_to, JsonValue::Bool(re.is_match(&value))),
+        Err(e) => BuiltinResult::Error(e),
+    }
+}
+
+/// `regex.capture <value> <pattern>` — the first match's groups: an object when the pattern
+/// names its groups (`(?P<year>\d{4})`), otherwise a list of the numbered groups (or the
+/// whole match when there are none). `null` when nothing matches.
+pub fn builtin_regex_capture(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let (value, re) = match value_and_pattern("regex.capture", args, ctx) {
+        Ok(found) => found,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    let group = |m: Option<::regex::Match>| m.map(|m| JsonValue::String(m.as_str().to_string())).unwrap_or(JsonValue::Null);
+    let captured = match re.captures(&value) {
+        None => JsonValue::Null,
+        Some(caps) if re.capture_names().flatten().next().is_some() => JsonValue::Object(
+            re.capture_names()
+                .flatten()
+                .map(|name| (name.to_string(), group(caps.name(name))))
+                .collect::<Map<_, _>>(),
+        ),
+        Some(caps) if caps.len() > 1 => JsonValue::Array(caps.iter().skip(1).map(group).collect()),
+        Some(caps) => JsonValue::Array(vec![group(caps.get(0))]),
+    };
+    store_result(ctx, assign_to, captured)
+}
+
+/// `regex.replace <value> <pattern> <replacement>` — replaces every match; the replacement
+/// may refer to groups as `$1` or `${name}`.
+pub fn builtin_regex_replace(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    if args.len() < 3 {
+        return BuiltinResult::Error("regex.replace requires a value, a pattern and a replacement".to_string());
+    }
+    let (value, re) = match value_and_pattern("regex.replace", args, ctx) {
+        Ok(found) => found,
+        Err(e) => return BuiltinResult::Error(e),
+    };
+    let replacement = arg_text(ctx, &args[2]);
+    let replaced = re.replace_all(&value, replacement.as_str()).into_owned();
+    store_result(ctx, assign_to, JsonValue::String(replaced))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_compiled_patterns_and_reports_bad_ones() {
+        assert!(compile(r"^\d+$").unwrap().is_match("123"));
+        assert!(compile(r"^\d+$").unwrap().is_match("456"));
+        assert!(compile("(unclosed").unwrap_err().starts_with("invalid regex '(unclosed'"));
+    }
+}
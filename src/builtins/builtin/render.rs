@@ -0,0 +1,67 @@
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::builtin::respond::set_content_type;
+use crate::builtins::path_utils::candidate_paths;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::tokenizer::unquote;
+use crate::core::AppState;
+use minijinja::{path_loader, Environment};
+use serde_json::{Map, Value as JsonValue};
+use std::path::Path;
+
+/// Renders `file` with `data`. Other templates in the same directory are available to
+/// `{% extends %}` and `{% include %}`; `.html` templates auto-escape their values.
+pub fn render_file(path: &Path, data: &JsonValue) -> Result<String, String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("invalid template path {}", path.display()))?;
+    let mut env = Environment::new();
+    env.set_loader(path_loader(path.parent().unwrap_or_else(|| Path::new("."))));
+    let template = env.get_template(name).map_err(|e| e.to_string())?;
+    template.render(data).map_err(|e| e.to_string())
+}
+
+/// Context variables a template sees when no data is given; internal keys are left out.
+fn visible_context(ctx: &Context) -> JsonValue {
+    JsonValue::Object(
+        ctx.iter()
+            .filter(|(k, _)| !k.starts_with("___"))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+/// `render <template> [with <data>]` — renders a template file, found relative to the rune
+/// directory, with `data` (an object path or inline object) or else the step context.
+/// Responds 200 with `text/html` unless the result is assigned to a variable.
+pub fn builtin_render(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some(template) = args.first() else {
+        return BuiltinResult::Error("render requires a template".to_string());
+    };
+    let template = unquote(template).unwrap_or_else(|| template.clone());
+    let data = match args.get(1).map(String::as_str) {
+        Some("with") => match args.get(2) {
+            Some(token) => value_arg(ctx, token),
+            None => return BuiltinResult::Error("render: `with` requires data".to_string()),
+        },
+        Some(other) => return BuiltinResult::Error(format!("render: expected `with` but found `{}`", other)),
+        None => visible_context(ctx),
+    };
+    let Some(path) = candidate_paths(&template, &app_state.path).into_iter().find(|p| p.is_file()) else {
+        return BuiltinResult::Error(format!("render: template {} not found", template));
+    };
+    let html = match render_file(&path, &data) {
+        Ok(html) => html,
+        Err(e) => return BuiltinResult::Error(format!("render {}: {}", template, e)),
+    };
+    if assign_to.is_some() {
+        return store_result(ctx, assign_to, JsonValue::String(html));
+    }
+    set_content_type(ctx, "text/html; charset=utf-8");
+    BuiltinResult::Respond(200, html)
+}
@@ -1,15 +1,209 @@
-use crate::builtins::{BuiltinResult, Context};
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::{BuiltinResult, Context, RESPONSE_HEADERS};
+use crate::core::resolve_path;
+use crate::util::json_to_xml;
+use serde_json::Value as JsonValue;
 
-pub fn builtin_respond(args: &[String], ctx: &Context) -> BuiltinResult {
-    let status: u16 = args.get(0).and_then(|s| s.parse().ok()).unwrap_or(200);
-    let msg = if args.len() > 1 {
-        if let Some(val) = ctx.get(&args[1]) {
-            val.to_string()
-        } else {
-            args[1..].join(" ")
+/// Output formats supported by `respond ... as <format>` and `return ... as <format>`.
+/// A full media type such as `"application/geo+json"` is accepted as well.
+pub const RESPONSE_FORMATS: &[&str] = &["json", "xml", "yaml", "text", "csv", "html"];
+
+/// Whether `format` names one of `RESPONSE_FORMATS` or is a media type.
+pub fn is_response_format(format: &str) -> bool {
+    RESPONSE_FORMATS.contains(&format) || format.contains('/')
+}
+
+/// Looks up a respond/return argument: a context variable, or a path into one such as
+/// `users[0].name` or `items[0:3]`.
+pub fn lookup_value(ctx: &Context, name: &str) -> Option<JsonValue> {
+    if let Some(val) = ctx.get(name) {
+        return Some(val.clone());
+    }
+    if (name.contains('.') || name.contains('[')) && name.parse::<f64>().is_err() {
+        return resolve_path(ctx, name, None);
+    }
+    None
+}
+
+pub fn builtin_respond(args: &[String], ctx: &mut Context) -> BuiltinResult {
+    let (args, explicit_format) = split_format_suffix(args);
+    let status: u16 = args.first().and_then(|s| s.parse().ok()).unwrap_or(200);
+    // These statuses never carry a body
+    if matches!(status, 204 | 304) {
+        return BuiltinResult::Respond(status, String::new());
+    }
+    if args.len() > 1 {
+        // An inline object, as in assignments: `respond 201 { id: new_id, status: "created" }`
+        let inline = args[1].starts_with('{').then(|| value_arg(ctx, &args[1]));
+        if let Some(val) = inline.or_else(|| lookup_value(ctx, &args[1])) {
+            let format = match explicit_format {
+                Some(f) => Some(f),
+                None => negotiate_format(ctx),
+            };
+            return match format {
+                Some(format) => match serialize_as(&val, &format) {
+                    Ok((body, content_type)) => {
+                        set_content_type(ctx, &content_type);
+                        BuiltinResult::Respond(status, body)
+                    }
+                    Err(e) => BuiltinResult::Respond(400, format!("respond: {}", e)),
+                },
+                None => BuiltinResult::respond_value(ctx, status, &val),
+            };
         }
+    }
+    let msg = if args.len() > 1 {
+        args[1..].join(" ")
     } else {
         "OK".to_string()
     };
     BuiltinResult::Respond(status, msg)
 }
+
+/// Splits a trailing `as <format>` off a respond/return argument list. Any other trailing
+/// `as <word>` is left in place, as part of a text message (`Logged in as admin`).
+pub fn split_format_suffix(args: &[String]) -> (&[String], Option<String>) {
+    let n = args.len();
+    if n >= 3 && args[n - 2] == "as" {
+        let format = args[n - 1].trim_matches('"').to_lowercase();
+        if is_response_format(&format) {
+            return (&args[..n - 2], Some(format));
+        }
+    }
+    (args, None)
+}
+
+/// Picks a response format from the request's Accept header, if it names one we support.
+pub fn negotiate_format(ctx: &Context) -> Option<String> {
+    let accept = ctx
+        .get("request.headers")
+        .and_then(|h| h.get("accept"))
+        .and_then(|v| v.as_str())?;
+
+    let mut candidates: Vec<(f32, &str)> = accept
+        .split(',')
+        .map(|part| {
+            let mut pieces = part.split(';');
+            let mime = pieces.next().unwrap_or("").trim();
+            let q = pieces
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (q, mime)
+        })
+        .collect();
+    // Stable sort keeps the client's order for equal weights.
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    candidates.into_iter().find_map(|(q, mime)| {
+        if q <= 0.0 {
+            return None;
+        }
+        let format = match mime {
+            "application/json" => "json",
+            "application/xml" | "text/xml" => "xml",
+            "application/yaml" | "application/x-yaml" | "text/yaml" => "yaml",
+            "text/csv" => "csv",
+            "text/plain" => "text",
+            _ => return None,
+        };
+        Some(format.to_string())
+    })
+}
+
+/// Serializes a context value into the requested format, returning the body and its content type.
+/// For a media type, strings are sent as they are and other values as JSON.
+pub fn serialize_as(value: &JsonValue, format: &str) -> Result<(String, String), String> {
+    let text = || match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match format {
+        "json" => Ok((
+            serde_json::to_string(value).map_err(|e| e.to_string())?,
+            "application/json".to_string(),
+        )),
+        "xml" => Ok((json_to_xml(value, "root"), "application/xml".to_string())),
+        "yaml" => Ok((
+            serde_yaml::to_string(value).map_err(|e| e.to_string())?,
+            "application/yaml".to_string(),
+        )),
+        "text" | "html" => Ok((text(), content_type_for(format))),
+        "csv" => Ok((to_csv(value)?, "text/csv".to_string())),
+        media_type if media_type.contains('/') => Ok((text(), media_type.to_string())),
+        other => Err(format!("unsupported output type {}", other)),
+    }
+}
+
+/// The Content-Type for a route's `content_type` setting: a short name (`html`, `json`,
+/// `xml`, `yaml`, `text`, `csv`) or a full media type.
+pub fn content_type_for(name: &str) -> String {
+    match name {
+        "html" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" => "application/yaml",
+        "text" => "text/plain; charset=utf-8",
+        "csv" => "text/csv",
+        other => other,
+    }
+    .to_string()
+}
+
+pub fn set_content_type(ctx: &mut Context, content_type: &str) {
+    let headers = ctx
+        .entry(RESPONSE_HEADERS.to_string())
+        .or_insert_with(|| JsonValue::Object(serde_json::Map::new()));
+    if let JsonValue::Object(map) = headers {
+        map.insert(
+            "content-type".to_string(),
+            JsonValue::String(content_type.to_string()),
+        );
+    }
+}
+
+fn csv_cell(value: Option<&JsonValue>) -> String {
+    match value {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Arrays of objects become one row per object with a header built from the keys
+/// in order of first appearance; scalars and arrays of scalars use a `value` column.
+fn to_csv(value: &JsonValue) -> Result<String, String> {
+    let rows: Vec<&JsonValue> = match value {
+        JsonValue::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+
+    let mut headers: Vec<String> = Vec::new();
+    for row in &rows {
+        if let JsonValue::Object(map) = row {
+            for key in map.keys() {
+                if !headers.contains(key) {
+                    headers.push(key.clone());
+                }
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if headers.is_empty() {
+        writer.write_record(["value"]).map_err(|e| e.to_string())?;
+        for row in &rows {
+            writer
+                .write_record([csv_cell(Some(row))])
+                .map_err(|e| e.to_string())?;
+        }
+    } else {
+        writer.write_record(&headers).map_err(|e| e.to_string())?;
+        for row in &rows {
+            let record: Vec<String> = headers.iter().map(|h| csv_cell(row.get(h))).collect();
+            writer.write_record(&record).map_err(|e| e.to_string())?;
+        }
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
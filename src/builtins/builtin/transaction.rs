@@ -0,0 +1,96 @@
+//! Transactions spanning several steps, opened by `datasource begin <Name>` and closed by
+//! `datasource commit <Name>` or `datasource rollback <Name>`.
+//!
+//! An open transaction is kept here under a random id that the step context records per
+//! datasource, and every query on that datasource runs inside it. A step error rolls back
+//! all open transactions, as does finishing the steps without committing.
+
+use crate::builtins::{BuiltinResult, Context};
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+use sqlx::{MySql, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub(crate) enum OpenTransaction {
+    Postgres(Transaction<'static, Postgres>),
+    MySql(Transaction<'static, MySql>),
+    /// A mocked datasource: writes apply as they happen, so rollback does not undo them.
+    Mock,
+}
+
+type SharedTransaction = Arc<tokio::sync::Mutex<OpenTransaction>>;
+
+static OPEN_TRANSACTIONS: Lazy<Mutex<HashMap<String, SharedTransaction>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+const CONTEXT_PREFIX: &str = "___transaction_";
+
+fn context_key(datasource: &str) -> String {
+    format!("{}{}___", CONTEXT_PREFIX, datasource)
+}
+
+/// Whether the steps have a transaction open on `datasource`.
+pub(crate) fn is_open(ctx: &Context, datasource: &str) -> bool {
+    ctx.contains_key(&context_key(datasource))
+}
+
+/// Records `tx` as the open transaction on `datasource` for these steps.
+pub(crate) fn register(ctx: &mut Context, datasource: &str, tx: OpenTransaction) {
+    let id = uuid::Uuid::new_v4().to_string();
+    OPEN_TRANSACTIONS
+        .lock()
+        .unwrap()
+        .insert(id.clone(), Arc::new(tokio::sync::Mutex::new(tx)));
+    ctx.insert(context_key(datasource), JsonValue::String(id));
+}
+
+/// The transaction open on `datasource`, if any.
+pub(crate) fn current(ctx: &Context, datasource: &str) -> Option<SharedTransaction> {
+    let id = ctx.get(&context_key(datasource))?.as_str()?;
+    OPEN_TRANSACTIONS.lock().unwrap().get(id).cloned()
+}
+
+fn take(ctx: &mut Context, datasource: &str) -> Option<OpenTransaction> {
+    let id = ctx.remove(&context_key(datasource))?;
+    let shared = OPEN_TRANSACTIONS.lock().unwrap().remove(id.as_str()?)?;
+    Arc::try_unwrap(shared).ok().map(|tx| tx.into_inner())
+}
+
+pub(crate) async fn commit(ctx: &mut Context, datasource: &str) -> BuiltinResult {
+    let result = match take(ctx, datasource) {
+        Some(OpenTransaction::Postgres(tx)) => tx.commit().await,
+        Some(OpenTransaction::MySql(tx)) => tx.commit().await,
+        Some(OpenTransaction::Mock) => Ok(()),
+        None => return BuiltinResult::Error(format!("no transaction open on {}", datasource)),
+    };
+    match result {
+        Ok(()) => BuiltinResult::Ok,
+        Err(e) => BuiltinResult::Error(format!("commit on {} failed: {}", datasource, e)),
+    }
+}
+
+pub(crate) async fn rollback(ctx: &mut Context, datasource: &str) -> BuiltinResult {
+    let result = match take(ctx, datasource) {
+        Some(OpenTransaction::Postgres(tx)) => tx.rollback().await,
+        Some(OpenTransaction::MySql(tx)) => tx.rollback().await,
+        Some(OpenTransaction::Mock) => Ok(()),
+        None => return BuiltinResult::Error(format!("no transaction open on {}", datasource)),
+    };
+    match result {
+        Ok(()) => BuiltinResult::Ok,
+        Err(e) => BuiltinResult::Error(format!("rollback on {} failed: {}", datasource, e)),
+    }
+}
+
+/// Rolls back every transaction the steps left open, returning their datasource names.
+pub async fn rollback_all(ctx: &mut Context) -> Vec<String> {
+    let datasources: Vec<String> = ctx
+        .keys()
+        .filter_map(|key| key.strip_prefix(CONTEXT_PREFIX)?.strip_suffix("___"))
+        .map(str::to_string)
+        .collect();
+    for datasource in &datasources {
+        rollback(ctx, datasource).await;
+    }
+    datasources
+}
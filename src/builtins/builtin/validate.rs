@@ -1,16 +1,59 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::builtins::builtin::date::parse_datetime;
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::builtin::regex;
 use crate::builtins::{BuiltinResult, Context, LAST_EXEC_RESULT};
-use crate::rune_ast::Section;
+use crate::core::constants::enum_values;
+use crate::core::relations::resolve_type;
+use crate::core::schema_options::vector_dimensions;
+use crate::core::eval_condition;
+use crate::core::messages::{self, validation_message};
+use crate::rune_ast::{RuneDocument, Section};
 use serde_json::Value as JsonValue;
 use crate::util::log;
 use crate::util::LogLevel;
 
+/// A schema rule from a `validate_if:` series, e.g. `- type == "company" requires vat_number`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalRule {
+    pub condition: String,
+    pub requires: Vec<String>,
+}
+
+/// Parses the schema's `validate_if:` rules. Fields named by a rule are only required when
+/// its condition holds; malformed rules are reported by name.
+pub fn conditional_rules(schema: &Section) -> Result<Vec<ConditionalRule>, String> {
+    let Some(items) = schema.series.get("validate_if") else {
+        return Ok(Vec::new());
+    };
+    items
+        .iter()
+        .map(|item| {
+            let text = item.as_str().unwrap_or_default();
+            let (condition, fields) = text
+                .split_once(" requires ")
+                .ok_or_else(|| format!("invalid validate_if rule '{}': expected `<condition> requires <field>`", text))?;
+            let requires: Vec<String> = fields
+                .split([',', ' '])
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect();
+            if condition.trim().is_empty() || requires.is_empty() {
+                return Err(format!("invalid validate_if rule '{}'", text));
+            }
+            Ok(ConditionalRule { condition: condition.trim().to_string(), requires })
+        })
+        .collect()
+}
+
 pub fn builtin_validate(
     args: &[String],
     ctx: &mut Context,
     schemas: &Arc<HashMap<String, Section>>,
+    doc: &RuneDocument,
 ) -> BuiltinResult {
     if args.is_empty() {
         log(LogLevel::Error, "validate: missing arguments");
@@ -24,28 +67,86 @@ pub fn builtin_validate(
         let value = ctx.get(var);
         let schema = schemas.get(schema_name);
         if let (Some(val), Some(schema_section)) = (value, schema) {
+            let rules = match conditional_rules(schema_section) {
+                Ok(rules) => rules,
+                Err(e) => return BuiltinResult::Error(format!("validate: {}", e)),
+            };
             for (field, typ) in &schema_section.kv {
-                if let Some(field_val) = val.get(field.clone()) {
-                    let type_ok = match (typ.as_str(), field_val) {
-                        (Some("string"), JsonValue::String(_)) => true,
-                        (Some("number"), JsonValue::Number(_)) => true,
-                        (Some("bool"), JsonValue::Bool(_)) => true,
+                // Boolean entries are table options (`timestamps = true`), not fields
+                let Some(typ) = typ.as_str().map(|t| resolve_type(t, schemas)) else {
+                    continue;
+                };
+                let conditional = rules.iter().any(|r| r.requires.contains(field));
+                if let Some(field_val) = val.get(field.clone()).filter(|v| !(conditional && v.is_null())) {
+                    if let Some(members) = enum_values(doc, typ) {
+                        if !members.contains(field_val) {
+                            let names: Vec<String> = members
+                                .iter()
+                                .map(|m| m.as_str().map(str::to_string).unwrap_or_else(|| m.to_string()))
+                                .collect();
+                            return BuiltinResult::Respond(
+                                400,
+                                validation_message(
+                                    doc,
+                                    ctx,
+                                    "not_one_of",
+                                    &[("field", field), ("values", &names.join(", "))],
+                                ),
+                            );
+                        }
+                        continue;
+                    }
+                    let type_ok = match (typ, field_val) {
+                        ("string", JsonValue::String(_)) => true,
+                        ("number", JsonValue::Number(_)) => true,
+                        ("bool", JsonValue::Bool(_)) => true,
+                        ("datetime", JsonValue::String(s)) => parse_datetime(s).is_some(),
+                        ("json", _) => true,
+                        (vector, JsonValue::Array(items)) => {
+                            vector_dimensions(vector) == Some(items.len()) && items.iter().all(JsonValue::is_number)
+                        }
                         _ => false,
                     };
                     if !type_ok {
                         return BuiltinResult::Respond(
                             400,
-                            format!("Field `{}` type mismatch", field),
+                            validation_message(doc, ctx, "type_mismatch", &[("field", field)]),
+                        );
+                    }
+                } else if !conditional {
+                    return BuiltinResult::Respond(
+                        400,
+                        validation_message(doc, ctx, "missing_field", &[("field", field)]),
+                    );
+                }
+            }
+            // Conditions see the payload's fields as variables: `type == "company"`
+            let fields: Context = val
+                .as_object()
+                .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                .unwrap_or_default();
+            for rule in &rules {
+                if !eval_condition(&fields, &rule.condition, Some(val)) {
+                    continue;
+                }
+                for field in &rule.requires {
+                    if val.get(field).is_none_or(JsonValue::is_null) {
+                        return BuiltinResult::Respond(
+                            400,
+                            validation_message(
+                                doc,
+                                ctx,
+                                "required_when",
+                                &[("field", field), ("condition", &rule.condition)],
+                            ),
                         );
                     }
-                } else {
-                    return BuiltinResult::Respond(400, format!("Missing field `{}`", field));
                 }
             }
             ctx.insert(LAST_EXEC_RESULT.to_string(), val.clone());
             return BuiltinResult::Ok;
         } else {
-            return BuiltinResult::Respond(400, "Validation failed".to_string());
+            return BuiltinResult::Respond(400, validation_message(doc, ctx, "validation_failed", &[]));
         }
     }
 
@@ -106,7 +207,7 @@ pub fn builtin_validate(
         current
     }
 
-    // Expect at least 4 args: left, op, right, message
+    // Expect at least 4 args: left, op (==, != or ~= for a regex), right, message
     if args.len() >= 4 {
         let left = args[0].as_str();
         let mut op = args[1].as_str().to_string();
@@ -119,7 +220,25 @@ pub fn builtin_validate(
         let right = args[right_index].as_str();
         // Message may already be a single consolidated arg (quotes removed upstream)
         let msg = args[(right_index + 1)..].join(" ");
+        // The message may be an `@Messages` code, rendered against the request context
+        let msg = match messages::template(doc, ctx, &msg) {
+            Some(template) => expand_log_message(template, ctx),
+            None => msg,
+        };
         let lv = resolve_path(ctx, left).unwrap_or(JsonValue::Null);
+        if op == "~=" {
+            // `field ~= "pattern"`: the right side is the pattern itself (or a context string)
+            let re = match regex::compile(&regex::pattern_text(ctx, right)) {
+                Ok(re) => re,
+                Err(e) => return BuiltinResult::Error(format!("validate: {}", e)),
+            };
+            let matched = match &lv {
+                JsonValue::String(s) => re.is_match(s),
+                JsonValue::Number(_) | JsonValue::Bool(_) => re.is_match(&lv.to_string()),
+                _ => false,
+            };
+            return if matched { BuiltinResult::Ok } else { BuiltinResult::Respond(400, msg) };
+        }
         let rv = resolve_path(ctx, right).unwrap_or(JsonValue::Null);
         // Loose numeric equality for number <-> numeric string
         fn loose_eq(a: &JsonValue, b: &JsonValue) -> bool {
@@ -0,0 +1,87 @@
+//! The `vector.*` builtins over the in-process vector index (`memory::vector`), for small
+//! semantic-search and RAG routes:
+//!
+//! ```rune
+//! @Route/POST /docs/{id}
+//! run:
+//!     parse-json
+//!     embedding = ai.embed body.text
+//!     vector.upsert id embedding body
+//!     respond 201 "indexed"
+//!
+//! @Route/GET /search
+//! run:
+//!     query = ai.embed query.q
+//!     results = vector.search query top_k=3
+//!     respond 200 results
+//! ```
+
+use crate::builtins::builtin::collection::value_arg;
+use crate::builtins::{arg_text, store_result, BuiltinResult, Context};
+use crate::memory::vector::VECTORS;
+use serde_json::Value as JsonValue;
+
+const DEFAULT_TOP_K: usize = 5;
+
+/// An embedding argument: a context list of numbers or inline JSON such as `[0.1,0.2]`.
+fn embedding_arg(ctx: &mut Context, token: &str, builtin: &str) -> Result<Vec<f64>, BuiltinResult> {
+    match value_arg(ctx, token) {
+        JsonValue::Array(items) => items
+            .iter()
+            .map(|item| item.as_f64())
+            .collect::<Option<Vec<f64>>>()
+            .ok_or_else(|| BuiltinResult::Error(format!("{}: `{}` holds something other than numbers", builtin, token))),
+        _ => Err(BuiltinResult::Error(format!("{}: `{}` is not an embedding (a list of numbers)", builtin, token))),
+    }
+}
+
+/// `vector.upsert <key> <embedding> [payload]` — stores the embedding under the key with
+/// the payload (a context value or literal) returned by searches. Stores the key.
+pub fn builtin_vector_upsert(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let [key, embedding, rest @ ..] = args else {
+        return BuiltinResult::Error("vector.upsert requires a key and an embedding".to_string());
+    };
+    let key = arg_text(ctx, key);
+    let embedding = match embedding_arg(ctx, embedding, "vector.upsert") {
+        Ok(embedding) => embedding,
+        Err(e) => return e,
+    };
+    let payload = rest.first().map(|token| value_arg(ctx, token)).unwrap_or(JsonValue::Null);
+    if let Err(e) = VECTORS.upsert(&key, embedding, payload) {
+        return BuiltinResult::Error(format!("vector.upsert: {}", e));
+    }
+    store_result(ctx, assign_to, JsonValue::String(key))
+}
+
+/// `results = vector.search <embedding> [top_k=N]` — stores the `top_k` (default 5) most
+/// similar entries, best first, as `{key, score, payload}` with the cosine similarity as
+/// `score`.
+pub fn builtin_vector_search(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some((query, options)) = args.split_first() else {
+        return BuiltinResult::Error("vector.search requires an embedding".to_string());
+    };
+    let query = match embedding_arg(ctx, query, "vector.search") {
+        Ok(query) => query,
+        Err(e) => return e,
+    };
+    let mut top_k = DEFAULT_TOP_K;
+    for option in options {
+        match option.split_once('=') {
+            Some(("top_k", n)) => match arg_text(ctx, n).parse::<usize>() {
+                Ok(n) => top_k = n,
+                Err(_) => return BuiltinResult::Error(format!("vector.search: invalid top_k '{}'", n)),
+            },
+            _ => return BuiltinResult::Error(format!("vector.search: unknown option '{}'", option)),
+        }
+    }
+    store_result(ctx, assign_to, JsonValue::Array(VECTORS.search(&query, top_k)))
+}
+
+/// `vector.delete <key>` — removes the entry; stores whether there was one.
+pub fn builtin_vector_delete(args: &[String], ctx: &mut Context, assign_to: Option<&str>) -> BuiltinResult {
+    let Some(key) = args.first() else {
+        return BuiltinResult::Error("vector.delete requires a key".to_string());
+    };
+    let key = arg_text(ctx, key);
+    store_result(ctx, assign_to, JsonValue::Bool(VECTORS.delete(&key)))
+}
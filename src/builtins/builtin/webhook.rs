@@ -0,0 +1,219 @@
+//! `@Webhook` subscriptions and the `emit-event` builtin:
+//!
+//! ```rune
+//! @Webhook/audit
+//! url = https://hooks.example.com/books
+//! events = (book.created book.deleted)
+//! secret = $WEBHOOK_SECRET$
+//!
+//! @Route/POST /books/{id}/publish
+//! run:
+//!     emit-event book.published body
+//!     respond 202 "queued"
+//! ```
+//!
+//! `events` lists event names, `book.*` for every event of a kind, or `*` (the default).
+//! CRUD routes emit `<schema>.created`, `<schema>.updated` and `<schema>.deleted` with the
+//! schema name in lower case.
+//!
+//! Deliveries run in the background, so the request never waits on them. Each is a JSON
+//! `POST` of `{id, event, created_at, data}` with `X-Vectrune-Event` and
+//! `X-Vectrune-Delivery` headers, plus `X-Vectrune-Signature: sha256=<hex>`, the
+//! HMAC-SHA256 of the body, when a `secret` is set. Failed deliveries are retried
+//! `retries` times (default 3), waiting `retry_delay` (default `1s`) and doubling it each
+//! time; a `4xx` other than `408` or `429` is not retried.
+
+use crate::builtins::builtin::control::parse_duration;
+use crate::builtins::builtin::crypto::hmac_hex;
+use crate::builtins::builtin::respond::lookup_value;
+use crate::builtins::{store_result, BuiltinResult, Context};
+use crate::core::AppState;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use crate::util::{log, LogLevel};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use std::time::Duration;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub name: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: Option<String>,
+    pub retries: u32,
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl Webhook {
+    pub fn from_section(section: &Section) -> Result<Self, String> {
+        let name = section.path.get(1).cloned().unwrap_or_default();
+        let text = |key: &str| match section.kv.get(key) {
+            Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+            _ => None,
+        };
+        let duration = |key: &str, default: Duration| match text(key) {
+            Some(spec) => parse_duration(&spec).ok_or_else(|| format!("@Webhook/{}: invalid {} `{}`", name, key, spec)),
+            None => Ok(default),
+        };
+        let url = text("url").ok_or_else(|| format!("@Webhook/{} requires a url", name))?;
+        let mut events: Vec<String> = match section.kv.get("events") {
+            Some(Value::List(items)) => items.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            Some(Value::String(list)) => list.split([' ', ',']).filter(|e| !e.is_empty()).map(str::to_string).collect(),
+            _ => Vec::new(),
+        };
+        if events.is_empty() {
+            events.push("*".to_string());
+        }
+        Ok(Webhook {
+            retries: section
+                .kv
+                .get("retries")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as u32)
+                .unwrap_or(DEFAULT_RETRIES),
+            retry_delay: duration("retry_delay", DEFAULT_RETRY_DELAY)?,
+            timeout: duration("timeout", DEFAULT_TIMEOUT)?,
+            secret: text("secret"),
+            url,
+            events,
+            name,
+        })
+    }
+
+    /// Whether the webhook wants `event`: by name, by `kind.*`, or through `*`.
+    pub fn subscribes(&self, event: &str) -> bool {
+        self.events.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => event.starts_with(prefix),
+            None => pattern == event,
+        })
+    }
+}
+
+/// The document's `@Webhook` sections; invalid ones are skipped with a warning.
+pub fn webhooks(doc: &RuneDocument) -> Vec<Webhook> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Webhook"))
+        .filter_map(|section| match Webhook::from_section(section) {
+            Ok(hook) => Some(hook),
+            Err(e) => {
+                log(LogLevel::Warn, &e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Queues a delivery of `event` to every webhook subscribed to it and returns how many
+/// were queued.
+pub fn emit(doc: &RuneDocument, event: &str, data: JsonValue) -> usize {
+    let hooks: Vec<Webhook> = webhooks(doc).into_iter().filter(|h| h.subscribes(event)).collect();
+    if hooks.is_empty() {
+        return 0;
+    }
+    let traceparent = crate::apps::trace::outgoing_traceparent();
+    let created_at = crate::builtins::builtin::date::to_iso(&chrono::Utc::now());
+    let count = hooks.len();
+    for hook in hooks {
+        let delivery = uuid::Uuid::new_v4().to_string();
+        let body = json!({ "id": delivery, "event": event, "created_at": created_at, "data": data }).to_string();
+        let event = event.to_string();
+        let traceparent = traceparent.clone();
+        tokio::spawn(async move { deliver(hook, event, delivery, body, traceparent).await });
+    }
+    count
+}
+
+/// Whether a failed delivery is worth retrying.
+fn retryable(status: reqwest::StatusCode) -> bool {
+    !status.is_client_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+async fn deliver(hook: Webhook, event: String, delivery: String, body: String, traceparent: Option<String>) {
+    let signature = hook.secret.as_deref().map(|secret| format!("sha256={}", hmac_hex(secret, &body)));
+    let mut delay = hook.retry_delay;
+    let mut attempt = 0;
+    let failure = loop {
+        attempt += 1;
+        let mut request = CLIENT
+            .post(&hook.url)
+            .timeout(hook.timeout)
+            .header("content-type", "application/json")
+            .header("x-vectrune-event", &event)
+            .header("x-vectrune-delivery", &delivery)
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header("x-vectrune-signature", signature);
+        }
+        if let Some(traceparent) = &traceparent {
+            request = request.header("traceparent", traceparent);
+        }
+        let (failure, retry) = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => (format!("status {}", response.status()), retryable(response.status())),
+            Err(e) => (e.to_string(), true),
+        };
+        if !retry || attempt > hook.retries {
+            break failure;
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    };
+    log(
+        LogLevel::Warn,
+        &format!(
+            "Webhook {}: giving up on {} delivery {} after {} attempt(s): {}",
+            hook.name, event, delivery, attempt, failure
+        ),
+    );
+}
+
+/// `emit-event <name> [payload]` — queues `name` for the `@Webhook`s subscribed to it,
+/// with the payload's context value (or the text itself) as `data`. Stores the number of
+/// deliveries queued.
+pub fn builtin_emit_event(
+    args: &[String],
+    ctx: &mut Context,
+    app_state: &AppState,
+    assign_to: Option<&str>,
+) -> BuiltinResult {
+    let Some(event) = args.first().filter(|e| !e.is_empty()) else {
+        return BuiltinResult::Error("emit-event requires an event name".to_string());
+    };
+    let data = match args.get(1) {
+        Some(arg) => lookup_value(ctx, arg).unwrap_or_else(|| JsonValue::String(arg.clone())),
+        None => JsonValue::Null,
+    };
+    let queued = emit(&app_state.doc, event, data);
+    store_result(ctx, assign_to, JsonValue::from(queued))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn parses_subscriptions_and_matches_events() {
+        let doc = parse_rune(
+            "#!RUNE\n@Webhook/audit\nurl = http://localhost/hook\nevents = (book.created book.deleted)\nretries = 5\nretry_delay = 200ms\n\n@Webhook/all\nurl = http://localhost/all\n\n@Webhook/authors\nurl = http://localhost/authors\nevents = author.*\n\n@Webhook/broken\nevents = *\n",
+        )
+        .unwrap();
+        let hooks = webhooks(&doc);
+        assert_eq!(hooks.iter().map(|h| h.name.as_str()).collect::<Vec<_>>(), ["audit", "all", "authors"]);
+        assert_eq!(hooks[0].events, ["book.created", "book.deleted"]);
+        assert_eq!((hooks[0].retries, hooks[0].retry_delay), (5, Duration::from_millis(200)));
+        assert!(hooks[0].subscribes("book.deleted") && !hooks[0].subscribes("book.updated"));
+        assert!(hooks[1].subscribes("anything"));
+        assert!(hooks[2].subscribes("author.updated") && !hooks[2].subscribes("book.created"));
+    }
+}
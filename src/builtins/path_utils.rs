@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 pub fn candidate_paths(filename: &str, rune_dir: &Path) -> Vec<PathBuf> {
     let provided = Path::new(filename);
@@ -27,3 +27,75 @@ pub fn resolve_write_path(filename: &str, rune_dir: &Path) -> PathBuf {
         rune_dir.join(provided)
     }
 }
+
+/// Resolves `filename` inside `rune_dir`, refusing absolute paths and anything that
+/// leaves the directory through `..` or a symlink.
+pub fn scoped_path(filename: &str, rune_dir: &Path) -> Result<PathBuf, String> {
+    let provided = Path::new(filename);
+    let mut relative = PathBuf::new();
+    for component in provided.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !relative.pop() {
+                    return Err(format!("{} is outside the rune directory", filename));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("{} must be relative to the rune directory", filename));
+            }
+        }
+    }
+    let path = rune_dir.join(&relative);
+
+    let root = rune_dir.canonicalize().map_err(|e| format!("{}: {}", rune_dir.display(), e))?;
+    match real_location(&path, 0) {
+        Some(real) if real.starts_with(&root) => Ok(path),
+        _ => Err(format!("{} is outside the rune directory", filename)),
+    }
+}
+
+/// Where `path` really points, decided by its nearest existing ancestor. A dangling
+/// symlink counts as existing and points wherever its target would be created.
+/// `None` when links loop.
+fn real_location(path: &Path, depth: usize) -> Option<PathBuf> {
+    let mut existing = path;
+    while existing.symlink_metadata().is_err() {
+        existing = existing.parent()?;
+    }
+    if let Ok(real) = existing.canonicalize() {
+        return Some(real);
+    }
+    if depth >= 40 {
+        return None;
+    }
+    let target = std::fs::read_link(existing).ok()?;
+    let parent = existing.parent().unwrap_or(Path::new(""));
+    real_location(&parent.join(target), depth + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scoped_path;
+
+    #[test]
+    fn scoped_paths_stay_inside_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(scoped_path("a/../b.json", dir.path()).unwrap(), dir.path().join("b.json"));
+        assert!(scoped_path("../b.json", dir.path()).is_err());
+        assert!(scoped_path("a/../../b.json", dir.path()).is_err());
+        assert!(scoped_path("/etc/passwd", dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks_cannot_leave_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path().join("new.json"), dir.path().join("out.json")).unwrap();
+        std::os::unix::fs::symlink("kept.json", dir.path().join("in.json")).unwrap();
+        assert!(scoped_path("out.json", dir.path()).is_err());
+        assert_eq!(scoped_path("in.json", dir.path()).unwrap(), dir.path().join("in.json"));
+    }
+}
@@ -1,127 +1,267 @@
 use anyhow::{bail, Context, Result};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::Duration;
 
-const DEFAULT_MODEL: &str = "phi4";
-const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:11434/api/generate";
+use crate::core::ai::{AiConfig, Provider};
+use crate::core::get_app_type;
+use crate::rune_parser::parse_rune;
+use crate::util::{log, LogLevel};
 
-#[derive(Serialize)]
-struct OllamaRequest {
-    model: String,
-    prompt: String,
-    stream: bool,
+pub async fn handle_ai(prompt: &str, model: Option<&str>) -> Result<()> {
+    let config = AiConfig::from_env(model).map_err(anyhow::Error::msg)?;
+
+    let system_instruction = "You are a CLI command shortcut assistant. Respond ONLY with JSON. Format: {\"command\":\"<cli>\"}.";
+
+    let prompt = if config.provider == Provider::Ollama {
+        format!(
+            "{}\nUser request: {}\nRemember: keep answers short and respond with JSON only.",
+            system_instruction,
+            prompt.trim()
+        )
+    } else {
+        format!("{}\n\nRequest: {}", system_instruction, prompt)
+    };
+    let reply = ask(&config, &prompt, true, "Vectrune is thinking...").await?;
+    emit_json_reply(reply.trim());
+
+    Ok(())
 }
 
-#[derive(Deserialize)]
-struct OllamaResponse {
-    response: String
+/// `vectrune ai new "a bookstore API with books and authors"`: asks the model for a whole
+/// Rune app, checks the reply the way `vectrune check` would, and sends any parse errors or
+/// problems back for another attempt before writing the file.
+pub async fn handle_ai_new(description: &str, model: Option<&str>, out: &str, retries: usize) -> Result<()> {
+    let config = AiConfig::from_env(model).map_err(anyhow::Error::msg)?;
+    let mut prompt = app_prompt(description);
+
+    for attempt in 1..=retries.max(1) {
+        let reply = ask(&config, &prompt, false, &format!("Vectrune is writing {} (attempt {})...", out, attempt)).await?;
+
+        let document = extract_document(&reply);
+        match validate_app(&document) {
+            Ok(()) => {
+                std::fs::write(out, &document).with_context(|| format!("Failed to write {}", out))?;
+                println!("Wrote {} (run it with `vectrune {}`)", out, out);
+                return Ok(());
+            }
+            Err(problems) => {
+                log(LogLevel::Warn, &format!("Attempt {} was not a valid app: {}", attempt, problems.join("; ")));
+                prompt = retry_prompt(description, &document, &problems);
+            }
+        }
+    }
+    bail!("the model did not produce a valid Rune app after {} attempts", retries.max(1))
 }
 
-pub async fn handle_ai(prompt: &str, model: Option<&str>) -> Result<()> {
-    let model = match model {
-        Some(m) if !m.is_empty() => m.to_string(),
-        _ => std::env::var("VECTRUNE_AI_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string()),
-    };
-    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+/// `vectrune ai explain app.rune [--trace GET /books/1]`: sends the document with its parse
+/// error, `vectrune check` problems or a step trace of a failing request to the model, then
+/// prints the explanation and the suggested fix as a unified diff. With `apply`, the fix is
+/// written back once it parses and passes the checks.
+pub async fn handle_ai_explain(
+    script: &str,
+    model: Option<&str>,
+    trace: Option<(&str, &str)>,
+    body_file: Option<&str>,
+    apply: bool,
+) -> Result<()> {
+    let source = std::fs::read_to_string(script).with_context(|| format!("Failed to read {}", script))?;
+    let config = match parse_rune(&source) {
+        Ok(doc) => AiConfig::from_doc(&doc, model),
+        Err(_) => AiConfig::from_env(model),
+    }
+    .map_err(anyhow::Error::msg)?;
+    let mut failure = diagnose(&source).join("\n");
+    if let (true, Some((method, target))) = (failure.is_empty(), trace) {
+        failure = match crate::cli::trace::trace_report(&[script], method, target, body_file).await {
+            Ok(report) => format!("Step trace of {} {}:\n{}", method, target, report),
+            Err(e) => format!("{} {} failed: {}", method, target, e),
+        };
+    }
+    if failure.is_empty() {
+        println!("{}: no parse errors or problems found (pass --trace METHOD PATH to explain a failing request)", script);
+        return Ok(());
+    }
 
-    let system_instruction = "You are a CLI command shortcut assistant. Respond ONLY with JSON. Format: {\"command\":\"<cli>\"}.";
+    let reply = ask(&config, &explain_prompt(&source, &failure), false, "Vectrune is reading the error...").await?;
+
+    let explanation = reply.split("```").next().unwrap_or_default().trim();
+    if !explanation.is_empty() {
+        println!("{}\n", explanation);
+    }
+    let fixed = extract_document(&reply);
+    let patch = unified_diff(&source, &fixed, script);
+    if patch.is_empty() {
+        bail!("the model did not suggest any change");
+    }
+    print!("{}", patch);
+
+    let problems = diagnose(&fixed);
+    if !problems.is_empty() {
+        let message = format!("the suggested fix still has problems: {}", problems.join("; "));
+        if apply {
+            bail!("{}; {} was not changed", message, script);
+        }
+        log(LogLevel::Warn, &message);
+    } else if apply {
+        std::fs::write(script, &fixed).with_context(|| format!("Failed to write {}", script))?;
+        log(LogLevel::Info, &format!("Applied the fix to {}", script));
+    }
+    Ok(())
+}
 
+fn explain_prompt(source: &str, failure: &str) -> String {
+    format!(
+        "You fix Vectrune apps written in the Rune language.\n\n{}\nThis document:\n{}\nfails with:\n{}\n\n\
+         Explain the cause in two or three sentences, then give the whole corrected document in one ```rune block.",
+        RUNE_GRAMMAR, source, failure
+    )
+}
+
+/// A unified diff from `old` to `new`, labelled `a/<path>` and `b/<path>`; empty when they match.
+fn unified_diff(old: &str, new: &str, path: &str) -> String {
+    let lines = |text: &str| -> Vec<String> {
+        text.split_inclusive('\n').map(|line| if line.ends_with('\n') { line.to_string() } else { format!("{}\n", line) }).collect()
+    };
+    let (old, new) = (lines(old), lines(new));
+    let old: Vec<&str> = old.iter().map(String::as_str).collect();
+    let new: Vec<&str> = new.iter().map(String::as_str).collect();
+    let path = path.trim_start_matches('/');
+    difflib::unified_diff(&old, &new, &format!("a/{}", path), &format!("b/{}", path), "", "", 3)
+        .into_iter()
+        // difflib writes a tab before the (empty) file date on the header lines.
+        .map(|line| line.replace("\t\n", "\n"))
+        .collect()
+}
+
+/// Sends `prompt` while a spinner shows the end of the reply as it streams in.
+async fn ask(config: &AiConfig, prompt: &str, json: bool, message: &str) -> Result<String> {
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(120));
     pb.set_style(
         ProgressStyle::with_template("{spinner:.cyan} {msg}")?
             .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
     );
-    pb.set_message("Vectrune is thinking...");
+    pb.set_message(message.to_string());
+    let reply = config
+        .complete(prompt, json, |text| pb.set_message(format!("{} {}", message, stream_tail(text))))
+        .await;
+    match reply {
+        Ok(reply) => {
+            pb.finish_and_clear();
+            Ok(reply)
+        }
+        Err(e) => {
+            pb.abandon();
+            bail!(e)
+        }
+    }
+}
 
-    if model.to_lowercase().contains("gemini") {
-        let api_key = std::env::var("GEMINI_API_KEY")
-            .context("GEMINI_API_KEY environment variable not set")?;
+/// The last line of a streaming reply, cut to fit beside the spinner.
+fn stream_tail(text: &str) -> String {
+    let line = text.trim_end().rsplit('\n').next().unwrap_or_default().trim();
+    let chars: Vec<char> = line.chars().collect();
+    match chars.len() {
+        0..=60 => line.to_string(),
+        n => format!("…{}", chars[n - 60..].iter().collect::<String>()),
+    }
+}
 
-        // Google uses a URL format like: https://generativelanguage.googleapis.com/v1beta/models/{model}:generateContent?key={api_key}
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            model, api_key
-        );
+/// The parts of the Rune grammar a generated app needs, with a small complete example.
+const RUNE_GRAMMAR: &str = r#"A Rune document starts with the line `#!RUNE` and is made of sections.
+- A section starts with `@Name` or `@Name/sub path` on its own line, e.g. `@App`, `@Schema/Book`, `@DataSource/Store`, `@Route/GET /books/{id}`.
+- Inside a section, `key = value` lines set values; values are plain text, numbers or true/false, without quotes.
+- `name:` followed by indented lines is a series; route handlers put their steps in a `run:` series.
+- Lines starting with `+` begin a record; the record's other fields follow indented by two spaces.
+- Lines starting with `#` are comments.
 
-        let payload = GeminiRequest {
-            contents: vec![Content {
-                role: "user".to_string(),
-                parts: vec![Part {
-                    text: format!("{}\n\nRequest: {}", system_instruction, prompt),
-                }],
-            }],
-            generation_config: Some(GenerationConfig {
-                response_mime_type: "application/json".to_string(),
-            }),
-        };
+Sections a REST app uses:
+- `@App` with `name`, `type = REST` and `version`.
+- `@Schema/<Name>` with one `field = string|number|bool` line per field.
+- `@DataSource/<Name>` with `type = postgres|mysql|jsonfile|csv|memory` and, for postgres and mysql, `connection = $DATABASE_URL$` and `db = <name>`.
+- `@Route/CRUD /<plural>` with `schema = <Schema>` and `data_source = <DataSource>` gives list, get, create, update and delete routes. Every schema and data_source a route names must be declared.
+- `@Route/<METHOD> <path>` with a `run:` series for custom handlers; steps include `log "text"`, `parse-json`, `validate body #Schema`, `if <condition>:` and `respond <status> <value>`.
 
-        let res = client.post(&url).json(&payload).send().await?;
+Example:
+#!RUNE
 
-        // 1. Grab the status code now (it's a simple value that can be copied)
-        let status = res.status();
+@App
+name = Cat API
+type = REST
+version = 1.0
 
-        if !status.is_success() {
-            // 2. Consume the response to get the text (this moves 'res')
-            let error_text = res.text().await.unwrap_or_else(|_| "Unknown error".into());
+@DataSource/Cats
+type = postgres
+connection = $DATABASE_URL$
+db = cats
 
-            pb.abandon();
-            // 3. Use the 'status' variable we saved earlier
-            bail!("Gemini API Error ({}): {}", status, error_text);
-        }
+@Schema/Cat
+name = string
+weight = number
+neutered = bool
 
-        // If successful, parse the body (this also moves 'res')
-        let gemini_res: GeminiResponse = res
-            .json()
-            .await
-            .context("Failed to parse successful Gemini response")?;
+@Route/CRUD /cats
+schema = Cat
+data_source = Cats
 
-        if let Some(candidate) = gemini_res.candidates.first() {
-            if let Some(part) = candidate.content.parts.first() {
-                emit_json_reply(part.text.trim());
-            }
-        }
-    } else {
-        let endpoint =
-            std::env::var("VECTRUNE_OLLAMA_URL").unwrap_or_else(|_| DEFAULT_ENDPOINT.to_string());
-        let payload = OllamaRequest {
-            model,
-            prompt: format!(
-                "{}\nUser request: {}\nRemember: keep answers short and respond with JSON only.",
-                system_instruction,
-                prompt.trim()
-            ),
-            stream: false,
-        };
+@Route/GET /health
+run:
+    respond 200 "ok"
+"#;
 
-        let response = client
-            .post(&endpoint)
-            .json(&payload)
-            .send()
-            .await
-            .context("failed to reach Ollama. Is it running?")?;
-
-        if !response.status().is_success() {
-            bail!(
-                "Ollama returned status {}. Body: {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            );
-        }
+fn app_prompt(description: &str) -> String {
+    format!(
+        "You write Vectrune apps in the Rune language.\n\n{}\nWrite a complete, runnable Rune document for this app: {}\n\
+         Respond with the document only, starting with #!RUNE, with no explanation and no markdown fences.",
+        RUNE_GRAMMAR,
+        description.trim()
+    )
+}
+
+fn retry_prompt(description: &str, previous: &str, problems: &[String]) -> String {
+    format!(
+        "{}\n\nYour previous answer was:\n{}\n\nIt has these problems:\n- {}\n\nFix them and respond with the corrected document only.",
+        app_prompt(description),
+        previous,
+        problems.join("\n- ")
+    )
+}
 
-        let ollama: OllamaResponse = response
-            .json()
-            .await
-            .context("invalid response from Ollama")?;
-        emit_json_reply(ollama.response.trim());
+/// The Rune document in a model reply, without markdown fences or text around it.
+fn extract_document(reply: &str) -> String {
+    let mut text = reply.trim();
+    if let Some(start) = text.find("```") {
+        let fenced = &text[start + 3..];
+        let body = fenced.split_once('\n').map(|(_, body)| body).unwrap_or(fenced);
+        text = body.split("```").next().unwrap_or(body);
     }
+    let text = text.find("#!RUNE").map(|i| &text[i..]).unwrap_or(text);
+    let text = text.trim();
+    let body = text.strip_prefix("#!RUNE").unwrap_or(text).trim_start_matches('\n');
+    format!("#!RUNE\n{}\n", body.trim_end())
+}
 
-    pb.finish_and_clear();
+/// The parse error of a document, or the problems `vectrune check` finds in it.
+fn diagnose(document: &str) -> Vec<String> {
+    match parse_rune(document) {
+        Ok(doc) => crate::cli::check::problems(&doc),
+        Err(e) => vec![e.to_string()],
+    }
+}
 
-    Ok(())
+/// Parses a generated document and runs the `vectrune check` rules on it.
+fn validate_app(document: &str) -> std::result::Result<(), Vec<String>> {
+    let doc = parse_rune(document).map_err(|e| vec![e.to_string()])?;
+    let mut problems = crate::cli::check::problems(&doc);
+    if get_app_type(&doc).is_none() {
+        problems.push("the document needs an @App section with a type".to_string());
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
 }
 
 fn emit_json_reply(reply: &str) {
@@ -157,34 +297,28 @@ fn emit_json_reply(reply: &str) {
     println!("{}", fallback);
 }
 
-#[derive(Serialize)]
-struct GeminiRequest {
-    contents: Vec<Content>,
-    generation_config: Option<GenerationConfig>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Content {
-    role: String,
-    parts: Vec<Part>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[derive(Serialize, Deserialize)]
-struct Part {
-    text: String,
-}
-
-#[derive(Serialize)]
-struct GenerationConfig {
-    response_mime_type: String, // "application/json"
-}
-
-#[derive(Deserialize)]
-struct GeminiResponse {
-    candidates: Vec<Candidate>,
-}
+    #[test]
+    fn generated_apps_are_unfenced_and_checked() {
+        let reply = "Here is your app:\n```rune\n#!RUNE\n@App\nname = Books\ntype = REST\n\n@Route/CRUD /books\nschema = Book\n```\nEnjoy!";
+        let document = extract_document(reply);
+        assert_eq!(document, "#!RUNE\n@App\nname = Books\ntype = REST\n\n@Route/CRUD /books\nschema = Book\n");
+        let problems = validate_app(&document).unwrap_err();
+        assert!(problems.iter().any(|p| p.contains("schema 'Book' is not declared")));
+        assert!(validate_app("#!RUNE\n@App\nname = Books\ntype = REST\n").is_ok());
+        assert!(validate_app("#!RUNE\n@App\nname = Books\n").is_err());
+    }
 
-#[derive(Deserialize)]
-struct Candidate {
-    content: Content,
+    #[test]
+    fn fixes_are_shown_as_unified_diffs() {
+        let patch = unified_diff("#!RUNE\n@App\nname = Books\n", "#!RUNE\n@App\nname = Books\ntype = REST\n", "app.rune");
+        assert_eq!(
+            patch,
+            "--- a/app.rune\n+++ b/app.rune\n@@ -1,3 +1,4 @@\n #!RUNE\n @App\n name = Books\n+type = REST\n"
+        );
+        assert_eq!(unified_diff("#!RUNE\n", "#!RUNE\n", "app.rune"), "");
+    }
 }
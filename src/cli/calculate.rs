@@ -1,115 +1,494 @@
-use crate::rune_ast::{RuneDocument, Value};
+//! `--calculate` queries over the records of a document.
+//!
+//! A query is an arithmetic expression over aggregates, optionally followed by a `where`
+//! filter and a `group by` field:
+//!
+//! ```text
+//! avg Skateboarder.age
+//! sum(Order.total) / count(Order)
+//! avg Skateboarder.age where country == 'USA' and age > 20
+//! count Order group by status
+//! ```
+//!
+//! Aggregates are `avg`, `sum`, `min`, `max`, `count`, `median`, `mode`, `variance`,
+//! `stddev`, `p90` (any `p<N>`) and `percentile:N`, written `func Section.field` or
+//! `func(Section.field)`; `count Section` counts records. A target may name a nested
+//! section (`App/Billing.amount`), a series or list (`Metrics.samples`) or a path into
+//! its map items (`Metrics.samples.value`, `it.value` in filters). The filter applies to the
+//! records of every aggregate, and with `group by` the query is evaluated once per
+//! distinct value of the field.
 
-pub fn handle_calculate(doc: &RuneDocument, expr: &str) -> Result<(), String> {
-    match calculate_to_string(doc, expr) {
-        Ok(s) => {
-            println!("{}", s);
-            Ok(())
+use crate::arithmetic::eval_arithmetic_with;
+use crate::rune_ast::{RuneDocument, Section, Value};
+
+const FUNCTIONS: [&str; 9] = ["avg", "sum", "min", "max", "count", "median", "mode", "variance", "stddev"];
+
+/// The percentile a `p90` or `percentile:90` aggregate asks for.
+fn percentile_of(func: &str) -> Option<f64> {
+    let n = func.strip_prefix("percentile:").or_else(|| func.strip_prefix('p'))?;
+    n.parse::<f64>().ok().filter(|p| (0.0..=100.0).contains(p))
+}
+
+fn is_function(func: &str) -> bool {
+    FUNCTIONS.contains(&func) || percentile_of(func).is_some()
+}
+
+const USAGE: &str = "Unsupported calculate expression. Examples: 'avg Section.field', 'count Section', \
+     'sum(Order.total) / count(Order)', 'count Order group by status'";
+
+pub fn handle_calculate(doc: &RuneDocument, expr: &str, output_format: Option<&str>) -> Result<(), String> {
+    let out = match output_format {
+        Some("json") => serde_json::to_string_pretty(&calculate_to_json(doc, expr)?).map_err(|e| e.to_string())?,
+        _ => calculate_to_string(doc, expr)?,
+    };
+    println!("{}", out);
+    Ok(())
+}
+
+/// The result of `expr` as text: a number, or a table with one row per group.
+pub fn calculate_to_string(doc: &RuneDocument, expr: &str) -> Result<String, String> {
+    let calculation = evaluate(doc, expr)?;
+    let Some(field) = &calculation.group_by else {
+        return Ok(calculation.format(calculation.rows[0].1));
+    };
+    let rows: Vec<(&str, String)> = calculation
+        .rows
+        .iter()
+        .map(|(key, value)| (key.as_str(), calculation.format(*value)))
+        .collect();
+    let width = rows.iter().map(|(key, _)| key.len()).chain([field.len()]).max().unwrap_or(0);
+    let mut table = format!("{:<width$}  {}", field, calculation.expr, width = width);
+    for (key, value) in rows {
+        table.push_str(&format!("\n{:<width$}  {}", key, value, width = width));
+    }
+    Ok(table)
+}
+
+/// The result of `expr` as JSON: a number, or an object keyed by group.
+pub fn calculate_to_json(doc: &RuneDocument, expr: &str) -> Result<serde_json::Value, String> {
+    let calculation = evaluate(doc, expr)?;
+    let number = |n: f64| serde_json::from_str(&calculation.format(n)).unwrap_or(serde_json::Value::Null);
+    if calculation.group_by.is_none() {
+        return Ok(number(calculation.rows[0].1));
+    }
+    Ok(serde_json::Value::Object(
+        calculation.rows.iter().map(|(key, value)| (key.clone(), number(*value))).collect(),
+    ))
+}
+
+struct Calculation {
+    expr: String,
+    group_by: Option<String>,
+    /// A lone `avg`, which has always been reported rounded to a whole number.
+    rounded: bool,
+    /// `(group, value)`; a single unnamed row without `group by`.
+    rows: Vec<(String, f64)>,
+}
+
+impl Calculation {
+    fn format(&self, n: f64) -> String {
+        if self.rounded {
+            (n.round() as i64).to_string()
+        } else {
+            format_number(n)
         }
-        Err(e) => Err(e),
     }
 }
 
-pub fn calculate_to_string(doc: &RuneDocument, expr: &str) -> Result<String, String> {
-    // Supported:
-    //  - avg Section.field
-    //  - sum Section.field
-    //  - min Section.field
-    //  - max Section.field
-    //  - count Section
-    //  - count Section.field
-    let parts: Vec<&str> = expr.split_whitespace().collect();
-    if parts.len() != 2 {
-        return Err(
-            "Unsupported calculate expression. Examples: 'avg Section.field', 'count Section'"
-                .to_string(),
-        );
+/// Whole numbers without a fraction, others to at most six decimal places.
+fn format_number(n: f64) -> String {
+    if n.fract().abs() < f64::EPSILON && n.abs() < i64::MAX as f64 {
+        return (n as i64).to_string();
+    }
+    let fixed = format!("{:.6}", n);
+    fixed.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+struct Aggregate {
+    func: String,
+    section: String,
+    field: Option<String>,
+}
+
+fn evaluate(doc: &RuneDocument, query: &str) -> Result<Calculation, String> {
+    let (rest, group_by) = match find_keyword(query, "group by") {
+        Some(at) => (&query[..at], Some(query[at + "group by".len()..].trim().to_string())),
+        None => (query, None),
+    };
+    let (expr, filter) = match find_keyword(rest, "where") {
+        Some(at) => (rest[..at].trim(), Some(parse_filter(&rest[at + "where".len()..])?)),
+        None => (rest.trim(), None),
+    };
+    if group_by.as_deref() == Some("") {
+        return Err("Expected a field after 'group by'".to_string());
     }
-    let func = parts[0].to_lowercase();
-    let target = parts[1];
-
-    match func.as_str() {
-        "avg" | "sum" | "min" | "max" => {
-            let (section, field) = target.split_once('.').ok_or("Expected Section.field")?;
-            let mut nums: Vec<f64> = Vec::new();
-            for sec in doc.get_sections(section) {
-                for rec in &sec.records {
-                    if let Some(val) = rec.kv.get(field) {
-                        let num = match val {
-                            Value::Number(n) => Some(*n),
-                            Value::String(s) => s.parse::<f64>().ok(),
-                            _ => None,
-                        };
-                        if let Some(n) = num {
-                            nums.push(n);
-                        }
-                    }
+    let (arithmetic, aggregates) = parse_aggregates(expr)?;
+    if aggregates.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let rounded = aggregates.len() == 1 && aggregates[0].func == "avg" && arithmetic.trim() == "__agg0";
+
+    let passes = |item: &Value| filter.as_ref().is_none_or(|f| f.matches(item));
+    let items: Vec<Vec<(Value, String)>> = aggregates
+        .iter()
+        .map(|aggregate| items(doc, aggregate).into_iter().filter(|(item, _)| passes(item)).collect())
+        .collect();
+    let groups: Vec<Option<String>> = match &group_by {
+        None => vec![None],
+        Some(field) => {
+            let mut keys: Vec<Option<String>> = Vec::new();
+            for (item, _) in items.iter().flatten() {
+                let key = Some(group_key(lookup(item, field)));
+                if !keys.contains(&key) {
+                    keys.push(key);
                 }
             }
-            if nums.is_empty() {
-                return Err(format!("No numeric values found for {}.{}", section, field));
-            }
-            match func.as_str() {
-                "avg" => {
-                    let sum: f64 = nums.iter().sum();
-                    let avg = sum / (nums.len() as f64);
-                    Ok((avg.round() as i64).to_string())
-                }
-                "sum" => {
-                    let sum: f64 = nums.iter().sum();
-                    // print integer if it's an integer value, else print as float trimmed
-                    if (sum.fract()).abs() < f64::EPSILON {
-                        Ok((sum as i64).to_string())
-                    } else {
-                        Ok(sum.to_string())
-                    }
-                }
-                "min" => {
-                    if let Some(min) = nums.iter().cloned().reduce(f64::min) {
-                        if (min.fract()).abs() < f64::EPSILON {
-                            Ok((min as i64).to_string())
-                        } else {
-                            Ok(min.to_string())
-                        }
-                    } else {
-                        Err("No values".to_string())
-                    }
-                }
-                "max" => {
-                    if let Some(max) = nums.iter().cloned().reduce(f64::max) {
-                        if (max.fract()).abs() < f64::EPSILON {
-                            Ok((max as i64).to_string())
-                        } else {
-                            Ok(max.to_string())
-                        }
-                    } else {
-                        Err("No values".to_string())
-                    }
-                }
-                _ => unreachable!(),
+            keys
+        }
+    };
+
+    let mut rows = Vec::new();
+    for group in groups {
+        let in_group = |item: &Value| match (&group, &group_by) {
+            (Some(key), Some(field)) => group_key(lookup(item, field)) == *key,
+            _ => true,
+        };
+        let values = aggregates
+            .iter()
+            .zip(&items)
+            .map(|(aggregate, items)| {
+                let items: Vec<&(Value, String)> = items.iter().filter(|(item, _)| in_group(item)).collect();
+                aggregate_value(aggregate, &items)
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        let value = eval_arithmetic_with(&arithmetic, |name| {
+            name.strip_prefix("__agg")
+                .and_then(|i| i.parse::<usize>().ok())
+                .and_then(|i| values.get(i).copied())
+        })?;
+        rows.push((group.unwrap_or_default(), value));
+    }
+    Ok(Calculation {
+        expr: expr.to_string(),
+        group_by,
+        rounded,
+        rows,
+    })
+}
+
+/// Sections named by a target: an exact path such as `App/Billing`, or any section with
+/// the name in its path.
+fn sections<'a>(doc: &'a RuneDocument, name: &str) -> Vec<&'a Section> {
+    if name.contains('/') {
+        doc.sections.iter().filter(|s| s.path.join("/") == name).collect()
+    } else {
+        doc.get_sections(name)
+    }
+}
+
+/// What an aggregate ranges over, with the path to its value inside each item: the
+/// entries of a series or list when the field names one (`Metrics.samples`,
+/// `Metrics.samples.value`), the section's own keys when it has no records, otherwise
+/// its records.
+fn items(doc: &RuneDocument, aggregate: &Aggregate) -> Vec<(Value, String)> {
+    let field = aggregate.field.as_deref().unwrap_or("");
+    let (head, rest) = field.split_once('.').unwrap_or((field, ""));
+    let mut items = Vec::new();
+    for section in sections(doc, &aggregate.section) {
+        let list = section.series.get(head).or(match section.kv.get(head) {
+            Some(Value::List(list)) => Some(list),
+            _ => None,
+        });
+        match list {
+            Some(list) if !head.is_empty() => items.extend(list.iter().map(|v| (v.clone(), rest.to_string()))),
+            _ if section.records.is_empty() && !head.is_empty() => {
+                items.push((Value::Map(section.kv.clone()), field.to_string()))
             }
+            _ => items.extend(section.records.iter().map(|r| (Value::Map(r.kv.clone()), field.to_string()))),
         }
-        "count" => {
-            if let Some((section, field)) = target.split_once('.') {
-                // count records with non-null field
-                let mut cnt = 0usize;
-                for sec in doc.get_sections(section) {
-                    for rec in &sec.records {
-                        if rec.kv.get(field).is_some() {
-                            cnt += 1;
-                        }
-                    }
-                }
-                Ok(cnt.to_string())
-            } else {
-                // count records in section
-                let mut cnt = 0usize;
-                for sec in doc.get_sections(target) {
-                    cnt += sec.records.len();
+    }
+    items
+}
+
+/// The value at a dotted `path` inside `item`; `it` (or an empty path) is the item itself.
+fn lookup<'v>(item: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').filter(|p| !p.is_empty() && *p != "it").try_fold(item, |value, key| match value {
+        Value::Map(map) => map.get(key),
+        Value::List(list) => key.parse::<usize>().ok().and_then(|i| list.get(i)),
+        _ => None,
+    })
+}
+
+fn aggregate_value(aggregate: &Aggregate, items: &[&(Value, String)]) -> Result<f64, String> {
+    if aggregate.func == "count" {
+        return Ok(items.iter().filter(|(item, path)| lookup(item, path).is_some()).count() as f64);
+    }
+    let Some(field) = &aggregate.field else {
+        return Err(format!("Expected Section.field after {}", aggregate.func));
+    };
+    let nums: Vec<f64> = items
+        .iter()
+        .filter_map(|(item, path)| match lookup(item, path)? {
+            Value::Number(n) => Some(*n),
+            Value::String(s) => s.parse::<f64>().ok(),
+            _ => None,
+        })
+        .collect();
+    if nums.is_empty() {
+        return Err(format!("No numeric values found for {}.{}", aggregate.section, field));
+    }
+    let mean = nums.iter().sum::<f64>() / nums.len() as f64;
+    let variance = || nums.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / nums.len() as f64;
+    Ok(match aggregate.func.as_str() {
+        "avg" => mean,
+        "sum" => nums.iter().sum(),
+        "min" => nums.iter().cloned().fold(f64::INFINITY, f64::min),
+        "max" => nums.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        "median" => percentile(nums, 50.0),
+        "mode" => mode(nums),
+        "variance" => variance(),
+        "stddev" => variance().sqrt(),
+        func => percentile(nums, percentile_of(func).unwrap_or(50.0)),
+    })
+}
+
+/// The `p`th percentile, interpolating linearly between the two nearest values.
+fn percentile(mut nums: Vec<f64>, p: f64) -> f64 {
+    nums.sort_by(f64::total_cmp);
+    let rank = p / 100.0 * (nums.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    nums[low] + (nums[high] - nums[low]) * (rank - low as f64)
+}
+
+/// The most frequent value; the smallest of them on a tie.
+fn mode(mut nums: Vec<f64>) -> f64 {
+    nums.sort_by(f64::total_cmp);
+    let mut best = (nums[0], 0);
+    let mut run = (nums[0], 0);
+    for n in nums {
+        run = if n == run.0 { (n, run.1 + 1) } else { (n, 1) };
+        if run.1 > best.1 {
+            best = run;
+        }
+    }
+    best.0
+}
+
+fn group_key(value: Option<&Value>) -> String {
+    match value {
+        None => "(none)".to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => format_number(*n),
+        Some(other) => other.to_json().to_string(),
+    }
+}
+
+/// Byte offset of `keyword` as a whole word outside quotes, ignoring case.
+fn find_keyword(s: &str, keyword: &str) -> Option<usize> {
+    let lower = s.to_ascii_lowercase();
+    let mut quote: Option<char> = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, _) => {
+                let before = s[..i].chars().last().is_none_or(char::is_whitespace);
+                let after = lower[i..].strip_prefix(keyword).is_some_and(|rest| rest.starts_with(char::is_whitespace));
+                if before && after {
+                    return Some(i);
                 }
-                Ok(cnt.to_string())
             }
         }
-        _ => Err("Supported functions: avg, sum, min, max, count".to_string()),
+    }
+    None
+}
+
+/// Replaces each aggregate in `expr` with an `__agg<N>` placeholder for the arithmetic
+/// evaluator, returning the rewritten expression and the aggregates in order.
+fn parse_aggregates(expr: &str) -> Result<(String, Vec<Aggregate>), String> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut out = String::new();
+    let mut aggregates = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if !(c.is_ascii_alphabetic() || c == '_') {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.' | '/' | '-' | ':')) {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        let func = word.to_ascii_lowercase();
+        if !is_function(&func) {
+            out.push_str(&word);
+            continue;
+        }
+        let mut j = i;
+        while j < chars.len() && chars[j].is_whitespace() {
+            j += 1;
+        }
+        let target: String = if chars.get(j) == Some(&'(') {
+            let close = chars[j..].iter().position(|&c| c == ')').ok_or("Missing closing parenthesis")? + j;
+            i = close + 1;
+            chars[j + 1..close].iter().collect()
+        } else if j > i {
+            let end = chars[j..]
+                .iter()
+                .position(|c| c.is_whitespace() || matches!(c, '+' | '*' | '%' | '(' | ')'))
+                .map_or(chars.len(), |p| p + j);
+            i = end;
+            chars[j..end].iter().collect()
+        } else {
+            return Err(format!("Expected a target after {}", func));
+        };
+        let target = target.trim().trim_start_matches('@');
+        if target.is_empty() {
+            return Err(format!("Expected a target after {}", func));
+        }
+        let (section, field) = match target.split_once('.') {
+            Some((section, field)) => (section.to_string(), Some(field.to_string())),
+            None => (target.to_string(), None),
+        };
+        out.push_str(&format!("__agg{}", aggregates.len()));
+        aggregates.push(Aggregate { func, section, field });
+    }
+    Ok((out, aggregates))
+}
+
+/// A `where` clause: any of several groups of comparisons that must all hold.
+struct Filter(Vec<Vec<Comparison>>);
+
+struct Comparison {
+    field: String,
+    op: &'static str,
+    value: String,
+}
+
+fn parse_filter(s: &str) -> Result<Filter, String> {
+    let mut any = Vec::new();
+    for alternative in split_keyword(s, "or") {
+        let mut all = Vec::new();
+        for comparison in split_keyword(alternative, "and") {
+            let (at, op) = ["==", "!=", ">=", "<=", ">", "<"]
+                .iter()
+                .find_map(|op| comparison.find(op).map(|at| (at, *op)))
+                .ok_or_else(|| format!("Expected a comparison like field == value, got '{}'", comparison.trim()))?;
+            let field = comparison[..at].trim().to_string();
+            let value = comparison[at + op.len()..].trim();
+            let value = ['\'', '"']
+                .iter()
+                .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+                .unwrap_or(value)
+                .to_string();
+            all.push(Comparison { field, op, value });
+        }
+        any.push(all);
+    }
+    Ok(Filter(any))
+}
+
+fn split_keyword<'a>(s: &'a str, keyword: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(at) = find_keyword(rest, keyword) {
+        parts.push(&rest[..at]);
+        rest = &rest[at + keyword.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+impl Filter {
+    fn matches(&self, item: &Value) -> bool {
+        self.0.iter().any(|all| all.iter().all(|c| c.matches(item)))
+    }
+}
+
+impl Comparison {
+    fn matches(&self, item: &Value) -> bool {
+        use std::cmp::Ordering;
+        let ordering = match lookup(item, &self.field) {
+            None => None,
+            Some(Value::Number(n)) => self.value.parse::<f64>().ok().and_then(|v| n.partial_cmp(&v)),
+            Some(Value::Bool(b)) => self.value.parse::<bool>().ok().map(|v| b.cmp(&v)),
+            Some(Value::String(s)) => match (s.parse::<f64>(), self.value.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.partial_cmp(&b),
+                _ => Some(s.as_str().cmp(self.value.as_str())),
+            },
+            Some(_) => None,
+        };
+        match self.op {
+            "==" => ordering == Some(Ordering::Equal),
+            "!=" => ordering != Some(Ordering::Equal),
+            ">=" => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            "<=" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            ">" => ordering == Some(Ordering::Greater),
+            _ => ordering == Some(Ordering::Less),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: &str = "#!RUNE\n@Order\n+ total = 10\n  status = paid\n  country = USA\n+ total = 25\n  status = pending\n  country = CA\n+ total = 5\n  status = paid\n  country = USA\n";
+
+    fn orders() -> RuneDocument {
+        RuneDocument::from_str(ORDERS).unwrap()
+    }
+
+    #[test]
+    fn combines_aggregates_with_arithmetic() {
+        let doc = orders();
+        assert_eq!(calculate_to_string(&doc, "sum(Order.total) / count(Order)").unwrap(), "13.333333");
+        assert_eq!(calculate_to_string(&doc, "max Order.total - min Order.total").unwrap(), "20");
+    }
+
+    #[test]
+    fn computes_distribution_statistics() {
+        let doc = orders();
+        assert_eq!(calculate_to_string(&doc, "median Order.total").unwrap(), "10");
+        assert_eq!(calculate_to_string(&doc, "variance Order.total").unwrap(), "72.222222");
+        assert_eq!(calculate_to_string(&doc, "stddev(Order.total)").unwrap(), "8.498366");
+        assert_eq!(calculate_to_string(&doc, "p90 Order.total").unwrap(), "22");
+        assert_eq!(calculate_to_string(&doc, "percentile:25 Order.total").unwrap(), "7.5");
+        assert_eq!(calculate_to_string(&doc, "mode Order.total").unwrap(), "5");
+    }
+
+    #[test]
+    fn reads_series_and_nested_sections() {
+        let mut doc = RuneDocument::from_str(
+            "#!RUNE\n@Metrics\nsamples = (3 5 10)\n\n@App/Billing\namount = 40\n\n@Billing\namount = 1\n",
+        )
+        .unwrap();
+        doc.merge(RuneDocument::from_json(&serde_json::json!({
+            "Hosts": {"hosts": [{"name": "a", "load": 2}, {"name": "b", "load": 6}]}
+        })));
+        assert_eq!(calculate_to_string(&doc, "avg Metrics.samples").unwrap(), "6");
+        assert_eq!(calculate_to_string(&doc, "count Metrics.samples where it > 4").unwrap(), "2");
+        assert_eq!(calculate_to_string(&doc, "sum Hosts.hosts.load where it.name == b").unwrap(), "6");
+        assert_eq!(calculate_to_string(&doc, "sum App/Billing.amount").unwrap(), "40");
+        assert_eq!(calculate_to_string(&doc, "sum Billing.amount").unwrap(), "41");
+    }
+
+    #[test]
+    fn filters_and_groups_records() {
+        let doc = orders();
+        assert_eq!(calculate_to_string(&doc, "sum Order.total where country == 'USA'").unwrap(), "15");
+        assert_eq!(calculate_to_string(&doc, "count Order where total > 5 and status != paid").unwrap(), "1");
+        assert_eq!(
+            calculate_to_string(&doc, "count Order group by status").unwrap(),
+            "status   count Order\npaid     2\npending  1"
+        );
+        assert_eq!(
+            calculate_to_json(&doc, "sum(Order.total) group by country").unwrap(),
+            serde_json::json!({"USA": 15, "CA": 25})
+        );
     }
 }
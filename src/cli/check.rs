@@ -0,0 +1,106 @@
+//! `vectrune check app.rune [more.rune ...]`: loads the documents the way a run would and
+//! reports what would break at startup or on the first request, without serving anything:
+//! unsupported app types, routes naming a schema or datasource that is not declared, and
+//! `@Seed` or `@Migration` sections that cannot be resolved.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+
+use crate::core::{extract_data_sources, extract_schemas, get_app_type, migrations, seeds};
+use crate::rune_ast::RuneDocument;
+use crate::rune_parser::load_rune_document_from_source;
+
+/// The problems found in a loaded document, one message each.
+pub(crate) fn problems(doc: &RuneDocument) -> Vec<String> {
+    let mut found = Vec::new();
+    if let Some(app_type) = get_app_type(doc) {
+        if !crate::apps::app_type_supported(&app_type) {
+            found.push(format!("@App type '{}' is not supported", app_type));
+        }
+    }
+    let schemas = extract_schemas(doc);
+    let data_sources = extract_data_sources(doc);
+    for route in doc.sections.iter().filter(|s| s.path.first().map(String::as_str) == Some("Route")) {
+        let label = format!("@{}", route.path.join("/"));
+        let is_crud = route.path.get(1).is_some_and(|m| m.eq_ignore_ascii_case("CRUD"));
+        match route.kv.get("schema").and_then(|v| v.as_str()) {
+            Some(name) if !schemas.contains_key(name) => {
+                found.push(format!("{}: schema '{}' is not declared", label, name))
+            }
+            None if is_crud => found.push(format!("{}: CRUD routes need a schema", label)),
+            _ => {}
+        }
+        match route.kv.get("data_source").and_then(|v| v.as_str()) {
+            Some(name) if !data_sources.contains_key(name) => {
+                found.push(format!("{}: datasource '{}' is not declared", label, name))
+            }
+            None if is_crud => found.push(format!("{}: CRUD routes need a data_source", label)),
+            _ => {}
+        }
+        if let Err(e) = crate::apps::rest::middleware::steps_for(doc, route) {
+            found.push(format!("{}: {}", label, e));
+        }
+    }
+    for function in doc.sections.iter().filter(|s| s.path.first().map(String::as_str) == Some("Function")) {
+        let header = function.path.get(1).map(String::as_str).unwrap_or_default();
+        if let Err(e) = crate::builtins::builtin::function::parse_signature(header) {
+            found.push(e);
+        } else if !function.series.contains_key("run") {
+            found.push(format!("@Function/{}: requires run: steps", header));
+        }
+    }
+    if let Err(e) = seeds::extract_seeds(doc) {
+        found.push(e);
+    }
+    if let Err(e) = migrations::extract_migrations(doc) {
+        found.push(e);
+    }
+    found
+}
+
+pub fn handle_check(matches: &ArgMatches) -> Result<()> {
+    let scripts: Vec<&String> = matches.get_many::<String>("SCRIPT").context("Missing rune files")?.collect();
+    let mut doc = RuneDocument { sections: Vec::new() };
+    let mut failed = 0;
+    for script in &scripts {
+        match load_rune_document_from_source(script) {
+            Ok(loaded) => doc.merge(loaded),
+            Err(e) => {
+                println!("{}: {}", script, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} files failed to load", failed);
+    }
+    let found = problems(&doc);
+    let name = scripts.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ");
+    if found.is_empty() {
+        println!("{}: ok", name);
+        return Ok(());
+    }
+    for problem in &found {
+        println!("{}: {}", name, problem);
+    }
+    bail!("{} problems found", found.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn routes_must_name_declared_schemas_and_datasources() {
+        let doc = parse_rune(
+            "#!RUNE\n@App\ntype = REST\n\n@Schema/Book\ntitle = string\n\n\
+             @Route/CRUD /books\nschema = Book\ndata_source = Shelf\n\n\
+             @Route/CRUD /authors\ndata_source = Shelf\n",
+        )
+        .unwrap();
+        let found = problems(&doc);
+        assert!(found.iter().any(|p| p.contains("datasource 'Shelf' is not declared")));
+        assert!(found.iter().any(|p| p.contains("CRUD routes need a schema")));
+    }
+}
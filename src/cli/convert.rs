@@ -0,0 +1,156 @@
+//! `vectrune convert <dir> --from yaml --to rune --out <dir>`: converts every matching
+//! file under a directory, mirroring its layout, and prints a summary. With `--check`
+//! nothing is written and the command fails if any output would change.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::rune_ast::RuneDocument;
+use crate::rune_parser::load_rune_document_from_str_with_base;
+use crate::util::json_to_xml;
+
+pub const FORMATS: [&str; 4] = ["rune", "json", "yaml", "xml"];
+
+/// File extensions read for an input format; the first is used for output.
+fn extensions(format: &str) -> &'static [&'static str] {
+    match format {
+        "json" => &["json"],
+        "yaml" => &["yaml", "yml"],
+        "xml" => &["xml"],
+        _ => &["rune"],
+    }
+}
+
+pub fn parse_as(content: &str, format: &str, path: &Path) -> Result<RuneDocument> {
+    match format {
+        "json" => Ok(RuneDocument::from_json(&serde_json::from_str(content)?)),
+        "yaml" => RuneDocument::from_yaml(content).map_err(|e| anyhow::anyhow!(e)),
+        "xml" => RuneDocument::from_xml(content).map_err(|e| anyhow::anyhow!(e)),
+        _ => {
+            let base = path.parent().unwrap_or_else(|| Path::new("."));
+            load_rune_document_from_str_with_base(content, base, &path.to_string_lossy())
+                .map_err(|e| anyhow::anyhow!(e))
+        }
+    }
+}
+
+pub fn render_as(doc: &RuneDocument, format: &str) -> Result<String> {
+    let text = match format {
+        "json" => serde_json::to_string_pretty(&doc.to_json())?,
+        "yaml" => serde_yaml::to_string(&doc.to_json())?,
+        "xml" => json_to_xml(&doc.to_json(), "root"),
+        _ => doc.to_string(),
+    };
+    Ok(if text.ends_with('\n') { text } else { format!("{}\n", text) })
+}
+
+/// Outcome of converting a tree.
+#[derive(Debug, Default)]
+pub struct ConvertReport {
+    /// Outputs written (or, with `check`, that would be written).
+    pub changed: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl ConvertReport {
+    pub fn summary(&self, check: bool) -> String {
+        let verb = if check { "would change" } else { "written" };
+        format!(
+            "{} files: {} {}, {} unchanged, {} failed",
+            self.changed.len() + self.unchanged.len() + self.failed.len(),
+            self.changed.len(),
+            verb,
+            self.unchanged.len(),
+            self.failed.len()
+        )
+    }
+}
+
+/// Converts `source` (a file or a directory, searched recursively) from `from` to `to`,
+/// writing under `out` (default: next to each input).
+pub fn convert_tree(source: &Path, from: &str, to: &str, out: Option<&Path>, check: bool) -> Result<ConvertReport> {
+    if from == to {
+        bail!("--from and --to are both {}", from);
+    }
+    let inputs: Vec<PathBuf> = if source.is_file() {
+        vec![source.to_path_buf()]
+    } else if source.is_dir() {
+        let mut files: Vec<PathBuf> = WalkDir::new(source)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| extensions(from).contains(&e))
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.sort();
+        files
+    } else {
+        bail!("{} does not exist", source.display());
+    };
+    let root = if source.is_file() { source.parent().unwrap_or(Path::new("")) } else { source };
+
+    let mut report = ConvertReport::default();
+    for input in inputs {
+        let relative = input.strip_prefix(root).unwrap_or(&input);
+        let target = out
+            .map(|out| out.join(relative))
+            .unwrap_or_else(|| input.clone())
+            .with_extension(extensions(to)[0]);
+        let converted = std::fs::read_to_string(&input)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| parse_as(&content, from, &input))
+            .and_then(|doc| render_as(&doc, to));
+        let text = match converted {
+            Ok(text) => text,
+            Err(e) => {
+                report.failed.push((input, e.to_string()));
+                continue;
+            }
+        };
+        if std::fs::read_to_string(&target).ok().as_deref() == Some(text.as_str()) {
+            report.unchanged.push(target);
+            continue;
+        }
+        if !check {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::write(&target, text).with_context(|| format!("Failed to write {}", target.display()))?;
+        }
+        report.changed.push(target);
+    }
+    Ok(report)
+}
+
+pub fn handle_convert(matches: &ArgMatches) -> Result<()> {
+    let source = matches.get_one::<String>("SOURCE").context("Missing source path")?;
+    let from = matches.get_one::<String>("from").context("Missing --from format")?;
+    let to = matches.get_one::<String>("to").context("Missing --to format")?;
+    let out = matches.get_one::<String>("out").map(PathBuf::from);
+    let check = matches.get_flag("check");
+
+    let report = convert_tree(Path::new(source), from, to, out.as_deref(), check)?;
+    for path in &report.changed {
+        println!("{} {}", if check { "would change" } else { "wrote" }, path.display());
+    }
+    for (path, error) in &report.failed {
+        eprintln!("failed {}: {}", path.display(), error);
+    }
+    println!("{}", report.summary(check));
+    if !report.failed.is_empty() {
+        bail!("{} files could not be converted", report.failed.len());
+    }
+    if check && !report.changed.is_empty() {
+        bail!("{} outputs are out of date", report.changed.len());
+    }
+    Ok(())
+}
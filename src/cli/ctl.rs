@@ -0,0 +1,182 @@
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Method;
+
+use crate::apps::admin::{ADMIN_PREFIX, ADMIN_TOKEN_ENV};
+
+/// Characters escaped in a path segment: all but RFC 3986 unreserved ones.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// A request against the admin API of a running instance.
+#[derive(Debug, PartialEq)]
+pub struct CtlRequest {
+    pub method: Method,
+    pub path: String,
+    pub body: Option<String>,
+}
+
+/// Maps a `vectrune ctl` subcommand onto the admin endpoint it talks to.
+pub fn build_ctl_request(matches: &ArgMatches) -> Result<CtlRequest> {
+    let request = match matches.subcommand() {
+        Some(("memory", memory)) => match memory.subcommand() {
+            Some(("get", args)) => CtlRequest {
+                method: Method::GET,
+                path: memory_path(required(args, "key")?),
+                body: None,
+            },
+            Some(("set", args)) => CtlRequest {
+                method: Method::PUT,
+                path: memory_path(required(args, "key")?),
+                body: Some(required(args, "value")?.to_string()),
+            },
+            Some(("del", args)) => CtlRequest {
+                method: Method::DELETE,
+                path: memory_path(required(args, "key")?),
+                body: None,
+            },
+            _ => bail!("Unsupported memory command. Try 'vectrune ctl memory --help'."),
+        },
+        Some(("routes", routes)) => match routes.subcommand() {
+            Some(("list", _)) => CtlRequest {
+                method: Method::GET,
+                path: format!("{}/routes", ADMIN_PREFIX),
+                body: None,
+            },
+            _ => bail!("Unsupported routes command. Try 'vectrune ctl routes --help'."),
+        },
+        Some(("document", document)) => match document.subcommand() {
+            Some(("get", _)) => CtlRequest {
+                method: Method::GET,
+                path: format!("{}/document", ADMIN_PREFIX),
+                body: None,
+            },
+            Some(("push", args)) => {
+                let file = required(args, "file")?;
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read {}", file))?;
+                CtlRequest {
+                    method: Method::POST,
+                    path: format!("{}/document", ADMIN_PREFIX),
+                    body: Some(content),
+                }
+            }
+            _ => bail!("Unsupported document command. Try 'vectrune ctl document --help'."),
+        },
+        Some(("experiments", _)) => CtlRequest {
+            method: Method::GET,
+            path: format!("{}/experiments", ADMIN_PREFIX),
+            body: None,
+        },
+        Some(("datasources", _)) => CtlRequest {
+            method: Method::GET,
+            path: format!("{}/datasources", ADMIN_PREFIX),
+            body: None,
+        },
+        Some(("reload", _)) => CtlRequest {
+            method: Method::POST,
+            path: format!("{}/reload", ADMIN_PREFIX),
+            body: None,
+        },
+        Some(("rollback", _)) => CtlRequest {
+            method: Method::POST,
+            path: format!("{}/rollback", ADMIN_PREFIX),
+            body: None,
+        },
+        _ => bail!("Unsupported ctl command. Try 'vectrune ctl --help'."),
+    };
+    Ok(request)
+}
+
+/// The admin path of a memory entry, with the key encoded as one path segment.
+fn memory_path(key: &str) -> String {
+    format!("{}/memory/{}", ADMIN_PREFIX, utf8_percent_encode(key, PATH_SEGMENT))
+}
+
+fn required<'a>(args: &'a ArgMatches, name: &str) -> Result<&'a str> {
+    args.get_one::<String>(name)
+        .map(|s| s.as_str())
+        .with_context(|| format!("Missing <{}> argument", name))
+}
+
+pub async fn handle_ctl(matches: &ArgMatches) -> Result<()> {
+    let addr = matches
+        .get_one::<String>("addr")
+        .map(|s| s.as_str())
+        .unwrap_or("127.0.0.1:3000");
+    let base = if addr.starts_with("http://") || addr.starts_with("https://") {
+        addr.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", addr)
+    };
+    let token = matches
+        .get_one::<String>("token")
+        .cloned()
+        .or_else(|| std::env::var(ADMIN_TOKEN_ENV).ok())
+        .with_context(|| format!("Admin token required: pass --token or set {}", ADMIN_TOKEN_ENV))?;
+
+    let request = build_ctl_request(matches)?;
+    let url = format!("{}{}", base, request.path);
+    let client = reqwest::Client::new();
+    let mut builder = client.request(request.method, &url).bearer_auth(token);
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+    let response = builder
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+
+    // Pretty-print JSON replies, pass everything else (e.g. Rune documents) through as-is.
+    let output = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or(text);
+    if !status.is_success() {
+        bail!("{} {}", status, output);
+    }
+    println!("{}", output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build_ctl_request;
+    use clap::{Arg, Command};
+    use reqwest::Method;
+
+    fn ctl_command() -> Command {
+        Command::new("ctl")
+            .subcommand(
+                Command::new("memory")
+                    .subcommand(Command::new("get").arg(Arg::new("key")))
+                    .subcommand(Command::new("set").arg(Arg::new("key")).arg(Arg::new("value"))),
+            )
+            .subcommand(Command::new("routes").subcommand(Command::new("list")))
+            .subcommand(Command::new("reload"))
+    }
+
+    #[test]
+    fn maps_subcommands_to_admin_endpoints() {
+        let matches = ctl_command().get_matches_from(["ctl", "memory", "get", "users"]);
+        let request = build_ctl_request(&matches).unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.path, "/__admin/memory/users");
+
+        let matches = ctl_command().get_matches_from(["ctl", "memory", "set", "count", "3"]);
+        let request = build_ctl_request(&matches).unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.body.as_deref(), Some("3"));
+
+        let matches = ctl_command().get_matches_from(["ctl", "memory", "get", "users/admin?x#1 é"]);
+        assert_eq!(build_ctl_request(&matches).unwrap().path, "/__admin/memory/users%2Fadmin%3Fx%231%20%C3%A9");
+
+        let matches = ctl_command().get_matches_from(["ctl", "routes", "list"]);
+        assert_eq!(build_ctl_request(&matches).unwrap().path, "/__admin/routes");
+
+        let matches = ctl_command().get_matches_from(["ctl", "reload"]);
+        assert_eq!(build_ctl_request(&matches).unwrap().method, Method::POST);
+    }
+}
@@ -0,0 +1,269 @@
+//! `vectrune diff a.rune b.rune`: a structural diff of two documents, for reviewing
+//! config changes in CI.
+//!
+//! Sections are matched by path, keys by name, and series items and records by a
+//! longest-common-subsequence match so an insertion does not shift every later item.
+//! A record that was edited in place is reported field by field.
+
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+
+use crate::cli::merge::load_document;
+use crate::rune_ast::{RuneDocument, Section, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Remove,
+    Change,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Remove => "remove",
+            Op::Change => "change",
+        }
+    }
+
+    fn marker(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Remove => '-',
+            Op::Change => '~',
+        }
+    }
+}
+
+/// One difference: `@Section`, `@Section.key`, `@Section.key[2]`, `@Section[1]` (a
+/// record) or `@Section[1].field`, with the old and new values where they exist.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub op: Op,
+    pub path: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+pub fn handle_diff(matches: &ArgMatches) -> Result<()> {
+    let a = matches.get_one::<String>("A").context("Missing first document")?;
+    let b = matches.get_one::<String>("B").context("Missing second document")?;
+    let old = load_document(a).map_err(anyhow::Error::msg)?;
+    let new = load_document(b).map_err(anyhow::Error::msg)?;
+    let changes = diff_documents(&old, &new);
+    let out = match matches.get_one::<String>("output").map(String::as_str) {
+        Some("json") => serde_json::to_string_pretty(&changes_to_json(&changes))?,
+        Some("rune-patch") => changes_to_rune_patch(&changes),
+        _ => changes_to_text(&changes),
+    };
+    if !out.is_empty() {
+        println!("{}", out.trim_end());
+    }
+    if matches.get_flag("exit-code") && !changes.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The changes that turn `old` into `new`, in document order.
+pub fn diff_documents(old: &RuneDocument, new: &RuneDocument) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut unmatched: Vec<&Section> = new.sections.iter().collect();
+    for section in &old.sections {
+        let label = format!("@{}", section.path.join("/"));
+        match unmatched.iter().position(|s| s.path == section.path) {
+            Some(i) => diff_sections(&label, section, unmatched.remove(i), &mut changes),
+            None => changes.push(change(Op::Remove, label, Some(section_json(section)), None)),
+        }
+    }
+    for section in unmatched {
+        changes.push(change(Op::Add, format!("@{}", section.path.join("/")), None, Some(section_json(section))));
+    }
+    changes
+}
+
+fn change(op: Op, path: String, old: Option<serde_json::Value>, new: Option<serde_json::Value>) -> Change {
+    Change { op, path, old, new }
+}
+
+/// A whole section in its `-o json` shape.
+pub(crate) fn section_json(section: &Section) -> serde_json::Value {
+    let mut obj: serde_json::Map<String, serde_json::Value> =
+        section.kv.iter().map(|(k, v)| (k.clone(), v.to_json())).collect();
+    if !section.records.is_empty() {
+        let records = section.records.iter().map(|r| Value::Map(r.kv.clone()).to_json()).collect();
+        obj.insert("record".to_string(), serde_json::Value::Array(records));
+    }
+    for (key, items) in &section.series {
+        obj.insert(key.clone(), list_json(items));
+    }
+    serde_json::Value::Object(obj)
+}
+
+fn diff_sections(label: &str, old: &Section, new: &Section, changes: &mut Vec<Change>) {
+    let json = |map: &std::collections::HashMap<String, Value>| -> serde_json::Map<String, serde_json::Value> {
+        map.iter().map(|(k, v)| (k.clone(), v.to_json())).collect()
+    };
+    diff_fields(label, &json(&old.kv), &json(&new.kv), changes);
+
+    let mut keys: Vec<&String> = old.series.keys().chain(new.series.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let path = format!("{}.{}", label, key);
+        match (old.series.get(key), new.series.get(key)) {
+            (Some(a), Some(b)) => {
+                let a: Vec<_> = a.iter().map(Value::to_json).collect();
+                let b: Vec<_> = b.iter().map(Value::to_json).collect();
+                diff_items(&path, &a, &b, false, changes);
+            }
+            (Some(a), None) => changes.push(change(Op::Remove, path, Some(list_json(a)), None)),
+            (None, Some(b)) => changes.push(change(Op::Add, path, None, Some(list_json(b)))),
+            (None, None) => {}
+        }
+    }
+
+    let records = |s: &Section| -> Vec<serde_json::Value> {
+        s.records.iter().map(|r| serde_json::Value::Object(json(&r.kv))).collect()
+    };
+    diff_items(label, &records(old), &records(new), true, changes);
+}
+
+fn list_json(items: &[Value]) -> serde_json::Value {
+    serde_json::Value::Array(items.iter().map(Value::to_json).collect())
+}
+
+/// Keys added, removed or changed between two maps, in key order.
+fn diff_fields(
+    label: &str,
+    old: &serde_json::Map<String, serde_json::Value>,
+    new: &serde_json::Map<String, serde_json::Value>,
+    changes: &mut Vec<Change>,
+) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let path = format!("{}.{}", label, key);
+        match (old.get(key), new.get(key)) {
+            (Some(a), Some(b)) if a != b => changes.push(change(Op::Change, path, Some(a.clone()), Some(b.clone()))),
+            (Some(a), None) => changes.push(change(Op::Remove, path, Some(a.clone()), None)),
+            (None, Some(b)) => changes.push(change(Op::Add, path, None, Some(b.clone()))),
+            _ => {}
+        }
+    }
+}
+
+/// Items removed and added between two lists. With `by_field`, a removed record and an
+/// added one at the same position are reported as field changes of that record.
+fn diff_items(
+    label: &str,
+    old: &[serde_json::Value],
+    new: &[serde_json::Value],
+    by_field: bool,
+    changes: &mut Vec<Change>,
+) {
+    let (mut i, mut j) = (0, 0);
+    let common = lcs(old, new);
+    for (ci, cj) in common.into_iter().chain([(old.len(), new.len())]) {
+        let removed = &old[i..ci];
+        let added = &new[j..cj];
+        let paired = if by_field { removed.len().min(added.len()) } else { 0 };
+        for k in 0..paired {
+            if let (serde_json::Value::Object(a), serde_json::Value::Object(b)) = (&removed[k], &added[k]) {
+                diff_fields(&format!("{}[{}]", label, j + k), a, b, changes);
+            }
+        }
+        for (k, item) in removed.iter().enumerate().skip(paired) {
+            changes.push(change(Op::Remove, format!("{}[{}]", label, i + k), Some(item.clone()), None));
+        }
+        for (k, item) in added.iter().enumerate().skip(paired) {
+            changes.push(change(Op::Add, format!("{}[{}]", label, j + k), None, Some(item.clone())));
+        }
+        (i, j) = (ci + 1, cj + 1);
+    }
+}
+
+/// The index pairs of a longest common subsequence of `a` and `b`.
+fn lcs(a: &[serde_json::Value], b: &[serde_json::Value]) -> Vec<(usize, usize)> {
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] { table[i + 1][j + 1] + 1 } else { table[i + 1][j].max(table[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j, mut pairs) = (0, 0, Vec::new());
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// A value on one line: scalars as written, anything else as compact JSON.
+fn inline(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => (f as i64).to_string(),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// One line per change: `+ @S.key: value`, `- @S.key: value`, `~ @S.key: old -> new`.
+pub fn changes_to_text(changes: &[Change]) -> String {
+    changes
+        .iter()
+        .map(|c| match (&c.old, &c.new) {
+            (Some(old), Some(new)) => format!("{} {}: {} -> {}", c.op.marker(), c.path, inline(old), inline(new)),
+            (Some(value), None) | (None, Some(value)) => format!("{} {}: {}", c.op.marker(), c.path, inline(value)),
+            (None, None) => format!("{} {}", c.op.marker(), c.path),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn changes_to_json(changes: &[Change]) -> serde_json::Value {
+    serde_json::Value::Array(
+        changes
+            .iter()
+            .map(|c| {
+                let mut entry = serde_json::Map::new();
+                entry.insert("op".to_string(), c.op.name().into());
+                entry.insert("path".to_string(), c.path.clone().into());
+                if let Some(old) = &c.old {
+                    entry.insert("old".to_string(), old.clone());
+                }
+                if let Some(new) = &c.new {
+                    entry.insert("new".to_string(), new.clone());
+                }
+                serde_json::Value::Object(entry)
+            })
+            .collect(),
+    )
+}
+
+/// The changes as a Rune document: an `@Patch` section with one record per change.
+pub fn changes_to_rune_patch(changes: &[Change]) -> String {
+    let mut out = String::from("#!RUNE\n@Patch\n");
+    for c in changes {
+        out.push_str(&format!("+ op = {}\n  path = {}\n", c.op.name(), c.path));
+        if let Some(old) = &c.old {
+            out.push_str(&format!("  old = {}\n", inline(old)));
+        }
+        if let Some(new) = &c.new {
+            out.push_str(&format!("  new = {}\n", inline(new)));
+        }
+    }
+    out
+}
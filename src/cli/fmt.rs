@@ -0,0 +1,160 @@
+//! `vectrune fmt app.rune [more.rune ...] [--check]`: normalizes the layout of rune documents
+//! in place. Trailing whitespace goes, top-level and record assignments read `key = value`,
+//! runs of blank lines collapse to one and every section header (with the comments directly
+//! above it) is set off by a blank line. Comments and indentation are kept as written.
+//!
+//! A file is only rewritten when the formatted text parses to the same document.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+
+use crate::rune_parser::load_rune_document_from_str_with_base;
+
+/// `key=value` as `key = value`, keeping a leading `+` of a record line.
+fn space_assignment(line: &str) -> String {
+    let (prefix, rest) = match line.strip_prefix('+') {
+        Some(rest) => ("+ ", rest.trim_start()),
+        None => ("", line),
+    };
+    let Some(eq) = rest.find('=') else {
+        return line.to_string();
+    };
+    let key = rest[..eq].trim();
+    let value = rest[eq + 1..].trim_start();
+    let is_key = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !is_key || value.starts_with('=') {
+        return line.to_string();
+    }
+    format!("{}{} = {}", prefix, key, value)
+}
+
+/// How many brackets and braces `line` opens, less those it closes, outside quotes.
+fn bracket_balance(line: &str) -> i32 {
+    let mut in_quotes = false;
+    let mut balance = 0;
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '[' | '{' if !in_quotes => balance += 1,
+            ']' | '}' if !in_quotes => balance -= 1,
+            _ => {}
+        }
+    }
+    balance
+}
+
+/// Starts a multi-line value (`key >`), which runs to the next blank line.
+fn starts_multiline(line: &str) -> bool {
+    line.strip_suffix('>').is_some_and(|key| {
+        let key = key.trim();
+        !key.is_empty() && !key.contains(char::is_whitespace)
+    })
+}
+
+fn format_source(source: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut depth = 0;
+    let mut in_multiline = false;
+    for raw in source.lines() {
+        let line = raw.trim_end();
+        if line.is_empty() {
+            in_multiline = false;
+            if out.last().is_some_and(|l| !l.is_empty()) {
+                out.push(String::new());
+            }
+            continue;
+        }
+        let mut line = line.to_string();
+        if line.starts_with('@') {
+            depth = 0;
+            in_multiline = false;
+            let mut at = out.len();
+            while at > 0 && out[at - 1].starts_with('#') && !out[at - 1].starts_with("#!") {
+                at -= 1;
+            }
+            if at > 0 && !out[at - 1].is_empty() {
+                out.insert(at, String::new());
+            }
+        } else if in_multiline {
+            out.push(line);
+            continue;
+        } else if depth == 0 && !line.starts_with([' ', '\t', '#']) {
+            line = space_assignment(&line);
+            in_multiline = starts_multiline(&line);
+        }
+        depth = (depth + bracket_balance(&line)).max(0);
+        out.push(line);
+    }
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    let mut formatted = out.join("\n");
+    formatted.push('\n');
+    formatted
+}
+
+/// Whether `formatted` loads to the same document as `source`, imports included.
+fn same_document(file: &str, source: &str, formatted: &str) -> Result<bool> {
+    let base = super::run::script_dir(file);
+    let load = |text: &str| {
+        load_rune_document_from_str_with_base(text, &base, file)
+            .map_err(|e| anyhow::anyhow!(e))
+            .and_then(|doc| Ok(serde_json::to_value(&doc)?))
+    };
+    Ok(load(source)? == load(formatted)?)
+}
+
+pub fn handle_fmt(matches: &ArgMatches) -> Result<()> {
+    let check = matches.get_flag("check");
+    let mut unformatted = 0;
+    let mut failed = 0;
+    for file in matches.get_many::<String>("FILES").context("Missing rune files")? {
+        let source = std::fs::read_to_string(file).with_context(|| format!("reading {}", file))?;
+        let formatted = format_source(&source);
+        if formatted == source {
+            println!("{}: already formatted", file);
+            continue;
+        }
+        match same_document(file, &source, &formatted) {
+            Ok(true) => {}
+            Ok(false) => {
+                failed += 1;
+                println!("{}: formatting would change what the document means; left unchanged", file);
+                continue;
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}: {}", file, e);
+                continue;
+            }
+        }
+        unformatted += 1;
+        if check {
+            println!("{}: needs formatting", file);
+        } else {
+            std::fs::write(file, formatted).with_context(|| format!("writing {}", file))?;
+            println!("{}: formatted", file);
+        }
+    }
+    if failed > 0 {
+        bail!("{} files could not be formatted", failed);
+    }
+    if check && unformatted > 0 {
+        bail!("{} files need formatting", unformatted);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_spacing_and_keeps_comments() {
+        let source = "#!RUNE\n@App\ntype=REST   \n\n\n# Books\n@Route/GET /books\nrun:\n    respond 200 \"ok\"\n+ id=1\n\n";
+        assert_eq!(
+            format_source(source),
+            "#!RUNE\n\n@App\ntype = REST\n\n# Books\n@Route/GET /books\nrun:\n    respond 200 \"ok\"\n+ id = 1\n"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! `vectrune serve --git <repo> --path <file>`: a minimal GitOps loop. The repository is
+//! cloned (shallow) into a checkout directory, the document at `--path` is served, and
+//! every `--poll` interval the branch is fetched again. A new commit whose document loads
+//! and builds replaces the running app; a broken commit is logged and the current
+//! version keeps serving.
+
+use anyhow::{bail, Context, Result};
+use axum::serve;
+use clap::ArgMatches;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use crate::apps::admin::{build_switchable_router, DocumentSwitch};
+use crate::apps::{app_type_supported, routes::render_route_summary};
+use crate::builtins::builtin::control::parse_duration;
+use crate::core::{extract_data_sources, extract_steps, extract_schemas, get_app_type, AppState};
+use crate::rune_ast::RuneDocument;
+use crate::rune_parser::load_rune_document_from_path;
+use crate::util::{log, LogLevel};
+
+const DEFAULT_POLL: Duration = Duration::from_secs(60);
+
+/// A git repository mirrored into a local checkout.
+pub struct GitSource {
+    pub url: String,
+    pub branch: Option<String>,
+    pub checkout: PathBuf,
+}
+
+impl GitSource {
+    /// Checks out `url` under the system temp directory, one directory per repository.
+    pub fn new(url: &str, branch: Option<String>) -> Self {
+        let digest: String = Sha256::digest(url.as_bytes())
+            .iter()
+            .take(8)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        GitSource {
+            url: url.to_string(),
+            branch,
+            checkout: std::env::temp_dir().join("vectrune-git").join(digest),
+        }
+    }
+
+    fn git(&self, dir: &Path, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// The commit currently checked out.
+    pub fn head(&self) -> Result<String> {
+        self.git(&self.checkout, &["rev-parse", "HEAD"])
+    }
+
+    /// Clones the repository, or fetches the branch and resets the checkout to it.
+    /// Returns whether the checked-out commit changed.
+    pub fn sync(&self) -> Result<bool> {
+        if !self.checkout.join(".git").exists() {
+            let parent = self.checkout.parent().unwrap_or_else(|| Path::new("."));
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+            let checkout = self.checkout.to_string_lossy();
+            let mut args = vec!["clone", "--depth", "1"];
+            if let Some(branch) = &self.branch {
+                args.extend(["--branch", branch.as_str()]);
+            }
+            args.extend([self.url.as_str(), checkout.as_ref()]);
+            self.git(parent, &args)?;
+            return Ok(true);
+        }
+        let before = self.head()?;
+        let branch = match &self.branch {
+            Some(branch) => branch.clone(),
+            None => self.git(&self.checkout, &["rev-parse", "--abbrev-ref", "HEAD"])?,
+        };
+        self.git(&self.checkout, &["fetch", "--depth", "1", "origin", &branch])?;
+        self.git(&self.checkout, &["reset", "--hard", "FETCH_HEAD"])?;
+        Ok(self.head()? != before)
+    }
+}
+
+/// Loads the document at `path` inside the checkout and checks it can be served.
+pub fn load_checkout_document(source: &GitSource, path: &str) -> Result<RuneDocument> {
+    let file = source.checkout.join(path);
+    let doc = load_rune_document_from_path(&file)
+        .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+    let app_type = get_app_type(&doc).unwrap_or_else(|| "REST".to_string());
+    if !app_type_supported(&app_type) {
+        bail!("{}: unsupported App type {}", file.display(), app_type);
+    }
+    Ok(doc)
+}
+
+/// Pulls the repository and, on a new commit, switches the server to its document.
+/// Returns the deployed commit, or `None` when nothing changed.
+pub async fn deploy_if_changed(
+    source: &GitSource,
+    path: &str,
+    switch: &DocumentSwitch,
+) -> Result<Option<String>> {
+    if !source.sync()? {
+        return Ok(None);
+    }
+    let commit = source.head()?;
+    let doc = load_checkout_document(source, path)
+        .with_context(|| format!("Commit {} not deployed", short(&commit)))?;
+    switch
+        .activate(doc)
+        .await
+        .map_err(|e| anyhow::anyhow!("Commit {} not deployed: {}", short(&commit), e))?;
+    Ok(Some(commit))
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(8)]
+}
+
+pub async fn handle_git_serve(matches: &ArgMatches) -> Result<()> {
+    let url = matches
+        .get_one::<String>("git")
+        .context("Missing --git repository")?;
+    let path = matches
+        .get_one::<String>("path")
+        .map(|s| s.as_str())
+        .unwrap_or("app.rune");
+    let poll = match matches.get_one::<String>("poll") {
+        Some(value) => parse_duration(value)
+            .filter(|d| !d.is_zero())
+            .with_context(|| format!("Invalid --poll interval `{}`", value))?,
+        None => DEFAULT_POLL,
+    };
+    let mut source = GitSource::new(url, matches.get_one::<String>("branch").cloned());
+    if let Some(checkout) = matches.get_one::<String>("checkout") {
+        source.checkout = PathBuf::from(checkout);
+    }
+
+    source.sync()?;
+    let commit = source.head()?;
+    let doc = load_checkout_document(&source, path)?;
+    log(
+        LogLevel::Info,
+        &format!("Serving {} from {} ({})", path, url, short(&commit)),
+    );
+
+    let host = matches
+        .get_one::<String>("host")
+        .map(|s| s.as_str())
+        .or_else(|| doc.get_section("App").and_then(|s| s.kv.get("host")).and_then(|v| v.as_str()))
+        .unwrap_or("127.0.0.1")
+        .to_string();
+    let port = matches.get_one::<u16>("port").copied().unwrap_or_else(|| {
+        doc.get_section("App")
+            .and_then(|s| s.kv.get("port"))
+            .and_then(|v| v.as_u64())
+            .and_then(|v| u16::try_from(v).ok())
+            .unwrap_or(3000)
+    });
+    let rune_dir = source
+        .checkout
+        .join(path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| source.checkout.clone());
+
+    let doc = Arc::new(doc);
+    let state = AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc: doc.clone(),
+        path: rune_dir,
+    };
+    let (app, switch) = build_switchable_router(state).await;
+
+    let address = format!("{}:{}", host, port);
+    let listener = TcpListener::bind(&address).await?;
+    log(
+        LogLevel::Info,
+        &format!(
+            "{}\n\nPolling for new commits every {}s. Press Ctrl+C to stop the server.",
+            render_route_summary(&doc, Some(&address)),
+            poll.as_secs_f64()
+        ),
+    );
+
+    let path = path.to_string();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(poll);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            // git runs synchronously, so keep it off the request-serving threads.
+            let result = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(deploy_if_changed(&source, &path, &switch))
+            });
+            match result {
+                Ok(Some(commit)) => log(LogLevel::Info, &format!("Deployed {}", short(&commit))),
+                Ok(None) => {}
+                Err(e) => log(
+                    LogLevel::Error,
+                    &format!("{:#}; keeping the current version", e),
+                ),
+            }
+        }
+    });
+
+    serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! `vectrune merge3 base mine theirs`: a structural three-way merge for documents kept
+//! in git.
+//!
+//! Sections are matched by path, then keys, series and records are merged the way git
+//! merges lines: a side that left something as it was in `base` takes the other side's
+//! version. Records are merged field by field when all three sides have the same number
+//! of them. What both sides changed differently is written between `<<<<<<< mine`,
+//! `=======` and `>>>>>>> theirs` markers.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+
+use crate::cli::diff::section_json;
+use crate::cli::merge::load_document;
+use crate::rune_ast::{Record, RuneDocument, Section, Value};
+use crate::util::{log, LogLevel};
+
+pub fn handle_merge3(matches: &ArgMatches) -> Result<()> {
+    let load = |id: &str| -> Result<RuneDocument> {
+        let path = matches.get_one::<String>(id).with_context(|| format!("Missing {} document", id))?;
+        load_document(path).map_err(anyhow::Error::msg)
+    };
+    let merged = merge3(&load("BASE")?, &load("MINE")?, &load("THEIRS")?);
+    print!("{}", merged.text);
+    if merged.conflicts > 0 {
+        log(LogLevel::Warn, &format!("{} conflict(s) left in the merged document", merged.conflicts));
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// A merged document as Rune text, and how many conflicts it holds.
+pub struct Merged {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+enum Outcome<T> {
+    Take(Option<T>),
+    Conflict(Option<T>, Option<T>),
+}
+
+/// The three-way rule: agreeing sides win, and a side that kept `base` takes the other.
+fn three_way<T: Clone>(
+    base: Option<&T>,
+    mine: Option<&T>,
+    theirs: Option<&T>,
+    same: impl Fn(Option<&T>, Option<&T>) -> bool,
+) -> Outcome<T> {
+    if same(mine, theirs) || same(base, theirs) {
+        Outcome::Take(mine.cloned())
+    } else if same(base, mine) {
+        Outcome::Take(theirs.cloned())
+    } else {
+        Outcome::Conflict(mine.cloned(), theirs.cloned())
+    }
+}
+
+fn same_json<T>(to_json: impl Fn(&T) -> serde_json::Value) -> impl Fn(Option<&T>, Option<&T>) -> bool {
+    move |a, b| a.map(&to_json) == b.map(&to_json)
+}
+
+pub fn merge3(base: &RuneDocument, mine: &RuneDocument, theirs: &RuneDocument) -> Merged {
+    let find = |doc: &RuneDocument, path: &[String]| doc.sections.iter().find(|s| s.path == path).cloned();
+    let mut paths: Vec<Vec<String>> = Vec::new();
+    for section in mine.sections.iter().chain(&theirs.sections) {
+        if !paths.contains(&section.path) {
+            paths.push(section.path.clone());
+        }
+    }
+
+    let mut merged = Merged { text: String::from("#!RUNE\n"), conflicts: 0 };
+    for path in paths {
+        let (b, m, t) = (find(base, &path), find(mine, &path), find(theirs, &path));
+        match three_way(b.as_ref(), m.as_ref(), t.as_ref(), same_json(section_json)) {
+            Outcome::Take(Some(section)) => merged.text.push_str(&section_text(&section)),
+            Outcome::Take(None) => {}
+            Outcome::Conflict(Some(m), Some(t)) => {
+                let b = b.unwrap_or_else(|| Section { path: path.clone(), ..empty_section() });
+                merge_section(&b, &m, &t, &mut merged);
+            }
+            Outcome::Conflict(m, t) => {
+                let text = |s: Option<Section>| s.map(|s| section_text(&s)).unwrap_or_default();
+                push_conflict(&mut merged, &text(m), &text(t));
+            }
+        }
+    }
+    merged
+}
+
+fn empty_section() -> Section {
+    Section { path: Vec::new(), kv: HashMap::new(), series: HashMap::new(), records: Vec::new(), source_file: None }
+}
+
+/// A section written the way `-o rune` writes it, followed by a blank line.
+fn section_text(section: &Section) -> String {
+    let text = RuneDocument { sections: vec![section.clone()] }.to_string();
+    text.strip_prefix("#!RUNE\n").unwrap_or(&text).to_string()
+}
+
+/// The body lines `-o rune` writes for a section holding only what `fill` adds.
+fn body(fill: impl FnOnce(&mut Section)) -> String {
+    let mut section = Section { path: vec!["_".to_string()], ..empty_section() };
+    fill(&mut section);
+    let text = section_text(&section);
+    let body = text.strip_prefix("@_\n").unwrap_or(&text);
+    body.strip_suffix('\n').unwrap_or(body).to_string()
+}
+
+fn push_conflict(merged: &mut Merged, mine: &str, theirs: &str) {
+    merged.conflicts += 1;
+    merged.text.push_str(&format!("<<<<<<< mine\n{}=======\n{}>>>>>>> theirs\n", mine, theirs));
+}
+
+fn merge_section(base: &Section, mine: &Section, theirs: &Section, merged: &mut Merged) {
+    merged.text.push_str(&format!("@{}\n", mine.path.join("/")));
+
+    let mut keys: Vec<&String> = mine.kv.keys().chain(theirs.kv.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let line = |v: Option<Value>| v.map(|v| body(|s| drop(s.kv.insert(key.clone(), v)))).unwrap_or_default();
+        match three_way(base.kv.get(key), mine.kv.get(key), theirs.kv.get(key), same_json(Value::to_json)) {
+            Outcome::Take(value) => merged.text.push_str(&line(value)),
+            Outcome::Conflict(m, t) => push_conflict(merged, &line(m), &line(t)),
+        }
+    }
+
+    let mut keys: Vec<&String> = mine.series.keys().chain(theirs.series.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let list_json = |items: &Vec<Value>| serde_json::Value::Array(items.iter().map(Value::to_json).collect());
+    for key in keys {
+        let lines =
+            |v: Option<Vec<Value>>| v.map(|v| body(|s| drop(s.series.insert(key.clone(), v)))).unwrap_or_default();
+        match three_way(base.series.get(key), mine.series.get(key), theirs.series.get(key), same_json(list_json)) {
+            Outcome::Take(items) => merged.text.push_str(&lines(items)),
+            Outcome::Conflict(m, t) => push_conflict(merged, &lines(m), &lines(t)),
+        }
+    }
+
+    let records_json = |records: &Vec<Record>| {
+        serde_json::Value::Array(records.iter().map(|r| Value::Map(r.kv.clone()).to_json()).collect())
+    };
+    let records_text = |records: Vec<Record>| body(|s| s.records = records);
+    match three_way(Some(&base.records), Some(&mine.records), Some(&theirs.records), same_json(records_json)) {
+        Outcome::Take(records) => merged.text.push_str(&records_text(records.unwrap_or_default())),
+        Outcome::Conflict(m, t) => {
+            let (m, t) = (m.unwrap_or_default(), t.unwrap_or_default());
+            if base.records.len() == m.len() && m.len() == t.len() {
+                for ((b, m), t) in base.records.iter().zip(m).zip(t) {
+                    merge_record(b, m, t, merged);
+                }
+            } else {
+                push_conflict(merged, &records_text(m), &records_text(t));
+            }
+        }
+    }
+    merged.text.push('\n');
+}
+
+/// Merges one record field by field. A record with a conflicting field is written once
+/// per side, each with the fields both sides agree on.
+fn merge_record(base: &Record, mine: Record, theirs: Record, merged: &mut Merged) {
+    let mut mine_fields = HashMap::new();
+    let mut theirs_fields = HashMap::new();
+    let mut conflicted = false;
+    let mut keys: Vec<&String> = mine.kv.keys().chain(theirs.kv.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        match three_way(base.kv.get(key), mine.kv.get(key), theirs.kv.get(key), same_json(Value::to_json)) {
+            Outcome::Take(value) => {
+                if let Some(value) = value {
+                    mine_fields.insert(key.clone(), value.clone());
+                    theirs_fields.insert(key.clone(), value);
+                }
+            }
+            Outcome::Conflict(m, t) => {
+                conflicted = true;
+                mine_fields.extend(m.map(|v| (key.clone(), v)));
+                theirs_fields.extend(t.map(|v| (key.clone(), v)));
+            }
+        }
+    }
+    let text = |kv| body(|s| s.records.push(Record { kv }));
+    if conflicted {
+        push_conflict(merged, &text(mine_fields), &text(theirs_fields));
+    } else {
+        merged.text.push_str(&text(mine_fields));
+    }
+}
@@ -0,0 +1,59 @@
+//! `vectrune migrate app.rune [status|up|down] [--steps N] [--dry-run]`: inspects and
+//! runs the document's `@Migration` sections.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+
+use crate::core::migrations::{migrate, status, Direction, PlannedMigration};
+use crate::rune_parser::load_rune_document_from_source;
+
+fn print_plan(planned: &[PlannedMigration], dry_run: bool) {
+    for migration in planned {
+        let verb = match (migration.direction, dry_run) {
+            (Direction::Up, false) => "applied",
+            (Direction::Down, false) => "reverted",
+            (Direction::Up, true) => "would apply",
+            (Direction::Down, true) => "would revert",
+        };
+        println!("{} {} ({})", verb, migration.version, migration.data_source);
+        if dry_run {
+            for statement in &migration.statements {
+                println!("    {};", statement);
+            }
+        }
+    }
+}
+
+pub async fn handle_migrate(matches: &ArgMatches) -> Result<()> {
+    let script = matches.get_one::<String>("SCRIPT").context("Missing rune document")?;
+    let doc = load_rune_document_from_source(script).map_err(|e| anyhow::anyhow!(e))?;
+    let action = matches.get_one::<String>("ACTION").map(String::as_str).unwrap_or("status");
+    let steps = matches.get_one::<usize>("steps").copied();
+    let dry_run = matches.get_flag("dry-run");
+
+    let direction = match action {
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        _ => {
+            let migrations = status(&doc).await.map_err(|e| anyhow::anyhow!(e))?;
+            if migrations.is_empty() {
+                println!("No @Migration sections");
+            }
+            for (migration, applied) in migrations {
+                let state = if applied { "applied" } else { "pending" };
+                println!("{:<8} {} ({})", state, migration.version, migration.data_source);
+            }
+            return Ok(());
+        }
+    };
+    let planned = migrate(&doc, direction, steps, dry_run).await.map_err(|e| anyhow::anyhow!(e))?;
+    if planned.is_empty() {
+        if direction == Direction::Up {
+            println!("Nothing to migrate");
+        } else {
+            bail!("No applied migrations to revert");
+        }
+    }
+    print_plan(&planned, dry_run);
+    Ok(())
+}
@@ -1,23 +1,54 @@
 mod ai;
 pub mod calculate;
+pub mod check;
+pub mod convert;
+pub mod ctl;
+pub mod diff;
+pub mod fmt;
+pub mod git_deploy;
 pub mod knowledge;
 pub mod lambda;
 pub mod merge;
+pub mod merge3;
+pub mod migrate;
+pub mod new;
 pub mod transform;
 pub mod repl;
+pub mod run;
+pub mod select;
+pub mod selector;
+pub mod template;
+pub mod test_runner;
+pub mod trace;
+pub mod upgrade;
 pub mod vect;
 pub mod vectrune;
 pub mod watch;
 #[allow(dead_code)]
 pub mod hooks_commands;
 
-pub use ai::handle_ai;
+pub use ai::{handle_ai, handle_ai_explain, handle_ai_new};
 pub use calculate::handle_calculate;
+pub use check::handle_check;
+pub use convert::handle_convert;
+pub use ctl::handle_ctl;
+pub use diff::handle_diff;
+pub use fmt::handle_fmt;
+pub use git_deploy::handle_git_serve;
 pub use knowledge::handle_knowledge;
 pub use lambda::handle_lambda;
-pub use merge::handle_merge;
+pub use merge::handle_merge_with;
+pub use merge3::handle_merge3;
+pub use migrate::handle_migrate;
+pub use new::handle_new;
 pub use transform::handle_transform;
 pub use repl::handle_repl;
+pub use run::handle_run;
+pub use select::handle_select;
+pub use template::handle_render;
+pub use test_runner::handle_test;
+pub use trace::handle_trace;
+pub use upgrade::handle_upgrade;
 pub use vect::handle_vect_file;
 pub use vectrune::handle_vectrune_file;
 pub use watch::start_file_watcher;
@@ -0,0 +1,223 @@
+//! `vectrune new shop --template rest-crud --name shop --port 3000 --entities book,author`:
+//! scaffolds a starter project.
+//!
+//! Templates:
+//! - `rest-crud`: a REST app with a schema, a jsonfile datasource and a CRUD route per entity
+//! - `graphql`: a GraphQL app with list, lookup and add fields per entity over memory records
+//! - `gateway`: a REST app proxying a path per entity to an upstream, plus a health route
+//! - `static-site`: a REST app serving `public/` with a starter `index.html`
+//!
+//! The template, app name, port and entities not given as flags are asked for on stdin,
+//! with defaults taken when the answer is blank or input ends.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::rune_parser::parse_rune;
+
+pub const TEMPLATES: &[&str] = &["rest-crud", "graphql", "gateway", "static-site"];
+
+/// An entity as its schema name (`Book`) and its collection name (`books`).
+#[derive(Debug, Clone, PartialEq)]
+struct Entity {
+    schema: String,
+    plural: String,
+}
+
+impl Entity {
+    fn new(raw: &str) -> Option<Entity> {
+        let word: String = raw.trim().chars().filter(|c| c.is_alphanumeric() || *c == '_').collect();
+        let mut chars = word.chars();
+        let first = chars.next().filter(|c| c.is_alphabetic())?;
+        let schema = first.to_uppercase().chain(chars).collect::<String>();
+        let lower = word.to_lowercase();
+        let plural = if lower.ends_with('y') && !lower.ends_with("ey") && !lower.ends_with("ay") && !lower.ends_with("oy") {
+            format!("{}ies", &lower[..lower.len() - 1])
+        } else if ["s", "x", "z", "ch", "sh"].iter().any(|end| lower.ends_with(end)) {
+            format!("{}es", lower)
+        } else {
+            format!("{}s", lower)
+        };
+        Some(Entity { schema, plural })
+    }
+}
+
+fn parse_entities(list: &str) -> Result<Vec<Entity>> {
+    let entities: Vec<Entity> = list
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| Entity::new(s).with_context(|| format!("'{}' is not a valid entity name", s)))
+        .collect::<Result<_>>()?;
+    if entities.is_empty() {
+        bail!("at least one entity is needed");
+    }
+    Ok(entities)
+}
+
+/// Asks `question` on stdout and returns the answer, or `default` for a blank one.
+fn ask(lines: &mut impl Iterator<Item = io::Result<String>>, question: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", question, default);
+    io::stdout().flush()?;
+    let answer = lines.next().transpose()?.unwrap_or_default();
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+}
+
+fn rest_crud(name: &str, port: u16, entities: &[Entity]) -> String {
+    let mut out = format!("#!RUNE\n\n@App\nname = {}\ntype = REST\nport = {}\n", name, port);
+    for entity in entities {
+        out.push_str(&format!(
+            "\n@Schema/{schema}\nname = string\n\n\
+             @DataSource/{schema}Store\ntype = jsonfile\nconnection = data/{plural}.json\n\n\
+             @Route/CRUD /{plural}\nschema = {schema}\ndata_source = {schema}Store\n",
+            schema = entity.schema,
+            plural = entity.plural,
+        ));
+    }
+    out
+}
+
+fn graphql(name: &str, port: u16, entities: &[Entity]) -> String {
+    let mut out = format!("#!RUNE\n\n@App\nname = {}\ntype = GraphQL\nport = {}\n", name, port);
+    for entity in entities {
+        let single = entity.schema.to_lowercase();
+        out.push_str(&format!(
+            "\n@Schema/{schema}\nid = number\nname = string\n\n\
+             @Memory/{plural}\n+ id = 1\n  name = \"First {single}\"\n\n\
+             @GraphQL/Query\n\
+             {plural}:\n    {plural} = memory.get \"{plural}\"\n    return {plural}\n\
+             {single}(id: number):\n    {plural} = memory.get \"{plural}\"\n    found = {plural}.find it.id == id\n    return found\n\n\
+             @GraphQL/Mutation/{schema}\n\
+             add{schema}(name: string):\n    {plural} = memory.get \"{plural}\"\n    new_id = {plural}.max it.id + 1\n    \
+             created = {{ id: new_id, name: name }}\n    memory.append \"{plural}\" created\n    return created\n",
+            schema = entity.schema,
+            plural = entity.plural,
+            single = single,
+        ));
+    }
+    out
+}
+
+fn gateway(name: &str, port: u16, entities: &[Entity]) -> String {
+    let mut out = format!(
+        "#!RUNE\n\n@App\nname = {}\ntype = REST\nport = {}\n\n@Route/GET /health\nrun:\n    respond 200 \"ok\"\n",
+        name, port
+    );
+    for (i, entity) in entities.iter().enumerate() {
+        out.push_str(&format!(
+            "\n# Forwards /{plural} and everything below it\n@Route/PROXY /{plural}/*\nupstream = http://127.0.0.1:{upstream}\n",
+            plural = entity.plural,
+            upstream = port as u32 + 1 + i as u32,
+        ));
+    }
+    out
+}
+
+fn static_site(name: &str, port: u16) -> String {
+    format!(
+        "#!RUNE\n\n@App\nname = {}\ntype = REST\nport = {}\n\n@Frontend\ntype = static\npath = /\nsrc = public\n",
+        name, port
+    )
+}
+
+fn index_html(name: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"utf-8\">\n  <title>{0}</title>\n</head>\n<body>\n  <h1>{0}</h1>\n  <p>Edit public/index.html to get started.</p>\n</body>\n</html>\n",
+        name
+    )
+}
+
+/// The files of a project, as paths relative to its directory and their contents.
+fn scaffold(template: &str, name: &str, port: u16, entities: &[Entity]) -> Result<Vec<(String, String)>> {
+    let app = match template {
+        "rest-crud" => rest_crud(name, port, entities),
+        "graphql" => graphql(name, port, entities),
+        "gateway" => gateway(name, port, entities),
+        "static-site" => static_site(name, port),
+        other => bail!("unknown template '{}' (available: {})", other, TEMPLATES.join(", ")),
+    };
+    parse_rune(&app).map_err(|e| anyhow::anyhow!("generated document does not parse: {}", e))?;
+    let mut files = vec![("app.rune".to_string(), app)];
+    match template {
+        "static-site" => files.push(("public/index.html".to_string(), index_html(name))),
+        "rest-crud" => files.push(("data/.gitkeep".to_string(), String::new())),
+        _ => {}
+    }
+    Ok(files)
+}
+
+pub fn handle_new(matches: &ArgMatches) -> Result<()> {
+    let dir = Path::new(matches.get_one::<String>("DIR").context("Missing project directory")?);
+    if dir.join("app.rune").exists() {
+        bail!("{} already exists", dir.join("app.rune").display());
+    }
+    let default_name = dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .filter(|n| !n.is_empty() && *n != ".")
+        .unwrap_or("my-app")
+        .to_string();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let template = match matches.get_one::<String>("template") {
+        Some(t) => t.clone(),
+        None => ask(&mut lines, &format!("Template ({})", TEMPLATES.join(", ")), TEMPLATES[0])?,
+    };
+    if !TEMPLATES.contains(&template.as_str()) {
+        bail!("unknown template '{}' (available: {})", template, TEMPLATES.join(", "));
+    }
+    let name = match matches.get_one::<String>("name") {
+        Some(n) => n.clone(),
+        None => ask(&mut lines, "App name", &default_name)?,
+    };
+    let port = match matches.get_one::<u16>("port") {
+        Some(p) => *p,
+        None => {
+            let answer = ask(&mut lines, "Port", "3000")?;
+            answer.parse().with_context(|| format!("'{}' is not a port", answer))?
+        }
+    };
+    let entities = match (template.as_str(), matches.get_one::<String>("entities")) {
+        ("static-site", _) => Vec::new(),
+        (_, Some(list)) => parse_entities(list)?,
+        (_, None) => parse_entities(&ask(&mut lines, "Entities (comma separated)", "item")?)?,
+    };
+
+    for (file, contents) in scaffold(&template, &name, port, &entities)? {
+        let path = dir.join(&file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Created {}", path.display());
+    }
+    println!("\nStart it with:\n  vectrune {}", dir.join("app.rune").display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_names_become_schemas_and_collections() {
+        let names: Vec<(String, String)> = parse_entities("book, category,box person")
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.schema, e.plural))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Book".to_string(), "books".to_string()),
+                ("Category".to_string(), "categories".to_string()),
+                ("Box".to_string(), "boxes".to_string()),
+                ("Person".to_string(), "persons".to_string()),
+            ]
+        );
+        assert!(parse_entities("9lives").is_err());
+    }
+}
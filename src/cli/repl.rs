@@ -1,33 +1,188 @@
-use std::io::{self, Write};
-use crate::rune_parser::parse_rune_line; // Assume this exists or will be implemented
-use crate::core::{ExecutionContext};
+//! `vectrune repl [app.rune]`: an interactive prompt that runs steps against a document.
+//!
+//! Each line is run as a step with one context kept across lines, so variables set by one
+//! line are there for the next; a line ending in `:` (`if`, `match`) starts a block that
+//! ends at a blank line. The document's `@Memory`, `@Const` and `@DataSource` sections are
+//! available as they are to `vectrune run`.
+//!
+//! Meta commands: `:ctx [name]` prints the context (or one value), `:doc` prints the
+//! document, `:reload` reads the document again keeping the context, `:clear` empties the
+//! context, `:help` lists the commands and `:quit` (or `exit`) leaves.
+
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+use super::run::{parse_steps, script_dir};
+use crate::builtins::{Context, LAST_EXEC_RESULT};
+use crate::core::{
+    constants, execute_steps_inner_no_fallthrough, extract_data_sources, extract_steps, extract_schemas,
+    initialize_memory_from_doc, tokenizer, AppState,
+};
+use crate::rune_ast::RuneDocument;
+use crate::rune_parser::load_rune_document_from_source;
 use crate::util::{log, LogLevel};
 
-pub async fn handle_repl() -> anyhow::Result<()> {
-    log(LogLevel::Info, "Vectrune REPL started. Type 'exit' or 'quit' to leave.");
-    let mut ctx = ExecutionContext::default(); // Or whatever context is needed
+const HELP: &str = "\
+Enter steps as in a run: series. A line ending in ':' starts a block; finish it with a blank line.
+  :ctx [name]  print the context, or one value
+  :doc         print the document
+  :reload      read the document again, keeping the context
+  :clear       empty the context
+  :help        show this help
+  :quit        leave (or exit, quit, Ctrl+D)";
+
+/// Reads the document, or an empty one without a script, and makes its memory available.
+async fn load_state(script: Option<&str>) -> Result<AppState> {
+    let (doc, path) = match script {
+        Some(script) => {
+            let doc = load_rune_document_from_source(script).map_err(|e| anyhow::anyhow!(e))?;
+            let path = if script == "-" {
+                std::env::current_dir()?
+            } else {
+                script_dir(script)
+            };
+            (doc, path)
+        }
+        None => (
+            RuneDocument {
+                sections: Vec::new(),
+            },
+            std::env::current_dir()?,
+        ),
+    };
+    initialize_memory_from_doc(&doc, &path).await;
+    Ok(AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc: Arc::new(doc),
+        path,
+    })
+}
+
+/// The context without the runtime's internal `___` entries.
+fn visible(ctx: &Context) -> serde_json::Map<String, serde_json::Value> {
+    let mut entries: Vec<_> = ctx.iter().filter(|(k, _)| !k.starts_with("___")).collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_else(|_| other.to_string()),
+    }
+}
+
+/// Runs the entered steps and prints what they produced: the response of a `respond` or
+/// `return`, the value assigned by a single assignment, or the last builtin's result.
+async fn run(state: &AppState, ctx: &mut Context, text: &str) {
+    let steps = match parse_steps(text) {
+        Ok(steps) => steps,
+        Err(e) => {
+            log(LogLevel::Error, &e.to_string());
+            return;
+        }
+    };
+    ctx.remove(LAST_EXEC_RESULT);
+    if let Some((code, body)) = execute_steps_inner_no_fallthrough(state.clone(), &steps, ctx).await
+    {
+        let body = serde_json::from_str(&body)
+            .map(|v| pretty(&v))
+            .unwrap_or(body);
+        println!("{} {}", code, body);
+        return;
+    }
+    let trimmed = text.trim();
+    let assigned = (!trimmed.contains('\n'))
+        .then(|| tokenizer::find_assignment_equals(trimmed))
+        .flatten()
+        .map(|eq| trimmed[..eq].trim());
+    let shown = match assigned {
+        Some(var) => crate::builtins::builtin::respond::lookup_value(ctx, var),
+        None => ctx.get(LAST_EXEC_RESULT).cloned(),
+    };
+    if let Some(value) = shown.filter(|v| !v.is_null()) {
+        println!("{}", pretty(&value));
+    }
+}
+
+pub async fn handle_repl(script: Option<&str>) -> Result<()> {
+    let mut state = load_state(script).await?;
+    let mut ctx = Context::new();
+    constants::seed_context(&state.doc, &mut ctx);
+    log(
+        LogLevel::Info,
+        "Vectrune REPL started. Type :help for commands, :quit to leave.",
+    );
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
     loop {
         print!("vectrune> ");
         io::stdout().flush()?;
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let line = input.trim();
-        if line.eq_ignore_ascii_case("exit") || line.eq_ignore_ascii_case("quit") {
-            log(LogLevel::Info, "Exiting Vectrune REPL.");
+        let Some(line) = lines.next().transpose()? else {
             break;
-        }
-        if line.is_empty() {
-            continue;
-        }
-        match parse_rune_line(line) {
-            Ok(parsed) => {
-                match crate::core::execute_line(&mut ctx, &parsed).await {
-                    Ok(result) => println!("{:?}", result),
-                    Err(e) => log(LogLevel::Error, &format!("Execution error: {}", e)),
+        };
+        let trimmed = line.trim();
+        match trimmed
+            .split_once(' ')
+            .map_or((trimmed, ""), |(cmd, rest)| (cmd, rest.trim()))
+        {
+            ("", _) => continue,
+            (":quit" | ":q" | "exit" | "quit", _) => break,
+            (":help", _) => println!("{}", HELP),
+            (":ctx", "") => println!("{}", pretty(&serde_json::Value::Object(visible(&ctx)))),
+            (":ctx", name) => match crate::builtins::builtin::respond::lookup_value(&ctx, name) {
+                Some(value) => println!("{}", pretty(&value)),
+                None => println!("{} is not set", name),
+            },
+            (":doc", _) => print!("{}", state.doc),
+            (":reload", _) => match load_state(script).await {
+                Ok(reloaded) => {
+                    state = reloaded;
+                    constants::seed_context(&state.doc, &mut ctx);
+                    println!("Reloaded {}", script.unwrap_or("(no document)"));
                 }
+                Err(e) => log(
+                    LogLevel::Error,
+                    &format!("Reload failed, keeping the old document: {}", e),
+                ),
             },
-            Err(e) => log(LogLevel::Error, &format!("Parse error: {}", e)),
+            (":clear", _) => {
+                ctx = Context::new();
+                constants::seed_context(&state.doc, &mut ctx);
+            }
+            (cmd, _) if cmd.starts_with(':') => println!("Unknown command {}; try :help", cmd),
+            _ => {
+                let mut text = line.clone();
+                if trimmed.ends_with(':') {
+                    loop {
+                        print!("      ... ");
+                        io::stdout().flush()?;
+                        match lines.next().transpose()? {
+                            Some(more) if !more.trim().is_empty() => {
+                                text.push('\n');
+                                text.push_str(&more);
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+                run(&state, &mut ctx, &text).await;
+            }
         }
     }
+    for datasource in crate::builtins::builtin::transaction::rollback_all(&mut ctx).await {
+        log(
+            LogLevel::Warn,
+            &format!("Rolled back uncommitted transaction on {}", datasource),
+        );
+    }
+    println!();
     Ok(())
 }
@@ -0,0 +1,115 @@
+//! `vectrune run app.rune --steps "users = csv.read 'u.csv'; return users"`: runs a step
+//! series against a document outside any HTTP request and prints the result.
+//!
+//! Steps are separated by `;` or newlines (indented lines form `if` and `match` blocks).
+//! Files resolve relative to the document, its `@Memory`, `@Const` and `@DataSource`
+//! sections are available, and an error response exits non-zero.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::core::{execute_request_steps, step, extract_data_sources, extract_steps, extract_schemas, initialize_memory_from_doc, AppState};
+use crate::rune_ast::Value;
+use crate::rune_parser::{load_rune_document_from_source, parse_rune};
+
+/// Splits `a; b "c;d"` on semicolons outside quotes.
+fn split_steps(text: &str) -> Vec<String> {
+    let mut steps = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match (c, quote) {
+            _ if escaped => escaped = false,
+            ('\\', Some(_)) => escaped = true,
+            ('"' | '\'', None) => quote = Some(c),
+            (q, Some(open)) if q == open => quote = None,
+            (';', None) => {
+                steps.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    steps.push(current);
+    steps
+}
+
+/// Parses the step text as the body of a `run:` series.
+pub fn parse_steps(text: &str) -> Result<Vec<Value>> {
+    let mut source = String::from("@Run\nrun:\n");
+    for step in split_steps(text) {
+        let lines: Vec<&str> = step.lines().filter(|l| !l.trim().is_empty()).collect();
+        let indent = lines.iter().map(|l| l.len() - l.trim_start().len()).min().unwrap_or(0);
+        for line in lines {
+            source.push_str("    ");
+            source.push_str(line[indent..].trim_end());
+            source.push('\n');
+        }
+    }
+    let doc = parse_rune(&source).map_err(|e| anyhow::anyhow!("invalid steps: {}", e))?;
+    let steps = doc
+        .sections
+        .first()
+        .and_then(|s| s.series.get("run"))
+        .cloned()
+        .unwrap_or_default();
+    if steps.is_empty() {
+        bail!("--steps is empty");
+    }
+    Ok(steps)
+}
+
+pub(crate) fn script_dir(script: &str) -> PathBuf {
+    let path = Path::new(script);
+    if script == "-" || path.is_dir() {
+        return path.to_path_buf();
+    }
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+}
+
+pub async fn handle_run(matches: &ArgMatches) -> Result<()> {
+    let script = matches.get_one::<String>("SCRIPT").context("Missing rune document")?;
+    let steps = parse_steps(matches.get_one::<String>("steps").context("Missing --steps")?)?;
+    let doc = load_rune_document_from_source(script).map_err(|e| anyhow::anyhow!(e))?;
+    let path = if script == "-" { std::env::current_dir()? } else { script_dir(script) };
+    initialize_memory_from_doc(&doc, &path).await;
+
+    let state = AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc: Arc::new(doc),
+        path,
+    };
+    let response = execute_request_steps(state, &step::compile(&steps), None, None, None, None, None).await;
+    if response.status.is_client_error() || response.status.is_server_error() {
+        bail!("{} {}", response.status.as_u16(), response.body);
+    }
+    match serde_json::from_str::<serde_json::Value>(&response.body) {
+        Ok(value @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => {
+            println!("{}", serde_json::to_string_pretty(&value)?)
+        }
+        _ => println!("{}", response.body),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_steps_outside_quotes() {
+        assert_eq!(
+            split_steps(r#"a = csv.read "x;y.csv"; return a"#),
+            vec![r#"a = csv.read "x;y.csv""#.to_string(), " return a".to_string()]
+        );
+    }
+}
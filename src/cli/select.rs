@@ -0,0 +1,63 @@
+//! `--select` queries: a path expression evaluated against a document, the jq of Rune.
+//!
+//! ```text
+//! App.port
+//! Skateboarder[age>30].name
+//! ..url
+//! ```
+//!
+//! See [`crate::cli::selector`] for the expression syntax. An expression made only of
+//! keys names one value; any other expression lists every value it selects.
+
+use crate::cli::selector::{document_value, Selector};
+use crate::rune_ast::RuneDocument;
+
+pub fn handle_select(doc: &RuneDocument, expr: &str, output_format: Option<&str>) -> Result<(), String> {
+    let out = select_to_string(doc, expr, output_format)?;
+    if !out.is_empty() {
+        println!("{}", out);
+    }
+    Ok(())
+}
+
+/// The result as JSON: the value of a key path (`null` when missing), or a list.
+pub fn select_to_json(doc: &RuneDocument, expr: &str) -> Result<serde_json::Value, String> {
+    let selector = Selector::parse(expr)?;
+    let root = document_value(doc);
+    let found: Vec<serde_json::Value> = selector.select(&root).into_iter().map(|v| v.to_json()).collect();
+    if selector.plain_keys().is_some() {
+        return Ok(found.into_iter().next().unwrap_or(serde_json::Value::Null));
+    }
+    Ok(serde_json::Value::Array(found))
+}
+
+/// The result in `output_format`: `json`, `yaml`, or by default text with one value
+/// per line, scalars plain and anything else as compact JSON.
+pub fn select_to_string(doc: &RuneDocument, expr: &str, output_format: Option<&str>) -> Result<String, String> {
+    let result = select_to_json(doc, expr)?;
+    match output_format {
+        Some("json") => serde_json::to_string_pretty(&result).map_err(|e| e.to_string()),
+        Some("yaml") => serde_yaml::to_string(&result)
+            .map(|yaml| yaml.trim_end().to_string())
+            .map_err(|e| e.to_string()),
+        _ => {
+            let values = match result {
+                serde_json::Value::Array(items) if Selector::parse(expr)?.plain_keys().is_none() => items,
+                serde_json::Value::Null => Vec::new(),
+                other => vec![other],
+            };
+            Ok(values.iter().map(text_line).collect::<Vec<_>>().join("\n"))
+        }
+    }
+}
+
+fn text_line(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Number(n) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => (f as i64).to_string(),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,567 @@
+//! Path expressions over documents, shared by `--merge-with`, `--select` and transforms.
+//!
+//! A document is read in its JSON shape: sections are nested maps and a section's
+//! records sit in its `record` list. An expression is a chain of steps:
+//!
+//! - `name`, `.name` or `['name with spaces']` — a key; `['a','b']` several keys
+//! - `*` or `[*]` — every value of a map or list
+//! - `..name` — `name` at any depth
+//! - `[0]`, `[-1]` — a list item; `[1:3]`, `[:2]`, `[-2:]` — a slice
+//! - `[?name=='x']` or `[age>30 && style=="Street"]` — the list items matching a condition
+//!
+//! A leading `$` names the document itself. Wildcard, index, slice and filter steps on a
+//! section with records apply to its records, so `Skateboarder[age>30].name` works.
+
+use std::collections::HashMap;
+
+use crate::rune_ast::{json_to_ast_value, RuneDocument, Value};
+
+/// One key or list position in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathKey {
+    Key(String),
+    Index(usize),
+}
+
+enum Step {
+    Key(String),
+    Keys(Vec<String>),
+    Wildcard,
+    Descend(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Filter(Condition),
+}
+
+/// A parsed path expression.
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+/// The document as a single value, in its JSON shape. Top-level data from JSON or
+/// YAML input (a section without a path) sits directly in the root map.
+pub fn document_value(doc: &RuneDocument) -> Value {
+    let mut root = json_to_ast_value(&doc.to_json());
+    if let Value::Map(map) = &mut root {
+        for section in doc.sections.iter().filter(|s| s.path.is_empty()) {
+            map.extend(section.kv.iter().map(|(k, v)| (k.clone(), v.clone())));
+            map.extend(section.series.iter().map(|(k, v)| (k.clone(), Value::List(v.clone()))));
+            if !section.records.is_empty() {
+                let records = section.records.iter().map(|r| Value::Map(r.kv.clone())).collect();
+                map.insert("record".to_string(), Value::List(records));
+            }
+        }
+    }
+    root
+}
+
+impl Selector {
+    pub fn parse(expr: &str) -> Result<Selector, String> {
+        let expr = expr.trim();
+        let mut rest = expr.strip_prefix('$').unwrap_or(expr);
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                let (name, next) = take_name(after);
+                if name.is_empty() {
+                    return Err(format!("Expected a name after '..' in '{}'", expr));
+                }
+                steps.push(Step::Descend(name.to_string()));
+                rest = next;
+            } else if rest.starts_with('[') {
+                let end = closing_bracket(rest).ok_or_else(|| format!("Unclosed '[' in '{}'", expr))?;
+                steps.push(parse_bracket(&rest[1..end])?);
+                rest = &rest[end + 1..];
+            } else {
+                let (name, next) = take_name(rest.strip_prefix('.').unwrap_or(rest));
+                match name {
+                    "" => return Err(format!("Empty step in '{}'", expr)),
+                    "*" => steps.push(Step::Wildcard),
+                    name => steps.push(Step::Key(name.to_string())),
+                }
+                rest = next;
+            }
+        }
+        Ok(Selector { steps })
+    }
+
+    /// Whether the expression ends in a plain key, which may not exist yet.
+    pub fn split_last_key(mut self) -> (Selector, Option<String>) {
+        match self.steps.pop() {
+            Some(Step::Key(key)) => (self, Some(key)),
+            Some(step) => {
+                self.steps.push(step);
+                (self, None)
+            }
+            None => (self, None),
+        }
+    }
+
+    /// The keys of an expression made only of plain keys, like `environment.prod`.
+    pub fn plain_keys(&self) -> Option<Vec<&str>> {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Step::Key(key) => Some(key.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The values the expression selects, in document order.
+    pub fn select<'v>(&self, root: &'v Value) -> Vec<&'v Value> {
+        self.locate(root).into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Where the selected values sit, for callers that update them.
+    pub fn select_paths(&self, root: &Value) -> Vec<Vec<PathKey>> {
+        self.locate(root).into_iter().map(|(path, _)| path).collect()
+    }
+
+    fn locate<'v>(&self, root: &'v Value) -> Vec<(Vec<PathKey>, &'v Value)> {
+        let mut current = vec![(Vec::new(), root)];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for (path, value) in current {
+                apply_step(step, path, value, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// The value at `path`, for updating a location found by [`Selector::select_paths`].
+pub fn get_mut<'v>(root: &'v mut Value, path: &[PathKey]) -> Option<&'v mut Value> {
+    path.iter().try_fold(root, |value, key| match (value, key) {
+        (Value::Map(map), PathKey::Key(k)) => map.get_mut(k),
+        (Value::List(items), PathKey::Index(i)) => items.get_mut(*i),
+        _ => None,
+    })
+}
+
+fn apply_step<'v>(step: &Step, path: Vec<PathKey>, value: &'v Value, out: &mut Vec<(Vec<PathKey>, &'v Value)>) {
+    let child = |key: PathKey| {
+        let mut path = path.clone();
+        path.push(key);
+        path
+    };
+    match step {
+        Step::Key(key) => {
+            if let Value::Map(map) = value {
+                if let Some(v) = map.get(key) {
+                    out.push((child(PathKey::Key(key.clone())), v));
+                }
+            }
+        }
+        Step::Keys(keys) => {
+            for key in keys {
+                apply_step(&Step::Key(key.clone()), path.clone(), value, out);
+            }
+        }
+        Step::Wildcard => match records(value) {
+            Some(records) => children(records, &child(PathKey::Key("record".to_string())), out),
+            None => children(value, &path, out),
+        },
+        Step::Descend(name) => {
+            let mut pending = vec![(path, value)];
+            while let Some((path, value)) = pending.pop() {
+                apply_step(&Step::Key(name.clone()), path.clone(), value, out);
+                let mut below = Vec::new();
+                children(value, &path, &mut below);
+                pending.extend(below.into_iter().rev());
+            }
+        }
+        Step::Index(_) | Step::Slice(..) | Step::Filter(_) => match (value, records(value)) {
+            (_, Some(records)) => {
+                let path = child(PathKey::Key("record".to_string()));
+                apply_step(step, path, records, out);
+            }
+            (Value::List(items), None) => {
+                let len = items.len() as i64;
+                let at = |i: i64| if i < 0 { len + i } else { i };
+                let range = match step {
+                    Step::Index(i) => at(*i)..at(*i) + 1,
+                    Step::Slice(from, to) => at(from.unwrap_or(0)).max(0)..at(to.unwrap_or(len)).min(len),
+                    _ => 0..len,
+                };
+                for i in range.filter(|i| (0..len).contains(i)).map(|i| i as usize) {
+                    let item = &items[i];
+                    if let Step::Filter(condition) = step {
+                        if !condition.matches(&|field| field_of(item, field)) {
+                            continue;
+                        }
+                    }
+                    out.push((child(PathKey::Index(i)), item));
+                }
+            }
+            _ => {}
+        },
+    }
+}
+
+/// The records of a section, which wildcard, index, slice and filter steps reach through.
+fn records(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Map(map) => map.get("record").filter(|r| matches!(r, Value::List(_))),
+        _ => None,
+    }
+}
+
+fn children<'v>(value: &'v Value, path: &[PathKey], out: &mut Vec<(Vec<PathKey>, &'v Value)>) {
+    let with = |key: PathKey| {
+        let mut path = path.to_vec();
+        path.push(key);
+        path
+    };
+    match value {
+        Value::Map(map) => {
+            for key in sorted_keys(map) {
+                out.push((with(PathKey::Key(key.clone())), &map[key]));
+            }
+        }
+        Value::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                out.push((with(PathKey::Index(i)), item));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sorted_keys(map: &HashMap<String, Value>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+/// A filter's field inside an item: `name`, `@.name` or a dotted `@.a.b`.
+fn field_of<'v>(item: &'v Value, field: &str) -> Option<&'v Value> {
+    let field = field.trim();
+    let field = field.strip_prefix("@.").or_else(|| field.strip_prefix('@')).unwrap_or(field);
+    if field.is_empty() {
+        return Some(item);
+    }
+    field.split('.').try_fold(item, |value, key| match value {
+        Value::Map(map) => map.get(key),
+        _ => None,
+    })
+}
+
+/// The name at the start of `s`, up to the next `.` or `[`.
+fn take_name(s: &str) -> (&str, &str) {
+    let end = s.find(['.', '[']).unwrap_or(s.len());
+    (s[..end].trim(), &s[end..])
+}
+
+/// The byte offset of the `]` closing the `[` that starts `s`.
+fn closing_bracket(s: &str) -> Option<usize> {
+    let (mut depth, mut quote) = (0i32, None);
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_bracket(inner: &str) -> Result<Step, String> {
+    let inner = inner.trim();
+    if inner == "*" {
+        return Ok(Step::Wildcard);
+    }
+    if let Some(condition) = inner.strip_prefix('?') {
+        return Ok(Step::Filter(parse_condition(condition.trim_start_matches('(').trim_end_matches(')'))?));
+    }
+    if let Ok(i) = inner.parse::<i64>() {
+        return Ok(Step::Index(i));
+    }
+    if let Some((from, to)) = inner.split_once(':') {
+        let bound = |s: &str| match s.trim() {
+            "" => Ok(None),
+            s => s.parse::<i64>().map(Some),
+        };
+        if let (Ok(from), Ok(to)) = (bound(from), bound(to)) {
+            return Ok(Step::Slice(from, to));
+        }
+    }
+    let quoted: Vec<&str> = split_top_level(inner, ",").into_iter().map(str::trim).collect();
+    if quoted.iter().all(|k| k.len() >= 2 && (k.starts_with('\'') && k.ends_with('\'') || k.starts_with('"') && k.ends_with('"'))) {
+        return Ok(Step::Keys(quoted.iter().map(|k| k[1..k.len() - 1].to_string()).collect()));
+    }
+    Ok(Step::Filter(parse_condition(inner)?))
+}
+
+/// The text of a scalar field value, as list items are written.
+pub(crate) fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(format!("{}", n)),
+        Value::Bool(b) => Some(format!("{}", b)),
+        _ => None,
+    }
+}
+
+/// A record filter: `field <op> literal` comparisons combined with `&&`, `||` and
+/// parentheses. Operators are `==`, `!=`, `<`, `>`, `<=`, `>=` and `contains`.
+pub(crate) enum Condition {
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Compare { field: String, op: &'static str, value: Literal },
+}
+
+pub(crate) type Lookup<'a> = dyn Fn(&str) -> Option<&'a Value> + 'a;
+
+pub(crate) fn passes<'a>(filters: &[Condition], lookup: &Lookup<'a>) -> bool {
+    filters.iter().all(|c| c.matches(lookup))
+}
+
+/// Splits `s` on `sep` outside quotes and parentheses. A single `|` never splits a `||`.
+pub(crate) fn split_top_level<'s>(s: &'s str, sep: &str) -> Vec<&'s str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0i32, None, 0usize);
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, _) if depth == 0 && s[i..].starts_with(sep) => {
+                if sep == "|" && (s[i + 1..].starts_with('|') || s[..i].ends_with('|')) {
+                    continue;
+                }
+                parts.push(&s[start..i]);
+                start = i + sep.len();
+                for _ in 1..sep.len() {
+                    chars.next();
+                }
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+pub(crate) fn parse_condition(s: &str) -> Result<Condition, String> {
+    let s = s.trim();
+    let any = split_top_level(s, "||");
+    if any.len() > 1 {
+        return Ok(Condition::Any(any.into_iter().map(parse_condition).collect::<Result<_, _>>()?));
+    }
+    let all = split_top_level(s, "&&");
+    if all.len() > 1 {
+        return Ok(Condition::All(all.into_iter().map(parse_condition).collect::<Result<_, _>>()?));
+    }
+    if let Some(inner) = s.strip_prefix('(').and_then(|i| i.strip_suffix(')')) {
+        if split_top_level(inner, ")").len() == 1 {
+            return parse_condition(inner);
+        }
+    }
+    let (at, op) = find_operator(s).ok_or_else(|| format!("Expected a comparison like age>30, got '{}'", s))?;
+    let field = s[..at].trim();
+    if field.is_empty() {
+        return Err(format!("Missing field in comparison '{}'", s));
+    }
+    Ok(Condition::Compare {
+        field: field.to_string(),
+        op,
+        value: parse_literal(&s[at + op.len()..])?,
+    })
+}
+
+/// The first comparison operator in `s` outside quotes, with its byte offset.
+fn find_operator(s: &str) -> Option<(usize, &'static str)> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, _) => {
+                let rest = &s[i..];
+                if rest.starts_with(" contains ") {
+                    return Some((i, " contains "));
+                }
+                if let Some(op) = ["==", "!=", "<=", ">=", "<", ">"].into_iter().find(|op| rest.starts_with(op)) {
+                    return Some((i, op));
+                }
+            }
+        }
+    }
+    None
+}
+
+impl Condition {
+    pub(crate) fn matches<'a>(&self, lookup: &Lookup<'a>) -> bool {
+        match self {
+            Condition::All(all) => all.iter().all(|c| c.matches(lookup)),
+            Condition::Any(any) => any.iter().any(|c| c.matches(lookup)),
+            Condition::Compare { field, op, value } => {
+                let Some(actual) = lookup(field) else {
+                    return *op == "!=";
+                };
+                match *op {
+                    "==" => literal_matches_value(value, actual),
+                    "!=" => !literal_matches_value(value, actual),
+                    " contains " => contains(actual, value),
+                    op => compare(actual, value).is_some_and(|ordering| match op {
+                        "<" => ordering.is_lt(),
+                        ">" => ordering.is_gt(),
+                        "<=" => ordering.is_le(),
+                        _ => ordering.is_ge(),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+fn literal_text(lit: &Literal) -> String {
+    match lit {
+        Literal::S(s) => s.clone(),
+        Literal::N(n) => format!("{}", n),
+        Literal::B(b) => format!("{}", b),
+    }
+}
+
+/// Orders a value against a literal: numerically when both are numbers, else as text.
+fn compare(actual: &Value, lit: &Literal) -> Option<std::cmp::Ordering> {
+    let text = scalar_text(actual)?;
+    let expected = literal_text(lit);
+    match (text.parse::<f64>(), expected.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(text.cmp(&expected)),
+    }
+}
+
+/// Case-insensitive substring test for text, membership for lists.
+fn contains(actual: &Value, lit: &Literal) -> bool {
+    match actual {
+        Value::List(items) => items.iter().any(|item| literal_matches_value(lit, item)),
+        other => scalar_text(other)
+            .is_some_and(|text| text.to_lowercase().contains(&literal_text(lit).to_lowercase())),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Literal {
+    S(String),
+    N(f64),
+    B(bool),
+}
+
+pub(crate) fn parse_literal(s: &str) -> Result<Literal, String> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("true") {
+        return Ok(Literal::B(true));
+    }
+    if s.eq_ignore_ascii_case("false") {
+        return Ok(Literal::B(false));
+    }
+    if let Ok(n) = s.parse::<f64>() {
+        return Ok(Literal::N(n));
+    }
+    // quoted string
+    if (s.starts_with('"') && s.ends_with('"') && s.len() >= 2)
+        || (s.starts_with('\'') && s.ends_with('\'') && s.len() >= 2)
+    {
+        let trimmed = &s[1..s.len() - 1];
+        return Ok(Literal::S(trimmed.to_string()));
+    }
+    // fallback: raw string
+    Ok(Literal::S(s.to_string()))
+}
+
+fn literal_matches_value(lit: &Literal, v: &Value) -> bool {
+    match (lit, v) {
+        (Literal::S(ls), Value::String(rs)) => ls == rs,
+        (Literal::N(ln), Value::Number(rn)) => (*ln - rn).abs() < f64::EPSILON,
+        (Literal::B(lb), Value::Bool(rb)) => lb == rb,
+        (Literal::S(ls), Value::Number(rn)) => ls
+            .parse::<f64>()
+            .map(|n| (n - rn).abs() < f64::EPSILON)
+            .unwrap_or(false),
+        (Literal::S(ls), Value::Bool(rb)) => {
+            if ls.eq_ignore_ascii_case("true") {
+                *rb
+            } else if ls.eq_ignore_ascii_case("false") {
+                !*rb
+            } else {
+                false
+            }
+        }
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skaters() -> Value {
+        document_value(&RuneDocument::from_json(&serde_json::json!({
+            "Skateboarder": {"record": [
+                {"name": "Tony Hawk", "age": 53, "style": "Vert"},
+                {"name": "Nyjah Huston", "age": 26, "style": "Street"},
+                {"name": "Leticia Bufoni", "age": 28, "style": "Street"}
+            ]},
+            "App": {"port": 8080, "tags": ["a", "b", "c"]}
+        })))
+    }
+
+    fn texts(expr: &str) -> Vec<String> {
+        let root = skaters();
+        Selector::parse(expr)
+            .unwrap()
+            .select(&root)
+            .into_iter()
+            .map(|v| scalar_text(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keys_indexes_and_slices() {
+        assert_eq!(texts("App.port"), vec!["8080"]);
+        assert_eq!(texts("$.App.tags[-1]"), vec!["c"]);
+        assert_eq!(texts("App.tags[1:]"), vec!["b", "c"]);
+        assert_eq!(texts("App['tags'][:2]"), vec!["a", "b"]);
+        assert_eq!(texts("Skateboarder[0].name"), vec!["Tony Hawk"]);
+        assert!(texts("App.tags[7]").is_empty());
+    }
+
+    #[test]
+    fn filters_wildcards_and_descent() {
+        assert_eq!(texts("Skateboarder[age>27].name"), vec!["Tony Hawk", "Leticia Bufoni"]);
+        assert_eq!(texts("Skateboarder.record[?@.style=='Street' && age<27].name"), vec!["Nyjah Huston"]);
+        assert_eq!(texts("Skateboarder[*].age").len(), 3);
+        assert_eq!(texts("..port"), vec!["8080"]);
+        assert!(Selector::parse("App.tags[0").is_err());
+    }
+
+    #[test]
+    fn paths_point_at_selected_values() {
+        let mut root = skaters();
+        let paths = Selector::parse("Skateboarder[?name=='Nyjah Huston'].age").unwrap().select_paths(&root);
+        assert_eq!(paths.len(), 1);
+        *get_mut(&mut root, &paths[0]).unwrap() = Value::Number(27.0);
+        assert_eq!(texts_in(&root, "Skateboarder[1].age"), vec!["27"]);
+    }
+
+    fn texts_in(root: &Value, expr: &str) -> Vec<String> {
+        Selector::parse(expr).unwrap().select(root).into_iter().filter_map(scalar_text).collect()
+    }
+}
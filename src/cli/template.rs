@@ -0,0 +1,123 @@
+//! `vectrune render template.rune --set name=orders --set port=8100`: instantiates a
+//! document template.
+//!
+//! A template is an ordinary rune document with an `@Template` section declaring its
+//! parameters; every `%param%` in the text is replaced before the result is parsed.
+//!
+//! ```rune
+//! @Template
+//! params = (name port)
+//! port = 8080
+//!
+//! @App
+//! name = %name%-service
+//! port = %port%
+//! ```
+//!
+//! Other keys of `@Template` declare parameters with a default. Only declared parameters
+//! are substituted, so placeholders such as `%ROOT%` pass through untouched.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::rune_ast::Value;
+use crate::rune_parser::parse_rune;
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) if n.fract() == 0.0 => Some(format!("{}", *n as i64)),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Drops the `@Template` section (up to the next section header) from the source text.
+fn strip_template_section(source: &str) -> String {
+    let mut in_template = false;
+    let mut out = String::new();
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('@') {
+            in_template = trimmed == "@Template" || trimmed.starts_with("@Template ");
+        }
+        if !in_template {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Instantiates `source` with `values`, which override the template's defaults.
+/// Fails on unknown or missing parameters, or if the result does not parse.
+pub fn render_template(source: &str, values: &[(String, String)]) -> Result<String> {
+    let doc = parse_rune(source)?;
+    let section = doc
+        .sections
+        .iter()
+        .find(|s| s.path.first().map(|p| p == "Template").unwrap_or(false))
+        .context("document has no @Template section")?;
+
+    let required: Vec<String> = match section.kv.get("params") {
+        Some(Value::List(items)) => items.iter().filter_map(value_text).collect(),
+        Some(other) => value_text(other).into_iter().collect(),
+        None => Vec::new(),
+    };
+    let mut params: BTreeMap<String, Option<String>> = required.into_iter().map(|p| (p, None)).collect();
+    for (key, value) in section.kv.iter().filter(|(key, _)| key.as_str() != "params") {
+        params.insert(key.clone(), value_text(value));
+    }
+    for (key, value) in values {
+        match params.get_mut(key) {
+            Some(slot) => *slot = Some(value.clone()),
+            None => {
+                let declared: Vec<&str> = params.keys().map(|k| k.as_str()).collect();
+                bail!("unknown template parameter '{}' (declared: {})", key, declared.join(", "))
+            }
+        }
+    }
+    let missing: Vec<&str> = params.iter().filter(|(_, v)| v.is_none()).map(|(k, _)| k.as_str()).collect();
+    if !missing.is_empty() {
+        bail!("missing template parameters: {} (pass --set name=value)", missing.join(", "));
+    }
+
+    let mut text = strip_template_section(source);
+    for (name, value) in &params {
+        text = text.replace(&format!("%{}%", name), value.as_deref().unwrap_or_default());
+    }
+    parse_rune(&text).map_err(|e| anyhow::anyhow!("rendered template does not parse: {}", e))?;
+    Ok(text)
+}
+
+/// Parses `--set name=value` arguments.
+fn parse_sets(matches: &ArgMatches) -> Result<Vec<(String, String)>> {
+    matches
+        .get_many::<String>("set")
+        .into_iter()
+        .flatten()
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+            _ => bail!("--set expects name=value, got '{}'", pair),
+        })
+        .collect()
+}
+
+pub fn handle_render(matches: &ArgMatches) -> Result<()> {
+    let template = matches.get_one::<String>("TEMPLATE").context("Missing template path")?;
+    let source = std::fs::read_to_string(template).with_context(|| format!("Failed to read {}", template))?;
+    let text = render_template(&source, &parse_sets(matches)?)?;
+    match matches.get_one::<String>("out") {
+        Some(out) => {
+            if let Some(parent) = Path::new(out).parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::write(out, text).with_context(|| format!("Failed to write {}", out))?;
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
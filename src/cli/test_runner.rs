@@ -0,0 +1,126 @@
+//! `vectrune test app.rune [more.rune ...]`: sends the request of every `@Test` section to
+//! the app, in document order and without opening a port, and checks the response.
+//!
+//! ```text
+//! @Test/lists books
+//! request = "GET /books?limit=5"
+//! body = {"title": "Dune"}
+//! status = 200
+//! contains = Dune
+//! ```
+//!
+//! `request` is required; `body` (a string or an object), `headers` (a map block),
+//! `status` (the expected code) and `contains` (text the body must include) are optional.
+//! Tests share one app instance, so memory written by one test is seen by the next.
+
+use anyhow::{bail, Context, Result};
+use axum::body::Body;
+use axum::http::Request;
+use clap::ArgMatches;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use super::run::script_dir;
+use crate::apps::build_app_router;
+use crate::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use crate::rune_ast::{RuneDocument, Section, Value};
+use crate::rune_parser::load_rune_document_from_source;
+
+/// The request a test section describes.
+fn build_request(section: &Section) -> Result<Request<Body>, String> {
+    let request = section.kv.get("request").and_then(|v| v.as_str()).ok_or("request is missing")?;
+    let (method, uri) = request
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("request '{}' should be METHOD PATH", request))?;
+    let mut builder = Request::builder().method(method.to_uppercase().as_str()).uri(uri.trim());
+    if let Some(Value::Map(headers)) = section.kv.get("headers") {
+        for (name, value) in headers {
+            builder = builder.header(name.as_str(), value.as_str().unwrap_or_default());
+        }
+    }
+    let body = match section.kv.get("body") {
+        Some(Value::String(text)) => Body::from(text.clone()),
+        Some(other) => {
+            builder = builder.header("content-type", "application/json");
+            Body::from(other.to_json().to_string())
+        }
+        None => Body::empty(),
+    };
+    builder.body(body).map_err(|e| e.to_string())
+}
+
+/// Sends one test's request and returns why it failed, if it did.
+async fn run_test(router: &axum::Router, section: &Section) -> Option<String> {
+    let request = match build_request(section) {
+        Ok(request) => request,
+        Err(e) => return Some(e),
+    };
+    let response = match router.clone().oneshot(request).await {
+        Ok(response) => response,
+        Err(e) => return Some(e.to_string()),
+    };
+    let status = response.status().as_u16();
+    let body = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => return Some(e.to_string()),
+    };
+    if let Some(expected) = section.kv.get("status").and_then(|v| v.as_u64()) {
+        if expected != status as u64 {
+            return Some(format!("expected status {}, got {} {}", expected, status, body));
+        }
+    }
+    if let Some(expected) = section.kv.get("contains") {
+        let expected = match expected {
+            Value::String(text) => text.clone(),
+            other => other.to_json().to_string(),
+        };
+        if !body.contains(&expected) {
+            return Some(format!("expected the body to contain {:?}, got {}", expected, body));
+        }
+    }
+    None
+}
+
+pub async fn handle_test(matches: &ArgMatches) -> Result<()> {
+    let scripts: Vec<&String> = matches.get_many::<String>("SCRIPT").context("Missing rune files")?.collect();
+    let mut doc = RuneDocument { sections: Vec::new() };
+    for script in &scripts {
+        doc.merge(load_rune_document_from_source(script).map_err(|e| anyhow::anyhow!(e))?);
+    }
+    let path = match scripts.first() {
+        Some(script) if script.as_str() != "-" => script_dir(script),
+        _ => std::env::current_dir()?,
+    };
+    let tests: Vec<Section> = doc
+        .sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Test"))
+        .cloned()
+        .collect();
+    let router = build_app_router(AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc: Arc::new(doc),
+        path,
+    })
+    .await;
+
+    let mut failed = 0;
+    for section in &tests {
+        let name = section.path[1..].join("/");
+        match run_test(&router, section).await {
+            None => println!("ok   {}", name.trim()),
+            Some(reason) => {
+                failed += 1;
+                println!("FAIL {}: {}", name.trim(), reason);
+            }
+        }
+    }
+    println!("\n{} passed; {} failed", tests.len() - failed, failed);
+    if failed > 0 {
+        bail!("{} tests failed", failed);
+    }
+    Ok(())
+}
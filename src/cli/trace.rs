@@ -0,0 +1,199 @@
+//! `vectrune app.rune --trace GET /books/1 [--body book.json]`: runs the steps of the route a
+//! request would reach, without starting a server, and prints each step with its resolved
+//! arguments, the context entries it changed and its result, then the response.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::run::script_dir;
+use crate::builtins::builtin::data_source::get_data_source_commands;
+use crate::core::step_trace::{self, TracedStep};
+use crate::core::{execute_request_steps, step, extract_data_sources, extract_steps, extract_schemas, initialize_memory_from_doc, AppState};
+use crate::rune_ast::{RuneDocument, Value};
+use crate::rune_parser::load_rune_document_from_source;
+
+/// The route a request reaches: its label, steps, `on_error` series and path parameters.
+struct Matched {
+    label: String,
+    steps: Vec<Value>,
+    on_error: Option<Vec<Value>>,
+    params: HashMap<String, String>,
+}
+
+/// Matches `segments` against a route template such as `books/{id}`.
+fn match_template(template: &[String], segments: &[&str]) -> Option<HashMap<String, String>> {
+    if template.len() != segments.len() {
+        return None;
+    }
+    let mut params = HashMap::new();
+    for (part, segment) in template.iter().zip(segments) {
+        match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) => {
+                params.insert(name.to_string(), segment.to_string());
+            }
+            None if part == segment => {}
+            None => return None,
+        }
+    }
+    Some(params)
+}
+
+fn find_route(state: &AppState, method: &str, path: &str) -> Option<Matched> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let routes = state
+        .doc
+        .sections
+        .iter()
+        .filter(|s| s.path.first().map(|p| p.as_str()) == Some("Route") && s.path.len() >= 3);
+    for section in routes {
+        let route_method = section.path[1].to_uppercase();
+        let template: Vec<String> = section.path[2..].iter().filter(|s| !s.is_empty()).cloned().collect();
+        let label = format!("@Route/{} /{}", section.path[1], section.path[2..].join("/"));
+        if route_method == "CRUD" && matches!(method, "GET" | "POST" | "PUT" | "DELETE") {
+            let mut item = template.clone();
+            item.push("{id}".to_string());
+            for (single, template) in [(false, &template), (true, &item)] {
+                if let Some(params) = match_template(template, &segments) {
+                    let steps =
+                        get_data_source_commands(method, section.clone(), &state.schemas, &state.data_sources, single);
+                    let on_error = section.series.get("on_error").cloned();
+                    return Some(Matched { label, steps, on_error, params });
+                }
+            }
+        } else if route_method == method {
+            if let Some(params) = match_template(&template, &segments) {
+                let steps = section
+                    .series
+                    .get("run")
+                    .cloned()
+                    .unwrap_or_else(|| vec![Value::String("respond 200 OK".to_string())]);
+                let on_error = section.series.get("on_error").cloned();
+                return Some(Matched { label, steps, on_error, params });
+            }
+        }
+    }
+    None
+}
+
+/// Compact JSON, shortened so one value stays on one line.
+fn brief(value: &JsonValue) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(120) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
+
+fn write_step(out: &mut String, index: usize, traced: &TracedStep) {
+    writeln!(out, "[{}] {}", index + 1, traced.step).unwrap();
+    if !traced.args.is_empty() {
+        let args: Vec<String> = traced.args.iter().map(brief).collect();
+        writeln!(out, "    args: {}", args.join(" ")).unwrap();
+    }
+    for change in &traced.changes {
+        match (&change.before, &change.after) {
+            (None, Some(after)) => writeln!(out, "    + {} = {}", change.key, brief(after)),
+            (Some(before), Some(after)) => {
+                writeln!(out, "    ~ {}: {} -> {}", change.key, brief(before), brief(after))
+            }
+            (_, None) => writeln!(out, "    - {}", change.key),
+        }
+        .unwrap();
+    }
+    if let Some(result) = &traced.result {
+        writeln!(out, "    => {}", brief(result)).unwrap();
+    }
+    if let Some((status, body)) = &traced.response {
+        writeln!(out, "    <= {} {}", status, body).unwrap();
+    }
+}
+
+pub async fn handle_trace(scripts: &[&str], method: &str, target: &str, body_file: Option<&str>) -> Result<()> {
+    print!("{}", trace_report(scripts, method, target, body_file).await?);
+    Ok(())
+}
+
+/// The trace `handle_trace` prints: the matched route, each step, then the response.
+pub(crate) async fn trace_report(scripts: &[&str], method: &str, target: &str, body_file: Option<&str>) -> Result<String> {
+    let mut doc: Option<RuneDocument> = None;
+    for script in scripts {
+        let loaded = load_rune_document_from_source(script).map_err(|e| anyhow::anyhow!(e))?;
+        match doc.as_mut() {
+            Some(d) => d.merge(loaded),
+            None => doc = Some(loaded),
+        }
+    }
+    let doc = doc.context("No rune document to trace")?;
+    let path = match scripts.first() {
+        Some(script) if *script != "-" => script_dir(script),
+        _ => std::env::current_dir()?,
+    };
+    initialize_memory_from_doc(&doc, &path).await;
+    let state = AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc: Arc::new(doc),
+        path,
+    };
+
+    let method = method.to_uppercase();
+    let (route_path, query) = target.split_once('?').unwrap_or((target, ""));
+    let Some(matched) = find_route(&state, &method, route_path) else {
+        bail!("No route matches {} {}", method, route_path);
+    };
+    let query = match query {
+        "" => HashMap::new(),
+        _ => {
+            let uri: axum::http::Uri = target.parse().with_context(|| format!("Invalid path {}", target))?;
+            axum::extract::Query::<HashMap<String, String>>::try_from_uri(&uri)?.0
+        }
+    };
+    let body = match body_file {
+        Some(file) => Some(std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?),
+        None => None,
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{} {} -> {}", method, target, matched.label).unwrap();
+    let mut params: Vec<_> = matched.params.iter().collect();
+    params.sort();
+    for (name, value) in params {
+        writeln!(out, "    {} = {:?}", name, value).unwrap();
+    }
+    out.push('\n');
+
+    let (response, steps) = step_trace::record(execute_request_steps(
+        state,
+        &step::compile(&matched.steps),
+        body,
+        Some(matched.params),
+        Some(query),
+        None,
+        matched.on_error.map(|on_error| step::compile(&on_error).into()),
+    ))
+    .await;
+    for (index, traced) in steps.iter().enumerate() {
+        write_step(&mut out, index, traced);
+    }
+    out.push('\n');
+    writeln!(out, "Response: {} {}", response.status.as_u16(), response.body).unwrap();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_capture_path_parameters() {
+        let template = vec!["books".to_string(), "{id}".to_string()];
+        let params = match_template(&template, &["books", "7"]).unwrap();
+        assert_eq!(params.get("id").map(String::as_str), Some("7"));
+        assert!(match_template(&template, &["authors", "7"]).is_none());
+        assert!(match_template(&template, &["books"]).is_none());
+    }
+}
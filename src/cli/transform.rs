@@ -1,18 +1,52 @@
-use crate::rune_ast::{RuneDocument, Value};
+use std::collections::HashMap;
+
+use crate::cli::selector::{document_value, parse_condition, parse_literal, passes, scalar_text, split_top_level, Condition, Literal, Selector};
+use crate::rune_ast::{RuneDocument, Section, Value};
 
 pub fn handle_transform(doc: &RuneDocument, spec: &str) -> Result<RuneDocument, String> {
-    let s = transform_to_string(doc, spec)?;
-    let new_doc = RuneDocument::from_str(&s)
-        .map_err(|e| format!("Failed to parse transformed Rune document: {}", e))?;
-    Ok(new_doc)
+    let (target, keys) = evaluate_spec(doc, spec)?;
+    let section = Section {
+        path: target.split('/').map(str::to_string).collect(),
+        kv: HashMap::new(),
+        series: keys.into_iter().collect(),
+        records: Vec::new(),
+        source_file: None,
+    };
+    Ok(RuneDocument { sections: vec![section] })
+}
+
+/// Reads a transform file (`.runet`): one spec per line, applied in order.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_spec_file(path: &str) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading transform file {}: {}", path, e))?;
+    let specs: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if specs.is_empty() {
+        return Err(format!("Transform file {} has no specs", path));
+    }
+    Ok(specs)
 }
 
-pub fn transform_to_string(doc: &RuneDocument, spec: &str) -> Result<String, String> {
+/// A target section and its keys with their items, in spec order.
+type Transformed = (String, Vec<(String, Vec<Value>)>);
+
+fn evaluate_spec(doc: &RuneDocument, spec: &str) -> Result<Transformed, String> {
     // Syntax examples:
     //  @Target key:[@Section.field]
     //  @Target names:[@Skateboarder.name|unique|sort]
     //  @Target ages:[@Skateboarder.age|sort:desc]
     //  @Target pro_names:[@Skateboarder.name=="Tony Hawk"|unique]
+    //  @Target veterans:[@Skateboarder.name|(age>30 && country=="USA")]
+    //  @Target people:[@Person.{name, age as years}]          (map items)
+    //  @Target entry:[@Person.{name,age} as "name (age)"]      (one string per record)
+    //  @Target full:[@Person.first + " " + @Person.last]
+    //  @Target rows:[@Order join @Customer on Order.customer_id == Customer.id -> {Order.total, Customer.name}]
+    //  @Target vert:[$.Skateboarder[?style=='Vert'].name]       (path expression, see selector)
     //  Multiple keys: @Target names:[@S.name] ages:[@S.age|sort]
 
     let spec = spec.trim();
@@ -69,7 +103,7 @@ pub fn transform_to_string(doc: &RuneDocument, spec: &str) -> Result<String, Str
     }
 
     // Each token should be key:[...]
-    let mut output: Vec<(String, Vec<String>)> = Vec::new();
+    let mut output: Vec<(String, Vec<Value>)> = Vec::new();
     for tok in tokens {
         let (key, list_part) = tok
             .split_once(':')
@@ -84,153 +118,398 @@ pub fn transform_to_string(doc: &RuneDocument, spec: &str) -> Result<String, Str
         output.push((key, values));
     }
 
-    // Build resulting Rune document string
-    let mut out = String::new();
-    out.push_str("#!RUNE\n");
-    out.push_str(&format!("@{}\n", target_section));
-    for (key, values) in output {
-        out.push_str(&format!("{}:\n", key));
-        for v in values {
-            out.push_str(&format!("  {}\n", v));
-        }
-    }
-
-    Ok(out)
+    Ok((target_section.to_string(), output))
 }
 
-fn evaluate_list_spec(doc: &RuneDocument, inner: &str) -> Result<Vec<String>, String> {
-    // inner format: @Section.field[==literal][|modifier[:arg]]...
-    if !inner.starts_with('@') {
-        return Err("List selector must start with '@'".to_string());
+fn evaluate_list_spec(doc: &RuneDocument, inner: &str) -> Result<Vec<Value>, String> {
+    // inner format: @Section.field[<op>literal][|(condition)][|modifier[:arg]]...
+    if !inner.starts_with('@') && !inner.starts_with('$') {
+        return Err("List selector must start with '@' or '$'".to_string());
     }
-    // split on '|' to separate selector and modifiers
-    let mut parts = inner.split('|');
+    // split on '|' to separate selector, record filters and modifiers
+    let mut parts = split_top_level(inner, "|").into_iter();
     let selector = parts.next().unwrap().trim();
-    let modifiers: Vec<&str> = parts.map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
-
-    // Handle optional equality filter inside selector: @Section.field==literal
-    let (section, field, filter): (&str, &str, Option<Literal>) =
-        if let Some((lhs, rhs)) = selector.split_once("==") {
-            let (section, field) = lhs
-                .trim_start_matches('@')
-                .split_once('.')
-                .ok_or("Selector must be @Section.field")?;
-            let lit = parse_literal(rhs.trim())?;
-            (section.trim(), field.trim(), Some(lit))
+    let mut filters = Vec::new();
+    let mut modifiers = Vec::new();
+    for part in parts.map(str::trim).filter(|s| !s.is_empty()) {
+        if part.starts_with('(') {
+            filters.push(parse_condition(part)?);
         } else {
-            let (section, field) = selector
-                .trim_start_matches('@')
-                .split_once('.')
-                .ok_or("Selector must be @Section.field")?;
-            (section.trim(), field.trim(), None)
-        };
-
-    // Collect values
-    let mut values: Vec<String> = Vec::new();
-    for sec in doc.get_sections(section) {
-        for rec in &sec.records {
-            // Apply filter if any
-            if let Some(ref lit) = filter {
-                if let Some(v) = rec.kv.get(field) {
-                    if !literal_matches_value(lit, v) {
-                        continue;
-                    }
-                } else {
-                    continue;
-                }
-            }
-            if let Some(val) = rec.kv.get(field) {
-                match val {
-                    Value::String(s) => values.push(s.clone()),
-                    Value::Number(n) => values.push(format!("{}", n)),
-                    Value::Bool(b) => values.push(format!("{}", b)),
-                    _ => {}
-                }
-            }
+            modifiers.push(part);
         }
     }
 
+    let values = if selector.starts_with('$') {
+        select_path(doc, selector, &filters)?
+    } else if selector.contains(" join ") {
+        join(doc, selector, &filters)?
+    } else if split_concatenation(selector).len() > 1 {
+        concatenate(doc, selector, &filters)?
+    } else if selector.contains(".{") {
+        project(doc, selector, &filters)?
+    } else {
+        select_field(doc, selector, &filters)?.into_iter().map(Value::String).collect()
+    };
+
     // Apply modifiers
+    let mut values = values;
     for m in modifiers {
         if m.eq_ignore_ascii_case("unique") || m.eq_ignore_ascii_case("distinct") {
             values = unique_stable(values);
         } else if m.starts_with("sort") {
             let desc = m.to_ascii_lowercase().starts_with("sort:desc");
             values = sort_maybe_numeric(values, desc);
+        } else if m.eq_ignore_ascii_case("reverse") {
+            values.reverse();
+        } else if let Some(n) = m.strip_prefix("limit:") {
+            values.truncate(count_arg(m, n)?);
+        } else if let Some(n) = m.strip_prefix("skip:") {
+            values.drain(..count_arg(m, n)?.min(values.len()));
+        } else if m.eq_ignore_ascii_case("sum") || m.eq_ignore_ascii_case("avg") {
+            let numbers = numeric_values(&values, m)?;
+            let total: f64 = numbers.iter().sum();
+            values = if m.eq_ignore_ascii_case("sum") {
+                vec![Value::Number(total)]
+            } else if numbers.is_empty() {
+                Vec::new()
+            } else {
+                vec![Value::Number(total / numbers.len() as f64)]
+            };
         }
     }
 
     Ok(values)
 }
 
-#[derive(Debug)]
-enum Literal {
-    S(String),
-    N(f64),
-    B(bool),
-}
-
-fn parse_literal(s: &str) -> Result<Literal, String> {
-    let s = s.trim();
-    if s.eq_ignore_ascii_case("true") {
-        return Ok(Literal::B(true));
-    }
-    if s.eq_ignore_ascii_case("false") {
-        return Ok(Literal::B(false));
-    }
-    if let Ok(n) = s.parse::<f64>() {
-        return Ok(Literal::N(n));
-    }
-    // quoted string
-    if (s.starts_with('"') && s.ends_with('"') && s.len() >= 2)
-        || (s.starts_with('\'') && s.ends_with('\'') && s.len() >= 2)
-    {
-        let trimmed = &s[1..s.len() - 1];
-        return Ok(Literal::S(trimmed.to_string()));
-    }
-    // fallback: raw string
-    Ok(Literal::S(s.to_string()))
-}
-
-fn literal_matches_value(lit: &Literal, v: &Value) -> bool {
-    match (lit, v) {
-        (Literal::S(ls), Value::String(rs)) => ls == rs,
-        (Literal::N(ln), Value::Number(rn)) => (*ln - rn).abs() < f64::EPSILON,
-        (Literal::B(lb), Value::Bool(rb)) => lb == rb,
-        (Literal::S(ls), Value::Number(rn)) => ls
-            .parse::<f64>()
-            .map(|n| (n - rn).abs() < f64::EPSILON)
-            .unwrap_or(false),
-        (Literal::S(ls), Value::Bool(rb)) => {
-            if ls.eq_ignore_ascii_case("true") {
-                *rb
-            } else if ls.eq_ignore_ascii_case("false") {
-                !*rb
+/// A `$` path expression; selected lists contribute their items.
+fn select_path(doc: &RuneDocument, selector: &str, filters: &[Condition]) -> Result<Vec<Value>, String> {
+    let root = document_value(doc);
+    let mut values = Vec::new();
+    for value in Selector::parse(selector)?.select(&root) {
+        match value {
+            Value::List(items) => values.extend(items.iter().cloned()),
+            other => values.push(other.clone()),
+        }
+    }
+    values.retain(|value| match value {
+        Value::Map(map) => passes(filters, &|f| map.get(f)),
+        _ => filters.is_empty(),
+    });
+    Ok(values)
+}
+
+/// `@Section.{a, b as c}` as one map per record, optionally followed by
+/// `as "template"` to turn each map into a string instead.
+fn project(doc: &RuneDocument, selector: &str, filters: &[Condition]) -> Result<Vec<Value>, String> {
+    let (section, rest) = selector
+        .trim_start_matches('@')
+        .split_once(".{")
+        .ok_or("Projection must be @Section.{field, ...}")?;
+    let (fields, rest) = rest.split_once('}').ok_or("Missing '}' in projection")?;
+    let template = match rest.trim() {
+        "" => None,
+        rest => Some(
+            rest.strip_prefix("as")
+                .map(|t| t.trim())
+                .filter(|t| t.len() >= 2 && (t.starts_with('"') && t.ends_with('"') || t.starts_with('\'') && t.ends_with('\'')))
+                .map(|t| &t[1..t.len() - 1])
+                .ok_or("Expected 'as \"template\"' after a projection")?,
+        ),
+    };
+    let fields: Vec<(&str, &str)> = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|f| match f.split_once(" as ") {
+            Some((field, name)) => (field.trim(), name.trim()),
+            None => (f, f),
+        })
+        .collect();
+    if fields.is_empty() {
+        return Err("Projection needs at least one field".to_string());
+    }
+
+    let mut values = Vec::new();
+    for sec in doc.get_sections(section.trim()) {
+        for rec in sec.records.iter().filter(|rec| passes(filters, &|f| rec.kv.get(f))) {
+            let map: HashMap<String, Value> = fields
+                .iter()
+                .filter_map(|(field, name)| Some((name.to_string(), rec.kv.get(*field)?.clone())))
+                .collect();
+            if map.is_empty() {
+                continue;
+            }
+            values.push(match template {
+                Some(template) => Value::String(fill_template(template, &map)),
+                None => Value::Map(map),
+            });
+        }
+    }
+    Ok(values)
+}
+
+/// Replaces `{name}` placeholders in `template`, or, when it has none, every word that
+/// is one of the projected names.
+fn fill_template(template: &str, values: &HashMap<String, Value>) -> String {
+    let text = |name: &str| values.get(name).and_then(scalar_text);
+    if template.contains('{') {
+        let mut out = template.to_string();
+        for name in values.keys() {
+            out = out.replace(&format!("{{{}}}", name), &text(name).unwrap_or_default());
+        }
+        return out;
+    }
+    let mut out = String::new();
+    let mut word = String::new();
+    for c in template.chars().chain(std::iter::once('\0')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        out.push_str(&text(&word).unwrap_or_else(|| word.clone()));
+        word.clear();
+        if c != '\0' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `Section.field` (with or without `@`) as its two parts.
+fn section_field(path: &str) -> Result<(&str, &str), String> {
+    path.trim()
+        .trim_start_matches('@')
+        .split_once('.')
+        .map(|(section, field)| (section.trim(), field.trim()))
+        .ok_or_else(|| format!("Expected Section.field, got '{}'", path.trim()))
+}
+
+/// `@Left join @Right on Left.a == Right.b [-> {Left.x, Right.y as z}]` as one map per
+/// matching pair of records. Without a projection each map holds every field of both
+/// records, the left one winning when both have a field.
+fn join(doc: &RuneDocument, selector: &str, filters: &[Condition]) -> Result<Vec<Value>, String> {
+    let (left, rest) = selector.split_once(" join ").ok_or("Expected '@Left join @Right on ...'")?;
+    let (right, rest) = rest.split_once(" on ").ok_or("Expected 'on Left.field == Right.field' after join")?;
+    let (condition, projection) = match rest.split_once("->") {
+        Some((condition, projection)) => (condition, Some(projection.trim())),
+        None => (rest, None),
+    };
+    let (left, right) = (left.trim().trim_start_matches('@'), right.trim().trim_start_matches('@'));
+    let (lhs, rhs) = condition.split_once("==").ok_or("Join condition must be Left.field == Right.field")?;
+    let (lhs, rhs) = (section_field(lhs)?, section_field(rhs)?);
+    let (left_key, right_key) = match (lhs.0, rhs.0) {
+        (l, r) if l == left && r == right => (lhs.1, rhs.1),
+        (l, r) if l == right && r == left => (rhs.1, lhs.1),
+        _ => return Err(format!("Join condition must compare fields of {} and {}", left, right)),
+    };
+
+    let mut fields: Vec<(bool, &str, &str)> = Vec::new();
+    if let Some(projection) = projection {
+        let inner = projection
+            .strip_prefix('{')
+            .and_then(|p| p.strip_suffix('}'))
+            .ok_or("Join projection must be {Section.field, ...}")?;
+        for item in inner.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (path, name) = match item.split_once(" as ") {
+                Some((path, name)) => (path, name.trim()),
+                None => (item, section_field(item)?.1),
+            };
+            let (section, field) = section_field(path)?;
+            if section != left && section != right {
+                return Err(format!("{} is not part of the join", section));
+            }
+            if fields.iter().any(|(_, _, n)| *n == name) {
+                return Err(format!("Join projection names '{}' twice; rename one with 'as'", name));
+            }
+            fields.push((section == left, field, name));
+        }
+    }
+
+    let records = |section: &str| -> Vec<&HashMap<String, Value>> {
+        doc.get_sections(section).into_iter().flat_map(|s| s.records.iter().map(|r| &r.kv)).collect()
+    };
+    let right_records = records(right);
+    let mut values = Vec::new();
+    for l in records(left) {
+        let Some(key) = l.get(left_key).and_then(scalar_text) else {
+            continue;
+        };
+        for r in right_records.iter().filter(|r| r.get(right_key).and_then(scalar_text).as_ref() == Some(&key)) {
+            let lookup = |path: &str| match section_field(path) {
+                Ok((section, field)) if section == left => l.get(field),
+                Ok((section, field)) if section == right => r.get(field),
+                _ => l.get(path).or_else(|| r.get(path)),
+            };
+            if !passes(filters, &lookup) {
+                continue;
+            }
+            let map: HashMap<String, Value> = if fields.is_empty() {
+                r.iter().chain(l.iter()).map(|(k, v)| (k.clone(), v.clone())).collect()
             } else {
-                false
+                fields
+                    .iter()
+                    .filter_map(|(from_left, field, name)| {
+                        let record = if *from_left { l } else { *r };
+                        Some((name.to_string(), record.get(*field)?.clone()))
+                    })
+                    .collect()
+            };
+            values.push(Value::Map(map));
+        }
+    }
+    Ok(values)
+}
+
+/// The `+`-separated operands of a concatenation, outside quotes.
+fn split_concatenation(selector: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '+') => {
+                parts.push(selector[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(selector[start..].trim());
+    parts
+}
+
+/// `@Person.first + " " + @Person.last` as one string per record of the section; records
+/// missing a field are skipped.
+fn concatenate(doc: &RuneDocument, selector: &str, filters: &[Condition]) -> Result<Vec<Value>, String> {
+    enum Part<'a> {
+        Field(&'a str),
+        Text(String),
+    }
+    let mut section: Option<&str> = None;
+    let mut parts = Vec::new();
+    for operand in split_concatenation(selector) {
+        if let Some(path) = operand.strip_prefix('@') {
+            let (sec, field) = path.split_once('.').ok_or("Selector must be @Section.field")?;
+            if section.is_some_and(|s| s != sec.trim()) {
+                return Err("All fields in a concatenation must come from the same section".to_string());
+            }
+            section = Some(sec.trim());
+            parts.push(Part::Field(field.trim()));
+        } else {
+            match parse_literal(operand)? {
+                Literal::S(s) => parts.push(Part::Text(s)),
+                Literal::N(n) => parts.push(Part::Text(format!("{}", n))),
+                Literal::B(b) => parts.push(Part::Text(format!("{}", b))),
+            }
+        }
+    }
+    let section = section.ok_or("A concatenation needs at least one @Section.field")?;
+    let mut values = Vec::new();
+    for sec in doc.get_sections(section) {
+        'records: for rec in sec.records.iter().filter(|rec| passes(filters, &|f| rec.kv.get(f))) {
+            let mut joined = String::new();
+            for part in &parts {
+                match part {
+                    Part::Text(text) => joined.push_str(text),
+                    Part::Field(field) => match rec.kv.get(*field).and_then(scalar_text) {
+                        Some(text) => joined.push_str(&text),
+                        None => continue 'records,
+                    },
+                }
             }
+            values.push(Value::String(joined));
         }
-        _ => false,
     }
+    Ok(values)
+}
+
+fn select_field(doc: &RuneDocument, selector: &str, filters: &[Condition]) -> Result<Vec<String>, String> {
+    // Handle an optional comparison inside the selector: @Section.field==literal
+    let (section, rest) = selector
+        .trim_start_matches('@')
+        .split_once('.')
+        .ok_or("Selector must be @Section.field")?;
+    let field_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(rest.len());
+    let field = &rest[..field_len];
+    let inline = match rest[field_len..].trim() {
+        "" => None,
+        comparison => Some(parse_condition(&format!("{}{}", field, comparison))?),
+    };
+    if field.is_empty() {
+        return Err("Selector must be @Section.field".to_string());
+    }
+
+    // Collect values
+    let mut values: Vec<String> = Vec::new();
+    for sec in doc.get_sections(section.trim()) {
+        for rec in &sec.records {
+            let lookup = |f: &str| rec.kv.get(f);
+            if !passes(filters, &lookup) || inline.as_ref().is_some_and(|c| !c.matches(&lookup)) {
+                continue;
+            }
+            if let Some(text) = rec.kv.get(field).and_then(scalar_text) {
+                values.push(text);
+            }
+        }
+        // A series of that name, such as an earlier transform's output, lists its items
+        for item in sec.series.get(field).into_iter().flatten() {
+            let lookup = |f: &str| (f == field).then_some(item);
+            if !passes(filters, &lookup) || inline.as_ref().is_some_and(|c| !c.matches(&lookup)) {
+                continue;
+            }
+            if let Some(text) = scalar_text(item) {
+                values.push(text);
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// How an item compares for `unique` and `sort`: its text, or its written form for maps.
+fn item_text(value: &Value) -> String {
+    scalar_text(value).unwrap_or_else(|| value.to_string())
+}
+
+fn count_arg(modifier: &str, arg: &str) -> Result<usize, String> {
+    arg.trim()
+        .parse()
+        .map_err(|_| format!("'{}' expects a whole number, e.g. limit:10", modifier))
+}
+
+/// The items as numbers for `sum` and `avg`; any non-numeric item is an error.
+fn numeric_values(values: &[Value], modifier: &str) -> Result<Vec<f64>, String> {
+    values
+        .iter()
+        .map(|v| {
+            let text = item_text(v);
+            text.parse::<f64>()
+                .map_err(|_| format!("'{}' needs numeric values, got '{}'", modifier, text))
+        })
+        .collect()
 }
 
-fn unique_stable(mut v: Vec<String>) -> Vec<String> {
+fn unique_stable(mut v: Vec<Value>) -> Vec<Value> {
     let mut seen = std::collections::HashSet::new();
-    v.retain(|s| seen.insert(s.clone()));
+    v.retain(|s| seen.insert(item_text(s)));
     v
 }
 
-fn sort_maybe_numeric(mut v: Vec<String>, desc: bool) -> Vec<String> {
+fn sort_maybe_numeric(mut v: Vec<Value>, desc: bool) -> Vec<Value> {
     // If all values parse as numbers, sort numerically; else lexicographically
-    let all_numeric = v.iter().all(|s| s.parse::<f64>().is_ok());
+    let all_numeric = v.iter().all(|s| item_text(s).parse::<f64>().is_ok());
     if all_numeric {
         v.sort_by(|a, b| {
-            let fa = a.parse::<f64>().unwrap();
-            let fb = b.parse::<f64>().unwrap();
+            let fa = item_text(a).parse::<f64>().unwrap();
+            let fb = item_text(b).parse::<f64>().unwrap();
             fa.partial_cmp(&fb).unwrap()
         });
     } else {
-        v.sort();
+        v.sort_by_key(item_text);
     }
     if desc {
         v.reverse();
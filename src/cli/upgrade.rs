@@ -0,0 +1,31 @@
+//! `vectrune upgrade app.rune [more.rune ...] [--check]`: rewrites renamed builtins and
+//! section headers (see `core::aliases`) to their current names, in place.
+
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+
+use crate::core::aliases::upgrade_source;
+
+pub fn handle_upgrade(matches: &ArgMatches) -> Result<()> {
+    let check = matches.get_flag("check");
+    let mut outdated = 0;
+    for file in matches.get_many::<String>("FILES").context("Missing rune files")? {
+        let source = std::fs::read_to_string(file).with_context(|| format!("reading {}", file))?;
+        let (upgraded, changes) = upgrade_source(&source);
+        if changes.is_empty() {
+            println!("{}: up to date", file);
+            continue;
+        }
+        outdated += 1;
+        for change in &changes {
+            println!("{}: {}", file, change);
+        }
+        if !check {
+            std::fs::write(file, upgraded).with_context(|| format!("writing {}", file))?;
+        }
+    }
+    if check && outdated > 0 {
+        bail!("{} files use deprecated names", outdated);
+    }
+    Ok(())
+}
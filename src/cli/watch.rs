@@ -29,7 +29,8 @@ pub fn start_file_watcher(script_paths: Vec<&str>) -> anyhow::Result<mpsc::Recei
 
     // Watch the directory or file
     for path_str in &script_paths {
-        if *path_str != "-" {
+        // Remote documents are fetched once per (re)start and not watched
+        if *path_str != "-" && !crate::rune_parser::remote::is_remote(path_str) {
             let path = PathBuf::from(path_str);
             let watch_path = if path.is_dir() {
                 path
@@ -0,0 +1,413 @@
+//! The model client behind `vectrune ai`: Ollama, Gemini, OpenAI-compatible chat
+//! completions and Anthropic, configured from an `@Ai` section or the environment.
+//!
+//! ```rune
+//! @Ai
+//! provider = openai
+//! model = gpt-4o-mini
+//! base_url = http://localhost:8000/v1
+//! api_key = $OPENAI_API_KEY$
+//! timeout = 60s
+//! retries = 2
+//! ```
+//!
+//! `embedding_model` names the model `embed` uses. Keys the section leaves out come from
+//! `VECTRUNE_AI_PROVIDER`, `VECTRUNE_AI_MODEL`, `VECTRUNE_AI_EMBEDDING_MODEL`,
+//! `VECTRUNE_AI_BASE_URL` (or `VECTRUNE_OLLAMA_URL`), `VECTRUNE_AI_API_KEY` (or the
+//! provider's own `OPENAI_API_KEY`, `ANTHROPIC_API_KEY`, `GEMINI_API_KEY`),
+//! `VECTRUNE_AI_TIMEOUT` and `VECTRUNE_AI_RETRIES`. Without a provider, a `gemini*`,
+//! `claude*` or `gpt*` model picks its provider and anything else goes to Ollama.
+//!
+//! Replies are streamed; each piece is handed to a callback as it arrives. Connection
+//! errors, timeouts, `408`, `429` and `5xx` responses are retried, waiting `1s` and
+//! doubling it each time.
+
+use crate::builtins::builtin::control::parse_duration;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use std::time::Duration;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_RETRIES: u32 = 2;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    Ollama,
+    Gemini,
+    OpenAi,
+    Anthropic,
+}
+
+impl Provider {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "ollama" => Ok(Provider::Ollama),
+            "gemini" | "google" => Ok(Provider::Gemini),
+            "openai" => Ok(Provider::OpenAi),
+            "anthropic" => Ok(Provider::Anthropic),
+            other => Err(format!("unknown AI provider '{}' (expected ollama, gemini, openai or anthropic)", other)),
+        }
+    }
+
+    /// The provider a model name implies when none is configured.
+    fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        if model.starts_with("gemini") {
+            Provider::Gemini
+        } else if model.starts_with("claude") {
+            Provider::Anthropic
+        } else if model.starts_with("gpt") {
+            Provider::OpenAi
+        } else {
+            Provider::Ollama
+        }
+    }
+
+    fn default_model(self) -> &'static str {
+        match self {
+            Provider::Ollama => "phi4",
+            Provider::Gemini => "gemini-1.5-flash",
+            Provider::OpenAi => "gpt-4o-mini",
+            Provider::Anthropic => "claude-3-5-haiku-latest",
+        }
+    }
+
+    fn default_embedding_model(self) -> &'static str {
+        match self {
+            Provider::Ollama => "nomic-embed-text",
+            Provider::Gemini => "text-embedding-004",
+            Provider::OpenAi => "text-embedding-3-small",
+            Provider::Anthropic => "",
+        }
+    }
+
+    fn default_base_url(self) -> &'static str {
+        match self {
+            Provider::Ollama => "http://127.0.0.1:11434",
+            Provider::Gemini => "https://generativelanguage.googleapis.com/v1beta",
+            Provider::OpenAi => "https://api.openai.com/v1",
+            Provider::Anthropic => "https://api.anthropic.com/v1",
+        }
+    }
+
+    fn key_env(self) -> Option<&'static str> {
+        match self {
+            Provider::Ollama => None,
+            Provider::Gemini => Some("GEMINI_API_KEY"),
+            Provider::OpenAi => Some("OPENAI_API_KEY"),
+            Provider::Anthropic => Some("ANTHROPIC_API_KEY"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiConfig {
+    pub provider: Provider,
+    pub model: String,
+    pub embedding_model: String,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl AiConfig {
+    /// The configuration from the environment alone, with `model` (when given) taking
+    /// precedence over `VECTRUNE_AI_MODEL`.
+    pub fn from_env(model: Option<&str>) -> Result<Self, String> {
+        Self::resolve(None, model)
+    }
+
+    /// The configuration of the document's `@Ai` section, falling back to the environment.
+    pub fn from_doc(doc: &RuneDocument, model: Option<&str>) -> Result<Self, String> {
+        Self::resolve(doc.get_section("Ai"), model)
+    }
+
+    fn resolve(section: Option<&Section>, model: Option<&str>) -> Result<Self, String> {
+        let setting = |key: &str, env: &[&str]| -> Option<String> {
+            let from_section = section.and_then(|s| match s.kv.get(key) {
+                Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+                Some(Value::Number(n)) => Some(n.to_string()),
+                _ => None,
+            });
+            from_section.or_else(|| env.iter().find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty())))
+        };
+        let model = model.filter(|m| !m.is_empty()).map(str::to_string).or_else(|| setting("model", &["VECTRUNE_AI_MODEL"]));
+        let provider = match setting("provider", &["VECTRUNE_AI_PROVIDER"]) {
+            Some(name) => Provider::parse(&name)?,
+            None => model.as_deref().map(Provider::for_model).unwrap_or(Provider::Ollama),
+        };
+        let base_url = setting("base_url", &["VECTRUNE_AI_BASE_URL"])
+            .or_else(|| {
+                // VECTRUNE_OLLAMA_URL has always named the full generate endpoint.
+                let ollama = (provider == Provider::Ollama).then(|| std::env::var("VECTRUNE_OLLAMA_URL").ok()).flatten();
+                ollama.map(|url| url.trim_end_matches("/api/generate").to_string())
+            })
+            .unwrap_or_else(|| provider.default_base_url().to_string());
+        let key_envs: Vec<&str> = std::iter::once("VECTRUNE_AI_API_KEY").chain(provider.key_env()).collect();
+        let timeout = match setting("timeout", &["VECTRUNE_AI_TIMEOUT"]) {
+            Some(spec) => parse_duration(&spec).ok_or_else(|| format!("invalid AI timeout '{}'", spec))?,
+            None => DEFAULT_TIMEOUT,
+        };
+        let retries = match setting("retries", &["VECTRUNE_AI_RETRIES"]) {
+            Some(n) => n.parse::<f64>().map(|n| n as u32).map_err(|_| format!("invalid AI retries '{}'", n))?,
+            None => DEFAULT_RETRIES,
+        };
+        Ok(AiConfig {
+            model: model.unwrap_or_else(|| provider.default_model().to_string()),
+            embedding_model: setting("embedding_model", &["VECTRUNE_AI_EMBEDDING_MODEL"])
+                .unwrap_or_else(|| provider.default_embedding_model().to_string()),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: setting("api_key", &key_envs),
+            timeout,
+            retries,
+            provider,
+        })
+    }
+
+    fn require_key(&self) -> Result<&str, String> {
+        self.api_key.as_deref().ok_or_else(|| {
+            let env = self.provider.key_env().unwrap_or("VECTRUNE_AI_API_KEY");
+            format!("{} environment variable not set (or set api_key in @Ai)", env)
+        })
+    }
+
+    fn request(&self, prompt: &str, json: bool) -> Result<reqwest::RequestBuilder, String> {
+        let messages = json!([{ "role": "user", "content": prompt }]);
+        let request = match self.provider {
+            Provider::Ollama => CLIENT
+                .post(format!("{}/api/generate", self.base_url))
+                .json(&json!({ "model": self.model, "prompt": prompt, "stream": true })),
+            Provider::Gemini => {
+                let mut payload = json!({ "contents": [{ "role": "user", "parts": [{ "text": prompt }] }] });
+                if json {
+                    payload["generationConfig"] = json!({ "responseMimeType": "application/json" });
+                }
+                CLIENT
+                    .post(format!("{}/models/{}:streamGenerateContent?alt=sse", self.base_url, self.model))
+                    .header("x-goog-api-key", self.require_key()?)
+                    .json(&payload)
+            }
+            Provider::OpenAi => {
+                let mut payload = json!({ "model": self.model, "messages": messages, "stream": true });
+                if json {
+                    payload["response_format"] = json!({ "type": "json_object" });
+                }
+                let request = CLIENT.post(format!("{}/chat/completions", self.base_url)).json(&payload);
+                // OpenAI-compatible local servers often need no key.
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            }
+            Provider::Anthropic => CLIENT
+                .post(format!("{}/messages", self.base_url))
+                .header("x-api-key", self.require_key()?)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&json!({
+                    "model": self.model,
+                    "max_tokens": ANTHROPIC_MAX_TOKENS,
+                    "messages": messages,
+                    "stream": true,
+                })),
+        };
+        Ok(request.timeout(self.timeout))
+    }
+
+    /// The text one streamed line adds to the reply: an NDJSON object for Ollama, an SSE
+    /// `data:` line for the others.
+    fn stream_piece(&self, line: &str) -> Option<String> {
+        let data = match self.provider {
+            Provider::Ollama => line,
+            _ => line.strip_prefix("data:")?.trim(),
+        };
+        let event: JsonValue = serde_json::from_str(data).ok()?;
+        let text = match self.provider {
+            Provider::Ollama => &event["response"],
+            Provider::Gemini => &event["candidates"][0]["content"]["parts"][0]["text"],
+            Provider::OpenAi => &event["choices"][0]["delta"]["content"],
+            Provider::Anthropic if event["type"] == "content_block_delta" => &event["delta"]["text"],
+            Provider::Anthropic => return None,
+        };
+        text.as_str().map(str::to_string)
+    }
+
+    /// Sends `prompt` and returns the whole reply, calling `on_text` with the reply so far
+    /// each time a piece arrives. `json` asks providers that support it for a JSON reply.
+    pub async fn complete(&self, prompt: &str, json: bool, mut on_text: impl FnMut(&str)) -> Result<String, String> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.stream_once(prompt, json, &mut on_text).await {
+                Ok(text) => return Ok(text),
+                Err(failure) => self.back_off(attempt, failure, &mut delay).await?,
+            }
+        }
+    }
+
+    /// The embedding vector of `text` from the configured embedding model.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f64>, String> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match self.embed_once(text).await {
+                Ok(vector) => return Ok(vector),
+                Err(failure) => self.back_off(attempt, failure, &mut delay).await?,
+            }
+        }
+    }
+
+    /// Waits before the next attempt, or gives up with the failure when it is not worth
+    /// retrying or the retries are spent.
+    async fn back_off(&self, attempt: u32, (failure, retry): (String, bool), delay: &mut Duration) -> Result<(), String> {
+        if !retry || attempt > self.retries {
+            return Err(match attempt {
+                1 => format!("{:?} request failed: {}", self.provider, failure),
+                _ => format!("{:?} request failed after {} attempts: {}", self.provider, attempt, failure),
+            });
+        }
+        tokio::time::sleep(*delay).await;
+        *delay *= 2;
+        Ok(())
+    }
+
+    async fn embed_once(&self, text: &str) -> Result<Vec<f64>, (String, bool)> {
+        let model = &self.embedding_model;
+        let request = match self.provider {
+            Provider::Ollama => CLIENT
+                .post(format!("{}/api/embeddings", self.base_url))
+                .json(&json!({ "model": model, "prompt": text })),
+            Provider::Gemini => CLIENT
+                .post(format!("{}/models/{}:embedContent", self.base_url, model))
+                .header("x-goog-api-key", self.require_key().map_err(|e| (e, false))?)
+                .json(&json!({ "content": { "parts": [{ "text": text }] } })),
+            Provider::OpenAi => {
+                let request = CLIENT
+                    .post(format!("{}/embeddings", self.base_url))
+                    .json(&json!({ "model": model, "input": text }));
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            }
+            Provider::Anthropic => {
+                return Err(("Anthropic has no embeddings API; use another provider for ai.embed".to_string(), false))
+            }
+        };
+        let response = request.timeout(self.timeout).send().await.map_err(|e| (e.to_string(), true))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err((format!("status {}: {}", status, body), retryable(status)));
+        }
+        let reply: JsonValue = response.json().await.map_err(|e| (e.to_string(), true))?;
+        let vector = match self.provider {
+            Provider::Ollama => &reply["embedding"],
+            Provider::Gemini => &reply["embedding"]["values"],
+            _ => &reply["data"][0]["embedding"],
+        };
+        vector
+            .as_array()
+            .map(|items| items.iter().filter_map(JsonValue::as_f64).collect())
+            .ok_or_else(|| (format!("no embedding in the reply: {}", reply), false))
+    }
+
+    /// One attempt; a failure carries whether it is worth retrying.
+    async fn stream_once(&self, prompt: &str, json: bool, on_text: &mut impl FnMut(&str)) -> Result<String, (String, bool)> {
+        let response = self.request(prompt, json).map_err(|e| (e, false))?.send().await.map_err(|e| {
+            let hint = if self.provider == Provider::Ollama { " (is Ollama running?)" } else { "" };
+            (format!("{}{}", e, hint), true)
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err((format!("status {}: {}", status, body), retryable(status)));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut pending: Vec<u8> = Vec::new();
+        let mut text = String::new();
+        let mut take_line = |line: &[u8], text: &mut String| {
+            if let Some(piece) = self.stream_piece(String::from_utf8_lossy(line).trim()) {
+                text.push_str(&piece);
+                on_text(text);
+            }
+        };
+        while let Some(chunk) = stream.next().await {
+            pending.extend_from_slice(&chunk.map_err(|e| (e.to_string(), true))?);
+            while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                take_line(&line, &mut text);
+            }
+        }
+        take_line(&pending, &mut text);
+        Ok(text)
+    }
+}
+
+/// Whether a failed response is worth retrying.
+fn retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn ai_sections_pick_the_provider_and_settings() {
+        let doc = parse_rune(
+            "#!RUNE\n@Ai\nprovider = openai\nmodel = local-llama\nbase_url = http://localhost:8000/v1/\napi_key = secret\ntimeout = 5s\nretries = 0\n",
+        )
+        .unwrap();
+        let config = AiConfig::from_doc(&doc, None).unwrap();
+        assert_eq!(config.provider, Provider::OpenAi);
+        assert_eq!(config.model, "local-llama");
+        assert_eq!(config.base_url, "http://localhost:8000/v1");
+        assert_eq!(config.api_key.as_deref(), Some("secret"));
+        assert_eq!((config.timeout, config.retries), (Duration::from_secs(5), 0));
+        assert_eq!(AiConfig::from_doc(&doc, Some("other")).unwrap().model, "other");
+        assert_eq!(Provider::for_model("claude-3-5-haiku-latest"), Provider::Anthropic);
+        assert_eq!(Provider::for_model("gemini-1.5-flash"), Provider::Gemini);
+        assert!(Provider::parse("watson").is_err());
+    }
+
+    #[test]
+    fn streamed_lines_are_read_per_provider() {
+        let config = |provider| AiConfig {
+            provider,
+            model: String::new(),
+            embedding_model: String::new(),
+            base_url: String::new(),
+            api_key: None,
+            timeout: DEFAULT_TIMEOUT,
+            retries: 0,
+        };
+        let piece = |provider, line: &str| config(provider).stream_piece(line);
+        assert_eq!(piece(Provider::Ollama, r#"{"response":"Hel","done":false}"#).as_deref(), Some("Hel"));
+        assert_eq!(piece(Provider::OpenAi, r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#).as_deref(), Some("lo"));
+        assert_eq!(piece(Provider::OpenAi, "data: [DONE]"), None);
+        assert_eq!(
+            piece(Provider::Anthropic, r#"data: {"type":"content_block_delta","delta":{"type":"text_delta","text":"!"}}"#)
+                .as_deref(),
+            Some("!")
+        );
+        assert_eq!(piece(Provider::Anthropic, "event: message_stop"), None);
+        assert_eq!(
+            piece(Provider::Gemini, r#"data: {"candidates":[{"content":{"parts":[{"text":"Hi"}]}}]}"#).as_deref(),
+            Some("Hi")
+        );
+    }
+}
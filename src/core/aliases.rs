@@ -0,0 +1,110 @@
+//! Renamed builtins and sections. Old names keep working, logging a deprecation warning
+//! (once per name) with the current spelling; `vectrune upgrade` rewrites them in place.
+
+use crate::util::{log, LogLevel};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alias {
+    pub old: &'static str,
+    pub current: &'static str,
+}
+
+/// Builtins that were renamed, old name first.
+pub const BUILTIN_ALIASES: &[Alias] = &[
+    Alias { old: "set-memory", current: "memory.set" },
+    Alias { old: "get-memory", current: "memory.get" },
+    Alias { old: "clear-memory", current: "memory.clear" },
+    Alias { old: "del-memory", current: "memory.del" },
+    Alias { old: "broadcast-websocket", current: "ws.broadcast" },
+];
+
+/// Section names with a different casing in older documents; matched ignoring case.
+pub const SECTION_ALIASES: &[Alias] = &[
+    Alias { old: "Datasource", current: "DataSource" },
+    Alias { old: "Graphql", current: "GraphQL" },
+];
+
+static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+pub fn builtin_alias(name: &str) -> Option<&'static Alias> {
+    BUILTIN_ALIASES.iter().find(|a| a.old == name)
+}
+
+pub fn section_alias(name: &str) -> Option<&'static Alias> {
+    SECTION_ALIASES
+        .iter()
+        .find(|a| name != a.current && name.eq_ignore_ascii_case(a.current))
+}
+
+/// Logs that `used` is deprecated in favour of `current`, once per name.
+pub fn warn_deprecated(kind: &str, used: &str, current: &str) {
+    if WARNED.lock().unwrap().insert(format!("{}:{}", kind, used)) {
+        log(
+            LogLevel::Warn,
+            &format!(
+                "deprecated {} `{}`: use `{}` instead (`vectrune upgrade <file>` rewrites it)",
+                kind, used, current
+            ),
+        );
+    }
+}
+
+/// Where the command of a step line starts: after `name = ` when the step assigns.
+fn command_offset(trimmed: &str) -> usize {
+    match trimmed.split_once(" = ") {
+        Some((target, _)) if !target.is_empty() && !target.contains(char::is_whitespace) => target.len() + 3,
+        _ => 0,
+    }
+}
+
+/// Rewrites deprecated section headers and step builtins in rune source. Returns the new
+/// text and a description of each change.
+pub fn upgrade_source(source: &str) -> (String, Vec<String>) {
+    let mut changes = Vec::new();
+    let mut out = String::with_capacity(source.len());
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[body.len()..];
+        let indent = body.len() - body.trim_start().len();
+        let trimmed = &body[indent..];
+        let mut upgraded = body.to_string();
+
+        if let Some(header) = trimmed.strip_prefix('@') {
+            let name = header.split('/').next().unwrap_or("").trim();
+            if let Some(alias) = section_alias(name) {
+                upgraded = format!("{}@{}{}", &body[..indent], alias.current, &header[name.len()..]);
+                changes.push(format!("line {}: @{} -> @{}", index + 1, name, alias.current));
+            }
+        } else if indent > 0 && !trimmed.starts_with('#') {
+            let start = command_offset(trimmed);
+            let command = trimmed[start..].split_whitespace().next().unwrap_or("");
+            if let Some(alias) = builtin_alias(command) {
+                let at = indent + start;
+                upgraded = format!("{}{}{}", &body[..at], alias.current, &body[at + command.len()..]);
+                changes.push(format!("line {}: {} -> {}", index + 1, alias.old, alias.current));
+            }
+        }
+        out.push_str(&upgraded);
+        out.push_str(ending);
+    }
+    (out, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_sections_and_builtins() {
+        let source = "@Datasource/db\ntype = memory\n\n@Route/GET /x\nrun:\n    set-memory hits 1\n    n = get-memory hits\n    # set-memory stays in comments\n    respond 200 n\n";
+        let (upgraded, changes) = upgrade_source(source);
+        assert_eq!(
+            upgraded,
+            "@DataSource/db\ntype = memory\n\n@Route/GET /x\nrun:\n    memory.set hits 1\n    n = memory.get hits\n    # set-memory stays in comments\n    respond 200 n\n"
+        );
+        assert_eq!(changes.len(), 3);
+        assert_eq!(upgrade_source(&upgraded).1, Vec::<String>::new());
+    }
+}
@@ -0,0 +1,83 @@
+//! `@Const` and `@Enum/<Name>` sections.
+//!
+//! ```rune
+//! @Const
+//! max_page = 100
+//!
+//! @Enum/Status
+//! values = (active suspended closed)
+//! ```
+//!
+//! Constants are visible to steps by name (`max_page`) and under `const` (`const.max_page`).
+//! Each enum is visible as an object mapping its members to themselves, so `Status.active`
+//! resolves to `"active"`; schema fields may use the enum name as their type.
+
+use super::number_to_json;
+use crate::builtins::Context;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+
+pub(crate) fn to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Number(n) => number_to_json(*n),
+        Value::List(items) => JsonValue::Array(items.iter().map(to_json).collect()),
+        Value::Map(m) => JsonValue::Object(m.iter().map(|(k, v)| (k.clone(), to_json(v))).collect()),
+        other => other.to_json(),
+    }
+}
+
+/// All `@Const` values, merged across sections.
+pub fn constants(doc: &RuneDocument) -> Map<String, JsonValue> {
+    let mut consts = Map::new();
+    for section in doc.sections.iter().filter(|s| s.path.first().map(String::as_str) == Some("Const")) {
+        for (name, value) in &section.kv {
+            consts.insert(name.clone(), to_json(value));
+        }
+        for (name, items) in &section.series {
+            consts.insert(name.clone(), JsonValue::Array(items.iter().map(to_json).collect()));
+        }
+    }
+    consts
+}
+
+/// All `@Enum/<Name>` sections with their members, from `values = (...)` or a `values:` series.
+pub fn enums(doc: &RuneDocument) -> HashMap<String, Vec<JsonValue>> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Enum"))
+        .filter_map(|s| Some((s.path.get(1)?.clone(), enum_members(s))))
+        .collect()
+}
+
+/// The members of an enum section, from `values = (...)` or a `values:` series.
+pub fn enum_members(section: &Section) -> Vec<JsonValue> {
+    match (section.kv.get("values"), section.series.get("values")) {
+        (Some(Value::List(items)), _) => items.iter().map(to_json).collect(),
+        (_, Some(items)) => items.iter().map(to_json).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Members of the named enum, if the document declares it.
+pub fn enum_values(doc: &RuneDocument, name: &str) -> Option<Vec<JsonValue>> {
+    enums(doc).remove(name)
+}
+
+/// Seeds a step context with constants and enums. Request values added afterwards win.
+pub fn seed_context(doc: &RuneDocument, ctx: &mut Context) {
+    let consts = constants(doc);
+    for (name, value) in &consts {
+        ctx.insert(name.clone(), value.clone());
+    }
+    if !consts.is_empty() {
+        ctx.insert("const".to_string(), JsonValue::Object(consts));
+    }
+    for (name, values) in enums(doc) {
+        let members = values
+            .into_iter()
+            .map(|v| (v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()), v))
+            .collect();
+        ctx.insert(name, JsonValue::Object(members));
+    }
+}
@@ -0,0 +1,118 @@
+use super::resolve_path;
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::Context;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use http::StatusCode;
+use serde_json::Value as JsonValue;
+
+/// Context key set when a builtin returns `Error`, holding the error message.
+pub const STEP_ERROR: &str = "___step_error___";
+
+const DEFAULT_ERROR_CONTENT_TYPE: &str = "application/json";
+
+/// The `error` object exposed to `on_error:` steps and `@Errors` templates.
+pub fn error_object(status: u16, message: &str) -> JsonValue {
+    let title = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Error");
+    serde_json::json!({
+        "status": status,
+        "title": title,
+        "message": message,
+    })
+}
+
+/// The message of an error response: the body's `message` (or `error`) when it is a JSON
+/// object, the text of a JSON string, or the body itself; the status's reason when empty.
+pub fn response_message(status: u16, body: &str) -> String {
+    let message = match serde_json::from_str::<JsonValue>(body) {
+        Ok(JsonValue::Object(obj)) => ["message", "error"]
+            .iter()
+            .find_map(|key| obj.get(*key).and_then(JsonValue::as_str))
+            .map(str::to_string),
+        Ok(JsonValue::String(text)) => Some(text),
+        _ => None,
+    };
+    message
+        .or_else(|| (!body.trim().is_empty()).then(|| body.to_string()))
+        .unwrap_or_else(|| {
+            StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("Error")
+                .to_string()
+        })
+}
+
+/// The body of a `409` for a unique value that is already taken: the `error_object`
+/// fields plus the `field` and `value`, when the datasource reports them.
+pub fn conflict_body(field: Option<&str>, value: Option<JsonValue>) -> String {
+    let message = match (field, &value) {
+        (Some(field), Some(JsonValue::String(value))) => format!("a record with {} '{}' already exists", field, value),
+        (Some(field), Some(value)) => format!("a record with {} {} already exists", field, value),
+        _ => "a record with these values already exists".to_string(),
+    };
+    let mut body = error_object(409, &message);
+    body["field"] = field.map(JsonValue::from).unwrap_or(JsonValue::Null);
+    body["value"] = value.unwrap_or(JsonValue::Null);
+    body.to_string()
+}
+
+/// The document's `@Errors` section, if any.
+pub fn errors_section(doc: &RuneDocument) -> Option<&Section> {
+    doc.sections
+        .iter()
+        .find(|s| s.path.first().map(|p| p.as_str()) == Some("Errors"))
+}
+
+/// Renders a template value using `{expr}` placeholders, as `log` does. A string that
+/// is exactly one placeholder keeps the resolved JSON type so `status = {error.status}`
+/// stays numeric.
+fn render_template_value(value: &Value, ctx: &Context) -> JsonValue {
+    match value {
+        Value::String(s) => {
+            let trimmed = s.trim();
+            if let Some(path) = trimmed
+                .strip_prefix('{')
+                .and_then(|p| p.strip_suffix('}'))
+                .filter(|p| !p.contains(['{', '}']))
+            {
+                if let Some(v) = resolve_path(ctx, path.trim(), None) {
+                    return v;
+                }
+            }
+            JsonValue::String(expand_log_message(s, ctx))
+        }
+        Value::Map(m) => JsonValue::Object(
+            m.iter()
+                .map(|(k, v)| (k.clone(), render_template_value(v, ctx)))
+                .collect(),
+        ),
+        Value::List(items) => {
+            JsonValue::Array(items.iter().map(|v| render_template_value(v, ctx)).collect())
+        }
+        other => serde_json::to_value(other).unwrap_or(JsonValue::Null),
+    }
+}
+
+/// Renders the `@Errors` body for a status: the entry keyed by the status code, or
+/// `default`. Map entries are serialized as JSON objects, strings are used as-is.
+/// Returns the body and the section's `content_type`.
+pub fn render_error_body(section: &Section, status: u16, ctx: &Context) -> Option<(String, String)> {
+    let template = section
+        .kv
+        .get(&status.to_string())
+        .or_else(|| section.kv.get("default"))?;
+    let body = match render_template_value(template, ctx) {
+        JsonValue::String(s) => s,
+        other => serde_json::to_string(&other).unwrap_or_default(),
+    };
+    let content_type = section
+        .kv
+        .get("content_type")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_ERROR_CONTENT_TYPE)
+        .to_string();
+    Some((body, content_type))
+}
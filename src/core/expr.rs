@@ -0,0 +1,626 @@
+//! Step expressions, parsed with a Pratt parser instead of being split on whitespace.
+//!
+//! An expression is built from:
+//! - literals: numbers, `"strings"` / `'strings'` (escapes as in `tokenizer::unquote`),
+//!   `true`, `false`, `null` and lists such as `[1, "two", x]`;
+//! - context paths such as `body.name`, `users[i + 1].email` or `it.id`;
+//! - operators, loosest first: `or` / `||`, `and` / `&&`, comparisons (`==`, `!=`, `<`,
+//!   `>`, `<=`, `>=`, `contains`), `+` / `-`, `*` / `/` / `%`, then prefix `-` and
+//!   `not` / `!`; parentheses group;
+//! - calls: `date.format(when, "YYYY")` calls a builtin, `line_total(price, qty)` a
+//!   `@Function`, and `users.filter(it.active)` a method on a path. Calls chain, as in
+//!   `users.filter(it.active).map(it.name)`.
+//!
+//! Step text that is not a single expression, such as the builtin call `csv.read "x.csv"`,
+//! does not parse here and runs as before.
+
+use super::{json_value_as_f64, number_to_json, resolve_path, tokenizer, AppState};
+use crate::builtins::builtin::collection::is_truthy;
+use crate::builtins::builtin::function::function_section;
+use crate::builtins::{call_builtin, is_builtin, BuiltinResult, Context};
+use async_recursion::async_recursion;
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(JsonValue),
+    Path(String),
+    List(Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `name(args)`, where `name` is a builtin or a path ending in a method name.
+    Call(String, Vec<Arg>),
+    /// `.name(args)` on a value that is not a path, such as another call's result.
+    Method(Box<Expr>, String, Vec<Arg>),
+}
+
+/// A call argument, with its text for builtins that read their arguments unevaluated
+/// (`users.filter(it.age > 30)`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub expr: Expr,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Contains,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+impl BinaryOp {
+    /// Binding power; higher binds tighter.
+    fn power(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Gt
+            | BinaryOp::Le
+            | BinaryOp::Ge
+            | BinaryOp::Contains => 3,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 5,
+        }
+    }
+}
+
+const PREFIX_POWER: u8 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(BinaryOp),
+    Not,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Dot,
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+/// Splits `src` into tokens with their byte spans.
+fn lex(src: &str) -> Result<Vec<(Token, usize, usize)>, String> {
+    let chars: Vec<(usize, char)> = src.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|(_, c)| *c);
+    let offset = |i: usize| chars.get(i).map(|(o, _)| *o).unwrap_or(src.len());
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(c) = at(i) {
+        let start = offset(i);
+        let two: String = chars[i..].iter().take(2).map(|(_, c)| c).collect();
+        let op = match two.as_str() {
+            "==" => Some(BinaryOp::Eq),
+            "!=" => Some(BinaryOp::Ne),
+            "<=" => Some(BinaryOp::Le),
+            ">=" => Some(BinaryOp::Ge),
+            "&&" => Some(BinaryOp::And),
+            "||" => Some(BinaryOp::Or),
+            _ => None,
+        };
+        if let Some(op) = op {
+            tokens.push((Token::Op(op), start, offset(i + 2)));
+            i += 2;
+            continue;
+        }
+        let single = match c {
+            '+' => Some(Token::Op(BinaryOp::Add)),
+            '-' => Some(Token::Op(BinaryOp::Sub)),
+            '*' => Some(Token::Op(BinaryOp::Mul)),
+            '/' => Some(Token::Op(BinaryOp::Div)),
+            '%' => Some(Token::Op(BinaryOp::Mod)),
+            '<' => Some(Token::Op(BinaryOp::Lt)),
+            '>' => Some(Token::Op(BinaryOp::Gt)),
+            '!' => Some(Token::Not),
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            '[' => Some(Token::LBracket),
+            ']' => Some(Token::RBracket),
+            ',' => Some(Token::Comma),
+            '.' if at(i + 1).is_some_and(is_ident_start) => Some(Token::Dot),
+            _ => None,
+        };
+        if let Some(token) = single {
+            tokens.push((token, start, offset(i + 1)));
+            i += 1;
+            continue;
+        }
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '"' | '\'' => {
+                let mut j = i + 1;
+                let mut escaped = false;
+                loop {
+                    match at(j) {
+                        None => return Err(format!("unterminated string in: {}", src)),
+                        Some('\\') if !escaped => escaped = true,
+                        Some(q) if q == c && !escaped => break,
+                        Some(_) => escaped = false,
+                    }
+                    j += 1;
+                }
+                let text = &src[start..offset(j + 1)];
+                let value = tokenizer::unquote(text).ok_or_else(|| format!("invalid string: {}", text))?;
+                tokens.push((Token::Str(value), start, offset(j + 1)));
+                i = j + 1;
+            }
+            '0'..='9' => {
+                let mut j = i;
+                while at(j).is_some_and(|d| d.is_ascii_digit() || (d == '.' && at(j + 1).is_some_and(|n| n.is_ascii_digit()))) {
+                    j += 1;
+                }
+                let text = &src[start..offset(j)];
+                let n = text.parse::<f64>().map_err(|_| format!("invalid number: {}", text))?;
+                tokens.push((Token::Number(n), start, offset(j)));
+                i = j;
+            }
+            c if is_ident_start(c) => {
+                // A path: `a.b`, `users[i + 1].name`; `parse-json` style names keep their hyphen
+                let mut j = i;
+                let mut depth = 0usize;
+                while let Some(d) = at(j) {
+                    let hyphenated = d == '-'
+                        && depth == 0
+                        && at(j.wrapping_sub(1)).is_some_and(|p| p.is_ascii_alphabetic() || p == '_')
+                        && at(j + 1).is_some_and(|n| n.is_ascii_alphabetic() || n == '_');
+                    let dotted = d == '.' && depth == 0 && at(j + 1).is_some_and(|n| n.is_ascii_alphanumeric() || n == '_');
+                    match d {
+                        _ if hyphenated || dotted => {}
+                        '[' => depth += 1,
+                        ']' if depth > 0 => depth -= 1,
+                        _ if depth > 0 => {}
+                        d if d.is_ascii_alphanumeric() || d == '_' || d == '$' => {}
+                        _ => break,
+                    }
+                    j += 1;
+                }
+                if depth > 0 {
+                    return Err(format!("unclosed bracket in: {}", src));
+                }
+                let word = &src[start..offset(j)];
+                let token = match word {
+                    "and" => Token::Op(BinaryOp::And),
+                    "or" => Token::Op(BinaryOp::Or),
+                    "contains" => Token::Op(BinaryOp::Contains),
+                    "not" => Token::Not,
+                    _ => Token::Ident(word.to_string()),
+                };
+                tokens.push((token, start, offset(j)));
+                i = j;
+            }
+            other => return Err(format!("unexpected character `{}` in: {}", other, src)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            other => Err(format!("expected {:?}, found {:?} in: {}", expected, other, self.src)),
+        }
+    }
+
+    fn expr(&mut self, min_power: u8) -> Result<Expr, String> {
+        let mut lhs = match self.next() {
+            Some(Token::Number(n)) => Expr::Literal(number_to_json(n)),
+            Some(Token::Str(s)) => Expr::Literal(JsonValue::String(s)),
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Expr::Literal(JsonValue::Bool(true)),
+                "false" => Expr::Literal(JsonValue::Bool(false)),
+                "null" => Expr::Literal(JsonValue::Null),
+                _ if self.peek() == Some(&Token::LParen) => {
+                    let args = self.args()?;
+                    Expr::Call(word, args)
+                }
+                _ => Expr::Path(word),
+            },
+            Some(Token::Op(BinaryOp::Sub)) => Expr::Unary(UnaryOp::Neg, Box::new(self.expr(PREFIX_POWER)?)),
+            Some(Token::Op(BinaryOp::Add)) => self.expr(PREFIX_POWER)?,
+            Some(Token::Not) => Expr::Unary(UnaryOp::Not, Box::new(self.expr(PREFIX_POWER)?)),
+            Some(Token::LParen) => {
+                let inner = self.expr(0)?;
+                self.expect(Token::RParen)?;
+                inner
+            }
+            Some(Token::LBracket) => {
+                let mut items = Vec::new();
+                while self.peek() != Some(&Token::RBracket) {
+                    items.push(self.expr(0)?);
+                    if self.peek() != Some(&Token::Comma) {
+                        break;
+                    }
+                    self.next();
+                }
+                self.expect(Token::RBracket)?;
+                Expr::List(items)
+            }
+            other => return Err(format!("unexpected {:?} in: {}", other, self.src)),
+        };
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.next();
+                    let Some(Token::Ident(name)) = self.next() else {
+                        return Err(format!("expected a method name in: {}", self.src));
+                    };
+                    if self.peek() != Some(&Token::LParen) {
+                        return Err(format!("expected `(` after .{} in: {}", name, self.src));
+                    }
+                    let args = self.args()?;
+                    lhs = Expr::Method(Box::new(lhs), name, args);
+                }
+                Some(Token::Op(op)) if op.power() > min_power => {
+                    let op = *op;
+                    self.next();
+                    let rhs = self.expr(op.power())?;
+                    lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    /// A parenthesized, comma-separated argument list.
+    fn args(&mut self) -> Result<Vec<Arg>, String> {
+        self.expect(Token::LParen)?;
+        let mut args = Vec::new();
+        while self.peek() != Some(&Token::RParen) {
+            let start = self.tokens.get(self.pos).map(|(_, s, _)| *s).unwrap_or(self.src.len());
+            let expr = self.expr(0)?;
+            let end = self.tokens.get(self.pos - 1).map(|(_, _, e)| *e).unwrap_or(self.src.len());
+            args.push(Arg { expr, source: self.src[start..end].to_string() });
+            if self.peek() != Some(&Token::Comma) {
+                break;
+            }
+            self.next();
+        }
+        self.expect(Token::RParen)?;
+        Ok(args)
+    }
+}
+
+/// Parses `src` as one expression.
+pub fn parse(src: &str) -> Result<Expr, String> {
+    let mut parser = Parser { src, tokens: lex(src)?, pos: 0 };
+    let expr = parser.expr(0)?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(t) => Err(format!("unexpected {:?} in: {}", t, src)),
+    }
+}
+
+impl Expr {
+    /// Whether evaluating this does more than read a value, so a step holding it is
+    /// run by the evaluator rather than as a copy or a builtin call.
+    pub fn is_compound(&self) -> bool {
+        !matches!(self, Expr::Literal(_) | Expr::Path(_))
+    }
+
+    /// Whether this reads `it`, the current item of a collection builtin.
+    pub fn mentions_it(&self) -> bool {
+        match self {
+            Expr::Literal(_) => false,
+            Expr::Path(p) => p == "it" || p.starts_with("it.") || p.starts_with("it["),
+            Expr::List(items) => items.iter().any(Expr::mentions_it),
+            Expr::Unary(_, e) => e.mentions_it(),
+            Expr::Binary(_, l, r) => l.mentions_it() || r.mentions_it(),
+            Expr::Call(_, args) => args.iter().any(|a| a.expr.mentions_it()),
+            Expr::Method(t, _, args) => t.mentions_it() || args.iter().any(|a| a.expr.mentions_it()),
+        }
+    }
+
+    /// Whether evaluating this runs a builtin.
+    pub fn has_calls(&self) -> bool {
+        match self {
+            Expr::Literal(_) | Expr::Path(_) => false,
+            Expr::List(items) => items.iter().any(Expr::has_calls),
+            Expr::Unary(_, e) => e.has_calls(),
+            Expr::Binary(_, l, r) => l.has_calls() || r.has_calls(),
+            Expr::Call(..) | Expr::Method(..) => true,
+        }
+    }
+
+    /// Evaluates without calls, for conditions that only read the context.
+    pub fn eval_pure(&self, ctx: &Context, it: Option<&JsonValue>) -> Result<JsonValue, String> {
+        match self {
+            Expr::Literal(v) => Ok(v.clone()),
+            Expr::Path(path) => Ok(resolve_path(ctx, path, it).unwrap_or(JsonValue::Null)),
+            Expr::List(items) => items.iter().map(|e| e.eval_pure(ctx, it)).collect::<Result<_, _>>().map(JsonValue::Array),
+            Expr::Unary(op, e) => unary(*op, e.eval_pure(ctx, it)?),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), l, r) => {
+                let l = is_truthy(&l.eval_pure(ctx, it)?);
+                if short_circuits(*op, l) {
+                    return Ok(JsonValue::Bool(l));
+                }
+                Ok(JsonValue::Bool(is_truthy(&r.eval_pure(ctx, it)?)))
+            }
+            Expr::Binary(op, l, r) => binary(*op, l.eval_pure(ctx, it)?, r.eval_pure(ctx, it)?),
+            Expr::Call(name, _) | Expr::Method(_, name, _) => Err(format!("{}(...) cannot be called here", name)),
+        }
+    }
+
+    /// Evaluates, running calls through `call_builtin`.
+    #[async_recursion]
+    pub async fn eval(&self, state: &AppState, ctx: &mut Context) -> Result<JsonValue, String> {
+        match self {
+            Expr::Literal(_) | Expr::Path(_) => self.eval_pure(ctx, None),
+            Expr::List(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(item.eval(state, ctx).await?);
+                }
+                Ok(JsonValue::Array(values))
+            }
+            Expr::Unary(op, e) => unary(*op, e.eval(state, ctx).await?),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), l, r) => {
+                let l = is_truthy(&l.eval(state, ctx).await?);
+                if short_circuits(*op, l) {
+                    return Ok(JsonValue::Bool(l));
+                }
+                Ok(JsonValue::Bool(is_truthy(&r.eval(state, ctx).await?)))
+            }
+            Expr::Binary(op, l, r) => {
+                let l = l.eval(state, ctx).await?;
+                binary(*op, l, r.eval(state, ctx).await?)
+            }
+            Expr::Call(name, args) => {
+                // `users.max(it.id)`: a method on the value at a path, unless the name is a builtin
+                let method = name
+                    .rsplit_once('.')
+                    .filter(|_| !is_builtin(name))
+                    .and_then(|(path, method)| Some((resolve_path(ctx, path, None)?, method)));
+                match method {
+                    Some((target, method)) => call_method(state, ctx, target, method, args).await,
+                    // `line_total(price, qty)` runs `@Function/line_total(price, qty)`
+                    None if !is_builtin(name) && function_section(&state.doc, name).is_some() => {
+                        let callee = Arg { expr: Expr::Path(name.clone()), source: name.clone() };
+                        let args: Vec<Arg> = std::iter::once(callee).chain(args.iter().cloned()).collect();
+                        call(state, ctx, "call", &args).await
+                    }
+                    None => call(state, ctx, name, args).await,
+                }
+            }
+            Expr::Method(target, method, args) => {
+                let target = target.eval(state, ctx).await?;
+                call_method(state, ctx, target, method, args).await
+            }
+        }
+    }
+}
+
+fn short_circuits(op: BinaryOp, lhs: bool) -> bool {
+    (op == BinaryOp::Or) == lhs
+}
+
+/// Runs `method` with the target bound to a temporary, as `<temp>.<method>`.
+async fn call_method(
+    state: &AppState,
+    ctx: &mut Context,
+    target: JsonValue,
+    method: &str,
+    args: &[Arg],
+) -> Result<JsonValue, String> {
+    let temp = temp_name(ctx);
+    ctx.insert(temp.clone(), target);
+    let result = call(state, ctx, &format!("{}.{}", temp, method), args).await;
+    ctx.remove(&temp);
+    result
+}
+
+async fn call(state: &AppState, ctx: &mut Context, name: &str, args: &[Arg]) -> Result<JsonValue, String> {
+    let mut temps = Vec::new();
+    let mut tokens = Vec::with_capacity(args.len());
+    for arg in args {
+        match &arg.expr {
+            // Conditions and projections over `it` are read by the builtin for each item,
+            // tokenized as they would be written after the builtin's name
+            e if e.mentions_it() || !e.is_compound() => tokens.extend(tokenizer::tokenize(&arg.source)),
+            e => match e.eval(state, ctx).await {
+                Ok(value) => tokens.push(value_token(ctx, value, &mut temps)),
+                Err(err) => {
+                    for temp in temps {
+                        ctx.remove(&temp);
+                    }
+                    return Err(err);
+                }
+            },
+        }
+    }
+    let result_var = temp_name(ctx);
+    let res = Box::pin(call_builtin(name, &tokens, ctx, state, Some(&result_var))).await;
+    let value = ctx.remove(&result_var).unwrap_or(JsonValue::Null);
+    for temp in temps {
+        ctx.remove(&temp);
+    }
+    match res {
+        BuiltinResult::Ok => Ok(value),
+        BuiltinResult::Respond(status, body) => Err(format!("{}: {} {}", name, status, body)),
+        BuiltinResult::Error(e) => Err(format!("{}: {}", name, e)),
+    }
+}
+
+
+fn temp_name(ctx: &Context) -> String {
+    (ctx.len()..).map(|n| format!("___expr_{}___", n)).find(|n| !ctx.contains_key(n)).unwrap_or_default()
+}
+
+/// A builtin argument token for an evaluated value: strings are quoted and scalars written
+/// out, while lists and objects are bound to a temporary whose name is recorded in `temps`.
+pub fn value_token(ctx: &mut Context, value: JsonValue, temps: &mut Vec<String>) -> String {
+    match value {
+        JsonValue::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        JsonValue::Array(_) | JsonValue::Object(_) => {
+            let temp = temp_name(ctx);
+            ctx.insert(temp.clone(), value);
+            temps.push(temp.clone());
+            temp
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+fn unary(op: UnaryOp, value: JsonValue) -> Result<JsonValue, String> {
+    match op {
+        UnaryOp::Not => Ok(JsonValue::Bool(!is_truthy(&value))),
+        UnaryOp::Neg => number(&value).map(|n| number_to_json(-n)),
+    }
+}
+
+fn number(value: &JsonValue) -> Result<f64, String> {
+    match value {
+        JsonValue::Null => Err("expected a number, found null".to_string()),
+        other => json_value_as_f64(other).ok_or_else(|| format!("expected a number, found {}", other)),
+    }
+}
+
+/// A value's text: strings as they are, anything else as JSON.
+fn text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn binary(op: BinaryOp, l: JsonValue, r: JsonValue) -> Result<JsonValue, String> {
+    let ordering = || loose_cmp(&l, &r);
+    let result = match op {
+        BinaryOp::Eq => JsonValue::Bool(ordering() == Some(Ordering::Equal)),
+        BinaryOp::Ne => JsonValue::Bool(ordering() != Some(Ordering::Equal)),
+        BinaryOp::Lt => JsonValue::Bool(ordering() == Some(Ordering::Less)),
+        BinaryOp::Gt => JsonValue::Bool(ordering() == Some(Ordering::Greater)),
+        BinaryOp::Le => JsonValue::Bool(matches!(ordering(), Some(Ordering::Less | Ordering::Equal))),
+        BinaryOp::Ge => JsonValue::Bool(matches!(ordering(), Some(Ordering::Greater | Ordering::Equal))),
+        BinaryOp::Contains => JsonValue::Bool(text(&l).to_lowercase().contains(&text(&r).to_lowercase())),
+        // Numbers (and numeric strings) add; otherwise lists concatenate and text joins
+        BinaryOp::Add => match (&l, &r) {
+            _ if number(&l).is_ok() && number(&r).is_ok() => number_to_json(number(&l)? + number(&r)?),
+            (JsonValue::Array(a), JsonValue::Array(b)) => JsonValue::Array(a.iter().chain(b).cloned().collect()),
+            (JsonValue::String(_), _) | (_, JsonValue::String(_)) => JsonValue::String(text(&l) + &text(&r)),
+            _ => return Err(format!("cannot add {} and {}", l, r)),
+        },
+        BinaryOp::Sub => number_to_json(number(&l)? - number(&r)?),
+        BinaryOp::Mul => number_to_json(number(&l)? * number(&r)?),
+        BinaryOp::Div => number_to_json(number(&l)? / number(&r)?),
+        BinaryOp::Mod => number_to_json(number(&l)? % number(&r)?),
+        BinaryOp::And | BinaryOp::Or => {
+            let l = is_truthy(&l);
+            JsonValue::Bool(if short_circuits(op, l) { l } else { is_truthy(&r) })
+        }
+    };
+    Ok(result)
+}
+
+/// Compares with loose numeric equality: numeric strings compare as numbers and
+/// `"true"` / `"false"` as booleans, as CSV and query values arrive as text.
+pub(crate) fn loose_cmp(a: &JsonValue, b: &JsonValue) -> Option<Ordering> {
+    use serde_json::Value::*;
+    match (a, b) {
+        (Number(na), Number(nb)) => match (na.as_f64(), nb.as_f64()) {
+            (Some(na), Some(nb)) => na.partial_cmp(&nb),
+            _ => None,
+        },
+        (String(sa), String(sb)) => {
+            if let (Ok(na), Ok(nb)) = (sa.parse::<f64>(), sb.parse::<f64>()) {
+                na.partial_cmp(&nb)
+            } else {
+                sa.partial_cmp(sb)
+            }
+        }
+        (Number(na), String(sb)) => sb.parse::<f64>().ok().and_then(|nb| na.as_f64()?.partial_cmp(&nb)),
+        (String(sa), Number(nb)) => sa.parse::<f64>().ok().and_then(|na| na.partial_cmp(&nb.as_f64()?)),
+        (Bool(ba), Bool(bb)) => ba.partial_cmp(bb),
+        (String(sa), Bool(bb)) => sa.parse::<bool>().ok().and_then(|ba| ba.partial_cmp(bb)),
+        (Bool(ba), String(sb)) => sb.parse::<bool>().ok().and_then(|bb| ba.partial_cmp(&bb)),
+        (Null, Null) => Some(Ordering::Equal),
+        _ => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(src: &str, ctx: &Context) -> JsonValue {
+        parse(src).unwrap().eval_pure(ctx, None).unwrap()
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        let ctx: Context = [("a".to_string(), json!(2)), ("name".to_string(), json!("Ada Lovelace"))].into();
+        assert_eq!(eval("1 + a * 3", &ctx), json!(7));
+        assert_eq!(eval("(1 + a) * 3", &ctx), json!(9));
+        assert_eq!(eval("-a - -1", &ctx), json!(-1));
+        assert_eq!(eval("a * 2 > 3 and not (a == 3)", &ctx), json!(true));
+        assert_eq!(eval("a > 5 || name contains \"love\"", &ctx), json!(true));
+        assert_eq!(eval("\"Hi, \" + name + \"!\"", &ctx), json!("Hi, Ada Lovelace!"));
+        assert_eq!(eval("[1, a] + [3]", &ctx), json!([1, 2, 3]));
+        assert_eq!(eval("name == \"Ada Lovelace\"", &ctx), json!(true));
+    }
+
+    #[test]
+    fn calls_keep_their_argument_text() {
+        let Expr::Method(target, method, args) = parse("users.filter(it.age > 30, \"a, b\").map(it.name)").unwrap() else {
+            panic!("expected a method call");
+        };
+        assert_eq!(method, "map");
+        assert_eq!(args[0].source, "it.name");
+        let Expr::Call(name, args) = *target else { panic!("expected a call") };
+        assert_eq!(name, "users.filter");
+        assert_eq!(args.iter().map(|a| a.source.as_str()).collect::<Vec<_>>(), ["it.age > 30", "\"a, b\""]);
+    }
+
+    #[test]
+    fn builtin_steps_are_not_expressions() {
+        assert!(parse("csv.read \"users.csv\"").is_err());
+        assert!(parse("books.max it.id + 1").is_err());
+        assert!(!parse("parse-json").unwrap().is_compound());
+        assert!(parse("users[i + 1].age * 2").unwrap().is_compound());
+    }
+}
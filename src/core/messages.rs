@@ -0,0 +1,124 @@
+//! `@Messages/<locale>` sections: translated validation messages.
+//!
+//! ```rune
+//! @App
+//! type = REST
+//! locale = fr
+//!
+//! @Messages/fr
+//! missing_field = "Le champ {field} est obligatoire"
+//! invalid_email = "Adresse e-mail invalide"
+//! ```
+//!
+//! The locale is the best match for the request's `Accept-Language` header, else the
+//! `@App` `locale`. `validate` renders its error codes (see [`DEFAULTS`]) from that
+//! locale's templates, falling back to the default locale and then to English. An
+//! expression check's message may itself be a code: `validate body.email ~= "@" invalid_email`.
+
+use crate::builtins::builtin::logger::expand_log_message;
+use crate::builtins::Context;
+use crate::rune_ast::{RuneDocument, Section};
+use serde_json::Value as JsonValue;
+
+/// Validation error codes and their English templates.
+pub const DEFAULTS: &[(&str, &str)] = &[
+    ("missing_field", "Missing field `{field}`"),
+    ("type_mismatch", "Field `{field}` type mismatch"),
+    ("not_one_of", "Field `{field}` must be one of: {values}"),
+    ("required_when", "Field `{field}` is required when {condition}"),
+    ("validation_failed", "Validation failed"),
+];
+
+fn normalize(tag: &str) -> String {
+    tag.trim().to_ascii_lowercase().replace('_', "-")
+}
+
+fn messages_sections(doc: &RuneDocument) -> impl Iterator<Item = (String, &Section)> {
+    doc.sections.iter().filter_map(|s| match s.path.as_slice() {
+        [kind, locale, ..] if kind == "Messages" => Some((normalize(locale), s)),
+        _ => None,
+    })
+}
+
+fn section_for<'a>(doc: &'a RuneDocument, locale: &str) -> Option<&'a Section> {
+    messages_sections(doc).find(|(l, _)| l == locale).map(|(_, s)| s)
+}
+
+/// The `@App` `locale`, if set.
+pub fn default_locale(doc: &RuneDocument) -> Option<String> {
+    doc.sections
+        .iter()
+        .find(|s| s.path.first().map(String::as_str) == Some("App"))
+        .and_then(|s| s.kv.get("locale"))
+        .and_then(|v| v.as_str())
+        .map(normalize)
+}
+
+/// Picks the `@Messages` locale for an `Accept-Language` value such as
+/// `fr-CA,fr;q=0.9,en;q=0.8`: exact tags first, then their primary language.
+pub fn negotiate(doc: &RuneDocument, accept_language: &str) -> Option<String> {
+    let mut ranges: Vec<(String, f32)> = accept_language
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let tag = normalize(pieces.next()?);
+            let q = pieces
+                .find_map(|p| p.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()))
+                .unwrap_or(1.0);
+            (!tag.is_empty() && tag != "*" && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    ranges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    let available: Vec<String> = messages_sections(doc).map(|(l, _)| l).collect();
+    ranges.iter().find_map(|(tag, _)| {
+        let primary = tag.split('-').next().unwrap_or(tag);
+        available
+            .iter()
+            .find(|l| *l == tag)
+            .or_else(|| available.iter().find(|l| *l == primary))
+            .cloned()
+    })
+}
+
+/// The request's locale: negotiated from `request.headers.accept-language`, else the default.
+pub fn request_locale(doc: &RuneDocument, ctx: &Context) -> Option<String> {
+    ctx.get("request.headers")
+        .and_then(|h| h.get("accept-language"))
+        .and_then(JsonValue::as_str)
+        .and_then(|header| negotiate(doc, header))
+        .or_else(|| default_locale(doc))
+}
+
+/// The translated template for `code`, from the request's locale or the default locale.
+pub fn template<'a>(doc: &'a RuneDocument, ctx: &Context, code: &str) -> Option<&'a str> {
+    [request_locale(doc, ctx), default_locale(doc)]
+        .into_iter()
+        .flatten()
+        .find_map(|locale| section_for(doc, &locale)?.kv.get(code)?.as_str())
+}
+
+/// Renders a validation message for `code`, filling `{name}` placeholders from `vars`.
+pub fn validation_message(doc: &RuneDocument, ctx: &Context, code: &str, vars: &[(&str, &str)]) -> String {
+    let template = template(doc, ctx, code)
+        .or_else(|| DEFAULTS.iter().find(|(c, _)| *c == code).map(|(_, t)| *t))
+        .unwrap_or(code);
+    let vars: Context = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), JsonValue::String(v.to_string())))
+        .collect();
+    expand_log_message(template, &vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn negotiates_by_quality_and_primary_language() {
+        let doc = parse_rune("@Messages/fr\na = b\n\n@Messages/en_US\na = c\n").unwrap();
+        assert_eq!(negotiate(&doc, "fr-CA,en;q=0.8").as_deref(), Some("fr"));
+        assert_eq!(negotiate(&doc, "de, en-us;q=0.5, fr;q=0.4").as_deref(), Some("en-us"));
+        assert_eq!(negotiate(&doc, "de, *;q=0.1"), None);
+    }
+}
@@ -0,0 +1,368 @@
+//! `@Migration/<version>` sections: ordered schema changes for a SQL datasource.
+//!
+//! ```rune
+//! @Migration/001_create_books
+//! data_source = Db
+//! up:
+//!     create_table Book
+//!     CREATE INDEX books_title ON Book (title)
+//! down:
+//!     drop_table Book
+//! ```
+//!
+//! Each `up`/`down` line is one SQL statement, except `create_table <Schema>` and
+//! `drop_table <Schema>`, which are generated from the `@Schema` the same way CRUD routes
+//! create their tables. Migrations run in version order (numeric prefixes compare as
+//! numbers) and each one runs in a transaction together with its row in the
+//! datasource's `schema_migrations` table. `data_source` may be omitted when the
+//! document declares a single SQL datasource.
+
+use crate::builtins::builtin::data_source::{foreign_keys, is_mocked, mysql_columns, schema_columns};
+use crate::builtins::builtin::mysql::create_or_reuse_mysql_pool;
+use crate::builtins::builtin::postgres::create_or_reuse_postgres_pool;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use sqlx::{Database, MySql, Pool, Postgres};
+use std::collections::HashSet;
+
+/// Table recording which migrations have been applied to a datasource.
+pub const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: String,
+    pub data_source: String,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+/// A migration to run, with its steps expanded to SQL.
+#[derive(Debug, Clone)]
+pub struct PlannedMigration {
+    pub version: String,
+    pub data_source: String,
+    pub direction: Direction,
+    pub statements: Vec<String>,
+}
+
+/// The `@<kind>/<name>` section.
+fn named_section<'a>(doc: &'a RuneDocument, kind: &str, name: &str) -> Option<&'a Section> {
+    doc.sections
+        .iter()
+        .find(|s| s.path.len() >= 2 && s.path[0] == kind && s.path[1] == name)
+}
+
+/// The type of a `postgres` or `mysql` datasource; `None` for anything else.
+fn sql_type(doc: &RuneDocument, data_source: &str) -> Option<String> {
+    named_section(doc, "DataSource", data_source)
+        .and_then(|s| s.kv.get("type"))
+        .and_then(|v| v.as_str())
+        .filter(|t| matches!(*t, "postgres" | "mysql"))
+        .map(|t| t.to_string())
+}
+
+fn sql_data_sources(doc: &RuneDocument) -> Vec<String> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(|p| p == "DataSource").unwrap_or(false))
+        .filter_map(|s| s.path.get(1))
+        .filter(|name| sql_type(doc, name).is_some())
+        .cloned()
+        .collect()
+}
+
+/// Orders `2_x` before `10_x`; versions without a numeric prefix sort after, by name.
+fn version_key(version: &str) -> (u64, String) {
+    let digits: String = version.chars().take_while(|c| c.is_ascii_digit()).collect();
+    (digits.parse().unwrap_or(u64::MAX), version.to_string())
+}
+
+fn series_strings(values: Option<&Vec<Value>>) -> Vec<String> {
+    values
+        .map(|values| values.iter().filter_map(|v| v.as_str()).map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The document's migrations in the order they apply.
+pub fn extract_migrations(doc: &RuneDocument) -> Result<Vec<Migration>, String> {
+    let defaults = sql_data_sources(doc);
+    let mut migrations = Vec::new();
+    for section in doc.sections.iter().filter(|s| s.path.first().map(|p| p == "Migration").unwrap_or(false)) {
+        let version = section.path.get(1).ok_or("@Migration needs a version, e.g. @Migration/001_create_books")?;
+        let data_source = match section.kv.get("data_source").and_then(|v| v.as_str()) {
+            Some(name) if sql_type(doc, name).is_some() => name.to_string(),
+            Some(name) => return Err(format!("migration {}: '{}' is not a postgres or mysql datasource", version, name)),
+            None if defaults.len() == 1 => defaults[0].clone(),
+            None => return Err(format!("migration {}: set data_source to one of the SQL datasources", version)),
+        };
+        migrations.push(Migration {
+            version: version.clone(),
+            data_source,
+            up: series_strings(section.series.get("up")),
+            down: series_strings(section.series.get("down")),
+        });
+    }
+    migrations.sort_by_key(|m| version_key(&m.version));
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            return Err(format!("duplicate migration version {}", pair[0].version));
+        }
+    }
+    Ok(migrations)
+}
+
+/// Picks the migrations to run: pending ones oldest first going up, applied ones newest
+/// first going down, at most `steps` of them.
+pub fn plan<'a>(
+    migrations: &'a [Migration],
+    applied: &HashSet<(String, String)>,
+    direction: Direction,
+    steps: Option<usize>,
+) -> Vec<&'a Migration> {
+    let is_applied = |m: &Migration| applied.contains(&(m.data_source.clone(), m.version.clone()));
+    let chosen: Vec<&Migration> = match direction {
+        Direction::Up => migrations.iter().filter(|m| !is_applied(m)).collect(),
+        Direction::Down => migrations.iter().rev().filter(|m| is_applied(m)).collect(),
+    };
+    chosen.into_iter().take(steps.unwrap_or(usize::MAX)).collect()
+}
+
+/// Expands `create_table`/`drop_table` steps into SQL for the datasource's dialect.
+fn expand_steps(steps: &[String], doc: &RuneDocument, conn_type: &str) -> Result<Vec<String>, String> {
+    steps
+        .iter()
+        .map(|step| {
+            let mut words = step.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some("create_table"), Some(name), None) => {
+                    let schema = named_section(doc, "Schema", name)
+                        .ok_or_else(|| format!("create_table: schema '{}' not found", name))?;
+                    let mut columns = schema_columns(schema, doc)?;
+                    if conn_type == "mysql" {
+                        columns = mysql_columns(columns);
+                    }
+                    columns.retain(|(field, _)| field != "id");
+                    columns.sort();
+                    let id = if conn_type == "mysql" { "INT AUTO_INCREMENT PRIMARY KEY" } else { "SERIAL PRIMARY KEY" };
+                    let columns: Vec<String> = std::iter::once(format!("id {}", id))
+                        .chain(columns.iter().map(|(field, typ)| format!("{} {}", field, typ)))
+                        .chain(foreign_keys(schema))
+                        .collect();
+                    Ok(format!("CREATE TABLE IF NOT EXISTS {} ({})", name, columns.join(", ")))
+                }
+                (Some("drop_table"), Some(name), None) => Ok(format!("DROP TABLE IF EXISTS {}", name)),
+                _ => Ok(step.trim_end_matches(';').to_string()),
+            }
+        })
+        .collect()
+}
+
+fn sql_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// A connected SQL datasource.
+enum Target {
+    Postgres(Pool<Postgres>),
+    MySql(Pool<MySql>),
+}
+
+async fn connect(doc: &RuneDocument, data_source: &str) -> Result<Target, String> {
+    let section = named_section(doc, "DataSource", data_source)
+        .ok_or_else(|| format!("datasource '{}' not found", data_source))?;
+    let conn = section
+        .kv
+        .get("connection")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("datasource '{}' has no connection", data_source))?;
+    match sql_type(doc, data_source).as_deref() {
+        Some("mysql") => create_or_reuse_mysql_pool(conn).await.map(Target::MySql),
+        _ => create_or_reuse_postgres_pool(conn).await.map(Target::Postgres),
+    }
+    .map_err(|e| format!("{}: {}", data_source, e))
+}
+
+async fn run_in_transaction<DB: Database>(pool: &Pool<DB>, statements: &[String]) -> Result<(), sqlx::Error>
+where
+    for<'c> &'c mut <DB as Database>::Connection: sqlx::Executor<'c, Database = DB>,
+{
+    let mut tx = pool.begin().await?;
+    for statement in statements {
+        sqlx::raw_sql(statement).execute(&mut *tx).await?;
+    }
+    tx.commit().await
+}
+
+impl Target {
+    fn conn_type(&self) -> &'static str {
+        match self {
+            Target::Postgres(_) => "postgres",
+            Target::MySql(_) => "mysql",
+        }
+    }
+
+    async fn ensure_table(&self) -> Result<(), sqlx::Error> {
+        let ddl = format!(
+            "CREATE TABLE IF NOT EXISTS {} (version VARCHAR(255) PRIMARY KEY, applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+            MIGRATIONS_TABLE
+        );
+        match self {
+            Target::Postgres(pool) => sqlx::raw_sql(&ddl).execute(pool).await.map(|_| ()),
+            Target::MySql(pool) => sqlx::raw_sql(&ddl).execute(pool).await.map(|_| ()),
+        }
+    }
+
+    /// Applied versions; none when the tracking table does not exist yet.
+    async fn applied(&self) -> Vec<String> {
+        let query = format!("SELECT version FROM {}", MIGRATIONS_TABLE);
+        match self {
+            Target::Postgres(pool) => sqlx::query_scalar::<_, String>(&query).fetch_all(pool).await,
+            Target::MySql(pool) => sqlx::query_scalar::<_, String>(&query).fetch_all(pool).await,
+        }
+        .unwrap_or_default()
+    }
+
+    async fn run(&self, statements: &[String]) -> Result<(), sqlx::Error> {
+        match self {
+            Target::Postgres(pool) => run_in_transaction(pool, statements).await,
+            Target::MySql(pool) => run_in_transaction(pool, statements).await,
+        }
+    }
+}
+
+/// Connects to every datasource the migrations use and reads what has been applied.
+async fn connect_all(
+    doc: &RuneDocument,
+    migrations: &[Migration],
+) -> Result<(Vec<(String, Target)>, HashSet<(String, String)>), String> {
+    let mut targets: Vec<(String, Target)> = Vec::new();
+    let mut applied = HashSet::new();
+    for migration in migrations {
+        if targets.iter().any(|(name, _)| *name == migration.data_source) {
+            continue;
+        }
+        let target = connect(doc, &migration.data_source).await?;
+        for version in target.applied().await {
+            applied.insert((migration.data_source.clone(), version));
+        }
+        targets.push((migration.data_source.clone(), target));
+    }
+    Ok((targets, applied))
+}
+
+/// Every migration and whether it has been applied.
+pub async fn status(doc: &RuneDocument) -> Result<Vec<(Migration, bool)>, String> {
+    let migrations = extract_migrations(doc)?;
+    let (_, applied) = connect_all(doc, &migrations).await?;
+    Ok(migrations
+        .into_iter()
+        .map(|m| {
+            let done = applied.contains(&(m.data_source.clone(), m.version.clone()));
+            (m, done)
+        })
+        .collect())
+}
+
+/// Runs up to `steps` migrations in `direction` (all pending ones going up, one going
+/// down when `steps` is `None`). With `dry_run` nothing is executed; the plan is returned
+/// either way. Stops at the first failure, leaving the failed migration unapplied.
+pub async fn migrate(
+    doc: &RuneDocument,
+    direction: Direction,
+    steps: Option<usize>,
+    dry_run: bool,
+) -> Result<Vec<PlannedMigration>, String> {
+    // Mocked datasources have no database to migrate; their tables follow the schemas
+    let migrations: Vec<Migration> = extract_migrations(doc)?
+        .into_iter()
+        .filter(|m| !named_section(doc, "DataSource", &m.data_source).is_some_and(is_mocked))
+        .collect();
+    if migrations.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (targets, applied) = connect_all(doc, &migrations).await?;
+    let steps = steps.or(if direction == Direction::Down { Some(1) } else { None });
+    let mut done = Vec::new();
+    for migration in plan(&migrations, &applied, direction, steps) {
+        let target = &targets.iter().find(|(name, _)| *name == migration.data_source).expect("connected above").1;
+        let (steps, tracking) = match direction {
+            Direction::Up => (
+                &migration.up,
+                format!("INSERT INTO {} (version) VALUES ({})", MIGRATIONS_TABLE, sql_literal(&migration.version)),
+            ),
+            Direction::Down => (
+                &migration.down,
+                format!("DELETE FROM {} WHERE version = {}", MIGRATIONS_TABLE, sql_literal(&migration.version)),
+            ),
+        };
+        let statements = expand_steps(steps, doc, target.conn_type())
+            .map_err(|e| format!("migration {}: {}", migration.version, e))?;
+        if !dry_run {
+            target.ensure_table().await.map_err(|e| format!("{}: {}", migration.data_source, e))?;
+            let mut all = statements.clone();
+            all.push(tracking);
+            target
+                .run(&all)
+                .await
+                .map_err(|e| format!("migration {} failed: {}", migration.version, e))?;
+        }
+        done.push(PlannedMigration {
+            version: migration.version.clone(),
+            data_source: migration.data_source.clone(),
+            direction,
+            statements,
+        });
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    const DOC: &str = r#"#!RUNE
+@DataSource/Db
+type = postgres
+connection = postgres://localhost/app
+
+@Schema/Book
+title = string
+pages = number
+
+@Migration/10_index
+up:
+    CREATE INDEX books_title ON Book (title)
+down:
+    DROP INDEX books_title
+
+@Migration/2_books
+up:
+    create_table Book
+down:
+    drop_table Book
+"#;
+
+    #[test]
+    fn orders_versions_numerically_and_plans_both_directions() {
+        let doc = parse_rune(DOC).unwrap();
+        let migrations = extract_migrations(&doc).unwrap();
+        let versions: Vec<&str> = migrations.iter().map(|m| m.version.as_str()).collect();
+        assert_eq!(versions, ["2_books", "10_index"]);
+        assert_eq!(migrations[0].data_source, "Db");
+
+        let applied: HashSet<(String, String)> = [("Db".to_string(), "2_books".to_string())].into();
+        let up: Vec<&str> = plan(&migrations, &applied, Direction::Up, None).iter().map(|m| m.version.as_str()).collect();
+        assert_eq!(up, ["10_index"]);
+        let down: Vec<&str> = plan(&migrations, &applied, Direction::Down, Some(5)).iter().map(|m| m.version.as_str()).collect();
+        assert_eq!(down, ["2_books"]);
+
+        let sql = expand_steps(&migrations[0].up, &doc, "postgres").unwrap();
+        assert_eq!(sql, ["CREATE TABLE IF NOT EXISTS Book (id SERIAL PRIMARY KEY, pages FLOAT, title TEXT)"]);
+    }
+}
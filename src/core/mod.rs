@@ -1,6 +1,6 @@
-use crate::builtins::{call_builtin, BuiltinResult, Context, LAST_EXEC_RESULT};
+use crate::builtins::builtin::collection;
+use crate::builtins::{call_builtin, BuiltinResult, Context, LAST_EXEC_RESULT, RESPONSE_CONTENT_TYPE, RESPONSE_HEADERS};
 use crate::rune_ast::{RuneDocument, Section, Value};
-use crate::rune_parser::ParsedLine;
 use crate::util::{log, LogLevel};
 use async_recursion::async_recursion;
 pub use http::StatusCode;
@@ -13,22 +13,37 @@ use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use crate::arithmetic::eval_arithmetic;
+use crate::arithmetic::{eval_arithmetic, eval_arithmetic_with, has_operator};
+use self::step::Step;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ai;
+pub mod aliases;
+pub mod constants;
+pub mod errors;
+pub mod expr;
+pub mod messages;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod migrations;
+pub mod pipe;
+pub mod relations;
+pub mod request_body;
+pub mod schema_options;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod seeds;
+pub mod step;
+pub mod step_trace;
+pub mod tokenizer;
 
 #[derive(Clone)]
 pub struct AppState {
     pub doc: Arc<RuneDocument>,
     pub schemas: Arc<HashMap<String, Section>>, // For @Schema
     pub data_sources: Arc<HashMap<String, Section>>, // For @Datasource
+    pub steps: Arc<step::DocumentSteps>,        // Compiled step series
     pub path: PathBuf,                          // Path to the rune document
 }
 
-#[derive(Default, Debug)]
-pub struct ExecutionContext {
-    pub vars: HashMap<String, serde_json::Value>,
-    pub memory: HashMap<String, serde_json::Value>,
-}
-
 pub fn get_app_type(doc: &RuneDocument) -> Option<String> {
     for section in &doc.sections {
         if section.path.first().map(|s| s.as_str()) == Some("App") {
@@ -52,6 +67,11 @@ pub fn extract_schemas(doc: &RuneDocument) -> HashMap<String, Section> {
     schemas
 }
 
+/// Compiles the document's step series, reporting the steps that cannot run.
+pub fn extract_steps(doc: &RuneDocument) -> step::DocumentSteps {
+    step::DocumentSteps::compile(doc)
+}
+
 pub fn extract_data_sources(doc: &RuneDocument) -> HashMap<String, Section> {
     let mut data_sources = HashMap::new();
     for section in &doc.sections {
@@ -117,6 +137,37 @@ fn json_value_to_lookup_key(value: &serde_json::Value) -> String {
         .unwrap_or_else(|| value.to_string())
 }
 
+/// Resolves the expression inside `[...]`: a path or literal, falling back to
+/// arithmetic over context values (`users[index + 1]`).
+pub(crate) fn resolve_index_expr(
+    ctx: &Context,
+    expr: &str,
+    it: Option<&serde_json::Value>,
+) -> Option<serde_json::Value> {
+    resolve_path(ctx, expr, it).or_else(|| {
+        eval_arithmetic_with(expr, |ident| {
+            resolve_path(ctx, ident, it).as_ref().and_then(json_value_as_f64)
+        })
+        .ok()
+        .map(number_to_json)
+    })
+}
+
+/// Converts an array index key to a position; negative indexes count from the end.
+fn array_index(key: &str, len: usize) -> Option<usize> {
+    let n = key.parse::<i64>().ok().or_else(|| {
+        key.parse::<f64>()
+            .ok()
+            .filter(|f| can_cast_to_i64(*f))
+            .map(|f| f as i64)
+    })?;
+    if n < 0 {
+        len.checked_sub(n.unsigned_abs() as usize)
+    } else {
+        Some(n as usize)
+    }
+}
+
 pub fn resolve_path(
     ctx: &Context,
     ident: &str,
@@ -183,24 +234,32 @@ pub fn resolve_path(
     for key in parts.iter().skip(start_index) {
         if key.starts_with('[') && key.ends_with(']') {
             let inner_expr = &key[1..key.len() - 1];
-            // Resolve the inner expression (could be a variable or a literal)
-            let resolved_key = resolve_path(ctx, inner_expr, it)?;
+            // `[start:end]` slices a list; either bound may be omitted or negative
+            if let Some(colon) = tokenizer::find_top_level(inner_expr, ':') {
+                let Some(serde_json::Value::Array(a)) = current.take() else {
+                    return None;
+                };
+                current = Some(slice_array(ctx, &a, &inner_expr[..colon], &inner_expr[colon + 1..], it)?);
+                continue;
+            }
+            // Resolve the inner expression (a variable, a literal or arithmetic like `i + 1`)
+            let resolved_key = resolve_index_expr(ctx, inner_expr, it)?;
             let key_str = json_value_to_lookup_key(&resolved_key);
 
             match current.take() {
                 Some(serde_json::Value::Object(m)) => current = m.get(&key_str).cloned(),
                 Some(serde_json::Value::Array(a)) => {
-                    if let Ok(idx) = key_str.parse::<usize>() {
-                        current = a.get(idx).cloned();
-                    } else {
-                        return None;
-                    }
+                    current = array_index(&key_str, a.len()).and_then(|idx| a.get(idx).cloned());
                 }
                 _ => return None,
             }
         } else {
             match current.take() {
                 Some(serde_json::Value::Object(m)) => current = m.get(key).cloned(),
+                // `items.0.name` is the same as `items[0].name`
+                Some(serde_json::Value::Array(a)) => {
+                    current = array_index(key, a.len()).and_then(|idx| a.get(idx).cloned());
+                }
                 _ => return None,
             }
         }
@@ -208,48 +267,42 @@ pub fn resolve_path(
     current
 }
 
-pub fn eval_condition(ctx: &Context, expr: &str, it: Option<&serde_json::Value>) -> bool {
-    // support ==, !=, >, <, >=, <= with loose numeric equality
-    fn loose_cmp(a: &serde_json::Value, b: &serde_json::Value) -> Option<std::cmp::Ordering> {
-        use serde_json::Value::*;
-        match (a, b) {
-            (Number(na), Number(nb)) => match (na.as_f64(), nb.as_f64()) {
-                (Some(na), Some(nb)) => na.partial_cmp(&nb),
-                _ => None,
-            },
-            (String(sa), String(sb)) => {
-                if let (Ok(na), Ok(nb)) = (sa.parse::<f64>(), sb.parse::<f64>()) {
-                    na.partial_cmp(&nb)
-                } else {
-                    sa.partial_cmp(sb)
-                }
-            }
-            (Number(na), String(sb)) => {
-                if let Ok(nb) = sb.parse::<f64>() {
-                    na.as_f64().and_then(|na| na.partial_cmp(&nb))
-                } else {
-                    None
-                }
-            }
-            (String(sa), Number(nb)) => {
-                if let Ok(na) = sa.parse::<f64>() {
-                    nb.as_f64().and_then(|nb| na.partial_cmp(&nb))
-                } else {
-                    None
-                }
-            }
-            (Bool(ba), Bool(bb)) => ba.partial_cmp(bb),
-            (Null, Null) => Some(std::cmp::Ordering::Equal),
-            _ => {
-                if a == b {
-                    Some(std::cmp::Ordering::Equal)
-                } else {
-                    None
-                }
-            }
+/// Returns `a[start:end]`, with Python-style defaults and negative bounds, clamped to the list.
+fn slice_array(
+    ctx: &Context,
+    a: &[serde_json::Value],
+    start: &str,
+    end: &str,
+    it: Option<&serde_json::Value>,
+) -> Option<serde_json::Value> {
+    let bound = |expr: &str, default: usize| -> Option<usize> {
+        let expr = expr.trim();
+        if expr.is_empty() {
+            return Some(default);
         }
-    }
+        let value = resolve_index_expr(ctx, expr, it)?;
+        let n = json_value_as_f64(&value).filter(|n| can_cast_to_i64(*n))? as i64;
+        Some(if n < 0 {
+            a.len().saturating_sub(n.unsigned_abs() as usize)
+        } else {
+            (n as usize).min(a.len())
+        })
+    };
+    let start = bound(start, 0)?;
+    let end = bound(end, a.len())?;
+    Some(serde_json::Value::Array(
+        a.get(start..end.max(start)).unwrap_or_default().to_vec(),
+    ))
+}
 
+/// Whether `expr` holds for the context, evaluated as an expression (see `expr`), so
+/// `it.age > 30 and it.active` and a bare `it.active` work as conditions. Text that is not
+/// an expression falls back to splitting on its first comparison operator.
+pub fn eval_condition(ctx: &Context, expr: &str, it: Option<&serde_json::Value>) -> bool {
+    use self::expr::loose_cmp;
+    if let Ok(value) = self::expr::parse(expr).and_then(|e| e.eval_pure(ctx, it)) {
+        return crate::builtins::builtin::collection::is_truthy(&value);
+    }
     if let Some(pos) = expr.find("==") {
         let (l, r) = expr.split_at(pos);
         let lv = resolve_path(ctx, l.trim(), it).unwrap_or(serde_json::Value::Null);
@@ -316,143 +369,208 @@ pub async fn execute_steps_inner(
     state: AppState,
     steps: &[Value],
     ctx: &mut Context,
+) -> Option<(u16, String)> {
+    execute_compiled_steps(&state, &step::compile(steps), ctx).await
+}
+
+/// Runs steps compiled by `step::compile`, falling through to the last step's value as
+/// `execute_steps_inner` does.
+pub async fn execute_compiled_steps(
+    state: &AppState,
+    steps: &[Step],
+    ctx: &mut Context,
+) -> Option<(u16, String)> {
+    if let Some(resp) = run_compiled_steps(state, steps, ctx).await {
+        return Some(resp);
+    }
+    resolve_last_step(steps.last().and_then(Step::source), ctx)
+}
+
+/// Runs compiled steps without falling through to the last step's value.
+#[async_recursion]
+pub(crate) async fn run_compiled_steps(
+    state: &AppState,
+    steps: &[Step],
+    ctx: &mut Context,
 ) -> Option<(u16, String)> {
     for step in steps {
-        match step {
-            Value::String(s) => {
-                let step_str = s.trim();
-                log(LogLevel::Debug, &format!("execute_steps_inner: processing step='{}'", step_str));
-                if let Some(eq_pos) = find_assignment_equals(step_str) {
-                    let (var, cmd) = step_str.split_at(eq_pos);
-                    let var = var.trim();
-                    let cmd = cmd[1..].trim();
-                    log(
-                        LogLevel::Debug,
-                        &format!("Handling assignment - var: '{}', cmd: '{}'", var, cmd),
-                    );
-                    if let Some(resp) = handle_assignment(&state, ctx, var, cmd).await {
-                        return Some(resp);
-                    }
-                } else {
-                    log(
-                        LogLevel::Debug,
-                        &format!("Handling plain cmd - step: '{}'", step_str),
-                    );
-                    if let Some(resp) = handle_plain_command(&state, ctx, step_str).await {
-                        return Some(resp);
-                    }
+        let resp = match step {
+            Step::If { cond, expr, body } => {
+                let held = match expr {
+                    Some(expr) => expr.eval(state, ctx).await.ok().map(|v| collection::is_truthy(&v)),
+                    None => None,
+                };
+                let held = held.unwrap_or_else(|| eval_condition(ctx, cond, None));
+                step_trace::push_condition(ctx, cond, held);
+                // Only an explicit respond/error propagates, not the block's last value
+                match held {
+                    true => run_compiled_steps(state, body, ctx).await,
+                    false => None,
                 }
             }
-            Value::Map(m) => {
-                log(
-                    LogLevel::Debug,
-                    &format!("Handling conditional block - block: {:#?}", m),
-                );
-                if let Some(resp) = handle_conditional_block(&state, m, ctx).await {
-                    return Some(resp);
-                }
+            Step::Match { subject, arms } => handle_match_block(state, subject, arms, ctx).await,
+            #[cfg(not(target_arch = "wasm32"))]
+            Step::Retry { args, body } => {
+                let res = crate::builtins::builtin::control::builtin_retry(args, body, ctx, state).await;
+                handle_builtin_result(ctx, res)
             }
-            _ => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            Step::Timeout { args, body } => {
+                let res = crate::builtins::builtin::control::builtin_timeout(args, body, ctx, state).await;
+                handle_builtin_result(ctx, res)
+            }
+            step => run_step(state, ctx, step).await,
+        };
+        if resp.is_some() {
+            return resp;
         }
     }
+    None
+}
 
-    resolve_last_response(steps, ctx)
+/// Runs one assignment or plain command, recording it when steps are being traced.
+async fn run_step(state: &AppState, ctx: &mut Context, step: &Step) -> Option<(u16, String)> {
+    let step_str = step.source().unwrap_or_default();
+    log(LogLevel::Debug, &format!("execute_steps_inner: processing step='{}'", step_str));
+    let before = step_trace::snapshot(ctx);
+    let (resp, var) = match step {
+        Step::Assign { var, value, .. } => {
+            log(
+                LogLevel::Debug,
+                &format!("Handling assignment - var: '{}', cmd: '{}'", var, value.text),
+            );
+            (handle_assignment(state, ctx, var, value).await, Some(var.as_str()))
+        }
+        Step::Call(call) => {
+            log(
+                LogLevel::Debug,
+                &format!("Handling plain cmd - step: '{}'", step_str),
+            );
+            (handle_plain_command(state, ctx, call).await, None)
+        }
+        Step::Invalid { position, message, .. } => {
+            let e = format!("step {}: {}", position, message);
+            log(LogLevel::Error, &e);
+            (handle_builtin_result(ctx, BuiltinResult::Error(e)), None)
+        }
+        Step::If { .. } | Step::Match { .. } | Step::Retry { .. } | Step::Timeout { .. } => (None, None),
+    };
+    if let Some(before) = before {
+        step_trace::push(step_str, var, before, ctx, resp.clone());
+    }
+    resp
 }
 
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "bool",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "list",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// Writes `new_val` at a context path such as `order.customer.name` or `users[i].email`.
+/// Missing or null intermediate values become objects. Writing through a scalar, or into
+/// a list with a non-numeric or out-of-range index, is an error; assigning one past the
+/// end of a list appends.
 pub fn mutate_path(
     ctx: &mut Context,
     ident: &str,
     new_val: serde_json::Value,
-) -> bool {
+) -> Result<(), String> {
     let raw_parts = split_path_parts(ident);
     let mut parts = Vec::with_capacity(raw_parts.len());
 
-    for part in raw_parts {
+    for part in &raw_parts {
         if part.starts_with('[') && part.ends_with(']') {
             let inner_expr = &part[1..part.len() - 1];
-            let resolved_key = resolve_path(ctx, inner_expr, None)
+            let resolved_key = resolve_index_expr(ctx, inner_expr, None)
                 .map(|v| json_value_to_lookup_key(&v))
                 .unwrap_or_else(|| inner_expr.trim_matches('"').to_string());
             parts.push(resolved_key);
         } else {
-            parts.push(part);
+            parts.push(part.clone());
         }
     }
 
     if parts.is_empty() {
-        return false;
+        return Err(format!("invalid assignment target `{}`", ident));
     }
-
-    let first = parts[0].clone();
     if parts.len() == 1 {
-        ctx.insert(first, new_val);
-        return true;
-    }
-
-    // Traverse to the parent of the last part
-    let mut current = if let Some(val) = ctx.get_mut(&first) {
-        val
-    } else {
-        return false;
-    };
-
-    for j in 1..parts.len() - 1 {
+        ctx.insert(parts.remove(0), new_val);
+        return Ok(());
+    }
+
+    // Flat keys such as `path.params` are roots too, as in `resolve_path`
+    let root_len = (1..parts.len())
+        .rev()
+        .find(|&n| ctx.contains_key(&parts[..n].join(".")))
+        .unwrap_or(1);
+    let root = parts[..root_len].join(".");
+    let mut walked = raw_parts[..root_len].join(".");
+    let mut current = ctx
+        .entry(root)
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+
+    let last = parts.len() - 1;
+    for j in root_len..parts.len() {
         let key = &parts[j];
-        match current {
-            serde_json::Value::Object(m) => {
-                if !m.contains_key(key) {
-                    return false;
-                }
-                current = m.get_mut(key).unwrap();
+        if current.is_null() {
+            *current = serde_json::Value::Object(serde_json::Map::new());
+        }
+        current = match current {
+            serde_json::Value::Object(m) if j == last => {
+                m.insert(key.clone(), new_val);
+                return Ok(());
             }
+            serde_json::Value::Object(m) => m.entry(key.clone()).or_insert(serde_json::Value::Null),
             serde_json::Value::Array(a) => {
-                if let Ok(idx) = key.parse::<usize>() {
-                    if idx >= a.len() {
-                        return false;
-                    }
-                    current = &mut a[idx];
-                } else {
-                    return false;
+                let Some(idx) = array_index(key, a.len()) else {
+                    return Err(format!("cannot assign `{}`: `{}` is a list and `{}` is not an index", ident, walked, key));
+                };
+                if j == last && idx == a.len() {
+                    a.push(new_val);
+                    return Ok(());
                 }
-            }
-            _ => return false,
-        }
-    }
-
-    // Apply the final part
-    let last_key = &parts[parts.len() - 1];
-    match current {
-        serde_json::Value::Object(m) => {
-            m.insert(last_key.clone(), new_val);
-            true
-        }
-        serde_json::Value::Array(a) => {
-            if let Ok(idx) = last_key.parse::<usize>() {
-                if idx < a.len() {
+                if idx >= a.len() {
+                    return Err(format!(
+                        "cannot assign `{}`: index {} is out of range for `{}` (length {})",
+                        ident, key, walked, a.len()
+                    ));
+                }
+                if j == last {
                     a[idx] = new_val;
-                    true
-                } else if idx == a.len() {
-                    a.push(new_val);
-                    true
-                } else {
-                    false
+                    return Ok(());
                 }
-            } else {
-                false
+                &mut a[idx]
+            }
+            other => {
+                return Err(format!(
+                    "cannot assign `{}`: `{}` is a {}, not an object or list",
+                    ident, walked, json_type_name(other)
+                ));
             }
+        };
+        let raw = &raw_parts[j];
+        if !raw.starts_with('[') {
+            walked.push('.');
         }
-        _ => false,
+        walked.push_str(raw);
     }
+    Ok(())
 }
 
 fn is_purely_arithmetic_expression(expr: &str) -> bool {
     !expr.is_empty()
         && expr
             .chars()
-            .all(|c| c.is_ascii_digit() || c == '.' || c.is_whitespace() || "+-*/()".contains(c))
+            .all(|c| c.is_ascii_digit() || c == '.' || c.is_whitespace() || "+-*/%()".contains(c))
 }
 
-fn json_value_as_f64(value: &serde_json::Value) -> Option<f64> {
+pub(crate) fn json_value_as_f64(value: &serde_json::Value) -> Option<f64> {
     match value {
         serde_json::Value::Number(n) => n.as_f64(),
         serde_json::Value::String(s) => s.parse::<f64>().ok(),
@@ -475,14 +593,14 @@ fn find_top_level_arithmetic_operator(expr: &str) -> Option<(usize, char)> {
 
     for (idx, ch) in expr.char_indices() {
         match ch {
-            '(' => depth += 1,
-            ')' => depth = depth.saturating_sub(1),
-            '+' | '-' | '*' | '/' if depth == 0 => {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '+' | '-' | '*' | '/' | '%' if depth == 0 => {
                 let next_non_ws = expr[idx + ch.len_utf8()..]
                     .chars()
                     .find(|c| !c.is_whitespace());
                 let is_unary_sign = matches!(ch, '+' | '-')
-                    && prev_non_ws.map(|c| "+-*/(".contains(c)).unwrap_or(true);
+                    && prev_non_ws.map(|c| "+-*/%(".contains(c)).unwrap_or(true);
                 let is_hyphenated_identifier = ch == '-'
                     && prev_non_ws
                         .map(|c| c.is_ascii_alphabetic() || c == '_')
@@ -534,8 +652,13 @@ async fn resolve_numeric_operand(
         return None;
     }
 
-    let parts: Vec<String> = operand.split_whitespace().map(|s| s.to_string()).collect();
-    if parts.is_empty() {
+    // Only a command such as `users.max it.id` can be run for its value
+    let parts: Vec<String> = tokenizer::tokenize(operand);
+    let is_command_name = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    };
+    if !parts.first().is_some_and(|name| is_command_name(name)) {
         return None;
     }
 
@@ -559,52 +682,144 @@ async fn handle_assignment(
     state: &AppState,
     ctx: &mut Context,
     var: &str,
-    cmd: &str,
+    value: &step::Call,
 ) -> Option<(u16, String)> {
+    let cmd = value.text.as_str();
+    // Pipeline: var = source |> stage |> stage
+    if let Some(stages) = &value.pipeline {
+        let stages: Vec<&str> = stages.iter().map(String::as_str).collect();
+        return match pipe::run_pipeline(state, ctx, &stages).await {
+            Ok(val) => assign_path(ctx, var, val),
+            Err(resp) => Some(resp),
+        };
+    }
+
     // 1. Object Construction: var = { ... }
     if cmd.starts_with('{') {
         let map = parse_object_literal(ctx, cmd);
-        mutate_path(ctx, var, serde_json::Value::Object(map));
-        return None;
+        return assign_path(ctx, var, serde_json::Value::Object(map));
     }
 
-    // 2. Arithmetic: var = x + y
+    // 2. Expressions: var = price * qty + 1, var = "Hi, " + name, var = users.filter(it.active)
+    if let Some(expr) = &value.expr {
+        if let Some(result) = eval_expression_step(state, ctx, cmd, expr).await {
+            return match result {
+                Ok(val) => assign_path(ctx, var, val),
+                Err(resp) => Some(resp),
+            };
+        }
+    }
+
+    // 3. Arithmetic with builtin operands: var = users.max it.id + 1
     if let Some(result) = try_execute_arithmetic(state, ctx, var, cmd).await {
-        mutate_path(ctx, var, result);
+        return assign_path(ctx, var, result);
+    }
+
+    let parts = &value.tokens;
+    if parts.is_empty() {
         return None;
     }
 
-    // 3. Nested or Path Assignment
-    if var.contains('.') || var.contains('[') {
-        // Resolve the RHS
-        if let Some(val) = resolve_path(ctx, cmd, None) {
-             if mutate_path(ctx, var, val) {
-                 return None;
-             }
+    // 4. Copy a value: tags = body.tags, order.customer.name = body.name
+    if parts.len() == 1 && !is_known_command_name(ctx, &parts[0]) {
+        let val = match tokenizer::unquote(&parts[0]) {
+            Some(s) => Some(serde_json::Value::String(s)),
+            None => resolve_path(ctx, cmd, None),
+        };
+        if let Some(val) = val {
+            return assign_path(ctx, var, val);
         }
     }
 
-    // 4. Default: Builtin Function Assignment
-    let parts: Vec<String> = cmd.split_whitespace().map(|s| s.to_string()).collect();
-    if parts.is_empty() {
-        return None;
+    // 5. Nested or Path Assignment: order.customer.name = csv.read "x.csv"
+    if var.contains('.') || var.contains('[') {
+        // A builtin's result is written to a temporary and then moved into place
+        let temp_var = format!("___assign_{}___", ctx.len());
+        let res = call_builtin(&parts[0], &parts[1..], ctx, state, Some(temp_var.as_str())).await;
+        let result = ctx.remove(&temp_var);
+        if let Some(resp) = handle_builtin_result(ctx, res) {
+            return Some(resp);
+        }
+        return match result {
+            Some(val) => assign_path(ctx, var, val),
+            None => None,
+        };
     }
 
+    // 6. Default: Builtin Function Assignment
     let res = call_builtin(&parts[0], &parts[1..], ctx, state, Some(&var.to_string())).await;
-    handle_builtin_result(res)
+    handle_builtin_result(ctx, res)
+}
+
+/// Evaluates a step's compound expression; `None` leaves the step to the
+/// whitespace-separated builtin syntax. An expression without calls that fails to evaluate
+/// (an operand that is not a number, say) also falls back, as earlier scripts relied on it.
+async fn eval_expression_step(
+    state: &AppState,
+    ctx: &mut Context,
+    cmd: &str,
+    parsed: &expr::Expr,
+) -> Option<Result<JsonValue, (u16, String)>> {
+    match parsed.eval(state, ctx).await {
+        Ok(val) => Some(Ok(val)),
+        Err(e) if parsed.has_calls() => {
+            log(LogLevel::Error, &e);
+            handle_builtin_result(ctx, BuiltinResult::Error(e)).map(Err)
+        }
+        Err(e) => {
+            log(LogLevel::Debug, &format!("eval_expression_step: falling back for '{}': {}", cmd, e));
+            None
+        }
+    }
+}
+
+/// Writes an assignment result, surfacing a path conflict as a step error.
+fn assign_path(ctx: &mut Context, var: &str, val: serde_json::Value) -> Option<(u16, String)> {
+    match mutate_path(ctx, var, val) {
+        Ok(()) => None,
+        Err(e) => {
+            log(LogLevel::Error, &e);
+            handle_builtin_result(ctx, BuiltinResult::Error(e))
+        }
+    }
 }
 
 /// Handles commands without assignments (e.g., "log hello")
 async fn handle_plain_command(
     state: &AppState,
     ctx: &mut Context,
-    step: &str,
+    call: &step::Call,
 ) -> Option<(u16, String)> {
-    let parts: Vec<String> = step.split_whitespace().map(|s| s.to_string()).collect();
+    let step = call.text.as_str();
+    if let Some(stages) = &call.pipeline {
+        let stages: Vec<&str> = stages.iter().map(String::as_str).collect();
+        return match pipe::run_pipeline(state, ctx, &stages).await {
+            Ok(val) => {
+                ctx.insert(LAST_EXEC_RESULT.to_string(), val);
+                None
+            }
+            Err(resp) => Some(resp),
+        };
+    }
+
+    let parts = &call.tokens;
     if parts.is_empty() {
         return None;
     }
 
+    // A call written as an expression: `users.filter(it.active).map(it.name)`
+    if let Some(expr) = &call.expr {
+        if let Some(result) = eval_expression_step(state, ctx, step, expr).await {
+            return match result {
+                Ok(val) => {
+                    ctx.insert(LAST_EXEC_RESULT.to_string(), val);
+                    None
+                }
+                Err(resp) => Some(resp),
+            };
+        }
+    }
+
     if !is_known_command_name(ctx, &parts[0]) {
         if let Some(_) = try_execute_arithmetic(state, ctx, LAST_EXEC_RESULT, step).await {
             return None;
@@ -612,24 +827,24 @@ async fn handle_plain_command(
     }
 
     let res = call_builtin(&parts[0], &parts[1..], ctx, state, None).await;
-    handle_builtin_result(res)
+    handle_builtin_result(ctx, res)
 }
 
 /// Parses the "key: value" syntax inside curly braces
-fn parse_object_literal(
+pub(crate) fn parse_object_literal(
     ctx: &mut Context,
     cmd: &str,
 ) -> serde_json::Map<String, serde_json::Value> {
     let mut map = serde_json::Map::new();
     let content = cmd.trim_matches(|c| c == '{' || c == '}');
 
-    for pair in split_outside_quotes(content, ',') {
+    for pair in tokenizer::split_top_level(content, ',') {
         let pair = pair.trim();
         if pair.is_empty() {
             continue;
         }
 
-        if let Some(colon_pos) = find_char_outside_quotes(pair, ':') {
+        if let Some(colon_pos) = tokenizer::find_top_level(pair, ':') {
             let (key, val_expr) = pair.split_at(colon_pos);
             let key = key
                 .trim()
@@ -656,127 +871,55 @@ fn parse_object_literal(
     map
 }
 
-/// Like execute_steps_inner but does NOT call resolve_last_response at the end.
-/// Used for conditional blocks so the outer loop continues after the if-body.
+/// Like execute_steps_inner but does not fall through to the last step's value.
+/// Used for conditional and control blocks so the outer loop continues after the body.
 #[async_recursion]
-async fn execute_steps_inner_no_fallthrough(
+pub async fn execute_steps_inner_no_fallthrough(
     state: AppState,
     steps: &[Value],
     ctx: &mut Context,
 ) -> Option<(u16, String)> {
-    for step in steps {
-        match step {
-            Value::String(s) => {
-                let step_str = s.trim();
-                if let Some(eq_pos) = find_assignment_equals(step_str) {
-                    let (var, cmd) = step_str.split_at(eq_pos);
-                    let var = var.trim();
-                    let cmd = cmd[1..].trim();
-                    if let Some(resp) = handle_assignment(&state, ctx, var, cmd).await {
-                        return Some(resp);
-                    }
-                } else {
-                    if let Some(resp) = handle_plain_command(&state, ctx, step_str).await {
-                        return Some(resp);
-                    }
-                }
-            }
-            Value::Map(m) => {
-                if let Some(resp) = handle_conditional_block(&state, m, ctx).await {
-                    return Some(resp);
-                }
-            }
-            _ => {}
-        }
-    }
-    None
+    run_compiled_steps(&state, &step::compile(steps), ctx).await
 }
 
-/// Handles the logic for "if" blocks in the steps
-async fn handle_conditional_block(
+/// Runs the first arm of a `match <expr>:` block whose value equals the subject. Arm keys
+/// are literals or paths such as `Status.active`; `default` matches anything.
+async fn handle_match_block(
     state: &AppState,
-    map: &HashMap<String, Value>,
+    subject: &str,
+    arms: &[(String, Vec<Step>)],
     ctx: &mut Context,
 ) -> Option<(u16, String)> {
-    if map.len() == 1 {
-        let (k, v) = map.iter().next()?;
-        if let Some(cond) = k.strip_prefix("if ") {
-            if let Value::List(nested) = v {
-                if eval_condition(ctx, cond, None) {
-                    // Execute nested steps; only propagate if it was an explicit respond/error,
-                    // not the implicit end-of-block fallthrough from resolve_last_response.
-                    if let Some(resp) = execute_steps_inner_no_fallthrough(state.clone(), nested, ctx).await {
-                        return Some(resp);
-                    }
-                }
-            }
+    let value = resolve_path(ctx, subject, None).unwrap_or(JsonValue::Null);
+    let as_text = |v: &JsonValue| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+    for (key, steps) in arms {
+        let key = key.as_str();
+        let matched = key == "default"
+            || match resolve_path(ctx, key, None) {
+                Some(arm_value) => match (arm_value.as_f64(), value.as_f64()) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => as_text(&arm_value) == as_text(&value),
+                },
+                None => tokenizer::unquote(key).as_deref().unwrap_or(key) == as_text(&value),
+            };
+        if matched {
+            return run_compiled_steps(state, steps, ctx).await;
         }
     }
     None
 }
 
-/// Helper to convert BuiltinResult to the standard return tuple
-fn handle_builtin_result(res: BuiltinResult) -> Option<(u16, String)> {
+/// Helper to convert BuiltinResult to the standard return tuple. Errors are also
+/// recorded in the context so `on_error:` steps can run.
+fn handle_builtin_result(ctx: &mut Context, res: BuiltinResult) -> Option<(u16, String)> {
     match res {
         BuiltinResult::Ok => None,
         BuiltinResult::Respond(code, msg) => Some((code, msg)),
-        BuiltinResult::Error(err) => Some((500, err)),
-    }
-}
-
-fn find_assignment_equals(s: &str) -> Option<usize> {
-    let mut in_quotes = false;
-    let bytes = s.as_bytes();
-    for i in 0..bytes.len() {
-        let c = bytes[i] as char;
-        if c == '"' {
-            in_quotes = !in_quotes;
-        }
-        if !in_quotes && c == '=' {
-            let prev = if i > 0 { bytes[i - 1] as char } else { '\0' };
-            let next = if i + 1 < bytes.len() {
-                bytes[i + 1] as char
-            } else {
-                '\0'
-            };
-            if prev != '=' && next != '=' && prev != '!' && next != '>' && prev != '<' {
-                return Some(i);
-            }
+        BuiltinResult::Error(err) => {
+            ctx.insert(errors::STEP_ERROR.to_string(), JsonValue::String(err.clone()));
+            Some((500, err))
         }
     }
-    None
-}
-
-fn find_char_outside_quotes(s: &str, target: char) -> Option<usize> {
-    let mut in_quotes = false;
-    for (i, c) in s.chars().enumerate() {
-        if c == '"' {
-            in_quotes = !in_quotes;
-        }
-        if c == target && !in_quotes {
-            return Some(i);
-        }
-    }
-    None
-}
-
-fn split_outside_quotes<'a>(s: &'a str, delimiter: char) -> Vec<&'a str> {
-    let mut parts = Vec::new();
-    let mut start = 0usize;
-    let mut in_quotes = false;
-
-    for (i, c) in s.char_indices() {
-        if c == '"' {
-            in_quotes = !in_quotes;
-        }
-        if c == delimiter && !in_quotes {
-            parts.push(&s[start..i]);
-            start = i + c.len_utf8();
-        }
-    }
-
-    parts.push(&s[start..]);
-    parts
 }
 
 async fn try_execute_arithmetic(
@@ -785,10 +928,25 @@ async fn try_execute_arithmetic(
     var: &str,
     cmd: &str,
 ) -> Option<serde_json::Value> {
+    // Whole-expression evaluation: operators with or without spaces, unary minus,
+    // modulo and indexed paths (`scores[i + 1] % 2`)
+    let first_word = cmd.split_whitespace().next().unwrap_or("");
+    if has_operator(cmd) && !is_known_command_name(ctx, first_word) {
+        let result = eval_arithmetic_with(cmd, |ident| {
+            resolve_path(ctx, ident, None).as_ref().and_then(json_value_as_f64)
+        });
+        if let Ok(result) = result {
+            let val = number_to_json(result);
+            ctx.insert(var.to_string(), val.clone());
+            return Some(val);
+        }
+    }
+
+    // Fallback: operands that are builtin calls, e.g. `users.max it.id + 1`
     let mut resolved_cmd = String::new();
     for token in cmd.split_whitespace() {
         // If the token is a math operator or a literal number, keep it
-        if ["+", "-", "*", "/", "(", ")"].contains(&token)
+        if ["+", "-", "*", "/", "%", "(", ")"].contains(&token)
             || token.parse::<f64>().is_ok()
             || token.chars().all(|c| c == '(' || c == ')')
         {
@@ -815,11 +973,7 @@ async fn try_execute_arithmetic(
     if is_purely_arithmetic_expression(resolved_cmd) {
         log(LogLevel::Debug, "try_execute_arithmetic: evaluating purely arithmetic expression via eval_arithmetic");
         return if let Ok(result) = eval_arithmetic(resolved_cmd) {
-            let val = if can_cast_to_i64(result) {
-                serde_json::Value::from(result as i64)
-            } else {
-                serde_json::Value::from(result)
-            };
+            let val = number_to_json(result);
             ctx.insert(var.to_string(), val.clone());
             Some(val)
         } else {
@@ -845,14 +999,11 @@ async fn try_execute_arithmetic(
             '-' => l_f - r_f,
             '*' => l_f * r_f,
             '/' => l_f / r_f,
+            '%' => l_f % r_f,
             _ => 0.0,
         };
 
-        let val = if can_cast_to_i64(result) {
-            serde_json::Value::from(result as i64)
-        } else {
-            serde_json::Value::from(result)
-        };
+        let val = number_to_json(result);
         ctx.insert(var.to_string(), val.clone());
         return Some(val);
     }
@@ -864,6 +1015,15 @@ fn can_cast_to_i64(n: f64) -> bool {
     n.fract() == 0.0 && n >= i64::MIN as f64 && n <= i64::MAX as f64
 }
 
+/// Whole numbers become JSON integers, everything else a float.
+pub(crate) fn number_to_json(n: f64) -> serde_json::Value {
+    if can_cast_to_i64(n) {
+        serde_json::Value::from(n as i64)
+    } else {
+        serde_json::Value::from(n)
+    }
+}
+
 fn is_known_command_name(ctx: &Context, name: &str) -> bool {
     if ctx.contains_key(&format!("func:{}", name)) {
         return true;
@@ -872,6 +1032,7 @@ fn is_known_command_name(ctx: &Context, name: &str) -> bool {
     if matches!(
         name,
         "func"
+            | "call"
             | "log"
             | "respond"
             | "parse-json"
@@ -892,6 +1053,35 @@ fn is_known_command_name(ctx: &Context, name: &str) -> bool {
             | "memory.del"
             | "delete"
             | "is-set"
+            | "now"
+            | "date.format"
+            | "date.parse"
+            | "date.add"
+            | "crypto.sha256"
+            | "crypto.hmac"
+            | "crypto.hmac_verify"
+            | "base64.encode"
+            | "base64.decode"
+            | "regex.match"
+            | "regex.capture"
+            | "regex.replace"
+            | "paginate"
+            | "obj.merge"
+            | "obj.pick"
+            | "obj.omit"
+            | "obj.keys"
+            | "obj.set"
+            | "render"
+            | "file.read"
+            | "file.write"
+            | "file.append"
+            | "file.list"
+            | "jwt.sign"
+            | "jwt.verify"
+            | "email.send"
+            | "emit-event"
+            | "enqueue"
+            | "exec"
             | "append"
             | "memory.append"
             | "ws.id"
@@ -899,6 +1089,9 @@ fn is_known_command_name(ctx: &Context, name: &str) -> bool {
             | "ws.broadcast"
             | "broadcast-websocket"
             | "return"
+            | "sleep"
+            | "retry"
+            | "timeout"
             | "#"
     ) {
         return true;
@@ -907,18 +1100,20 @@ fn is_known_command_name(ctx: &Context, name: &str) -> bool {
     if let Some(dot_pos) = name.find('.') {
         let target = &name[..dot_pos];
         let method = &name[dot_pos + 1..];
+        if let Some((list_path, list_method)) = name.rsplit_once('.') {
+            if collection::METHODS.contains(&list_method) && resolve_path(ctx, list_path, None).is_some() {
+                return true;
+            }
+        }
         return matches!(method, "find" | "find-index" | "max" | "remove") && ctx.contains_key(target);
     }
 
     false
 }
 
-/// Check last step for a response
-pub fn resolve_last_response(steps: &[Value], ctx: &mut Context) -> Option<(u16, String)> {
-    let step = match steps.last() {
-        Some(Value::String(step)) => step.trim(),
-        _ => return None,
-    };
+/// Check the last step for a response
+fn resolve_last_step(step: Option<&str>, ctx: &mut Context) -> Option<(u16, String)> {
+    let step = step?;
 
     // Case 1: Assignment
     if let Some(eq_pos) = step.find('=') {
@@ -951,13 +1146,85 @@ fn get_last_builtin_result(ctx: &Context) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Result of running a route's steps: status, body and any headers the steps set.
+/// `content_type` is the one implied by the value responded with, used when no
+/// `content-type` header was set.
+#[derive(Debug, Clone)]
+pub struct StepResponse {
+    pub status: StatusCode,
+    pub body: String,
+    pub headers: Vec<(String, String)>,
+    pub content_type: Option<String>,
+}
+
+impl StepResponse {
+    /// The response's Content-Type: a header set by the steps, else the implied one.
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+            .map(|(_, v)| v.as_str())
+            .or(self.content_type.as_deref())
+    }
+
+    /// The body as a value: parsed when it is JSON, a string when it is text. Bodies
+    /// without a Content-Type are parsed when they hold JSON.
+    pub fn value(&self) -> JsonValue {
+        match self.content_type() {
+            Some(t) if !t.contains("json") => JsonValue::String(self.body.clone()),
+            _ => serde_json::from_str(&self.body).unwrap_or_else(|_| JsonValue::String(self.body.clone())),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl axum::response::IntoResponse for StepResponse {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.body).into_response();
+        // Headers set by the steps come later and replace the implied Content-Type
+        let implied = self.content_type.map(|content_type| ("content-type".to_string(), content_type));
+        for (name, value) in implied.into_iter().chain(self.headers) {
+            if let (Ok(name), Ok(value)) = (
+                axum::http::HeaderName::from_bytes(name.as_bytes()),
+                axum::http::HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        response
+    }
+}
+
 pub async fn execute_steps(
     state: AppState,
-    steps: Vec<Value>,
+    steps: &[Value],
     body: Option<String>,
     path_params: Option<HashMap<String, String>>,
-) -> (StatusCode, String) {
+) -> StepResponse {
+    let steps = step::compile(steps);
+    execute_request_steps(state, &steps, body, path_params, None, None, None).await
+}
+
+/// Runs a compiled step sequence for an HTTP request. Query string parameters are exposed as
+/// `request.query`, request headers (lower-cased names) as `request.headers`, the W3C trace
+/// ids of the request as `trace`, and headers recorded by builtins are returned
+/// alongside the status and body.
+///
+/// When a builtin fails, `on_error` (or the `@Errors` section's `on_error:` series)
+/// runs with the failure exposed as `error`. Error statuses with an `@Errors` entry
+/// get their body rendered from that template. Datasource transactions still open after
+/// an error, or at the end, are rolled back.
+pub async fn execute_request_steps(
+    state: AppState,
+    steps: &[Step],
+    body: Option<String>,
+    path_params: Option<HashMap<String, String>>,
+    query: Option<HashMap<String, String>>,
+    headers: Option<HashMap<String, String>>,
+    on_error: Option<Arc<[Step]>>,
+) -> StepResponse {
     let mut ctx: Context = Context::new();
+    constants::seed_context(&state.doc, &mut ctx);
 
     // Store path params in context
     if let Some(params) = path_params {
@@ -975,17 +1242,107 @@ pub async fn execute_steps(
             ),
         );
     }
-    // Store body in context
+    if let Some(query) = query {
+        ctx.insert(
+            "request.query".to_string(),
+            JsonValue::Object(query.into_iter().map(|(k, v)| (k, JsonValue::String(v))).collect()),
+        );
+    }
+    if let Some(headers) = headers {
+        ctx.insert(
+            "request.headers".to_string(),
+            JsonValue::Object(
+                headers
+                    .into_iter()
+                    .map(|(k, v)| (k.to_lowercase(), JsonValue::String(v)))
+                    .collect(),
+            ),
+        );
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(trace) = crate::apps::trace::current() {
+        ctx.insert("trace".to_string(), trace.to_json());
+    }
+    // Store body in context, parsed when its Content-Type is one `request_body` knows
     if let Some(body_str) = &body {
-        ctx.insert("body".to_string(), body_str.clone().into());
+        let content_type = ctx
+            .get("request.headers")
+            .and_then(|h| h.get("content-type"))
+            .and_then(JsonValue::as_str);
+        let parsed = match request_body::parse(body_str, content_type) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return StepResponse {
+                    status: StatusCode::BAD_REQUEST,
+                    body: e,
+                    headers: Vec::new(),
+                    content_type: None,
+                }
+            }
+        };
+        ctx.insert(request_body::RAW_BODY.to_string(), body_str.clone().into());
+        ctx.insert("body".to_string(), parsed.unwrap_or_else(|| body_str.clone().into()));
     }
 
-    let last_response = execute_steps_inner(state.clone(), &steps, &mut ctx).await;
+    let mut last_response = execute_compiled_steps(&state, steps, &mut ctx).await;
 
-    if let Some((code, msg)) = last_response {
+    let errors_section = errors::errors_section(&state.doc);
+    if let Some(JsonValue::String(message)) = ctx.remove(errors::STEP_ERROR) {
+        #[cfg(not(target_arch = "wasm32"))]
+        for datasource in crate::builtins::builtin::transaction::rollback_all(&mut ctx).await {
+            log(LogLevel::Info, &format!("Rolled back transaction on {} after error: {}", datasource, message));
+        }
+        let handler = on_error.or_else(|| state.steps.get(errors_section?, "on_error"));
+        if let Some(handler) = handler {
+            ctx.insert("error".to_string(), errors::error_object(500, &message));
+            if let Some(resp) = run_compiled_steps(&state, &handler, &mut ctx).await {
+                last_response = Some(resp);
+            }
+            ctx.remove(errors::STEP_ERROR);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    for datasource in crate::builtins::builtin::transaction::rollback_all(&mut ctx).await {
+        log(LogLevel::Warn, &format!("Rolled back uncommitted transaction on {}", datasource));
+    }
+
+    let mut headers: Vec<(String, String)> = match ctx.remove(RESPONSE_HEADERS) {
+        Some(JsonValue::Object(map)) => map
+            .into_iter()
+            .filter_map(|(k, v)| v.as_str().map(|v| (k, v.to_string())))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let content_type = match ctx.remove(RESPONSE_CONTENT_TYPE) {
+        Some(JsonValue::String(content_type)) if last_response.is_some() => Some(content_type),
+        _ => None,
+    };
+    let (status, mut body) = if let Some((code, msg)) = last_response {
         (StatusCode::from_u16(code).unwrap_or(StatusCode::OK), msg)
     } else {
         (StatusCode::OK, "OK".to_string())
+    };
+    if status.is_client_error() || status.is_server_error() {
+        if let Some(section) = errors_section {
+            // `on_error:` may respond with another status than the 500 its `error` was made for
+            let error_status = ctx.get("error").and_then(|e| e.get("status")).and_then(JsonValue::as_u64);
+            if error_status != Some(status.as_u16().into()) {
+                ctx.insert("error".to_string(), errors::error_object(status.as_u16(), &body));
+            }
+            if let Some((rendered, content_type)) =
+                errors::render_error_body(section, status.as_u16(), &ctx)
+            {
+                body = rendered;
+                headers.retain(|(k, _)| !k.eq_ignore_ascii_case("content-type"));
+                headers.push(("content-type".to_string(), content_type));
+            }
+        }
+    }
+    StepResponse {
+        status,
+        body,
+        headers,
+        content_type,
     }
 }
 
@@ -1123,70 +1480,3 @@ pub async fn initialize_memory_from_doc(doc: &RuneDocument, path: &PathBuf) {
     }
 }
 
-pub async fn execute_line(
-    ctx: &mut ExecutionContext,
-    parsed: &ParsedLine,
-) -> anyhow::Result<serde_json::Value> {
-    // Convert ParsedLine back to a string representation
-    let line_str = match parsed {
-        ParsedLine::Assignment { var, expr } => format!("{} = {}", var, expr),
-        ParsedLine::Builtin { name, args } => {
-            let args_str = args.join(" ");
-            format!("{} {}", name, args_str)
-        }
-        ParsedLine::Object { var, fields } => {
-            let fields_str = fields
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("{} = {{ {} }}", var, fields_str)
-        }
-        ParsedLine::Comment => String::from("#"),
-        ParsedLine::Raw(line) => line.clone(),
-    };
-
-    // Convert ExecutionContext to Context
-    let steps = [Value::String(line_str.clone())];
-    // Create a Context and copy vars/memory
-    let mut core_ctx = crate::builtins::Context::new();
-    for (k, v) in ctx.vars.iter() {
-        core_ctx.insert(k.clone(), v.clone());
-    }
-    for (k, v) in ctx.memory.iter() {
-        core_ctx.insert(k.clone(), v.clone());
-    }
-
-    // Dummy AppState (not used in REPL)
-    let app_state = AppState {
-        doc: Arc::new(RuneDocument { sections: vec![] }),
-        schemas: Arc::new(HashMap::new()),
-        data_sources: Arc::new(HashMap::new()),
-        path: PathBuf::new(),
-    };
-
-    // Call execute_steps_inner directly (since execute_steps expects HTTP context)
-    let result = execute_steps_inner(app_state, &steps, &mut core_ctx).await;
-
-    // Update ExecutionContext from core_ctx
-    ctx.vars.clear();
-    ctx.memory.clear();
-    for (k, v) in core_ctx.iter() {
-        if k == LAST_EXEC_RESULT {
-            continue;
-        }
-        // Heuristic: keys that start with "memory." go to memory, else to vars
-        if k.starts_with("memory.") {
-            ctx.memory.insert(k.clone(), v.clone());
-        } else {
-            ctx.vars.insert(k.clone(), v.clone());
-        }
-    }
-
-    // Return the last response or Null
-    if let Some((_code, msg)) = result {
-        Ok(serde_json::Value::String(msg))
-    } else {
-        Ok(serde_json::Value::Null)
-    }
-}
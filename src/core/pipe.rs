@@ -0,0 +1,153 @@
+//! The `|>` pipe operator: `names = csv.read "u.csv" |> filter it.active == true |> map it.name |> sort`.
+//!
+//! The first stage produces a value (a builtin call or a context path). Each later stage
+//! receives it as the implicit intermediate value:
+//! - `filter <cond>`, `map <expr>`, `sort [expr] [desc]`, `group-by <expr>`, `sum [expr]`,
+//!   `reverse`, `take <n>`, `first` and `count` operate on it directly, with `it` bound to
+//!   each list item (see [`crate::builtins::builtin::collection`]);
+//! - any other builtin runs with the value available as `_`, and its result (if it
+//!   produces one) becomes the next value.
+
+use super::{handle_builtin_result, is_known_command_name, resolve_path, tokenizer, AppState};
+use crate::builtins::builtin::collection::{filter_items, group_items, map_items, sort_items, sum_items};
+use crate::builtins::{call_builtin, BuiltinResult, Context};
+use serde_json::Value as JsonValue;
+
+/// Context key holding the intermediate value while a generic builtin stage runs.
+pub const PIPE_VALUE: &str = "_";
+
+/// Splits `cmd` on top-level `|>`; returns `None` when there is no pipe.
+pub fn split_pipeline(cmd: &str) -> Option<Vec<&str>> {
+    let scanned = tokenizer::scan(cmd);
+    let mut stages = Vec::new();
+    let mut start = 0usize;
+    for pair in scanned.windows(2) {
+        let ((i, a, scope), (_, b, _)) = (pair[0], pair[1]);
+        if a == '|' && b == '>' && scope == tokenizer::Scope::Top {
+            stages.push(cmd[start..i].trim());
+            start = i + 2;
+        }
+    }
+    if stages.is_empty() {
+        return None;
+    }
+    stages.push(cmd[start..].trim());
+    Some(stages)
+}
+
+/// Runs a pipeline. A stage that responds or fails stops it and returns the response.
+pub async fn run_pipeline(
+    state: &AppState,
+    ctx: &mut Context,
+    stages: &[&str],
+) -> Result<JsonValue, (u16, String)> {
+    let first = stages.first().copied().unwrap_or_default();
+    if first.is_empty() || stages.iter().any(|s| s.is_empty()) {
+        return Err(stage_error(ctx, "pipe: empty stage".to_string()));
+    }
+    let mut value = first_value(state, ctx, first).await?;
+    for stage in &stages[1..] {
+        value = run_stage(state, ctx, stage, value).await?;
+    }
+    Ok(value)
+}
+
+fn stage_error(ctx: &mut Context, message: String) -> (u16, String) {
+    handle_builtin_result(ctx, BuiltinResult::Error(message)).unwrap_or_default()
+}
+
+async fn first_value(state: &AppState, ctx: &mut Context, stage: &str) -> Result<JsonValue, (u16, String)> {
+    let parts = tokenizer::tokenize(stage);
+    if parts.len() == 1 && !is_known_command_name(ctx, &parts[0]) {
+        if let Some(value) = resolve_path(ctx, &parts[0], None) {
+            return Ok(value);
+        }
+    }
+    call_stage_builtin(state, ctx, &parts).await.map(|v| v.unwrap_or(JsonValue::Null))
+}
+
+/// Calls a builtin, capturing what it assigns. `None` means it produced no value.
+async fn call_stage_builtin(
+    state: &AppState,
+    ctx: &mut Context,
+    parts: &[String],
+) -> Result<Option<JsonValue>, (u16, String)> {
+    let temp_var = format!("___pipe_{}___", ctx.len());
+    let res = call_builtin(&parts[0], &parts[1..], ctx, state, Some(temp_var.as_str())).await;
+    let value = ctx.remove(&temp_var);
+    match handle_builtin_result(ctx, res) {
+        Some(resp) => Err(resp),
+        None => Ok(value),
+    }
+}
+
+fn items(ctx: &mut Context, stage: &str, value: JsonValue) -> Result<Vec<JsonValue>, (u16, String)> {
+    match value {
+        JsonValue::Array(a) => Ok(a),
+        other => Err(stage_error(
+            ctx,
+            format!("pipe: `{}` expects a list, got {}", stage, other),
+        )),
+    }
+}
+
+async fn run_stage(
+    state: &AppState,
+    ctx: &mut Context,
+    stage: &str,
+    value: JsonValue,
+) -> Result<JsonValue, (u16, String)> {
+    let (name, rest) = stage.split_once(char::is_whitespace).unwrap_or((stage, ""));
+    let rest = rest.trim();
+    match name {
+        "filter" | "map" | "sort" | "group-by" | "sum" => {
+            let list = items(ctx, stage, value)?;
+            Ok(match name {
+                "filter" => JsonValue::Array(filter_items(ctx, rest, list)),
+                "map" => JsonValue::Array(map_items(ctx, rest, &list)),
+                "sort" => JsonValue::Array(sort_items(ctx, rest, list)),
+                "group-by" => JsonValue::Object(group_items(ctx, rest, list)),
+                _ => sum_items(ctx, rest, &list),
+            })
+        }
+        "reverse" => {
+            let mut list = items(ctx, stage, value)?;
+            list.reverse();
+            Ok(JsonValue::Array(list))
+        }
+        "take" => {
+            let n = resolve_path(ctx, rest, None)
+                .and_then(|v| v.as_f64())
+                .filter(|n| *n >= 0.0)
+                .ok_or_else(|| stage_error(ctx, format!("pipe: `{}` needs a count", stage)))?;
+            let list = items(ctx, stage, value)?;
+            Ok(JsonValue::Array(list.into_iter().take(n as usize).collect()))
+        }
+        "first" => Ok(items(ctx, stage, value)?.into_iter().next().unwrap_or(JsonValue::Null)),
+        "count" => Ok(JsonValue::from(items(ctx, stage, value)?.len())),
+        _ => {
+            let parts = tokenizer::tokenize(stage);
+            let previous = ctx.insert(PIPE_VALUE.to_string(), value.clone());
+            let result = call_stage_builtin(state, ctx, &parts).await;
+            match previous {
+                Some(prev) => ctx.insert(PIPE_VALUE.to_string(), prev),
+                None => ctx.remove(PIPE_VALUE),
+            };
+            Ok(result?.unwrap_or(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_top_level_pipes_only() {
+        assert_eq!(
+            split_pipeline(r#"csv.read "a|>b.csv" |> map it.name |> sort"#),
+            Some(vec![r#"csv.read "a|>b.csv""#, "map it.name", "sort"])
+        );
+        assert_eq!(split_pipeline("log a | b"), None);
+    }
+}
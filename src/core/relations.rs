@@ -0,0 +1,117 @@
+//! Relations between schemas, declared with a `ref(<Schema>.<field>)` field type:
+//!
+//! ```rune
+//! @Schema/Book
+//! title = string
+//! author_id = ref(Author.id)
+//! ```
+//!
+//! The field holds values of the referenced field's type. SQL datasources create it with a
+//! foreign key, and a CRUD route's GET accepts `?include=author` (the field name without
+//! `_id`) to nest the referenced record in each result.
+
+use super::schema_options::base_type;
+use crate::rune_ast::{RuneDocument, Section, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// The field holding the key, e.g. `author_id`.
+    pub field: String,
+    /// The name `?include=` uses, e.g. `author`.
+    pub name: String,
+    pub schema: String,
+    pub column: String,
+}
+
+/// Splits `ref(Author.id)` into `("Author", "id")`; `ref(Author)` refers to `id`.
+pub fn parse_ref(typ: &str) -> Option<(&str, &str)> {
+    let inner = base_type(typ).strip_prefix("ref(")?.strip_suffix(')')?.trim();
+    let (schema, column) = inner.split_once('.').unwrap_or((inner, "id"));
+    (!schema.is_empty()).then(|| (schema.trim(), column.trim()))
+}
+
+/// The schema's `ref(...)` fields, ordered by field name.
+pub fn references(schema: &Section) -> Vec<Reference> {
+    let mut references: Vec<Reference> = schema
+        .kv
+        .iter()
+        .filter_map(|(field, typ)| {
+            let (target, column) = parse_ref(typ.as_str()?)?;
+            Some(Reference {
+                field: field.clone(),
+                name: field.strip_suffix("_id").unwrap_or(field).to_string(),
+                schema: target.to_string(),
+                column: column.to_string(),
+            })
+        })
+        .collect();
+    references.sort_by(|a, b| a.field.cmp(&b.field));
+    references
+}
+
+/// The datasource of the first route serving `schema`, where its records are kept.
+pub fn routed_data_source<'a>(doc: &'a RuneDocument, schema: &str) -> Option<&'a str> {
+    doc.sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("Route"))
+        .find(|s| s.kv.get("schema").and_then(Value::as_str) == Some(schema))
+        .and_then(|s| s.kv.get("data_source").and_then(Value::as_str))
+}
+
+/// The type a schema declares for `field`, as `field = type` or as `field: type` in its
+/// `fields:` series.
+pub fn declared_type<'a>(schema: &'a Section, field: &str) -> Option<&'a str> {
+    schema.kv.get(field).and_then(Value::as_str).or_else(|| {
+        schema.series.get("fields")?.iter().filter_map(Value::as_str).find_map(|line| {
+            let (name, typ) = line.split_once(':')?;
+            (name.trim() == field).then(|| typ.trim())
+        })
+    })
+}
+
+/// The value type of a field: a `ref(...)` takes the referenced field's declared type, and
+/// an undeclared `id` is a number. Modifiers such as `unique` are dropped.
+pub fn resolve_type<'a>(typ: &'a str, schemas: &'a HashMap<String, Section>) -> &'a str {
+    let Some((schema, column)) = parse_ref(typ) else {
+        return base_type(typ);
+    };
+    match schemas.get(schema).and_then(|s| declared_type(s, column)) {
+        Some(declared) if parse_ref(declared).is_none() => base_type(declared),
+        _ if column == "id" => "number",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn finds_references_and_their_types() {
+        let doc = parse_rune(
+            "#!RUNE\n@Schema/Author\nid = string\nname = string\n\n@Schema/Book\ntitle = string\nauthor_id = ref(Author.id)\nshelf = ref(Shelf)\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        let refs = references(&schemas["Book"]);
+        assert_eq!(refs.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["author", "shelf"]);
+        assert_eq!((refs[1].schema.as_str(), refs[1].column.as_str()), ("Shelf", "id"));
+        assert_eq!(resolve_type("ref(Author.id)", &schemas), "string");
+        assert_eq!(resolve_type("ref(Shelf)", &schemas), "number");
+        assert_eq!(resolve_type("bool", &schemas), "bool");
+    }
+
+    #[test]
+    fn declared_types_come_from_fields_too() {
+        let doc = parse_rune(
+            "#!RUNE\n@Schema/Skater\nfields:\n    id: String\n    age: Integer\n\n@Schema/Trick\nskater_id = ref(Skater)\n",
+        )
+        .unwrap();
+        let schemas = crate::core::extract_schemas(&doc);
+        assert_eq!(declared_type(&schemas["Skater"], "age"), Some("Integer"));
+        assert_eq!(declared_type(&schemas["Skater"], "name"), None);
+        assert_eq!(resolve_type("ref(Skater)", &schemas), "String");
+    }
+}
@@ -0,0 +1,140 @@
+//! Request bodies parsed by their Content-Type before a route's steps run:
+//!
+//! - `application/json` (and `+json` types) becomes the JSON value;
+//! - `application/x-www-form-urlencoded` becomes an object of strings, with a list for a
+//!   repeated field;
+//! - `application/xml`, `text/xml` (and `+xml` types) becomes an object of the root
+//!   element's children, with text-only elements as strings.
+//!
+//! Steps see the result as `body` and the text as received as `body.raw`. Other bodies, and
+//! bodies sent without a Content-Type, stay text in `body`, as `parse-json` expects.
+
+use quick_xml::de::from_str as xml_from_str;
+use serde_json::{Map, Value as JsonValue};
+
+/// The flat context key holding the body as received.
+pub const RAW_BODY: &str = "body.raw";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Form,
+    Xml,
+}
+
+fn format_of(content_type: Option<&str>) -> Option<Format> {
+    let media_type = content_type?.split(';').next().unwrap_or_default().trim().to_lowercase();
+    match media_type.as_str() {
+        "application/json" => Some(Format::Json),
+        "application/x-www-form-urlencoded" => Some(Format::Form),
+        "application/xml" | "text/xml" => Some(Format::Xml),
+        t if t.ends_with("+json") => Some(Format::Json),
+        t if t.ends_with("+xml") => Some(Format::Xml),
+        _ => None,
+    }
+}
+
+/// Whether bodies of this Content-Type are parsed.
+pub fn is_parsed(content_type: Option<&str>) -> bool {
+    format_of(content_type).is_some()
+}
+
+/// The structured value of `raw` for its Content-Type; `Ok(None)` when the type is not
+/// one parsed here or the body is empty, and an error when the body does not match it.
+pub fn parse(raw: &str, content_type: Option<&str>) -> Result<Option<JsonValue>, String> {
+    let Some(format) = format_of(content_type).filter(|_| !raw.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match format {
+        Format::Json => parse_json(raw),
+        Format::Form => Ok(Some(parse_form(raw))),
+        Format::Xml => parse_xml(raw),
+    }
+}
+
+fn parse_json(raw: &str) -> Result<Option<JsonValue>, String> {
+    serde_json::from_str(raw).map(Some).map_err(|e| format!("invalid JSON body: {}", e))
+}
+
+fn parse_xml(raw: &str) -> Result<Option<JsonValue>, String> {
+    let value: JsonValue = xml_from_str(raw).map_err(|e| format!("invalid XML body: {}", e))?;
+    Ok(Some(collapse_text(value)))
+}
+
+/// quick-xml reads `<name>Ada</name>` as `{"$text": "Ada"}`; elements holding only text
+/// become that text.
+fn collapse_text(value: JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(mut fields) if fields.len() == 1 && fields.contains_key("$text") => {
+            fields.remove("$text").unwrap_or_default()
+        }
+        JsonValue::Object(fields) => JsonValue::Object(fields.into_iter().map(|(k, v)| (k, collapse_text(v))).collect()),
+        JsonValue::Array(items) => JsonValue::Array(items.into_iter().map(collapse_text).collect()),
+        other => other,
+    }
+}
+
+fn parse_form(raw: &str) -> JsonValue {
+    let mut fields = Map::new();
+    for pair in raw.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (key, value) = (decode_component(key), JsonValue::String(decode_component(value)));
+        match fields.get_mut(&key) {
+            Some(JsonValue::Array(values)) => values.push(value),
+            Some(first) => *first = JsonValue::Array(vec![first.take(), value]),
+            None => {
+                fields.insert(key, value);
+            }
+        }
+    }
+    JsonValue::Object(fields)
+}
+
+/// Decodes `+` and `%XX` escapes; malformed escapes are kept as written.
+fn decode_component(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 2;
+            }
+            (b'+', _) => out.push(b' '),
+            (b, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bodies_are_parsed_by_content_type() {
+        assert_eq!(
+            parse(r#"{"name": "Ada"}"#, Some("application/json; charset=utf-8")).unwrap(),
+            Some(json!({"name": "Ada"}))
+        );
+        assert!(parse("{oops", Some("application/json")).is_err());
+        assert_eq!(
+            parse("name=Ada+L%C3%B6f&tag=a&tag=b&bad=%zz", Some("application/x-www-form-urlencoded")).unwrap(),
+            Some(json!({"name": "Ada Löf", "tag": ["a", "b"], "bad": "%zz"}))
+        );
+        assert_eq!(
+            parse("<user><name>Ada</name><address><city>Oslo</city></address></user>", Some("text/xml")).unwrap(),
+            Some(json!({"name": "Ada", "address": {"city": "Oslo"}}))
+        );
+        assert_eq!(parse("plain", Some("text/plain")).unwrap(), None);
+        assert_eq!(parse(r#"{"a": 1}"#, None).unwrap(), None);
+        assert_eq!(parse("", Some("application/json")).unwrap(), None);
+    }
+}
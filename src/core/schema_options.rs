@@ -0,0 +1,116 @@
+//! Table options of a `@Schema`, set next to its fields or on a route serving it:
+//!
+//! ```rune
+//! @Schema/Post
+//! title = string
+//! timestamps = true
+//! soft_delete = true
+//! ```
+//!
+//! `timestamps` adds `created_at` and `updated_at` columns that inserts and updates set;
+//! `soft_delete` adds `deleted_at`, which `DELETE` sets instead of removing the record and
+//! reads skip. Options are booleans, so they never read as fields, whose values are types.
+//!
+//! A field type may be followed by modifiers: `email = string unique` gives the column a
+//! `UNIQUE` constraint, and inserts of a value already taken respond `409`.
+//!
+//! `embedding = vector(768)` holds an embedding of that many numbers; postgres stores it
+//! in a pgvector column, which `datasource vector_search` ranks by cosine distance.
+
+use crate::rune_ast::{RuneDocument, Section, Value};
+
+pub const CREATED_AT: &str = "created_at";
+pub const UPDATED_AT: &str = "updated_at";
+pub const DELETED_AT: &str = "deleted_at";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SchemaOptions {
+    pub timestamps: bool,
+    pub soft_delete: bool,
+}
+
+impl SchemaOptions {
+    /// The options set on `@Schema/<schema>` or on any route with `schema = <schema>`.
+    pub fn of(schema: &str, doc: &RuneDocument) -> Self {
+        let sections: Vec<_> = doc
+            .sections
+            .iter()
+            .filter(|s| match s.path.first().map(String::as_str) {
+                Some("Schema") => s.path.get(1).map(String::as_str) == Some(schema),
+                Some("Route") => s.kv.get("schema").and_then(Value::as_str) == Some(schema),
+                _ => false,
+            })
+            .collect();
+        let enabled = |option: &str| sections.iter().any(|s| matches!(s.kv.get(option), Some(Value::Bool(true))));
+        SchemaOptions {
+            timestamps: enabled("timestamps"),
+            soft_delete: enabled("soft_delete"),
+        }
+    }
+
+    /// The `datetime` columns the options add.
+    pub fn columns(&self) -> Vec<&'static str> {
+        let mut columns = Vec::new();
+        if self.timestamps {
+            columns.extend([CREATED_AT, UPDATED_AT]);
+        }
+        if self.soft_delete {
+            columns.push(DELETED_AT);
+        }
+        columns
+    }
+}
+
+/// A field's type without its modifiers: `string` for `string unique`.
+pub fn base_type(typ: &str) -> &str {
+    typ.split_whitespace().next().unwrap_or_default()
+}
+
+/// The dimensions of a `vector(<n>)` field type.
+pub fn vector_dimensions(typ: &str) -> Option<usize> {
+    let inner = base_type(typ).strip_prefix("vector(")?.strip_suffix(')')?;
+    inner.trim().parse().ok().filter(|dimensions| *dimensions > 0)
+}
+
+/// Whether a field type carries the `unique` modifier.
+pub fn is_unique(typ: &str) -> bool {
+    typ.split_whitespace().skip(1).any(|modifier| modifier == "unique")
+}
+
+/// The schema's `unique` fields, ordered by name.
+pub fn unique_fields(schema: &Section) -> Vec<String> {
+    let mut fields: Vec<String> = schema
+        .kv
+        .iter()
+        .filter(|(_, typ)| typ.as_str().is_some_and(is_unique))
+        .map(|(field, _)| field.clone())
+        .collect();
+    fields.sort();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    #[test]
+    fn options_come_from_the_schema_or_its_routes() {
+        let doc = parse_rune(
+            "#!RUNE\n@Schema/Post\ntitle = string\ntimestamps = true\n\n@Schema/Tag\nname = string\n\n@Route/CRUD /posts\nschema = Post\nsoft_delete = true\n",
+        )
+        .unwrap();
+        let post = SchemaOptions::of("Post", &doc);
+        assert_eq!(post, SchemaOptions { timestamps: true, soft_delete: true });
+        assert_eq!(post.columns(), [CREATED_AT, UPDATED_AT, DELETED_AT]);
+        assert_eq!(SchemaOptions::of("Tag", &doc).columns(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn modifiers_follow_the_type() {
+        let doc = parse_rune("#!RUNE\n@Schema/User\nemail = string unique\nname = string\n").unwrap();
+        assert_eq!(base_type("string unique"), "string");
+        assert!(is_unique("ref(Team.id) unique") && !is_unique("string"));
+        assert_eq!(unique_fields(&doc.sections[0]), ["email"]);
+    }
+}
@@ -0,0 +1,152 @@
+//! `@Seed/<Schema>` sections: rows inserted into the schema's datasource at startup when
+//! its table is empty.
+//!
+//! ```rune
+//! @Seed/Product
+//! + name = "Lamp"
+//!   price = 25
+//! + name = "Desk"
+//!   price = 180
+//! ```
+//!
+//! `data_source = <Name>` picks the datasource; without it the one used by routes serving
+//! the schema is taken, or the document's only datasource. SQL datasources are seeded in
+//! a transaction.
+
+use super::constants::to_json;
+use super::relations::routed_data_source;
+use super::AppState;
+use crate::builtins::builtin::data_source::{
+    begin_transaction, create_table, fetch_all_from_datasource, insert_into_datasource, is_mocked, query_datasource,
+};
+use crate::builtins::builtin::transaction;
+use crate::builtins::{BuiltinResult, Context};
+use crate::rune_ast::{RuneDocument, Section, Value};
+use serde_json::Value as JsonValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seed {
+    pub schema: String,
+    pub data_source: String,
+    pub records: Vec<JsonValue>,
+}
+
+fn string_key<'a>(section: &'a Section, key: &str) -> Option<&'a str> {
+    match section.kv.get(key) {
+        Some(Value::String(s)) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+/// The datasource a seed without `data_source` goes to: the one routes serving the schema
+/// use, or the document's only datasource.
+fn default_data_source(doc: &RuneDocument, schema: &str) -> Option<String> {
+    if let Some(name) = routed_data_source(doc, schema) {
+        return Some(name.to_string());
+    }
+    let mut data_sources = doc
+        .sections
+        .iter()
+        .filter(|s| s.path.first().map(String::as_str) == Some("DataSource"))
+        .filter_map(|s| s.path.get(1));
+    match (data_sources.next(), data_sources.next()) {
+        (Some(name), None) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// The document's seeds, in document order.
+pub fn extract_seeds(doc: &RuneDocument) -> Result<Vec<Seed>, String> {
+    let mut seeds = Vec::new();
+    for section in doc.sections.iter().filter(|s| s.path.first().map(String::as_str) == Some("Seed")) {
+        let schema = section.path.get(1).ok_or("@Seed needs a schema, e.g. @Seed/Product")?;
+        let data_source = match string_key(section, "data_source") {
+            Some(name) => name.to_string(),
+            None => default_data_source(doc, schema)
+                .ok_or_else(|| format!("seed {}: set data_source to the datasource to seed", schema))?,
+        };
+        let records = section
+            .records
+            .iter()
+            .map(|r| JsonValue::Object(r.kv.iter().map(|(k, v)| (k.clone(), to_json(v))).collect()))
+            .collect();
+        seeds.push(Seed { schema: schema.clone(), data_source, records });
+    }
+    Ok(seeds)
+}
+
+fn message(result: BuiltinResult) -> Option<String> {
+    match result {
+        BuiltinResult::Ok => None,
+        BuiltinResult::Error(msg) | BuiltinResult::Respond(_, msg) => Some(msg),
+    }
+}
+
+async fn apply(seed: &Seed, sql_type: Option<&str>, state: &AppState, ctx: &mut Context) -> Result<usize, String> {
+    let in_ds = ["in".to_string(), seed.data_source.clone()];
+    let from_ds = ["from".to_string(), seed.data_source.clone()];
+    if let Some(e) = message(create_table(&seed.schema, &in_ds, state).await) {
+        return Err(e);
+    }
+    if let Some(e) = message(fetch_all_from_datasource(&seed.schema, &from_ds, state, ctx, Some("existing")).await) {
+        return Err(e);
+    }
+    if ctx.get("existing").and_then(|rows| rows.as_array()).is_some_and(|rows| !rows.is_empty()) {
+        return Ok(0);
+    }
+    if sql_type.is_some() {
+        if let Some(e) = message(begin_transaction(&seed.data_source, state, ctx).await) {
+            return Err(e);
+        }
+    }
+    let into_ds = ["into".to_string(), seed.data_source.clone()];
+    for record in &seed.records {
+        ctx.insert("body".to_string(), record.clone());
+        if let Some(e) = message(insert_into_datasource(&seed.schema, &into_ds, state, ctx).await) {
+            return Err(e);
+        }
+    }
+    // Rows seeded with explicit ids leave the id sequence behind them
+    let explicit_ids = seed.records.iter().any(|r| r.get("id").is_some());
+    if sql_type == Some("postgres") && explicit_ids {
+        let sql = format!(
+            "SELECT setval(pg_get_serial_sequence('{0}', 'id'), (SELECT MAX(id) FROM {0}))",
+            seed.schema
+        );
+        if let Some(e) = message(query_datasource(&seed.data_source, &[sql], state, ctx, None).await) {
+            return Err(e);
+        }
+    }
+    if sql_type.is_some() {
+        if let Some(e) = message(transaction::commit(ctx, &seed.data_source).await) {
+            return Err(e);
+        }
+    }
+    Ok(seed.records.len())
+}
+
+/// Inserts each seed whose table is empty. Returns the seeds applied with their row counts.
+pub async fn seed(state: &AppState) -> Result<Vec<(Seed, usize)>, String> {
+    let mut applied = Vec::new();
+    for seed in extract_seeds(&state.doc)? {
+        let Some(ds) = state.data_sources.get(&seed.data_source) else {
+            return Err(format!("seed {}: datasource '{}' not found", seed.schema, seed.data_source));
+        };
+        if !state.schemas.contains_key(&seed.schema) {
+            return Err(format!("seed {}: no @Schema/{}", seed.schema, seed.schema));
+        }
+        let sql_type = match ds.kv.get("type") {
+            Some(Value::String(t)) if (t == "postgres" || t == "mysql") && !is_mocked(ds) => Some(t.as_str()),
+            _ => None,
+        };
+        let mut ctx = Context::new();
+        let result = apply(&seed, sql_type, state, &mut ctx).await;
+        transaction::rollback_all(&mut ctx).await;
+        match result {
+            Ok(0) => {}
+            Ok(count) => applied.push((seed, count)),
+            Err(e) => return Err(format!("seed {}: {}", seed.schema, e)),
+        }
+    }
+    Ok(applied)
+}
@@ -0,0 +1,329 @@
+//! Steps compiled once, when a document is loaded, rather than re-scanned each time they
+//! run.
+//!
+//! `compile` finds each step's assignment, pipeline stages, tokens and expression up front,
+//! and compiles the bodies of `if`, `match`, `retry` and `timeout` blocks with it.
+//! [`DocumentSteps`] holds every step series of a document (`run:`, `on_error:` and the
+//! websocket handlers), compiled.
+//!
+//! A step that cannot run (an unclosed quote, `total =` with no value) compiles to
+//! [`Step::Invalid`], named by its position (`step 3`, or `step 3.1` for the first step of
+//! the block at step 3); it is reported when the document is compiled and fails when reached.
+
+use super::expr::{self, Expr};
+use super::{pipe, tokenizer};
+use crate::rune_ast::{RuneDocument, Section, Value};
+use crate::util::{log, LogLevel};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The series that hold steps.
+const STEP_SERIES: [&str; 5] = ["run", "on_error", "on_connect", "on_message", "on_disconnect"];
+
+/// A compiled step. `respond` stays a [`Call`]: its status, format and body are resolved
+/// by the `respond` builtin against the request (the `Accept` header, inline objects),
+/// so there is nothing more to split ahead of time than any other builtin's arguments.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// `var = value`
+    Assign { source: String, var: String, value: Call },
+    /// A builtin call, pipeline or expression run for its result
+    Call(Call),
+    /// `if <cond>:` and the steps it guards
+    If { cond: String, expr: Option<Expr>, body: Vec<Step> },
+    /// `match <subject>:` and its arms, by their keys
+    Match { subject: String, arms: Vec<(String, Vec<Step>)> },
+    /// `retry <attempts> [options]:` or `retry <attempts> { step; step }`
+    Retry { args: Vec<String>, body: Vec<Step> },
+    /// `timeout <duration>:` or `timeout <duration> { step; step }`
+    Timeout { args: Vec<String>, body: Vec<Step> },
+    /// A step that fails with `message` when reached
+    Invalid { position: String, source: String, message: String },
+}
+
+/// A command or an assignment's value, split up ahead of time.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub text: String,
+    /// Stages of `source |> stage |> stage`
+    pub pipeline: Option<Vec<String>>,
+    /// Set when the text is a compound expression (see `expr`)
+    pub expr: Option<Expr>,
+    pub tokens: Vec<String>,
+}
+
+impl Step {
+    /// The step as written, for steps that are a single line.
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            Step::Assign { source, .. } | Step::Invalid { source, .. } => Some(source),
+            Step::Call(call) => Some(&call.text),
+            Step::If { .. } | Step::Match { .. } | Step::Retry { .. } | Step::Timeout { .. } => None,
+        }
+    }
+}
+
+impl Call {
+    /// A command: only a single call token (`users.filter(it.active)`) is an expression.
+    fn command(text: &str) -> Self {
+        let tokens = tokenizer::tokenize(text);
+        let expr = match tokens.as_slice() {
+            [token] if token.ends_with(')') => compound(text),
+            _ => None,
+        };
+        Call { text: text.to_string(), pipeline: stages(text), expr, tokens }
+    }
+
+    /// An assignment's value, which may be any expression.
+    fn value(text: &str) -> Self {
+        Call {
+            text: text.to_string(),
+            pipeline: stages(text),
+            expr: compound(text),
+            tokens: tokenizer::tokenize(text),
+        }
+    }
+}
+
+fn stages(text: &str) -> Option<Vec<String>> {
+    pipe::split_pipeline(text).map(|stages| stages.into_iter().map(str::to_string).collect())
+}
+
+fn compound(text: &str) -> Option<Expr> {
+    expr::parse(text).ok().filter(Expr::is_compound)
+}
+
+/// Compiles a `run:` series (or any other list of steps).
+pub fn compile(steps: &[Value]) -> Vec<Step> {
+    compile_at(steps, "")
+}
+
+fn compile_at(steps: &[Value], parent: &str) -> Vec<Step> {
+    let mut compiled = Vec::with_capacity(steps.len());
+    for (i, step) in steps.iter().enumerate() {
+        let position = if parent.is_empty() { (i + 1).to_string() } else { format!("{}.{}", parent, i + 1) };
+        match step {
+            Value::String(s) => compiled.push(compile_line(s.trim(), position)),
+            Value::Map(m) => compiled.push(compile_block(m, position)),
+            _ => {}
+        }
+    }
+    compiled
+}
+
+fn compile_block(map: &HashMap<String, Value>, position: String) -> Step {
+    let (key, body) = match map.iter().next() {
+        Some((key, Value::List(body))) if map.len() == 1 => (key.trim(), body),
+        _ => return invalid(position, "", "a block needs one `key:` and its steps".to_string()),
+    };
+    if let Some(open) = tokenizer::unclosed(key) {
+        return invalid(position, key, format!("`{}` is never closed", open));
+    }
+    let mut words = tokenizer::tokenize(key);
+    let keyword = if words.is_empty() { String::new() } else { words.remove(0) };
+    match keyword.as_str() {
+        "if" => {
+            let cond = key["if".len()..].trim().to_string();
+            Step::If { expr: expr::parse(&cond).ok(), body: compile_at(body, &position), cond }
+        }
+        "match" => Step::Match {
+            subject: key["match".len()..].trim().to_string(),
+            arms: compile_arms(body, &position),
+        },
+        "retry" => Step::Retry { args: words, body: compile_at(body, &position) },
+        "timeout" => Step::Timeout { args: words, body: compile_at(body, &position) },
+        _ => invalid(position, key, format!("`{}:` is not an if, match, retry or timeout block", key)),
+    }
+}
+
+/// `match` arms: each is a `key:` with its steps.
+fn compile_arms(arms: &[Value], parent: &str) -> Vec<(String, Vec<Step>)> {
+    arms.iter()
+        .enumerate()
+        .filter_map(|(i, arm)| {
+            let Value::Map(m) = arm else { return None };
+            let (key, Value::List(steps)) = m.iter().next()? else { return None };
+            Some((key.trim().to_string(), compile_at(steps, &format!("{}.{}", parent, i + 1))))
+        })
+        .collect()
+}
+
+fn compile_line(line: &str, position: String) -> Step {
+    if let Some(open) = tokenizer::unclosed(line) {
+        return invalid(position, line, format!("`{}` is never closed", open));
+    }
+    let Some(eq_pos) = tokenizer::find_assignment_equals(line) else {
+        return compile_command(line, position);
+    };
+    let (var, value) = (line[..eq_pos].trim(), line[eq_pos + 1..].trim());
+    if var.is_empty() {
+        return invalid(position, line, "the assignment has no target".to_string());
+    }
+    if value.is_empty() {
+        return invalid(position, line, format!("nothing is assigned to `{}`", var));
+    }
+    Step::Assign { source: line.to_string(), var: var.to_string(), value: Call::value(value) }
+}
+
+/// A command; `retry 3 { a; b }` and `timeout 2s { a; b }` compile their inline block.
+fn compile_command(line: &str, position: String) -> Step {
+    let call = Call::command(line);
+    let Some((name, args)) = call.tokens.split_first() else {
+        return Step::Call(call);
+    };
+    match (name.as_str(), split_inline_block(args)) {
+        ("retry", (args, Some(body))) => Step::Retry { args: args.to_vec(), body: compile_at(&body, &position) },
+        ("timeout", (args, Some(body))) => Step::Timeout { args: args.to_vec(), body: compile_at(&body, &position) },
+        _ => Step::Call(call),
+    }
+}
+
+/// Splits an inline `{ step; step }` block off the end of the args.
+pub fn split_inline_block(args: &[String]) -> (&[String], Option<Vec<Value>>) {
+    match args.last() {
+        Some(last) if last.starts_with('{') && last.ends_with('}') => {
+            let inner = &last[1..last.len() - 1];
+            let steps = tokenizer::split_top_level(inner, ';')
+                .into_iter()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| Value::String(s.to_string()))
+                .collect();
+            (&args[..args.len() - 1], Some(steps))
+        }
+        _ => (args, None),
+    }
+}
+
+fn invalid(position: String, source: &str, message: String) -> Step {
+    Step::Invalid { position, source: source.to_string(), message }
+}
+
+/// Why each invalid step in `steps` cannot run, as `step 3 (`total =`): ...`.
+pub fn problems(steps: &[Step]) -> Vec<String> {
+    let mut found = Vec::new();
+    for step in steps {
+        match step {
+            Step::Invalid { position, source, message } => {
+                found.push(format!("step {} (`{}`): {}", position, source, message))
+            }
+            Step::If { body, .. } | Step::Retry { body, .. } | Step::Timeout { body, .. } => {
+                found.extend(problems(body))
+            }
+            Step::Match { arms, .. } => found.extend(arms.iter().flat_map(|(_, body)| problems(body))),
+            Step::Assign { .. } | Step::Call(_) => {}
+        }
+    }
+    found
+}
+
+/// Logs the problems in `steps`, which belong to `owner` (e.g. `@Route/GET/users run`).
+pub fn report(owner: &str, steps: &[Step]) {
+    for problem in problems(steps) {
+        log(LogLevel::Error, &format!("{}: {}", owner, problem));
+    }
+}
+
+/// The compiled step series of a document, by section path and series name. Built once
+/// per document (see `extract_steps`) and kept in `AppState`, so routes, `@Function`s,
+/// `@Worker`s and error handlers run the same compiled steps.
+#[derive(Debug, Default)]
+pub struct DocumentSteps {
+    sections: HashMap<Vec<String>, HashMap<&'static str, Arc<[Step]>>>,
+}
+
+impl DocumentSteps {
+    /// Compiles the step series of `doc`, reporting the steps that cannot run.
+    pub fn compile(doc: &RuneDocument) -> Self {
+        let mut sections: HashMap<Vec<String>, HashMap<&'static str, Arc<[Step]>>> = HashMap::new();
+        for section in &doc.sections {
+            for name in STEP_SERIES {
+                let Some(series) = section.series.get(name) else {
+                    continue;
+                };
+                let steps = compile(series);
+                report(&format!("@{} {}", section.path.join("/"), name), &steps);
+                sections.entry(section.path.clone()).or_default().entry(name).or_insert_with(|| steps.into());
+            }
+        }
+        DocumentSteps { sections }
+    }
+
+    /// The compiled `series` of `section`.
+    pub fn get(&self, section: &Section, series: &str) -> Option<Arc<[Step]>> {
+        self.sections.get(section.path.as_slice())?.get(series).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rune_parser::parse_rune;
+
+    fn lines(steps: &[&str]) -> Vec<Value> {
+        steps.iter().map(|s| Value::String(s.to_string())).collect()
+    }
+
+    #[test]
+    fn steps_are_split_ahead_of_time() {
+        let steps = compile(&lines(&[
+            "total = price * qty",
+            "names = users |> map it.name",
+            "respond 200 total",
+            "users.filter(it.active)",
+            "retry 3 backoff=exp { x = 1; respond 200 x }",
+        ]));
+        let Step::Assign { var, value, .. } = &steps[0] else { panic!("expected an assignment") };
+        assert_eq!(var, "total");
+        assert!(value.expr.is_some());
+        let Step::Assign { value, .. } = &steps[1] else { panic!("expected an assignment") };
+        assert_eq!(value.pipeline.as_deref(), Some(&["users".to_string(), "map it.name".to_string()][..]));
+        let Step::Call(call) = &steps[2] else { panic!("expected a call") };
+        assert_eq!(call.tokens, ["respond", "200", "total"]);
+        assert!(call.expr.is_none());
+        let Step::Call(call) = &steps[3] else { panic!("expected a call") };
+        assert!(call.expr.is_some());
+        let Step::Retry { args, body } = &steps[4] else { panic!("expected a retry") };
+        assert_eq!(args, &["3", "backoff=exp"]);
+        assert_eq!(body.len(), 2);
+    }
+
+    #[test]
+    fn invalid_steps_are_reported_by_position() {
+        let mut block = HashMap::new();
+        block.insert("if total > 1".to_string(), Value::List(lines(&["log \"big"])));
+        let mut steps = lines(&["total =", "log ok"]);
+        steps.push(Value::Map(block));
+        assert_eq!(
+            problems(&compile(&steps)),
+            [
+                "step 1 (`total =`): nothing is assigned to `total`",
+                "step 3.1 (`log \"big`): `\"` is never closed",
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_inline_blocks_on_semicolons() {
+        let args = vec![
+            "3".to_string(),
+            "backoff=exp".to_string(),
+            r#"{ log "a; b"; respond 200 ok }"#.to_string(),
+        ];
+        let (opts, steps) = split_inline_block(&args);
+        assert_eq!(opts, &args[..2]);
+        let steps = steps.unwrap();
+        assert_eq!(steps.len(), 2);
+        assert!(matches!(&steps[0], Value::String(s) if s == r#"log "a; b""#));
+    }
+
+    #[test]
+    fn document_series_are_compiled_once() {
+        let doc = parse_rune("#!RUNE\n@Function/twice(n)\nrun:\n    n = n * 2\n    return n\n").unwrap();
+        let steps = DocumentSteps::compile(&doc);
+        let first = steps.get(&doc.sections[0], "run").unwrap();
+        assert!(Arc::ptr_eq(&first, &steps.get(&doc.sections[0], "run").unwrap()));
+        assert_eq!(first.len(), 2);
+        assert!(steps.get(&doc.sections[0], "on_error").is_none());
+    }
+}
@@ -0,0 +1,110 @@
+//! Step-by-step recording for `vectrune --trace`. While a future runs inside [`record`],
+//! every step executed in it is captured with its resolved arguments, the context entries it
+//! changed and what it produced. Outside of [`record`] nothing is captured.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::cell::RefCell;
+use std::future::Future;
+
+use super::{resolve_path, tokenizer};
+use crate::builtins::{Context, LAST_EXEC_RESULT};
+
+/// A context entry a step added, replaced or removed.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ContextChange {
+    pub key: String,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TracedStep {
+    pub step: String,
+    /// The command's tokens, with context references replaced by their values.
+    pub args: Vec<JsonValue>,
+    pub changes: Vec<ContextChange>,
+    /// The assigned value, or the result of a plain command.
+    pub result: Option<JsonValue>,
+    /// The status and body, when the step ended the request.
+    pub response: Option<(u16, String)>,
+}
+
+tokio::task_local! {
+    static RECORDER: RefCell<Vec<TracedStep>>;
+}
+
+/// Runs `fut`, returning its output with every step it executed.
+pub async fn record<F: Future>(fut: F) -> (F::Output, Vec<TracedStep>) {
+    RECORDER
+        .scope(RefCell::new(Vec::new()), async {
+            let output = fut.await;
+            (output, RECORDER.with(|steps| steps.take()))
+        })
+        .await
+}
+
+/// A copy of the context to diff against, if steps are being recorded.
+pub(crate) fn snapshot(ctx: &Context) -> Option<Context> {
+    RECORDER.try_with(|_| ctx.clone()).ok()
+}
+
+/// The tokens of `command`, each a literal or the value of the context path it names.
+fn resolved_args(before: &Context, command: &str) -> Vec<JsonValue> {
+    tokenizer::tokenize(command)
+        .iter()
+        .map(|token| match (tokenizer::unquote(token), token.parse::<i64>()) {
+            (Some(text), _) => JsonValue::String(text),
+            (None, Ok(n)) => JsonValue::from(n),
+            (None, Err(_)) => resolve_path(before, token, None)
+                .filter(|v| !v.is_null())
+                .unwrap_or_else(|| JsonValue::String(token.clone())),
+        })
+        .collect()
+}
+
+fn changes(before: &Context, after: &Context) -> Vec<ContextChange> {
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).filter(|k| !k.starts_with("___")).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|k| before.get(*k) != after.get(*k))
+        .map(|k| ContextChange {
+            key: k.clone(),
+            before: before.get(k).cloned(),
+            after: after.get(k).cloned(),
+        })
+        .collect()
+}
+
+/// Records a step run against `before`, leaving `ctx` as it ended.
+pub(crate) fn push(step: &str, assigned: Option<&str>, before: Context, ctx: &Context, response: Option<(u16, String)>) {
+    let command = match assigned {
+        Some(_) => tokenizer::find_assignment_equals(step).map_or(step, |eq| step[eq + 1..].trim()),
+        None => step,
+    };
+    let result = match assigned {
+        Some(var) => ctx.get(var).cloned(),
+        None => ctx.get(LAST_EXEC_RESULT).filter(|v| before.get(LAST_EXEC_RESULT) != Some(*v)).cloned(),
+    };
+    let traced = TracedStep {
+        step: step.to_string(),
+        args: resolved_args(&before, command),
+        changes: changes(&before, ctx),
+        result: result.filter(|_| response.is_none()),
+        response,
+    };
+    let _ = RECORDER.try_with(|steps| steps.borrow_mut().push(traced));
+}
+
+/// Records whether the condition of an `if` block held.
+pub(crate) fn push_condition(ctx: &Context, cond: &str, held: bool) {
+    let traced = TracedStep {
+        step: format!("if {}:", cond.trim()),
+        args: resolved_args(ctx, cond),
+        changes: Vec::new(),
+        result: Some(JsonValue::Bool(held)),
+        response: None,
+    };
+    let _ = RECORDER.try_with(|steps| steps.borrow_mut().push(traced));
+}
@@ -0,0 +1,249 @@
+//! Step tokenizer shared by step execution and builtin argument handling.
+//!
+//! Quoting rules:
+//! - `"..."` and `'...'` group text (including whitespace, `=`, `{` and `}`) into one token.
+//! - Inside quotes, `\` escapes the next character (`\"`, `\'`, `\\`, `\n`, `\t`, `\r`);
+//!   any other escape is kept as written, so regex patterns like `"^\d+$"` survive.
+//! - Outside quotes, `{ ... }`, `[ ... ]` and `( ... )` groups are kept whole, so inline
+//!   objects, lists and call arguments survive as a single argument.
+
+/// Where a character sits relative to quotes and brackets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Top level: outside any quotes or brackets.
+    Top,
+    /// Inside `{ }` / `[ ]` / `( )` but not inside quotes.
+    Nested,
+    /// Inside a quoted string, including its delimiting quotes.
+    Quoted,
+}
+
+/// Walks `s`, yielding each character's byte index and scope.
+pub fn scan(s: &str) -> Vec<(usize, char, Scope)> {
+    scan_with_open(s).0
+}
+
+/// The quote or bracket `s` leaves open, if any: `"a` gives `"` and `f(a` gives `(`.
+pub fn unclosed(s: &str) -> Option<char> {
+    scan_with_open(s).1
+}
+
+fn scan_with_open(s: &str) -> (Vec<(usize, char, Scope)>, Option<char>) {
+    let mut out = Vec::with_capacity(s.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut open: Vec<char> = Vec::new();
+    for (i, c) in s.char_indices() {
+        if let Some(q) = quote {
+            out.push((i, c, Scope::Quoted));
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' if starts_quote(s, i, c) => {
+                quote = Some(c);
+                out.push((i, c, Scope::Quoted));
+            }
+            '{' | '[' | '(' => {
+                open.push(c);
+                out.push((i, c, Scope::Nested));
+            }
+            '}' | ']' | ')' if !open.is_empty() => {
+                open.pop();
+                out.push((i, c, Scope::Nested));
+            }
+            _ => out.push((i, c, if open.is_empty() { Scope::Top } else { Scope::Nested })),
+        }
+    }
+    (out, quote.or(open.last().copied()))
+}
+
+/// A single quote only opens a string at the start of a word, so apostrophes in
+/// bare words (`don't`) stay literal. Double quotes always open a string.
+fn starts_quote(s: &str, i: usize, c: char) -> bool {
+    if c == '"' {
+        return true;
+    }
+    s[..i]
+        .chars()
+        .next_back()
+        .map(|prev| prev.is_whitespace() || "([{,:=".contains(prev))
+        .unwrap_or(true)
+}
+
+/// Splits a step into whitespace-separated tokens. Quoted strings and bracket groups
+/// stay whole and keep their delimiters; use [`unquote`] to get a string's value.
+pub fn tokenize(step: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for (_, c, scope) in scan(step) {
+        if scope == Scope::Top && c.is_whitespace() {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// If `token` is a single quoted string, returns its contents with escapes resolved.
+pub fn unquote(token: &str) -> Option<String> {
+    let q = token.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    if token.len() < 2 || !token.ends_with(q) {
+        return None;
+    }
+    let inner = &token[1..token.len() - 1];
+    // The opening quote must close at the very end, not earlier (`"a" + "b"`).
+    let mut escaped = false;
+    for c in inner.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == q {
+            return None;
+        }
+    }
+    if escaped {
+        return None;
+    }
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c @ ('\\' | '"' | '\'')) => out.push(c),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    Some(out)
+}
+
+/// Byte index of the first top-level occurrence of `target`: outside quotes and,
+/// unless `target` is itself a bracket, outside `{ }` / `[ ]` groups.
+pub fn find_top_level(s: &str, target: char) -> Option<usize> {
+    scan(s)
+        .into_iter()
+        .find(|(_, c, scope)| *c == target && *scope == Scope::Top)
+        .map(|(i, _, _)| i)
+}
+
+/// Splits `s` on top-level occurrences of `delimiter`.
+pub fn split_top_level(s: &str, delimiter: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0usize;
+    for (i, c, scope) in scan(s) {
+        if c == delimiter && scope == Scope::Top {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Byte index of the `=` in an assignment step (`var = cmd`), ignoring `==`, `!=`,
+/// `<=`, `>=`, `=>`, `~=` and any `=` inside quotes or brackets. The target must be a single
+/// path, so `retry 3 backoff=exp { ... }` and `log a=b` are commands, not assignments.
+pub fn find_assignment_equals(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let scanned = scan(s);
+    let eq = scanned
+        .iter()
+        .filter(|(_, c, scope)| *c == '=' && *scope == Scope::Top)
+        .map(|(i, _, _)| *i)
+        .find(|&i| {
+            let prev = if i > 0 { bytes[i - 1] } else { 0 };
+            let next = bytes.get(i + 1).copied().unwrap_or(0);
+            prev != b'=' && next != b'=' && prev != b'!' && next != b'>' && prev != b'<' && prev != b'>' && prev != b'~'
+        })?;
+    let target = s[..eq].trim();
+    let target_start = s.len() - s.trim_start().len();
+    let target_is_path = !target.is_empty()
+        && !scanned.iter().any(|(i, c, scope)| {
+            *i >= target_start && *i < target_start + target.len() && *scope != Scope::Nested && c.is_whitespace()
+        });
+    target_is_path.then_some(eq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_quoted_strings_and_groups_whole() {
+        assert_eq!(
+            tokenize(r#"log "a = b { c" 'single quoted' plain"#),
+            vec![r#"log"#, r#""a = b { c""#, "'single quoted'", "plain"]
+        );
+        assert_eq!(
+            tokenize(r#"ws.broadcast /ws { "type": "move", "x": x }"#),
+            vec!["ws.broadcast", "/ws", r#"{ "type": "move", "x": x }"#]
+        );
+        assert_eq!(
+            tokenize(r#"respond 200 (price * qty) users.filter(it.age > 30, ")")"#),
+            vec!["respond", "200", "(price * qty)", r#"users.filter(it.age > 30, ")")"#]
+        );
+    }
+
+    #[test]
+    fn resolves_escapes_when_unquoting() {
+        let tokens = tokenize(r#"log "say \"hi\" now" 'it\'s'"#);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(unquote(&tokens[1]).as_deref(), Some(r#"say "hi" now"#));
+        assert_eq!(unquote(&tokens[2]).as_deref(), Some("it's"));
+        assert_eq!(unquote(r#""a\nb""#).as_deref(), Some("a\nb"));
+        assert_eq!(unquote(r#""a" + "b""#), None);
+        assert_eq!(unquote("plain"), None);
+        assert_eq!(unquote(r#""^\d+$""#).as_deref(), Some(r"^\d+$"));
+    }
+
+    #[test]
+    fn apostrophes_in_bare_words_are_literal() {
+        assert_eq!(tokenize("log don't stop"), vec!["log", "don't", "stop"]);
+    }
+
+    #[test]
+    fn reports_unclosed_quotes_and_brackets() {
+        assert_eq!(unclosed(r#"log "a (b""#), None);
+        assert_eq!(unclosed(r#"log "oops"#), Some('"'));
+        assert_eq!(unclosed("x = { a: [1, 2 }"), Some('{'));
+        assert_eq!(unclosed("users.filter(it.age > 30"), Some('('));
+        assert_eq!(unclosed("log don't"), None);
+    }
+
+    #[test]
+    fn assignment_equals_ignores_quotes_and_comparisons() {
+        assert_eq!(find_assignment_equals("x = 1"), Some(2));
+        assert_eq!(find_assignment_equals(r#"log "a = b""#), None);
+        assert_eq!(find_assignment_equals(r#"log "q \" = \"""#), None);
+        assert_eq!(find_assignment_equals("ws.send /ws id { a=1 }"), None);
+        assert_eq!(find_assignment_equals("if a == b"), None);
+        assert_eq!(find_assignment_equals("if a >= b"), None);
+        assert_eq!(find_assignment_equals(r#"validate email ~= "@""#), None);
+        assert_eq!(find_assignment_equals("log a=b"), None);
+        assert_eq!(find_assignment_equals("retry 3 backoff=exp { x = 1 }"), None);
+        assert_eq!(find_assignment_equals("users[i + 1].age = 2"), Some(17));
+    }
+}
@@ -77,7 +77,7 @@ pub mod handler {
 
 pub async fn cold_start(rune_path: &str) {
     use std::sync::Arc;
-    use crate::core::{extract_schemas, extract_data_sources};
+    use crate::core::{extract_schemas, extract_data_sources, extract_steps};
     use crate::rune_parser::load_rune_document_from_path;
     let doc_result = match load_rune_document_from_path(std::path::Path::new(rune_path)) {
         Ok(doc) => Ok(Arc::new(doc)),
@@ -86,11 +86,13 @@ pub async fn cold_start(rune_path: &str) {
     let router = if let Ok(ref doc_arc) = doc_result {
         let schemas = Arc::new(extract_schemas(doc_arc));
         let data_sources = Arc::new(extract_data_sources(doc_arc));
+        let steps = Arc::new(extract_steps(doc_arc));
         let path = std::env::current_dir().unwrap();
         Some(crate::apps::build_vectrune_router(
             doc_arc.clone(),
             schemas,
             data_sources,
+            steps,
             path
         ).await)
     } else {
@@ -14,12 +14,13 @@ mod rune_parser;
 mod util;
 mod vectrune;
 
-use crate::core::{extract_data_sources, extract_schemas, get_app_type};
+use crate::core::{extract_data_sources, extract_schemas, extract_steps, get_app_type};
 use crate::rune_ast::{RuneDocument, Value};
-use crate::rune_parser::{load_rune_document_from_path, load_rune_document_from_str_with_base};
+use crate::rune_parser::remote::is_remote;
+use crate::rune_parser::{load_rune_document_from_source, load_rune_document_from_str_with_base};
 use crate::util::{api_doc, json_to_xml, log, set_log_level, LogLevel};
 use axum::serve;
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
 use std::convert::TryFrom;
 use std::process;
 use std::{env, fs};
@@ -40,29 +41,19 @@ async fn main() -> anyhow::Result<()> {
         .version(env!("CARGO_PKG_VERSION"))
         .author("David Thomas")
         .about("Vectrune: Structured data in motion.")
-        .arg(
-            Arg::new("SCRIPT")
-                .help("Path to the .rune, .vect, or .vectrune script, directory, or '-' to read from STDIN")
-                .num_args(1..)
-                .action(clap::ArgAction::Append)
-                .conflicts_with("ai"),
-        )
-        .arg(
-            Arg::new("input")
-                .short('i')
-                .long("input")
-                .help("Input data type")
-                .value_name("input_format")
-                .value_parser(["json", "rune", "xml", "yaml"]),
-        )
+        .arg(scripts_arg().conflicts_with("ai"))
+        .arg(input_arg())
         .arg(
             Arg::new("output")
                 .short('o')
                 .long("output")
                 .help("Output format")
                 .value_name("output_format")
-                .value_parser(["text", "json", "rune", "xml", "yaml", "curl", "html"]),
+                .value_parser(["text", "json", "rune", "xml", "yaml", "csv", "proto", "ts-client", "curl", "html"]),
         )
+        .arg(section_arg())
+        .arg(out_arg())
+        .arg(in_place_arg())
         .arg(
             Arg::new("path")
                 .long("path")
@@ -71,12 +62,7 @@ async fn main() -> anyhow::Result<()> {
                 .num_args(1)
                 .default_value("/"),
         )
-        .arg(
-            Arg::new("filter")
-                .long("filter")
-                .help("Filter output to only include sections matching a path, e.g. '@Memory' or '@Page'")
-                .value_name("FILTER_PATH"),
-        )
+        .arg(filter_arg())
         .arg(
             Arg::new("calculate")
                 .long("calculate")
@@ -84,13 +70,22 @@ async fn main() -> anyhow::Result<()> {
                 .value_name("EXPR")
                 .help("Perform a calculation over data, e.g. 'avg Section.field'"),
         )
+        .arg(
+            Arg::new("select")
+                .long("select")
+                .num_args(1)
+                .value_name("PATH")
+                .help("Print the values a path expression selects, e.g. 'Skateboarder[age>30].name'"),
+        )
         .arg(
             Arg::new("transform")
                 .long("transform")
                 .num_args(1)
                 .value_name("SPEC")
-                .help("Transform data into a new document, e.g. '@Target key:[@Section.field]'"),
+                .action(clap::ArgAction::Append)
+                .help("Transform data into a new document, e.g. '@Target key:[@Section.field]'; repeat to apply in sequence"),
         )
+        .arg(transform_file_arg())
         .arg(
             Arg::new("merge-with")
                 .long("merge-with")
@@ -98,28 +93,47 @@ async fn main() -> anyhow::Result<()> {
                 .value_name("MERGE_SPEC")
                 .help("Merge with another document: base_file@selector"),
         )
+        .arg(merge_strategy_arg())
+        .arg(each_arg())
         .arg(
             Arg::new("log-level")
                 .short('l')
                 .long("log-level")
                 .help("Set log level (debug, info, warn, error)")
                 .value_name("LEVEL")
-                .value_parser(["debug", "info", "warn", "error"]),
+                .value_parser(["debug", "info", "warn", "error"])
+                .global(true),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("log-level")
+                .help("Only log warnings and errors")
+                .global(true),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .num_args(1)
+                .value_name("FILE")
+                .help("Append log lines to FILE instead of writing them to stderr")
+                .global(true),
         )
         .arg(
             Arg::new("ai")
                 .long("ai")
                 .num_args(1)
                 .value_name("PROMPT")
-                .help("Send a CLI-assistant prompt to the local Ollama instance"),
+                .help("Send a CLI-assistant prompt to the configured AI provider (Ollama, OpenAI, Anthropic or Gemini)"),
         )
         .arg(
             Arg::new("ml")
                 .long("model")
-                .help("Set AI model for --ai prompt (default: phi4)")
+                .help("Set AI model for --ai prompt (default: @Ai model, $VECTRUNE_AI_MODEL, or phi4 on Ollama)")
                 .num_args(1)
-                .value_name("MODEL")
-                .default_value("phi4"),
+                .value_name("MODEL"),
         )
         .arg(
             Arg::new("port")
@@ -137,6 +151,36 @@ async fn main() -> anyhow::Result<()> {
                 .num_args(1)
                 .default_value("127.0.0.1"),
         )
+        .arg(
+            Arg::new("chaos")
+                .long("chaos")
+                .num_args(1)
+                .value_name("SPEC")
+                .help("Inject faults, e.g. \"latency=200ms,errors=5%\" (also latency=100ms..500ms, status=503)")
+                .global(true),
+        )
+        .arg(
+            Arg::new("mock-datasources")
+                .long("mock-datasources")
+                .help("Emulate postgres and mysql datasources in memory, seeded from @Memory and @Seed")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .num_args(2)
+                .value_names(["METHOD", "PATH"])
+                .help("Run the route matching METHOD PATH without a server and print a step-by-step trace"),
+        )
+        .arg(
+            Arg::new("body")
+                .long("body")
+                .num_args(1)
+                .value_name("FILE")
+                .requires("trace")
+                .help("Request body for --trace"),
+        )
         .arg(
             Arg::new("watch")
                 .short('w')
@@ -254,13 +298,468 @@ async fn main() -> anyhow::Result<()> {
             Command::new("repl")
                 .about("Start the Vectrune REPL shell")
                 .arg(
-                    Arg::new("log-level")
-                        .short('l')
-                        .long("log-level")
-                        .help("Set log level (debug, info, warn, error)")
-                        .value_name("LEVEL")
-                        .value_parser(["debug", "info", "warn", "error"]),
+                    Arg::new("SCRIPT")
+                        .help("Rune document to load; its memory, constants and data sources are available")
+                        .required(false)
+                        .index(1),
+                )
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert every matching file under a directory between formats")
+                .arg(
+                    Arg::new("SOURCE")
+                        .help("File or directory to convert (searched recursively)")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(cli::convert::FORMATS)
+                        .help("Format of the input files"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(cli::convert::FORMATS)
+                        .help("Format to write"),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .num_args(1)
+                        .value_name("DIR")
+                        .help("Output directory mirroring the source layout (default: next to each input)"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Write nothing; exit non-zero if any output would change"),
+                ),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Show, apply or revert the document's @Migration sections")
+                .arg(Arg::new("SCRIPT").help("Rune document with @Migration sections").required(true))
+                .arg(
+                    Arg::new("ACTION")
+                        .value_parser(["status", "up", "down"])
+                        .default_value("status")
+                        .help("status lists migrations; up applies pending ones; down reverts the latest"),
+                )
+                .arg(
+                    Arg::new("steps")
+                        .long("steps")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .help("How many migrations to apply or revert (default: all pending for up, 1 for down)"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print the SQL that would run without executing it"),
+                ),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run a step series against a document outside any HTTP request")
+                .arg(Arg::new("SCRIPT").help("Rune document; files resolve relative to it").required(true))
+                .arg(
+                    Arg::new("steps")
+                        .long("steps")
+                        .num_args(1)
+                        .required(true)
+                        .value_name("STEPS")
+                        .help("Steps separated by ';' or newlines, e.g. \"rows = csv.read 'u.csv'; return rows\""),
+                ),
+        )
+        .subcommand(
+            Command::new("upgrade")
+                .about("Rewrite deprecated builtin and section names to their current spelling")
+                .arg(Arg::new("FILES").help("Rune documents to rewrite in place").required(true).num_args(1..))
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Write nothing; exit non-zero if any file uses deprecated names"),
+                ),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Scaffold a starter project from a template")
+                .arg(Arg::new("DIR").help("Directory to create the project in").required(true))
+                .arg(
+                    Arg::new("template")
+                        .short('t')
+                        .long("template")
+                        .num_args(1)
+                        .value_parser(cli::new::TEMPLATES.to_vec())
+                        .help("Project template (asked for when omitted)"),
+                )
+                .arg(Arg::new("name").long("name").num_args(1).help("App name (asked for when omitted)"))
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(u16))
+                        .help("App port (asked for when omitted)"),
+                )
+                .arg(
+                    Arg::new("entities")
+                        .long("entities")
+                        .num_args(1)
+                        .value_name("NAMES")
+                        .help("Comma-separated entity names, e.g. book,author (asked for when omitted)"),
+                ),
+        )
+        .subcommand(
+            Command::new("render")
+                .about("Instantiate an @Template document with parameter values")
+                .arg(
+                    Arg::new("TEMPLATE")
+                        .help("Rune document with an @Template section")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("set")
+                        .long("set")
+                        .num_args(1)
+                        .action(clap::ArgAction::Append)
+                        .value_name("NAME=VALUE")
+                        .help("Value for a %NAME% placeholder (repeatable)"),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .help("Write the rendered document here instead of stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve a document, or one from a git repository redeployed on new commits")
+                .arg(
+                    scripts_arg()
+                        .required_unless_present("git")
+                        .conflicts_with("git"),
+                )
+                .arg(
+                    Arg::new("git")
+                        .long("git")
+                        .num_args(1)
+                        .value_name("REPO")
+                        .help("Repository URL (or local path) to clone and serve instead of SCRIPT"),
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .default_value("app.rune")
+                        .help("Document to serve, relative to the repository root"),
+                )
+                .arg(
+                    Arg::new("branch")
+                        .long("branch")
+                        .num_args(1)
+                        .value_name("BRANCH")
+                        .help("Branch to follow (default: the remote's default branch)"),
+                )
+                .arg(
+                    Arg::new("poll")
+                        .long("poll")
+                        .num_args(1)
+                        .value_name("INTERVAL")
+                        .default_value("60s")
+                        .help("How often to check for new commits, e.g. 30s or 5m"),
+                )
+                .arg(
+                    Arg::new("checkout")
+                        .long("checkout")
+                        .num_args(1)
+                        .value_name("DIR")
+                        .help("Directory for the working copy (default: under the temp directory)"),
                 )
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .num_args(1)
+                        .value_name("HOST")
+                        .help("Override the host to bind to"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .num_args(1)
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16))
+                        .help("Override the port to listen on"),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .short('w')
+                        .long("watch")
+                        .action(clap::ArgAction::SetTrue)
+                        .conflicts_with("git")
+                        .help("Watch for file changes and automatically restart the server"),
+                ),
+        )
+        .subcommand(
+            Command::new("calculate")
+                .about("Perform a calculation over data, e.g. 'avg Section.field'")
+                .arg(Arg::new("calculate").value_name("EXPR").required(true))
+                .arg(scripts_arg().required(true))
+                .arg(each_arg())
+                .arg(input_arg())
+                .arg(section_arg())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Print the result as text (a number or table) or JSON")
+                        .value_name("output_format")
+                        .value_parser(["text", "json"]),
+                ),
+        )
+        .subcommand(
+            Command::new("select")
+                .about("Print the values a path expression selects, e.g. 'Skateboarder[age>30].name'")
+                .arg(Arg::new("select").value_name("PATH").required(true))
+                .arg(scripts_arg().required(true))
+                .arg(each_arg())
+                .arg(input_arg())
+                .arg(section_arg())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Print the result as text (one value per line), JSON or YAML")
+                        .value_name("output_format")
+                        .value_parser(["text", "json", "yaml"]),
+                ),
+        )
+        .subcommand(
+            Command::new("transform")
+                .about("Transform data into a new document, e.g. '@Target key:[@Section.field]'")
+                .arg(Arg::new("transform").value_name("SPEC").required(true))
+                .arg(scripts_arg().required(true))
+                .arg(each_arg())
+                .arg(transform_file_arg())
+                .arg(input_arg())
+                .arg(document_output_arg())
+                .arg(section_arg())
+                .arg(filter_arg())
+                .arg(out_arg())
+                .arg(in_place_arg()),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge with another document: base_file@selector")
+                .arg(Arg::new("merge-with").value_name("SPEC").required(true))
+                .arg(scripts_arg().required(true))
+                .arg(each_arg())
+                .arg(merge_strategy_arg())
+                .arg(input_arg())
+                .arg(document_output_arg())
+                .arg(section_arg())
+                .arg(filter_arg())
+                .arg(out_arg())
+                .arg(in_place_arg()),
+        )
+        .subcommand(
+            Command::new("merge3")
+                .about("Three-way merge two edited copies of a document, marking conflicts")
+                .arg(Arg::new("BASE").help("The common ancestor (.rune, .json or .yaml)").required(true))
+                .arg(Arg::new("MINE").help("Our edited copy").required(true))
+                .arg(Arg::new("THEIRS").help("Their edited copy").required(true)),
+        )
+        .subcommand(
+            Command::new("ai")
+                .about("Send a CLI-assistant prompt to the configured AI provider (Ollama, OpenAI, Anthropic or Gemini)")
+                .args_conflicts_with_subcommands(true)
+                .arg(Arg::new("PROMPT").required(true))
+                .arg(ai_model_arg())
+                .subcommand(
+                    Command::new("new")
+                        .about("Generate a runnable Rune app from a description")
+                        .arg(Arg::new("DESCRIPTION").help("What the app should do").required(true))
+                        .arg(ai_model_arg())
+                        .arg(
+                            Arg::new("out")
+                                .long("out")
+                                .num_args(1)
+                                .value_name("FILE")
+                                .default_value("app.rune")
+                                .help("File to write the generated app to"),
+                        )
+                        .arg(
+                            Arg::new("retries")
+                                .long("retries")
+                                .num_args(1)
+                                .value_name("N")
+                                .default_value("3")
+                                .value_parser(clap::value_parser!(usize))
+                                .help("How many times to ask the model before giving up"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("explain")
+                        .about("Explain why a document fails to parse, check or run, and suggest a fix")
+                        .arg(Arg::new("SCRIPT").help("The .rune file to explain").required(true))
+                        .arg(ai_model_arg())
+                        .arg(
+                            Arg::new("trace")
+                                .long("trace")
+                                .num_args(2)
+                                .value_names(["METHOD", "PATH"])
+                                .help("Explain the step trace of a failing request instead"),
+                        )
+                        .arg(
+                            Arg::new("body")
+                                .long("body")
+                                .num_args(1)
+                                .value_name("FILE")
+                                .requires("trace")
+                                .help("Request body for --trace"),
+                        )
+                        .arg(
+                            Arg::new("apply")
+                                .long("apply")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Write the suggested fix back once it parses and passes the checks"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Parse documents and report broken references without running them")
+                .arg(scripts_arg().required(true)),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Show the structural differences between two documents")
+                .arg(Arg::new("A").help("The original document (.rune, .json or .yaml)").required(true))
+                .arg(Arg::new("B").help("The changed document").required(true))
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Print the changes as text, JSON or a Rune @Patch document")
+                        .value_name("output_format")
+                        .value_parser(["text", "json", "rune-patch"]),
+                )
+                .arg(
+                    Arg::new("exit-code")
+                        .long("exit-code")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Exit with status 1 when the documents differ"),
+                ),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Send the requests of the document's @Test sections and check the responses")
+                .arg(scripts_arg().required(true)),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Normalize spacing and blank lines in rune documents")
+                .arg(Arg::new("FILES").help("Rune documents to rewrite in place").required(true).num_args(1..))
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Write nothing; exit non-zero if any file is not formatted"),
+                ),
+        )
+        .subcommand(
+            Command::new("routes")
+                .about("Print the routes and mounts a document would serve, without starting it")
+                .arg(
+                    Arg::new("SCRIPT")
+                        .help("Path to the .rune file or directory")
+                        .required(true)
+                        .num_args(1..),
+                ),
+        )
+        .subcommand(
+            Command::new("ctl")
+                .about("Inspect and manage a running instance through its admin API")
+                .subcommand_required(true)
+                .arg(
+                    Arg::new("addr")
+                        .long("addr")
+                        .num_args(1)
+                        .value_name("HOST:PORT")
+                        .default_value("127.0.0.1:3000")
+                        .help("Address of the running instance"),
+                )
+                .arg(
+                    Arg::new("token")
+                        .long("token")
+                        .num_args(1)
+                        .value_name("TOKEN")
+                        .help("Admin bearer token (default: $VECTRUNE_ADMIN_TOKEN)"),
+                )
+                .subcommand(
+                    Command::new("memory")
+                        .about("Read and write shared memory")
+                        .subcommand_required(true)
+                        .subcommand(
+                            Command::new("get")
+                                .about("Print a memory entry")
+                                .arg(Arg::new("key").required(true)),
+                        )
+                        .subcommand(
+                            Command::new("set")
+                                .about("Set a memory entry (JSON or plain string)")
+                                .arg(Arg::new("key").required(true))
+                                .arg(Arg::new("value").required(true)),
+                        )
+                        .subcommand(
+                            Command::new("del")
+                                .about("Delete a memory entry")
+                                .arg(Arg::new("key").required(true)),
+                        ),
+                )
+                .subcommand(
+                    Command::new("routes")
+                        .about("Inspect mounted routes")
+                        .subcommand_required(true)
+                        .subcommand(Command::new("list").about("List mounted routes")),
+                )
+                .subcommand(
+                    Command::new("document")
+                        .about("Inspect or replace the active document")
+                        .subcommand_required(true)
+                        .subcommand(Command::new("get").about("Print the active document"))
+                        .subcommand(
+                            Command::new("push")
+                                .about("Upload a new document and switch traffic to it")
+                                .arg(Arg::new("file").required(true)),
+                        ),
+                )
+                .subcommand(
+                    Command::new("experiments")
+                        .about("Show how many requests each experiment variant has served"),
+                )
+                .subcommand(
+                    Command::new("datasources")
+                        .about("Show connection pool size, idle connections and wait times per datasource"),
+                )
+                .subcommand(
+                    Command::new("reload")
+                        .about("Reload the document from the files the server was started with"),
+                )
+                .subcommand(
+                    Command::new("rollback").about("Switch back to the previous document"),
+                ),
         )
         .subcommand(
             Command::new("knowledge")
@@ -280,26 +779,26 @@ async fn main() -> anyhow::Result<()> {
         )
         .get_matches();
 
-    let log_level = matches
-        .get_one::<String>("log-level")
-        .map(|s| s.as_str())
-        .or_else(|| {
-            if let Some(("repl", repl_matches)) = matches.subcommand() {
-                repl_matches
-                    .get_one::<String>("log-level")
-                    .map(|s| s.as_str())
-            } else {
-                None
-            }
-        });
+    if let Some(path) = matches.get_one::<String>("log-file") {
+        crate::util::set_log_file(std::path::Path::new(path))
+            .map_err(|e| anyhow::anyhow!("Cannot open log file {}: {}", path, e))?;
+    }
+    let log_level = matches.get_one::<String>("log-level").map(|s| s.as_str());
 
     match log_level {
+        _ if matches.get_flag("quiet") => set_log_level(LogLevel::Warn, true),
         Some("debug") => set_log_level(LogLevel::Debug, false),
         Some("info") => set_log_level(LogLevel::Info, false),
         Some("warn") => set_log_level(LogLevel::Warn, false),
         Some("error") => set_log_level(LogLevel::Error, false),
         _ => set_log_level(LogLevel::Info, true),
     }
+    if let Some(spec) = matches.get_one::<String>("chaos") {
+        apps::chaos::set_global_chaos(spec).map_err(|e| anyhow::anyhow!("Invalid --chaos: {}", e))?;
+    }
+    if matches.get_flag("mock-datasources") {
+        crate::builtins::builtin::data_source::set_mock_datasources(true);
+    }
 
     if let Some(("lambda", lambda_matches)) = matches.subcommand() {
         match lambda_matches.subcommand() {
@@ -345,8 +844,115 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    if let Some(("repl", _)) = matches.subcommand() {
-        crate::cli::handle_repl().await?;
+    if let Some(("repl", repl_matches)) = matches.subcommand() {
+        let script = repl_matches.get_one::<String>("SCRIPT").map(String::as_str);
+        crate::cli::handle_repl(script).await?;
+        return Ok(());
+    }
+
+    if let Some(("routes", routes_matches)) = matches.subcommand() {
+        let mut doc = RuneDocument { sections: Vec::new() };
+        for path in routes_matches.get_many::<String>("SCRIPT").into_iter().flatten() {
+            let loaded = load_rune_document_from_source(path).map_err(|e| anyhow::anyhow!(e))?;
+            doc.merge(loaded);
+        }
+        println!("{}", apps::routes::render_route_summary(&doc, None));
+        return Ok(());
+    }
+
+    if let Some(("convert", convert_matches)) = matches.subcommand() {
+        cli::handle_convert(convert_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("migrate", migrate_matches)) = matches.subcommand() {
+        cli::handle_migrate(migrate_matches).await?;
+        return Ok(());
+    }
+
+    if let Some(("run", run_matches)) = matches.subcommand() {
+        cli::handle_run(run_matches).await?;
+        return Ok(());
+    }
+
+    if let Some(("upgrade", upgrade_matches)) = matches.subcommand() {
+        cli::handle_upgrade(upgrade_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("new", new_matches)) = matches.subcommand() {
+        cli::handle_new(new_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("render", render_matches)) = matches.subcommand() {
+        cli::handle_render(render_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("serve", serve_matches)) = matches.subcommand() {
+        if serve_matches.get_one::<String>("git").is_some() {
+            cli::handle_git_serve(serve_matches).await?;
+            return Ok(());
+        }
+        return run_scripts(ScriptOptions::from_matches(serve_matches)).await;
+    }
+
+    if let Some(("calculate" | "select" | "transform" | "merge", script_matches)) = matches.subcommand() {
+        return run_scripts(ScriptOptions::from_matches(script_matches)).await;
+    }
+
+    if let Some(("ai", ai_matches)) = matches.subcommand() {
+        if let Some(("new", new_matches)) = ai_matches.subcommand() {
+            let description = new_matches.get_one::<String>("DESCRIPTION").map(|s| s.as_str()).unwrap_or_default();
+            let model = new_matches.get_one::<String>("model").map(|s| s.as_str());
+            let out = new_matches.get_one::<String>("out").map(|s| s.as_str()).unwrap_or("app.rune");
+            let retries = new_matches.get_one::<usize>("retries").copied().unwrap_or(3);
+            cli::handle_ai_new(description, model, out, retries).await?;
+            return Ok(());
+        }
+        if let Some(("explain", explain_matches)) = ai_matches.subcommand() {
+            let script = explain_matches.get_one::<String>("SCRIPT").map(|s| s.as_str()).unwrap_or_default();
+            let model = explain_matches.get_one::<String>("model").map(|s| s.as_str());
+            let trace: Option<Vec<&String>> = explain_matches.get_many::<String>("trace").map(|v| v.collect());
+            let trace = trace.as_deref().map(|t| (t[0].as_str(), t[1].as_str()));
+            let body = explain_matches.get_one::<String>("body").map(|s| s.as_str());
+            cli::handle_ai_explain(script, model, trace, body, explain_matches.get_flag("apply")).await?;
+            return Ok(());
+        }
+        let prompt = ai_matches.get_one::<String>("PROMPT").map(|s| s.as_str()).unwrap_or_default();
+        let model = ai_matches.get_one::<String>("model").map(|s| s.as_str());
+        cli::handle_ai(prompt, model).await?;
+        return Ok(());
+    }
+
+    if let Some(("check", check_matches)) = matches.subcommand() {
+        cli::handle_check(check_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("merge3", merge3_matches)) = matches.subcommand() {
+        cli::handle_merge3(merge3_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("diff", diff_matches)) = matches.subcommand() {
+        cli::handle_diff(diff_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("test", test_matches)) = matches.subcommand() {
+        cli::handle_test(test_matches).await?;
+        return Ok(());
+    }
+
+    if let Some(("fmt", fmt_matches)) = matches.subcommand() {
+        cli::handle_fmt(fmt_matches)?;
+        return Ok(());
+    }
+
+    if let Some(("ctl", ctl_matches)) = matches.subcommand() {
+        cli::handle_ctl(ctl_matches).await?;
         return Ok(());
     }
 
@@ -357,41 +963,174 @@ async fn main() -> anyhow::Result<()> {
 
     // Use gemini-1.5-flash for free google access, but allow override for users with local models or Ollama Pro
     // Requires Google AI key set as environment variable GEMINI_API_KEY
-    let model = matches.get_one::<String>("ml").map(|s| s.as_str());
-    let output_format = matches.get_one::<String>("output").map(|s| s.as_str());
-    let input_format = matches.get_one::<String>("input").map(|s| s.as_str());
-    let calc_expr = matches.get_one::<String>("calculate").map(|s| s.as_str());
-    let transform_spec = matches.get_one::<String>("transform").map(|s| s.as_str());
-    let merge_spec = matches.get_one::<String>("merge-with").map(|s| s.as_str());
-    let ai_prompt = matches.get_one::<String>("ai").map(|s| s.as_str());
-    let port_override = matches.get_one::<u16>("port").copied();
-    let host_override = matches.get_one::<String>("host").map(|s| s.as_str());
-    let watch_files = matches.get_flag("watch");
-    let filter_path = matches.get_one::<String>("filter").map(|s| s.as_str());
-    let render_path = matches
-        .get_one::<String>("path")
-        .map(|s| s.as_str())
-        .unwrap_or("/");
-
-    if let Some(prompt) = ai_prompt {
+    if let Some(prompt) = matches.get_one::<String>("ai") {
+        let model = matches.get_one::<String>("ml").map(|s| s.as_str());
         cli::handle_ai(prompt, model).await?;
         return Ok(());
     }
 
-    let script_paths: Vec<&str> = match matches.get_many::<String>("SCRIPT") {
-        Some(paths) => paths.map(|s| s.as_str()).collect(),
-        None => {
-            log(
-                LogLevel::Error,
-                "No Vectrune script provided. Pass a .rune file, .vect file, .vectrune file, directory, or '-' for STDIN.",
-            );
-            process::exit(1);
+    if matches.get_many::<String>("SCRIPT").is_none() {
+        log(
+            LogLevel::Error,
+            "No Vectrune script provided. Pass a .rune file, .vect file, .vectrune file, directory, or '-' for STDIN.",
+        );
+        process::exit(1);
+    }
+    run_scripts(ScriptOptions::from_matches(&matches)).await
+}
+
+/// What to do with the scripts named on the command line. Shared by the bare
+/// `vectrune app.rune` invocation and the `serve`, `calculate`, `select`, `transform` and `merge`
+/// subcommands; options a command does not define are left unset.
+struct ScriptOptions<'a> {
+    script_paths: Vec<&'a str>,
+    input_format: Option<&'a str>,
+    output_format: Option<&'a str>,
+    csv_section: Option<&'a str>,
+    filter_path: Option<&'a str>,
+    calc_expr: Option<&'a str>,
+    select_expr: Option<&'a str>,
+    transform_specs: Vec<&'a str>,
+    transform_file: Option<&'a str>,
+    merge_spec: Option<&'a str>,
+    merge_strategy: Option<&'a str>,
+    port_override: Option<u16>,
+    host_override: Option<&'a str>,
+    watch_files: bool,
+    render_path: &'a str,
+    trace: Option<(&'a str, &'a str)>,
+    body: Option<&'a str>,
+    out_path: Option<&'a str>,
+    in_place: bool,
+    each: bool,
+}
+
+impl<'a> ScriptOptions<'a> {
+    fn from_matches(matches: &'a ArgMatches) -> Self {
+        let one = |id: &str| {
+            matches
+                .try_get_one::<String>(id)
+                .ok()
+                .flatten()
+                .map(|s| s.as_str())
+        };
+        let many = |id: &str| -> Vec<&'a str> {
+            matches
+                .try_get_many::<String>(id)
+                .ok()
+                .flatten()
+                .map(|values| values.map(|s| s.as_str()).collect())
+                .unwrap_or_default()
+        };
+        let trace = many("trace");
+        ScriptOptions {
+            script_paths: many("SCRIPT"),
+            input_format: one("input"),
+            output_format: one("output"),
+            csv_section: one("section"),
+            filter_path: one("filter"),
+            calc_expr: one("calculate"),
+            select_expr: one("select"),
+            transform_specs: many("transform"),
+            transform_file: one("transform-file"),
+            merge_spec: one("merge-with"),
+            merge_strategy: one("merge-strategy"),
+            port_override: matches.try_get_one::<u16>("port").ok().flatten().copied(),
+            host_override: one("host"),
+            watch_files: matches.try_get_one::<bool>("watch").ok().flatten().copied().unwrap_or(false),
+            render_path: one("path").unwrap_or("/"),
+            trace: (trace.len() == 2).then(|| (trace[0], trace[1])),
+            body: one("body"),
+            out_path: one("out"),
+            in_place: matches.try_get_one::<bool>("in-place").ok().flatten().copied().unwrap_or(false),
+            each: matches.try_get_one::<bool>("each").ok().flatten().copied().unwrap_or(false),
         }
+    }
+}
+
+async fn run_scripts(options: ScriptOptions<'_>) -> anyhow::Result<()> {
+    let ScriptOptions {
+        script_paths,
+        input_format,
+        output_format,
+        csv_section,
+        filter_path,
+        calc_expr,
+        select_expr,
+        transform_specs,
+        transform_file,
+        merge_spec,
+        merge_strategy,
+        port_override,
+        host_override,
+        watch_files,
+        render_path,
+        trace,
+        body,
+        out_path,
+        in_place,
+        each,
+    } = options;
+
+    if let Some((method, path)) = trace {
+        cli::handle_trace(&script_paths, method, path, body).await?;
+        return Ok(());
+    }
+
+    let output_target = if in_place {
+        match script_paths.as_slice() {
+            [path] if *path != "-" && !is_remote(path) && std::path::Path::new(path).is_file() => {
+                Some(std::path::PathBuf::from(path))
+            }
+            _ => return Err(anyhow::anyhow!("--in-place needs exactly one input file")),
+        }
+    } else {
+        out_path.map(std::path::PathBuf::from)
     };
+    let output_format = output_format.or(output_target.as_deref().map(format_for_path));
+
+    let mut transform_specs: Vec<String> = transform_specs.into_iter().map(str::to_string).collect();
+    if let Some(path) = transform_file {
+        transform_specs.extend(cli::transform::read_spec_file(path).map_err(|e| anyhow::anyhow!(e))?);
+    }
+
+    if each {
+        if script_paths != ["-"] {
+            return Err(anyhow::anyhow!("--each reads a stream of documents from STDIN; pass '-' as the only input"));
+        }
+        use std::io::Read;
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        let mut results = Vec::new();
+        for (i, text) in split_documents(&buf, input_format)?.iter().enumerate() {
+            let process = || -> anyhow::Result<String> {
+                let mut doc = parse_content("-", text, input_format, csv_section)?;
+                if let Some(expr) = calc_expr {
+                    return Ok(match output_format {
+                        Some("json") => format!("{}\n", serde_json::to_string_pretty(&cli::calculate::calculate_to_json(&doc, expr).map_err(anyhow::Error::msg)?)?),
+                        _ => format!("{}\n", cli::calculate::calculate_to_string(&doc, expr).map_err(anyhow::Error::msg)?),
+                    });
+                }
+                if let Some(expr) = select_expr {
+                    let out = cli::select::select_to_string(&doc, expr, output_format).map_err(anyhow::Error::msg)?;
+                    return Ok(if out.is_empty() { out } else { format!("{}\n", out) });
+                }
+                reshape_document(&mut doc, &transform_specs, merge_spec, merge_strategy).map_err(anyhow::Error::msg)?;
+                if let Some(filter) = filter_path {
+                    doc = apply_filter(&doc, filter);
+                }
+                render_document(&doc, output_format, csv_section)
+            };
+            results.push(process().map_err(|e| anyhow::anyhow!("document {}: {}", i + 1, e))?);
+        }
+        emit_output(&results.join("---\n"), output_target.as_deref())?;
+        return Ok(());
+    }
 
     if input_format.is_none()
         && calc_expr.is_none()
-        && transform_spec.is_none()
+        && select_expr.is_none()
+        && transform_specs.is_empty()
         && merge_spec.is_none()
         && output_format.is_none()
         && script_paths.len() == 1
@@ -461,7 +1200,7 @@ async fn main() -> anyhow::Result<()> {
                     );
                     process::exit(1);
                 });
-                let stdin_doc = parse_content("-", &buf, input_format)?;
+                let stdin_doc = parse_content("-", &buf, input_format, csv_section)?;
                 if let Some(ref mut d) = doc {
                     d.merge(stdin_doc);
                 } else {
@@ -469,8 +1208,10 @@ async fn main() -> anyhow::Result<()> {
                 }
             } else {
                 let path = std::path::Path::new(path_str);
-                if input_format.is_none() && (path.is_dir() || path.extension().and_then(|s| s.to_str()) == Some("rune")) {
-                    let file_doc = load_rune_document_from_path(path).map_err(|e| anyhow::anyhow!(e))?;
+                if input_format.is_none()
+                    && (is_remote(path_str) || path.is_dir() || path.extension().and_then(|s| s.to_str()) == Some("rune"))
+                {
+                    let file_doc = load_rune_document_from_source(path_str).map_err(|e| anyhow::anyhow!(e))?;
                     if let Some(ref mut d) = doc {
                         d.merge(file_doc);
                     } else {
@@ -481,7 +1222,7 @@ async fn main() -> anyhow::Result<()> {
                         log(LogLevel::Error, &format!("Error reading script {}: {}", path_str, err));
                         process::exit(1);
                     });
-                    let file_doc = parse_content(path_str, &content, input_format)?;
+                    let file_doc = parse_content(path_str, &content, input_format, csv_section)?;
                     if let Some(ref mut d) = doc {
                         d.merge(file_doc);
                     } else {
@@ -495,37 +1236,25 @@ async fn main() -> anyhow::Result<()> {
 
         // Calculation mode
         if let Some(expr) = calc_expr {
-            if let Err(e) = crate::cli::handle_calculate(&doc, expr) {
+            if let Err(e) = crate::cli::handle_calculate(&doc, expr, output_format) {
                 log(LogLevel::Error, &format!("{}", e));
                 process::exit(1);
             }
             process::exit(0);
         }
 
-        // Transform mode
-        if let Some(spec) = transform_spec {
-            match crate::cli::handle_transform(&doc, spec) {
-                Ok(new_doc) => {
-                    doc.update_from(&new_doc);
-                }
-                Err(e) => {
-                    log(LogLevel::Error, &format!("Transform error: {}", e));
-                    process::exit(1);
-                }
+        // Select mode
+        if let Some(expr) = select_expr {
+            if let Err(e) = crate::cli::handle_select(&doc, expr, output_format) {
+                log(LogLevel::Error, &e);
+                process::exit(1);
             }
+            process::exit(0);
         }
 
-        // Merge mode
-        if let Some(spec) = merge_spec {
-            match crate::cli::handle_merge(&doc, spec) {
-                Ok(merged_doc) => {
-                    doc = merged_doc;
-                }
-                Err(e) => {
-                    log(LogLevel::Error, &format!("Merge error: {}", e));
-                    process::exit(1);
-                }
-            }
+        if let Err(e) = reshape_document(&mut doc, &transform_specs, merge_spec, merge_strategy) {
+            log(LogLevel::Error, &e);
+            process::exit(1);
         }
 
         let app_type = get_app_type(&doc);
@@ -534,12 +1263,12 @@ async fn main() -> anyhow::Result<()> {
             match get_frontend_type(&doc) {
                 Some("rune-web") => {
                     let html = crate::apps::rune_web::render_html_for_path(&doc, render_path).await?;
-                    println!("{}", html);
+                    emit_output(&format!("{}\n", html), output_target.as_deref())?;
                     break;
                 }
                 Some("static") => {
                     let html = render_static_html_for_path(&doc, &script_paths, render_path)?;
-                    println!("{}", html);
+                    emit_output(&format!("{}\n", html), output_target.as_deref())?;
                     break;
                 }
                 _ => {
@@ -567,18 +1296,12 @@ async fn main() -> anyhow::Result<()> {
                 .and_then(|val| val.as_u64())
                 .and_then(|v| u16::try_from(v).ok());
             let effective_port = port_override.unwrap_or(doc_port.unwrap_or(3000));
+            let mcp_stdio = app_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case("MCP"))
+                && apps::mcp::uses_stdio(&doc);
 
-            log(
-                LogLevel::Info,
-                &format!(
-                    "Starting Vectrune {} application...",
-                    app_type.as_deref().unwrap_or("REST")
-                ),
-            );
-            log(LogLevel::Info, "Press Ctrl+C to stop the server.");
             log(LogLevel::Debug, &format!("Config: \n{}", api_doc(&doc)));
 
-            let rune_dir = if script_paths.contains(&"-") {
+            let rune_dir = if script_paths.contains(&"-") || is_remote(script_paths[0]) {
                 env::current_dir()?
             } else {
                 let p = std::path::Path::new(script_paths[0]);
@@ -591,12 +1314,47 @@ async fn main() -> anyhow::Result<()> {
                 }
             };
 
+            apps::admin::set_reload_sources(
+                script_paths
+                    .iter()
+                    .filter(|p| input_format.is_none() && **p != "-" && !is_remote(p))
+                    .map(std::path::PathBuf::from)
+                    .collect(),
+            );
+            for migration in crate::core::migrations::migrate(&doc, crate::core::migrations::Direction::Up, None, false)
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?
+            {
+                log(
+                    LogLevel::Info,
+                    &format!("Applied migration {} ({})", migration.version, migration.data_source),
+                );
+            }
             let schemas = std::sync::Arc::new(extract_schemas(&doc));
             let data_sources = std::sync::Arc::new(extract_data_sources(&doc));
+            let steps = std::sync::Arc::new(extract_steps(&doc));
+            let seed_state = crate::core::AppState {
+                doc: std::sync::Arc::new(doc.clone()),
+                schemas: schemas.clone(),
+                data_sources: data_sources.clone(),
+                steps: steps.clone(),
+                path: rune_dir.clone(),
+            };
+            for (seed, count) in crate::core::seeds::seed(&seed_state).await.map_err(|e| anyhow::anyhow!(e))? {
+                log(
+                    LogLevel::Info,
+                    &format!("Seeded {} {} rows into {}", count, seed.schema, seed.data_source),
+                );
+            }
+            if mcp_stdio {
+                apps::mcp::serve_stdio(seed_state).await?;
+                break;
+            }
             let app = apps::build_vectrune_router(
                 std::sync::Arc::new(doc.clone()),
                 schemas.clone(),
                 data_sources.clone(),
+                steps.clone(),
                 rune_dir.clone(),
             )
             .await;
@@ -604,7 +1362,10 @@ async fn main() -> anyhow::Result<()> {
             let listener = TcpListener::bind(host_address.clone()).await?;
             log(
                 LogLevel::Info,
-                &format!("Vectrune runtime listening on http://{}", host_address),
+                &format!(
+                    "{}\n\nPress Ctrl+C to stop the server.",
+                    apps::routes::render_route_summary(&doc, Some(&host_address))
+                ),
             );
 
             if let Some(ref rx) = watch_rx {
@@ -654,6 +1415,7 @@ async fn main() -> anyhow::Result<()> {
                     .unwrap_or("localhost");
                 let host_port = format!("{}:{}", doc_host, doc_port);
                 let routes = doc.get_sections("Route");
+                let mut curl = String::new();
                 for route in routes {
                     if let Some(path) = route.path.join("/").strip_prefix("Route/").and_then(|p| {
                         p.strip_prefix(&format!(
@@ -685,24 +1447,25 @@ async fn main() -> anyhow::Result<()> {
                                 }
                             }
                             obj_body.push_str("\n}");
-                            println!("curl -X GET    http://{}/{}", host_port, collection_path);
-                            println!("curl -X POST   http://{}/{} \\", host_port, collection_path);
-                            println!("     -H 'Content-Type: application/json' \\");
-                            println!("     -d '{}'", obj_body.replace('\'', "\\'"));
-                            println!("curl -X GET    http://{}/{}/123", host_port, collection_path);
-                            println!("curl -X PUT    http://{}/{}/123 \\", host_port, collection_path);
-                            println!("     -H 'Content-Type: application/json' \\");
-                            println!("     -d '{}'", obj_body.replace('\'', "\\'"));
-                            println!("curl -X DELETE http://{}/{}/123", host_port, collection_path);
+                            curl.push_str(&format!("curl -X GET    http://{}/{}\n", host_port, collection_path));
+                            curl.push_str(&format!("curl -X POST   http://{}/{} \\\n", host_port, collection_path));
+                            curl.push_str("     -H 'Content-Type: application/json' \\\n");
+                            curl.push_str(&format!("     -d '{}'\n", obj_body.replace('\'', "\\'")));
+                            curl.push_str(&format!("curl -X GET    http://{}/{}/123\n", host_port, collection_path));
+                            curl.push_str(&format!("curl -X PUT    http://{}/{}/123 \\\n", host_port, collection_path));
+                            curl.push_str("     -H 'Content-Type: application/json' \\\n");
+                            curl.push_str(&format!("     -d '{}'\n", obj_body.replace('\'', "\\'")));
+                            curl.push_str(&format!("curl -X DELETE http://{}/{}/123\n", host_port, collection_path));
                             continue;
                         }
                         let mut curl_cmd = format!("curl -X {} http://{}/{}", method, host_port, path);
                         if let Some(Value::String(desc)) = route.kv.get("description") {
                             curl_cmd.push_str(&format!("  # {}", desc));
                         }
-                        println!("{}", curl_cmd);
+                        curl.push_str(&format!("{}\n", curl_cmd));
                     }
                 }
+                emit_output(&curl, output_target.as_deref())?;
                 break;
             }
 
@@ -714,48 +1477,210 @@ async fn main() -> anyhow::Result<()> {
                 doc = apply_filter(&doc, filter);
             }
 
-            match output_format {
-                Some("json") => {
-                    let json_output =
-                        serde_json::to_string_pretty(&doc.to_json()).unwrap_or_else(|err| {
-                            log(LogLevel::Error, &format!("Error converting to JSON: {}", err));
-                            process::exit(1);
-                        });
-                    println!("{}", json_output);
-                }
-                Some("text") => {
-                    println!("{}", doc);
-                }
-                Some("rune") => {
-                    println!("{}", doc);
-                }
-                Some("xml") => {
-                    let xml_output = json_to_xml(&doc.to_json(), "root");
-                    println!("{}", xml_output);
-                }
-                Some("yaml") => {
-                    let yaml_output = serde_yaml::to_string(&doc.to_json()).unwrap_or_else(|err| {
-                        eprintln!("Error converting to YAML: {}", err);
-                        process::exit(1);
-                    });
-                    println!("{}", yaml_output);
-                }
-                _ => {
-                    println!("{}", doc);
-                }
-            }
+            let rendered = render_document(&doc, output_format, csv_section)?;
+            emit_output(&rendered, output_target.as_deref())?;
             break;
         }
     }
     Ok(())
 }
 
+fn scripts_arg() -> Arg {
+    Arg::new("SCRIPT")
+        .help("Path to the .rune, .vect, or .vectrune script, directory, or '-' to read from STDIN")
+        .num_args(1..)
+        .action(clap::ArgAction::Append)
+}
+
+fn input_arg() -> Arg {
+    Arg::new("input")
+        .short('i')
+        .long("input")
+        .help("Input data type")
+        .value_name("input_format")
+        .value_parser(["json", "rune", "xml", "yaml", "csv"])
+}
+
+fn document_output_arg() -> Arg {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .help("Output format")
+        .value_name("output_format")
+        .value_parser(["text", "json", "rune", "xml", "yaml", "csv"])
+}
+
+fn out_arg() -> Arg {
+    Arg::new("out")
+        .long("out")
+        .num_args(1)
+        .value_name("FILE")
+        .conflicts_with("in-place")
+        .help("Write the output to FILE instead of stdout; the format follows its extension unless -o is given")
+}
+
+fn merge_strategy_arg() -> Arg {
+    Arg::new("merge-strategy")
+        .long("merge-strategy")
+        .num_args(1)
+        .value_name("STRATEGY")
+        .value_parser(["deep", "replace", "append", "union"])
+        .help("How values both documents define are combined, unless the selector ends in !STRATEGY (default: deep)")
+}
+
+fn ai_model_arg() -> Arg {
+    Arg::new("model")
+        .long("model")
+        .num_args(1)
+        .value_name("MODEL")
+        .help("AI model to prompt (default: @Ai model, $VECTRUNE_AI_MODEL, or phi4 on Ollama)")
+}
+
+fn each_arg() -> Arg {
+    Arg::new("each")
+        .long("each")
+        .action(clap::ArgAction::SetTrue)
+        .help("Treat STDIN as a stream of documents (NDJSON, or documents separated by ---) and process each one")
+}
+
+fn transform_file_arg() -> Arg {
+    Arg::new("transform-file")
+        .long("transform-file")
+        .num_args(1)
+        .value_name("FILE")
+        .help("Apply the transform specs in FILE, one per line, after any given on the command line")
+}
+
+fn in_place_arg() -> Arg {
+    Arg::new("in-place")
+        .long("in-place")
+        .action(clap::ArgAction::SetTrue)
+        .help("Write the output back over the single input file, in that file's format unless -o is given")
+}
+
+/// The output format implied by a file name, for `--out` and `--in-place` without `-o`.
+fn format_for_path(path: &std::path::Path) -> &'static str {
+    match path.extension().and_then(|s| s.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("json") => "json",
+        Some("yaml" | "yml") => "yaml",
+        Some("xml") => "xml",
+        Some("csv") => "csv",
+        Some("proto") => "proto",
+        Some("ts") => "ts-client",
+        Some("html" | "htm") => "html",
+        Some("txt") => "text",
+        _ => "rune",
+    }
+}
+
+/// Prints `text`, or replaces `target` with it.
+/// Runs the `--transform` specs in order, then `--merge-with`, over a loaded document.
+fn reshape_document(
+    doc: &mut RuneDocument,
+    transform_specs: &[String],
+    merge_spec: Option<&str>,
+    merge_strategy: Option<&str>,
+) -> Result<(), String> {
+    // Transform mode: each spec reshapes the previous one's output
+    for spec in transform_specs {
+        let new_doc = crate::cli::handle_transform(doc, spec).map_err(|e| format!("Transform error: {}", e))?;
+        doc.update_from(&new_doc);
+    }
+    // Merge mode
+    if let Some(spec) = merge_spec {
+        let strategy = merge_strategy.map(str::parse).transpose()?;
+        *doc = crate::cli::handle_merge_with(doc, spec, strategy.unwrap_or_default())
+            .map_err(|e| format!("Merge error: {}", e))?;
+    }
+    Ok(())
+}
+
+/// A document in one of the `-o` document formats (json, xml, yaml, csv, proto,
+/// ts-client, or rune text by default).
+fn render_document(doc: &RuneDocument, output_format: Option<&str>, csv_section: Option<&str>) -> anyhow::Result<String> {
+    Ok(match output_format {
+        Some("json") => format!("{}\n", serde_json::to_string_pretty(&doc.to_json())?),
+        Some("xml") => format!("{}\n", json_to_xml(&doc.to_json(), "root")),
+        Some("yaml") => format!("{}\n", serde_yaml::to_string(&doc.to_json())?),
+        Some("csv") => doc.to_csv(csv_section).map_err(|e| anyhow::anyhow!(e))?,
+        Some("proto") => {
+            let file = apps::grpc::proto_file(doc, &extract_schemas(doc)).map_err(|e| anyhow::anyhow!(e))?;
+            apps::grpc::render_proto(&file)
+        }
+        Some("ts-client") => apps::rest::ts_client::generate_ts_client(doc),
+        // text, rune, or default
+        _ => format!("{}\n", doc),
+    })
+}
+
+/// Splits a stream of documents read with `--each`: JSON values one after another (or
+/// NDJSON), or for other formats documents separated by `---` lines, where each new
+/// `#!RUNE` header also starts a document.
+fn split_documents(input: &str, input_format: Option<&str>) -> anyhow::Result<Vec<String>> {
+    if input_format == Some("json") {
+        return serde_json::Deserializer::from_str(input)
+            .into_iter::<serde_json::Value>()
+            .map(|value| Ok(value?.to_string()))
+            .collect();
+    }
+    let mut documents = vec![String::new()];
+    for line in input.lines() {
+        let current = documents.last_mut().unwrap();
+        let starts_rune = line.starts_with("#!RUNE") && !current.trim().is_empty();
+        if line.trim_end() == "---" || starts_rune {
+            documents.push(String::new());
+        }
+        if line.trim_end() != "---" {
+            let current = documents.last_mut().unwrap();
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    documents.retain(|doc| !doc.trim().is_empty());
+    Ok(documents)
+}
+
+fn emit_output(text: &str, target: Option<&std::path::Path>) -> anyhow::Result<()> {
+    match target {
+        Some(path) => crate::util::replace_file(path, text.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Error writing {}: {}", path.display(), e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn section_arg() -> Arg {
+    Arg::new("section")
+        .long("section")
+        .num_args(1)
+        .value_name("NAME")
+        .help("Section whose records -o csv writes, and that -i csv reads rows into (default: the file name)")
+}
+
+fn filter_arg() -> Arg {
+    Arg::new("filter")
+        .long("filter")
+        .help("Filter output to only include sections matching a path, e.g. '@Memory' or '@Page'")
+        .value_name("FILTER_PATH")
+}
+
 fn parse_content(
     path: &str,
     content: &str,
     input_format: Option<&str>,
+    csv_section: Option<&str>,
 ) -> anyhow::Result<crate::rune_ast::RuneDocument> {
-    match input_format {
+    let file = std::path::Path::new(path);
+    let is_csv_file = file.extension().and_then(|s| s.to_str()) == Some("csv");
+    match input_format.or(is_csv_file.then_some("csv")) {
+        Some("csv") => {
+            let section = csv_section
+                .or(file.file_stem().and_then(|s| s.to_str()).filter(|_| path != "-"))
+                .unwrap_or("Rows");
+            crate::rune_ast::RuneDocument::from_csv(content, section).map_err(|e| anyhow::anyhow!(e))
+        }
         Some("json") => {
             let json_value: serde_json::Value = serde_json::from_str(content)?;
             Ok(crate::rune_ast::RuneDocument::from_json(&json_value))
@@ -6,6 +6,7 @@ pub mod reactive;
 pub mod hooks;
 pub mod hook_parser;
 pub mod hook_context;
+pub mod vector;
 
 #[allow(dead_code)]
 pub use reactive::{ReactiveMemoryBackend, ReactiveMemoryConfig, BroadcastMode};
@@ -0,0 +1,91 @@
+//! The `vector` memory namespace: embeddings kept in process memory with a payload each,
+//! searched by cosine similarity. Like the key-value memory, it lives as long as the
+//! process and is shared by every route.
+
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+pub static VECTORS: Lazy<VectorIndex> = Lazy::new(VectorIndex::default);
+
+#[derive(Default)]
+pub struct VectorIndex {
+    entries: RwLock<HashMap<String, (Vec<f64>, Value)>>,
+}
+
+impl VectorIndex {
+    /// Stores or replaces the embedding and payload under `key`. Every embedding in the
+    /// index must have the same length.
+    pub fn upsert(&self, key: &str, embedding: Vec<f64>, payload: Value) -> Result<(), String> {
+        if embedding.is_empty() {
+            return Err("the embedding is empty".to_string());
+        }
+        let mut entries = self.entries.write().unwrap();
+        let other = entries.iter().find(|(other, _)| other.as_str() != key);
+        if let Some((_, (existing, _))) = other.filter(|(_, (existing, _))| existing.len() != embedding.len()) {
+            return Err(format!(
+                "the embedding has {} dimensions but the index holds {}",
+                embedding.len(),
+                existing.len()
+            ));
+        }
+        entries.insert(key.to_string(), (embedding, payload));
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> bool {
+        self.entries.write().unwrap().remove(key).is_some()
+    }
+
+    /// The `top_k` entries most similar to `query`, best first, as `{key, score, payload}`.
+    pub fn search(&self, query: &[f64], top_k: usize) -> Vec<Value> {
+        let entries = self.entries.read().unwrap();
+        let mut scored: Vec<(f64, &String, &Value)> = entries
+            .iter()
+            .filter(|(_, (embedding, _))| embedding.len() == query.len())
+            .map(|(key, (embedding, payload))| (cosine_similarity(query, embedding), key, payload))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        scored
+            .into_iter()
+            .take(top_k)
+            .map(|(score, key, payload)| json!({ "key": key, "score": score, "payload": payload }))
+            .collect()
+    }
+}
+
+/// The cosine of the angle between two vectors of the same length; 0 when either is zero.
+pub fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f64]| v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    match norm(a) * norm(b) {
+        0.0 => 0.0,
+        product => dot / product,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_ranks_entries_by_cosine_similarity() {
+        let index = VectorIndex::default();
+        index.upsert("north", vec![0.0, 1.0], json!({ "name": "North" })).unwrap();
+        index.upsert("east", vec![1.0, 0.0], json!("East")).unwrap();
+        index.upsert("north-east", vec![1.0, 1.0], json!(null)).unwrap();
+        assert!(index.upsert("bad", vec![1.0, 0.0, 0.0], json!(null)).is_err());
+
+        let results = index.search(&[0.1, 1.0], 2);
+        let keys: Vec<&str> = results.iter().map(|r| r["key"].as_str().unwrap()).collect();
+        assert_eq!(keys, ["north", "north-east"]);
+        assert_eq!(results[0]["payload"]["name"], "North");
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 1.0]) - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-9);
+
+        index.upsert("north", vec![-1.0, 0.0], json!("moved")).unwrap();
+        assert_eq!(index.search(&[-1.0, 0.0], 1)[0]["payload"], "moved");
+        assert!(index.delete("north") && !index.delete("north"));
+        assert_eq!(index.search(&[1.0, 0.0], 10).len(), 2);
+    }
+}
@@ -127,6 +127,87 @@ impl RuneDocument {
 
         Ok(Self::from_json(&json_val))
     }
+
+    /// Reads CSV rows as the records of one section, keyed by the header row.
+    pub fn from_csv(s: &str, section: &str) -> Result<RuneDocument, String> {
+        let mut reader = csv::ReaderBuilder::new().from_reader(s.as_bytes());
+        let headers = reader.headers().map_err(|e| format!("CSV parse error: {}", e))?.clone();
+        let mut records = Vec::new();
+        for row in reader.records() {
+            let row = row.map_err(|e| format!("CSV parse error: {}", e))?;
+            let kv = headers
+                .iter()
+                .zip(row.iter())
+                .map(|(key, cell)| (key.to_string(), csv_cell_value(cell)))
+                .collect();
+            records.push(Record { kv });
+        }
+        Ok(RuneDocument {
+            sections: vec![Section {
+                path: section.split('/').map(|p| p.to_string()).collect(),
+                kv: HashMap::new(),
+                series: HashMap::new(),
+                records,
+                source_file: None,
+            }],
+        })
+    }
+
+    /// Writes the records of `section` (or of the only section with records) as CSV, with
+    /// the record keys in alphabetical order as the header row.
+    pub fn to_csv(&self, section: Option<&str>) -> Result<String, String> {
+        let chosen = match section {
+            Some(name) => self
+                .sections
+                .iter()
+                .find(|s| s.path.join("/") == name.trim_start_matches('@'))
+                .ok_or_else(|| format!("no section named {}", name))?,
+            None => {
+                let mut with_records = self.sections.iter().filter(|s| !s.records.is_empty());
+                match (with_records.next(), with_records.next()) {
+                    (Some(only), None) => only,
+                    (None, _) => return Err("no section has records to write as CSV".to_string()),
+                    (Some(_), Some(_)) => {
+                        return Err("several sections have records; pick one with --section".to_string())
+                    }
+                }
+            }
+        };
+        let mut columns: Vec<&String> = chosen.records.iter().flat_map(|r| r.kv.keys()).collect();
+        columns.sort();
+        columns.dedup();
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let to_csv_err = |e: csv::Error| format!("CSV write error: {}", e);
+        writer.write_record(&columns).map_err(to_csv_err)?;
+        for record in &chosen.records {
+            let row = columns.iter().map(|column| match record.kv.get(*column) {
+                None => String::new(),
+                Some(Value::String(text)) => text.clone(),
+                Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+                Some(other) => other.to_json().to_string(),
+            });
+            writer.write_record(row).map_err(to_csv_err)?;
+        }
+        let bytes = writer.into_inner().map_err(|e| format!("CSV write error: {}", e))?;
+        String::from_utf8(bytes).map_err(|e| format!("CSV write error: {}", e))
+    }
+}
+
+/// A CSV cell as a number or boolean when it reads as one, otherwise as text. Numbers with
+/// leading zeros, like postal codes, stay text.
+fn csv_cell_value(cell: &str) -> Value {
+    let leading_zero = cell.len() > 1 && cell.starts_with('0') && !cell.starts_with("0.");
+    match cell {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if leading_zero || cell.trim() != cell => Value::String(cell.to_string()),
+        _ => cell
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::String(cell.to_string())),
+    }
 }
 
 impl RuneDocument {
@@ -199,7 +280,9 @@ impl RuneDocument {
                     serde_json::Value::Array(records_array),
                 );
             }
-            for (key, items) in &section.series {
+            let mut series: Vec<_> = section.series.iter().collect();
+            series.sort_by(|a, b| a.0.cmp(b.0));
+            for (key, items) in series {
                 let mut json_items = Vec::new();
                 for item in items {
                     json_items.push(item.to_json());
@@ -326,8 +409,10 @@ impl fmt::Display for RuneDocument {
             }
             writeln!(f)?;
 
-            // Key/Value Assignments
-            for (key, value) in &section.kv {
+            // Key/Value Assignments, sorted so the output is stable
+            let mut kv: Vec<_> = section.kv.iter().collect();
+            kv.sort_by(|a, b| a.0.cmp(b.0));
+            for (key, value) in kv {
                 writeln!(f, "{} = {}", key, value)?;
             }
 
@@ -342,7 +427,9 @@ impl fmt::Display for RuneDocument {
             // Record Lists (+ host = ...)
             for record in &section.records {
                 let mut first = true;
-                for (key, value) in &record.kv {
+                let mut kv: Vec<_> = record.kv.iter().collect();
+                kv.sort_by(|a, b| a.0.cmp(b.0));
+                for (key, value) in kv {
                     if first {
                         write!(f, "+ ")?; // Record items start with +
                         first = false;
@@ -386,7 +473,9 @@ impl fmt::Display for Value {
                 // Maps aren't explicitly defined for inline use in the RFC,
                 // but we keep a compact JSON-like format for internal values.
                 write!(f, "{{")?;
-                for (i, (k, v)) in map.iter().enumerate() {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (i, (k, v)) in entries.into_iter().enumerate() {
                     if i > 0 {
                         write!(f, " ")?;
                     }
@@ -4,6 +4,9 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
     #[error("No current section for line: {0}")]
@@ -30,6 +33,8 @@ pub enum LoadError {
     InvalidImport { path: String, message: String },
     #[error("Import cycle detected while loading {path}")]
     ImportCycle { path: String },
+    #[error("Failed to load remote Rune document {url}: {message}")]
+    Remote { url: String, message: String },
 }
 
 pub fn load_rune_document_from_path(path: &Path) -> Result<RuneDocument, LoadError> {
@@ -38,6 +43,13 @@ pub fn load_rune_document_from_path(path: &Path) -> Result<RuneDocument, LoadErr
     load_rune_document_from_path_inner(path, &mut visiting, &mut loaded)
 }
 
+/// Loads a document from a path, or from an `http(s)://` URL (see [`remote`]).
+pub fn load_rune_document_from_source(source: &str) -> Result<RuneDocument, LoadError> {
+    let mut visiting = HashSet::new();
+    let mut loaded = HashSet::new();
+    load_import(source, Path::new("."), ".", &mut visiting, &mut loaded)
+}
+
 pub fn load_rune_document_from_str_with_base(
     content: &str,
     base_dir: &Path,
@@ -114,8 +126,7 @@ fn load_rune_document_from_content_inner(
 
     let mut doc = RuneDocument { sections: Vec::new() };
     for import_path in imports {
-        let resolved = base_dir.join(import_path);
-        let imported = load_rune_document_from_path_inner(&resolved, visiting, loaded)?;
+        let imported = load_import(&import_path, base_dir, source_name, visiting, loaded)?;
         doc.merge(imported);
     }
 
@@ -127,6 +138,53 @@ fn load_rune_document_from_content_inner(
     Ok(doc)
 }
 
+/// Loads an import: a URL, a path relative to a remote importer's URL, or a path
+/// relative to `base_dir`.
+fn load_import(
+    import_path: &str,
+    base_dir: &Path,
+    source_name: &str,
+    visiting: &mut HashSet<PathBuf>,
+    loaded: &mut HashSet<PathBuf>,
+) -> Result<RuneDocument, LoadError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if remote::is_remote(import_path) || remote::is_remote(source_name) {
+        let source = remote::join_url(source_name, import_path);
+        return load_remote_document_inner(&source, visiting, loaded);
+    }
+    if import_path.starts_with("https://") || import_path.starts_with("http://") {
+        return Err(LoadError::InvalidImport {
+            path: source_name.to_string(),
+            message: format!("remote imports are not supported here: {}", import_path),
+        });
+    }
+    load_rune_document_from_path_inner(&base_dir.join(import_path), visiting, loaded)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_remote_document_inner(
+    source: &str,
+    visiting: &mut HashSet<PathBuf>,
+    loaded: &mut HashSet<PathBuf>,
+) -> Result<RuneDocument, LoadError> {
+    let key = PathBuf::from(source.split('#').next().unwrap_or(source));
+    if loaded.contains(&key) {
+        return Ok(RuneDocument { sections: Vec::new() });
+    }
+    if !visiting.insert(key.clone()) {
+        return Err(LoadError::ImportCycle {
+            path: key.display().to_string(),
+        });
+    }
+    let result = remote::load_remote(source)
+        .and_then(|(url, content)| load_rune_document_from_content_inner(&content, Path::new("."), &url, visiting, loaded));
+    visiting.remove(&key);
+    if result.is_ok() {
+        loaded.insert(key);
+    }
+    result
+}
+
 fn extract_imports(content: &str, source_name: &str) -> Result<(Vec<String>, String), LoadError> {
     let mut imports = Vec::new();
     let mut stripped_lines = Vec::new();
@@ -247,7 +305,11 @@ pub fn parse_rune_with_source(input: &str, source_file: Option<String>) -> Resul
             }
 
             let path_str = &line[1..];
-            let path: Vec<String> = path_str.split('/').map(|s| s.trim().to_string()).collect();
+            let mut path: Vec<String> = path_str.split('/').map(|s| s.trim().to_string()).collect();
+            if let Some(alias) = crate::core::aliases::section_alias(&path[0]) {
+                crate::core::aliases::warn_deprecated("section", &format!("@{}", path[0]), &format!("@{}", alias.current));
+                path[0] = alias.current.to_string();
+            }
 
             current_section = Some(Section {
                 path,
@@ -586,12 +648,13 @@ pub fn parse_rune_with_source(input: &str, source_file: Option<String>) -> Resul
                             list.push(Value::String(assignment));
                             continue;
                         }
-                        let item_text = if line.trim_start().starts_with('-') {
-                            line.trim_start()[1..].trim().to_string()
+                        // `- item` entries are data, so literals are typed; other
+                        // series items are steps and stay as raw strings.
+                        if let Some(item) = line.trim_start().strip_prefix('-') {
+                            list.push(parse_list_literal(item.trim()));
                         } else {
-                            line.trim().to_string()
-                        };
-                        list.push(Value::String(item_text));
+                            list.push(Value::String(line.trim().to_string()));
+                        }
                         continue;
                     }
                 }
@@ -646,19 +709,9 @@ pub fn parse_rune_with_source(input: &str, source_file: Option<String>) -> Resul
 
                 let value = if value_raw.starts_with('(') && value_raw.ends_with(')') {
                     let inner = &value_raw[1..value_raw.len() - 1];
-                    let items: Vec<Value> = inner
-                        .split_whitespace()
-                        .map(|s| {
-                            if s.starts_with('$') && s.ends_with('$') && s.len() > 2 {
-                                let var_name = &s[1..s.len() - 1];
-                                match std::env::var(var_name) {
-                                    Ok(val) => Value::String(val),
-                                    Err(_) => Value::String(String::new()),
-                                }
-                            } else {
-                                Value::String(s.to_string())
-                            }
-                        })
+                    let items: Vec<Value> = split_list_items(inner)
+                        .iter()
+                        .map(|s| parse_list_literal(s))
                         .collect();
                     Value::List(items)
                 } else if looks_like_object_literal_start(&value_raw) && value_raw.trim_end().ends_with('}') {
@@ -758,59 +811,65 @@ pub fn parse_rune_with_source(input: &str, source_file: Option<String>) -> Resul
     Ok(RuneDocument { sections })
 }
 
-#[derive(Debug)]
-pub enum ParsedLine {
-    Assignment { var: String, expr: String },
-    Builtin { name: String, args: Vec<String> },
-    Object { var: String, fields: Vec<(String, String)> },
-    Comment,
-    Raw(String),
+/// Splits the inside of a `( ... )` list on whitespace, keeping quoted items whole.
+fn split_list_items(inner: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for ch in inner.chars() {
+        if escaped {
+            current.push(ch);
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_quotes => {
+                current.push(ch);
+                escaped = true;
+            }
+            '"' => {
+                current.push(ch);
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    items.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        items.push(current);
+    }
+    items
 }
 
-pub fn parse_rune_line(line: &str) -> Result<ParsedLine, ParseError> {
-    let line = line.trim();
-    if line.is_empty() {
-        return Err(ParseError::General("Empty line".to_string()));
+/// Parses a list item into a typed value: `true`/`false`, numbers, quoted strings
+/// (unquoted and unescaped) and `$VAR$` env substitution. Anything else is a string.
+fn parse_list_literal(item: &str) -> Value {
+    match item {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
     }
-    if line.starts_with('#') {
-        return Ok(ParsedLine::Comment);
+    if item.len() >= 2 && item.starts_with('"') && item.ends_with('"') {
+        return Value::String(unescape_string(&item[1..item.len() - 1]));
     }
-    // Assignment: var = expr
-    if let Some(eq_pos) = line.find('=') {
-        // Ignore '==' or '!='
-        let is_assignment = !line.contains("==") && !line.contains("!=");
-        if is_assignment {
-            let var = line[..eq_pos].trim().to_string();
-            let expr = line[eq_pos + 1..].trim().to_string();
-            // Object construction: var = { ... }
-            if expr.starts_with('{') && expr.ends_with('}') {
-                // Parse fields
-                let fields_str = &expr[1..expr.len() - 1];
-                let fields: Vec<(String, String)> = fields_str
-                    .split(',')
-                    .filter_map(|f| {
-                        let parts: Vec<_> = f.split(':').collect();
-                        if parts.len() == 2 {
-                            Some((parts[0].trim().to_string(), parts[1].trim().to_string()))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                return Ok(ParsedLine::Object { var, fields });
-            }
-            return Ok(ParsedLine::Assignment { var, expr });
-        }
+    if item.len() > 2 && item.starts_with('$') && item.ends_with('$') {
+        return Value::String(std::env::var(&item[1..item.len() - 1]).unwrap_or_default());
     }
-    // Builtin: memory.get ... or similar
-    let parts: Vec<_> = line.split_whitespace().collect();
-    if !parts.is_empty() && parts[0].contains('.') {
-        let name = parts[0].to_string();
-        let args = parts[1..].iter().map(|s| s.to_string()).collect();
-        return Ok(ParsedLine::Builtin { name, args });
+    // Only plain decimal notation; `f64::from_str` also accepts words like "inf" and "nan"
+    let looks_numeric = item
+        .trim_start_matches(['-', '+'])
+        .starts_with(|c: char| c.is_ascii_digit() || c == '.');
+    if looks_numeric {
+        if let Ok(n) = item.parse::<f64>() {
+            return Value::Number(n);
+        }
     }
-    // Raw line
-    Ok(ParsedLine::Raw(line.to_string()))
+    Value::String(item.to_string())
 }
 
 fn is_char_in_quotes(s: &str, target_idx: usize) -> bool {
@@ -0,0 +1,198 @@
+//! Rune documents fetched over HTTP(S), as a CLI script or an `import`:
+//!
+//! ```rune
+//! import "https://configs.example.com/shared.rune#sha256=9f86d081884c7d65..."
+//! ```
+//!
+//! - A `#sha256=<hex>` fragment pins the document's content; a mismatch fails the load.
+//! - When `VECTRUNE_SIGNING_KEY` is set, every remote document must come with a
+//!   `<url>.sig` holding its hex HMAC-SHA256 under that key.
+//! - Fetched documents are cached under `VECTRUNE_CACHE_DIR` (default
+//!   `~/.cache/vectrune/remote`) and the cached copy is used when the server cannot be
+//!   reached. Cached copies are verified the same way.
+
+use super::LoadError;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const SIGNING_KEY_ENV: &str = "VECTRUNE_SIGNING_KEY";
+pub const CACHE_DIR_ENV: &str = "VECTRUNE_CACHE_DIR";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether `source` names a document by URL rather than by path.
+pub fn is_remote(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// Resolves an import written in a remote document: absolute URLs stay as they are,
+/// `/path` is relative to the host and anything else to the document's directory.
+pub fn join_url(base: &str, import: &str) -> String {
+    if is_remote(import) {
+        return import.to_string();
+    }
+    let base = base.split(['#', '?']).next().unwrap_or(base);
+    let scheme_end = base.find("://").map(|i| i + 3).unwrap_or(0);
+    let host_end = base[scheme_end..].find('/').map(|i| i + scheme_end).unwrap_or(base.len());
+    let dir_end = base.rfind('/').filter(|i| *i >= host_end).unwrap_or(host_end);
+    let mut segments: Vec<&str> = if import.starts_with('/') {
+        Vec::new()
+    } else {
+        base[host_end..dir_end].split('/').filter(|s| !s.is_empty()).collect()
+    };
+    for part in import.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+    }
+    format!("{}/{}", &base[..host_end], segments.join("/"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn remote_error(url: &str, message: impl Into<String>) -> LoadError {
+    LoadError::Remote {
+        url: url.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Splits a `#sha256=<hex>` pin off the URL.
+fn split_checksum(source: &str) -> (&str, Option<String>) {
+    match source.split_once('#') {
+        Some((url, fragment)) => (
+            url,
+            fragment.strip_prefix("sha256=").map(|sum| sum.trim().to_ascii_lowercase()),
+        ),
+        None => (source, None),
+    }
+}
+
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let dir = std::env::var(CACHE_DIR_ENV)
+        .ok()
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".cache/vectrune/remote")))?;
+    Some(dir.join(format!("{}.rune", hex(&Sha256::digest(url.as_bytes())))))
+}
+
+/// Fetches a URL's body as text. The blocking client runs on its own thread so loads
+/// also work from inside the server's async runtime.
+fn fetch(url: &str) -> Result<String, String> {
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let response = client.get(&url).send().map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("server responded {}", response.status()));
+        }
+        response.text().map_err(|e| e.to_string())
+    })
+    .join()
+    .unwrap_or_else(|_| Err("fetch thread panicked".to_string()))
+}
+
+fn verify(url: &str, content: &str, checksum: Option<&str>, signature: Option<&str>) -> Result<(), LoadError> {
+    if let Some(expected) = checksum {
+        let actual = hex(&Sha256::digest(content.as_bytes()));
+        if actual != expected {
+            return Err(remote_error(url, format!("checksum mismatch: expected {}, got {}", expected, actual)));
+        }
+    }
+    if let Ok(key) = std::env::var(SIGNING_KEY_ENV) {
+        let signature = signature.ok_or_else(|| remote_error(url, format!("missing signature {}.sig", url)))?;
+        let signature = signature.trim();
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature).to_ascii_lowercase();
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(content.as_bytes());
+        if hex(&mac.finalize().into_bytes()) != signature {
+            return Err(remote_error(url, "signature does not match"));
+        }
+    }
+    Ok(())
+}
+
+/// Fetches and verifies a remote document's text, falling back to the cached copy when
+/// the server cannot be reached. Returns the URL without its checksum fragment.
+pub fn load_remote(source: &str) -> Result<(String, String), LoadError> {
+    let (url, checksum) = split_checksum(source);
+    let signed = std::env::var(SIGNING_KEY_ENV).is_ok();
+    let cache = cache_path(url);
+    let fetched = fetch(url).and_then(|content| {
+        let signature = if signed { Some(fetch(&format!("{}.sig", url))?) } else { None };
+        Ok((content, signature))
+    });
+    let (content, signature) = match fetched {
+        Ok(found) => found,
+        Err(e) => {
+            let cached = cache.as_ref().and_then(|path| {
+                let content = std::fs::read_to_string(path).ok()?;
+                let signature = std::fs::read_to_string(path.with_extension("sig")).ok();
+                Some((content, signature))
+            });
+            match cached {
+                Some(found) => {
+                    crate::util::log(
+                        crate::util::LogLevel::Warn,
+                        &format!("Could not fetch {} ({}); using cached copy", url, e),
+                    );
+                    found
+                }
+                None => return Err(remote_error(url, e)),
+            }
+        }
+    };
+    verify(url, &content, checksum.as_deref(), signature.as_deref())?;
+    if let Some(path) = cache {
+        let written = path
+            .parent()
+            .map(std::fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| std::fs::write(&path, &content))
+            .and_then(|_| match &signature {
+                Some(sig) => std::fs::write(path.with_extension("sig"), sig),
+                None => Ok(()),
+            });
+        if let Err(e) = written {
+            crate::util::log(
+                crate::util::LogLevel::Warn,
+                &format!("Could not cache {}: {}", url, e),
+            );
+        }
+    }
+    Ok((url.to_string(), content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_imports_against_the_document_url() {
+        let base = "https://cfg.example.com/apps/api/app.rune#sha256=ab";
+        assert_eq!(join_url(base, "shared.rune"), "https://cfg.example.com/apps/api/shared.rune");
+        assert_eq!(join_url(base, "../common/auth.rune"), "https://cfg.example.com/apps/common/auth.rune");
+        assert_eq!(join_url(base, "/root.rune"), "https://cfg.example.com/root.rune");
+        assert_eq!(join_url(base, "http://other/x.rune"), "http://other/x.rune");
+    }
+
+    #[test]
+    fn verifies_pinned_checksums() {
+        let content = "@App\ntype = REST\n";
+        let sum = hex(&Sha256::digest(content.as_bytes()));
+        assert!(verify("u", content, Some(&sum), None).is_ok());
+        assert!(verify("u", content, Some("00"), None).is_err());
+        assert_eq!(split_checksum("https://h/a.rune#sha256=AB"), ("https://h/a.rune", Some("ab".to_string())));
+    }
+}
@@ -2,6 +2,7 @@ use crate::rune_ast;
 use crate::rune_ast::RuneDocument;
 use serde_json::Value;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use once_cell::sync::Lazy;
 
 pub fn json_to_xml(value: &Value, root: &str) -> String {
@@ -87,6 +88,10 @@ impl fmt::Display for LogLevel {
     }
 }
 
+/// Where log lines go when `--log-file` is given; otherwise they go to stderr, keeping
+/// stdout for document output and protocols such as MCP over stdio.
+static LOG_FILE: Lazy<Mutex<Option<std::fs::File>>> = Lazy::new(|| Mutex::new(None));
+
 static LOG_LEVEL: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(log_level_to_usize(&LogLevel::Debug)));
 
 fn log_level_to_usize(level: &LogLevel) -> usize {
@@ -108,9 +113,27 @@ fn get_log_level() -> LogLevel {
     }
 }
 
+/// Appends log lines to `path` instead of writing them to stderr.
+pub fn set_log_file(path: &std::path::Path) -> std::io::Result<()> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    Ok(())
+}
+
+fn write_log_line(line: &str) {
+    let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    match file.as_mut() {
+        Some(file) => {
+            use std::io::Write;
+            let _ = writeln!(file, "{}", line);
+        }
+        None => eprintln!("{}", line),
+    }
+}
+
 pub fn set_log_level(level: LogLevel, silent: bool) {
     if !silent {
-        println!("Setting log level to {}", level);
+        write_log_line(&format!("Setting log level to {}", level));
     }
     LOG_LEVEL.store(log_level_to_usize(&level), Ordering::Relaxed);
 }
@@ -125,7 +148,7 @@ pub fn log(level: LogLevel, msg: &str) {
         LogLevel::Warn => "[WARN]",
         LogLevel::Error => "[ERROR]",
     };
-    println!("{} {}", prefix, msg);
+    write_log_line(&format!("{} {}", prefix, msg));
 }
 
 pub fn unescape_string(s: &str) -> String {
@@ -152,3 +175,15 @@ pub fn unescape_string(s: &str) -> String {
     }
     result
 }
+
+/// Writes `bytes` to a temporary file next to `path` and renames it into place, so readers
+/// never see a half-written file and a failed write leaves the original untouched.
+pub fn replace_file(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, bytes).and_then(|_| std::fs::rename(&tmp, path)).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
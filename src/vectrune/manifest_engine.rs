@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use rust_embed::RustEmbed;
 
 use crate::rune_ast::Value;
+use crate::util::{log, LogLevel};
 use super::ast::{Intent, VectruneDocument};
 use super::definition::{IntentRule, LanguageDefinition};
 use super::engine::{normalize, LanguageEngine};
@@ -26,7 +27,7 @@ impl ManifestEngine {
         for m in &rule.mandatory {
             let m_norm = normalize(m);
             if !normalized_body.contains(&m_norm) {
-                println!("DEBUG: Mandatory mismatch: '{}' not in body", m_norm);
+                log(LogLevel::Debug, &format!("Mandatory mismatch: '{}' not in body", m_norm));
                 return false;
             }
         }
@@ -51,7 +52,7 @@ impl ManifestEngine {
             }
         }
 
-        println!("DEBUG: No optional or phrase matched for intent '{}'", rule.intent_id);
+        log(LogLevel::Debug, &format!("No optional or phrase matched for intent '{}'", rule.intent_id));
         false
     }
 
@@ -23,6 +23,7 @@ pub async fn run_rune_wasm(source: &str, input_data: &str) -> Result<String, JsV
         doc: Arc::new(doc.clone()),
         schemas: Arc::new(HashMap::new()),
         data_sources: Arc::new(HashMap::new()),
+        steps: Arc::default(),
         path: PathBuf::from("."),
     };
 
@@ -0,0 +1,186 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::admin::build_admin_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const V1: &str = r#"#!RUNE
+
+@App
+name = Blue
+type = REST
+
+@Admin
+token = secret-token
+
+@Route/GET /version
+run:
+    respond 200 "blue"
+"#;
+
+const V2: &str = r#"#!RUNE
+
+@App
+name = Green
+type = REST
+
+@Admin
+token = secret-token
+
+@Route/GET /version
+run:
+    respond 200 "green"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_admin_router(state).await
+}
+
+async fn send(app: &Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+fn admin_post(uri: &str, token: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn upload_switches_traffic_to_new_document() {
+    let app = build_router_from_str(V1).await;
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("blue"));
+
+    let (status, _) = send(&app, admin_post("/__admin/document", "secret-token", V2)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("green"));
+
+    let (status, _) = send(&app, admin_post("/__admin/rollback", "secret-token", "")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("blue"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_switches_and_rollbacks_keep_serving() {
+    let app = build_router_from_str(V1).await;
+    send(&app, admin_post("/__admin/document", "secret-token", V2)).await;
+
+    let mut tasks = Vec::new();
+    for i in 0..8 {
+        let app = app.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                let req = if i % 2 == 0 {
+                    admin_post("/__admin/document", "secret-token", if i % 4 == 0 { V1 } else { V2 })
+                } else {
+                    admin_post("/__admin/rollback", "secret-token", "")
+                };
+                send(&app, req).await;
+            }
+        }));
+    }
+    let all = futures::future::join_all(tasks);
+    tokio::time::timeout(std::time::Duration::from_secs(20), all)
+        .await
+        .expect("switches and rollbacks should not deadlock");
+
+    let (status, _) = tokio::time::timeout(std::time::Duration::from_secs(5), send(&app, get("/version")))
+        .await
+        .expect("traffic should still be served");
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn upload_requires_admin_token() {
+    let app = build_router_from_str(V1).await;
+    let (status, _) = send(&app, admin_post("/__admin/document", "wrong", V2)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("blue"));
+}
+
+#[tokio::test]
+async fn invalid_upload_keeps_current_document() {
+    let app = build_router_from_str(V1).await;
+    let invalid = "#!RUNE\n\n@App\ntype = SOAP\n";
+    let (status, body) = send(&app, admin_post("/__admin/document", "secret-token", invalid)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("Unsupported App type"));
+
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("blue"));
+}
+
+#[tokio::test]
+async fn oversized_upload_keeps_current_document() {
+    let app = build_router_from_str(V1).await;
+    let oversized = format!("{}# {}\n", V2, "x".repeat(11 * 1024 * 1024));
+    let (status, _) = send(&app, admin_post("/__admin/document", "secret-token", &oversized)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(&app, get("/version")).await;
+    assert!(body.contains("blue"));
+}
+
+#[tokio::test]
+async fn admin_lists_routes_and_manages_memory() {
+    let app = build_router_from_str(V1).await;
+
+    let req = Request::builder()
+        .uri("/__admin/routes")
+        .header("Authorization", "Bearer secret-token")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    let routes: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(routes[0]["method"], "GET");
+    assert_eq!(routes[0]["path"], "/version");
+
+    let req = Request::builder()
+        .method("PUT")
+        .uri("/__admin/memory/admin_test_key")
+        .header("Authorization", "Bearer secret-token")
+        .body(Body::from("{\"count\": 3}"))
+        .unwrap();
+    let (status, _) = send(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let req = Request::builder()
+        .uri("/__admin/memory/admin_test_key")
+        .header("Authorization", "Bearer secret-token")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "{\"count\":3}");
+}
@@ -0,0 +1,126 @@
+use axum::http::{Request, StatusCode};
+use axum::routing::post;
+use axum::{body::Body, Json, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Ai
+provider = ollama
+base_url = BASE_URL
+retries = 0
+
+@Route/POST /summary
+run:
+    parse-json
+    summary = ai "Summarize: ${body.text}" model=tiny
+    respond 200 summary
+
+@Route/POST /slow
+run:
+    summary = ai "slow" timeout=200ms
+    respond 200 summary
+
+@Route/POST /docs/{id}
+run:
+    parse-json
+    embedding = ai.embed body.text
+    vector.upsert id embedding body
+    respond 201 "indexed"
+
+@Route/POST /search
+run:
+    parse-json
+    query = ai.embed body.q
+    results = vector.search query top_k=1
+    respond 200 results
+"#;
+
+/// A stand-in for Ollama's `/api/generate` that streams the model name and prompt back,
+/// and stalls on the prompt `slow`. Its `/api/embeddings` places text mentioning cats
+/// and text mentioning dogs on different axes.
+async fn start_model() -> String {
+    let generate = |Json(request): Json<Value>| async move {
+        if request["prompt"] == "slow" {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+        let model = request["model"].as_str().unwrap_or_default();
+        let pieces = [format!("{}: ", model), request["prompt"].as_str().unwrap_or_default().to_string()];
+        pieces
+            .iter().map(|piece| format!("{}\n", json!({ "response": piece, "done": false }))).collect::<String>()
+    };
+    let embeddings = |Json(request): Json<Value>| async move {
+        let text = request["prompt"].as_str().unwrap_or_default();
+        Json(json!({ "embedding": [f64::from(text.contains("cat")), f64::from(text.contains("dog")), 0.1] }))
+    };
+    let model = Router::new().route("/api/generate", post(generate)).route("/api/embeddings", post(embeddings));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, model).await.unwrap();
+    });
+    base
+}
+
+async fn build_router(base: &str) -> Router {
+    let doc = parse_rune(&APP.replace("BASE_URL", base)).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::temp_dir(),
+    };
+    build_app_router(state).await
+}
+
+async fn post_json(app: &Router, uri: &str, body: Value) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn ai_steps_call_the_configured_model() {
+    let app = build_router(&start_model().await).await;
+
+    let (status, body) = post_json(&app, "/summary", json!({ "text": "a long story" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body, "tiny: Summarize: a long story");
+
+    let (status, body) = post_json(&app, "/slow", json!({})).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR, "{}", body);
+}
+
+#[tokio::test]
+async fn embeddings_feed_vector_search() {
+    let app = build_router(&start_model().await).await;
+
+    for (id, text) in [("1", "cats purr"), ("2", "dogs bark")] {
+        let (status, body) = post_json(&app, &format!("/docs/{}", id), json!({ "text": text })).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+    }
+    let (status, body) = post_json(&app, "/search", json!({ "q": "do dogs dream?" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let results: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(results.as_array().unwrap().len(), 1);
+    assert_eq!(results[0]["key"], "2");
+    assert_eq!(results[0]["payload"]["text"], "dogs bark");
+}
@@ -10,6 +10,7 @@ async fn test_arithmetic_assignment() {
         doc: std::sync::Arc::new(rune_ast::RuneDocument { sections: vec![] }),
         schemas: std::sync::Arc::new(std::collections::HashMap::new()),
         data_sources: std::sync::Arc::new(std::collections::HashMap::new()),
+        steps: std::sync::Arc::default(),
         path: std::path::PathBuf::new(),
     };
     let mut ctx = Context::new();
@@ -33,6 +34,7 @@ async fn test_arithmetic_assignment_with_builtin_operand() {
         doc: std::sync::Arc::new(rune_ast::RuneDocument { sections: vec![] }),
         schemas: std::sync::Arc::new(std::collections::HashMap::new()),
         data_sources: std::sync::Arc::new(std::collections::HashMap::new()),
+        steps: std::sync::Arc::default(),
         path: std::path::PathBuf::new(),
     };
     let mut ctx = Context::new();
@@ -56,6 +58,7 @@ async fn test_parse_json_command_is_not_treated_as_subtraction() {
         doc: std::sync::Arc::new(rune_ast::RuneDocument { sections: vec![] }),
         schemas: std::sync::Arc::new(std::collections::HashMap::new()),
         data_sources: std::sync::Arc::new(std::collections::HashMap::new()),
+        steps: std::sync::Arc::default(),
         path: std::path::PathBuf::new(),
     };
     let mut ctx = Context::new();
@@ -76,3 +79,55 @@ async fn test_parse_json_command_is_not_treated_as_subtraction() {
     );
 }
 
+
+#[tokio::test]
+async fn test_arithmetic_with_negatives_modulo_and_index_expressions() {
+    let app_state = AppState {
+        doc: std::sync::Arc::new(rune_ast::RuneDocument { sections: vec![] }),
+        schemas: std::sync::Arc::new(std::collections::HashMap::new()),
+        data_sources: std::sync::Arc::new(std::collections::HashMap::new()),
+        steps: std::sync::Arc::default(),
+        path: std::path::PathBuf::new(),
+    };
+    let mut ctx = Context::new();
+    ctx.insert("index".to_string(), serde_json::json!(0));
+    ctx.insert(
+        "users".to_string(),
+        serde_json::json!([{ "age": 30 }, { "age": 41 }, { "age": 52 }]),
+    );
+    let steps = [
+        Value::String("a = -index - 1.5".to_string()),
+        Value::String("b = users[index + 1].age % 10".to_string()),
+        Value::String("c = users[-1].age*2".to_string()),
+        Value::String("users[index + 2].age = 60".to_string()),
+    ];
+
+    execute_steps_inner(app_state, &steps, &mut ctx).await;
+    assert_eq!(ctx.get("a"), Some(&serde_json::json!(-1.5)));
+    assert_eq!(ctx.get("b"), Some(&serde_json::json!(1)));
+    assert_eq!(ctx.get("c"), Some(&serde_json::json!(104)));
+    assert_eq!(ctx["users"][2]["age"], serde_json::json!(60));
+}
+
+#[tokio::test]
+async fn test_bare_index_expression_logs_no_error() {
+    let log_dir = tempfile::tempdir().unwrap();
+    let log_path = log_dir.path().join("steps.log");
+    rune_runtime::util::set_log_file(&log_path).unwrap();
+    let app_state = AppState {
+        doc: std::sync::Arc::new(rune_ast::RuneDocument { sections: vec![] }),
+        schemas: std::sync::Arc::new(std::collections::HashMap::new()),
+        data_sources: std::sync::Arc::new(std::collections::HashMap::new()),
+        steps: std::sync::Arc::default(),
+        path: std::path::PathBuf::new(),
+    };
+    let mut ctx = Context::new();
+    ctx.insert("i".to_string(), serde_json::json!(1));
+    ctx.insert("xs".to_string(), serde_json::json!([10, 20, 30]));
+    let steps = [Value::String("v = xs[i + 1]".to_string())];
+
+    execute_steps_inner(app_state, &steps, &mut ctx).await;
+    assert_eq!(ctx.get("v"), Some(&serde_json::json!(30)));
+    let logged = std::fs::read_to_string(&log_path).unwrap_or_default();
+    assert!(!logged.contains("unknown builtin"), "{}", logged);
+}
@@ -1,6 +1,6 @@
 // Integration tests for the arithmetic engine
 
-use rune_runtime::arithmetic::eval_arithmetic;
+use rune_runtime::arithmetic::{eval_arithmetic, eval_arithmetic_with};
 
 #[test]
 fn test_simple() {
@@ -28,3 +28,25 @@ fn test_complex() {
     assert_eq!(eval_arithmetic("(1 + 2) * (3 - 4 / 2)").unwrap(), 3.0);
 }
 
+
+#[test]
+fn test_modulo_and_floats() {
+    assert_eq!(eval_arithmetic("7 % 3").unwrap(), 1.0);
+    assert_eq!(eval_arithmetic("-2.5 * 2").unwrap(), -5.0);
+    assert_eq!(eval_arithmetic("1.5+.5").unwrap(), 2.0);
+    assert!(eval_arithmetic("1 2").is_err());
+}
+
+#[test]
+fn test_identifiers_resolve_through_callback() {
+    let resolve = |name: &str| match name {
+        "index" => Some(2.0),
+        "scores[index - 1]" => Some(10.0),
+        _ => None,
+    };
+    assert_eq!(eval_arithmetic_with("index+1", resolve).unwrap(), 3.0);
+    assert_eq!(eval_arithmetic_with("-index", resolve).unwrap(), -2.0);
+    assert_eq!(eval_arithmetic_with("scores[index - 1] % 4", resolve).unwrap(), 2.0);
+    assert!(eval_arithmetic_with("missing + 1", resolve).is_err());
+    assert!(eval_arithmetic_with("parse-json", resolve).is_err());
+}
@@ -0,0 +1,71 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::apps::chaos::set_global_chaos;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /plain
+run:
+    respond 200 OK
+
+@Route/GET /broken
+chaos = "errors=100%,status=500"
+run:
+    respond 200 OK
+
+@Route/GET /slow
+chaos = "latency=150ms"
+run:
+    respond 200 OK
+
+@Route/GET /stable
+chaos = off
+run:
+    respond 200 OK
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn status(app: &Router, uri: &str) -> StatusCode {
+    let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    app.clone().oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn chaos_delays_and_fails_requests() {
+    let app = build_router().await;
+    assert_eq!(status(&app, "/plain").await, StatusCode::OK);
+    assert_eq!(status(&app, "/broken").await, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let started = Instant::now();
+    assert_eq!(status(&app, "/slow").await, StatusCode::OK);
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    // The global spec covers routes without their own setting
+    set_global_chaos("errors=100%").unwrap();
+    let app = build_router().await;
+    assert_eq!(status(&app, "/plain").await, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(status(&app, "/broken").await, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(status(&app, "/stable").await, StatusCode::OK);
+}
@@ -0,0 +1,128 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/POST /report
+run:
+    parse-json
+    adults = body.users.filter it.age > 18
+    users = body.users.sort-by it.name
+    in_oslo = users.filter it.city == "Oslo"
+    names = users.map it.name
+    oldest_first = users.sort-by it.age desc
+    by_city = users.group-by it.city
+    total = users.sum it.spent
+    count = users.count
+    active = users.count it.active
+    out = { adults: adults, in_oslo: in_oslo, names: names, oldest: oldest_first.0.name, by_city: by_city, total: total, count: count, active: active }
+    respond 200 out
+
+@Route/POST /edit
+run:
+    parse-json
+    tags = body.tags
+    tags.push "new"
+    tags.insert 0 { name: body.first }
+    middle = tags.slice 1 -1
+    distinct = body.tags.unique
+    backwards = body.tags.reverse
+    has_rust = body.tags.contains "rust"
+    has_go = body.tags.contains "go"
+    out = { tags: tags, middle: middle, distinct: distinct, backwards: backwards, has_rust: has_rust, has_go: has_go }
+    respond 200 out
+
+@Route/POST /not-a-list
+run:
+    parse-json
+    x = body.name.count
+    respond 200 x
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn list_methods_on_context_arrays() {
+    let app = build_router_from_str(SCRIPT).await;
+    let users = json!({"users": [
+        {"name": "ann", "age": 34, "city": "Oslo", "spent": 12.5, "active": true},
+        {"name": "bob", "age": 17, "city": "Rome", "spent": 3, "active": false},
+        {"name": "cid", "age": 52, "city": "Oslo", "spent": "4.5", "active": true}
+    ]});
+    let (status, body) = send(app, post("/report", &users.to_string())).await;
+    assert_eq!(status, StatusCode::OK, "body: {}", body);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(out["adults"].as_array().map(Vec::len), Some(2));
+    assert_eq!(out["in_oslo"].as_array().map(Vec::len), Some(2));
+    assert_eq!(out["names"], json!(["ann", "bob", "cid"]));
+    assert_eq!(out["oldest"], json!("cid"));
+    assert_eq!(out["by_city"]["Oslo"].as_array().map(Vec::len), Some(2));
+    assert_eq!(out["by_city"]["Rome"][0]["name"], json!("bob"));
+    assert_eq!(out["total"], json!(20));
+    assert_eq!(out["count"], json!(3));
+    assert_eq!(out["active"], json!(2));
+}
+
+#[tokio::test]
+async fn push_insert_slice_unique_reverse_contains() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(
+        app,
+        post("/edit", r#"{"first":"ann","tags":["rust","web","rust"]}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "body: {}", body);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(out["tags"], json!([{"name": "ann"}, "rust", "web", "rust", "new"]));
+    assert_eq!(out["middle"], json!(["rust", "web", "rust"]));
+    assert_eq!(out["distinct"], json!(["rust", "web"]));
+    assert_eq!(out["backwards"], json!(["rust", "web", "rust"]));
+    assert_eq!(out["has_rust"], json!(true));
+    assert_eq!(out["has_go"], json!(false));
+}
+
+#[tokio::test]
+async fn list_method_on_a_non_list_is_an_error() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, _) = send(app, post("/not-a-list", r#"{"name":"ann"}"#)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
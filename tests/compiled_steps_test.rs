@@ -0,0 +1,122 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /totals
+run:
+    price = 4
+    total = price * 3
+    if total > 10:
+        label = "big"
+        respond 200 label
+    respond 200 "small"
+
+@Route/GET /broken
+run:
+    log "starting"
+    total =
+    respond 200 "unreachable"
+
+@Route/GET /recovered
+run:
+    if true:
+        log "never closed
+    respond 200 "unreachable"
+on_error:
+    respond 422 error.message
+
+@Function/shout(word)
+run:
+    loud =
+    return word
+
+@Route/GET /matched
+run:
+    kind = "b"
+    match kind:
+        "a":
+            respond 200 "a"
+        "b":
+            log "never closed
+    respond 200 "unreachable"
+on_error:
+    respond 422 error.message
+
+@Route/GET /retried
+run:
+    attempts = 0
+    retry 3 delay=1ms:
+        attempts = attempts + 1
+        if attempts < 3:
+            boom =
+        respond 200 attempts
+
+@Route/GET /called
+run:
+    said = shout("hi")
+    respond 200 said
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::current_dir().unwrap(),
+    };
+    build_app_router(state).await
+}
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+    let req = Request::builder().method("GET").uri(uri).body(Body::empty()).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn compiled_steps_run_on_every_request() {
+    let app = build_router().await;
+    for _ in 0..2 {
+        assert_eq!(get(&app, "/totals").await, (StatusCode::OK, "big".to_string()));
+    }
+}
+
+#[tokio::test]
+async fn invalid_steps_fail_with_their_position() {
+    let app = build_router().await;
+    let (status, body) = get(&app, "/broken").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, "step 2: nothing is assigned to `total`");
+
+    let (status, body) = get(&app, "/recovered").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("step 1.1: `\"` is never closed"), "{}", body);
+}
+
+#[tokio::test]
+async fn block_bodies_are_compiled_with_their_positions() {
+    let app = build_router().await;
+    let (status, body) = get(&app, "/matched").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("step 2.2.1: `\"` is never closed"), "{}", body);
+
+    assert_eq!(get(&app, "/retried").await, (StatusCode::OK, "3".to_string()));
+
+    let (status, body) = get(&app, "/called").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("step 1: nothing is assigned to `loud`"), "{}", body);
+}
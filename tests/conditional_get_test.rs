@@ -0,0 +1,129 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /catalog
+cache_ttl = 60
+run:
+    respond 200 "catalog v1"
+
+@Route/GET /uncached
+run:
+    respond 200 "plain"
+
+@Authentication/Staff
+type = jwt
+secret = shh
+
+@Route/GET /orders
+auth = Staff
+cache_ttl = 60
+run:
+    respond 200 "orders"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+#[tokio::test]
+async fn cache_ttl_route_sets_etag_and_cache_control() {
+    let app = build_router_from_str(SCRIPT).await;
+    let resp = app
+        .clone()
+        .oneshot(Request::builder().uri("/catalog").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert_eq!(
+        resp.headers().get("cache-control").unwrap(),
+        "public, max-age=60"
+    );
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/catalog")
+                .header("If-None-Match", etag.as_str())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(bytes.is_empty());
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/catalog")
+                .header("If-None-Match", "\"stale\"")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn plain_get_route_has_no_etag() {
+    let app = build_router_from_str(SCRIPT).await;
+    let resp = app
+        .oneshot(Request::builder().uri("/uncached").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(resp.headers().get("etag").is_none());
+}
+
+#[tokio::test]
+async fn cache_ttl_route_behind_auth_is_private() {
+    let app = build_router_from_str(SCRIPT).await;
+    let claims = serde_json::json!({ "sub": "staff", "exp": 4102444800u64 });
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(b"shh"),
+    )
+    .unwrap();
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/orders")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("cache-control").unwrap(),
+        "private, max-age=60"
+    );
+}
@@ -0,0 +1,111 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::constants::{constants, enum_values};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use serde_json::json;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Const
+max_page = 100
+greeting = "hello there"
+
+@Enum/Status
+values = (active suspended closed)
+
+@Schema/Account
+name = string
+status = Status
+
+@Route/GET /limits
+run:
+    half = max_page / 2
+    out = { max: const.max_page, half: half, greeting: greeting }
+    respond 200 out
+
+@Route/POST /accounts
+run:
+    parse-json
+    validate body #Account
+    match body.status:
+        Status.active:
+            respond 200 "welcome"
+        "suspended":
+            respond 403 "suspended"
+        default:
+            respond 410 "gone"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post_status(status: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/accounts")
+        .header("content-type", "application/json")
+        .body(Body::from(format!(r#"{{"name": "ann", "status": "{}"}}"#, status)))
+        .unwrap()
+}
+
+#[test]
+fn collects_constants_and_enum_members() {
+    let doc = parse_rune(SCRIPT).unwrap();
+    let consts = constants(&doc);
+    assert_eq!(consts["max_page"], json!(100));
+    assert_eq!(consts["greeting"], json!("hello there"));
+    assert_eq!(
+        enum_values(&doc, "Status"),
+        Some(vec![json!("active"), json!("suspended"), json!("closed")])
+    );
+    assert_eq!(enum_values(&doc, "Missing"), None);
+}
+
+#[tokio::test]
+async fn constants_resolve_in_steps() {
+    let app = build_router_from_str(SCRIPT).await;
+    let req = Request::builder().uri("/limits").body(Body::empty()).unwrap();
+    let (status, body) = send(app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    let out: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(out, json!({"max": 100, "half": 50, "greeting": "hello there"}));
+}
+
+#[tokio::test]
+async fn enum_fields_are_validated_and_matched() {
+    let app = build_router_from_str(SCRIPT).await;
+    assert_eq!(send(app.clone(), post_status("active")).await, (StatusCode::OK, "welcome".to_string()));
+    assert_eq!(send(app.clone(), post_status("suspended")).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(app.clone(), post_status("closed")).await.0, StatusCode::GONE);
+
+    let (status, body) = send(app, post_status("deleted")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "Field `status` must be one of: active, suspended, closed");
+}
@@ -0,0 +1,94 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /retry-inline
+run:
+    attempts = 0
+    retry 3 backoff=exp delay=1ms { attempts = attempts + 1; datasource fetch Widget from Nowhere into data }
+on_error:
+    respond 503 attempts
+
+@Route/GET /retry-block
+run:
+    attempts = 0
+    retry 2 delay=1ms:
+        attempts = attempts + 1
+        if attempts == 2:
+            respond 200 attempts
+        datasource fetch Widget from Nowhere into data
+
+@Route/GET /timeout
+run:
+    timeout 20ms { sleep 2s; respond 200 "too slow" }
+
+@Route/GET /sleep
+run:
+    sleep 30ms
+    respond 200 "rested"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn get(uri: &str) -> (StatusCode, String) {
+    let app = build_router_from_str(SCRIPT).await;
+    let resp = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn retry_runs_block_until_attempts_are_exhausted() {
+    assert_eq!(
+        get("/retry-inline").await,
+        (StatusCode::SERVICE_UNAVAILABLE, "3".to_string())
+    );
+}
+
+#[tokio::test]
+async fn retry_block_stops_once_steps_succeed() {
+    assert_eq!(get("/retry-block").await, (StatusCode::OK, "2".to_string()));
+}
+
+#[tokio::test]
+async fn timeout_responds_504_when_block_is_too_slow() {
+    let started = Instant::now();
+    let (status, _) = get("/timeout").await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn sleep_waits_before_continuing() {
+    let started = Instant::now();
+    assert_eq!(get("/sleep").await, (StatusCode::OK, "rested".to_string()));
+    assert!(started.elapsed() >= Duration::from_millis(30));
+}
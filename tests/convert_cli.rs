@@ -0,0 +1,45 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn convert_mirrors_a_directory_tree_and_checks_for_drift() {
+    let dir = tempfile::tempdir().unwrap();
+    let configs = dir.path().join("configs");
+    std::fs::create_dir_all(configs.join("services")).unwrap();
+    std::fs::write(configs.join("app.yaml"), "App:\n  name: Shop\n  type: REST\n").unwrap();
+    std::fs::write(configs.join("services/users.yml"), "Service:\n  name: users\n").unwrap();
+    std::fs::write(configs.join("notes.txt"), "not a config").unwrap();
+    let out = dir.path().join("runes");
+
+    let convert = |check: bool| {
+        let mut cmd = vectrune_cmd();
+        cmd.arg("convert")
+            .arg(&configs)
+            .args(["--from", "yaml", "--to", "rune", "--out"])
+            .arg(&out);
+        if check {
+            cmd.arg("--check");
+        }
+        cmd
+    };
+
+    let assert = convert(true).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("2 files: 2 would change"));
+    assert!(!out.exists(), "--check must not write");
+
+    let assert = convert(false).assert().success();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("2 files: 2 written"));
+    let app = std::fs::read_to_string(out.join("app.rune")).unwrap();
+    assert!(app.contains("@App"), "{}", app);
+    assert!(app.contains("name = Shop"), "{}", app);
+    assert!(out.join("services/users.rune").is_file());
+
+    let assert = convert(true).assert().success();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("0 would change, 2 unchanged"));
+
+    std::fs::write(configs.join("app.yaml"), "App:\n  name: Store\n").unwrap();
+    convert(true).assert().failure();
+}
@@ -0,0 +1,117 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/UsersFile
+type = jsonfile
+connection = data/users.json
+
+@Schema/User
+email = string unique
+age = number
+
+@Route/CRUD /users
+data_source = UsersFile
+schema = User
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::String(String::from_utf8_lossy(&bytes).into())))
+}
+
+#[tokio::test]
+async fn bulk_insert_reports_each_item() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, bulk) = send(
+        &app,
+        "POST",
+        "/users/bulk",
+        Some(json!([
+            {"email": "ada@example.com", "age": 36},
+            {"email": "lin@example.com", "age": "old"},
+            "not a record",
+            {"email": "ada@example.com", "age": 40},
+            {"email": "bob@example.com", "age": 22}
+        ])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!((bulk["succeeded"].clone(), bulk["failed"].clone()), (json!(2), json!(3)));
+    let results = bulk["results"].as_array().unwrap();
+    assert_eq!(results[0], json!({"index": 0, "status": 201, "record": {"id": 1, "email": "ada@example.com", "age": 36}}));
+    assert_eq!(results[1]["status"], 400);
+    assert_eq!(results[2], json!({"index": 2, "status": 400, "error": "item is not an object"}));
+    assert_eq!((results[3]["status"].clone(), results[3]["error"]["field"].clone()), (json!(409), json!("email")));
+    assert_eq!(results[4]["record"]["id"], 2);
+
+    let (status, users) = send(&app, "GET", "/users", None).await;
+    assert_eq!((status, users.as_array().unwrap().len()), (StatusCode::OK, 2));
+
+    let (status, body) = send(&app, "POST", "/users/bulk", Some(json!({"email": "x@example.com"}))).await;
+    assert_eq!((status, body), (StatusCode::BAD_REQUEST, json!("expected a JSON array of records")));
+}
+
+#[tokio::test]
+async fn bulk_delete_reports_each_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+    send(
+        &app,
+        "POST",
+        "/users/bulk",
+        Some(json!([{"email": "a@example.com", "age": 1}, {"email": "b@example.com", "age": 2}, {"email": "c@example.com", "age": 3}])),
+    )
+    .await;
+
+    let (status, bulk) = send(&app, "DELETE", "/users/bulk", Some(json!([1, "3", 7, "x"]))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        bulk,
+        json!({
+            "succeeded": 2,
+            "failed": 2,
+            "results": [
+                {"index": 0, "status": 204, "id": 1},
+                {"index": 1, "status": 204, "id": 3},
+                {"index": 2, "status": 404, "error": "no record found"},
+                {"index": 3, "status": 400, "error": "invalid id 'x': expected an integer"}
+            ]
+        })
+    );
+    let (_, users) = send(&app, "GET", "/users", None).await;
+    assert_eq!(users, json!([{"id": 2, "email": "b@example.com", "age": 2}]));
+}
@@ -0,0 +1,96 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/PostsFile
+type = jsonfile
+connection = data/posts.json
+
+@Schema/Post
+title = string
+slug = string
+
+@Route/CRUD /posts
+data_source = PostsFile
+schema = Post
+before_create:
+    if body.title == "admin":
+        respond 403 "reserved title"
+    obj.set body.slug body.title
+after_create:
+    obj.set created.announced true
+before_delete:
+    if request.query.confirm != "yes":
+        respond 409 "confirm=yes required"
+after_delete:
+    respond 200 "deleted"
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::String(String::from_utf8_lossy(&bytes).into())))
+}
+
+#[tokio::test]
+async fn create_hooks_run_around_the_insert() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    // `slug` is required by the schema and filled in by `before_create`
+    let (status, created) = send(&app, "POST", "/posts", Some(json!({"title": "hello"}))).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    assert_eq!(created, json!({"id": 1, "title": "hello", "slug": "hello", "announced": true}));
+
+    let (status, _) = send(&app, "POST", "/posts", Some(json!({"title": "admin"}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, posts) = send(&app, "GET", "/posts", None).await;
+    assert_eq!(posts.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn delete_hooks_can_refuse_and_replace_the_response() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+    send(&app, "POST", "/posts", Some(json!({"title": "hello"}))).await;
+
+    let (status, _) = send(&app, "DELETE", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(&app, "GET", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(&app, "DELETE", "/posts/1?confirm=yes", None).await;
+    assert_eq!((status, body), (StatusCode::OK, json!("deleted")));
+    let (status, _) = send(&app, "GET", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,69 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::json;
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::builtins::builtin::data_source::coerce_id;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/CatsDataSource
+type = postgres
+connection = postgres://nobody@127.0.0.1:1/none
+
+@Schema/Cat
+name = string
+
+@Route/CRUD /cats
+data_source = CatsDataSource
+schema = Cat
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+#[tokio::test]
+async fn crud_item_route_rejects_non_numeric_id() {
+    let app = build_router_from_str(SCRIPT).await;
+    for method in ["GET", "DELETE"] {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri("/cats/abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[test]
+fn coerce_id_follows_schema_id_type() {
+    assert_eq!(coerce_id(&json!("42"), "number").unwrap(), json!(42));
+    assert_eq!(coerce_id(&json!(7), "number").unwrap(), json!(7));
+    assert!(coerce_id(&json!("1; DROP TABLE cats"), "number").is_err());
+    assert!(coerce_id(&json!("4.5"), "number").is_err());
+    assert_eq!(coerce_id(&json!("abc"), "string").unwrap(), json!("abc"));
+    assert_eq!(coerce_id(&json!(3), "string").unwrap(), json!("3"));
+}
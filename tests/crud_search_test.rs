@@ -0,0 +1,95 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/UsersFile
+type = jsonfile
+connection = data/users.json
+
+@Schema/User
+name = string
+email = string
+age = number
+
+@Route/CRUD /users
+data_source = UsersFile
+schema = User
+soft_delete = true
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::String(String::from_utf8_lossy(&bytes).into())))
+}
+
+fn names(users: &JsonValue) -> Vec<&str> {
+    users.as_array().unwrap().iter().map(|u| u["name"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn search_matches_string_fields_ignoring_case() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+    for (name, email) in [("Ada Lovelace", "ada@example.com"), ("Lin", "lin@ADA.dev"), ("Bob", "bob@example.com")] {
+        let (status, _) = send(&app, "POST", "/users", Some(json!({"name": name, "email": email, "age": 30}))).await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    let (status, users) = send(&app, "GET", "/users/search?q=ada", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&users), ["Ada Lovelace", "Lin"]);
+
+    let (_, users) = send(&app, "GET", "/users/search?q=ADA&fields=name", None).await;
+    assert_eq!(names(&users), ["Ada Lovelace"]);
+
+    let (_, users) = send(&app, "GET", "/users/search?q=%25", None).await;
+    assert_eq!(names(&users), Vec::<&str>::new());
+
+    let (status, _) = send(&app, "DELETE", "/users/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, users) = send(&app, "GET", "/users/search?q=ada", None).await;
+    assert_eq!(names(&users), ["Lin"]);
+}
+
+#[tokio::test]
+async fn search_rejects_a_missing_term_or_unsearchable_field() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, _) = send(&app, "GET", "/users/search", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, error) = send(&app, "GET", "/users/search?q=ada&fields=age", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.to_string().contains("expected one of: email, name"), "{}", error);
+}
@@ -0,0 +1,98 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/PostsFile
+type = jsonfile
+connection = data/posts.json
+
+@Schema/Post
+title = string
+timestamps = true
+
+@Route/CRUD /posts
+data_source = PostsFile
+schema = Post
+soft_delete = true
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn timestamps_are_maintained_and_deletes_are_soft() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, created) = send(
+        &app,
+        "POST",
+        "/posts",
+        Some(json!({"title": "Hello", "created_at": "1999-01-01T00:00:00Z"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created_at = created["created_at"].as_str().unwrap().to_string();
+    assert_ne!(created_at, "1999-01-01T00:00:00Z");
+    assert_eq!(created["updated_at"], created["created_at"]);
+    send(&app, "POST", "/posts", Some(json!({"title": "World"}))).await;
+
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let (status, updated) = send(&app, "PUT", "/posts/1", Some(json!({"title": "Hello again"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["created_at"], json!(created_at));
+    assert!(updated["updated_at"].as_str().unwrap() > created_at.as_str());
+
+    let (status, _) = send(&app, "DELETE", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, "DELETE", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, "GET", "/posts/1", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&app, "PUT", "/posts/1", Some(json!({"title": "Back"}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, posts) = send(&app, "GET", "/posts", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(posts.as_array().unwrap().len(), 1);
+    assert_eq!(posts[0]["title"], "World");
+
+    // The deleted record stays on disk with its deletion time
+    let on_disk: JsonValue =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("data/posts.json")).unwrap()).unwrap();
+    assert!(on_disk[0]["deleted_at"].is_string());
+    assert!(on_disk[1].get("deleted_at").is_none());
+}
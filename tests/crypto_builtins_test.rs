@@ -0,0 +1,145 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Const
+webhook_secret = "whsec"
+
+@Route/POST /digest
+run:
+    parse-json
+    hash = crypto.sha256 body.text
+    encoded = base64.encode body.text
+    decoded = base64.decode encoded
+    out = { hash: hash, encoded: encoded, decoded: decoded }
+    respond 200 out
+
+@Route/POST /webhook
+run:
+    ok = crypto.hmac_verify webhook_secret body request.headers.x-signature
+    if ok == false:
+        respond 401 "bad signature"
+    respond 200 "accepted"
+
+@Route/POST /token
+run:
+    parse-json
+    claims = { sub: body.user, role: "admin" }
+    token = jwt.sign claims "s3cret" 1h
+    respond 200 token
+
+@Route/GET /me
+run:
+    claims = jwt.verify request.headers.authorization "s3cret"
+    respond 200 claims.sub
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn hashes_and_base64_round_trips() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app, post("/digest", r#"{"text": "abc"}"#)).await;
+    assert_eq!(status, StatusCode::OK);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        out,
+        json!({
+            "hash": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "encoded": "YWJj",
+            "decoded": "abc"
+        })
+    );
+}
+
+#[tokio::test]
+async fn verifies_webhook_signatures() {
+    let app = build_router_from_str(SCRIPT).await;
+    let payload = r#"{"event":"ping"}"#;
+    // HMAC-SHA256 of the payload with key "whsec"
+    let signature = {
+        use hmac::{Hmac, Mac};
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"whsec").unwrap();
+        mac.update(payload.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    };
+    let signed = |sig: String| {
+        Request::builder()
+            .method("POST")
+            .uri("/webhook")
+            .header("x-signature", sig)
+            .body(Body::from(payload))
+            .unwrap()
+    };
+    let (status, body) = send(app.clone(), signed(format!("sha256={}", signature))).await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "accepted"));
+    let (status, _) = send(app, signed("sha256=deadbeef".to_string())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn signs_and_verifies_jwts() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app.clone(), post("/token", r#"{"user": "ann"}"#)).await;
+    assert_eq!(status, StatusCode::OK);
+    let token = body;
+    assert_eq!(token.split('.').count(), 3);
+
+    let me = |auth: String| {
+        Request::builder()
+            .uri("/me")
+            .header("authorization", auth)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, body) = send(app.clone(), me(format!("Bearer {}", token))).await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "ann"));
+
+    let (status, body) = send(app, me(format!("Bearer {}x", token))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.starts_with("invalid token"));
+}
@@ -0,0 +1,80 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/UsersCsv
+type = csv
+connection = data/users.csv
+
+@Schema/User
+name = string
+age = number
+active = bool
+
+@Route/CRUD /users
+data_source = UsersCsv
+schema = User
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn crud_routes_store_rows_in_a_csv_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, created) =
+        send(&app, "POST", "/users", Some(json!({"name": "Ada", "age": 36, "active": true}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["id"], 1);
+    send(&app, "POST", "/users", Some(json!({"name": "Bob", "age": 41, "active": false}))).await;
+
+    let csv = std::fs::read_to_string(dir.path().join("data/users.csv")).unwrap();
+    assert_eq!(csv, "id,active,age,name\n1,true,36,Ada\n2,false,41,Bob\n");
+
+    let (status, updated) = send(&app, "PUT", "/users/2", Some(json!({"active": true}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated, json!({"id": 2, "name": "Bob", "age": 41, "active": true}));
+
+    let (_, active) = send(&app, "GET", "/users?active=true&age=36", None).await;
+    assert_eq!(active, json!([{"id": 1, "name": "Ada", "age": 36, "active": true}]));
+
+    let (status, _) = send(&app, "DELETE", "/users/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, users) = send(&app, "GET", "/users", None).await;
+    assert_eq!(users.as_array().unwrap().len(), 1);
+}
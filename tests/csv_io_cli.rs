@@ -0,0 +1,70 @@
+use assert_cmd::Command;
+
+use rune_runtime::rune_ast::{RuneDocument, Value};
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+const SKATERS: &str = "id,name,zip,active\n1,Tony Hawk,02134,true\n2,\"Huston, Nyjah\",90210,false\n";
+
+#[test]
+fn csv_rows_become_typed_records() {
+    let doc = RuneDocument::from_csv(SKATERS, "Skater").unwrap();
+    assert_eq!(doc.sections.len(), 1);
+    let section = &doc.sections[0];
+    assert_eq!(section.path, vec!["Skater".to_string()]);
+    assert_eq!(section.records.len(), 2);
+    let first = &section.records[0].kv;
+    assert_eq!(first.get("id"), Some(&Value::Number(1.0)));
+    assert_eq!(first.get("active"), Some(&Value::Bool(true)));
+    assert_eq!(first.get("zip"), Some(&Value::String("02134".to_string())));
+    assert_eq!(section.records[1].kv.get("name"), Some(&Value::String("Huston, Nyjah".to_string())));
+}
+
+#[test]
+fn csv_to_json_names_the_section_after_the_file_or_section_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("skaters.csv");
+    std::fs::write(&file, SKATERS).unwrap();
+
+    let assert = vectrune_cmd().arg(&file).args(["-o", "json"]).assert().success();
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(json["skaters"]["record"][1]["name"], "Huston, Nyjah");
+
+    let assert = vectrune_cmd()
+        .args(["-", "-i", "csv", "--section", "Skater", "-o", "json"])
+        .write_stdin(SKATERS)
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(json["Skater"]["record"][0]["zip"], "02134");
+}
+
+#[test]
+fn records_flatten_to_csv() {
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-o", "csv", "--section", "Skateboarder"])
+        .assert()
+        .success();
+    assert_eq!(
+        String::from_utf8_lossy(&assert.get_output().stdout),
+        "age,name,style\n53,Tony Hawk,Vert\n26,Nyjah Huston,Street\n28,Leticia Bufoni,Street\n"
+    );
+
+    let assert = vectrune_cmd()
+        .args(["-", "-i", "csv", "-o", "csv"])
+        .write_stdin(SKATERS)
+        .assert()
+        .success();
+    assert_eq!(
+        String::from_utf8_lossy(&assert.get_output().stdout),
+        "active,id,name,zip\ntrue,1,Tony Hawk,02134\nfalse,2,\"Huston, Nyjah\",90210\n"
+    );
+
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-o", "csv", "--section", "Missing"])
+        .assert()
+        .failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("no section named Missing"));
+}
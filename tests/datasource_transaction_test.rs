@@ -0,0 +1,77 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/Cache
+type = memory
+connection = tx_cache
+
+@Route/POST /memory
+run:
+    datasource begin Cache
+    respond 200 "began"
+
+@Route/POST /commit
+run:
+    datasource commit Ledger
+    respond 200 "committed"
+
+@Route/POST /recover
+run:
+    datasource rollback Ledger
+    respond 200 "rolled back"
+on_error:
+    respond 422 error.message
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: &Router, uri: &str) -> (StatusCode, String) {
+    let req = Request::builder().method("POST").uri(uri).body(Body::empty()).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[tokio::test]
+async fn transactions_need_a_sql_datasource() {
+    let app = build_router().await;
+    let (status, body) = post(&app, "/memory").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("does not support transactions"), "{}", body);
+}
+
+#[tokio::test]
+async fn commit_and_rollback_without_begin_are_step_errors() {
+    let app = build_router().await;
+    let (status, body) = post(&app, "/commit").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("no transaction open on Ledger"), "{}", body);
+
+    let (status, body) = post(&app, "/recover").await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("no transaction open on Ledger"), "{}", body);
+}
@@ -0,0 +1,126 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::Value as JsonValue;
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Schema/Event
+name = string
+created_at = datetime
+
+@Route/GET /dates
+run:
+    start = date.parse "01/05/2024 08:30" "%d/%m/%Y %H:%M"
+    later = date.add start 1d -30m
+    earlier = date.add start -1y
+    day = date.format later "%Y-%m-%d"
+    stamp = date.format "2024-05-01T00:00:00Z" unix
+    out = { start: start, later: later, earlier: earlier, day: day, stamp: stamp }
+    respond 200 out
+
+@Route/GET /now
+run:
+    ts = now
+    respond 200 ts
+
+@Route/POST /events
+run:
+    parse-json
+    validate body #Event
+    respond 201 body.created_at
+
+@Route/GET /bad
+run:
+    when = date.parse "yesterday-ish"
+    respond 200 when
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn parses_adds_and_formats_dates() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app, get("/dates")).await;
+    assert_eq!(status, StatusCode::OK);
+    let json: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["start"], "2024-05-01T08:30:00Z");
+    assert_eq!(json["later"], "2024-05-02T08:00:00Z");
+    assert_eq!(json["earlier"], "2023-05-01T08:30:00Z");
+    assert_eq!(json["day"], "2024-05-02");
+    assert_eq!(json["stamp"], 1714521600);
+}
+
+#[tokio::test]
+async fn now_returns_an_iso_timestamp() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app, get("/now")).await;
+    assert_eq!(status, StatusCode::OK);
+    let ts = body;
+    assert!(ts.ends_with('Z'), "unexpected timestamp {}", ts);
+    assert!(chrono::DateTime::parse_from_rfc3339(&ts).is_ok());
+}
+
+#[tokio::test]
+async fn validate_checks_datetime_fields() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, _) = send(
+        app.clone(),
+        post("/events", r#"{"name": "launch", "created_at": "2024-05-01T12:00:00+02:00"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = send(
+        app.clone(),
+        post("/events", r#"{"name": "launch", "created_at": "last tuesday"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("created_at"));
+
+    let (status, body) = send(app, get("/bad")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("invalid date"));
+}
@@ -0,0 +1,132 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/POST /orders
+run:
+    parse-json
+    order.customer.name = body.name
+    order.customer.tier = "gold"
+    order.total = body.qty * 2
+    order.meta = { source: "api" }
+    order.placed_at = date.parse "2024-05-01"
+    respond 200 order
+
+@Route/POST /users
+run:
+    parse-json
+    body.users[body.index].email = body.email
+    body.users[-1].active = true
+    body.users[2] = { name: "cy" }
+    respond 200 body.users
+
+@Route/POST /conflict
+run:
+    parse-json
+    body.name.first = "ann"
+    respond 200 body
+
+@Route/POST /out-of-range
+run:
+    parse-json
+    body.users[5].email = body.email
+    respond 200 body
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: Router, uri: &str, body: &str) -> (StatusCode, String) {
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn assignment_creates_intermediate_objects() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = post(app, "/orders", r#"{"name": "ann", "qty": 3}"#).await;
+    assert_eq!(status, StatusCode::OK);
+    let order: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        order,
+        json!({
+            "customer": {"name": "ann", "tier": "gold"},
+            "total": 6,
+            "meta": {"source": "api"},
+            "placed_at": "2024-05-01T00:00:00Z"
+        })
+    );
+}
+
+#[tokio::test]
+async fn assignment_writes_into_list_elements() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = post(
+        app,
+        "/users",
+        r#"{"users": [{"name": "ann"}, {"name": "bo"}], "index": 0, "email": "ann@example.com"}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let users: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        users,
+        json!([
+            {"name": "ann", "email": "ann@example.com"},
+            {"name": "bo", "active": true},
+            {"name": "cy"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn assignment_reports_type_conflicts() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = post(app.clone(), "/conflict", r#"{"name": "ann"}"#).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("`body.name` is a string"), "{}", body);
+
+    let (status, body) = post(
+        app,
+        "/out-of-range",
+        r#"{"users": [{"name": "ann"}], "email": "x@example.com"}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("out of range for `body.users` (length 1)"), "{}", body);
+}
@@ -0,0 +1,58 @@
+use assert_cmd::Command;
+
+const OLD: &str = "#!RUNE\n@App\nname = Shop\nport = 8080\n\n@Tags\nlist:\n  - a\n  - b\n  - c\n\n@User\n+ name = Ada\n  age = 36\n+ name = Bob\n  age = 40\n\n@Old\nx = 1\n";
+const NEW: &str = "#!RUNE\n@App\nname = Shop\nport = 9090\ndebug = true\n\n@Tags\nlist:\n  - a\n  - x\n  - c\n  - d\n\n@User\n+ name = Ada\n  age = 37\n+ name = Bob\n  age = 40\n+ name = Cy\n  age = 20\n\n@New\ny = 2\n";
+
+fn diff(args: &[&str], expect_failure: bool) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let (old, new) = (dir.path().join("old.rune"), dir.path().join("new.rune"));
+    std::fs::write(&old, OLD).unwrap();
+    std::fs::write(&new, NEW).unwrap();
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"));
+    cmd.args(["diff", old.to_str().unwrap(), new.to_str().unwrap()]).args(args);
+    let assert = if expect_failure { cmd.assert().failure() } else { cmd.assert().success() };
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn diff_reports_structural_changes_as_text() {
+    let expected = "\
++ @App.debug: true
+~ @App.port: 8080 -> 9090
+- @Tags.list[1]: b
++ @Tags.list[1]: x
++ @Tags.list[3]: d
+~ @User[0].age: 36 -> 37
++ @User[2]: {\"age\":20.0,\"name\":\"Cy\"}
+- @Old: {\"x\":1.0}
++ @New: {\"y\":2.0}
+";
+    assert_eq!(diff(&[], false), expected);
+}
+
+#[test]
+fn diff_prints_json_and_rune_patches() {
+    let json: serde_json::Value = serde_json::from_str(&diff(&["-o", "json"], false)).unwrap();
+    assert_eq!(json[1], serde_json::json!({"op": "change", "path": "@App.port", "old": 8080.0, "new": 9090.0}));
+    assert_eq!(json.as_array().unwrap().len(), 9);
+
+    let patch = diff(&["-o", "rune-patch"], false);
+    let doc = rune_runtime::rune_parser::parse_rune(&patch).unwrap();
+    let patch_section = doc.sections.iter().find(|s| s.path == ["Patch"]).unwrap();
+    assert_eq!(patch_section.records.len(), 9);
+    assert!(patch.contains("+ op = change\n  path = @App.port\n  old = 8080\n  new = 9090\n"), "{}", patch);
+}
+
+#[test]
+fn diff_exit_code_flags_differences() {
+    diff(&["--exit-code"], true);
+
+    let dir = tempfile::tempdir().unwrap();
+    let same = dir.path().join("same.rune");
+    std::fs::write(&same, OLD).unwrap();
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .args(["diff", same.to_str().unwrap(), same.to_str().unwrap(), "--exit-code"])
+        .assert()
+        .success();
+    assert!(assert.get_output().stdout.is_empty());
+}
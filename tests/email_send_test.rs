@@ -0,0 +1,122 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+/// A minimal SMTP server that accepts every message and records the raw transcript.
+async fn start_smtp_server() -> (u16, Arc<Mutex<String>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let transcripts = Arc::new(Mutex::new(String::new()));
+    let recorded = transcripts.clone();
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let recorded = recorded.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = socket.into_split();
+                let mut lines = BufReader::new(read).lines();
+                let mut in_data = false;
+                write.write_all(b"220 test ESMTP\r\n").await.unwrap();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    {
+                        let mut transcript = recorded.lock().unwrap();
+                        transcript.push_str(&line);
+                        transcript.push('\n');
+                    }
+                    let reply: &[u8] = if in_data {
+                        if line != "." {
+                            continue;
+                        }
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else if line.starts_with("EHLO") {
+                        b"250 test\r\n"
+                    } else if line == "DATA" {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    } else if line == "QUIT" {
+                        write.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    } else {
+                        b"250 ok\r\n"
+                    };
+                    write.write_all(reply).await.unwrap();
+                }
+            });
+        }
+    });
+    (port, transcripts)
+}
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn email_send_delivers_through_smtp_section() {
+    let (port, transcripts) = start_smtp_server().await;
+    let rune = format!(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Smtp
+host = 127.0.0.1
+port = {port}
+tls = none
+from = "Shop <noreply@shop.test>"
+
+@Route/POST /orders
+run:
+    parse-json
+    sent = email.send body.email "Order {{body.id}} received" "Thanks {{body.name}}!"
+    respond 201 sent
+
+@Route/POST /bad
+run:
+    email.send "not an address" "Hi" "Body"
+    respond 200 OK
+"#
+    );
+    let app = build_router_from_str(&rune).await;
+
+    let resp = app
+        .clone()
+        .oneshot(post("/orders", r#"{"id":7,"name":"Ann","email":"ann@example.com"}"#))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let transcript = transcripts.lock().unwrap().clone();
+    assert!(transcript.contains("MAIL FROM:<noreply@shop.test>"), "{}", transcript);
+    assert!(transcript.contains("RCPT TO:<ann@example.com>"), "{}", transcript);
+    assert!(transcript.contains("Subject: Order 7 received"), "{}", transcript);
+    assert!(transcript.contains("Thanks Ann!"), "{}", transcript);
+
+    let resp = app.oneshot(post("/bad", "{}")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,111 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Errors
+content_type = application/problem+json
+404 {
+    type = about:blank
+    title = {error.title}
+    status = {error.status}
+    detail = {error.message}
+}
+
+@Route/GET /missing
+run:
+    respond 404 "no such widget"
+
+@Route/GET /broken
+run:
+    datasource fetch Widget from Nowhere into data
+    respond 200 data
+
+@Route/GET /recovered
+run:
+    datasource fetch Widget from Nowhere into data
+on_error:
+    respond 503 "try again later"
+
+@Route/GET /gone
+run:
+    datasource fetch Widget from Nowhere into data
+on_error:
+    respond 404 "widget retired"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn get(uri: &str) -> (StatusCode, Option<String>, String) {
+    let app = build_router_from_str(SCRIPT).await;
+    let resp = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, content_type, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn errors_section_templates_problem_json() {
+    let (status, content_type, body) = get("/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["title"], "Not Found");
+    assert_eq!(json["status"], 404);
+    assert_eq!(json["detail"], "no such widget");
+}
+
+#[tokio::test]
+async fn unmapped_builtin_error_keeps_500_body() {
+    let (status, _, body) = get("/broken").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("Nowhere"));
+}
+
+#[tokio::test]
+async fn on_error_steps_handle_builtin_errors() {
+    let (status, _, body) = get("/recovered").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body, "try again later");
+}
+
+#[tokio::test]
+async fn on_error_status_is_used_for_the_error_template() {
+    let (status, content_type, body) = get("/gone").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(content_type.as_deref(), Some("application/problem+json"));
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["status"], 404);
+    assert_eq!(json["title"], "Not Found");
+    assert_eq!(json["detail"], "widget retired");
+}
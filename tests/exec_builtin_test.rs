@@ -0,0 +1,77 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+allow_exec = (scripts/greet.sh)
+
+@Route/POST /greet
+run:
+    parse-json
+    result = exec scripts/greet.sh body.name body.tags
+    respond 200 result
+
+@Route/GET /shell
+run:
+    result = exec sh -c "echo pwned"
+    respond 200 result
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn exec_runs_allowlisted_commands_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("scripts")).unwrap();
+    let script = dir.path().join("scripts/greet.sh");
+    std::fs::write(&script, "#!/bin/sh\necho \"hello $1 ($#)\"\necho oops >&2\nexit 3\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let app = build_router(dir.path()).await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/greet")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"name":"Ann Lee","tags":["a","b"]}"#))
+        .unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(result["stdout"], "hello Ann Lee (3)");
+    assert_eq!(result["stderr"], "oops");
+    assert_eq!(result["code"], 3);
+
+    let req = Request::builder().uri("/shell").body(Body::empty()).unwrap();
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!body.contains("pwned"), "{}", body);
+}
@@ -0,0 +1,100 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::admin::build_admin_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+name = Shop
+type = REST
+
+@Admin
+token = secret-token
+
+@Route/GET /price
+run:
+    respond 200 "9.99"
+
+@Experiment/pricing
+route = GET /price
+sticky = header x-session-id
+split {
+    control = 50
+    discount = 50
+}
+discount:
+    respond 200 "7.99"
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_admin_router(state).await
+}
+
+async fn price(app: &Router, session: &str) -> (String, String) {
+    let req = Request::builder()
+        .uri("/price")
+        .header("x-session-id", session)
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let variant = resp
+        .headers()
+        .get("x-experiment")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (variant, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn splits_traffic_between_sticky_variants() {
+    let app = build_router().await;
+
+    let mut seen = std::collections::HashSet::new();
+    for i in 0..40 {
+        let session = format!("session-{}", i);
+        let (variant, body) = price(&app, &session).await;
+        match variant.as_str() {
+            "pricing=control" => assert_eq!(body, "9.99"),
+            "pricing=discount" => assert_eq!(body, "7.99"),
+            other => panic!("unexpected variant header `{}`", other),
+        }
+        // The same session always gets the same variant
+        assert_eq!(price(&app, &session).await, (variant.clone(), body));
+        seen.insert(variant);
+    }
+    assert_eq!(seen.len(), 2, "both variants should receive traffic");
+
+    let req = Request::builder()
+        .uri("/__admin/experiments")
+        .header("Authorization", "Bearer secret-token")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let counts: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let served: u64 = counts["pricing"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|v| v.as_u64().unwrap())
+        .sum();
+    assert_eq!(served, 80);
+}
@@ -0,0 +1,111 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Function/line_total(price, qty)
+run:
+    return (price * qty)
+
+@Route/POST /orders
+run:
+    total = (body.price + 2) * body.qty - 1
+    label = "Order for " + body.name + "!"
+    discounted = line_total(body.price, body.qty) * 0.5
+    big = total > 20 and not (body.name == "Linus")
+    summary = { total: total, label: label, discounted: discounted, big: big }
+    respond 200 summary
+
+@Route/POST /names
+run:
+    names = body.users.filter(it.age > 30).map(it.name)
+    respond 200 names
+
+@Route/POST /check
+run:
+    if body.qty * body.price >= 100 || body.vip:
+        respond 200 "priority"
+    respond 200 (body.qty + 1)
+
+@Route/POST /legacy
+run:
+    users = body.users
+    oldest = users.max it.age
+    count = users.count it.age > 30
+    next = users.max it.age + 1
+    result = { oldest: oldest, count: count, next: next }
+    respond 200 result
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::current_dir().unwrap(),
+    };
+    build_app_router(state).await
+}
+
+async fn post(uri: &str, body: Value) -> (StatusCode, Value) {
+    let app = build_router().await;
+    let req = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    (status, serde_json::from_str(&text).unwrap_or(Value::String(text)))
+}
+
+#[tokio::test]
+async fn assignments_evaluate_expressions() {
+    let (status, body) = post("/orders", json!({"name": "Ada", "price": 4, "qty": 3})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({"total": 17, "label": "Order for Ada!", "discounted": 6, "big": false})
+    );
+}
+
+#[tokio::test]
+async fn method_calls_chain_over_it() {
+    let users = json!([{"name": "Ada", "age": 36}, {"name": "Linus", "age": 28}, {"name": "Grace", "age": 45}]);
+    let (_, body) = post("/names", json!({"users": users})).await;
+    assert_eq!(body, json!(["Ada", "Grace"]));
+}
+
+#[tokio::test]
+async fn conditions_and_arguments_are_expressions() {
+    let (_, body) = post("/check", json!({"qty": 10, "price": 10})).await;
+    assert_eq!(body, json!("priority"));
+    let (_, body) = post("/check", json!({"qty": 1, "price": 10, "vip": true})).await;
+    assert_eq!(body, json!("priority"));
+    let (_, body) = post("/check", json!({"qty": 1, "price": 10})).await;
+    assert_eq!(body, json!(2));
+}
+
+#[tokio::test]
+async fn whitespace_builtin_calls_still_run() {
+    let users = json!([{"name": "Ada", "age": 36}, {"name": "Linus", "age": 28}, {"name": "Grace", "age": 45}]);
+    let (_, body) = post("/legacy", json!({"users": users})).await;
+    assert_eq!(body["oldest"].as_f64(), Some(45.0));
+    assert_eq!(body["count"], json!(2));
+    assert_eq!(body["next"], json!(46));
+}
@@ -0,0 +1,91 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/PUT /notes/{id}
+run:
+    parse-json
+    file.write "data/{id}.json" body
+    file.append data/log.jsonl body
+    respond 204 OK
+
+@Route/GET /notes/{id}
+run:
+    note = file.read "data/{id}.json"
+    respond 200 note
+
+@Route/GET /files
+run:
+    files = file.list data
+    log_text = file.read data/log.jsonl
+    listing = { files: files, log: log_text }
+    respond 200 listing
+
+@Route/GET /escape
+run:
+    secret = file.read "../secret.txt"
+    respond 200 secret
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn file_builtins_persist_inside_the_rune_directory() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("app");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(root.path().join("secret.txt"), "top secret").unwrap();
+    let app = build_router(&dir).await;
+
+    let (status, _) = send(&app, "PUT", "/notes/1", r#"{"title":"first"}"#).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    send(&app, "PUT", "/notes/2", r#"{"title":"second"}"#).await;
+    assert!(dir.join("data/1.json").is_file());
+
+    let (status, body) = send(&app, "GET", "/notes/2", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["title"], "second");
+
+    let (_, body) = send(&app, "GET", "/files", "").await;
+    let listing: serde_json::Value = serde_json::from_str(&body).expect(&body);
+    assert_eq!(listing["files"], serde_json::json!(["1.json", "2.json", "log.jsonl"]));
+    assert_eq!(listing["log"], "{\"title\":\"first\"}\n{\"title\":\"second\"}\n");
+
+    let (status, body) = send(&app, "GET", "/escape", "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(!body.contains("top secret"));
+}
@@ -0,0 +1,104 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Function/line_total(price, qty)
+run:
+    total = price * qty
+    return total
+
+@Function/require_name(person)
+run:
+    if person.name == null:
+        respond 422 "name is required"
+    return person.name
+
+@Function/countdown(n)
+run:
+    if n == 0:
+        return "liftoff"
+    next = n - 1
+    result = call countdown next
+    return result
+
+@Route/POST /orders
+run:
+    parse-json
+    total = call line_total body.price body.qty
+    name = call require_name body
+    order = {"name": name, "total": total, "price_seen": price}
+    respond 200 order
+
+@Route/GET /countdown
+run:
+    result = call countdown 3
+    respond 200 result
+
+@Route/GET /forever
+run:
+    result = call countdown -1
+    respond 200 result
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::current_dir().unwrap(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn functions_return_values_from_their_own_context() {
+    let app = build_router().await;
+
+    let (status, body) = send(&app, "POST", "/orders", r#"{"name": "Ada", "price": 2.5, "qty": 4}"#).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let order: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(order["name"], "Ada");
+    assert_eq!(order["total"], 10.0);
+    assert!(order["price_seen"].is_null(), "parameters stay in the function: {}", body);
+
+    let (status, body) = send(&app, "POST", "/orders", r#"{"price": 1, "qty": 1}"#).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(body.contains("name is required"), "{}", body);
+}
+
+#[tokio::test]
+async fn functions_can_call_themselves_up_to_a_limit() {
+    let app = build_router().await;
+
+    let (status, body) = send(&app, "GET", "/countdown", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("liftoff"), "{}", body);
+
+    let (status, _) = send(&app, "GET", "/forever", "").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,88 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::admin::build_switchable_router;
+use rune_runtime::cli::git_deploy::{deploy_if_changed, load_checkout_document, GitSource};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+
+fn app(version: &str) -> String {
+    format!(
+        r#"#!RUNE
+
+@App
+name = Deployed
+type = REST
+
+@Route/GET /version
+run:
+    respond 200 "{}"
+"#,
+        version
+    )
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("git should run");
+    assert!(status.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&status.stderr));
+}
+
+fn commit(repo: &Path, contents: &str, message: &str) {
+    std::fs::write(repo.join("apis/app.rune"), contents).unwrap();
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "-q", "-m", message]);
+}
+
+async fn get_version(app: &Router) -> (StatusCode, String) {
+    let resp = app
+        .clone()
+        .oneshot(Request::builder().uri("/version").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn redeploys_new_commits_and_keeps_serving_through_broken_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let origin = dir.path().join("origin");
+    std::fs::create_dir_all(origin.join("apis")).unwrap();
+    git(&origin, &["init", "-q", "-b", "main"]);
+    commit(&origin, &app("v1"), "v1");
+
+    let mut source = GitSource::new(&format!("file://{}", origin.display()), None);
+    source.checkout = dir.path().join("checkout");
+    assert!(source.sync().unwrap(), "first sync clones");
+    assert!(!source.sync().unwrap(), "nothing new to pull");
+
+    let doc = Arc::new(load_checkout_document(&source, "apis/app.rune").unwrap());
+    let state = AppState {
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        doc,
+        path: source.checkout.join("apis"),
+    };
+    let (router, switch) = build_switchable_router(state).await;
+    assert_eq!(get_version(&router).await, (StatusCode::OK, "v1".to_string()));
+
+    commit(&origin, &app("v2"), "v2");
+    let deployed = deploy_if_changed(&source, "apis/app.rune", &switch).await.unwrap();
+    assert!(deployed.is_some());
+    assert_eq!(get_version(&router).await, (StatusCode::OK, "v2".to_string()));
+    assert_eq!(deploy_if_changed(&source, "apis/app.rune", &switch).await.unwrap(), None);
+
+    commit(&origin, "#!RUNE\n\n@App\ntype = SOAP\n", "broken");
+    assert!(deploy_if_changed(&source, "apis/app.rune", &switch).await.is_err());
+    assert_eq!(get_version(&router).await, (StatusCode::OK, "v2".to_string()));
+}
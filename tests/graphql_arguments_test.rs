@@ -0,0 +1,91 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@Schema/Book
+id = number
+title = string
+
+@GraphQL/Query/JSON
+page(limit: number = 2, title: string?):
+    out = { limit: limit, title: title }
+    return out
+total(ids: [number]):
+    total = ids.sum
+    return total
+tagged(tags: [string?]? = ["a", "b"]):
+    return tags
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_arguments.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn optional_arguments_take_their_defaults() {
+    let app = build_router().await;
+
+    let json = query(&app, "{ page }").await;
+    assert_eq!(json["data"]["page"], json!({ "limit": 2, "title": null }), "{}", json);
+    let json = query(&app, r#"{ page(limit: 5, title: "Dune") }"#).await;
+    assert_eq!(json["data"]["page"], json!({ "limit": 5, "title": "Dune" }));
+    let json = query(&app, "{ tagged }").await;
+    assert_eq!(json["data"]["tagged"], json!(["a", "b"]));
+}
+
+#[tokio::test]
+async fn list_arguments_arrive_as_lists() {
+    let app = build_router().await;
+
+    let json = query(&app, "{ total(ids: [1, 3.5]) }").await;
+    assert_eq!(json["data"]["total"], 4.5, "{}", json);
+
+    let json = query(&app, "{ total }").await;
+    assert!(json["errors"][0]["message"].as_str().unwrap().contains("ids"), "{}", json);
+}
+
+#[tokio::test]
+async fn argument_types_are_introspectable() {
+    let app = build_router().await;
+
+    let json = query(&app, r#"{ __type(name: "Query") { fields { name args { name defaultValue type { kind ofType { kind ofType { kind name } } } } } } }"#).await;
+    let fields = json["data"]["__type"]["fields"].as_array().unwrap();
+    let args = |field: &str| fields.iter().find(|f| f["name"] == field).unwrap()["args"].clone();
+    assert_eq!(args("page")[0]["defaultValue"], "2");
+    assert_eq!(args("page")[1]["type"]["kind"], "SCALAR");
+    assert_eq!(args("total")[0]["type"], json!({ "kind": "NON_NULL", "ofType": { "kind": "LIST", "ofType": { "kind": "NON_NULL", "name": null } } }));
+    assert_eq!(args("tagged")[0]["type"]["kind"], "LIST");
+}
@@ -0,0 +1,108 @@
+use axum::http::{Request, StatusCode};
+use axum::routing::post;
+use axum::{body::Body, Json, Router};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+/// Starts a GraphQL endpoint that answers with the variables it received.
+async fn start_echo_graphql() -> String {
+    let app = Router::new().route(
+        "/graphql",
+        post(|Json(req): Json<Value>| async move {
+            Json(json!({ "data": { "echo": req["variables"] } }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/graphql", addr)
+}
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+    let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn graphql_routes_query_the_local_schema_and_remote_endpoints() {
+    let endpoint = start_echo_graphql().await;
+    let rune = format!(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Schema/Book
+id = number
+title = string
+
+@GraphQL/Query
+books:
+    books = memory.get "books"
+    return books
+book(id: number):
+    books = memory.get "books"
+    book = books.find it.id == id
+    return book
+
+@Memory/books
++ id = 1
+  title = "Dune"
++ id = 2
+  title = "Emma"
+
+@Route/GET /books
+graphql = "{{ books {{ title }} }}"
+
+@Route/GET /books/{{id}}
+graphql = "query($id: Float!) {{ book(id: $id) {{ title }} }}"
+
+@Route/GET /broken
+graphql = "{{ shelves {{ title }} }}"
+
+@Route/GET /remote/{{id}}
+graphql = "query($id: Int!, $q: String) {{ echo }}"
+graphql_endpoint = "{endpoint}"
+"#
+    );
+    let app = build_router_from_str(&rune).await;
+
+    let (status, body) = get_json(&app, "/books").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "books": [{ "title": "Dune" }, { "title": "Emma" }] }));
+
+    let (status, body) = get_json(&app, "/books/2").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["book"]["title"], "Emma");
+
+    let (status, body) = get_json(&app, "/broken").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["errors"][0]["message"].as_str().unwrap().contains("shelves"));
+
+    let (status, body) = get_json(&app, "/remote/5?q=rust").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["echo"], json!({ "id": 5, "q": "rust" }));
+}
@@ -0,0 +1,112 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@GraphQL/Enum/Status
+values = (ACTIVE SUSPENDED)
+description = "Account state"
+
+@GraphQL/Scalar/Date
+pattern = "^[0-9]+-[0-9]+-[0-9]+$"
+
+@Enum/Plan
+values = (free pro)
+
+@Memory/accounts
++ id = 1
+  name = "Ada"
+  status = "ACTIVE"
+  plan = "pro"
+  opened = "2024-01-05"
++ id = 2
+  name = "Grace"
+  status = "SUSPENDED"
+  plan = "free"
+  opened = "2023-11-20"
+
+@Schema/Account
+name = string
+status = Status
+plan = Plan
+opened = Date
+
+@GraphQL/Query
+accounts(status: Status):
+    accounts = memory.get "accounts"
+    matching = accounts.filter it.status == status
+    return matching
+
+@GraphQL/Query/Date
+openedOn(day: Date):
+    return day
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_custom_types.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = serde_json::json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn enum_arguments_and_fields_are_typed() {
+    let app = build_router().await;
+
+    let json = query(&app, "{ accounts(status: SUSPENDED) { name status plan opened } }").await;
+    assert_eq!(
+        json["data"]["accounts"],
+        serde_json::json!([{ "name": "Grace", "status": "SUSPENDED", "plan": "free", "opened": "2023-11-20" }])
+    );
+
+    let json = query(&app, "{ accounts(status: CLOSED) { name } }").await;
+    assert!(json["errors"][0]["message"].as_str().unwrap().contains("CLOSED"), "{}", json);
+}
+
+#[tokio::test]
+async fn custom_scalars_validate_inputs_and_are_introspectable() {
+    let app = build_router().await;
+
+    let json = query(&app, r#"{ openedOn(day: "2024-01-05") }"#).await;
+    assert!(json["errors"].is_null(), "{}", json);
+
+    let json = query(&app, r#"{ openedOn(day: "last tuesday") }"#).await;
+    assert!(json["errors"].is_array(), "{}", json);
+
+    let json = query(&app, r#"{ __type(name: "Status") { kind description enumValues { name } } }"#).await;
+    assert_eq!(json["data"]["__type"]["kind"], "ENUM");
+    assert_eq!(json["data"]["__type"]["description"], "Account state");
+    assert_eq!(json["data"]["__type"]["enumValues"], serde_json::json!([{ "name": "ACTIVE" }, { "name": "SUSPENDED" }]));
+
+    let json = query(&app, r#"{ __type(name: "Date") { kind } }"#).await;
+    assert_eq!(json["data"]["__type"]["kind"], "SCALAR");
+}
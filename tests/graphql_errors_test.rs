@@ -0,0 +1,93 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@Memory/books
++ id = 1
+  title = "Dune"
+
+@Schema/Book
+id = number
+title = string
+
+@GraphQL/Query
+books:
+    books = memory.get "books"
+    return books
+
+@GraphQL/Query/Book
+book(id: number):
+    books = memory.get "books"
+    found = books.find it.id == id
+    if found == null:
+        respond 404 "no book with that id"
+    return found
+
+@GraphQL/Mutation/Book
+publish(id: number):
+    enqueue missing_worker id
+    respond 200 "ok"
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_errors.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn error_responses_become_field_errors() {
+    let app = build_router().await;
+    let res = query(&app, "{ found: book(id: 1) { title } missing: book(id: 9) { title } }").await;
+    assert_eq!(res["data"]["found"], json!({ "title": "Dune" }));
+    assert_eq!(res["data"]["missing"], Value::Null);
+
+    let errors = res["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1, "{}", res);
+    assert_eq!(errors[0]["message"], "no book with that id");
+    assert_eq!(errors[0]["path"], json!(["missing"]));
+    assert_eq!(errors[0]["extensions"]["status"], 404);
+    assert_eq!(errors[0]["extensions"]["code"], "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn step_errors_carry_their_status() {
+    let app = build_router().await;
+    let res = query(&app, "mutation { publish(id: 1) { title } }").await;
+    let error = &res["errors"][0];
+    assert_eq!(error["path"], json!(["publish"]), "{}", res);
+    assert_eq!(error["extensions"]["status"], 500);
+    assert_eq!(error["extensions"]["code"], "INTERNAL_SERVER_ERROR");
+    assert!(error["message"].as_str().unwrap().contains("missing_worker"), "{}", res);
+}
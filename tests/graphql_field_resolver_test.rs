@@ -0,0 +1,109 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@Memory/library_books
++ id = 1
+  title = "Dune"
+  author_id = 10
++ id = 2
+  title = "Emma"
+  author_id = 20
++ id = 3
+  title = "Persuasion"
+  author_id = 20
+
+@Memory/library_authors
++ id = 10
+  name = "Frank Herbert"
++ id = 20
+  name = "Jane Austen"
+
+@Schema/Book
+id = number
+title = string
+author_id = number
+
+@Schema/Author
+id = number
+name = string
+
+@GraphQL/Query
+books:
+    books = memory.get "library_books"
+    return books
+
+@GraphQL/Type/Book
+author:
+    authors = memory.get "library_authors"
+    author = authors.find it.id == parent.author_id
+    return author
+
+@GraphQL/Type/Author
+books:
+    books = memory.get "library_books"
+    written = books.filter it.author_id == parent.id
+    return written
+
+@GraphQL/Type/Author/String
+signature(text: string):
+    return text
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_field_resolver.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn field_resolvers_see_the_parent_object() {
+    let app = build_router().await;
+
+    let json = query(&app, "{ books { title author { name books { title } } } }").await;
+    assert!(json["errors"].is_null(), "{}", json);
+    let books = json["data"]["books"].as_array().unwrap();
+    assert_eq!(books[0]["author"]["name"], "Frank Herbert");
+    assert_eq!(books[1]["author"]["name"], "Jane Austen");
+    assert_eq!(books[1]["author"]["books"], json!([{ "title": "Emma" }, { "title": "Persuasion" }]));
+}
+
+#[tokio::test]
+async fn field_resolvers_take_arguments_and_a_declared_return_type() {
+    let app = build_router().await;
+
+    let json = query(&app, r#"{ books { author { name signature(text: "with thanks") } } }"#).await;
+    assert!(json["errors"].is_null(), "{}", json);
+    assert_eq!(json["data"]["books"][0]["author"]["signature"], "with thanks");
+}
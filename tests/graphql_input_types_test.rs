@@ -0,0 +1,96 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@Schema/Author
+name = string
+
+@Schema/Book
+id = number
+title = string
+published = bool
+author = Author
+
+@Memory/books
++ id = 1
+  title = "Dune"
+  published = true
+
+@GraphQL/Mutation/Book
+addBook(input: BookInput):
+    books = memory.get "books"
+    new_id = books.max it.id + 1
+    obj.set body.id new_id
+    memory.append "books" body
+    return body
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_input_types.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn mutations_take_schema_input_objects_as_body() {
+    let app = build_router().await;
+
+    let json = query(
+        &app,
+        r#"mutation { addBook(input: { id: 0, title: "Emma", published: false, author: { name: "Austen" } }) { id title author { name } } }"#,
+    )
+    .await;
+    assert!(json["errors"].is_null(), "{}", json);
+    assert_eq!(json["data"]["addBook"], json!({ "id": 2, "title": "Emma", "author": { "name": "Austen" } }));
+
+    let json = query(&app, r#"mutation { addBook(input: { title: 7 }) { id } }"#).await;
+    assert!(json["errors"].is_array(), "{}", json);
+}
+
+#[tokio::test]
+async fn input_objects_mirror_schema_fields() {
+    let app = build_router().await;
+
+    let json = query(&app, r#"{ __type(name: "BookInput") { kind inputFields { name type { name ofType { name } } } } }"#).await;
+    assert_eq!(json["data"]["__type"]["kind"], "INPUT_OBJECT");
+    assert_eq!(
+        json["data"]["__type"]["inputFields"],
+        json!([
+            { "name": "author", "type": { "name": "AuthorInput", "ofType": null } },
+            { "name": "id", "type": { "name": "Float", "ofType": null } },
+            { "name": "published", "type": { "name": "Boolean", "ofType": null } },
+            { "name": "title", "type": { "name": "String", "ofType": null } }
+        ])
+    );
+}
@@ -0,0 +1,106 @@
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Graphql
+
+@Enum/Status
+values = (draft published)
+
+@Memory/events
++ id = 1
+  title = "Launch"
+  status = "published"
+  starts_at = "2024-03-01T09:00:00Z"
+  meta = "none"
+
+@Schema/Event
+title = string
+status = Status
+starts_at = datetime
+meta = json
+
+@GraphQL/Query
+events:
+    events = memory.get "events"
+    return events
+
+@GraphQL/Query/DateTime
+echoTime(at: datetime):
+    return at
+
+@GraphQL/Query/JSON
+echoJson(value: json):
+    return value
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("graphql_scalars.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn query(app: &Router, query: &str) -> Value {
+    let body = json!({ "query": query }).to_string();
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn datetime_and_json_fields_use_scalars() {
+    let app = build_router().await;
+    let types = query(
+        &app,
+        r#"{ __type(name: "Event") { fields { name type { ofType { name kind } } } } }"#,
+    )
+    .await;
+    let fields = types["data"]["__type"]["fields"].as_array().unwrap();
+    let type_of = |name: &str| {
+        fields.iter().find(|f| f["name"] == name).unwrap()["type"]["ofType"].clone()
+    };
+    assert_eq!(type_of("starts_at"), json!({ "name": "DateTime", "kind": "SCALAR" }));
+    assert_eq!(type_of("meta"), json!({ "name": "JSON", "kind": "SCALAR" }));
+    assert_eq!(type_of("status"), json!({ "name": "Status", "kind": "ENUM" }));
+
+    let events = query(&app, "{ events { title status starts_at } }").await;
+    assert_eq!(
+        events["data"]["events"],
+        json!([{ "title": "Launch", "status": "published", "starts_at": "2024-03-01T09:00:00Z" }])
+    );
+}
+
+#[tokio::test]
+async fn datetime_arguments_are_validated() {
+    let app = build_router().await;
+    let ok = query(&app, r#"{ echoTime(at: "2024-03-01T09:00:00Z") }"#).await;
+    assert_eq!(ok["data"]["echoTime"], "2024-03-01T09:00:00Z");
+
+    let bad = query(&app, r#"{ echoTime(at: "next tuesday") }"#).await;
+    assert!(bad["errors"].as_array().is_some_and(|e| !e.is_empty()), "{}", bad);
+
+    let object = query(&app, r#"{ echoJson(value: {tags: ["a", "b"], n: 2}) }"#).await;
+    assert_eq!(object["data"]["echoJson"], json!({ "tags": ["a", "b"], "n": 2 }));
+}
@@ -0,0 +1,127 @@
+use futures::StreamExt;
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tonic::transport::Channel;
+use tonic::Code;
+use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1::ServerReflectionRequest;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::apps::grpc::{descriptor_pool, DynamicCodec};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = Grpc
+package = library
+
+@Memory/books
++ id = 1
+  title = "Dune"
+  available = true
++ id = 2
+  title = "Emma"
+  available = false
+
+@Schema/Book
+id = number
+title = string
+available = bool
+
+@Schema/BookId
+id = number
+
+@Rpc/Library/GetBook
+input = BookId
+output = Book
+run:
+    books = memory.get "books"
+    book = books.find it.id == body.id
+    if book == null:
+        respond 404 "no book with that id"
+    respond 200 book
+
+@Rpc/Library/ListBooks
+output = [Book]
+run:
+    books = memory.get "books"
+    respond 200 books
+"#;
+
+async fn start() -> (Channel, DescriptorPool) {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("grpc_app.rune"),
+    };
+    let pool = descriptor_pool(&state.doc, &state.schemas).unwrap();
+    let app = build_app_router(state).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+    (channel, pool)
+}
+
+fn method(pool: &DescriptorPool, name: &str) -> MethodDescriptor {
+    let service = pool.get_service_by_name("library.Library").unwrap();
+    let found = service.methods().find(|m| m.name() == name).unwrap();
+    found
+}
+
+async fn call(channel: &Channel, method: &MethodDescriptor, request: Value) -> Result<Value, tonic::Status> {
+    let message = DynamicMessage::deserialize(method.input(), request).unwrap();
+    let mut client = tonic::client::Grpc::new(channel.clone());
+    client.ready().await.unwrap();
+    let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
+    let response = client
+        .unary(tonic::Request::new(message), path.parse().unwrap(), DynamicCodec(method.output()))
+        .await?;
+    Ok(serde_json::to_value(response.into_inner()).unwrap())
+}
+
+#[tokio::test]
+async fn unary_calls_run_their_steps() {
+    let (channel, pool) = start().await;
+    let book = call(&channel, &method(&pool, "GetBook"), json!({ "id": 1 })).await.unwrap();
+    assert_eq!(book, json!({ "id": 1.0, "title": "Dune", "available": true }));
+
+    let list = call(&channel, &method(&pool, "ListBooks"), json!({})).await.unwrap();
+    let titles: Vec<&str> = list["items"].as_array().unwrap().iter().map(|b| b["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Dune", "Emma"]);
+
+    let missing = call(&channel, &method(&pool, "GetBook"), json!({ "id": 9 })).await.unwrap_err();
+    assert_eq!(missing.code(), Code::NotFound);
+    assert_eq!(missing.message(), "no book with that id");
+}
+
+#[tokio::test]
+async fn reflection_lists_the_services() {
+    let (channel, _) = start().await;
+    let mut client = ServerReflectionClient::new(channel);
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = client
+        .server_reflection_info(futures::stream::iter(vec![request]))
+        .await
+        .unwrap()
+        .into_inner();
+    let response = responses.next().await.unwrap().unwrap();
+    let Some(MessageResponse::ListServicesResponse(list)) = response.message_response else {
+        panic!("expected a list of services");
+    };
+    let names: Vec<String> = list.service.into_iter().map(|s| s.name).collect();
+    assert!(names.contains(&"library.Library".to_string()), "{:?}", names);
+}
@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use tower::ServiceExt; // for `oneshot`
 
 use rune_runtime::apps::build_app_router;
-use rune_runtime::core::{extract_data_sources, extract_schemas, AppState};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
 use rune_runtime::rune_parser::parse_rune;
 use std::sync::Arc;
 
@@ -15,6 +15,7 @@ async fn build_router_from_str(contents: &str) -> Router {
         doc: Arc::new(doc),
         schemas: Arc::new(extract_schemas(&parse_rune("").unwrap())),
         data_sources: Arc::new(extract_data_sources(&parse_rune("").unwrap())),
+        steps: Arc::new(extract_steps(&parse_rune("").unwrap())),
         path,
     };
     build_app_router(state).await
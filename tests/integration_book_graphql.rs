@@ -6,7 +6,7 @@ use std::sync::Arc;
 use tower::ServiceExt;
 
 use rune_runtime::apps::build_app_router;
-use rune_runtime::core::{extract_data_sources, extract_schemas, AppState};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
 use rune_runtime::rune_parser::parse_rune;
 use rune_runtime::util::{set_log_level, LogLevel};
 
@@ -18,6 +18,7 @@ async fn build_router_from_file(path: &str) -> Router {
         doc: Arc::new(doc.clone()),
         schemas: Arc::new(extract_schemas(&doc)),
         data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
         path: path_buf,
     };
     build_app_router(state).await
@@ -32,19 +32,108 @@ fn calculate_basic_functions() {
 #[test]
 fn transform_baseline_names_list() {
     let doc = load_example("examples/skateboarders.rune");
-    let out = transform::transform_to_string(&doc, "@Skaters name:[@Skateboarder.name]").unwrap();
-    let expected = "#!RUNE\n@Skaters\nname:\n  Tony Hawk\n  Nyjah Huston\n  Leticia Bufoni\n";
-    assert_eq!(out.trim_end(), expected.trim_end());
+    let out = transform::handle_transform(&doc, "@Skaters name:[@Skateboarder.name]").unwrap();
+    let expected = "#!RUNE\n@Skaters\nname:\n    \"Tony Hawk\"\n    \"Nyjah Huston\"\n    \"Leticia Bufoni\"\n";
+    assert_eq!(out.to_string().trim_end(), expected.trim_end());
+}
+
+#[test]
+fn transform_projections_templates_and_concatenation() {
+    let doc = load_example("examples/skateboarders.rune");
+    let out = transform::handle_transform(
+        &doc,
+        "@Skaters entry:[@Skateboarder.{name,age} as \"name (age)\"] full:[@Skateboarder.name + \": \" + @Skateboarder.style]",
+    )
+    .unwrap()
+    .to_string();
+    assert!(out.starts_with("#!RUNE\n@Skaters\n"), "{}", out);
+    assert!(out.contains("entry:\n    \"Tony Hawk (53)\"\n    \"Nyjah Huston (26)\"\n    \"Leticia Bufoni (28)\"\n"), "{}", out);
+    assert!(out.contains("full:\n    \"Tony Hawk: Vert\"\n    \"Nyjah Huston: Street\"\n    \"Leticia Bufoni: Street\"\n"), "{}", out);
+
+    let out = transform::handle_transform(&doc, "@Skaters people:[@Skateboarder.{name, age as years}|sort]").unwrap();
+    let json = out.to_json();
+    assert_eq!(json["Skaters"]["people"][0]["name"], "Leticia Bufoni");
+    assert_eq!(json["Skaters"]["people"][0]["years"], 28.0);
+    assert!(json["Skaters"]["people"][0].get("age").is_none());
+}
+
+#[test]
+fn transform_joins_two_sections() {
+    let doc = rune_runtime::rune_parser::parse_rune(
+        "#!RUNE\n@Customer\n+ id = 1\n  name = Ada\n+ id = 2\n  name = Grace\n\n@Order\n+ total = 30\n  customer_id = 2\n+ total = 12\n  customer_id = 1\n+ total = 9\n  customer_id = 3\n",
+    )
+    .unwrap();
+    let out = transform::handle_transform(
+        &doc,
+        "@Report rows:[@Order join @Customer on Customer.id == Order.customer_id -> {Order.total, Customer.name as customer}]",
+    )
+    .unwrap();
+    let rows = out.to_json()["Report"]["rows"].clone();
+    assert_eq!(rows, serde_json::json!([{"total": 30.0, "customer": "Grace"}, {"total": 12.0, "customer": "Ada"}]));
+}
+
+#[test]
+fn transform_filters_records_with_conditions() {
+    let doc = load_example("examples/skateboarders.rune");
+    let names = |spec: &str| {
+        let out = transform::handle_transform(&doc, &format!("@Pick names:[{}]", spec)).unwrap();
+        out.to_json()["Pick"]["names"].clone()
+    };
+    assert_eq!(
+        names(r#"@Skateboarder.name|(age>27 && style=="Street")"#),
+        serde_json::json!(["Leticia Bufoni"])
+    );
+    assert_eq!(
+        names(r#"@Skateboarder.name|(age>=50 || name contains "nyjah")"#),
+        serde_json::json!(["Tony Hawk", "Nyjah Huston"])
+    );
+    assert_eq!(
+        names(r#"@Skateboarder.name!="Tony Hawk"|sort"#),
+        serde_json::json!(["Leticia Bufoni", "Nyjah Huston"])
+    );
+    assert_eq!(names("@Skateboarder.age<28"), serde_json::json!(["26"]));
+}
+
+#[test]
+fn transform_paging_and_aggregate_modifiers() {
+    let doc = load_example("examples/skateboarders.rune");
+    let out = transform::handle_transform(
+        &doc,
+        "@Stats oldest:[@Skateboarder.age|sort:desc|limit:2] rest:[@Skateboarder.name|skip:1|reverse] total:[@Skateboarder.age|sum] mean:[@Skateboarder.age|avg]",
+    )
+    .unwrap();
+    let stats = out.to_json()["Stats"].clone();
+    assert_eq!(stats["oldest"], serde_json::json!(["53", "28"]));
+    assert_eq!(stats["rest"], serde_json::json!(["Leticia Bufoni", "Nyjah Huston"]));
+    assert_eq!(stats["total"], serde_json::json!([107.0]));
+    assert_eq!(stats["mean"].as_array().unwrap()[0].as_f64().unwrap().round(), 36.0);
+
+    let err = transform::handle_transform(&doc, "@Stats total:[@Skateboarder.name|sum]").unwrap_err();
+    assert!(err.contains("numeric"), "{}", err);
+}
+
+#[test]
+fn transform_accepts_path_expressions() {
+    let doc = load_example("examples/skateboarders.rune");
+    let out = transform::handle_transform(
+        &doc,
+        "@Pick street:[$.Skateboarder[?style=='Street'].name|sort] first:[$.Skateboarder[0].name]",
+    )
+    .unwrap();
+    let pick = out.to_json()["Pick"].clone();
+    assert_eq!(pick["street"], serde_json::json!(["Leticia Bufoni", "Nyjah Huston"]));
+    assert_eq!(pick["first"], serde_json::json!(["Tony Hawk"]));
 }
 
 #[test]
 fn transform_unique_and_sort_modifiers() {
     let doc = load_example("examples/skateboarders.rune");
-    let out = transform::transform_to_string(
+    let out = transform::handle_transform(
         &doc,
         "@Skaters names:[@Skateboarder.name|unique|sort] ages:[@Skateboarder.age|sort:desc]",
     )
-    .unwrap();
-    let expected = "#!RUNE\n@Skaters\nnames:\n  Leticia Bufoni\n  Nyjah Huston\n  Tony Hawk\nages:\n  53\n  28\n  26\n";
-    assert_eq!(out.trim_end(), expected.trim_end());
+    .unwrap()
+    .to_string();
+    assert!(out.contains("names:\n    \"Leticia Bufoni\"\n    \"Nyjah Huston\"\n    \"Tony Hawk\"\n"), "{}", out);
+    assert!(out.contains("ages:\n    53\n    28\n    26\n"), "{}", out);
 }
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use tower::ServiceExt;
 
 use rune_runtime::apps::build_app_router;
-use rune_runtime::core::{extract_data_sources, extract_schemas, AppState};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
 use rune_runtime::rune_parser::parse_rune;
 use std::sync::Arc;
 
@@ -27,6 +27,7 @@ async fn build_router_from_str(contents: &str) -> Router {
         doc: Arc::new(doc.clone()),
         schemas: Arc::new(extract_schemas(&doc)),
         data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
         path,
     };
     build_app_router(state).await
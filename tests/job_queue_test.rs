@@ -0,0 +1,129 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Worker/welcome
+concurrency = 2
+run:
+    file.append "out/welcomed.jsonl" job
+
+@Worker/flaky
+retries = 2
+retry_delay = 5ms
+run:
+    if attempt < 3:
+        respond 503 "not yet"
+    file.append "out/flaky.jsonl" job_id
+
+@Worker/doomed
+retries = 1
+retry_delay = 5ms
+run:
+    respond 500 "always down"
+on_error:
+    file.append "out/errors.jsonl" error.message
+
+@Route/POST /users
+run:
+    parse-json
+    enqueue welcome body
+    respond 202 "queued"
+
+@Route/POST /flaky
+run:
+    id = enqueue flaky
+    respond 202 id
+
+@Route/POST /doomed
+run:
+    enqueue doomed
+    respond 202 "queued"
+
+@Route/POST /missing
+run:
+    enqueue nobody
+    respond 202 "queued"
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: &Router, uri: &str, body: JsonValue) -> (StatusCode, String) {
+    let req = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&bytes).into())
+}
+
+/// The lines of `file` once it has `count` of them.
+async fn lines(dir: &Path, file: &str, count: usize) -> Vec<String> {
+    let path = dir.join("out").join(file);
+    for _ in 0..200 {
+        let lines: Vec<String> = std::fs::read_to_string(&path)
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        if lines.len() >= count {
+            return lines;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} never reached {} lines", file, count);
+}
+
+#[tokio::test]
+async fn enqueued_jobs_run_in_the_background() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    for name in ["ada", "lin", "bob"] {
+        let (status, _) = post(&app, "/users", json!({"name": name})).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    let mut welcomed: Vec<JsonValue> =
+        lines(dir.path(), "welcomed.jsonl", 3).await.iter().map(|l| serde_json::from_str(l).unwrap()).collect();
+    welcomed.sort_by_key(|w| w["name"].as_str().unwrap().to_string());
+    assert_eq!(welcomed, [json!({"name": "ada"}), json!({"name": "bob"}), json!({"name": "lin"})]);
+}
+
+#[tokio::test]
+async fn failed_jobs_are_retried_then_handed_to_on_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, id) = post(&app, "/flaky", json!({})).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(lines(dir.path(), "flaky.jsonl", 1).await, [id]);
+
+    post(&app, "/doomed", json!({})).await;
+    let errors = lines(dir.path(), "errors.jsonl", 1).await;
+    assert!(errors[0].contains("always down"), "{:?}", errors);
+
+    let (status, _) = post(&app, "/missing", json!({})).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
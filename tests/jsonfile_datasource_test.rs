@@ -0,0 +1,100 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/BooksFile
+type = jsonfile
+connection = data/books.json
+
+@Schema/Book
+title = string
+pages = number
+
+@Route/CRUD /books
+data_source = BooksFile
+schema = Book
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn crud_routes_store_records_in_a_json_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, books) = send(&app, "GET", "/books", None).await;
+    assert_eq!((status, books), (StatusCode::OK, json!([])));
+
+    let (status, created) = send(&app, "POST", "/books", Some(json!({"title": "Dune", "pages": 412}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["id"], 1);
+    send(&app, "POST", "/books", Some(json!({"title": "Emma", "pages": 474}))).await;
+
+    let (status, updated) = send(&app, "PUT", "/books/2", Some(json!({"pages": 480}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated, json!({"id": 2, "title": "Emma", "pages": 480}));
+
+    let (status, book) = send(&app, "GET", "/books/2", None).await;
+    assert_eq!((status, book["pages"].clone()), (StatusCode::OK, json!(480)));
+
+    let (status, _) = send(&app, "DELETE", "/books/1", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, "GET", "/books/1", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let on_disk: JsonValue =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("data/books.json")).unwrap()).unwrap();
+    assert_eq!(on_disk, json!([{"id": 2, "title": "Emma", "pages": 480}]));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_inserts_are_not_lost() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+    let inserts: Vec<_> = (0..20)
+        .map(|i| {
+            let app = app.clone();
+            let book = json!({"title": format!("Book {}", i), "pages": i});
+            tokio::spawn(async move { send(&app, "POST", "/books", Some(book)).await })
+        })
+        .collect();
+    for insert in inserts {
+        assert_eq!(insert.await.unwrap().0, StatusCode::CREATED);
+    }
+    let (_, books) = send(&app, "GET", "/books", None).await;
+    assert_eq!(books.as_array().unwrap().len(), 20);
+}
@@ -11,6 +11,7 @@ fn app_state() -> AppState {
         doc: Arc::new(RuneDocument { sections: vec![] }),
         schemas: Arc::new(HashMap::new()),
         data_sources: Arc::new(HashMap::new()),
+        steps: Arc::default(),
         path: PathBuf::new(),
     }
 }
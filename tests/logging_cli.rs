@@ -0,0 +1,40 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn logs_stay_out_of_document_output() {
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-o", "json", "-l", "debug"])
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(json["Skateboarder"]["record"][0]["name"], "Tony Hawk");
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("Setting log level to Debug"), "{}", stderr);
+    assert!(stderr.contains("[DEBUG] Parsed Vectrune script:"), "{}", stderr);
+}
+
+#[test]
+fn log_file_collects_log_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("vectrune.log");
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-o", "yaml", "-l", "debug", "--log-file"])
+        .arg(&log)
+        .assert()
+        .success();
+    assert!(assert.get_output().stderr.is_empty());
+    assert!(std::fs::read_to_string(&log).unwrap().contains("[DEBUG] Parsed Vectrune script:"));
+}
+
+#[test]
+fn quiet_conflicts_with_an_explicit_level() {
+    vectrune_cmd().args(["examples/skateboarders.rune", "-o", "json", "-q"]).assert().success();
+    vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-q", "-l", "info"])
+        .assert()
+        .failure();
+}
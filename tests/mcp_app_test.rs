@@ -0,0 +1,186 @@
+use assert_cmd::Command;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = MCP
+name = library
+
+@Memory/books
++ id = 1
+  title = "Dune"
++ id = 2
+  title = "Emma"
+
+@Schema/BookQuery
+title = string
+
+@Tool/find_books
+description = "Find the books with a title"
+input = BookQuery
+run:
+    books = memory.get "books"
+    matching = books.filter it.title == body.title
+    respond 200 matching
+
+@Tool/get_book
+run:
+    respond 404 "no such book"
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("mcp_app.rune"),
+    };
+    build_app_router(state).await
+}
+
+async fn rpc(app: &Router, message: Value) -> Value {
+    let req = Request::builder()
+        .method("POST")
+        .uri("/mcp")
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(message.to_string()))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn tools_are_listed_and_called() {
+    let app = build_router().await;
+    let list = rpc(&app, json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" })).await;
+    let tools = list["result"]["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 2);
+    assert_eq!(tools[0]["name"], "find_books");
+    assert_eq!(tools[0]["description"], "Find the books with a title");
+    assert_eq!(tools[0]["inputSchema"]["properties"]["title"], json!({ "type": "string" }));
+
+    let call = rpc(
+        &app,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": { "name": "find_books", "arguments": { "title": "Emma" } } }),
+    )
+    .await;
+    assert_eq!(call["result"]["isError"], false);
+    let text = call["result"]["content"][0]["text"].as_str().unwrap();
+    let books: Value = serde_json::from_str(text).unwrap();
+    assert_eq!(books.as_array().unwrap().len(), 1);
+    assert_eq!(books[0]["title"], "Emma");
+
+    let failed = rpc(
+        &app,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "get_book" } }),
+    )
+    .await;
+    assert_eq!(failed["result"]["isError"], true);
+    assert_eq!(failed["result"]["content"][0]["text"], "no such book");
+
+    let unknown = rpc(
+        &app,
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": { "name": "nope" } }),
+    )
+    .await;
+    assert_eq!(unknown["error"]["code"], -32602);
+}
+
+/// Reads server-sent events until one named `name` arrives and returns its data.
+async fn next_event(
+    stream: &mut (impl futures::Stream<Item = reqwest::Result<axum::body::Bytes>> + Unpin),
+    buffer: &mut String,
+    name: &str,
+) -> String {
+    loop {
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            let field = |key: &str| {
+                event
+                    .lines()
+                    .find_map(|line| line.strip_prefix(key).map(|v| v.trim().to_string()))
+            };
+            if field("event:").as_deref() == Some(name) {
+                return field("data:").unwrap_or_default();
+            }
+        }
+        let chunk = stream.next().await.expect("stream ended").unwrap();
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+    }
+}
+
+#[tokio::test]
+async fn replies_arrive_over_sse() {
+    let app = build_router().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let mut stream = client.get(format!("{}/sse", base)).send().await.unwrap().bytes_stream();
+    let mut buffer = String::new();
+    let endpoint = next_event(&mut stream, &mut buffer, "endpoint").await;
+    assert!(endpoint.starts_with("/messages?session_id="), "{}", endpoint);
+
+    let posted = client
+        .post(format!("{}{}", base, endpoint))
+        .json(&json!({ "jsonrpc": "2.0", "id": 7, "method": "initialize", "params": { "protocolVersion": "2024-11-05" } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(posted.status(), 202);
+    let reply: Value = serde_json::from_str(&next_event(&mut stream, &mut buffer, "message").await).unwrap();
+    assert_eq!(reply["id"], 7);
+    assert_eq!(reply["result"]["serverInfo"]["name"], "library");
+
+    let stale = client
+        .post(format!("{}/messages?session_id=unknown", base))
+        .json(&json!({ "jsonrpc": "2.0", "id": 8, "method": "ping" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), 404);
+}
+
+#[test]
+fn stdio_transport_keeps_stdout_for_replies() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("library.rune");
+    std::fs::write(&script, SCRIPT.replace("name = library\n", "name = library\ntransport = stdio\n")).unwrap();
+    let input = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2025-06-18" } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": { "name": "find_books", "arguments": { "title": "Dune" } } }),
+    ]
+    .iter()
+    .map(|m| format!("{}\n", m))
+    .collect::<String>();
+
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .arg(&script)
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    let replies: Vec<Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(replies.len(), 2, "{}", stdout);
+    assert_eq!(replies[0]["result"]["protocolVersion"], "2025-06-18");
+    let books: Value = serde_json::from_str(replies[1]["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(books[0]["title"], "Dune");
+}
@@ -0,0 +1,90 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::builtins::builtin::memory::get_memory_value;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/Demo
+type = memory
+connection = demo_products
+
+@Memory/demo_products
++ id = 1
+  name = "Lamp"
+  price = 25
++ id = 2
+  name = "Desk"
+  price = 180
+
+@Schema/Product
+name = string
+price = number
+
+@Route/CRUD /products
+data_source = Demo
+schema = Product
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn crud_routes_serve_records_seeded_from_memory() {
+    let app = build_router().await;
+
+    let (status, products) = send(&app, "GET", "/products", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(products.as_array().unwrap().len(), 2);
+
+    let (status, desk) = send(&app, "GET", "/products/2", None).await;
+    assert_eq!((status, desk), (StatusCode::OK, json!({"id": 2, "name": "Desk", "price": 180})));
+
+    let (status, created) = send(&app, "POST", "/products", Some(json!({"name": "Chair", "price": 90}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["id"], 3);
+
+    let (status, updated) = send(&app, "PUT", "/products/1", Some(json!({"price": 30}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["price"], 30);
+
+    let (status, _) = send(&app, "DELETE", "/products/2", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, "GET", "/products/2", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let stored = get_memory_value("demo_products").await.unwrap();
+    let names: Vec<&str> = stored.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Lamp", "Chair"]);
+}
@@ -0,0 +1,44 @@
+use assert_cmd::Command;
+
+const BASE: &str = "#!RUNE\n@App\nname = Shop\nport = 8080\n\n@User\n+ name = Ada\n  age = 36\n+ name = Bob\n  age = 40\n\n@Gone\nx = 1\n";
+
+fn merge3(mine: &str, theirs: &str, expect_conflicts: bool) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |name: &str, content: &str| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let (base, mine, theirs) = (path("base.rune", BASE), path("mine.rune", mine), path("theirs.rune", theirs));
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"));
+    cmd.args(["merge3", &base, &mine, &theirs]);
+    let assert = if expect_conflicts { cmd.assert().failure() } else { cmd.assert().success() };
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn merge3_combines_independent_edits() {
+    let mine = "#!RUNE\n@App\nname = Shop\nport = 9090\n\n@User\n+ name = Ada\n  age = 37\n+ name = Bob\n  age = 40\n\n@Gone\nx = 1\n";
+    let theirs = "#!RUNE\n@App\nname = Shop2\nport = 8080\n\n@User\n+ name = Ada\n  age = 36\n  role = admin\n+ name = Bob\n  age = 40\n\n@New\ny = 2\n";
+    let out = merge3(mine, theirs, false);
+    let doc = rune_runtime::rune_parser::parse_rune(&out).unwrap();
+    let json = doc.to_json();
+    assert_eq!(json["App"], serde_json::json!({"name": "Shop2", "port": 9090.0}));
+    assert_eq!(json["User"]["record"][0], serde_json::json!({"name": "Ada", "age": 37.0, "role": "admin"}));
+    assert_eq!(json["New"], serde_json::json!({"y": 2.0}));
+    assert!(json.get("Gone").is_none(), "{}", out);
+}
+
+#[test]
+fn merge3_marks_conflicting_edits() {
+    let mine = BASE.replace("port = 8080", "port = 9090").replace("age = 40", "age = 41");
+    let theirs = BASE.replace("port = 8080", "port = 7070").replace("age = 40", "age = 42");
+    let out = merge3(&mine, &theirs, true);
+    assert!(out.contains("<<<<<<< mine\nport = 9090\n=======\nport = 7070\n>>>>>>> theirs\n"), "{}", out);
+    assert!(
+        out.contains("<<<<<<< mine\n+ age = 41\n  name = Bob\n=======\n+ age = 42\n  name = Bob\n>>>>>>> theirs\n"),
+        "{}",
+        out
+    );
+    assert!(out.contains("+ age = 36\n  name = Ada\n"), "{}", out);
+}
@@ -53,7 +53,7 @@ Ips:
             input.path_str(),
             "--merge-with",
             &format!(
-                "{}@environment.preview.[].(name=allowedIps on value from Ips)",
+                "{}@environment.preview[?name=='allowedIps'].value from Ips",
                 base.path_str()
             ),
             "-o",
@@ -68,7 +68,6 @@ Ips:
     assert!(stdout.contains("allowedIps"));
 }
 
-#[ignore]
 #[test]
 fn test_merge_cli_rune() {
     let base_rune = "
@@ -106,7 +105,6 @@ new_perms:
     assert!(stdout.contains("new_perms"));
 }
 
-#[ignore]
 #[test]
 fn test_merge_cli_json_to_yaml() {
     let base_yaml = "
@@ -136,3 +134,42 @@ api:
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("new_key: secret"));
 }
+
+#[test]
+fn test_merge_strategies_combine_shared_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_path = dir.path().join("base.json");
+    fs::write(
+        &base_path,
+        r#"{"Config": {"name": "base", "limits": {"cpu": 1, "mem": 2}, "tags": ["a", "b", "x"]}}"#,
+    )
+    .unwrap();
+    let input = rune_runtime::rune_ast::RuneDocument::from_json(&serde_json::json!({
+        "Config": {"limits": {"cpu": 4}, "tags": ["b", "c"], "extra": true}
+    }));
+    let merge = |selector: &str, default| {
+        let spec = format!("{}@{}", base_path.display(), selector);
+        let merged = rune_runtime::cli::merge::handle_merge_with(&input, &spec, default).unwrap();
+        merged.to_json()["Config"].clone()
+    };
+    use rune_runtime::cli::merge::MergeStrategy;
+
+    let deep = merge("$", MergeStrategy::Deep);
+    assert_eq!(deep["limits"], serde_json::json!({"cpu": 4.0, "mem": 2.0}));
+    assert_eq!(deep["tags"], serde_json::json!(["b", "c", "x"]));
+    assert_eq!(deep["name"], "base");
+    assert_eq!(deep["extra"], true);
+
+    let replace = merge("$!replace", MergeStrategy::Deep);
+    assert_eq!(replace["tags"], serde_json::json!(["b", "c"]));
+    assert_eq!(replace["limits"], serde_json::json!({"cpu": 4.0, "mem": 2.0}));
+
+    let append = merge("", MergeStrategy::Append);
+    assert_eq!(append["tags"], serde_json::json!(["a", "b", "x", "b", "c"]));
+
+    let union = merge("!union", MergeStrategy::Replace);
+    assert_eq!(union["tags"], serde_json::json!(["a", "b", "x", "c"]));
+
+    let spec = format!("{}@$!sideways", base_path.display());
+    assert!(rune_runtime::cli::merge::handle_merge_with(&input, &spec, MergeStrategy::Deep).is_err());
+}
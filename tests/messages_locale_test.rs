@@ -0,0 +1,98 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+locale = en
+
+@Schema/User
+name = string
+age = number
+
+@Messages/en
+invalid_email = "Please enter a valid email address"
+
+@Messages/fr
+missing_field = "Le champ {field} est obligatoire"
+type_mismatch = "Le champ {field} a un type invalide"
+invalid_email = "Adresse e-mail invalide : {body.email}"
+
+@Route/POST /users
+run:
+    parse-json
+    validate body #User
+    respond 200 "ok"
+
+@Route/POST /subscribe
+run:
+    parse-json
+    validate body.email ~= "^[^@]+@[^@]+$" invalid_email
+    respond 200 "ok"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str, accept_language: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(lang) = accept_language {
+        builder = builder.header("accept-language", lang);
+    }
+    builder.body(Body::from(body.to_string())).unwrap()
+}
+
+#[tokio::test]
+async fn schema_errors_follow_accept_language() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app.clone(), post("/users", r#"{"age":3}"#, Some("fr-CA,fr;q=0.9"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Le champ name est obligatoire"), "body: {}", body);
+
+    let (_, body) = send(app.clone(), post("/users", r#"{"name":"ann","age":"x"}"#, Some("fr"))).await;
+    assert!(body.contains("Le champ age a un type invalide"), "body: {}", body);
+
+    // No `en` translation for missing_field: the built-in message is used
+    let (_, body) = send(app, post("/users", r#"{"age":3}"#, Some("de, en;q=0.5"))).await;
+    assert!(body.contains("Missing field `name`"), "body: {}", body);
+}
+
+#[tokio::test]
+async fn message_codes_fall_back_to_the_app_locale() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app.clone(), post("/subscribe", r#"{"email":"nope"}"#, None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Please enter a valid email address"), "body: {}", body);
+
+    let (_, body) = send(app, post("/subscribe", r#"{"email":"nope"}"#, Some("fr"))).await;
+    assert!(body.contains("Adresse e-mail invalide : nope"), "body: {}", body);
+}
@@ -0,0 +1,89 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Middleware/api_key
+run:
+    if request.headers.authorization != "Key secret":
+        respond 401 "missing API key"
+    caller = "partner"
+
+@Middleware/tag
+run:
+    tag = caller
+
+@Route/GET /reports
+middleware = (api_key tag)
+run:
+    report = {"caller": caller, "tag": tag}
+    respond 200 report
+
+@Route/GET /open
+run:
+    respond 200 "open"
+
+@Route/GET /broken
+middleware = (nowhere)
+run:
+    respond 200 "unprotected"
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::current_dir().unwrap(),
+    };
+    build_app_router(state).await
+}
+
+async fn get(app: &Router, uri: &str, key: Option<&str>) -> (StatusCode, String) {
+    let mut req = Request::builder().method("GET").uri(uri);
+    if let Some(key) = key {
+        req = req.header("authorization", key);
+    }
+    let resp = app.clone().oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn middleware_runs_before_the_route_and_can_respond() {
+    let app = build_router().await;
+
+    let (status, body) = get(&app, "/reports", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("missing API key"), "{}", body);
+
+    let (status, body) = get(&app, "/reports", Some("Key secret")).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["caller"], "partner");
+    assert_eq!(report["tag"], "partner");
+
+    let (status, _) = get(&app, "/open", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn routes_naming_missing_middleware_are_not_served() {
+    let app = build_router().await;
+
+    let (status, body) = get(&app, "/broken", None).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!body.contains("unprotected"), "{}", body);
+}
@@ -0,0 +1,23 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn migrate_validates_migrations_before_connecting() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+
+    std::fs::write(&app, "#!RUNE\n@App\ntype = REST\n").unwrap();
+    let assert = vectrune_cmd().arg("migrate").arg(&app).assert().success();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("No @Migration sections"));
+
+    std::fs::write(
+        &app,
+        "#!RUNE\n@DataSource/Files\ntype = jsonfile\nconnection = data.json\n\n@Migration/1_init\ndata_source = Files\nup:\n    CREATE TABLE t (id INT)\n",
+    )
+    .unwrap();
+    let assert = vectrune_cmd().arg("migrate").arg(&app).args(["up", "--dry-run"]).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("'Files' is not a postgres or mysql datasource"));
+}
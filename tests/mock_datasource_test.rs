@@ -0,0 +1,152 @@
+use assert_cmd::Command;
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/Db
+type = postgres
+connection = "postgres://nobody@127.0.0.1:1/none"
+mode = mock
+
+@Memory/Product
++ id = 1
+  name = "Lamp"
+  price = 25
+
+@Schema/Product
+name = string
+price = number
+
+@Route/CRUD /products
+data_source = Db
+schema = Product
+
+@Route/POST /restock
+run:
+    datasource begin Db
+    parse-json
+    datasource insert Product into Db
+    datasource commit Db
+    respond 201 "restocked"
+
+@Route/GET /report
+run:
+    rows = datasource query Db "SELECT count(*) FROM Product"
+    respond 200 rows
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let text = String::from_utf8_lossy(&bytes).to_string();
+    (status, serde_json::from_str(&text).unwrap_or(JsonValue::String(text)))
+}
+
+#[tokio::test]
+async fn mocked_sql_datasource_serves_crud_from_memory() {
+    let app = build_router().await;
+
+    let (status, lamp) = send(&app, "GET", "/products/1", None).await;
+    assert_eq!((status, lamp), (StatusCode::OK, json!({"id": 1, "name": "Lamp", "price": 25})));
+
+    let (status, created) = send(&app, "POST", "/products", Some(json!({"name": "Desk", "price": 180}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(created["id"], 2);
+
+    let (status, _) = send(&app, "POST", "/restock", Some(json!({"name": "Chair", "price": 90}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (_, products) = send(&app, "GET", "/products", None).await;
+    let names: Vec<&str> = products.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Lamp", "Desk", "Chair"]);
+
+    let (status, error) = send(&app, "GET", "/report", None).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(error.to_string().contains("mocked"), "{}", error);
+}
+
+#[test]
+fn mock_datasources_flag_mocks_every_sql_datasource() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, SCRIPT.replace("mode = mock\n", "")).unwrap();
+
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .arg(&app)
+        .args(["--mock-datasources", "--trace", "GET", "/products/1"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains(r#"Response: 200 {"id":1,"name":"Lamp","price":25}"#), "{}", stdout);
+}
+
+#[tokio::test]
+async fn seeds_fill_mocked_tables() {
+    let script = r#"#!RUNE
+@App
+type = REST
+
+@DataSource/Shop
+type = mysql
+connection = "mysql://nobody@127.0.0.1:1/none"
+mode = mock
+
+@Schema/Order
+item = string
+
+@Seed/Order
++ item = "Lamp"
++ item = "Desk"
+
+@Route/CRUD /orders
+data_source = Shop
+schema = Order
+"#;
+    let doc = parse_rune(script).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    let applied = rune_runtime::core::seeds::seed(&state).await.unwrap();
+    assert_eq!(applied[0].1, 2);
+    // A second start leaves the filled table alone
+    assert!(rune_runtime::core::seeds::seed(&state).await.unwrap().is_empty());
+
+    let app = build_app_router(state).await;
+    let (status, orders) = send(&app, "GET", "/orders", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(orders, json!([{"id": 1, "item": "Lamp"}, {"id": 2, "item": "Desk"}]));
+}
@@ -0,0 +1,109 @@
+use assert_cmd::Command;
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+async fn router_for(dir: &Path) -> Router {
+    let source = std::fs::read_to_string(dir.join("app.rune")).unwrap();
+    let doc = parse_rune(&source).expect("scaffolded document should parse");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn rest_crud_project_serves_its_entities() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("shop");
+    vectrune_cmd()
+        .arg("new")
+        .arg(&dir)
+        .args(["--template", "rest-crud", "--name", "shop", "--port", "3100", "--entities", "book,category"])
+        .assert()
+        .success();
+    let source = std::fs::read_to_string(dir.join("app.rune")).unwrap();
+    assert!(source.contains("name = shop\ntype = REST\nport = 3100\n"), "{}", source);
+    assert!(source.contains("@Route/CRUD /categories\n"), "{}", source);
+
+    let app = router_for(&dir).await;
+    let (status, _) = send(&app, "POST", "/books", Some(json!({ "name": "Dune" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, books) = send(&app, "GET", "/books", None).await;
+    assert_eq!((status, books), (StatusCode::OK, json!([{ "id": 1, "name": "Dune" }])));
+}
+
+#[tokio::test]
+async fn missing_answers_are_asked_for() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("library");
+    let assert = vectrune_cmd().arg("new").arg(&dir).write_stdin("graphql\n\n4000\nbook\n").assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("App name [library]: "), "{}", stdout);
+
+    let source = std::fs::read_to_string(dir.join("app.rune")).unwrap();
+    assert!(source.contains("name = library\ntype = GraphQL\nport = 4000\n"), "{}", source);
+    let app = router_for(&dir).await;
+    let (status, reply) = send(
+        &app,
+        "POST",
+        "/graphql",
+        Some(json!({ "query": "mutation { addBook(name: \"Emma\") { id } } " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply["data"]["addBook"]["id"], 2);
+}
+
+#[test]
+fn static_site_gateway_and_existing_projects() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("site");
+    vectrune_cmd()
+        .arg("new")
+        .arg(&dir)
+        .args(["-t", "static-site", "--name", "My Site", "--port", "8000"])
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(dir.join("public/index.html")).unwrap().contains("<h1>My Site</h1>"));
+
+    let gateway = root.path().join("edge");
+    vectrune_cmd()
+        .arg("new")
+        .arg(&gateway)
+        .args(["-t", "gateway", "--name", "edge", "--port", "8000", "--entities", "order"])
+        .assert()
+        .success();
+    let source = std::fs::read_to_string(gateway.join("app.rune")).unwrap();
+    assert!(source.contains("@Route/PROXY /orders/*\nupstream = http://127.0.0.1:8001\n"), "{}", source);
+
+    let assert = vectrune_cmd().arg("new").arg(&dir).args(["-t", "gateway"]).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("already exists"));
+}
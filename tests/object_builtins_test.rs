@@ -0,0 +1,90 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/POST /users
+run:
+    parse-json
+    public = obj.omit body (password token)
+    contact = obj.pick body (name email)
+    defaults = { role: "member", active: true }
+    user = obj.merge defaults public { source: "api" }
+    fields = obj.keys contact
+    obj.set user.profile.name body.name
+    obj.set user.profile.note "body.name"
+    out = { user: user, contact: contact, fields: fields }
+    respond 200 out
+
+@Route/POST /not-an-object
+run:
+    parse-json
+    keys = obj.keys body.name
+    respond 200 keys
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn object_builtins_shape_a_response() {
+    let app = build_router_from_str(SCRIPT).await;
+    let body = r#"{"name":"ann","email":"a@x.io","password":"hunter2","token":"t","role":"admin"}"#;
+    let (status, body) = send(app, post("/users", body)).await;
+    assert_eq!(status, StatusCode::OK, "body: {}", body);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        out["user"],
+        json!({
+            "name": "ann", "email": "a@x.io", "role": "admin", "active": true, "source": "api",
+            "profile": {"name": "ann", "note": "body.name"}
+        })
+    );
+    assert_eq!(out["contact"], json!({"name": "ann", "email": "a@x.io"}));
+    assert_eq!(out["fields"], json!(["email", "name"]));
+}
+
+#[tokio::test]
+async fn object_builtin_on_a_non_object_is_an_error() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, _) = send(app, post("/not-an-object", r#"{"name":"ann"}"#)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,37 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn out_writes_the_document_and_keeps_logs_on_the_terminal() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("skaters.json");
+    let assert = vectrune_cmd()
+        .args(["transform", "@Names name:[@Skateboarder.name]", "examples/skateboarders.rune", "-l", "error", "--out"])
+        .arg(&out)
+        .assert()
+        .success();
+    assert!(assert.get_output().stdout.is_empty());
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(json["Names"]["name"][2], "Leticia Bufoni");
+}
+
+#[test]
+fn in_place_rewrites_the_input_in_its_own_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("skaters.yaml");
+    std::fs::write(&file, "Skateboarder:\n  record:\n  - name: Tony Hawk\n    style: Vert\n").unwrap();
+    vectrune_cmd()
+        .args(["-i", "yaml", "--transform", "@Names name:[@Skateboarder.name]", "--in-place"])
+        .arg(&file)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "Names:\n  name:\n  - Tony Hawk\n\n");
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+    assert_eq!(leftovers.len(), 1);
+
+    let assert = vectrune_cmd().args(["-", "--in-place"]).write_stdin("@App\n").assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("--in-place needs exactly one input file"));
+}
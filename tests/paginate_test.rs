@@ -0,0 +1,102 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::builtins::builtin::data_source::get_data_source_commands;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_ast::Value;
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/CatsDataSource
+type = postgres
+connection = postgres://nobody@127.0.0.1:1/none
+
+@Schema/Cat
+name = string
+
+@Route/CRUD /cats
+data_source = CatsDataSource
+schema = Cat
+page_size = 25
+
+@Const
+numbers = (1 2 3 4 5 6 7)
+
+@Route/GET /numbers
+run:
+    result = paginate numbers request.query.page request.query.size 3
+    respond 200 result
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn get(app: Router, uri: &str) -> (StatusCode, String) {
+    let resp = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn paginates_with_query_parameters() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = get(app.clone(), "/numbers").await;
+    assert_eq!(status, StatusCode::OK, "body: {}", body);
+    let page: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(page, json!({"data": [1, 2, 3], "page": 1, "size": 3, "total": 7, "next": 2}));
+
+    let (_, body) = get(app.clone(), "/numbers?page=2&size=5").await;
+    let page: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(page, json!({"data": [6, 7], "page": 2, "size": 5, "total": 7, "next": null}));
+
+    let (_, body) = get(app.clone(), "/numbers?page=9").await;
+    let page: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(page["data"], json!([]));
+
+    let (status, _) = get(app, "/numbers?page=0").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn crud_list_with_page_size_is_paginated() {
+    let doc = parse_rune(SCRIPT).unwrap();
+    let section = doc
+        .sections
+        .iter()
+        .find(|s| s.path.get(1).map(String::as_str) == Some("CRUD"))
+        .unwrap()
+        .clone();
+    let schemas = Arc::new(extract_schemas(&doc));
+    let sources: Arc<HashMap<_, _>> = Arc::new(extract_data_sources(&doc));
+    let steps = get_data_source_commands("GET", section.clone(), &schemas, &sources, false);
+    assert!(steps.contains(&Value::String(
+        "data_page = paginate data request.query.page request.query.size 25".to_string()
+    )));
+    let item_steps = get_data_source_commands("GET", section, &schemas, &sources, true);
+    assert!(item_steps.contains(&Value::String("respond 200 data".to_string())));
+}
@@ -0,0 +1,48 @@
+use rune_runtime::rune_ast::Value;
+use rune_runtime::rune_parser::parse_rune;
+use serde_json::json;
+
+fn first_section(input: &str) -> rune_runtime::rune_ast::Section {
+    parse_rune(input)
+        .expect("parse_rune should succeed")
+        .sections
+        .into_iter()
+        .next()
+        .expect("expected one section")
+}
+
+#[test]
+fn inline_list_items_are_typed() {
+    let section = first_section(
+        r#"@Config
+values = (1 -2.5 true false "two words" plain 1.1.1.1)
+"#,
+    );
+
+    let value = serde_json::to_value(section.kv.get("values").unwrap()).unwrap();
+    assert_eq!(
+        value,
+        json!([1.0, -2.5, true, false, "two words", "plain", "1.1.1.1"])
+    );
+}
+
+#[test]
+fn dash_series_items_are_typed_but_steps_stay_strings() {
+    let section = first_section(
+        r#"@Config
+ports:
+  - 8080
+  - "read"
+  - true
+run:
+    respond 200 "ok"
+"#,
+    );
+
+    let ports = serde_json::to_value(section.series.get("ports").unwrap()).unwrap();
+    assert_eq!(ports, json!([8080.0, "read", true]));
+    match &section.series.get("run").unwrap()[0] {
+        Value::String(step) => assert_eq!(step, "respond 200 \"ok\""),
+        other => panic!("expected step string, got {other:?}"),
+    }
+}
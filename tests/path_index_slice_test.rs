@@ -0,0 +1,100 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::json;
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::builtins::Context;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, resolve_path, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+fn items_ctx() -> Context {
+    let mut ctx = Context::new();
+    ctx.insert(
+        "items".to_string(),
+        json!([{"name": "a"}, {"name": "b"}, {"name": "c"}, {"name": "d"}]),
+    );
+    ctx.insert("i".to_string(), json!(1));
+    ctx
+}
+
+#[test]
+fn resolves_list_indexes_and_slices() {
+    let ctx = items_ctx();
+    let get = |path: &str| resolve_path(&ctx, path, None);
+    assert_eq!(get("items[2].name"), Some(json!("c")));
+    assert_eq!(get("items[-1].name"), Some(json!("d")));
+    assert_eq!(get("items.0.name"), Some(json!("a")));
+    assert_eq!(get("items[i].name"), Some(json!("b")));
+    assert_eq!(get("items[0:2]"), Some(json!([{"name": "a"}, {"name": "b"}])));
+    assert_eq!(get("items[3:]"), Some(json!([{"name": "d"}])));
+    assert_eq!(get("items[:-3]"), Some(json!([{"name": "a"}])));
+    assert_eq!(get("items[i:i + 1][0].name"), Some(json!("b")));
+    assert_eq!(get("items[2:99]").unwrap().as_array().unwrap().len(), 2);
+    assert_eq!(get("items[3:1]"), Some(json!([])));
+    assert_eq!(get("items[9]"), None);
+}
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/POST /first
+run:
+    parse-json
+    respond 200 body.users[0].name
+
+@Route/POST /last-two
+run:
+    parse-json
+    respond 200 body.users[-2:]
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: Router, uri: &str, body: &str) -> (StatusCode, String) {
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn respond_accepts_index_and_slice_paths() {
+    let app = build_router_from_str(SCRIPT).await;
+    let users = r#"{"users": [{"name": "ann"}, {"name": "bo"}, {"name": "cy"}]}"#;
+
+    let (status, body) = post(app.clone(), "/first", users).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "ann");
+
+    let (status, body) = post(app, "/last-two", users).await;
+    assert_eq!(status, StatusCode::OK);
+    let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(parsed, json!([{"name": "bo"}, {"name": "cy"}]));
+}
@@ -0,0 +1,110 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::{Path, PathBuf};
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /names
+run:
+    names = csv.read "users.csv" |> filter it.active == true |> map it.name |> sort
+    respond 200 names
+
+@Route/POST /top
+run:
+    parse-json
+    top = body.scores |> filter it.points > 10 |> sort it.points desc |> take 2 |> map it.player
+    count = body.scores |> filter it.points |> count
+    out = { top: top, count: count }
+    respond 200 out
+
+@Route/POST /year
+run:
+    parse-json
+    year = body.when |> date.parse _ "%d/%m/%Y" |> date.format _ "%Y"
+    respond 200 year
+
+@Route/POST /not-a-list
+run:
+    parse-json
+    x = body.name |> sort
+    respond 200 x
+"#;
+
+async fn build_router(contents: &str, dir: &Path) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from(dir),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pipes_csv_rows_through_filter_map_and_sort() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("users.csv"),
+        "name,active\ncy,true\nann,true\nbo,false\n",
+    )
+    .unwrap();
+    let app = build_router(SCRIPT, dir.path()).await;
+    let req = Request::builder().uri("/names").body(Body::empty()).unwrap();
+    let (status, body) = send(app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    let names: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(names, json!(["ann", "cy"]));
+}
+
+#[tokio::test]
+async fn pipes_context_values_through_list_stages() {
+    let app = build_router(SCRIPT, Path::new(".")).await;
+    let scores = r#"{"scores": [
+        {"player": "ann", "points": 12},
+        {"player": "bo", "points": 0},
+        {"player": "cy", "points": 30},
+        {"player": "di", "points": 15}
+    ]}"#;
+    let (status, body) = send(app.clone(), post("/top", scores)).await;
+    assert_eq!(status, StatusCode::OK);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(out, json!({"top": ["cy", "di"], "count": 3}));
+
+    let (status, body) = send(app.clone(), post("/year", r#"{"when": "01/05/2024"}"#)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "2024");
+
+    let (status, body) = send(app, post("/not-a-list", r#"{"name": "ann"}"#)).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("expects a list"), "{}", body);
+}
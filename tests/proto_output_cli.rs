@@ -0,0 +1,46 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = Grpc
+package = library
+
+@Schema/Book
+id = number
+title = string
+meta = json
+
+@Rpc/Library/Ping
+run:
+    respond 200 "ok"
+
+@Route/CRUD /books
+schema = Book
+data_source = Shelf
+"#;
+
+#[test]
+fn proto_output_describes_schemas_rpcs_and_crud_routes() {
+    let assert = vectrune_cmd().args(["-", "-o", "proto"]).write_stdin(SCRIPT).assert().success();
+    let proto = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(
+        proto.starts_with(
+            "syntax = \"proto3\";\n\npackage library;\n\nimport \"google/protobuf/empty.proto\";\nimport \"google/protobuf/struct.proto\";\n"
+        ),
+        "{}",
+        proto
+    );
+    assert!(
+        proto.contains("message Book {\n  double id = 1;\n  google.protobuf.Value meta = 2;\n  string title = 3;\n}\n"),
+        "{}",
+        proto
+    );
+    assert!(proto.contains("service Library {\n  rpc Ping(google.protobuf.Empty) returns (google.protobuf.Empty);\n}\n"), "{}", proto);
+    assert!(proto.contains("service BookService {\n  rpc ListBooks(google.protobuf.Empty) returns (ListBooksResponse);\n"), "{}", proto);
+    assert!(proto.contains("  rpc UpdateBook(Book) returns (Book);\n"), "{}", proto);
+}
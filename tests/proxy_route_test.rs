@@ -0,0 +1,135 @@
+use axum::http::{Request, StatusCode};
+use axum::routing::any;
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+/// Starts an upstream that echoes the method, path, query, a header and the body.
+async fn start_echo_upstream() -> String {
+    let app = Router::new().fallback(any(|req: Request<Body>| async move {
+        let (parts, body) = req.into_parts();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let gateway = parts
+            .headers
+            .get("x-gateway")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        serde_json::json!({
+            "method": parts.method.as_str(),
+            "path": parts.uri.path(),
+            "query": parts.uri.query(),
+            "gateway": gateway,
+            "body": String::from_utf8_lossy(&bytes),
+        })
+        .to_string()
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+#[tokio::test]
+async fn proxy_forwards_method_path_query_and_body() {
+    let upstream = start_echo_upstream().await;
+    let script = format!(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/PROXY /api/*
+upstream = {upstream}
+headers {{
+    x-gateway = vectrune
+}}
+"#
+    );
+    let app = build_router_from_str(&script).await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/api/users?limit=2")
+        .body(Body::from("{\"name\":\"Ada\"}"))
+        .unwrap();
+    let (status, json) = send(app, req).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["method"], "POST");
+    assert_eq!(json["path"], "/api/users");
+    assert_eq!(json["query"], "limit=2");
+    assert_eq!(json["gateway"], "vectrune");
+    assert_eq!(json["body"], "{\"name\":\"Ada\"}");
+}
+
+#[tokio::test]
+async fn proxy_rewrites_matched_prefix() {
+    let upstream = start_echo_upstream().await;
+    let script = format!(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/PROXY /api/*
+upstream = {upstream}
+rewrite = /v2
+"#
+    );
+    let app = build_router_from_str(&script).await;
+
+    let req = Request::builder()
+        .uri("/api/users/7")
+        .body(Body::empty())
+        .unwrap();
+    let (_, json) = send(app, req).await;
+    assert_eq!(json["path"], "/v2/users/7");
+}
+
+#[tokio::test]
+async fn proxy_returns_bad_gateway_when_upstream_is_down() {
+    let script = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/PROXY /api/*
+upstream = http://127.0.0.1:1
+"#;
+    let app = build_router_from_str(script).await;
+    let req = Request::builder()
+        .uri("/api/anything")
+        .body(Body::empty())
+        .unwrap();
+    let (status, _) = send(app, req).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
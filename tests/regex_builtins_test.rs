@@ -0,0 +1,113 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Const
+slug_pattern = "^[a-z0-9]+(-[a-z0-9]+)*$"
+
+@Route/POST /signup
+run:
+    parse-json
+    validate body.email ~= "^[^@\s]+@[^@\s]+\.[a-z]{2,}$" "invalid email"
+    validate body.slug ~= slug_pattern "invalid slug"
+    respond 200 "ok"
+
+@Route/POST /inspect
+run:
+    parse-json
+    is_code = regex.match body.text "[A-Z]{3}-\d+"
+    parts = regex.capture body.text "(?P<prefix>[A-Z]{3})-(?P<num>\d+)"
+    numbers = regex.capture body.text "(\d+)"
+    clean = regex.replace body.text "\s+" " "
+    masked = regex.replace body.text "(?P<prefix>[A-Z]{3})-\d+" "${prefix}-***"
+    missing = regex.capture body.text "zzz"
+    out = { is_code: is_code, parts: parts, numbers: numbers, clean: clean, masked: masked, missing: missing }
+    respond 200 out
+
+@Route/GET /broken
+run:
+    ok = regex.match "abc" "(unclosed"
+    respond 200 ok
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, req: Request<Body>) -> (StatusCode, String) {
+    let resp = app.oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn validate_regex_operator_sanitizes_input() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, _) = send(app.clone(), post("/signup", r#"{"email":"ann@example.com","slug":"ann-smith"}"#)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(app.clone(), post("/signup", r#"{"email":"ann at example","slug":"ann"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("invalid email"), "body: {}", body);
+
+    let (status, body) = send(app.clone(), post("/signup", r#"{"email":"ann@example.com","slug":"Ann Smith"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("invalid slug"), "body: {}", body);
+
+    let (status, _) = send(app, post("/signup", r#"{"slug":"ann"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn match_capture_and_replace() {
+    let app = build_router_from_str(SCRIPT).await;
+    let (status, body) = send(app, post("/inspect", r#"{"text":"order  ABC-42   shipped"}"#)).await;
+    assert_eq!(status, StatusCode::OK, "body: {}", body);
+    let out: JsonValue = serde_json::from_str(&body).unwrap();
+    assert_eq!(out["is_code"], json!(true));
+    assert_eq!(out["parts"], json!({"prefix": "ABC", "num": "42"}));
+    assert_eq!(out["numbers"], json!(["42"]));
+    assert_eq!(out["clean"], json!("order ABC-42 shipped"));
+    assert_eq!(out["masked"], json!("order  ABC-***   shipped"));
+    assert_eq!(out["missing"], JsonValue::Null);
+}
+
+#[tokio::test]
+async fn invalid_pattern_is_a_step_error() {
+    let app = build_router_from_str(SCRIPT).await;
+    let req = Request::builder().uri("/broken").body(Body::empty()).unwrap();
+    let (status, _) = send(app, req).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
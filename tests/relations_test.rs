@@ -0,0 +1,111 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/Library
+type = jsonfile
+connection = data/library.json
+
+@DataSource/Shelves
+type = jsonfile
+connection = data/shelves.json
+
+@Schema/Author
+name = string
+
+@Schema/Book
+title = string
+author_id = ref(Author.id)
+
+@Route/CRUD /authors
+data_source = Library
+schema = Author
+
+@Route/CRUD /books
+data_source = Shelves
+schema = Book
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::String(String::from_utf8_lossy(&bytes).into())))
+}
+
+#[tokio::test]
+async fn include_nests_referenced_records() {
+    let dir = tempfile::tempdir().unwrap();
+    // Authors and books live in separate files, so each schema has its own store
+    std::fs::create_dir_all(dir.path().join("data")).unwrap();
+    std::fs::write(
+        dir.path().join("data/shelves.json"),
+        json!([
+            {"id": 1, "title": "Dune", "author_id": 1},
+            {"id": 2, "title": "Emma", "author_id": 2},
+            {"id": 3, "title": "Untitled", "author_id": 9}
+        ])
+        .to_string(),
+    )
+    .unwrap();
+    let app = build_router(dir.path()).await;
+    send(&app, "POST", "/authors", Some(json!({"name": "Herbert"}))).await;
+    send(&app, "POST", "/authors", Some(json!({"name": "Austen"}))).await;
+
+    let (status, books) = send(&app, "GET", "/books", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(books[0].get("author").is_none());
+
+    let (status, books) = send(&app, "GET", "/books?include=author", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(books[0]["author"], json!({"id": 1, "name": "Herbert"}));
+    assert_eq!(books[1]["author"]["name"], "Austen");
+    assert_eq!(books[2]["author"], JsonValue::Null);
+
+    let (status, book) = send(&app, "GET", "/books/2?include=author", None).await;
+    assert_eq!((status, book["author"]["name"].clone()), (StatusCode::OK, json!("Austen")));
+}
+
+#[tokio::test]
+async fn unknown_relations_and_bad_keys_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, body) = send(&app, "GET", "/books?include=publisher", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, json!("unknown relation 'publisher' on Book; expected one of: author"));
+
+    let (status, _) = send(&app, "POST", "/books", Some(json!({"title": "Dune", "author_id": "one"}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, "POST", "/books", Some(json!({"title": "Dune", "author_id": 1}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
@@ -0,0 +1,87 @@
+use axum::routing::get;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use rune_runtime::rune_parser::load_rune_document_from_source;
+use rune_runtime::rune_parser::remote::{CACHE_DIR_ENV, SIGNING_KEY_ENV};
+use sha2::{Digest, Sha256};
+use std::fs;
+use tempfile::tempdir;
+
+const APP: &str = r#"#!RUNE
+import "shared/auth.rune"
+
+@App
+name = Remote
+type = REST
+"#;
+
+const AUTH: &str = r#"@Authentication/Main
+secret = s3cret
+"#;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sign(key: &str, content: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+    mac.update(content.as_bytes());
+    hex(&mac.finalize().into_bytes())
+}
+
+/// Serves `/app.rune` and `/shared/auth.rune` with their HMAC signatures under `key`.
+async fn start_config_server(key: &'static str) -> (String, tokio::task::JoinHandle<()>) {
+    let app = Router::new()
+        .route("/app.rune", get(|| async { APP }))
+        .route("/app.rune.sig", get(move || async move { sign(key, APP) }))
+        .route("/shared/auth.rune", get(|| async { AUTH }))
+        .route("/shared/auth.rune.sig", get(move || async move { sign(key, AUTH) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (format!("http://{}", addr), server)
+}
+
+async fn load(source: String) -> Result<rune_runtime::rune_ast::RuneDocument, String> {
+    tokio::task::spawn_blocking(move || load_rune_document_from_source(&source).map_err(|e| e.to_string()))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn loads_remote_documents_with_verification_and_cache() {
+    let cache = tempdir().expect("tempdir");
+    std::env::set_var(CACHE_DIR_ENV, cache.path());
+    std::env::remove_var(SIGNING_KEY_ENV);
+    let (base, server) = start_config_server("team-key").await;
+
+    // Relative imports resolve against the document's URL
+    let doc = load(format!("{}/app.rune", base)).await.expect("load remote document");
+    assert!(doc.get_section("App").is_some());
+    assert!(doc.sections.iter().any(|s| s.path == vec!["Authentication", "Main"]));
+
+    // A pinned checksum must match
+    let sum = hex(&Sha256::digest(APP.as_bytes()));
+    assert!(load(format!("{}/app.rune#sha256={}", base, sum)).await.is_ok());
+    let err = load(format!("{}/app.rune#sha256={}", base, "0".repeat(64))).await.unwrap_err();
+    assert!(err.contains("checksum mismatch"), "{}", err);
+
+    // With a signing key set, documents need a valid `.sig`
+    std::env::set_var(SIGNING_KEY_ENV, "team-key");
+    assert!(load(format!("{}/app.rune", base)).await.is_ok());
+    std::env::set_var(SIGNING_KEY_ENV, "other-key");
+    let err = load(format!("{}/app.rune", base)).await.unwrap_err();
+    assert!(err.contains("signature does not match"), "{}", err);
+    std::env::remove_var(SIGNING_KEY_ENV);
+
+    // Once the server is gone, the cached copies are used
+    server.abort();
+    let _ = server.await;
+    let doc = load(format!("{}/app.rune", base)).await.expect("load from cache");
+    assert!(doc.sections.iter().any(|s| s.path == vec!["Authentication", "Main"]));
+    assert!(fs::read_dir(cache.path()).unwrap().count() >= 2);
+    let err = load(format!("{}/missing.rune", base)).await.unwrap_err();
+    assert!(err.contains("Failed to load remote Rune document"), "{}", err);
+}
@@ -0,0 +1,68 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+const TEMPLATE: &str = r#"#!RUNE
+
+@Template
+params = (name)
+port = 8080
+
+@App
+name = %name%-service
+type = REST
+port = %port%
+
+@Route/GET /%name%
+run:
+    respond 200 %name%
+"#;
+
+#[test]
+fn render_substitutes_parameters_and_drops_the_template_section() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("service.rune");
+    std::fs::write(&template, TEMPLATE).unwrap();
+
+    let assert = vectrune_cmd()
+        .arg("render")
+        .arg(&template)
+        .args(["--set", "name=orders", "--set", "port=8100"])
+        .assert()
+        .success();
+    let out = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(out.contains("name = orders-service"), "{}", out);
+    assert!(out.contains("port = 8100"), "{}", out);
+    assert!(out.contains("@Route/GET /orders"), "{}", out);
+    assert!(!out.contains("@Template"), "{}", out);
+
+    let written = dir.path().join("out/billing.rune");
+    vectrune_cmd()
+        .arg("render")
+        .arg(&template)
+        .args(["--set", "name=billing", "--out"])
+        .arg(&written)
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(&written).unwrap().contains("port = 8080"));
+}
+
+#[test]
+fn render_rejects_missing_and_unknown_parameters() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("service.rune");
+    std::fs::write(&template, TEMPLATE).unwrap();
+
+    let assert = vectrune_cmd().arg("render").arg(&template).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("missing template parameters: name"));
+
+    let assert = vectrune_cmd()
+        .arg("render")
+        .arg(&template)
+        .args(["--set", "name=a", "--set", "colour=red"])
+        .assert()
+        .failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("unknown template parameter 'colour'"));
+}
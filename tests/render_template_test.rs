@@ -0,0 +1,95 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::tempdir;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /books/{id}
+run:
+    book = { title: "Dune <1965>", id: path.params.id }
+    render templates/book.html with book
+
+@Route/GET /greeting
+run:
+    name = "Ann"
+    html = render templates/greeting.html
+    respond 200 html as text
+
+@Route/GET /fragment
+content_type = html
+run:
+    respond 200 "<p>hi</p>"
+
+@Route/GET /missing
+run:
+    render templates/nope.html
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn get(app: &Router, uri: &str) -> (StatusCode, String, String) {
+    let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn renders_templates_relative_to_the_rune_dir() {
+    let temp = tempdir().expect("tempdir");
+    let templates = temp.path().join("templates");
+    fs::create_dir(&templates).unwrap();
+    fs::write(templates.join("base.html"), "<main>{% block body %}{% endblock %}</main>").unwrap();
+    fs::write(
+        templates.join("book.html"),
+        "{% extends \"base.html\" %}{% block body %}{% if id %}<h1 id=\"b{{ id }}\">{{ title }}</h1>{% endif %}{% endblock %}",
+    )
+    .unwrap();
+    fs::write(templates.join("greeting.html"), "Hello {{ name }}").unwrap();
+    let app = build_router(temp.path()).await;
+
+    let (status, content_type, body) = get(&app, "/books/1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/html"), "{}", content_type);
+    assert_eq!(body, "<main><h1 id=\"b1\">Dune &lt;1965&gt;</h1></main>");
+
+    let (status, _, body) = get(&app, "/greeting").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Hello Ann");
+
+    let (status, content_type, body) = get(&app, "/fragment").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/html"), "{}", content_type);
+    assert_eq!(body, "<p>hi</p>");
+
+    let (status, _, _) = get(&app, "/missing").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
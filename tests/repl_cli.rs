@@ -0,0 +1,43 @@
+use assert_cmd::Command;
+
+const SCRIPT: &str = "#!RUNE\n@App\nname = shelf\n\n@Const\nlimit = 1\n\n@Memory/books\n+ id = 1\n  title = \"Dune\"\n";
+
+#[test]
+fn repl_keeps_context_between_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, SCRIPT).unwrap();
+
+    let input = "books = memory.get \"books\"\nn = books.count\nif n >= limit:\n    size = \"enough\"\n\n:ctx size\nrespond 200 n\n:nope\n:quit\n";
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .arg("repl")
+        .arg(&app)
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("\"title\": \"Dune\""), "{}", stdout);
+    assert!(stdout.contains("vectrune> enough\n"), "{}", stdout);
+    assert!(stdout.contains("vectrune> 200 1\n"), "{}", stdout);
+    assert!(stdout.contains("Unknown command :nope"), "{}", stdout);
+}
+
+#[test]
+fn repl_reload_reads_the_document_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, SCRIPT).unwrap();
+
+    let input = "x = 7\n:doc\n:reload\n:ctx x\n:ctx missing\n";
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .arg("repl")
+        .arg(&app)
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("@Memory/books"), "{}", stdout);
+    assert!(stdout.contains("Reloaded"), "{}", stdout);
+    assert!(stdout.contains("vectrune> 7\n"), "{}", stdout);
+    assert!(stdout.contains("missing is not set"), "{}", stdout);
+}
@@ -0,0 +1,98 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const APP: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Schema/Book
+title = string
+pages = number
+
+@Route/POST /echo
+run:
+    reply = {"name": body.name, "raw": body.raw}
+    respond 200 reply
+
+@Route/POST /legacy
+run:
+    parse-json
+    respond 200 body.name
+
+@Route/POST /books
+schema = Book
+run:
+    respond 201 body.title
+"#;
+
+async fn build_router() -> Router {
+    let doc = parse_rune(APP).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: std::env::current_dir().unwrap(),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: &Router, uri: &str, content_type: Option<&str>, body: &str) -> (StatusCode, String) {
+    let mut req = Request::builder().method("POST").uri(uri);
+    if let Some(content_type) = content_type {
+        req = req.header("content-type", content_type);
+    }
+    let resp = app.clone().oneshot(req.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn bodies_are_parsed_by_content_type_with_the_raw_text_kept() {
+    let app = build_router().await;
+
+    for (content_type, body) in [
+        ("application/json", r#"{"name": "Ada"}"#),
+        ("application/x-www-form-urlencoded", "name=Ada"),
+        ("application/xml", "<user><name>Ada</name></user>"),
+    ] {
+        let (status, reply) = post(&app, "/echo", Some(content_type), body).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", content_type, reply);
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["name"], "Ada", "{}", content_type);
+        assert_eq!(reply["raw"], body, "{}", content_type);
+    }
+
+    let (status, _) = post(&app, "/echo", Some("application/json"), "{oops").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn parse_json_accepts_parsed_and_raw_bodies() {
+    let app = build_router().await;
+
+    let (status, reply) = post(&app, "/legacy", Some("application/json"), r#"{"name": "Ada"}"#).await;
+    assert_eq!((status, reply.as_str()), (StatusCode::OK, "Ada"));
+
+    let (status, reply) = post(&app, "/legacy", None, r#"{"name": "Ada"}"#).await;
+    assert_eq!((status, reply.as_str()), (StatusCode::OK, "Ada"));
+}
+
+#[tokio::test]
+async fn routes_with_a_schema_validate_parsed_bodies() {
+    let app = build_router().await;
+
+    let (status, reply) = post(&app, "/books", Some("application/json"), r#"{"title": "Dune", "pages": 412}"#).await;
+    assert_eq!((status, reply.as_str()), (StatusCode::CREATED, "Dune"));
+
+    let (status, _) = post(&app, "/books", Some("application/json"), r#"{"title": "Dune"}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,165 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/POST /users
+run:
+    parse-json
+    respond 200 body
+
+@Route/POST /users.csv
+run:
+    parse-json
+    respond 200 body as csv
+
+@Route/POST /users.yaml
+run:
+    parse-json
+    return body as yaml
+
+@Route/POST /users/created
+run:
+    new_id = 7
+    respond 201 { id: new_id, status: "created" }
+
+@Route/POST /users/cleared
+run:
+    respond 204
+
+@Route/POST /users/first
+run:
+    parse-json
+    name = body[0].name
+    respond 200 name
+
+@Route/POST /users/feed
+run:
+    parse-json
+    return body as "application/feed+json"
+
+@Route/POST /users/page
+content_type = html
+run:
+    page = "<h1>Users</h1>"
+    respond 200 page
+
+@Route/POST /login
+run:
+    respond 200 Logged in as admin
+"#;
+
+const USERS: &str = r#"[{"id": 1, "name": "Ada"}, {"id": 2, "name": "Linus"}]"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn post(uri: &str, accept: Option<&str>) -> (StatusCode, Option<String>, String) {
+    let app = build_router_from_str(SCRIPT).await;
+    let mut builder = Request::builder().method("POST").uri(uri);
+    if let Some(accept) = accept {
+        builder = builder.header("Accept", accept);
+    }
+    let resp = app
+        .oneshot(builder.body(Body::from(USERS)).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, content_type, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn respond_as_csv_serializes_records() {
+    let (status, content_type, body) = post("/users.csv", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("text/csv"));
+    assert_eq!(body, "id,name\n1,Ada\n2,Linus\n");
+}
+
+#[tokio::test]
+async fn return_as_yaml_serializes_value() {
+    let (_, content_type, body) = post("/users.yaml", None).await;
+    assert_eq!(content_type.as_deref(), Some("application/yaml"));
+    assert!(body.contains("name: Ada"));
+}
+
+#[tokio::test]
+async fn respond_negotiates_format_from_accept_header() {
+    let (_, content_type, body) = post("/users", Some("application/xml")).await;
+    assert_eq!(content_type.as_deref(), Some("application/xml"));
+    assert!(body.contains("<name>Ada</name>"));
+
+    let (_, content_type, body) =
+        post("/users", Some("text/html;q=0.9, application/yaml;q=0.8")).await;
+    assert_eq!(content_type.as_deref(), Some("application/yaml"));
+    assert!(body.contains("name: Linus"));
+}
+
+#[tokio::test]
+async fn respond_without_accept_keeps_json_body() {
+    let (_, content_type, body) = post("/users", None).await;
+    assert_eq!(content_type.as_deref(), Some("application/json"));
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json[1]["name"], "Linus");
+}
+
+#[tokio::test]
+async fn respond_builds_inline_objects_and_empty_bodies() {
+    let (status, _, body) = post("/users/created", None).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(created, serde_json::json!({"id": 7, "status": "created"}));
+
+    let (status, _, body) = post("/users/cleared", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn responses_take_their_content_type_from_the_value() {
+    let (_, content_type, body) = post("/users/first", None).await;
+    assert_eq!(content_type.as_deref(), Some("text/plain; charset=utf-8"));
+    assert_eq!(body, "Ada");
+
+    let (_, content_type, body) = post("/users/feed", None).await;
+    assert_eq!(content_type.as_deref(), Some("application/feed+json"));
+    assert!(body.starts_with("[{"), "{}", body);
+
+    let (_, content_type, body) = post("/users/page", None).await;
+    assert_eq!(content_type.as_deref(), Some("text/html; charset=utf-8"));
+    assert_eq!(body, "<h1>Users</h1>");
+}
+
+#[tokio::test]
+async fn text_ending_in_as_a_word_keeps_every_word() {
+    let (status, _, body) = post("/login", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "Logged in as admin");
+}
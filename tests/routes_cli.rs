@@ -0,0 +1,77 @@
+use assert_cmd::Command;
+use rune_runtime::apps::routes::{mount_table, route_table};
+use rune_runtime::rune_parser::parse_rune;
+use std::path::Path;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn routes_command_prints_route_table_without_serving() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let assert = vectrune_cmd()
+        .current_dir(manifest_dir)
+        .arg("routes")
+        .arg("examples/datasource.rune")
+        .assert()
+        .success();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(stdout.contains("METHOD  PATH"));
+    assert!(stdout.contains("GET     /cats/{id}    -     Cat     CatsDataSource"));
+    assert!(stdout.contains("GET     /cats/search  -     Cat     CatsDataSource"));
+    assert!(stdout.contains("frontend"));
+}
+
+#[test]
+fn route_table_reports_auth_and_mounts() {
+    let doc = parse_rune(
+        r#"#!RUNE
+
+@App
+type = REST
+swagger = true
+
+@Route/GET /me
+auth = Bearer
+run:
+    respond 200 "me"
+
+@Websocket /live
+on_message:
+    respond 200 "ok"
+"#,
+    )
+    .unwrap();
+
+    let routes = route_table(&doc);
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].auth.as_deref(), Some("Bearer"));
+
+    let kinds: Vec<String> = mount_table(&doc).into_iter().map(|m| m.kind).collect();
+    assert!(kinds.contains(&"websocket".to_string()));
+    assert!(kinds.contains(&"swagger".to_string()));
+}
+
+#[test]
+fn route_table_lists_crud_search_and_bulk_routes() {
+    let doc = parse_rune(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/CRUD /books
+schema = Book
+data_source = Books
+"#,
+    )
+    .unwrap();
+
+    let routes: Vec<(String, String)> = route_table(&doc).into_iter().map(|r| (r.method, r.path)).collect();
+    assert_eq!(routes.len(), 11);
+    for (method, path) in [("GET", "/books/search"), ("POST", "/books/bulk"), ("DELETE", "/books/bulk")] {
+        assert!(routes.contains(&(method.to_string(), path.to_string())), "missing {} {}", method, path);
+    }
+}
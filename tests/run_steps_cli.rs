@@ -0,0 +1,36 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn run_executes_steps_with_files_relative_to_the_script() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, "#!RUNE\n@App\nname = chores\n\n@Const\nlimit = 2\n").unwrap();
+    std::fs::write(dir.path().join("u.csv"), "name,country\nAda,UK\nLin,CN\nBob,UK\n").unwrap();
+
+    let assert = vectrune_cmd()
+        .current_dir(std::env::temp_dir())
+        .arg("run")
+        .arg(&app)
+        .args(["--steps", "users = csv.read 'u.csv'; report = users.group-by it.country; return report"])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(report["UK"].as_array().unwrap().len(), 2);
+    assert_eq!(report["CN"][0]["name"], "Lin");
+}
+
+#[test]
+fn run_supports_blocks_and_fails_on_error_responses() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, "#!RUNE\n@App\nname = chores\n\n@Const\nlimit = 2\n").unwrap();
+    std::fs::write(dir.path().join("u.csv"), "name\nAda\nLin\nBob\n").unwrap();
+
+    let steps = "users = csv.read 'u.csv'\nn = users.count\nif n > limit:\n    respond 422 \"too many users\"\nreturn users";
+    let assert = vectrune_cmd().arg("run").arg(&app).args(["--steps", steps]).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("422 too many users"));
+}
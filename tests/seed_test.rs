@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use rune_runtime::core::seeds::{extract_seeds, seed};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+fn state_for(script: &str, dir: &Path) -> AppState {
+    let doc = parse_rune(script).expect("parse_rune should succeed");
+    AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    }
+}
+
+const SCRIPT: &str = r#"#!RUNE
+
+@DataSource/Files
+type = jsonfile
+connection = products.json
+
+@DataSource/Scratch
+type = memory
+connection = seed_scratch
+
+@Schema/Product
+name = string
+price = number
+
+@Seed/Product
++ name = "Lamp"
+  price = 25
++ name = "Desk"
+  price = 180
+
+@Route/CRUD /products
+schema = Product
+data_source = Files
+"#;
+
+#[tokio::test]
+async fn seeds_empty_tables_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = state_for(SCRIPT, dir.path());
+
+    let applied = seed(&state).await.unwrap();
+    assert_eq!(applied.len(), 1);
+    assert_eq!((applied[0].0.data_source.as_str(), applied[0].1), ("Files", 2));
+
+    let stored: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("products.json")).unwrap()).unwrap();
+    assert_eq!(stored, serde_json::json!([
+        {"id": 1, "name": "Lamp", "price": 25},
+        {"id": 2, "name": "Desk", "price": 180}
+    ]));
+
+    assert!(seed(&state).await.unwrap().is_empty(), "a non-empty table is left alone");
+}
+
+#[test]
+fn seeds_without_a_route_or_single_datasource_need_data_source() {
+    let script = SCRIPT.replace("@Route/CRUD /products\nschema = Product\ndata_source = Files\n", "");
+    let doc = parse_rune(&script).unwrap();
+    let err = extract_seeds(&doc).unwrap_err();
+    assert!(err.contains("set data_source"), "{}", err);
+
+    let script = script.replace("@Seed/Product\n", "@Seed/Product\ndata_source = Scratch\n");
+    let seeds = extract_seeds(&parse_rune(&script).unwrap()).unwrap();
+    assert_eq!(seeds[0].data_source, "Scratch");
+}
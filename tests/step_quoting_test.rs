@@ -0,0 +1,57 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /escaped
+run:
+    respond 200 "say \"hi\" = { ok }"
+
+@Route/GET /single
+run:
+    respond 200 'it\'s = fine'
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn get(uri: &str) -> (StatusCode, String) {
+    let app = build_router_from_str(SCRIPT).await;
+    let resp = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn quoted_messages_with_equals_and_braces_are_one_argument() {
+    assert_eq!(
+        get("/escaped").await,
+        (StatusCode::OK, r#"say "hi" = { ok }"#.to_string())
+    );
+    assert_eq!(get("/single").await, (StatusCode::OK, "it's = fine".to_string()));
+}
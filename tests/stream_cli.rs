@@ -0,0 +1,34 @@
+use assert_cmd::Command;
+
+fn run_each(args: &[&str], stdin: &str) -> String {
+    let assert = Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .args(args)
+        .write_stdin(stdin)
+        .assert()
+        .success();
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+#[test]
+fn each_runs_calculate_on_every_ndjson_document() {
+    let stdin = "{\"Order\":{\"record\":[{\"total\":3},{\"total\":4}]}}\n{\"Order\":{\"record\":[{\"total\":10}]}}\n";
+    let out = run_each(&["-i", "json", "-", "--each", "--calculate", "sum Order.total"], stdin);
+    assert_eq!(out, "7\n---\n10\n");
+}
+
+#[test]
+fn each_transforms_rune_documents_separated_by_dashes() {
+    let stdin = "#!RUNE\n@S\n+ n = a\n---\n@S\n+ n = b\n";
+    let out = run_each(&["transform", "@T names:[@S.n]", "-", "--each", "-o", "json"], stdin);
+    let docs: Vec<serde_json::Value> =
+        out.split("---\n").map(|doc| serde_json::from_str(doc).unwrap()).collect();
+    assert_eq!(docs, vec![serde_json::json!({"T": {"names": ["a"]}}), serde_json::json!({"T": {"names": ["b"]}})]);
+}
+
+#[test]
+fn each_requires_stdin() {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+        .args(["examples/skateboarders.rune", "--each"])
+        .assert()
+        .failure();
+}
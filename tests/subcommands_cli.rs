@@ -0,0 +1,176 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+fn stdout(assert: &assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stdout).to_string()
+}
+
+const BOOKS: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Memory/books
++ id = 1
+  title = Dune
+
+@Route/GET /books
+run:
+    books = memory.get "books"
+    respond 200 books
+
+@Route/POST /books
+run:
+    parse-json
+    books = memory.get "books"
+    memory.append "books" body
+    memory.set books
+    respond 201 body
+
+@Test/adds a book
+request = "POST /books"
+body = {"id": 2, "title": "Emma"}
+status = 201
+
+@Test/lists books
+request = "GET /books"
+status = 200
+contains = Emma
+"#;
+
+#[test]
+fn calculate_subcommand_matches_the_flag() {
+    let assert = vectrune_cmd()
+        .args(["calculate", "avg Skateboarder.age", "examples/skateboarders.rune"])
+        .assert()
+        .success();
+    assert_eq!(stdout(&assert).trim(), "36");
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "--calculate", "avg Skateboarder.age"])
+        .assert()
+        .success();
+    assert_eq!(stdout(&assert).trim(), "36");
+}
+
+#[test]
+fn transform_subcommand_takes_its_own_output_format() {
+    let assert = vectrune_cmd()
+        .args([
+            "transform",
+            "@Skaters name:[@Skateboarder.name]",
+            "examples/skateboarders.rune",
+            "-o",
+            "json",
+            "--log-level",
+            "error",
+        ])
+        .assert()
+        .success();
+    let out = stdout(&assert);
+    assert!(out.contains("\"Skaters\": {"), "{}", out);
+    assert!(out.contains("\"Tony Hawk\""), "{}", out);
+}
+
+#[test]
+fn transforms_chain_in_order_and_load_from_a_file() {
+    let first = r#"@Streets name:[@Skateboarder.name|(style=="Street")]"#;
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "-l", "error", "--transform", first])
+        .args(["--transform", "@Picked names:[@Streets.name|sort|limit:1]", "-o", "json"])
+        .assert()
+        .success();
+    let out: serde_json::Value = serde_json::from_str(&stdout(&assert)).unwrap();
+    assert_eq!(out, serde_json::json!({"Picked": {"names": ["Leticia Bufoni"]}}));
+
+    let dir = tempfile::tempdir().unwrap();
+    let specs = dir.path().join("specs.runet");
+    std::fs::write(&specs, "# newest first\n@Picked names:[@Streets.name|reverse]\n\n@Final top:[@Picked.names|limit:1]\n").unwrap();
+    let assert = vectrune_cmd()
+        .args(["transform", first, "examples/skateboarders.rune"])
+        .args(["--transform-file", specs.to_str().unwrap(), "-o", "json", "-l", "error"])
+        .assert()
+        .success();
+    let out: serde_json::Value = serde_json::from_str(&stdout(&assert)).unwrap();
+    assert_eq!(out, serde_json::json!({"Final": {"top": ["Leticia Bufoni"]}}));
+}
+
+#[test]
+fn select_prints_the_values_a_path_selects() {
+    let assert = vectrune_cmd()
+        .args(["select", "Skateboarder[age>27].name", "examples/skateboarders.rune"])
+        .assert()
+        .success();
+    assert_eq!(stdout(&assert), "Tony Hawk\nLeticia Bufoni\n");
+
+    let assert = vectrune_cmd()
+        .args(["examples/skateboarders.rune", "--select", "Skateboarder[0]", "-o", "json"])
+        .assert()
+        .success();
+    let out: serde_json::Value = serde_json::from_str(&stdout(&assert)).unwrap();
+    assert_eq!(out, serde_json::json!([{"name": "Tony Hawk", "age": 53.0, "style": "Vert"}]));
+
+    let assert = vectrune_cmd()
+        .args(["select", "Skateboarder.missing", "examples/skateboarders.rune", "-o", "json"])
+        .assert()
+        .success();
+    assert_eq!(stdout(&assert).trim(), "null");
+
+    vectrune_cmd()
+        .args(["select", "Skateboarder[0", "examples/skateboarders.rune"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn serve_needs_a_script_or_a_repository() {
+    let assert = vectrune_cmd().arg("serve").assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("<SCRIPT>"));
+}
+
+#[test]
+fn check_reports_undeclared_references() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, "#!RUNE\n@App\ntype = REST\n\n@Route/CRUD /books\nschema = Book\ndata_source = Shelf\n").unwrap();
+    let assert = vectrune_cmd().arg("check").arg(&app).assert().failure();
+    let out = stdout(&assert);
+    assert!(out.contains("schema 'Book' is not declared"), "{}", out);
+    assert!(out.contains("datasource 'Shelf' is not declared"), "{}", out);
+
+    vectrune_cmd().args(["check", "examples/app.rune"]).assert().success();
+}
+
+#[test]
+fn test_runs_test_sections_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, BOOKS).unwrap();
+    let assert = vectrune_cmd().arg("test").arg(&app).assert().success();
+    let out = stdout(&assert);
+    assert!(out.contains("ok   adds a book\nok   lists books\n"), "{}", out);
+    assert!(out.contains("2 passed; 0 failed"), "{}", out);
+
+    std::fs::write(&app, BOOKS.replace("status = 201", "status = 200")).unwrap();
+    let assert = vectrune_cmd().arg("test").arg(&app).assert().failure();
+    let out = stdout(&assert);
+    assert!(out.contains("FAIL adds a book: expected status 200, got 201"), "{}", out);
+}
+
+#[test]
+fn fmt_checks_then_rewrites_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, "#!RUNE\n@App\ntype=REST\n# Greeting\n@Route/GET /hi\nrun:\n    respond 200 \"hi\"   \n\n\n").unwrap();
+
+    let assert = vectrune_cmd().args(["fmt", "--check"]).arg(&app).assert().failure();
+    assert!(stdout(&assert).contains("needs formatting"));
+    vectrune_cmd().arg("fmt").arg(&app).assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&app).unwrap(),
+        "#!RUNE\n\n@App\ntype = REST\n\n# Greeting\n@Route/GET /hi\nrun:\n    respond 200 \"hi\"\n"
+    );
+    vectrune_cmd().args(["fmt", "--check"]).arg(&app).assert().success();
+}
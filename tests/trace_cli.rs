@@ -0,0 +1,68 @@
+use assert_cmd::Command;
+
+const SCRIPT: &str = r#"#!RUNE
+@App
+type = REST
+name = shelf
+
+@Memory/books
++ id = 1
+  title = "Dune"
+
+@Route/GET /books/{id}
+run:
+    books = memory.get "books"
+    book = books.find it.id == id
+    if book == null:
+        respond 404 "no book"
+    respond 200 book
+
+@Route/POST /books
+run:
+    parse-json
+    title = body.title
+    respond 201 title
+"#;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+#[test]
+fn trace_prints_each_step_of_the_matching_route() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, SCRIPT).unwrap();
+
+    let assert = vectrune_cmd().arg(&app).args(["--trace", "GET", "/books/7"]).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.starts_with("GET /books/7 -> @Route/GET /books/{id}\n    id = \"7\"\n"), "{}", stdout);
+    assert!(stdout.contains("[2] book = books.find it.id == id\n    args: \"books.find\" \"it.id\" \"==\" \"7\"\n    + book = null\n"), "{}", stdout);
+    assert!(stdout.contains("[3] if book == null:\n"), "{}", stdout);
+    assert!(stdout.contains("    => true\n"), "{}", stdout);
+    assert!(stdout.contains("    <= 404 no book\n"), "{}", stdout);
+    assert!(stdout.trim_end().ends_with("Response: 404 no book"), "{}", stdout);
+}
+
+#[test]
+fn trace_reads_the_body_and_rejects_unknown_routes() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, SCRIPT).unwrap();
+    let body = dir.path().join("book.json");
+    std::fs::write(&body, r#"{"title":"Emma"}"#).unwrap();
+
+    let assert = vectrune_cmd()
+        .arg(&app)
+        .args(["--trace", "post", "/books", "--body"])
+        .arg(&body)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("~ body: \"{\\\"title\\\":\\\"Emma\\\"}\" -> {\"title\":\"Emma\"}"), "{}", stdout);
+    assert!(stdout.contains("+ title = \"Emma\""), "{}", stdout);
+    assert!(stdout.contains("Response: 201 Emma"), "{}", stdout);
+
+    let assert = vectrune_cmd().arg(&app).args(["--trace", "GET", "/authors"]).assert().failure();
+    assert!(String::from_utf8_lossy(&assert.get_output().stderr).contains("No route matches GET /authors"));
+}
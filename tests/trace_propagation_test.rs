@@ -0,0 +1,131 @@
+use axum::http::{Request, StatusCode};
+use axum::routing::any;
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const INCOMING: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Starts an upstream that echoes every `traceparent` header it received.
+async fn start_trace_upstream() -> String {
+    let app = Router::new().fallback(any(|req: Request<Body>| async move {
+        let received: Vec<String> = req
+            .headers()
+            .get_all("traceparent")
+            .iter()
+            .filter_map(|v| v.to_str().ok().map(str::to_string))
+            .collect();
+        serde_json::json!({ "traceparent": received }).to_string()
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}", addr)
+}
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: Router, uri: &str, traceparent: Option<&str>) -> (StatusCode, String, serde_json::Value) {
+    let mut req = Request::builder().uri(uri);
+    if let Some(tp) = traceparent {
+        req = req.header("traceparent", tp);
+    }
+    let resp = app.oneshot(req.body(Body::empty()).unwrap()).await.unwrap();
+    let status = resp.status();
+    let echoed = resp
+        .headers()
+        .get("traceparent")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, echoed, json)
+}
+
+#[tokio::test]
+async fn steps_see_the_incoming_trace_and_response_carries_the_span() {
+    let app = build_router_from_str(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /trace
+run:
+    respond 200 trace
+"#,
+    )
+    .await;
+
+    let (status, echoed, json) = send(app, "/trace", Some(INCOMING)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(json["parent_id"], "00f067aa0ba902b7");
+    let span_id = json["span_id"].as_str().unwrap();
+    assert_eq!(span_id.len(), 16);
+    assert_eq!(echoed, format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", span_id));
+}
+
+#[tokio::test]
+async fn a_new_trace_starts_without_a_valid_traceparent() {
+    let app = build_router_from_str(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/GET /trace
+run:
+    respond 200 trace
+"#,
+    )
+    .await;
+
+    let (_, echoed, json) = send(app, "/trace", Some("not-a-traceparent")).await;
+    assert!(json["parent_id"].is_null());
+    assert_eq!(json["trace_id"].as_str().unwrap().len(), 32);
+    assert!(echoed.contains(json["trace_id"].as_str().unwrap()));
+}
+
+#[tokio::test]
+async fn proxy_forwards_the_trace_with_its_own_span_as_parent() {
+    let upstream = start_trace_upstream().await;
+    let script = format!(
+        r#"#!RUNE
+
+@App
+type = REST
+
+@Route/PROXY /api/*
+upstream = {upstream}
+"#
+    );
+    let app = build_router_from_str(&script).await;
+
+    let (status, echoed, json) = send(app, "/api/orders", Some(INCOMING)).await;
+    assert_eq!(status, StatusCode::OK);
+    let forwarded = json["traceparent"].as_array().unwrap();
+    assert_eq!(forwarded.len(), 1, "the incoming traceparent is replaced, not duplicated");
+    let forwarded = forwarded[0].as_str().unwrap();
+    assert!(forwarded.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+    assert_ne!(forwarded, INCOMING);
+    assert_eq!(forwarded, echoed);
+}
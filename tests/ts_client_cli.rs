@@ -0,0 +1,63 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+name = Library
+type = REST
+port = 8080
+
+@Enum/Genre
+values = (fiction poetry)
+
+@Schema/Book
+id = number
+title = string
+genre = Genre
+timestamps = true
+
+@Authentication/Staff
+type = jwt
+secret = shh
+token_endpoint = /auth/token
+
+@Route/CRUD /books
+schema = Book
+data_source = Shelf
+auth = Staff
+page_size = 20
+
+@Route/POST /books/{id}/reviews
+expect = Book
+run:
+    respond 201 body
+"#;
+
+#[test]
+fn ts_client_types_schemas_and_wraps_routes() {
+    let assert = vectrune_cmd().args(["-", "-o", "ts-client"]).write_stdin(SCRIPT).assert().success();
+    let ts = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(ts.contains("export type Genre = \"fiction\" | \"poetry\";\n"), "{}", ts);
+    assert!(
+        ts.contains("export interface Book {\n  genre: Genre;\n  id: number;\n  title: string;\n  readonly created_at?: string;\n"),
+        "{}",
+        ts
+    );
+    assert!(ts.contains("options.baseUrl ?? \"http://127.0.0.1:8080\""), "{}", ts);
+    assert!(ts.contains("async loginStaff(username: string, password: string)"), "{}", ts);
+    assert!(
+        ts.contains("get: (id: string | number) => request<Book>(\"GET\", `/books/${segment(id)}`, { auth: true }),"),
+        "{}",
+        ts
+    );
+    assert!(ts.contains("request<{ data: Book[]; page: number; size: number; total: number; next: number | null }>"), "{}", ts);
+    assert!(
+        ts.contains("postBooksByIdReviews: <T = unknown>(id: string | number, body: Book, query?: Query) =>"),
+        "{}",
+        ts
+    );
+}
@@ -0,0 +1,81 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@DataSource/UsersFile
+type = jsonfile
+connection = data/users.json
+
+@Schema/User
+email = string unique
+name = string
+
+@Route/CRUD /users
+data_source = UsersFile
+schema = User
+"#;
+
+async fn build_router(dir: &Path) -> Router {
+    let doc = parse_rune(SCRIPT).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> (StatusCode, JsonValue) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    let resp = app.clone().oneshot(req.body(body).unwrap()).await.unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(JsonValue::Null))
+}
+
+#[tokio::test]
+async fn duplicate_unique_values_respond_409() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(dir.path()).await;
+
+    let (status, _) = send(&app, "POST", "/users", Some(json!({"email": "ada@example.com", "name": "Ada"}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(&app, "POST", "/users", Some(json!({"email": "lin@example.com", "name": "Lin"}))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, error) = send(&app, "POST", "/users", Some(json!({"email": "ada@example.com", "name": "Eve"}))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        error,
+        json!({
+            "status": 409,
+            "title": "Conflict",
+            "message": "a record with email 'ada@example.com' already exists",
+            "field": "email",
+            "value": "ada@example.com"
+        })
+    );
+
+    let (status, error) = send(&app, "PUT", "/users/2", Some(json!({"email": "ada@example.com"}))).await;
+    assert_eq!((status, error["field"].clone()), (StatusCode::CONFLICT, json!("email")));
+    let (status, _) = send(&app, "PUT", "/users/1", Some(json!({"email": "ada@example.com", "name": "Ada L"}))).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,53 @@
+use assert_cmd::Command;
+
+fn vectrune_cmd() -> Command {
+    Command::new(assert_cmd::cargo::cargo_bin!("vectrune"))
+}
+
+const OLD_APP: &str = "#!RUNE\n@App\nname = counter\n\n@Datasource/store\ntype = memory\n\n@Route/POST /hits\nrun:\n    set-memory hits 1\n    hits = get-memory hits\n    respond 200 hits\n";
+
+#[test]
+fn upgrade_check_reports_without_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, OLD_APP).unwrap();
+
+    let assert = vectrune_cmd().arg("upgrade").arg(&app).arg("--check").assert().failure();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert!(stdout.contains("line 5: @Datasource -> @DataSource"), "{}", stdout);
+    assert!(stdout.contains("line 10: set-memory -> memory.set"), "{}", stdout);
+    assert_eq!(std::fs::read_to_string(&app).unwrap(), OLD_APP);
+}
+
+#[test]
+fn upgrade_rewrites_files_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, OLD_APP).unwrap();
+
+    vectrune_cmd().arg("upgrade").arg(&app).assert().success();
+    let upgraded = std::fs::read_to_string(&app).unwrap();
+    assert!(upgraded.contains("@DataSource/store\n"));
+    assert!(upgraded.contains("    memory.set hits 1\n    hits = memory.get hits\n"));
+
+    vectrune_cmd().arg("upgrade").arg(&app).arg("--check").assert().success();
+}
+
+#[test]
+fn deprecated_builtins_still_run_with_a_warning() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.rune");
+    std::fs::write(&app, OLD_APP).unwrap();
+
+    let assert = vectrune_cmd()
+        .arg("run")
+        .arg(&app)
+        .args(["--steps", "set-memory hits 3; hits = get-memory hits; return hits"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    assert!(stderr.contains("[WARN] deprecated section `@Datasource`: use `@DataSource`"), "{}", stderr);
+    assert!(stderr.contains("[WARN] deprecated builtin `set-memory`: use `memory.set`"), "{}", stderr);
+    assert_eq!(stdout.trim(), "3");
+}
@@ -0,0 +1,99 @@
+use axum::http::{Request, StatusCode};
+use axum::{body::Body, Router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::builtins::builtin::validate::{conditional_rules, ConditionalRule};
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"#!RUNE
+
+@App
+type = REST
+
+@Schema/Customer
+name = string
+type = string
+vat_number = string
+age = number
+validate_if:
+    - type == "company" requires vat_number
+    - type == "person" requires age
+
+@Route/POST /customers
+run:
+    parse-json
+    validate body #Customer
+    respond 201 "created"
+"#;
+
+async fn build_router_from_str(contents: &str) -> Router {
+    let doc = parse_rune(contents).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: PathBuf::from("."),
+    };
+    build_app_router(state).await
+}
+
+async fn post(app: Router, body: &str) -> (StatusCode, String) {
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/customers")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[test]
+fn parses_validate_if_rules() {
+    let doc = parse_rune(SCRIPT).unwrap();
+    let schemas = extract_schemas(&doc);
+    let rules = conditional_rules(&schemas["Customer"]).unwrap();
+    assert_eq!(
+        rules[0],
+        ConditionalRule {
+            condition: r#"type == "company""#.to_string(),
+            requires: vec!["vat_number".to_string()],
+        }
+    );
+}
+
+#[tokio::test]
+async fn required_fields_depend_on_other_fields() {
+    let app = build_router_from_str(SCRIPT).await;
+
+    let (status, _) = post(app.clone(), r#"{"name": "Acme", "type": "company", "vat_number": "GB123"}"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = post(app.clone(), r#"{"name": "Acme", "type": "company"}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, r#"Field `vat_number` is required when type == "company""#);
+
+    let (status, _) = post(app.clone(), r#"{"name": "Ann", "type": "person", "age": 40, "vat_number": null}"#).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = post(app.clone(), r#"{"name": "Ann", "type": "person", "vat_number": 5}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "Field `vat_number` type mismatch");
+
+    let (status, body) = post(app, r#"{"type": "person", "age": 40}"#).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body, "Missing field `name`");
+}
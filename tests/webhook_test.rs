@@ -0,0 +1,151 @@
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::routing::post;
+use axum::{body::Body, Router};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value as JsonValue};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use rune_runtime::apps::build_app_router;
+use rune_runtime::core::{extract_data_sources, extract_steps, extract_schemas, AppState};
+use rune_runtime::rune_parser::parse_rune;
+
+type Received = mpsc::UnboundedReceiver<(HeaderMap, String)>;
+
+/// A receiver that answers `503` to its first `failures` deliveries and `200` after.
+async fn receiver(failures: usize) -> (String, Received) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let seen = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: String| {
+            let (tx, seen) = (tx.clone(), seen.clone());
+            async move {
+                tx.send((headers, body)).unwrap();
+                if seen.fetch_add(1, Ordering::SeqCst) < failures {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                }
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, rx)
+}
+
+async fn next(rx: &mut Received) -> (HeaderMap, JsonValue, String) {
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    (headers, serde_json::from_str(&body).unwrap(), body)
+}
+
+async fn build_router(script: &str, dir: &std::path::Path) -> Router {
+    let doc = parse_rune(script).expect("parse_rune should succeed");
+    let state = AppState {
+        doc: Arc::new(doc.clone()),
+        schemas: Arc::new(extract_schemas(&doc)),
+        data_sources: Arc::new(extract_data_sources(&doc)),
+        steps: Arc::new(extract_steps(&doc)),
+        path: dir.to_path_buf(),
+    };
+    build_app_router(state).await
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<JsonValue>) -> StatusCode {
+    let mut req = Request::builder().method(method).uri(uri);
+    if body.is_some() {
+        req = req.header("content-type", "application/json");
+    }
+    let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+    app.clone().oneshot(req.body(body).unwrap()).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn crud_events_are_delivered_signed_and_retried() {
+    let (url, mut rx) = receiver(1).await;
+    let script = format!(
+        r#"#!RUNE
+@App
+type = REST
+
+@DataSource/BooksFile
+type = jsonfile
+connection = data/books.json
+
+@Schema/Book
+title = string
+
+@Route/CRUD /books
+data_source = BooksFile
+schema = Book
+
+@Webhook/audit
+url = {url}
+events = (book.created book.deleted)
+secret = s3cret
+retry_delay = 10ms
+"#
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(&script, dir.path()).await;
+
+    assert_eq!(send(&app, "POST", "/books", Some(json!({"title": "Dune"}))).await, StatusCode::CREATED);
+    let (_, failed, _) = next(&mut rx).await;
+    let (headers, delivered, raw) = next(&mut rx).await;
+    assert_eq!(failed["id"], delivered["id"], "a retry resends the same delivery");
+    assert_eq!((delivered["event"].clone(), delivered["data"].clone()), (json!("book.created"), json!({"id": 1, "title": "Dune"})));
+    assert_eq!(headers["x-vectrune-event"], "book.created");
+    assert_eq!(headers["x-vectrune-delivery"], delivered["id"].as_str().unwrap());
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+    mac.update(raw.as_bytes());
+    let expected: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(headers["x-vectrune-signature"].to_str().unwrap(), format!("sha256={}", expected));
+
+    // `book.updated` has no subscriber
+    assert_eq!(send(&app, "PUT", "/books/1", Some(json!({"title": "Dune Messiah"}))).await, StatusCode::OK);
+    assert_eq!(send(&app, "DELETE", "/books/1", None).await, StatusCode::NO_CONTENT);
+    let (_, deleted, _) = next(&mut rx).await;
+    assert_eq!((deleted["event"].clone(), deleted["data"].clone()), (json!("book.deleted"), json!({"id": 1})));
+}
+
+#[tokio::test]
+async fn emit_event_queues_custom_events() {
+    let (url, mut rx) = receiver(0).await;
+    let script = format!(
+        r#"#!RUNE
+@App
+type = REST
+
+@Route/POST /publish
+run:
+    parse-json
+    queued = emit-event book.published body
+    respond 202 queued
+
+@Webhook/books
+url = {url}
+events = book.*
+"#
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let app = build_router(&script, dir.path()).await;
+
+    let req = Request::post("/publish")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({"title": "Dune"}).to_string()))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&bytes[..], b"1");
+    let (headers, delivered, _) = next(&mut rx).await;
+    assert_eq!(delivered["data"], json!({"title": "Dune"}));
+    assert!(headers.get("x-vectrune-signature").is_none());
+}
@@ -19,6 +19,7 @@ fn app_state() -> AppState {
         doc: Arc::new(RuneDocument { sections: vec![] }),
         schemas: Arc::new(HashMap::new()),
         data_sources: Arc::new(HashMap::new()),
+        steps: Arc::default(),
         path: PathBuf::new(),
     }
 }
@@ -28,6 +29,7 @@ fn worm_game_app_state() -> AppState {
         doc: Arc::new(RuneDocument { sections: vec![] }),
         schemas: Arc::new(HashMap::new()),
         data_sources: Arc::new(HashMap::new()),
+        steps: Arc::default(),
         path: std::env::current_dir()
             .expect("current_dir should be available")
             .join("examples")